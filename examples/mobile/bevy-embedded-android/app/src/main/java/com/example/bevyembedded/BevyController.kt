@@ -1,5 +1,59 @@
 package com.example.bevyembedded
 
+import java.nio.ByteBuffer
+import java.nio.ByteOrder
+import kotlin.coroutines.cancellation.CancellationException
+import kotlinx.coroutines.channels.consume
+import kotlinx.coroutines.flow.Flow
+import kotlinx.coroutines.flow.consumeAsFlow
+import kotlinx.coroutines.flow.emitAll
+import kotlinx.coroutines.flow.firstOrNull
+import kotlinx.coroutines.flow.flow
+import kotlinx.coroutines.flow.mapNotNull
+
+/**
+ * Errors of Bevy requests and jobs
+ */
+sealed class BevyChannelException(message: String) : Exception(message) {
+    /** The view and its app went away before a response arrived */
+    class Closed : BevyChannelException("Bevy app closed")
+
+    /** A job failed or had no handler in the app */
+    class JobFailed(error: String) : BevyChannelException(error)
+}
+
+/** Topic of job results, `ReservedTopic::JobResult` in the Rust crate */
+const val BEVY_JOB_RESULT_TOPIC = 24
+
+/** Topic of property binds and sets, `ReservedTopic::Properties` in the Rust crate */
+const val BEVY_PROPERTIES_TOPIC = 35
+
+/** Topic of keyboard requests for text fields, `ReservedTopic::TextInputRequest` in the Rust crate */
+const val BEVY_TEXT_INPUT_REQUEST_TOPIC = 40
+
+/** Topic of text field edits, `ReservedTopic::TextInput` in the Rust crate */
+const val BEVY_TEXT_INPUT_TOPIC = 41
+
+/** Topic of screen reader announcements, `ReservedTopic::Announce` in the Rust crate */
+const val BEVY_ANNOUNCE_TOPIC = 42
+
+private val RESERVED_MAGIC = byteArrayOf(0xFF.toByte(), 'B'.code.toByte(), 'E'.code.toByte(), 'M'.code.toByte())
+
+/**
+ * Payload of a reserved message on [topic], or null for other messages
+ */
+fun bevyReservedPayload(message: ByteArray, topic: Int): ByteArray? {
+    val header = RESERVED_MAGIC + byteArrayOf((topic and 0xFF).toByte(), (topic shr 8).toByte())
+    if (message.size < header.size || !header.indices.all { message[it] == header[it] }) return null
+    return message.copyOfRange(header.size, message.size)
+}
+
+/**
+ * A reserved message on [topic] carrying [payload]
+ */
+fun bevyReservedMessage(topic: Int, payload: ByteArray): ByteArray =
+    RESERVED_MAGIC + byteArrayOf((topic and 0xFF).toByte(), (topic shr 8).toByte()) + payload
+
 /**
  * Controller interface for interacting with a Bevy app instance
  * Provides a clean Kotlin API similar to the iOS BevyViewController
@@ -20,4 +74,88 @@ class BevyController(
     fun sendBytes(vararg bytes: Byte) {
         sendMessage(bytes)
     }
+
+    /**
+     * Messages from Bevy, from when collection starts until the app is destroyed
+     *
+     * Each collector gets every message; map them to typed values with the app's decoder.
+     */
+    fun messages(): Flow<ByteArray> = flow { emitAll(surfaceView.openMessageChannel()) }
+
+    /**
+     * Payloads of the reserved messages on [topic]
+     */
+    fun reservedMessages(topic: Int): Flow<ByteArray> = messages().mapNotNull { bevyReservedPayload(it, topic) }
+
+    /**
+     * Bind a property the app declared with `add_host_property` to [id]
+     *
+     * Bevy answers on topic 36 with the property's kind. Binding again is harmless.
+     */
+    fun bindProperty(name: String, id: Int) {
+        val utf8 = name.encodeToByteArray()
+        val payload = ByteBuffer.allocate(7 + utf8.size).order(ByteOrder.LITTLE_ENDIAN)
+        payload.put(0).putShort(id.toShort()).putInt(utf8.size).put(utf8)
+        sendMessage(bevyReservedMessage(BEVY_PROPERTIES_TOPIC, payload.array()))
+    }
+
+    /**
+     * Set a bound property, tweening over [duration] seconds if it is positive
+     *
+     * [easing] is 0 linear, 1 ease-in, 2 ease-out, 3 ease-in-out, 4 back-out,
+     * 5 elastic-out or 6 bounce-out.
+     */
+    fun setProperty(id: Int, values: FloatArray, duration: Float = 0f, easing: Int = 0) {
+        val payload = ByteBuffer.allocate(8 + 4 * values.size).order(ByteOrder.LITTLE_ENDIAN)
+        payload.put(1).putShort(id.toShort()).putFloat(duration).put(easing.toByte())
+        values.forEach { payload.putFloat(it) }
+        sendMessage(bevyReservedMessage(BEVY_PROPERTIES_TOPIC, payload.array()))
+    }
+
+    /**
+     * Send a message and wait for the first message from Bevy that [isResponse] accepts
+     */
+    suspend fun request(data: ByteArray, isResponse: (ByteArray) -> Boolean): ByteArray {
+        // Subscribe before sending, so a response in the next frame isn't missed
+        surfaceView.openMessageChannel().consume {
+            sendMessage(data)
+            for (message in this) {
+                if (isResponse(message)) return message
+            }
+        }
+        throw BevyChannelException.Closed()
+    }
+
+    /**
+     * Run a job the app registered with `add_host_job` and wait for its output
+     *
+     * Cancelling the calling coroutine cancels the job.
+     */
+    suspend fun runJob(kind: String, input: ByteArray): ByteArray {
+        val app = surfaceView.appPtr
+        if (app == 0L) throw BevyChannelException.Closed()
+        val results = surfaceView.openMessageChannel()
+        val token = BevyNative.nativeSubmitJob(app, kind, input)
+        if (token == 0L) {
+            results.cancel()
+            throw BevyChannelException.Closed()
+        }
+
+        // token: u64 | status: u8 | output or error
+        val result = try {
+            results.consumeAsFlow()
+                .mapNotNull { bevyReservedPayload(it, BEVY_JOB_RESULT_TOPIC) }
+                .firstOrNull { it.size >= 9 && ByteBuffer.wrap(it).order(ByteOrder.LITTLE_ENDIAN).long == token }
+        } catch (e: CancellationException) {
+            surfaceView.appPtr.takeIf { it != 0L }?.let { BevyNative.nativeCancelJob(it, token) }
+            throw e
+        } ?: throw BevyChannelException.Closed()
+
+        val output = result.copyOfRange(9, result.size)
+        when (result[8].toInt()) {
+            0 -> return output
+            1 -> throw BevyChannelException.JobFailed(output.decodeToString())
+            else -> throw CancellationException("Job canceled by Bevy")
+        }
+    }
 }
@@ -1,5 +1,6 @@
 package com.example.bevyembedded
 
+import android.app.Activity
 import android.view.Surface
 
 /**
@@ -12,38 +13,93 @@ object BevyNative {
 
     /**
      * Create a new Bevy app instance
-     * @param surface The Android Surface to render to
+     * @param surface The Android Surface to render to, or null for a headless app
      * @param width Surface width in pixels
      * @param height Surface height in pixels
      * @param scaleFactor Display density scale factor
      * @return Pointer to the Bevy app instance
      */
     external fun nativeCreateApp(
-        surface: Surface,
+        surface: Surface?,
         width: Int,
         height: Int,
         scaleFactor: Float
     ): Long
 
+    /**
+     * Create a new Bevy app instance with creation config values
+     * @param config Creation config from nativeNewConfig, or 0
+     * @see nativeCreateApp
+     */
+    external fun nativeCreateAppWithConfig(
+        surface: Surface?,
+        width: Int,
+        height: Int,
+        scaleFactor: Float,
+        config: Long
+    ): Long
+
     /**
      * Update the Bevy app (render one frame)
+     *
+     * Must be called on the thread that created the app unless it was created with the
+     * `marshal` thread policy; calls from other threads fail with a non-zero code.
      * @param appPtr Pointer to the Bevy app instance
      * @return Error code (0 = success, non-zero = error)
      */
     external fun nativeUpdate(appPtr: Long): Int
 
+    /**
+     * Update the Bevy app and report the frame's scheduling signals
+     * @param appPtr Pointer to the Bevy app instance
+     * @param info Receives frame index, CPU ms, GPU ms (-1 if unmeasured), pending
+     *             messages, needs redraw (0/1) and idle (0/1)
+     * @return Error code (0 = success, non-zero = error)
+     */
+    external fun nativeUpdateWithInfo(appPtr: Long, info: DoubleArray): Int
+
+    /**
+     * Run one part of a frame instead of a whole update, for hosts driving Bevy from
+     * their own loop
+     * @param appPtr Pointer to the Bevy app instance
+     * @param schedule 0 = main, 1 = render, 2 = input, 3 = first, 4 = pre-update,
+     *                 5 = fixed step, 6 = update, 7 = post-update, 8 = last
+     * @return 0 if it ran, 1 on error, 2 if the app doesn't have the schedule
+     */
+    external fun nativeRunSchedule(appPtr: Long, schedule: Int): Int
+
     /**
      * Get the last error message from Bevy (if any)
+     *
+     * Includes failed JNI calls of other native functions, with the description of any
+     * Java exception they threw; native functions clear such exceptions instead of
+     * rethrowing them.
      * @return Error message string, or null if no error
      */
     external fun nativeGetLastError(): String?
 
     /**
      * Destroy the Bevy app instance and free resources
+     * Closes the app's window and runs its final updates first, for up to the teardown
+     * timeout, so keep the surface alive until this returns.
      * @param appPtr Pointer to the Bevy app instance
      */
     external fun nativeDestroy(appPtr: Long)
 
+    /**
+     * Ask the app to exit without blocking; call nativeTeardownStep once per frame instead
+     * of nativeUpdate until it returns true, then remove the view
+     * @param appPtr Pointer to the Bevy app instance
+     */
+    external fun nativeDestroyAsync(appPtr: Long)
+
+    /**
+     * Run the next final update after nativeDestroyAsync
+     * @param appPtr Pointer to the Bevy app instance
+     * @return true once the app is destroyed and appPtr is no longer valid
+     */
+    external fun nativeTeardownStep(appPtr: Long): Boolean
+
     /**
      * Send a touch event to Bevy
      * @param appPtr Pointer to the Bevy app instance
@@ -60,6 +116,110 @@ object BevyNative {
         id: Long
     )
 
+    /**
+     * Send a touch event with MotionEvent contact axes to Bevy, delivered as TouchInput
+     * (with force) plus a TouchContactInput message
+     * @param appPtr Pointer to the Bevy app instance
+     * @param phase Touch phase (0=Started, 1=Moved, 2=Ended, 3=Canceled)
+     * @param x X coordinate
+     * @param y Y coordinate
+     * @param id Touch pointer ID
+     * @param pressure MotionEvent.getPressure
+     * @param touchMajor MotionEvent.getTouchMajor, in pixels
+     * @param touchMinor MotionEvent.getTouchMinor, in pixels
+     * @param orientation MotionEvent.getOrientation, in radians
+     */
+    external fun nativeTouchEventWithAxes(
+        appPtr: Long,
+        phase: Int,
+        x: Float,
+        y: Float,
+        id: Long,
+        pressure: Float,
+        touchMajor: Float,
+        touchMinor: Float,
+        orientation: Float
+    )
+
+    /**
+     * Queue a predicted position for a touch, e.g. from MotionPredictor.predict, delivered
+     * in the PredictedTouches resource. Send after the frame's real touches; predictions
+     * only last until the next update.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param x Predicted X coordinate
+     * @param y Predicted Y coordinate
+     * @param id Touch pointer ID the prediction extends
+     */
+    external fun nativePredictedTouch(
+        appPtr: Long,
+        x: Float,
+        y: Float,
+        id: Long
+    )
+
+    /**
+     * Cancel every touch Bevy has down, e.g. when the view gets ACTION_CANCEL
+     * @param appPtr Pointer to the Bevy app instance
+     */
+    external fun nativeCancelTouches(appPtr: Long)
+
+    /**
+     * Send rotary input (Wear OS bezel or crown) to Bevy, delivered as RotaryInput and a
+     * vertical MouseWheel in pixels
+     * @param appPtr Pointer to the Bevy app instance
+     * @param delta Scroll distance in pixels, positive for clockwise rotation:
+     *   -AXIS_SCROLL * ViewConfiguration.scaledVerticalScrollFactor
+     */
+    external fun nativeRotaryEvent(appPtr: Long, delta: Float)
+
+    /**
+     * Send a hardware key press or release to Bevy, delivered as KeyboardInput and, for
+     * registered shortcuts, ShortcutTriggered
+     * @param appPtr Pointer to the Bevy app instance
+     * @param keyCode KeyEvent.getKeyCode
+     * @param action KeyEvent.getAction (ACTION_DOWN or ACTION_UP)
+     * @param repeatCount KeyEvent.getRepeatCount
+     * @param metaState KeyEvent.getMetaState
+     * @param unicodeChar KeyEvent.getUnicodeChar, 0 if the key produces no text
+     */
+    external fun nativeKeyEvent(
+        appPtr: Long,
+        keyCode: Int,
+        action: Int,
+        repeatCount: Int,
+        metaState: Int,
+        unicodeChar: Int
+    )
+
+    /**
+     * Tell Bevy the view gained or lost keyboard focus; losing it releases all keys
+     * @param appPtr Pointer to the Bevy app instance
+     * @param focused Whether the view has focus
+     */
+    external fun nativeKeyboardFocus(appPtr: Long, focused: Boolean)
+
+    /**
+     * Create an empty creation config for nativeCreateAppWithConfig, nativeCreateAppAsync or
+     * nativeSetPreloadTarget
+     * The create calls copy the values, so the config can be freed right after them.
+     * @return Handle of the config, to free with nativeFreeConfig
+     */
+    external fun nativeNewConfig(): Long
+
+    /**
+     * Set a creation config value
+     * Recognised keys: headless, touch_coordinates, color_space (srgb, display_p3, hdr).
+     * Other keys are passed to the app in EmbeddedConfig.values.
+     * @param config Handle from nativeNewConfig
+     */
+    external fun nativeConfigSet(config: Long, key: String, value: String)
+
+    /**
+     * Free a config from nativeNewConfig
+     * @param config Handle from nativeNewConfig
+     */
+    external fun nativeFreeConfig(config: Long)
+
     /**
      * Notify Bevy of surface size changes
      * @param appPtr Pointer to the Bevy app instance
@@ -74,6 +234,355 @@ object BevyNative {
         scaleFactor: Float
     )
 
+    /**
+     * Move Bevy to a different surface without recreating the app, e.g. for full screen
+     * Keep the previous surface alive until the next nativeUpdate returns.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param surface The new Android Surface
+     * @param width Surface width in pixels
+     * @param height Surface height in pixels
+     * @param scaleFactor Display density scale factor
+     * @param presentation 0 = inline, 1 = full screen, 2 = picture-in-picture
+     */
+    external fun nativeReplaceSurface(
+        appPtr: Long,
+        surface: Surface,
+        width: Int,
+        height: Int,
+        scaleFactor: Float,
+        presentation: Int
+    )
+
+    /**
+     * Ask the app to close its window, e.g. to open a new one with nativeOpenWindow
+     * Keep calling nativeUpdate until nativeIsWindowClosed returns true. nativeDestroy
+     * closes the window itself.
+     * @param appPtr Pointer to the Bevy app instance
+     * @return false if the app has no window
+     */
+    external fun nativeCloseWindow(appPtr: Long): Boolean
+
+    /**
+     * Check whether the app's window is closed
+     * @param appPtr Pointer to the Bevy app instance
+     */
+    external fun nativeIsWindowClosed(appPtr: Long): Boolean
+
+    /**
+     * Open a new window on a surface after the app's window was closed
+     * @param appPtr Pointer to the Bevy app instance
+     * @param surface The Android Surface to render to
+     * @param width Surface width in pixels
+     * @param height Surface height in pixels
+     * @param scaleFactor Display density scale factor
+     * @return false if the app still has a window or the surface is invalid
+     */
+    external fun nativeOpenWindow(
+        appPtr: Long,
+        surface: Surface,
+        width: Int,
+        height: Int,
+        scaleFactor: Float
+    ): Boolean
+
+    /**
+     * Set how many apps to keep preloaded, e.g. for the Bevy cells of a scrolling list
+     *
+     * @param count Number of apps to keep ready
+     * @param width Expected surface width in pixels
+     * @param height Expected surface height in pixels
+     * @param scaleFactor Display density scale factor
+     * @param config Creation config for the preloaded apps, from nativeNewConfig, or 0
+     */
+    external fun nativeSetPreloadTarget(
+        count: Int,
+        width: Int,
+        height: Int,
+        scaleFactor: Float,
+        config: Long
+    )
+
+    /**
+     * Create or destroy one preloaded app; call while the UI is idle until it returns 0
+     * @return 1 if there was work to do, 0 if the pool is at its target
+     */
+    external fun nativePreloadStep(): Int
+
+    /**
+     * Destroy every app waiting in the preload pool
+     */
+    external fun nativeClearPreloaded()
+
+    /**
+     * Take a preloaded app and attach it to a surface
+     * The caller owns the app and destroys it with nativeDestroy.
+     * @param surface The Android Surface to render to
+     * @param width Surface width in pixels
+     * @param height Surface height in pixels
+     * @param scaleFactor Display density scale factor
+     * @return Pointer to the app, or 0 if none is preloaded
+     */
+    external fun nativeTakePreloadedApp(
+        surface: Surface,
+        width: Int,
+        height: Int,
+        scaleFactor: Float
+    ): Long
+
+    /**
+     * Start creating the app on a worker thread, without blocking the UI thread
+     * Plugins must not insert non-send resources while building.
+     * @param activity The Activity context (for AssetManager access)
+     * @param width Expected surface width in pixels
+     * @param height Expected surface height in pixels
+     * @param scaleFactor Display density scale factor
+     * @param config Creation config from nativeNewConfig, or 0
+     * @return Handle of the pending app, or 0 on failure
+     */
+    external fun nativeCreateAppAsync(
+        activity: Activity,
+        width: Int,
+        height: Int,
+        scaleFactor: Float,
+        config: Long
+    ): Long
+
+    /**
+     * Progress of an app from nativeCreateAppAsync
+     * @param pendingPtr Handle from nativeCreateAppAsync
+     * @return 0 while initializing, 1 once ready, 2 once taken
+     */
+    external fun nativeAsyncAppStatus(pendingPtr: Long): Int
+
+    /**
+     * Take the app from nativeCreateAppAsync once it is ready and attach it to a surface
+     * The caller owns the app and destroys it with nativeDestroy.
+     * @param pendingPtr Handle from nativeCreateAppAsync
+     * @param surface The Android Surface to render to
+     * @param width Surface width in pixels
+     * @param height Surface height in pixels
+     * @param scaleFactor Display density scale factor
+     * @return Pointer to the app, or 0 if it is not ready yet
+     */
+    external fun nativeTakeAsyncApp(
+        pendingPtr: Long,
+        surface: Surface,
+        width: Int,
+        height: Int,
+        scaleFactor: Float
+    ): Long
+
+    /**
+     * Release a handle from nativeCreateAppAsync, destroying the app unless it was taken
+     * @param pendingPtr Handle from nativeCreateAppAsync
+     */
+    external fun nativeReleaseAsyncApp(pendingPtr: Long)
+
+    /**
+     * Get how far the app's plugins have initialized, e.g. to sequence a splash screen
+     * Plugins finish in the first nativeUpdate after the surface is attached.
+     * @param appPtr Pointer to the Bevy app instance
+     * @return 0 = building, 1 = waiting for a surface, 2 = ready, 3 = finished
+     */
+    external fun nativeInitProgress(appPtr: Long): Int
+
+    /**
+     * Attach a surface on an external display (e.g. a Presentation) as an extra window
+     * @param appPtr Pointer to the Bevy app instance
+     * @param surface Surface on the external display
+     * @param width Surface width in pixels
+     * @param height Surface height in pixels
+     * @param scaleFactor Display density scale factor
+     * @return Display id, or 0 on failure
+     */
+    external fun nativeAttachExternalDisplay(
+        appPtr: Long,
+        surface: Surface,
+        width: Int,
+        height: Int,
+        scaleFactor: Float
+    ): Int
+
+    /**
+     * Notify Bevy of an external display surface size change
+     */
+    external fun nativeResizeExternalDisplay(
+        appPtr: Long,
+        displayId: Int,
+        width: Int,
+        height: Int,
+        scaleFactor: Float
+    )
+
+    /**
+     * Detach an external display; keep its surface alive until the next nativeUpdate returns
+     */
+    external fun nativeDetachExternalDisplay(appPtr: Long, displayId: Int)
+
+    /**
+     * Report a host lifecycle change
+     * @param appPtr Pointer to the Bevy app instance
+     * @param state 0 = active, 1 = inactive, 2 = background
+     */
+    external fun nativeSetLifecycle(appPtr: Long, state: Int)
+
+    /**
+     * Report the user's accessibility preferences; call after creating the app and on
+     * configuration changes
+     * @param appPtr Pointer to the Bevy app instance
+     * @param reduceMotion Whether animations are off (ANIMATOR_DURATION_SCALE is 0)
+     * @param boldText Whether bold text is on (Configuration.fontWeightAdjustment > 0)
+     * @param fontScale Configuration.fontScale
+     */
+    external fun nativeSetAccessibility(
+        appPtr: Long,
+        reduceMotion: Boolean,
+        boldText: Boolean,
+        fontScale: Float
+    )
+
+    /**
+     * Report the insets system bars and display cutouts cover; call after creating the app
+     * and whenever window insets change
+     * @param appPtr Pointer to the Bevy app instance
+     * @param top Top inset in dp
+     * @param left Left inset in dp
+     * @param bottom Bottom inset in dp
+     * @param right Right inset in dp
+     */
+    external fun nativeSetSafeArea(appPtr: Long, top: Float, left: Float, bottom: Float, right: Float)
+
+    /**
+     * Report the host's monotonic clock so host timestamps can be converted to Bevy time.
+     * Call it right before each update.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param hostTime Seconds on the host clock, e.g. `SystemClock.elapsedRealtimeNanos() / 1e9`
+     */
+    external fun nativeSyncClock(appPtr: Long, hostTime: Double)
+
+    /**
+     * Turn touch latency measurement on or off. While on, per-stage percentiles arrive
+     * from [nativeReceiveMessage] as `InputLatency` reserved messages.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param enabled Whether to measure
+     */
+    external fun nativeSetLatencyMeasurement(appPtr: Long, enabled: Boolean)
+
+    /**
+     * Report when the touch sent last was captured, for latency measurement.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param hostTime Seconds on the clock passed to [nativeSyncClock]
+     */
+    external fun nativeInputTimestamp(appPtr: Long, hostTime: Double)
+
+    /**
+     * Report that the oldest unreported frame was presented, for latency measurement.
+     * Call it once per update.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param hostTime Seconds on the clock passed to [nativeSyncClock]
+     */
+    external fun nativeFramePresented(appPtr: Long, hostTime: Double)
+
+    /**
+     * Set the rotation and mirroring applied when presenting Bevy's content
+     * @param appPtr Pointer to the Bevy app instance
+     * @param rotation 0 = none, 1 = 90, 2 = 180, 3 = 270 degrees clockwise
+     * @param mirror Mirror the content horizontally before rotating
+     */
+    external fun nativeSetSurfaceTransform(appPtr: Long, rotation: Int, mirror: Boolean)
+
+    /**
+     * Place the camera with a world-to-view matrix, disabling the orbit camera rig
+     * @param appPtr Pointer to the Bevy app instance
+     * @param view Column-major 4x4 matrix (16 floats), as used by android.opengl.Matrix
+     */
+    external fun nativeSetCameraView(appPtr: Long, view: FloatArray)
+
+    /**
+     * Configure dynamic resolution scaling, which lowers the render resolution when
+     * frames miss the target frame rate
+     * @param appPtr Pointer to the Bevy app instance
+     * @param enabled Whether the render scale is adjusted
+     * @param minScale Lowest render scale, greater than 0
+     * @param maxScale Highest render scale, at most 1
+     * @param targetFps Frame rate to sustain
+     */
+    external fun nativeSetDynamicResolution(
+        appPtr: Long,
+        enabled: Boolean,
+        minScale: Float,
+        maxScale: Float,
+        targetFps: Float
+    )
+
+    /**
+     * Get the shared state block the host writes scalar state into every frame, such as
+     * scroll offsets and slider values, without sending messages
+     * @param appPtr Pointer to the Bevy app instance
+     * @return Direct buffer over 64 native-order floats, valid until the app is destroyed;
+     *   use `order(ByteOrder.nativeOrder()).asFloatBuffer()` to write it
+     */
+    external fun nativeSharedState(appPtr: Long): java.nio.ByteBuffer?
+
+    /**
+     * Send a message to Bevy on a ring channel opened with `add_ring_channel`
+     * @param appPtr Pointer to the Bevy app instance
+     * @param topic Topic id of the ring channel
+     * @param buffer Direct ByteBuffer holding the message from position 0
+     * @param length Message length in bytes
+     * @return false if the ring is full or the topic has no ring channel
+     */
+    external fun nativeRingSend(appPtr: Long, topic: Int, buffer: java.nio.ByteBuffer, length: Int): Boolean
+
+    /**
+     * Receive the next message Bevy sent on a ring channel (non-blocking poll)
+     * @param appPtr Pointer to the Bevy app instance
+     * @param topic Topic id of the ring channel
+     * @param buffer Direct ByteBuffer the message is copied to, from position 0
+     * @return Message length, or 0 if none is waiting; if it exceeds the buffer capacity
+     *   nothing is copied and the message stays queued for a larger buffer
+     */
+    external fun nativeRingReceive(appPtr: Long, topic: Int, buffer: java.nio.ByteBuffer): Int
+
+    /**
+     * Push PCM audio captured by the host, e.g. with AudioRecord in ENCODING_PCM_FLOAT.
+     * Does not allocate, so it can be called from the recording thread.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param buffer Direct ByteBuffer in native byte order holding interleaved float samples
+     * @param sampleCount Number of samples (frames times channels) in the buffer
+     * @param channels Number of interleaved channels
+     * @param sampleRate Frames per second
+     * @param timestamp Capture time of the first frame in seconds, e.g. from
+     *   `SystemClock.elapsedRealtimeNanos() / 1e9`
+     * @return false if Bevy has fallen behind and the buffer was dropped
+     */
+    external fun nativePushAudioInput(
+        appPtr: Long,
+        buffer: java.nio.ByteBuffer,
+        sampleCount: Int,
+        channels: Int,
+        sampleRate: Int,
+        timestamp: Double
+    ): Boolean
+
+    /**
+     * Render Bevy's mixed audio output into a buffer for AudioTrack or Oboe.
+     * Requires the `host_audio` feature and HostAudioPlugin.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param buffer Direct ByteBuffer in native byte order receiving interleaved float samples
+     * @param sampleCount Number of samples (frames times channels) to render
+     * @param channels Number of interleaved channels, as configured in HostAudioPlugin
+     * @param sampleRate Frames per second, as configured in HostAudioPlugin
+     * @return false if the format does not match; output silence in that case
+     */
+    external fun nativeRenderAudio(
+        appPtr: Long,
+        buffer: java.nio.ByteBuffer,
+        sampleCount: Int,
+        channels: Int,
+        sampleRate: Int
+    ): Boolean
+
     /**
      * Send a message to Bevy
      * @param appPtr Pointer to the Bevy app instance
@@ -81,10 +590,254 @@ object BevyNative {
      */
     external fun nativeSendMessage(appPtr: Long, data: ByteArray)
 
+    /**
+     * Send a message to Bevy from a direct ByteBuffer, without allocating a ByteArray.
+     * Reuse one buffer for high-frequency messages.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param buffer Direct ByteBuffer holding the message from position 0
+     * @param length Message length in bytes
+     * @return false if the buffer is not direct or shorter than length
+     */
+    external fun nativeSendMessageBuffer(appPtr: Long, buffer: java.nio.ByteBuffer, length: Int): Boolean
+
     /**
      * Receive a message from Bevy
      * @param appPtr Pointer to the Bevy app instance
      * @return Message data as byte array, or null if no message available
      */
     external fun nativeReceiveMessage(appPtr: Long): ByteArray?
+
+    /**
+     * Receive a message from Bevy into a direct ByteBuffer (non-blocking poll), without
+     * allocating a ByteArray. Reuse one buffer for high-frequency messages.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param buffer Direct ByteBuffer the message is copied to, from position 0
+     * @return Message length, or 0 if none is waiting; if it exceeds the buffer capacity
+     *   nothing is copied and the message stays queued for a larger buffer
+     */
+    external fun nativeReceiveMessageInto(appPtr: Long, buffer: java.nio.ByteBuffer): Int
+
+    /**
+     * Receive up to maxMessages messages from Bevy in one call (non-blocking poll)
+     * @param appPtr Pointer to the Bevy app instance
+     * @param buffer Direct ByteBuffer the messages are copied to from position 0, each as a
+     *   little-endian Int length followed by its bytes
+     * @param maxMessages Most messages to copy
+     * @return Bytes written, or 0 if none is waiting; messages that don't fit stay queued,
+     *   and if not even the first fits, nothing is copied and the length returned exceeds
+     *   the buffer capacity
+     */
+    external fun nativeReceiveMessages(appPtr: Long, buffer: java.nio.ByteBuffer, maxMessages: Int): Int
+
+    /**
+     * Seal a message with the app's channel key before nativeSendMessage
+     * Requires the channel_encryption feature; the key is the one set as channel_key.
+     * @param key 32-byte channel key
+     * @param data Message to seal
+     * @return The sealed message, or null if the key is not 32 bytes
+     */
+    external fun nativeSealMessage(key: ByteArray, data: ByteArray): ByteArray?
+
+    /**
+     * Open a message from nativeReceiveMessage with the app's channel key
+     * @param key 32-byte channel key
+     * @param sealed Message received from Bevy
+     * @return The message, or null if it failed to authenticate
+     */
+    external fun nativeOpenMessage(key: ByteArray, sealed: ByteArray): ByteArray?
+
+    /**
+     * Get the regions that changed in the last update, to composite only those
+     * @param appPtr Pointer to the Bevy app instance
+     * @param rects Receives x, y, width, height per rect, in physical pixels
+     * @return Number of rects, 0 if nothing changed, or -1 if the whole frame changed
+     */
+    external fun nativeFrameDamage(appPtr: Long, rects: IntArray): Int
+
+    /**
+     * Get the GPU time of the most recently measured frame, if the app adds
+     * `GpuTimingPlugin`
+     * @param appPtr Pointer to the Bevy app instance
+     * @return Milliseconds, or -1 if GPU time isn't measured on this device
+     */
+    external fun nativeGpuFrameTimeMs(appPtr: Long): Double
+
+    /**
+     * Get when a startup phase started and how long it took; see `StartupTimings`
+     * @param appPtr Pointer to the Bevy app instance
+     * @param phase 0 = build, 1 = device request, 2 = finish, 3 = first pipeline,
+     * 4 = first frame, 5 = deferred plugins
+     * @param out Receives the milliseconds from creation to the start and the duration
+     * @return false if the phase hasn't ended yet
+     */
+    external fun nativeStartupPhase(appPtr: Long, phase: Int, out: DoubleArray): Boolean
+
+    /**
+     * Get the GPU adapter and limits the app renders with; see `GpuReport`
+     * @param appPtr Pointer to the Bevy app instance
+     * @return A JSON object, or null if the app doesn't render
+     */
+    external fun nativeGpuReport(appPtr: Long): String?
+
+    /**
+     * Get the error that froze the app; see `RecoveryPolicy`
+     * A frozen app keeps its last frame on screen and no longer updates.
+     * @param appPtr Pointer to the Bevy app instance
+     * @return The error, or null while the app is running
+     */
+    external fun nativeFrozenError(appPtr: Long): String?
+
+    /**
+     * Set the image a frozen app presents when it can't render its last frame
+     * Shown scaled to fit the surface on black; see `frozen_frame`.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param pixels width * height RGBA8 sRGB pixels, or null to clear the image
+     * @param width Width in pixels
+     * @param height Height in pixels
+     * @return false if the pixels don't match the dimensions
+     */
+    external fun nativeSetFallbackFrame(appPtr: Long, pixels: ByteArray?, width: Int, height: Int): Boolean
+
+    /**
+     * Release the GPU memory of dropped render resources, e.g. from `onTrimMemory`
+     * Empties the texture cache and waits for the GPU; see `RenderGcPlugin`.
+     * @param appPtr Pointer to the Bevy app instance
+     * @return false if the app has no render device
+     */
+    external fun nativeFlushRenderResources(appPtr: Long): Boolean
+
+    /**
+     * Capture the next frame the app renders in RenderDoc; needs the `gpu_capture` cargo
+     * feature and the app launched from RenderDoc
+     * @param appPtr Pointer to the Bevy app instance
+     * @return false if the app doesn't render
+     */
+    external fun nativeCaptureGpuFrame(appPtr: Long): Boolean
+
+    /**
+     * Start recording a Chrome trace of every app's schedules and systems; needs the
+     * `chrome_trace` cargo feature
+     * @param path File the trace is written to, for chrome://tracing or Perfetto
+     * @return false if a trace is already recording or the file can't be created
+     */
+    external fun nativeStartChromeTrace(path: String): Boolean
+
+    /**
+     * Stop recording the Chrome trace and complete its file
+     * @return false if no trace was recording or the file couldn't be written
+     */
+    external fun nativeStopChromeTrace(): Boolean
+
+    /**
+     * Switch a debug renderer on or off; needs the `debug_toggles` cargo feature
+     * @param appPtr Pointer to the Bevy app instance
+     * @param toggle 0 = AABBs, 1 = wireframe, 2 = UI overlay, 3 = lights
+     * @param enabled Whether to draw it
+     * @return false if the app doesn't have the renderer
+     */
+    external fun nativeSetDebugToggle(appPtr: Long, toggle: Int, enabled: Boolean): Boolean
+
+    /**
+     * Whether a debug renderer is on; needs the `debug_toggles` cargo feature
+     * @param appPtr Pointer to the Bevy app instance
+     * @param toggle 0 = AABBs, 1 = wireframe, 2 = UI overlay, 3 = lights
+     * @return 1 if on, 0 if off, -1 if the app doesn't have the renderer
+     */
+    external fun nativeDebugToggle(appPtr: Long, toggle: Int): Int
+
+    /**
+     * Receive the oldest lifecycle telemetry event of any app, for host analytics
+     * Events keep coming after an app is destroyed, so poll from a process-wide sink.
+     * @return The event as a reserved message, or null if none is queued
+     */
+    external fun nativeReceiveTelemetry(): ByteArray?
+
+    /**
+     * Send the host's protocol to the app, which checks it on the next update and replies
+     * with its own
+     * @param appPtr Pointer to the Bevy app instance
+     * @param version Channel protocol version the host was built against
+     * @param schemaHash Hash of the host's message definitions, or 0 to skip the check
+     * @param codecs Comma-separated encodings, most preferred first, or null for none
+     */
+    external fun nativeSendHandshake(appPtr: Long, version: Int, schemaHash: Long, codecs: String?)
+
+    /**
+     * Get the state of the protocol handshake
+     * @param appPtr Pointer to the Bevy app instance
+     * @return 0 = pending, 1 = compatible, 2 = mismatched
+     */
+    external fun nativeHandshakeStatus(appPtr: Long): Int
+
+    /**
+     * Get why the protocol handshake failed
+     * @param appPtr Pointer to the Bevy app instance
+     * @return The error message, or null if the handshake hasn't failed
+     */
+    external fun nativeHandshakeError(appPtr: Long): String?
+
+    /**
+     * Submit a long-running job to a handler registered with `add_host_job`.
+     *
+     * The result arrives from [nativeReceiveMessage] as a `JobResult` reserved message
+     * carrying the returned token.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param kind The kind of job, as registered by the app
+     * @param input The job's input
+     * @return The job's token, or 0 if it could not be submitted
+     */
+    external fun nativeSubmitJob(appPtr: Long, kind: String, input: ByteArray): Long
+
+    /**
+     * Cancel a job. A `Canceled` result follows unless the job already finished.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param token The token returned by [nativeSubmitJob]
+     */
+    external fun nativeCancelJob(appPtr: Long, token: Long)
+
+    /**
+     * Start recording input for replay, discarding a recording in progress.
+     * @param appPtr Pointer to the Bevy app instance
+     */
+    external fun nativeStartRecording(appPtr: Long)
+
+    /**
+     * Stop recording input.
+     * @param appPtr Pointer to the Bevy app instance
+     * @return The recorded log, or null if the app wasn't recording
+     */
+    external fun nativeStopRecording(appPtr: Long): ByteArray?
+
+    /**
+     * Replay a log from [nativeStopRecording], running one update per recorded frame.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param log The recorded log
+     * @return The number of frames run, or -1 if the log is malformed
+     */
+    external fun nativeReplay(appPtr: Long, log: ByteArray): Int
+
+    /**
+     * Save the app's state and destroy the app, releasing its surface and GPU resources.
+     * @param appPtr Pointer to the Bevy app instance, no longer valid unless this returns null
+     * @return The state to pass to [nativeRehydrate], or null if the app is still alive
+     */
+    external fun nativeHibernate(appPtr: Long): ByteArray?
+
+    /**
+     * Restore state from [nativeHibernate] in the app's next update, e.g. in an app from
+     * [nativeTakePreloadedApp].
+     * @param appPtr Pointer to the Bevy app instance
+     * @param state The hibernated state
+     * @return false if the state is malformed or from another version
+     */
+    external fun nativeRehydrate(appPtr: Long, state: ByteArray): Boolean
+
+    /**
+     * Wait until Bevy's latest frame is done on the GPU, before compositing a shared
+     * buffer it rendered into. Requires the `gpu_interop` feature.
+     * @param appPtr Pointer to the Bevy app instance
+     * @param timeoutMs Longest time to wait, in milliseconds
+     * @return false on timeout or if the app does not render with Vulkan
+     */
+    external fun nativeWaitFrameFence(appPtr: Long, timeoutMs: Int): Boolean
 }
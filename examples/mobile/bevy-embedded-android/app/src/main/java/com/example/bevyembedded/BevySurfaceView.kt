@@ -1,14 +1,37 @@
 package com.example.bevyembedded
 
+import android.content.ComponentCallbacks2
 import android.content.Context
+import android.content.res.Configuration
+import android.graphics.Rect
+import android.os.Build
+import android.provider.Settings
+import android.text.Editable
+import android.text.InputType
+import android.text.Selection
+import android.text.SpannableStringBuilder
 import android.util.AttributeSet
 import android.util.Log
 import android.view.Choreographer
+import android.view.InputDevice
+import android.view.KeyEvent
 import android.view.MotionEvent
 import android.view.Surface
 import android.view.SurfaceHolder
 import android.view.SurfaceView
+import android.view.ViewConfiguration
+import android.view.WindowInsets
+import android.view.accessibility.AccessibilityManager
+import android.view.inputmethod.BaseInputConnection
+import android.view.inputmethod.EditorInfo
+import android.view.inputmethod.InputConnection
+import android.view.inputmethod.InputMethodManager
+import androidx.core.view.WindowInsetsCompat
+import java.nio.ByteBuffer
+import java.nio.ByteOrder
 import java.util.concurrent.atomic.AtomicBoolean
+import kotlinx.coroutines.channels.Channel
+import kotlinx.coroutines.channels.ReceiveChannel
 
 /**
  * Android SurfaceView that hosts the Bevy engine
@@ -29,6 +52,19 @@ class BevySurfaceView
             private const val PHASE_MOVED = 1
             private const val PHASE_ENDED = 2
             private const val PHASE_CANCELED = 3
+
+            // Lifecycle states matching Rust
+            const val LIFECYCLE_ACTIVE = 0
+            const val LIFECYCLE_INACTIVE = 1
+            const val LIFECYCLE_BACKGROUND = 2
+
+            // Indices into the frame info written by nativeUpdateWithInfo
+            const val FRAME_INFO_FRAME = 0
+            const val FRAME_INFO_CPU_MS = 1
+            const val FRAME_INFO_GPU_MS = 2
+            const val FRAME_INFO_PENDING_MESSAGES = 3
+            const val FRAME_INFO_NEEDS_REDRAW = 4
+            const val FRAME_INFO_IDLE = 5
         }
 
         private var bevyAppPtr: Long = 0
@@ -37,6 +73,23 @@ class BevySurfaceView
 
         var onMessageReceived: ((ByteArray) -> Unit)? = null
         var onError: ((String) -> Unit)? = null
+        private val messageChannels = mutableSetOf<Channel<ByteArray>>()
+        // Receives batches of length-prefixed messages, grown for messages that don't fit
+        private var messageBuffer = ByteBuffer.allocateDirect(64 * 1024).order(ByteOrder.LITTLE_ENDIAN)
+        private var lifecycleState = LIFECYCLE_ACTIVE
+
+        /** Bevy's focused `HostTextField` as entity bits, or null while none is focused */
+        private var textField: Long? = null
+        private var textFieldKind = 0
+        private var textFieldMultiline = false
+        private val textFieldEditable = SpannableStringBuilder()
+
+        /** Signals of the last update, indexed by the FRAME_INFO_ constants */
+        val frameInfo = DoubleArray(6)
+
+        /** Pointer to the Bevy app, or 0 before it's created and after it's destroyed */
+        val appPtr: Long
+            get() = bevyAppPtr
         private val scaleFactor: Float = context.resources.displayMetrics.density
 
         init {
@@ -44,6 +97,8 @@ class BevySurfaceView
             // Enable touch events
             isClickable = true
             isFocusable = true
+            // Take hardware keyboard focus when touched
+            isFocusableInTouchMode = true
         }
 
         override fun surfaceCreated(holder: SurfaceHolder) {
@@ -74,6 +129,10 @@ class BevySurfaceView
             Log.d(TAG, "Surface destroyed")
             // Only stop rendering, don't destroy Bevy - it might come back
             pauseRendering()
+            // Touches that were down never get their up events
+            if (bevyAppPtr != 0L) {
+                BevyNative.nativeCancelTouches(bevyAppPtr)
+            }
         }
 
         private fun setupBevy(
@@ -88,6 +147,12 @@ class BevySurfaceView
 
                 if (bevyAppPtr != 0L) {
                     Log.d(TAG, "Bevy app created successfully: $bevyAppPtr")
+                    context.registerComponentCallbacks(memoryCallbacks)
+                    sendAccessibilitySettings(resources.configuration)
+                    rootWindowInsets?.let { sendSafeArea(it) }
+                    if (lifecycleState != LIFECYCLE_ACTIVE) {
+                        BevyNative.nativeSetLifecycle(bevyAppPtr, lifecycleState)
+                    }
                     startRenderLoop()
                 } else {
                     Log.e(TAG, "Failed to create Bevy app")
@@ -97,6 +162,40 @@ class BevySurfaceView
             }
         }
 
+        override fun onConfigurationChanged(newConfig: Configuration) {
+            super.onConfigurationChanged(newConfig)
+            sendAccessibilitySettings(newConfig)
+        }
+
+        override fun onApplyWindowInsets(insets: WindowInsets): WindowInsets {
+            sendSafeArea(insets)
+            return super.onApplyWindowInsets(insets)
+        }
+
+        private fun sendSafeArea(insets: WindowInsets) {
+            if (bevyAppPtr == 0L) return
+            val safeArea =
+                WindowInsetsCompat
+                    .toWindowInsetsCompat(insets, this)
+                    .getInsets(WindowInsetsCompat.Type.systemBars() or WindowInsetsCompat.Type.displayCutout())
+            BevyNative.nativeSetSafeArea(
+                bevyAppPtr,
+                safeArea.top / scaleFactor,
+                safeArea.left / scaleFactor,
+                safeArea.bottom / scaleFactor,
+                safeArea.right / scaleFactor,
+            )
+        }
+
+        private fun sendAccessibilitySettings(config: Configuration) {
+            if (bevyAppPtr == 0L) return
+            val animatorScale =
+                Settings.Global.getFloat(context.contentResolver, Settings.Global.ANIMATOR_DURATION_SCALE, 1f)
+            val boldText =
+                Build.VERSION.SDK_INT >= Build.VERSION_CODES.S && config.fontWeightAdjustment > 0
+            BevyNative.nativeSetAccessibility(bevyAppPtr, animatorScale == 0f, boldText, config.fontScale)
+        }
+
         private fun startRenderLoop() {
             isRunning.set(true)
             Log.d(TAG, "Render loop started")
@@ -115,7 +214,7 @@ class BevySurfaceView
 
                     try {
                         // Update Bevy (renders one frame) - returns 0 on success
-                        val errorCode = BevyNative.nativeUpdate(bevyAppPtr)
+                        val errorCode = BevyNative.nativeUpdateWithInfo(bevyAppPtr, frameInfo)
 
                         if (errorCode != 0) {
                             // Get the error message
@@ -132,8 +231,10 @@ class BevySurfaceView
                             return
                         }
 
-                        // Poll for messages from Bevy
-                        pollBevyMessages()
+                        // Poll for messages from Bevy, if it sent any
+                        if (frameInfo[FRAME_INFO_PENDING_MESSAGES] > 0) {
+                            pollBevyMessages()
+                        }
                     } catch (e: Exception) {
                         Log.e(TAG, "Error in render loop", e)
                     }
@@ -145,16 +246,53 @@ class BevySurfaceView
 
         private fun pollBevyMessages() {
             try {
-                val message = BevyNative.nativeReceiveMessage(bevyAppPtr)
-                if (message != null && message.isNotEmpty()) {
-                    // Choreographer callbacks run on main thread, so we can invoke directly
-                    onMessageReceived?.invoke(message)
+                // Drain every message of the frame, so channels don't fall behind Bevy. Each
+                // call receives a batch of `len: Int | bytes` messages.
+                while (true) {
+                    val bytesRead = BevyNative.nativeReceiveMessages(bevyAppPtr, messageBuffer, 64)
+                    if (bytesRead == 0) break
+                    if (bytesRead > messageBuffer.capacity()) {
+                        messageBuffer = ByteBuffer.allocateDirect(bytesRead).order(ByteOrder.LITTLE_ENDIAN)
+                        continue
+                    }
+
+                    var offset = 0
+                    while (offset + 4 <= bytesRead) {
+                        val message = ByteArray(messageBuffer.getInt(offset))
+                        messageBuffer.position(offset + 4)
+                        messageBuffer.get(message)
+                        offset += 4 + message.size
+                        handleBevyMessage(message)
+                    }
                 }
             } catch (e: Exception) {
                 Log.e(TAG, "Error polling messages", e)
             }
         }
 
+        private fun handleBevyMessage(message: ByteArray) {
+            bevyReservedPayload(message, BEVY_TEXT_INPUT_REQUEST_TOPIC)?.let(::handleTextInputRequest)
+            bevyReservedPayload(message, BEVY_ANNOUNCE_TOPIC)?.let(::postAnnouncement)
+            for (channel in synchronized(messageChannels) { messageChannels.toList() }) {
+                channel.trySend(message)
+            }
+            // Choreographer callbacks run on main thread, so we can invoke directly
+            onMessageReceived?.invoke(message)
+        }
+
+        /**
+         * Open a channel receiving every message from Bevy, until it's cancelled or the app is destroyed
+         *
+         * Messages are delivered from the next frame on, so a channel opened before sending a
+         * request can't miss its response.
+         */
+        fun openMessageChannel(): ReceiveChannel<ByteArray> {
+            val channel = Channel<ByteArray>(Channel.UNLIMITED)
+            synchronized(messageChannels) { messageChannels.add(channel) }
+            channel.invokeOnClose { synchronized(messageChannels) { messageChannels.remove(channel) } }
+            return channel
+        }
+
         private fun pauseRendering() {
             if (isRunning.compareAndSet(true, false)) {
                 Log.d(TAG, "Pausing rendering")
@@ -162,10 +300,26 @@ class BevySurfaceView
             }
         }
 
+        /** Releases the GPU memory of dropped resources when the system runs low */
+        private val memoryCallbacks =
+            object : ComponentCallbacks2 {
+                override fun onTrimMemory(level: Int) {
+                    if (bevyAppPtr != 0L && level >= ComponentCallbacks2.TRIM_MEMORY_RUNNING_LOW) {
+                        BevyNative.nativeFlushRenderResources(bevyAppPtr)
+                    }
+                }
+
+                override fun onConfigurationChanged(newConfig: Configuration) {}
+
+                @Deprecated("Deprecated in Java")
+                override fun onLowMemory() = onTrimMemory(ComponentCallbacks2.TRIM_MEMORY_COMPLETE)
+            }
+
         private fun stopBevy() {
             pauseRendering()
 
             if (bevyAppPtr != 0L) {
+                context.unregisterComponentCallbacks(memoryCallbacks)
                 try {
                     BevyNative.nativeDestroy(bevyAppPtr)
                     Log.d(TAG, "Bevy app destroyed")
@@ -174,6 +328,9 @@ class BevySurfaceView
                 }
                 bevyAppPtr = 0
             }
+            for (channel in synchronized(messageChannels) { messageChannels.toList() }) {
+                channel.close()
+            }
         }
 
         override fun onTouchEvent(event: MotionEvent): Boolean {
@@ -198,10 +355,9 @@ class BevySurfaceView
                         sendTouchEvent(PHASE_ENDED, event, pointerIndex)
                     }
                     MotionEvent.ACTION_CANCEL -> {
-                        // Send cancel for all active pointers
-                        for (i in 0 until event.pointerCount) {
-                            sendTouchEvent(PHASE_CANCELED, event, i)
-                        }
+                        // Cancel every touch Bevy has down, including pointers whose up
+                        // events were lost
+                        BevyNative.nativeCancelTouches(bevyAppPtr)
                     }
                     else -> return super.onTouchEvent(event)
                 }
@@ -213,6 +369,201 @@ class BevySurfaceView
             return true
         }
 
+        override fun onGenericMotionEvent(event: MotionEvent): Boolean {
+            if (bevyAppPtr == 0L ||
+                event.action != MotionEvent.ACTION_SCROLL ||
+                !event.isFromSource(InputDevice.SOURCE_ROTARY_ENCODER)
+            ) {
+                return super.onGenericMotionEvent(event)
+            }
+            val scrollFactor = ViewConfiguration.get(context).scaledVerticalScrollFactor
+            BevyNative.nativeRotaryEvent(bevyAppPtr, -event.getAxisValue(MotionEvent.AXIS_SCROLL) * scrollFactor)
+            return true
+        }
+
+        override fun onKeyDown(keyCode: Int, event: KeyEvent): Boolean =
+            sendKeyEvent(event) || super.onKeyDown(keyCode, event)
+
+        override fun onKeyUp(keyCode: Int, event: KeyEvent): Boolean =
+            sendKeyEvent(event) || super.onKeyUp(keyCode, event)
+
+        override fun onFocusChanged(gainFocus: Boolean, direction: Int, previouslyFocusedRect: Rect?) {
+            super.onFocusChanged(gainFocus, direction, previouslyFocusedRect)
+            if (bevyAppPtr != 0L) {
+                BevyNative.nativeKeyboardFocus(bevyAppPtr, gainFocus)
+            }
+        }
+
+        private fun sendKeyEvent(event: KeyEvent): Boolean {
+            // Leave system keys such as back and volume to the activity
+            if (bevyAppPtr == 0L || event.isSystem) return false
+            BevyNative.nativeKeyEvent(
+                bevyAppPtr, event.keyCode, event.action, event.repeatCount,
+                event.metaState, event.unicodeChar
+            )
+            return true
+        }
+
+        override fun onCheckIsTextEditor(): Boolean = textField != null
+
+        override fun onCreateInputConnection(outAttrs: EditorInfo): InputConnection? {
+            if (textField == null) return null
+            outAttrs.inputType =
+                when (textFieldKind) {
+                    1 -> InputType.TYPE_CLASS_NUMBER
+                    2 -> InputType.TYPE_CLASS_NUMBER or InputType.TYPE_NUMBER_FLAG_DECIMAL
+                    3 -> InputType.TYPE_CLASS_TEXT or InputType.TYPE_TEXT_VARIATION_EMAIL_ADDRESS
+                    4 -> InputType.TYPE_CLASS_TEXT or InputType.TYPE_TEXT_VARIATION_URI
+                    5 -> InputType.TYPE_CLASS_TEXT or InputType.TYPE_TEXT_VARIATION_PASSWORD
+                    6 -> InputType.TYPE_CLASS_PHONE
+                    else -> InputType.TYPE_CLASS_TEXT
+                }
+            if (textFieldMultiline) {
+                outAttrs.inputType = outAttrs.inputType or InputType.TYPE_TEXT_FLAG_MULTI_LINE
+                outAttrs.imeOptions = EditorInfo.IME_FLAG_NO_FULLSCREEN
+            } else {
+                outAttrs.imeOptions = EditorInfo.IME_ACTION_DONE or EditorInfo.IME_FLAG_NO_FULLSCREEN
+            }
+            outAttrs.initialSelStart = Selection.getSelectionStart(textFieldEditable)
+            outAttrs.initialSelEnd = Selection.getSelectionEnd(textFieldEditable)
+            return TextFieldConnection()
+        }
+
+        override fun onKeyPreIme(keyCode: Int, event: KeyEvent): Boolean {
+            // Back closes the keyboard, which blurs the field
+            val field = textField
+            if (field != null && keyCode == KeyEvent.KEYCODE_BACK && event.action == KeyEvent.ACTION_UP) {
+                textField = null
+                sendTextInput(ByteBuffer.allocate(9).order(ByteOrder.LITTLE_ENDIAN).put(2).putLong(field))
+            }
+            return super.onKeyPreIme(keyCode, event)
+        }
+
+        /**
+         * Speak a `priority: u8 | text: string` announcement with TalkBack
+         */
+        private fun postAnnouncement(payload: ByteArray) {
+            val accessibility = context.getSystemService(AccessibilityManager::class.java)
+            if (!accessibility.isEnabled || payload.size < 5) return
+            val announcement = ByteBuffer.wrap(payload).order(ByteOrder.LITTLE_ENDIAN)
+            announcement.get() // TalkBack has no priorities
+            val text = ByteArray(announcement.getInt()).also { announcement.get(it) }
+            announceForAccessibility(text.decodeToString())
+        }
+
+        /**
+         * Present or dismiss the keyboard for a `HostTextField` as Bevy asks
+         */
+        private fun handleTextInputRequest(payload: ByteArray) {
+            val request = ByteBuffer.wrap(payload).order(ByteOrder.LITTLE_ENDIAN)
+            val op = request.get().toInt()
+            val field = request.getLong()
+            val imm = context.getSystemService(InputMethodManager::class.java)
+            if (op != 0) {
+                if (textField == field) {
+                    textField = null
+                    imm.hideSoftInputFromWindow(windowToken, 0)
+                    imm.restartInput(this)
+                }
+                return
+            }
+
+            textFieldKind = request.get().toInt()
+            textFieldMultiline = request.get().toInt() != 0
+            val utf8 = ByteArray(request.getInt()).also { request.get(it) }
+            // Bevy sends byte offsets, Android uses UTF-16 indices
+            fun charIndex(offset: Int) = utf8.copyOfRange(0, offset.coerceIn(0, utf8.size)).decodeToString().length
+            val selectionStart = charIndex(request.getInt())
+            val selectionEnd = charIndex(request.getInt())
+            textFieldEditable.replace(0, textFieldEditable.length, utf8.decodeToString())
+            Selection.setSelection(textFieldEditable, selectionStart, selectionEnd)
+            textField = field
+            requestFocus()
+            imm.restartInput(this)
+            imm.showSoftInput(this, 0)
+        }
+
+        /**
+         * Send the text, selection and composition of the focused field to Bevy
+         */
+        private fun sendTextFieldEdit() {
+            val field = textField ?: return
+            val editable = textFieldEditable
+            fun utf8Offset(index: Int) =
+                editable.subSequence(0, index.coerceIn(0, editable.length)).toString().encodeToByteArray().size
+            val text = editable.toString().encodeToByteArray()
+            val composingStart = BaseInputConnection.getComposingSpanStart(editable)
+            val composingEnd = BaseInputConnection.getComposingSpanEnd(editable)
+            val payload = ByteBuffer.allocate(29 + text.size).order(ByteOrder.LITTLE_ENDIAN)
+            payload.put(0).putLong(field).putInt(text.size).put(text)
+            payload.putInt(utf8Offset(Selection.getSelectionStart(editable)))
+            payload.putInt(utf8Offset(Selection.getSelectionEnd(editable)))
+            if (composingStart < 0) {
+                payload.putInt(0).putInt(0)
+            } else {
+                payload.putInt(utf8Offset(composingStart)).putInt(utf8Offset(composingEnd))
+            }
+            sendTextInput(payload)
+        }
+
+        private fun sendTextInput(payload: ByteBuffer) {
+            sendMessage(bevyReservedMessage(BEVY_TEXT_INPUT_TOPIC, payload.array()))
+        }
+
+        /**
+         * Lets the keyboard edit the focused field, reporting every change to Bevy
+         */
+        private inner class TextFieldConnection : BaseInputConnection(this@BevySurfaceView, true) {
+            override fun getEditable(): Editable = textFieldEditable
+
+            override fun commitText(text: CharSequence?, newCursorPosition: Int): Boolean =
+                super.commitText(text, newCursorPosition).also { sendTextFieldEdit() }
+
+            override fun setComposingText(text: CharSequence?, newCursorPosition: Int): Boolean =
+                super.setComposingText(text, newCursorPosition).also { sendTextFieldEdit() }
+
+            override fun setComposingRegion(start: Int, end: Int): Boolean =
+                super.setComposingRegion(start, end).also { sendTextFieldEdit() }
+
+            override fun finishComposingText(): Boolean =
+                super.finishComposingText().also { sendTextFieldEdit() }
+
+            override fun deleteSurroundingText(beforeLength: Int, afterLength: Int): Boolean =
+                super.deleteSurroundingText(beforeLength, afterLength).also { sendTextFieldEdit() }
+
+            override fun setSelection(start: Int, end: Int): Boolean =
+                super.setSelection(start, end).also { sendTextFieldEdit() }
+
+            override fun performEditorAction(actionCode: Int): Boolean {
+                val field = textField ?: return false
+                sendTextInput(ByteBuffer.allocate(9).order(ByteOrder.LITTLE_ENDIAN).put(1).putLong(field))
+                return true
+            }
+
+            override fun sendKeyEvent(event: KeyEvent): Boolean {
+                // Some keyboards send backspace and return as key events
+                if (event.action == KeyEvent.ACTION_DOWN) {
+                    when (event.keyCode) {
+                        KeyEvent.KEYCODE_DEL -> {
+                            val start = Selection.getSelectionStart(textFieldEditable)
+                            val end = Selection.getSelectionEnd(textFieldEditable)
+                            if (start in 0 until end) {
+                                textFieldEditable.delete(start, end)
+                                sendTextFieldEdit()
+                            } else {
+                                deleteSurroundingText(1, 0)
+                            }
+                            return true
+                        }
+                        KeyEvent.KEYCODE_ENTER -> if (!textFieldMultiline) {
+                            return performEditorAction(EditorInfo.IME_ACTION_DONE)
+                        }
+                    }
+                }
+                return super.sendKeyEvent(event)
+            }
+        }
+
         override fun performClick(): Boolean {
             super.performClick()
             return true
@@ -222,7 +573,32 @@ class BevySurfaceView
             val x = event.getX(pointerIndex)
             val y = event.getY(pointerIndex)
             val id = event.getPointerId(pointerIndex).toLong()
-            BevyNative.nativeTouchEvent(bevyAppPtr, phase, x, y, id)
+            BevyNative.nativeTouchEventWithAxes(
+                bevyAppPtr, phase, x, y, id,
+                event.getPressure(pointerIndex),
+                event.getTouchMajor(pointerIndex),
+                event.getTouchMinor(pointerIndex),
+                event.getOrientation(pointerIndex)
+            )
+        }
+
+        /**
+         * Report a host lifecycle change, one of the `LIFECYCLE_` constants
+         *
+         * A state set before the app is created is applied when it is.
+         */
+        fun setLifecycle(state: Int) {
+            lifecycleState = state
+            if (bevyAppPtr != 0L) {
+                BevyNative.nativeSetLifecycle(bevyAppPtr, state)
+            }
+        }
+
+        /**
+         * Stop rendering and destroy the Bevy app
+         */
+        fun release() {
+            stopBevy()
         }
 
         /**
@@ -238,6 +614,19 @@ class BevySurfaceView
             }
         }
 
+        /**
+         * Send `length` bytes from position 0 of a direct ByteBuffer to Bevy, reusing the buffer
+         */
+        fun sendMessage(buffer: ByteBuffer, length: Int) {
+            if (bevyAppPtr != 0L && isRunning.get()) {
+                try {
+                    BevyNative.nativeSendMessageBuffer(bevyAppPtr, buffer, length)
+                } catch (e: Exception) {
+                    Log.e(TAG, "Error sending message", e)
+                }
+            }
+        }
+
         override fun onDetachedFromWindow() {
             super.onDetachedFromWindow()
             stopBevy()
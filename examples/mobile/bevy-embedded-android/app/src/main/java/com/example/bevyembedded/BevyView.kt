@@ -0,0 +1,67 @@
+package com.example.bevyembedded
+
+import androidx.compose.runtime.Composable
+import androidx.compose.runtime.DisposableEffect
+import androidx.compose.runtime.getValue
+import androidx.compose.runtime.mutableStateOf
+import androidx.compose.runtime.remember
+import androidx.compose.runtime.rememberUpdatedState
+import androidx.compose.runtime.setValue
+import androidx.compose.ui.Modifier
+import androidx.compose.ui.viewinterop.AndroidView
+import androidx.lifecycle.Lifecycle
+import androidx.lifecycle.LifecycleEventObserver
+import androidx.lifecycle.compose.LocalLifecycleOwner
+
+/**
+ * Composable that hosts a Bevy app
+ *
+ * Creates the app when its surface appears, updates it on every Choreographer frame,
+ * forwards touches with their pointer IDs, reports the lifecycle of the enclosing
+ * LifecycleOwner and destroys the app when it leaves the composition.
+ *
+ * @param modifier Modifier of the view
+ * @param onController Called once with the controller of the app
+ * @param onMessage Called on the main thread with each message from Bevy
+ * @param onError Called when Bevy stops on an error
+ */
+@Composable
+fun BevyView(
+    modifier: Modifier = Modifier,
+    onController: (BevyController) -> Unit = {},
+    onMessage: (ByteArray) -> Unit = {},
+    onError: (String) -> Unit = {},
+) {
+    val currentOnMessage by rememberUpdatedState(onMessage)
+    val currentOnError by rememberUpdatedState(onError)
+    val lifecycleOwner = LocalLifecycleOwner.current
+    var surfaceView by remember { mutableStateOf<BevySurfaceView?>(null) }
+
+    AndroidView(
+        factory = { context ->
+            BevySurfaceView(context).apply {
+                onMessageReceived = { currentOnMessage(it) }
+                this.onError = { currentOnError(it) }
+                onController(BevyController(this))
+                surfaceView = this
+            }
+        },
+        modifier = modifier,
+        onRelease = { it.release() },
+    )
+
+    DisposableEffect(lifecycleOwner, surfaceView) {
+        val view = surfaceView
+        val observer =
+            LifecycleEventObserver { _, event ->
+                when (event) {
+                    Lifecycle.Event.ON_RESUME -> view?.setLifecycle(BevySurfaceView.LIFECYCLE_ACTIVE)
+                    Lifecycle.Event.ON_PAUSE -> view?.setLifecycle(BevySurfaceView.LIFECYCLE_INACTIVE)
+                    Lifecycle.Event.ON_STOP -> view?.setLifecycle(BevySurfaceView.LIFECYCLE_BACKGROUND)
+                    else -> {}
+                }
+            }
+        lifecycleOwner.lifecycle.addObserver(observer)
+        onDispose { lifecycleOwner.lifecycle.removeObserver(observer) }
+    }
+}
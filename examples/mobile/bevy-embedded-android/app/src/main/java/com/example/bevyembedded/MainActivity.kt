@@ -16,7 +16,6 @@ import androidx.compose.ui.graphics.Color
 import androidx.compose.ui.text.font.FontFamily
 import androidx.compose.ui.unit.dp
 import androidx.compose.ui.unit.sp
-import androidx.compose.ui.viewinterop.AndroidView
 import java.nio.ByteBuffer
 import java.nio.ByteOrder
 
@@ -36,17 +35,10 @@ fun BevyEmbeddedApp() {
 
     Box(modifier = Modifier.fillMaxSize()) {
         // Bevy render view
-        AndroidView(
-            factory = { context ->
-                BevySurfaceView(context).apply {
-                    bevyController = BevyController(this)
-
-                    onMessageReceived = { data ->
-                        cameraMatrix = handleBevyMessage(data)
-                    }
-                }
-            },
+        BevyView(
             modifier = Modifier.fillMaxSize(),
+            onController = { bevyController = it },
+            onMessage = { data -> cameraMatrix = handleBevyMessage(data) },
         )
 
         // UI overlay
@@ -130,6 +122,9 @@ fun ColorButton(
     }
 }
 
+/** Id the example binds the cube color property to */
+const val CUBE_COLOR_PROPERTY = 0
+
 fun sendColor(
     controller: BevyController?,
     color: Color,
@@ -149,11 +144,9 @@ fun sendColor(
             floatArrayOf(color.red, color.green, color.blue, color.alpha)
         }
 
-    // Pack Vec4 as 16 bytes (4 x f32 in little-endian)
-    val buffer = ByteBuffer.allocate(16).order(ByteOrder.LITTLE_ENDIAN)
-    rgba.forEach { buffer.putFloat(it) }
-
-    controller?.sendMessage(buffer.array())
+    // `cube.color` is declared by the Rust app; Bevy tweens to the new color
+    controller?.bindProperty("cube.color", CUBE_COLOR_PROPERTY)
+    controller?.setProperty(CUBE_COLOR_PROPERTY, rgba, duration = 0.3f, easing = 3)
 }
 
 fun handleBevyMessage(data: ByteArray): String =
@@ -1,17 +1,22 @@
 //! Embedded Bevy example for iOS
 
 use bevy::{
-    input::touch::{TouchInput, TouchPhase},
     log::{Level, LogPlugin},
     prelude::*,
 };
 use bevy_embedded::{export_embedded_app, prelude::*};
 
+/// Topic of the camera matrices sent to the host
+const CAMERA_TOPIC: u16 = 1;
+
 /// Our embedded app implementation
 struct MobileEmbeddedExample;
 
 impl EmbeddedApp for MobileEmbeddedExample {
     fn setup(mut app: &mut App) {
+        // The host picks the adapter and limits through the creation config
+        let gpu = app.world().resource::<EmbeddedConfig>().gpu.clone();
+
         // Configure plugins and systems
         app.add_plugins(
             DefaultPlugins
@@ -25,10 +30,23 @@ impl EmbeddedApp for MobileEmbeddedExample {
                     level: Level::DEBUG,
                     filter: "wgpu=debug,bevy_render=debug,bevy_ecs=debug".to_string(),
                     ..Default::default()
-                }),
+                })
+                .with_gpu_preferences(&gpu),
+        )
+        // Release GPU memory of dropped assets, also while the widget is hidden
+        .add_plugins(RenderGcPlugin)
+        // Orbit the camera with touches, without coasting while reduce motion is on
+        .add_plugins((CameraRigPlugin, AccessibilityPlugin))
+        // Orbiting sends a matrix per touch move; the host only needs the latest per frame
+        .set_send_policy(CAMERA_TOPIC, SendPolicy::KeepLatest)
+        // The host tweens the cube color through the property protocol
+        .add_host_property(
+            "cube.color",
+            PropertyValue::Color(Color::srgb(0.5, 0.4, 0.3)),
+            set_cube_color,
         )
         .add_systems(Startup, setup_scene)
-        .add_systems(Update, (touch_camera, handle_messages));
+        .add_systems(Update, send_camera_transform);
     }
 }
 
@@ -46,7 +64,7 @@ fn setup_scene(
         MeshMaterial3d(materials.add(Color::srgb(0.1, 0.2, 0.1))),
     ));
 
-    // cube (this one will change color from Swift messages)
+    // cube (this one changes color when the host sets `cube.color`)
     commands.spawn((
         Mesh3d(meshes.add(Cuboid::default())),
         MeshMaterial3d(materials.add(Color::srgb(0.5, 0.4, 0.3))),
@@ -71,78 +89,38 @@ fn setup_scene(
         Transform::from_xyz(4.0, 8.0, 4.0),
     ));
 
-    // camera
+    // camera, orbited with one finger, panned with two and zoomed by pinching
     commands.spawn((
         Camera3d::default(),
-        Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        OrbitCamera::looking_at(Vec3::new(-2.0, 2.5, 5.0), Vec3::ZERO),
     ));
 }
 
-fn touch_camera(
-    mut touch_inputs: MessageReader<TouchInput>,
-    mut camera_transform: Single<&mut Transform, With<Camera3d>>,
-    mut last_position: Local<Option<Vec2>>,
-    windows: Query<&Window>,
+fn send_camera_transform(
+    camera_transform: Single<&Transform, (With<Camera3d>, Changed<Transform>)>,
     channel: Res<HostChannel>,
 ) {
-    let Ok(window) = windows.single() else {
-        return;
-    };
-
-    for touch_input in touch_inputs.read() {
-        if touch_input.phase == TouchPhase::Started {
-            *last_position = None;
-        }
-        if let Some(last_pos) = *last_position {
-            **camera_transform = Transform::from_xyz(
-                camera_transform.translation.x
-                    + (touch_input.position.x - last_pos.x) / window.width() * 5.0,
-                camera_transform.translation.y,
-                camera_transform.translation.z
-                    + (touch_input.position.y - last_pos.y) / window.height() * 5.0,
-            )
-            .looking_at(Vec3::ZERO, Vec3::Y);
-
-            // Send camera transform matrix to host app
-            let mat = camera_transform.to_matrix();
-            let bytes: [u8; 64] = bytemuck::cast(mat.to_cols_array());
-            channel.send(bytes.to_vec());
-        }
-        *last_position = Some(touch_input.position);
-    }
+    // Send camera transform matrix to host app
+    let mat = camera_transform.to_matrix();
+    let bytes: [u8; 64] = bytemuck::cast(mat.to_cols_array());
+    channel.send_topic(CAMERA_TOPIC, bytes.to_vec());
 }
 
 /// Component to mark the cube that changes color
 #[derive(Component)]
 struct ColorChangingCube;
 
-fn handle_messages(
-    channel: Res<HostChannel>,
-    mut cubes: Query<&mut MeshMaterial3d<StandardMaterial>, With<ColorChangingCube>>,
+fn set_cube_color(
+    In(value): In<PropertyValue>,
+    cubes: Query<&MeshMaterial3d<StandardMaterial>, With<ColorChangingCube>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    // Receive Vec4 color messages from Swift (16 bytes: 4 f32s for RGBA)
-    while let Some(message) = channel.receive() {
-        if message.len() == 16 {
-            // Unpack 4 f32s from the binary blob
-            let r = f32::from_le_bytes([message[0], message[1], message[2], message[3]]);
-            let g = f32::from_le_bytes([message[4], message[5], message[6], message[7]]);
-            let b = f32::from_le_bytes([message[8], message[9], message[10], message[11]]);
-            let a = f32::from_le_bytes([message[12], message[13], message[14], message[15]]);
-
-            info!("Received color from Swift: ({}, {}, {}, {})", r, g, b, a);
-
-            // Update cube material color
-            for material_handle in cubes.iter_mut() {
-                if let Some(material) = materials.get_mut(&material_handle.0) {
-                    material.base_color = Color::srgba(r, g, b, a);
-                }
-            }
-        } else {
-            warn!(
-                "Received message with unexpected length: {} bytes",
-                message.len()
-            );
+    let PropertyValue::Color(color) = value else {
+        return;
+    };
+    for material_handle in &cubes {
+        if let Some(material) = materials.get_mut(&material_handle.0) {
+            material.base_color = color;
         }
     }
 }
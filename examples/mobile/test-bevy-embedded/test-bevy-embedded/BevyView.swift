@@ -0,0 +1,178 @@
+//
+//  BevyView.swift
+//  Bevy Embedded Example
+//
+
+import SwiftUI
+import MetalKit
+import UIKit.UIGestureRecognizerSubclass
+
+/// Forwards every touch on its view to Bevy, including coalesced and predicted samples
+///
+/// It never prevents other recognizers and doesn't cancel touches in the view, so
+/// SwiftUI gestures and controls layered on top keep working.
+class BevyTouchGestureRecognizer: UIGestureRecognizer {
+    weak var coordinator: BevyMetalViewCoordinator?
+    private var activeTouches = Set<UITouch>()
+
+    init(coordinator: BevyMetalViewCoordinator) {
+        self.coordinator = coordinator
+        super.init(target: nil, action: nil)
+        cancelsTouchesInView = false
+        delaysTouchesBegan = false
+        delaysTouchesEnded = false
+    }
+
+    override func canPrevent(_ preventedGestureRecognizer: UIGestureRecognizer) -> Bool { false }
+
+    override func canBePrevented(by preventingGestureRecognizer: UIGestureRecognizer) -> Bool { false }
+
+    override func touchesBegan(_ touches: Set<UITouch>, with event: UIEvent) {
+        forward(touches, phase: 0)
+        activeTouches.formUnion(touches)
+        if state == .possible {
+            state = .began
+        }
+    }
+
+    override func touchesMoved(_ touches: Set<UITouch>, with event: UIEvent) {
+        guard let view = view, let coordinator = coordinator else { return }
+        for touch in touches {
+            let id = UInt64(touch.hash)
+            // Coalesced samples carry the movement between display refreshes
+            for sample in event.coalescedTouches(for: touch) ?? [touch] {
+                coordinator.handleTouch(phase: 1, location: sample.location(in: view), id: id)
+            }
+            // Predictions extend the touches just sent, so they follow them
+            for predicted in event.predictedTouches(for: touch) ?? [] {
+                coordinator.handlePredictedTouch(location: predicted.location(in: view), id: id)
+            }
+        }
+        state = .changed
+    }
+
+    override func touchesEnded(_ touches: Set<UITouch>, with event: UIEvent) {
+        forward(touches, phase: 2)
+        finish(touches, as: .ended)
+    }
+
+    override func touchesCancelled(_ touches: Set<UITouch>, with event: UIEvent) {
+        forward(touches, phase: 3)
+        finish(touches, as: .cancelled)
+    }
+
+    override func reset() {
+        super.reset()
+        // Touches still down were lost, e.g. when the view left the window mid-gesture
+        if !activeTouches.isEmpty {
+            coordinator?.cancelTouches()
+        }
+        activeTouches.removeAll()
+    }
+
+    private func forward(_ touches: Set<UITouch>, phase: UInt8) {
+        guard let view = view, let coordinator = coordinator else { return }
+        for touch in touches {
+            coordinator.handleTouch(phase: phase, location: touch.location(in: view), id: UInt64(touch.hash))
+        }
+    }
+
+    private func finish(_ touches: Set<UITouch>, as finalState: UIGestureRecognizer.State) {
+        activeTouches.subtract(touches)
+        if activeTouches.isEmpty {
+            state = finalState
+        }
+    }
+}
+
+/// SwiftUI view hosting a Bevy app
+///
+/// Updates the app from a CADisplayLink, forwards touches through a gesture recognizer,
+/// reports the safe area and follows the scene phase as the host lifecycle. Use it
+/// with `.ignoresSafeArea()` so Bevy renders edge to edge and gets the real insets.
+struct BevyView: View {
+    @Environment(\.scenePhase) private var scenePhase
+
+    /// Binding to control the view (send messages, etc.)
+    @Binding var controller: BevyViewController?
+
+    /// Callback for messages received from Bevy
+    var onMessageReceived: ((Data) -> Void)?
+
+    init(controller: Binding<BevyViewController?> = .constant(nil), onMessageReceived: ((Data) -> Void)? = nil) {
+        self._controller = controller
+        self.onMessageReceived = onMessageReceived
+    }
+
+    var body: some View {
+        BevyHostView(controller: $controller, onMessageReceived: onMessageReceived, scenePhase: scenePhase)
+    }
+}
+
+/// UIKit side of ``BevyView``
+private struct BevyHostView: UIViewRepresentable {
+    @Binding var controller: BevyViewController?
+    var onMessageReceived: ((Data) -> Void)?
+    var scenePhase: ScenePhase
+
+    func makeCoordinator() -> BevyMetalViewCoordinator {
+        let coord = BevyMetalViewCoordinator()
+        coord.onMessageReceived = onMessageReceived
+        DispatchQueue.main.async {
+            self.controller = BevyViewController(coordinator: coord)
+        }
+        return coord
+    }
+
+    func makeUIView(context: Context) -> BevyTouchView {
+        let coordinator = context.coordinator
+        let hostView = BevyTouchView()
+        hostView.coordinator = coordinator
+        hostView.forwardsTouches = false
+        hostView.isMultipleTouchEnabled = true
+        hostView.addGestureRecognizer(BevyTouchGestureRecognizer(coordinator: coordinator))
+
+        guard let device = MTLCreateSystemDefaultDevice() else {
+            fatalError("Metal is not supported on this device")
+        }
+
+        // The display link drives updates; the MTKView only tracks the drawable size
+        let metalView = MTKView(frame: .zero, device: device)
+        metalView.isPaused = true
+        metalView.enableSetNeedsDisplay = false
+        metalView.framebufferOnly = true
+        metalView.isMultipleTouchEnabled = true
+        metalView.clearColor = MTLClearColor(red: 0, green: 0, blue: 0, alpha: 1)
+
+        hostView.addSubview(metalView)
+        metalView.translatesAutoresizingMaskIntoConstraints = false
+        NSLayoutConstraint.activate([
+            metalView.topAnchor.constraint(equalTo: hostView.topAnchor),
+            metalView.bottomAnchor.constraint(equalTo: hostView.bottomAnchor),
+            metalView.leadingAnchor.constraint(equalTo: hostView.leadingAnchor),
+            metalView.trailingAnchor.constraint(equalTo: hostView.trailingAnchor)
+        ])
+
+        // Initialize Bevy once the view has been laid out
+        DispatchQueue.main.async {
+            let size = metalView.drawableSize
+            let scale = size.width / metalView.bounds.width
+            coordinator.setupBevy(metalView: metalView, size: size, scale: scale)
+            coordinator.handleSafeArea(hostView.safeAreaInsets)
+            metalView.delegate = coordinator
+            coordinator.startDisplayLink()
+        }
+
+        return hostView
+    }
+
+    func updateUIView(_ uiView: BevyTouchView, context: Context) {
+        context.coordinator.onMessageReceived = onMessageReceived
+        context.coordinator.handleScenePhase(scenePhase)
+    }
+
+    static func dismantleUIView(_ uiView: BevyTouchView, coordinator: BevyMetalViewCoordinator) {
+        // Releases the coordinator, which destroys the app
+        coordinator.stopDisplayLink()
+    }
+}
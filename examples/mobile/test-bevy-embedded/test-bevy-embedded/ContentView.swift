@@ -14,7 +14,7 @@ struct ContentView: View {
     var body: some View {
         ZStack {
             // Bevy render view
-            BevyMetalView(
+            BevyView(
                 controller: $bevyController,
                 onMessageReceived: { data in
                     handleBevyMessage(data)
@@ -64,6 +64,9 @@ struct ContentView: View {
     }
 }
 
+/// Id the example binds the cube color property to
+let cubeColorProperty: UInt16 = 0
+
 struct ColorButton: View {
     let title: String
     let color: Color?
@@ -93,14 +96,9 @@ struct ColorButton: View {
             rgba = (Float.random(in: 0...1), Float.random(in: 0...1), Float.random(in: 0...1), 1.0)
         }
 
-        // Pack Vec4 as 16 bytes (4 x f32 in little-endian)
-        var data = Data()
-        withUnsafeBytes(of: rgba.0.bitPattern.littleEndian) { data.append(contentsOf: $0) }
-        withUnsafeBytes(of: rgba.1.bitPattern.littleEndian) { data.append(contentsOf: $0) }
-        withUnsafeBytes(of: rgba.2.bitPattern.littleEndian) { data.append(contentsOf: $0) }
-        withUnsafeBytes(of: rgba.3.bitPattern.littleEndian) { data.append(contentsOf: $0) }
-
-        controller?.sendMessage(data)
+        // `cube.color` is declared by the Rust app; Bevy tweens to the new color
+        controller?.bindProperty("cube.color", id: cubeColorProperty)
+        controller?.setProperty(id: cubeColorProperty, [rgba.0, rgba.1, rgba.2, rgba.3], duration: 0.3, easing: 3)
     }
 }
 
@@ -0,0 +1,120 @@
+//! Drives the shared FFI entry points with arbitrary host input
+//!
+//! The input is decoded into a sequence of operations (touch, resize, send, receive,
+//! update) applied to a mock embedded app, the way a misbehaving host might call them.
+//!
+//! Run with `cargo fuzz run ffi_entry_points` from the repository root.
+
+#![no_main]
+
+use std::ffi::c_void;
+
+use bevy::prelude::*;
+use bevy_embedded::{ffi, test::MockHost};
+use libfuzzer_sys::fuzz_target;
+
+/// Minimal cursor over the fuzz input
+struct Input<'a>(&'a [u8]);
+
+impl Input<'_> {
+    fn u8(&mut self) -> Option<u8> {
+        let (first, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(*first)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.0.split_first_chunk::<N>()?;
+        self.0 = rest;
+        Some(*head)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.bytes().map(f32::from_le_bytes)
+    }
+
+    fn slice(&mut self, len: usize) -> &[u8] {
+        let len = len.min(self.0.len());
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        head
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut host = MockHost::from_setup(Default::default(), |app| {
+        app.add_plugins(MinimalPlugins);
+    });
+    let app_ptr = host.app_ptr();
+    let mut input = Input(data);
+
+    while let Some(op) = input.u8() {
+        // SAFETY: app_ptr is the handle of the live App owned by `host`
+        let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr) }) else {
+            return;
+        };
+
+        match op % 6 {
+            0 => {
+                let (Some(phase), Some(x), Some(y), Some(id)) =
+                    (input.u8(), input.f32(), input.f32(), input.u64())
+                else {
+                    return;
+                };
+                let _ = ffi::touch_event(&mut app, phase, x, y, id);
+            }
+            1 => {
+                let (Some(width), Some(height), Some(scale)) =
+                    (input.u32(), input.u32(), input.f32())
+                else {
+                    return;
+                };
+                let _ = ffi::resize(&mut app, width, height, scale);
+            }
+            2 => {
+                let len = input.u8().unwrap_or(0) as usize;
+                let message = input.slice(len);
+                // SAFETY: the slice is valid for `len` bytes
+                if let Ok(message) = unsafe { ffi::message_slice(message.as_ptr(), message.len()) }
+                {
+                    let _ = ffi::send_message(app_ptr, message.to_vec());
+                }
+            }
+            3 => {
+                let mut buffer = vec![0; input.u8().unwrap_or(0) as usize];
+                // SAFETY: the buffer is valid for its whole length
+                let _ = unsafe {
+                    ffi::receive_message_into(app_ptr, buffer.as_mut_ptr(), buffer.len())
+                };
+                let max_messages = input.u8().unwrap_or(0) as usize;
+                // SAFETY: the buffer is valid for its whole length
+                let _ = unsafe {
+                    ffi::receive_messages_into(
+                        app_ptr,
+                        buffer.as_mut_ptr(),
+                        buffer.len(),
+                        max_messages,
+                    )
+                };
+            }
+            4 => {
+                // Null and unknown handles must be rejected, never dereferenced
+                let bogus = (app_ptr as *mut u8).wrapping_add(1) as *mut c_void;
+                let _ = unsafe { ffi::app_mut(std::ptr::null_mut()) };
+                let _ = unsafe { ffi::message_slice(std::ptr::null(), 16) };
+                let _ = unsafe { ffi::app_mut(bogus) };
+                let _ = ffi::send_message(bogus, vec![0; 4]);
+                let _ = ffi::receive_message(std::ptr::null());
+            }
+            _ => app.update(),
+        }
+    }
+});
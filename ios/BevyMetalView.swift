@@ -14,6 +14,16 @@ struct EmbeddedSurfaceInfo {
     var scaleFactor: Float
 }
 
+// Scheduling signals of the last update, matching Rust's FrameInfo
+struct BevyFrameInfo {
+    var frame: UInt64 = 0
+    var cpuMs: Double = 0
+    var gpuMs: Double = -1
+    var pendingMessages: UInt32 = 0
+    var needsRedraw: UInt8 = 0
+    var idle: UInt8 = 0
+}
+
 // Global storage for the current surface being initialized
 private var currentSurface: EmbeddedSurfaceInfo?
 
@@ -31,25 +41,291 @@ func bevyEmbeddedGetSurface(_ out: UnsafeMutablePointer<EmbeddedSurfaceInfo>) {
 @_silgen_name("bevy_embedded_create_app")
 func bevyEmbeddedCreateApp() -> UnsafeMutableRawPointer?
 
+@_silgen_name("bevy_embedded_create_app_with_config")
+func bevyEmbeddedCreateAppWithConfig(_ config: OpaquePointer?) -> UnsafeMutableRawPointer?
+
 @_silgen_name("bevy_embedded_update")
 func bevyEmbeddedUpdate(_ app: UnsafeMutableRawPointer)
 
+@_silgen_name("bevy_embedded_update_with_info")
+func bevyEmbeddedUpdateWithInfo(_ app: UnsafeMutableRawPointer, _ info: UnsafeMutablePointer<BevyFrameInfo>?)
+
+@_silgen_name("bevy_embedded_run_schedule")
+func bevyEmbeddedRunSchedule(_ app: UnsafeMutableRawPointer, _ schedule: UInt8) -> UInt8
+
 @_silgen_name("bevy_embedded_destroy")
 func bevyEmbeddedDestroy(_ app: UnsafeMutableRawPointer)
 
+@_silgen_name("bevy_embedded_destroy_async")
+func bevyEmbeddedDestroyAsync(_ app: UnsafeMutableRawPointer, _ onDestroyed: (@convention(c) (UnsafeMutableRawPointer?) -> Void)?, _ userData: UnsafeMutableRawPointer?)
+
+@_silgen_name("bevy_embedded_ios_create_app_for_scene")
+func bevyEmbeddedIosCreateAppForScene(_ scene: UInt64, _ surface: UnsafePointer<EmbeddedSurfaceInfo>, _ config: OpaquePointer?) -> UnsafeMutableRawPointer?
+
+@_silgen_name("bevy_embedded_preload_step")
+func bevyEmbeddedPreloadStep() -> UInt8
+
+@_silgen_name("bevy_embedded_clear_preloaded")
+func bevyEmbeddedClearPreloaded()
+
+@_silgen_name("bevy_embedded_create_app_async")
+func bevyEmbeddedCreateAppAsync(_ config: OpaquePointer?, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float, _ onReady: (@convention(c) (UnsafeMutableRawPointer?, UnsafeRawPointer?) -> Void)?, _ userData: UnsafeMutableRawPointer?) -> UnsafeRawPointer?
+
+@_silgen_name("bevy_embedded_pending_app_status")
+func bevyEmbeddedPendingAppStatus(_ pending: UnsafeRawPointer) -> UInt8
+
+@_silgen_name("bevy_embedded_release_pending_app")
+func bevyEmbeddedReleasePendingApp(_ pending: UnsafeRawPointer)
+
 // Import FFI functions from bevy_embedded crate
+@_silgen_name("bevy_embedded_ios_new_config")
+func bevyEmbeddedIosNewConfig() -> OpaquePointer?
+
+@_silgen_name("bevy_embedded_ios_config_set")
+func bevyEmbeddedIosConfigSet(_ config: OpaquePointer, _ key: UnsafePointer<CChar>, _ value: UnsafePointer<CChar>)
+
+@_silgen_name("bevy_embedded_ios_free_config")
+func bevyEmbeddedIosFreeConfig(_ config: OpaquePointer?)
+
 @_silgen_name("bevy_embedded_ios_touch_event")
 func bevyEmbeddedIosTouchEvent(_ app: UnsafeMutableRawPointer, _ phase: UInt8, _ x: Float, _ y: Float, _ id: UInt64)
 
+@_silgen_name("bevy_embedded_ios_predicted_touch")
+func bevyEmbeddedIosPredictedTouch(_ app: UnsafeMutableRawPointer, _ x: Float, _ y: Float, _ id: UInt64)
+
+@_silgen_name("bevy_embedded_ios_cancel_touches")
+func bevyEmbeddedIosCancelTouches(_ app: UnsafeMutableRawPointer)
+
+@_silgen_name("bevy_embedded_ios_key_event")
+func bevyEmbeddedIosKeyEvent(_ app: UnsafeMutableRawPointer, _ hidUsage: UInt32, _ pressed: Bool, _ modifierFlags: UInt64, _ characters: UnsafePointer<CChar>?)
+
+@_silgen_name("bevy_embedded_ios_keyboard_focus")
+func bevyEmbeddedIosKeyboardFocus(_ app: UnsafeMutableRawPointer, _ focused: Bool)
+
+@_silgen_name("bevy_embedded_ios_rotary_event")
+func bevyEmbeddedIosRotaryEvent(_ app: UnsafeMutableRawPointer, _ delta: Float)
+
 @_silgen_name("bevy_embedded_ios_resize")
 func bevyEmbeddedIosResize(_ app: UnsafeMutableRawPointer, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float)
 
+@_silgen_name("bevy_embedded_ios_replace_surface")
+func bevyEmbeddedIosReplaceSurface(_ app: UnsafeMutableRawPointer, _ uiView: UnsafeRawPointer, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float, _ presentation: UInt8)
+
+@_silgen_name("bevy_embedded_ios_close_window")
+func bevyEmbeddedIosCloseWindow(_ app: UnsafeMutableRawPointer) -> Bool
+
+@_silgen_name("bevy_embedded_ios_window_closed")
+func bevyEmbeddedIosWindowClosed(_ app: UnsafeMutableRawPointer) -> Bool
+
+@_silgen_name("bevy_embedded_ios_open_window")
+func bevyEmbeddedIosOpenWindow(_ app: UnsafeMutableRawPointer, _ uiView: UnsafeRawPointer, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float) -> Bool
+
+@_silgen_name("bevy_embedded_ios_set_preload_target")
+func bevyEmbeddedIosSetPreloadTarget(_ count: UInt32, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float, _ config: OpaquePointer?)
+
+@_silgen_name("bevy_embedded_ios_take_preloaded_app")
+func bevyEmbeddedIosTakePreloadedApp(_ uiView: UnsafeRawPointer, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float) -> UnsafeMutableRawPointer?
+
+@_silgen_name("bevy_embedded_ios_take_async_app")
+func bevyEmbeddedIosTakeAsyncApp(_ pending: UnsafeRawPointer, _ uiView: UnsafeRawPointer, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float) -> UnsafeMutableRawPointer?
+
+@_silgen_name("bevy_embedded_ios_init_progress")
+func bevyEmbeddedIosInitProgress(_ app: UnsafeMutableRawPointer) -> UInt8
+
+@_silgen_name("bevy_embedded_ios_frame_damage")
+func bevyEmbeddedIosFrameDamage(_ app: UnsafeMutableRawPointer, _ rects: UnsafeMutablePointer<UInt32>?, _ capacity: Int) -> Int32
+
+@_silgen_name("bevy_embedded_ios_gpu_frame_time_ms")
+func bevyEmbeddedIosGpuFrameTimeMs(_ app: UnsafeMutableRawPointer) -> Double
+
+@_silgen_name("bevy_embedded_ios_startup_phase")
+func bevyEmbeddedIosStartupPhase(_ app: UnsafeMutableRawPointer, _ phase: UInt8, _ out: UnsafeMutablePointer<Double>) -> Bool
+
+@_silgen_name("bevy_embedded_ios_gpu_report")
+func bevyEmbeddedIosGpuReport(_ app: UnsafeMutableRawPointer) -> UnsafeMutablePointer<CChar>?
+
+@_silgen_name("bevy_embedded_ios_frozen_error")
+func bevyEmbeddedIosFrozenError(_ app: UnsafeMutableRawPointer) -> UnsafeMutablePointer<CChar>?
+
+@_silgen_name("bevy_embedded_ios_set_fallback_frame")
+func bevyEmbeddedIosSetFallbackFrame(_ app: UnsafeMutableRawPointer, _ pixels: UnsafePointer<UInt8>?, _ len: Int, _ width: UInt32, _ height: UInt32) -> Bool
+
+@_silgen_name("bevy_embedded_ios_flush_render_resources")
+func bevyEmbeddedIosFlushRenderResources(_ app: UnsafeMutableRawPointer) -> Bool
+
+@_silgen_name("bevy_embedded_ios_receive_telemetry")
+func bevyEmbeddedIosReceiveTelemetry(_ buffer: UnsafeMutablePointer<UInt8>, _ bufferLen: Int) -> Int
+
+@_silgen_name("bevy_embedded_ios_send_handshake")
+func bevyEmbeddedIosSendHandshake(_ app: UnsafeMutableRawPointer, _ version: UInt16, _ schemaHash: UInt64, _ codecs: UnsafePointer<CChar>?)
+
+@_silgen_name("bevy_embedded_ios_handshake_status")
+func bevyEmbeddedIosHandshakeStatus(_ app: UnsafeMutableRawPointer) -> UInt8
+
+@_silgen_name("bevy_embedded_ios_handshake_error")
+func bevyEmbeddedIosHandshakeError(_ app: UnsafeMutableRawPointer) -> UnsafeMutablePointer<CChar>?
+
+@_silgen_name("bevy_embedded_ios_submit_job")
+func bevyEmbeddedIosSubmitJob(_ app: UnsafeMutableRawPointer, _ kind: UnsafePointer<CChar>, _ data: UnsafePointer<UInt8>?, _ len: Int) -> UInt64
+
+@_silgen_name("bevy_embedded_ios_cancel_job")
+func bevyEmbeddedIosCancelJob(_ app: UnsafeMutableRawPointer, _ token: UInt64)
+
+@_silgen_name("bevy_embedded_ios_start_recording")
+func bevyEmbeddedIosStartRecording(_ app: UnsafeMutableRawPointer)
+
+@_silgen_name("bevy_embedded_ios_recording_len")
+func bevyEmbeddedIosRecordingLen(_ app: UnsafeMutableRawPointer) -> Int
+
+@_silgen_name("bevy_embedded_ios_stop_recording")
+func bevyEmbeddedIosStopRecording(_ app: UnsafeMutableRawPointer, _ out: UnsafeMutablePointer<UInt8>, _ outLen: Int) -> Int
+
+@_silgen_name("bevy_embedded_ios_replay")
+func bevyEmbeddedIosReplay(_ app: UnsafeMutableRawPointer, _ data: UnsafePointer<UInt8>, _ len: Int) -> Int32
+
+@_silgen_name("bevy_embedded_ios_hibernation_len")
+func bevyEmbeddedIosHibernationLen(_ app: UnsafeMutableRawPointer) -> Int
+
+@_silgen_name("bevy_embedded_ios_hibernate")
+func bevyEmbeddedIosHibernate(_ app: UnsafeMutableRawPointer, _ out: UnsafeMutablePointer<UInt8>, _ outLen: Int) -> Int
+
+@_silgen_name("bevy_embedded_ios_rehydrate")
+func bevyEmbeddedIosRehydrate(_ app: UnsafeMutableRawPointer, _ data: UnsafePointer<UInt8>, _ len: Int) -> Bool
+
+@_silgen_name("bevy_embedded_ios_set_host_device")
+func bevyEmbeddedIosSetHostDevice(_ device: UnsafeMutableRawPointer, _ queue: UnsafeMutableRawPointer) -> Bool
+
+@_silgen_name("bevy_embedded_ios_set_host_frame")
+func bevyEmbeddedIosSetHostFrame(_ app: UnsafeMutableRawPointer, _ texture: UnsafeMutableRawPointer, _ x: UInt32, _ y: UInt32, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float)
+
+@_silgen_name("bevy_embedded_ios_encode_host_frame")
+func bevyEmbeddedIosEncodeHostFrame(_ app: UnsafeMutableRawPointer, _ commandBuffer: UnsafeMutableRawPointer)
+
+@_silgen_name("bevy_embedded_ios_set_offscreen_target")
+func bevyEmbeddedIosSetOffscreenTarget(_ app: UnsafeMutableRawPointer, _ width: UInt32, _ height: UInt32, _ format: UInt8, _ srgb: Bool, _ mipLevels: UInt32, _ scaleFactor: Float) -> UnsafeMutableRawPointer?
+
+@_silgen_name("bevy_embedded_ios_frame_fence_event")
+func bevyEmbeddedIosFrameFenceEvent(_ app: UnsafeMutableRawPointer) -> UnsafeMutableRawPointer?
+
+@_silgen_name("bevy_embedded_ios_frame_fence_value")
+func bevyEmbeddedIosFrameFenceValue(_ app: UnsafeMutableRawPointer) -> UInt64
+
+@_silgen_name("bevy_embedded_ios_wait_frame_fence")
+func bevyEmbeddedIosWaitFrameFence(_ app: UnsafeMutableRawPointer, _ value: UInt64, _ timeoutMs: UInt32) -> Bool
+
+@_silgen_name("bevy_embedded_ios_attach_external_display")
+func bevyEmbeddedIosAttachExternalDisplay(_ app: UnsafeMutableRawPointer, _ uiView: UnsafeRawPointer, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float) -> UInt32
+
+@_silgen_name("bevy_embedded_ios_resize_external_display")
+func bevyEmbeddedIosResizeExternalDisplay(_ app: UnsafeMutableRawPointer, _ displayId: UInt32, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float)
+
+@_silgen_name("bevy_embedded_ios_detach_external_display")
+func bevyEmbeddedIosDetachExternalDisplay(_ app: UnsafeMutableRawPointer, _ displayId: UInt32)
+
+@_silgen_name("bevy_embedded_ios_set_lifecycle")
+func bevyEmbeddedIosSetLifecycle(_ app: UnsafeMutableRawPointer, _ state: UInt8)
+
+@_silgen_name("bevy_embedded_ios_app_for_scene")
+func bevyEmbeddedIosAppForScene(_ scene: UInt64) -> UnsafeMutableRawPointer?
+
+@_silgen_name("bevy_embedded_ios_scene_disconnected")
+func bevyEmbeddedIosSceneDisconnected(_ app: UnsafeMutableRawPointer)
+
+@_silgen_name("bevy_embedded_ios_scene_connected")
+func bevyEmbeddedIosSceneConnected(_ app: UnsafeMutableRawPointer, _ uiView: UnsafeRawPointer, _ width: UInt32, _ height: UInt32, _ scaleFactor: Float)
+
+@_silgen_name("bevy_embedded_ios_set_accessibility")
+func bevyEmbeddedIosSetAccessibility(_ app: UnsafeMutableRawPointer, _ reduceMotion: Bool, _ boldText: Bool, _ fontScale: Float)
+
+@_silgen_name("bevy_embedded_ios_set_safe_area")
+func bevyEmbeddedIosSetSafeArea(_ app: UnsafeMutableRawPointer, _ top: Float, _ left: Float, _ bottom: Float, _ right: Float)
+
+@_silgen_name("bevy_embedded_ios_sync_clock")
+func bevyEmbeddedIosSyncClock(_ app: UnsafeMutableRawPointer, _ hostTime: Double)
+
+@_silgen_name("bevy_embedded_ios_set_latency_measurement")
+func bevyEmbeddedIosSetLatencyMeasurement(_ app: UnsafeMutableRawPointer, _ enabled: Bool)
+
+@_silgen_name("bevy_embedded_ios_input_timestamp")
+func bevyEmbeddedIosInputTimestamp(_ app: UnsafeMutableRawPointer, _ hostTime: Double)
+
+@_silgen_name("bevy_embedded_ios_frame_presented")
+func bevyEmbeddedIosFramePresented(_ app: UnsafeMutableRawPointer, _ hostTime: Double)
+
+@_silgen_name("bevy_embedded_ios_set_surface_transform")
+func bevyEmbeddedIosSetSurfaceTransform(_ app: UnsafeMutableRawPointer, _ rotation: UInt8, _ mirror: Bool)
+
+@_silgen_name("bevy_embedded_ios_set_camera_view")
+func bevyEmbeddedIosSetCameraView(_ app: UnsafeMutableRawPointer, _ view: UnsafePointer<Float>)
+
+@_silgen_name("bevy_embedded_ios_set_dynamic_resolution")
+func bevyEmbeddedIosSetDynamicResolution(_ app: UnsafeMutableRawPointer, _ enabled: Bool, _ minScale: Float, _ maxScale: Float, _ targetFps: Float)
+
+@_silgen_name("bevy_embedded_ios_shared_state")
+func bevyEmbeddedIosSharedState(_ app: UnsafeMutableRawPointer) -> UnsafeMutablePointer<Float>?
+
+@_silgen_name("bevy_embedded_ios_ring_send")
+func bevyEmbeddedIosRingSend(_ app: UnsafeMutableRawPointer, _ topic: UInt16, _ data: UnsafePointer<UInt8>, _ len: Int) -> Bool
+
+@_silgen_name("bevy_embedded_ios_ring_receive")
+func bevyEmbeddedIosRingReceive(_ app: UnsafeMutableRawPointer, _ topic: UInt16, _ buffer: UnsafeMutablePointer<UInt8>, _ bufferLen: Int) -> Int
+
+@_silgen_name("bevy_embedded_ios_push_audio_input")
+func bevyEmbeddedIosPushAudioInput(_ app: UnsafeMutableRawPointer, _ samples: UnsafePointer<Float>, _ frames: Int, _ channels: UInt16, _ sampleRate: UInt32, _ timestamp: Double) -> Bool
+
+@_silgen_name("bevy_embedded_ios_render_audio")
+func bevyEmbeddedIosRenderAudio(_ app: UnsafeMutableRawPointer, _ out: UnsafeMutablePointer<Float>, _ frames: Int, _ channels: UInt16, _ sampleRate: UInt32) -> Bool
+
 @_silgen_name("bevy_embedded_ios_send_message")
 func bevyEmbeddedIosSendMessage(_ app: UnsafeMutableRawPointer, _ data: UnsafePointer<UInt8>, _ length: Int)
 
 @_silgen_name("bevy_embedded_ios_receive_message")
 func bevyEmbeddedIosReceiveMessage(_ app: UnsafeMutableRawPointer, _ buffer: UnsafeMutablePointer<UInt8>, _ bufferLen: Int) -> Int
 
+@_silgen_name("bevy_embedded_ios_receive_messages")
+func bevyEmbeddedIosReceiveMessages(_ app: UnsafeMutableRawPointer, _ buffer: UnsafeMutablePointer<UInt8>, _ bufferLen: Int, _ maxMessages: Int) -> Int
+
+@_silgen_name("bevy_embedded_ios_seal_message")
+func bevyEmbeddedIosSealMessage(_ key: UnsafePointer<UInt8>, _ keyLen: Int, _ data: UnsafePointer<UInt8>, _ len: Int, _ out: UnsafeMutablePointer<UInt8>, _ outLen: Int) -> Int
+
+@_silgen_name("bevy_embedded_ios_open_message")
+func bevyEmbeddedIosOpenMessage(_ key: UnsafePointer<UInt8>, _ keyLen: Int, _ sealed: UnsafePointer<UInt8>, _ len: Int, _ out: UnsafeMutablePointer<UInt8>, _ outLen: Int) -> Int
+
+/// Errors of the async channel helpers
+enum BevyChannelError: Error {
+    /// The view and its app went away before a response arrived
+    case closed
+    /// A job failed or had no handler in the app
+    case jobFailed(String)
+}
+
+/// Topic of job results, `ReservedTopic::JobResult` in the Rust crate
+let bevyJobResultTopic: UInt16 = 24
+
+/// Topic of property binds and sets, `ReservedTopic::Properties` in the Rust crate
+let bevyPropertiesTopic: UInt16 = 35
+
+/// Topic of keyboard requests for text fields, `ReservedTopic::TextInputRequest` in the Rust crate
+let bevyTextInputRequestTopic: UInt16 = 40
+
+/// Topic of text field edits, `ReservedTopic::TextInput` in the Rust crate
+let bevyTextInputTopic: UInt16 = 41
+
+/// Topic of screen reader announcements, `ReservedTopic::Announce` in the Rust crate
+let bevyAnnounceTopic: UInt16 = 42
+
+/// Payload of a reserved message on `topic`, or nil for other messages
+func bevyReservedPayload(_ message: Data, topic: UInt16) -> Data? {
+    let header: [UInt8] = [0xFF, 0x42, 0x45, 0x4D, UInt8(topic & 0xFF), UInt8(topic >> 8)]
+    guard message.starts(with: header) else { return nil }
+    return Data(message.dropFirst(header.count))
+}
+
+/// A reserved message on `topic` carrying `payload`
+func bevyReservedMessage(topic: UInt16, _ payload: Data) -> Data {
+    Data([0xFF, 0x42, 0x45, 0x4D, UInt8(topic & 0xFF), UInt8(topic >> 8)]) + payload
+}
+
 /// Public API for controlling a Bevy view
 ///
 /// This provides a clean, Swift-friendly interface for interacting with Bevy
@@ -76,12 +352,254 @@ class BevyViewController {
     func sendBytes(_ bytes: [UInt8]) {
         sendMessage(Data(bytes))
     }
+
+    /// Messages from Bevy, delivered after each frame
+    ///
+    /// Every call returns a new stream, so several tasks can iterate at once. Streams
+    /// finish when the view goes away.
+    func messages() -> AsyncStream<Data> {
+        guard let coordinator else { return AsyncStream { $0.finish() } }
+        return coordinator.messageStream()
+    }
+
+    /// Payloads of the reserved messages on `topic`
+    func reservedMessages(topic: UInt16) -> AsyncStream<Data> {
+        let messages = messages()
+        return AsyncStream { continuation in
+            let task = Task {
+                for await message in messages {
+                    if let payload = bevyReservedPayload(message, topic: topic) {
+                        continuation.yield(payload)
+                    }
+                }
+                continuation.finish()
+            }
+            continuation.onTermination = { _ in task.cancel() }
+        }
+    }
+
+    /// Bind a property the app declared with `add_host_property` to `id`
+    ///
+    /// Bevy answers on topic 36 with the property's kind. Binding again is harmless.
+    func bindProperty(_ name: String, id: UInt16) {
+        var payload = Data([0])
+        let utf8 = Data(name.utf8)
+        withUnsafeBytes(of: id.littleEndian) { payload.append(contentsOf: $0) }
+        withUnsafeBytes(of: UInt32(utf8.count).littleEndian) { payload.append(contentsOf: $0) }
+        payload.append(utf8)
+        sendMessage(bevyReservedMessage(topic: bevyPropertiesTopic, payload))
+    }
+
+    /// Set a bound property, tweening over `duration` seconds if it is positive
+    ///
+    /// `easing` is 0 linear, 1 ease-in, 2 ease-out, 3 ease-in-out, 4 back-out,
+    /// 5 elastic-out or 6 bounce-out.
+    func setProperty(id: UInt16, _ values: [Float], duration: Float = 0, easing: UInt8 = 0) {
+        var payload = Data([1])
+        withUnsafeBytes(of: id.littleEndian) { payload.append(contentsOf: $0) }
+        withUnsafeBytes(of: duration.bitPattern.littleEndian) { payload.append(contentsOf: $0) }
+        payload.append(easing)
+        for value in values {
+            withUnsafeBytes(of: value.bitPattern.littleEndian) { payload.append(contentsOf: $0) }
+        }
+        sendMessage(bevyReservedMessage(topic: bevyPropertiesTopic, payload))
+    }
+
+    /// Send a message and wait for the first message from Bevy that `isResponse` accepts
+    func request(_ data: Data, where isResponse: @escaping (Data) -> Bool) async throws -> Data {
+        // Subscribe before sending, so a response in the next frame isn't missed
+        let responses = messages()
+        sendMessage(data)
+        for await message in responses where isResponse(message) {
+            return message
+        }
+        try Task.checkCancellation()
+        throw BevyChannelError.closed
+    }
+
+    /// Run a job the app registered with `add_host_job` and wait for its output
+    ///
+    /// Cancelling the calling task cancels the job.
+    func runJob(kind: String, input: Data) async throws -> Data {
+        guard let app = coordinator?.bevyApp else { throw BevyChannelError.closed }
+        let results = reservedMessages(topic: bevyJobResultTopic)
+        let token = kind.withCString { kind in
+            input.withUnsafeBytes { bytes in
+                bevyEmbeddedIosSubmitJob(app, kind, bytes.baseAddress?.assumingMemoryBound(to: UInt8.self), input.count)
+            }
+        }
+        guard token != 0 else { throw BevyChannelError.closed }
+
+        return try await withTaskCancellationHandler {
+            // token: u64 | status: u8 | output or error
+            for await result in results where result.count >= 9 {
+                let resultToken = result.prefix(8).reversed().reduce(UInt64(0)) { $0 << 8 | UInt64($1) }
+                guard resultToken == token else { continue }
+                let output = Data(result.dropFirst(9))
+                switch result[8] {
+                case 0: return output
+                case 1: throw BevyChannelError.jobFailed(String(decoding: output, as: UTF8.self))
+                default: throw CancellationError()
+                }
+            }
+            try Task.checkCancellation()
+            throw BevyChannelError.closed
+        } onCancel: {
+            bevyEmbeddedIosCancelJob(app, token)
+        }
+    }
+}
+
+/// An invisible native text field that edits Bevy's focused `HostTextField`
+///
+/// The keyboard, IME composition, autocorrect and dictation all work on it, and every
+/// change is sent to Bevy.
+class BevyTextInput: NSObject, UITextFieldDelegate {
+    private let textField = UITextField(frame: .zero)
+    private let sendMessage: (Data) -> Void
+    /// Entity bits of the focused field, or nil while none is focused
+    private var field: UInt64?
+
+    init(in view: UIView, sendMessage: @escaping (Data) -> Void) {
+        self.sendMessage = sendMessage
+        super.init()
+        textField.alpha = 0
+        textField.delegate = self
+        textField.addTarget(self, action: #selector(editingChanged), for: .editingChanged)
+        view.addSubview(textField)
+    }
+
+    /// Present or dismiss the keyboard as Bevy asks
+    func handleRequest(_ payload: Data) {
+        let bytes = [UInt8](payload)
+        func u32(_ at: Int) -> Int { Int(bytes[at..<at + 4].reversed().reduce(UInt32(0)) { $0 << 8 | UInt32($1) }) }
+        guard bytes.count >= 9 else { return }
+        let id = bytes[1..<9].reversed().reduce(UInt64(0)) { $0 << 8 | UInt64($1) }
+        guard bytes[0] == 0 else {
+            if field == id {
+                field = nil
+                textField.resignFirstResponder()
+            }
+            return
+        }
+
+        // field: u64 | kind: u8 | multiline: u8 | text: string | selection: 2 × u32
+        guard bytes.count >= 15 else { return }
+        let length = u32(11)
+        guard bytes.count >= 23 + length else { return }
+        let text = String(decoding: bytes[15..<15 + length], as: UTF8.self)
+        let kind = bytes[9]
+        switch kind {
+        case 1: textField.keyboardType = .numberPad
+        case 2: textField.keyboardType = .decimalPad
+        case 3: textField.keyboardType = .emailAddress
+        case 4: textField.keyboardType = .URL
+        case 6: textField.keyboardType = .phonePad
+        default: textField.keyboardType = .default
+        }
+        textField.isSecureTextEntry = kind == 5
+        if textField.text != text {
+            textField.text = text
+        }
+        if let start = position(utf8Offset: u32(15 + length)), let end = position(utf8Offset: u32(19 + length)) {
+            textField.selectedTextRange = textField.textRange(from: start, to: end)
+        }
+
+        field = id
+        if textField.isFirstResponder {
+            textField.reloadInputViews()
+        } else {
+            textField.becomeFirstResponder()
+        }
+    }
+
+    /// Position at a byte offset into the field's UTF-8 text
+    private func position(utf8Offset offset: Int) -> UITextPosition? {
+        let text = textField.text ?? ""
+        let utf8 = text.utf8
+        let index = utf8.index(utf8.startIndex, offsetBy: min(offset, utf8.count))
+        return textField.position(from: textField.beginningOfDocument, offset: index.utf16Offset(in: text))
+    }
+
+    /// Byte offsets into the field's UTF-8 text of a range of it
+    private func utf8Offsets(_ range: UITextRange?) -> (UInt32, UInt32) {
+        guard let range else { return (0, 0) }
+        let text = textField.text ?? ""
+        func offset(_ position: UITextPosition) -> UInt32 {
+            let utf16 = textField.offset(from: textField.beginningOfDocument, to: position)
+            let index = String.Index(utf16Offset: utf16, in: text)
+            return UInt32(text.utf8.distance(from: text.startIndex, to: index))
+        }
+        return (offset(range.start), offset(range.end))
+    }
+
+    @objc private func editingChanged() {
+        guard let field else { return }
+        let utf8 = Data((textField.text ?? "").utf8)
+        let selection = utf8Offsets(textField.selectedTextRange)
+        let composing = utf8Offsets(textField.markedTextRange)
+        var payload = Data([0])
+        withUnsafeBytes(of: field.littleEndian) { payload.append(contentsOf: $0) }
+        withUnsafeBytes(of: UInt32(utf8.count).littleEndian) { payload.append(contentsOf: $0) }
+        payload.append(utf8)
+        for offset in [selection.0, selection.1, composing.0, composing.1] {
+            withUnsafeBytes(of: offset.littleEndian) { payload.append(contentsOf: $0) }
+        }
+        sendMessage(bevyReservedMessage(topic: bevyTextInputTopic, payload))
+    }
+
+    func textFieldShouldReturn(_ textField: UITextField) -> Bool {
+        guard let field else { return false }
+        var payload = Data([1])
+        withUnsafeBytes(of: field.littleEndian) { payload.append(contentsOf: $0) }
+        sendMessage(bevyReservedMessage(topic: bevyTextInputTopic, payload))
+        return false
+    }
+
+    func textFieldDidEndEditing(_ textField: UITextField) {
+        // Editing that ends without Bevy asking means the user dismissed the keyboard
+        guard let field else { return }
+        self.field = nil
+        var payload = Data([2])
+        withUnsafeBytes(of: field.littleEndian) { payload.append(contentsOf: $0) }
+        sendMessage(bevyReservedMessage(topic: bevyTextInputTopic, payload))
+    }
 }
 
 /// A MetalKit view that hosts the Bevy engine
 class BevyMetalViewCoordinator: NSObject, MTKViewDelegate {
     var bevyApp: UnsafeMutableRawPointer?
     var onMessageReceived: ((Data) -> Void)?
+    private var messageContinuations: [UUID: AsyncStream<Data>.Continuation] = [:]
+    private let messageLock = NSLock()
+    /// Receives batches of length-prefixed messages, grown for messages that don't fit
+    private var messageBuffer = [UInt8](repeating: 0, count: 64 * 1024)
+    private var displayLink: CADisplayLink?
+    private var lifecycleState: UInt8 = 0
+    private var safeAreaInsets = UIEdgeInsets.zero
+    private var textInput: BevyTextInput?
+    /// Signals of the last update, e.g. `idle` to lower the frame rate
+    private(set) var frameInfo = BevyFrameInfo()
+    /// The window scene whose app this view shows, if it was created for one
+    private var sceneId: UInt64?
+    /// Number of views showing each scene's app; the last one destroys it
+    private static var sceneViews: [UInt64: Int] = [:]
+
+    /// A new stream of the messages received after each frame
+    func messageStream() -> AsyncStream<Data> {
+        AsyncStream { continuation in
+            let id = UUID()
+            messageLock.lock()
+            messageContinuations[id] = continuation
+            messageLock.unlock()
+            continuation.onTermination = { [weak self] _ in
+                guard let self else { return }
+                self.messageLock.lock()
+                self.messageContinuations[id] = nil
+                self.messageLock.unlock()
+            }
+        }
+    }
 
     func mtkView(_ view: MTKView, drawableSizeWillChange size: CGSize) {
         guard let app = bevyApp else { return }
@@ -90,53 +608,218 @@ class BevyMetalViewCoordinator: NSObject, MTKViewDelegate {
     }
 
     func draw(in view: MTKView) {
+        step()
+    }
+
+    /// Update Bevy by one frame and deliver its messages
+    func step() {
         guard let app = bevyApp else { return }
-        bevyEmbeddedUpdate(app)
+        bevyEmbeddedUpdateWithInfo(app, &frameInfo)
 
-        // Poll for messages from Bevy
-        pollBevyMessages()
+        // Poll for messages from Bevy, if it sent any
+        if frameInfo.pendingMessages > 0 {
+            pollBevyMessages()
+        }
     }
 
     func pollBevyMessages() {
-        guard let app = bevyApp, let callback = onMessageReceived else { return }
+        guard let app = bevyApp else { return }
+
+        // Each call drains a batch of `len: u32 | bytes` messages
+        while true {
+            let bytesRead = bevyEmbeddedIosReceiveMessages(app, &messageBuffer, messageBuffer.count, 64)
+            if bytesRead == 0 { break }
+            if bytesRead > messageBuffer.count {
+                messageBuffer = [UInt8](repeating: 0, count: bytesRead)
+                continue
+            }
 
-        var buffer = [UInt8](repeating: 0, count: 1024)
-        let bytesRead = bevyEmbeddedIosReceiveMessage(app, &buffer, buffer.count)
+            var offset = 0
+            while offset + 4 <= bytesRead {
+                let length = Int(messageBuffer[offset..<offset + 4].reversed().reduce(UInt32(0)) { $0 << 8 | UInt32($1) })
+                handleBevyMessage(Data(messageBuffer[offset + 4..<offset + 4 + length]))
+                offset += 4 + length
+            }
+        }
+    }
 
-        if bytesRead > 0 {
-            let data = Data(buffer.prefix(bytesRead))
+    private func handleBevyMessage(_ data: Data) {
+        if let payload = bevyReservedPayload(data, topic: bevyTextInputRequestTopic) {
+            textInput?.handleRequest(payload)
+        }
+        if let payload = bevyReservedPayload(data, topic: bevyAnnounceTopic) {
+            postAnnouncement(payload)
+        }
+        messageLock.lock()
+        let continuations = Array(messageContinuations.values)
+        messageLock.unlock()
+        for continuation in continuations {
+            continuation.yield(data)
+        }
+        if let callback = onMessageReceived {
             DispatchQueue.main.async {
                 callback(data)
             }
         }
     }
 
+    /// Speak a `priority: u8 | text: string` announcement with VoiceOver
+    private func postAnnouncement(_ payload: Data) {
+        guard UIAccessibility.isVoiceOverRunning, payload.count >= 5 else { return }
+        let bytes = [UInt8](payload)
+        let length = Int(bytes[1..<5].reversed().reduce(UInt32(0)) { $0 << 8 | UInt32($1) })
+        guard bytes.count >= 5 + length else { return }
+        let text = String(decoding: bytes[5..<5 + length], as: UTF8.self)
+        if #available(iOS 17.0, *) {
+            var announcement = AttributedString(text)
+            announcement.accessibilitySpeechAnnouncementPriority = bytes[0] == 1 ? .high : .default
+            UIAccessibility.post(notification: .announcement, argument: NSAttributedString(announcement))
+        } else {
+            UIAccessibility.post(notification: .announcement, argument: text)
+        }
+    }
+
     func setupBevy(metalView: MTKView, size: CGSize, scale: CGFloat) {
         print("Setting up Bevy with size: \(size), scale: \(scale)")
 
-        // Set the current surface for the callback
         let viewPtr = Unmanaged.passUnretained(metalView).toOpaque()
-        currentSurface = EmbeddedSurfaceInfo(
+        var surface = EmbeddedSurfaceInfo(
             uiView: viewPtr,
             width: UInt32(size.width),
             height: UInt32(size.height),
             scaleFactor: Float(scale)
         )
 
-        // Create the app - this will call bevy_embedded_get_surface() during plugin finish()
-        bevyApp = bevyEmbeddedCreateApp()
+        if let scene = metalView.window?.windowScene {
+            // One app per window, so iPad windows don't share the global surface
+            let sceneId = UInt64(UInt(bitPattern: scene.session.persistentIdentifier.hashValue))
+            if let app = bevyEmbeddedIosAppForScene(sceneId) {
+                // The scene already has an app, e.g. SwiftUI recreated the view: move it here
+                bevyEmbeddedIosSceneConnected(app, viewPtr, surface.width, surface.height, surface.scaleFactor)
+                bevyApp = app
+            } else {
+                bevyApp = bevyEmbeddedIosCreateAppForScene(sceneId, &surface, nil)
+            }
+            if bevyApp != nil {
+                self.sceneId = sceneId
+                Self.sceneViews[sceneId, default: 0] += 1
+            }
+            NotificationCenter.default.addObserver(self, selector: #selector(sceneDidDisconnect), name: UIScene.didDisconnectNotification, object: scene)
+        } else {
+            // Set the current surface for the callback
+            currentSurface = surface
 
-        // Clear the surface info
-        currentSurface = nil
+            // Create the app - this will call bevy_embedded_get_surface() during plugin finish()
+            bevyApp = bevyEmbeddedCreateApp()
+
+            // Clear the surface info
+            currentSurface = nil
+        }
+
+        textInput = BevyTextInput(in: metalView) { [weak self] data in
+            self?.sendMessage(data)
+        }
+        sendAccessibilitySettings()
+        if let app = bevyApp, lifecycleState != 0 {
+            bevyEmbeddedIosSetLifecycle(app, lifecycleState)
+        }
+        handleSafeArea(safeAreaInsets)
+        for name in [
+            UIAccessibility.reduceMotionStatusDidChangeNotification,
+            UIAccessibility.boldTextStatusDidChangeNotification,
+            UIContentSizeCategory.didChangeNotification,
+        ] {
+            NotificationCenter.default.addObserver(self, selector: #selector(sendAccessibilitySettings), name: name, object: nil)
+        }
+        NotificationCenter.default.addObserver(self, selector: #selector(handleMemoryWarning), name: UIApplication.didReceiveMemoryWarningNotification, object: nil)
 
         print("Bevy app initialized: \(bevyApp != nil)")
     }
 
+    @objc func sceneDidDisconnect(_ notification: Notification) {
+        guard let app = bevyApp else { return }
+        bevyEmbeddedIosSceneDisconnected(app)
+    }
+
     func handleTouch(phase: UInt8, location: CGPoint, id: UInt64) {
         guard let app = bevyApp else { return }
         bevyEmbeddedIosTouchEvent(app, phase, Float(location.x), Float(location.y), id)
     }
 
+    @objc func sendAccessibilitySettings() {
+        guard let app = bevyApp else { return }
+        let fontScale = UIFontMetrics.default.scaledValue(for: 17) / 17
+        bevyEmbeddedIosSetAccessibility(app, UIAccessibility.isReduceMotionEnabled, UIAccessibility.isBoldTextEnabled, Float(fontScale))
+    }
+
+    /// Release the GPU memory of dropped resources when the system runs low
+    @objc func handleMemoryWarning() {
+        guard let app = bevyApp else { return }
+        _ = bevyEmbeddedIosFlushRenderResources(app)
+    }
+
+    func handlePredictedTouch(location: CGPoint, id: UInt64) {
+        guard let app = bevyApp else { return }
+        bevyEmbeddedIosPredictedTouch(app, Float(location.x), Float(location.y), id)
+    }
+
+    /// Cancel every touch Bevy has down, when the view lost them without ending each one
+    func cancelTouches() {
+        guard let app = bevyApp else { return }
+        bevyEmbeddedIosCancelTouches(app)
+    }
+
+    func handleKey(_ key: UIKey, pressed: Bool) {
+        guard let app = bevyApp else { return }
+        key.characters.withCString { characters in
+            bevyEmbeddedIosKeyEvent(app, UInt32(key.keyCode.rawValue), pressed, UInt64(key.modifierFlags.rawValue), characters)
+        }
+    }
+
+    /// Report a scene phase as the host lifecycle; kept for the app if it doesn't exist yet
+    func handleScenePhase(_ phase: ScenePhase) {
+        let state: UInt8
+        switch phase {
+        case .active: state = 0
+        case .background: state = 2
+        default: state = 1
+        }
+        guard state != lifecycleState else { return }
+        lifecycleState = state
+        guard let app = bevyApp else { return }
+        bevyEmbeddedIosSetLifecycle(app, state)
+    }
+
+    /// Report the view's safe area; kept for the app if it doesn't exist yet
+    func handleSafeArea(_ insets: UIEdgeInsets) {
+        safeAreaInsets = insets
+        guard let app = bevyApp else { return }
+        bevyEmbeddedIosSetSafeArea(app, Float(insets.top), Float(insets.left), Float(insets.bottom), Float(insets.right))
+    }
+
+    /// Drive updates from a CADisplayLink instead of the MTKView's own loop
+    func startDisplayLink() {
+        guard displayLink == nil else { return }
+        let link = CADisplayLink(target: self, selector: #selector(displayLinkFired))
+        link.add(to: .main, forMode: .common)
+        displayLink = link
+    }
+
+    /// Stop the display link; it retains the coordinator until then
+    func stopDisplayLink() {
+        displayLink?.invalidate()
+        displayLink = nil
+    }
+
+    @objc private func displayLinkFired(_ link: CADisplayLink) {
+        step()
+    }
+
+    func handleKeyboardFocus(_ focused: Bool) {
+        guard let app = bevyApp else { return }
+        bevyEmbeddedIosKeyboardFocus(app, focused)
+    }
+
     func sendMessage(_ data: Data) {
         guard let app = bevyApp else { return }
         data.withUnsafeBytes { ptr in
@@ -147,10 +830,22 @@ class BevyMetalViewCoordinator: NSObject, MTKViewDelegate {
     }
 
     deinit {
-        if let app = bevyApp {
+        for continuation in messageContinuations.values {
+            continuation.finish()
+        }
+        let lastView = releaseScene()
+        if let app = bevyApp, lastView {
             bevyEmbeddedDestroy(app)
         }
     }
+
+    /// Stops showing the scene's app, returning whether no other view shows it
+    private func releaseScene() -> Bool {
+        guard let sceneId else { return true }
+        let views = (Self.sceneViews[sceneId] ?? 1) - 1
+        Self.sceneViews[sceneId] = views > 0 ? views : nil
+        return views == 0
+    }
 }
 
 /// SwiftUI wrapper for the Bevy Metal view
@@ -240,20 +935,100 @@ struct BevyMetalView: UIViewRepresentable {
 /// A container view that captures touches for the Metal view
 class BevyTouchView: UIView {
     var coordinator: BevyMetalViewCoordinator?
+    /// Off when a gesture recognizer forwards the touches instead
+    var forwardsTouches = true
+
+    // Hardware keyboard presses go to the first responder
+    override var canBecomeFirstResponder: Bool { true }
+
+    override func safeAreaInsetsDidChange() {
+        super.safeAreaInsetsDidChange()
+        coordinator?.handleSafeArea(safeAreaInsets)
+    }
+
+    override func didMoveToWindow() {
+        super.didMoveToWindow()
+        if window != nil {
+            becomeFirstResponder()
+        } else {
+            // UIKit doesn't end the touches of a view that leaves its window
+            coordinator?.cancelTouches()
+        }
+    }
+
+    override func becomeFirstResponder() -> Bool {
+        let became = super.becomeFirstResponder()
+        if became {
+            coordinator?.handleKeyboardFocus(true)
+        }
+        return became
+    }
+
+    override func resignFirstResponder() -> Bool {
+        let resigned = super.resignFirstResponder()
+        if resigned {
+            coordinator?.handleKeyboardFocus(false)
+        }
+        return resigned
+    }
+
+    override func pressesBegan(_ presses: Set<UIPress>, with event: UIPressesEvent?) {
+        var unhandled = Set<UIPress>()
+        for press in presses {
+            if let key = press.key {
+                coordinator?.handleKey(key, pressed: true)
+            } else {
+                unhandled.insert(press)
+            }
+        }
+        if !unhandled.isEmpty {
+            super.pressesBegan(unhandled, with: event)
+        }
+    }
+
+    override func pressesEnded(_ presses: Set<UIPress>, with event: UIPressesEvent?) {
+        var unhandled = Set<UIPress>()
+        for press in presses {
+            if let key = press.key {
+                coordinator?.handleKey(key, pressed: false)
+            } else {
+                unhandled.insert(press)
+            }
+        }
+        if !unhandled.isEmpty {
+            super.pressesEnded(unhandled, with: event)
+        }
+    }
+
+    override func pressesCancelled(_ presses: Set<UIPress>, with event: UIPressesEvent?) {
+        pressesEnded(presses, with: event)
+    }
 
     override func touchesBegan(_ touches: Set<UITouch>, with event: UIEvent?) {
+        guard forwardsTouches else { return super.touchesBegan(touches, with: event) }
         BevyMetalView.handleTouches(touches, phase: 0, view: self, coordinator: coordinator)
     }
 
     override func touchesMoved(_ touches: Set<UITouch>, with event: UIEvent?) {
+        guard forwardsTouches else { return super.touchesMoved(touches, with: event) }
         BevyMetalView.handleTouches(touches, phase: 1, view: self, coordinator: coordinator)
+
+        // Predictions extend the touches just sent, so they follow them
+        guard let event = event else { return }
+        for touch in touches {
+            for predicted in event.predictedTouches(for: touch) ?? [] {
+                coordinator?.handlePredictedTouch(location: predicted.location(in: self), id: UInt64(touch.hash))
+            }
+        }
     }
 
     override func touchesEnded(_ touches: Set<UITouch>, with event: UIEvent?) {
+        guard forwardsTouches else { return super.touchesEnded(touches, with: event) }
         BevyMetalView.handleTouches(touches, phase: 2, view: self, coordinator: coordinator)
     }
 
     override func touchesCancelled(_ touches: Set<UITouch>, with event: UIEvent?) {
+        guard forwardsTouches else { return super.touchesCancelled(touches, with: event) }
         BevyMetalView.handleTouches(touches, phase: 3, view: self, coordinator: coordinator)
     }
 }
@@ -0,0 +1,196 @@
+//! Derive macros for `bevy_embedded`'s typed host events
+//!
+//! See `bevy_embedded::host_events`; the macros are re-exported by `bevy_embedded`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Attribute, Data, DataStruct, DeriveInput, Error, Fields, Ident, ext::IdentExt,
+    parse_macro_input,
+};
+
+/// Derives `HostEvent` and Bevy's `Message` for a struct sent between the host and the app
+///
+/// The struct must also derive serde's `Serialize` and `Deserialize`, which encode it,
+/// and needs a `#[host_event(to_bevy)]` or `#[host_event(to_host)]` attribute for the side
+/// sending it.
+#[proc_macro_derive(HostEvent, attributes(host_event))]
+pub fn derive_host_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    host_event(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `EventField` for a struct or an enum without variant data used in host events
+#[proc_macro_derive(EventField)]
+pub fn derive_event_field(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    event_field(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn host_event(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    check_plain(input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "host events must be structs; wrap enums in a struct field",
+        ));
+    };
+    let direction = direction(input)?;
+    let name = &input.ident;
+    let name_str = name.unraw().to_string();
+    let fields = field_types(data)?;
+
+    Ok(quote! {
+        impl ::bevy_embedded::bevy::ecs::message::Message for #name {}
+
+        impl ::bevy_embedded::HostEvent for #name {
+            const NAME: &'static str = #name_str;
+            const ID: u32 = ::bevy_embedded::event_id(#name_str);
+            const DIRECTION: ::bevy_embedded::EventDirection =
+                ::bevy_embedded::EventDirection::#direction;
+
+            fn fields() -> ::std::vec::Vec<(&'static str, ::bevy_embedded::FieldType)> {
+                #fields
+            }
+        }
+    })
+}
+
+fn event_field(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    check_plain(input)?;
+    let name = &input.ident;
+    let name_str = name.unraw().to_string();
+    let field_type = match &input.data {
+        Data::Struct(data) => {
+            let fields = field_types(data)?;
+            quote! {
+                ::bevy_embedded::FieldType::Struct(::bevy_embedded::StructSchema {
+                    name: #name_str,
+                    fields: #fields,
+                })
+            }
+        }
+        Data::Enum(data) => {
+            if data.variants.is_empty() {
+                return Err(Error::new_spanned(
+                    name,
+                    "enums without variants can't be event fields",
+                ));
+            }
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "only enums without variant data can be event fields",
+                    ));
+                }
+                check_no_serde(&variant.attrs)?;
+                variants.push(variant.ident.unraw().to_string());
+            }
+            quote! {
+                ::bevy_embedded::FieldType::Enum(::bevy_embedded::EnumSchema {
+                    name: #name_str,
+                    variants: ::std::vec![#(#variants),*],
+                })
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(name, "unions can't be event fields"));
+        }
+    };
+
+    Ok(quote! {
+        impl ::bevy_embedded::EventField for #name {
+            fn field_type() -> ::bevy_embedded::FieldType {
+                #field_type
+            }
+        }
+    })
+}
+
+/// Rejects what the generated host code can't mirror: generics, and serde attributes,
+/// which change the encoding the schema describes
+fn check_plain(input: &DeriveInput) -> Result<(), Error> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "host events and their fields can't be generic, as the host's code is generated for concrete types",
+        ));
+    }
+    check_no_serde(&input.attrs)
+}
+
+fn check_no_serde(attrs: &[Attribute]) -> Result<(), Error> {
+    match attrs.iter().find(|attr| attr.path().is_ident("serde")) {
+        Some(attr) => Err(Error::new_spanned(
+            attr,
+            "serde attributes would change the encoding the host's generated code expects",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The `EventDirection` variant of the `#[host_event(...)]` attribute
+fn direction(input: &DeriveInput) -> Result<Ident, Error> {
+    let mut attrs = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("host_event"));
+    let Some(attr) = attrs.next() else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "missing #[host_event(to_bevy)] or #[host_event(to_host)]",
+        ));
+    };
+    if let Some(duplicate) = attrs.next() {
+        return Err(Error::new_spanned(
+            duplicate,
+            "an event has one #[host_event] attribute",
+        ));
+    }
+
+    let direction: Ident = attr.parse_args()?;
+    match direction.to_string().as_str() {
+        "to_bevy" => Ok(Ident::new("ToBevy", direction.span())),
+        "to_host" => Ok(Ident::new("ToHost", direction.span())),
+        _ => Err(Error::new_spanned(
+            direction,
+            "expected `to_bevy` or `to_host`",
+        )),
+    }
+}
+
+/// A `Vec` of the names and field types of a struct's fields, in declaration order
+fn field_types(data: &DataStruct) -> Result<TokenStream2, Error> {
+    let fields = match &data.fields {
+        Fields::Named(fields) => &fields.named,
+        Fields::Unit => return Ok(quote! { ::std::vec::Vec::new() }),
+        Fields::Unnamed(fields) => {
+            return Err(Error::new_spanned(
+                fields,
+                "fields need names for the host's generated code",
+            ));
+        }
+    };
+
+    let mut entries = Vec::new();
+    for field in fields {
+        check_no_serde(&field.attrs)?;
+        let name = field
+            .ident
+            .as_ref()
+            .map(|ident| ident.unraw().to_string())
+            .unwrap_or_default();
+        let ty = &field.ty;
+        entries.push(quote! {
+            (#name, <#ty as ::bevy_embedded::EventField>::field_type())
+        });
+    }
+    Ok(quote! { ::std::vec![#(#entries),*] })
+}
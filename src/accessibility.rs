@@ -0,0 +1,159 @@
+//! Accessibility preferences of the host
+//!
+//! The host forwards the user's "reduce motion", "bold text" and preferred text size
+//! settings with `bevy_embedded_ios_set_accessibility` / `nativeSetAccessibility`, at
+//! startup and whenever they change (`UIAccessibility` notifications,
+//! `onConfigurationChanged`). With [`AccessibilityPlugin`] added, they are kept in the
+//! [`AccessibilitySettings`] resource, and each change is announced with
+//! [`AccessibilitySettingsChanged`].
+//!
+//! Some responses are built in:
+//!
+//! - [`OrbitCamera`](crate::OrbitCamera)s stop as soon as the fingers lift instead of
+//!   coasting while reduce motion is on, unless their `respect_reduce_motion` is cleared.
+//! - With the `accessibility_text` feature, text entities with a [`ScaledText`] component
+//!   follow the preferred text size, and switch to a bold font while bold text is on.
+
+use bevy::{
+    app::{App, Plugin},
+    ecs::{message::Message, resource::Resource},
+};
+
+/// Keeps the host's accessibility preferences; see the [module docs](self)
+#[derive(Default)]
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AccessibilitySettings>()
+            .add_message::<AccessibilitySettingsChanged>();
+    }
+
+    #[cfg(feature = "accessibility_text")]
+    fn finish(&self, app: &mut App) {
+        // Text scaling needs text entities in the app
+        if app
+            .world()
+            .contains_resource::<bevy::asset::Assets<bevy::text::Font>>()
+        {
+            app.add_systems(bevy::app::Update, text::apply_scaled_text);
+        }
+    }
+}
+
+/// Accessibility preferences the host reported
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct AccessibilitySettings {
+    /// Avoid non-essential motion such as camera inertia, parallax and shake
+    pub reduce_motion: bool,
+    /// Prefer bold text
+    pub bold_text: bool,
+    /// Preferred text size relative to the default, e.g. 1.3 for one of the larger
+    /// Dynamic Type sizes or the Android font scale
+    pub font_scale: f32,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            reduce_motion: false,
+            bold_text: false,
+            font_scale: 1.0,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    /// A font size scaled to the preferred text size
+    pub fn scale_font(&self, size: f32) -> f32 {
+        size * self.font_scale
+    }
+}
+
+/// Sent when the host reports changed accessibility preferences
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+pub struct AccessibilitySettingsChanged {
+    /// Preferences before the change
+    pub previous: AccessibilitySettings,
+    /// Preferences after the change
+    pub current: AccessibilitySettings,
+}
+
+#[cfg(feature = "accessibility_text")]
+pub use text::*;
+
+#[cfg(feature = "accessibility_text")]
+mod text {
+    use bevy::{
+        asset::Handle,
+        ecs::{
+            change_detection::{DetectChanges, DetectChangesMut},
+            component::Component,
+            system::{Query, Res},
+        },
+        text::{Font, TextFont},
+    };
+
+    use super::AccessibilitySettings;
+
+    /// Makes a text entity's `TextFont` follow the host's accessibility preferences
+    ///
+    /// The font size is `size` times [`AccessibilitySettings::font_scale`]. While bold text
+    /// is on, `bold_font` (if set) replaces the entity's font.
+    #[derive(Component, Debug, Clone)]
+    pub struct ScaledText {
+        /// Font size at the default text size
+        pub size: f32,
+        /// Font used while the host prefers bold text
+        pub bold_font: Option<Handle<Font>>,
+        /// The entity's own font, kept while the bold font replaces it
+        regular_font: Option<Handle<Font>>,
+    }
+
+    impl ScaledText {
+        /// Scale text whose default size is `size`
+        pub fn new(size: f32) -> Self {
+            Self {
+                size,
+                bold_font: None,
+                regular_font: None,
+            }
+        }
+
+        /// Use `font` while the host prefers bold text
+        pub fn with_bold_font(mut self, font: Handle<Font>) -> Self {
+            self.bold_font = Some(font);
+            self
+        }
+    }
+
+    /// Applies the preferred text size and weight to [`ScaledText`] entities
+    pub(super) fn apply_scaled_text(
+        settings: Res<AccessibilitySettings>,
+        mut texts: Query<(&mut ScaledText, &mut TextFont)>,
+    ) {
+        for (mut scaled, mut font) in &mut texts {
+            if !settings.is_changed() && !scaled.is_changed() {
+                continue;
+            }
+
+            let size = settings.scale_font(scaled.size);
+            if font.font_size != size {
+                font.font_size = size;
+            }
+
+            let scaled = scaled.bypass_change_detection();
+            match (&scaled.bold_font, settings.bold_text) {
+                (Some(bold), true) if font.font != *bold => {
+                    scaled.regular_font = Some(std::mem::replace(&mut font.font, bold.clone()));
+                }
+                (_, false) => {
+                    if let Some(regular) = scaled.regular_font.take() {
+                        font.font = regular;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
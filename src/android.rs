@@ -1,5 +1,8 @@
 //! Android-specific embedded integration with JNI functions
-use crate::HostChannel;
+use crate::{
+    EmbeddedConfig, HandshakeStatus, HostConfig, InitProgress, KeyModifiers, TouchContact,
+    app_thread, ffi, key_code_from_android, pending, preload, replay, teardown,
+};
 use bevy::{
     app::App,
     asset::{
@@ -10,17 +13,31 @@ use bevy::{
         },
     },
     log::info,
-    math::Vec2,
     window::{
         PrimaryWindow, RawHandleWrapper, RawHandleWrapperHolder, Window, WindowResolution,
         WindowWrapper,
     },
 };
+#[cfg(feature = "gles_fallback")]
+use bevy::{
+    app::SubApp,
+    ecs::{schedule::IntoScheduleConfigs, system::Res},
+    render::{
+        Render,
+        renderer::RenderAdapterInfo,
+        view::{
+            ExtractedWindows,
+            window::{create_surfaces, prepare_windows},
+        },
+    },
+};
 use futures_lite::stream;
 use jni::{
     JNIEnv,
-    objects::{JByteArray, JClass, JObject},
-    sys::{jbyteArray, jfloat, jint, jlong},
+    objects::{
+        JByteArray, JByteBuffer, JClass, JDoubleArray, JFloatArray, JIntArray, JObject, JString,
+    },
+    sys::{jboolean, jbyteArray, jdouble, jfloat, jint, jlong, jobject},
 };
 use log::{debug, error};
 use raw_window_handle::{
@@ -29,8 +46,9 @@ use raw_window_handle::{
 };
 use std::{
     ffi::{CString, c_void},
+    path::{Component, Path, PathBuf},
     ptr::NonNull,
-    sync::{Arc, Mutex, OnceLock},
+    sync::{Arc, Mutex, OnceLock, PoisonError},
 };
 
 /// Android surface information passed from Java/Kotlin
@@ -55,7 +73,7 @@ pub fn get_android_surface() -> Option<AndroidSurfaceInfo> {
     CURRENT_SURFACE
         .get_or_init(|| Mutex::new(None))
         .lock()
-        .unwrap()
+        .unwrap_or_else(PoisonError::into_inner)
         .take()
 }
 
@@ -64,7 +82,7 @@ pub fn set_android_surface(surface: AndroidSurfaceInfo) {
     *CURRENT_SURFACE
         .get_or_init(|| Mutex::new(None))
         .lock()
-        .unwrap() = Some(surface);
+        .unwrap_or_else(PoisonError::into_inner) = Some(surface);
 }
 
 /// Wrapper for the Android native window that implements the required traits
@@ -96,6 +114,69 @@ impl HasDisplayHandle for AndroidWindowWrapper {
     }
 }
 
+/// Creates the raw handle for an `ANativeWindow`
+fn native_window_handle(native_window: *mut c_void) -> Option<RawHandleWrapper> {
+    let android_wrapper = AndroidWindowWrapper {
+        window_handle: AndroidNdkWindowHandle::new(NonNull::new(native_window)?),
+        display_handle: AndroidDisplayHandle::new(),
+    };
+
+    let window_wrapper = WindowWrapper::new(android_wrapper);
+    RawHandleWrapper::new(&window_wrapper).ok()
+}
+
+/// Keeps GLES window surfaces at the resolution Bevy renders at
+///
+/// Vulkan swapchains size the window's buffers to the swapchain, and the compositor scales
+/// them to the view. EGL instead sizes its surface to the window's buffers, which wgpu
+/// resets to the view's size whenever it creates a surface, so with a lower render
+/// resolution (dynamic resolution, picture-in-picture) the frame would fill only a corner
+/// of the view.
+#[cfg(feature = "gles_fallback")]
+pub(crate) fn add_gles_render_systems(render_app: &mut SubApp) {
+    render_app.add_systems(
+        Render,
+        sync_gles_window_buffers
+            .after(create_surfaces)
+            .before(prepare_windows),
+    );
+}
+
+/// Sizes the buffers of GLES windows to their surface, before the frame acquires one
+#[cfg(feature = "gles_fallback")]
+fn sync_gles_window_buffers(adapter_info: Res<RenderAdapterInfo>, windows: Res<ExtractedWindows>) {
+    if adapter_info.backend.to_str() != "gl" {
+        return;
+    }
+    for window in windows.values() {
+        let RawWindowHandle::AndroidNdk(handle) = window.handle.get_window_handle() else {
+            continue;
+        };
+        let native_window = handle.a_native_window.as_ptr() as *mut ndk_sys::ANativeWindow;
+        let (width, height) = (window.physical_width as i32, window.physical_height as i32);
+        // Cheap queries; the buffer size reads back as set until wgpu resets it
+        let (buffer_width, buffer_height) = unsafe {
+            (
+                ndk_sys::ANativeWindow_getWidth(native_window),
+                ndk_sys::ANativeWindow_getHeight(native_window),
+            )
+        };
+        if (buffer_width, buffer_height) == (width, height) {
+            continue;
+        }
+
+        let result = unsafe {
+            let format = ndk_sys::ANativeWindow_getFormat(native_window);
+            ndk_sys::ANativeWindow_setBuffersGeometry(native_window, width, height, format)
+        };
+        if result != 0 {
+            error!("Failed to size GLES window buffers to {width}x{height} ({result})");
+            continue;
+        }
+        debug!("Sized GLES window buffers from {buffer_width}x{buffer_height} to {width}x{height}");
+    }
+}
+
 /// Called by EmbeddedPlugin during finish() to create the window from Android surface
 pub fn create_window_from_host(app: &mut App) {
     let surface_info = match get_android_surface() {
@@ -116,22 +197,11 @@ pub fn create_window_from_host(app: &mut App) {
         surface_info.width, surface_info.height, surface_info.scale_factor
     );
 
-    // Create the window wrapper for raw-window-handle
-    let window_handle = AndroidNdkWindowHandle::new(
-        NonNull::new(surface_info.native_window).expect("Native window pointer is null"),
-    );
-
-    let display_handle = AndroidDisplayHandle::new();
-
-    let android_wrapper = AndroidWindowWrapper {
-        window_handle,
-        display_handle,
-    };
-
     // Create WindowWrapper and RawHandleWrapper
-    let window_wrapper = WindowWrapper::new(android_wrapper);
-    let handle_wrapper =
-        RawHandleWrapper::new(&window_wrapper).expect("Failed to create RawHandleWrapper");
+    let Some(handle_wrapper) = native_window_handle(surface_info.native_window) else {
+        report_jni_error("Failed to create a window handle for the host's surface".to_string());
+        return;
+    };
 
     let handle_holder = RawHandleWrapperHolder(Arc::new(Mutex::new(Some(handle_wrapper.clone()))));
 
@@ -154,26 +224,51 @@ pub fn create_window_from_host(app: &mut App) {
 
 /// Custom AssetReader for embedded Android contexts that uses AssetManager directly
 /// without requiring ANDROID_APP
+///
+/// Reads from a folder inside the APK's assets, so instances with different
+/// [`asset_root`](EmbeddedConfig::asset_root)s share the AssetManager but not their assets.
+#[derive(Clone)]
 pub struct EmbeddedAndroidAssetReader {
     asset_manager: Arc<ndk::asset::AssetManager>,
+    root: PathBuf,
 }
 
 impl EmbeddedAndroidAssetReader {
-    /// Create a new reader from an AssetManager pointer
+    /// Create a new reader from an AssetManager pointer, or `None` if it is null
     ///
     /// # Safety
-    /// The asset_manager_ptr must be a valid AAssetManager pointer that will remain
-    /// valid for the lifetime of this reader
-    pub unsafe fn new(asset_manager_ptr: *mut ndk_sys::AAssetManager) -> Self {
-        let asset_manager = unsafe {
-            ndk::asset::AssetManager::from_ptr(
-                NonNull::new(asset_manager_ptr).expect("AssetManager pointer is null"),
-            )
-        };
-        Self {
+    /// The asset_manager_ptr must be null or a valid AAssetManager pointer that will
+    /// remain valid for the lifetime of this reader
+    pub unsafe fn new(asset_manager_ptr: *mut ndk_sys::AAssetManager) -> Option<Self> {
+        let asset_manager =
+            unsafe { ndk::asset::AssetManager::from_ptr(NonNull::new(asset_manager_ptr)?) };
+        Some(Self {
             asset_manager: Arc::new(asset_manager),
+            root: PathBuf::new(),
+        })
+    }
+
+    /// A reader for the same AssetManager, reading from `root` inside the assets
+    pub fn with_root(&self, root: impl AsRef<Path>) -> Self {
+        Self {
+            asset_manager: self.asset_manager.clone(),
+            // AssetManager paths are relative and can't leave the assets
+            root: root
+                .as_ref()
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .collect(),
         }
     }
+
+    /// The AssetManager path of an asset
+    fn asset_path(&self, path: &Path) -> Result<CString, AssetReaderError> {
+        self.root
+            .join(path)
+            .to_str()
+            .and_then(|path| CString::new(path).ok())
+            .ok_or_else(|| AssetReaderError::NotFound(path.to_path_buf()))
+    }
 }
 
 impl AssetReader for EmbeddedAndroidAssetReader {
@@ -181,8 +276,7 @@ impl AssetReader for EmbeddedAndroidAssetReader {
         &'a self,
         path: &'a std::path::Path,
     ) -> Result<Box<dyn Reader + 'a>, AssetReaderError> {
-        let path_cstr = CString::new(path.to_str().unwrap())
-            .map_err(|_| AssetReaderError::NotFound(path.to_path_buf()))?;
+        let path_cstr = self.asset_path(path)?;
 
         let mut opened_asset = self
             .asset_manager
@@ -209,8 +303,7 @@ impl AssetReader for EmbeddedAndroidAssetReader {
             .unwrap_or_default();
         extension.push(".meta");
         meta_path.set_extension(extension);
-        let path_cstr = CString::new(meta_path.to_str().unwrap())
-            .map_err(|_| AssetReaderError::NotFound(meta_path.clone()))?;
+        let path_cstr = self.asset_path(&meta_path)?;
 
         let mut opened_asset = self
             .asset_manager
@@ -229,8 +322,7 @@ impl AssetReader for EmbeddedAndroidAssetReader {
         &'a self,
         path: &'a std::path::Path,
     ) -> Result<Box<PathStream>, AssetReaderError> {
-        let path_cstr = CString::new(path.to_str().unwrap())
-            .map_err(|_| AssetReaderError::NotFound(path.to_path_buf()))?;
+        let path_cstr = self.asset_path(path)?;
 
         let opened_assets_dir = self
             .asset_manager
@@ -239,7 +331,8 @@ impl AssetReader for EmbeddedAndroidAssetReader {
 
         let mapped_stream: Vec<_> = opened_assets_dir
             .filter_map(move |f| {
-                let file_path = path.join(std::path::Path::new(f.to_str().unwrap()));
+                // Asset names that aren't UTF-8 can't be asset paths
+                let file_path = path.join(std::path::Path::new(f.to_str().ok()?));
                 // Filter out meta files as they are not considered assets
                 if let Some(ext) = file_path.extension().and_then(|e| e.to_str()) {
                     if ext.eq_ignore_ascii_case("meta") {
@@ -258,8 +351,7 @@ impl AssetReader for EmbeddedAndroidAssetReader {
         &'a self,
         path: &'a std::path::Path,
     ) -> Result<bool, AssetReaderError> {
-        let cpath = CString::new(path.to_str().unwrap())
-            .map_err(|_| AssetReaderError::NotFound(path.to_path_buf()))?;
+        let cpath = self.asset_path(path)?;
 
         // Check if path exists as a directory
         let _ = self
@@ -272,19 +364,25 @@ impl AssetReader for EmbeddedAndroidAssetReader {
     }
 }
 
-/// Global storage for the embedded asset reader
+/// Reader for the root of the activity's assets, which every instance reads below
 static EMBEDDED_ASSET_READER: OnceLock<EmbeddedAndroidAssetReader> = OnceLock::new();
 
 /// Initialize the embedded asset reader with the given AssetManager
 ///
+/// A null AssetManager is reported as an error and leaves the reader uninitialized.
+///
 /// # Safety
-/// The asset_manager_ptr must be a valid AAssetManager pointer
+/// The asset_manager_ptr must be null or a valid AAssetManager pointer
 pub unsafe fn init_embedded_asset_reader(asset_manager_ptr: *mut ndk_sys::AAssetManager) {
-    let reader = unsafe { EmbeddedAndroidAssetReader::new(asset_manager_ptr) };
-    let _ = EMBEDDED_ASSET_READER.set(reader);
+    match unsafe { EmbeddedAndroidAssetReader::new(asset_manager_ptr) } {
+        Some(reader) => {
+            let _ = EMBEDDED_ASSET_READER.set(reader);
+        }
+        None => report_jni_error("The host passed a null AssetManager".to_string()),
+    }
 }
 
-/// Get the embedded asset reader, if initialized
+/// Get the reader for the root of the activity's assets, if initialized
 pub fn get_embedded_asset_reader() -> Option<&'static EmbeddedAndroidAssetReader> {
     EMBEDDED_ASSET_READER.get()
 }
@@ -294,7 +392,10 @@ pub fn get_embedded_asset_reader() -> Option<&'static EmbeddedAndroidAssetReader
 /// **IMPORTANT**: Call this BEFORE adding AssetPlugin/DefaultPlugins to your app!
 ///
 /// This function replaces the default Android asset reader with our custom
-/// embedded reader that works in embedded widget contexts.
+/// embedded reader that works in embedded widget contexts. The reader reads from the
+/// [`asset_root`](EmbeddedConfig::asset_root) of the app's [`EmbeddedConfig`], if it has
+/// one, so insert the config first. If the host hasn't initialized the reader, the error is
+/// reported and the app keeps Bevy's default asset source.
 ///
 /// # Example
 /// ```ignore
@@ -318,18 +419,23 @@ pub fn get_embedded_asset_reader() -> Option<&'static EmbeddedAndroidAssetReader
 #[cfg(target_os = "android")]
 pub fn configure_embedded_asset_source(app: &mut App) {
     // Get the embedded asset reader
-    let reader = get_embedded_asset_reader()
-        .expect("Embedded asset reader must be initialized before configuring Bevy app");
+    let Some(reader) = get_embedded_asset_reader() else {
+        report_jni_error(
+            "The embedded asset reader must be initialized before configuring the app".to_string(),
+        );
+        return;
+    };
 
-    // Clone the Arc so we can share it with the closure
-    let asset_manager = reader.asset_manager.clone();
+    // Read from this instance's folder of the assets
+    let root = app
+        .world()
+        .get_resource::<EmbeddedConfig>()
+        .and_then(|config| config.asset_root.clone())
+        .unwrap_or_default();
+    let reader = reader.with_root(root);
 
     // Create a custom asset source that uses our embedded reader
-    let source = AssetSourceBuilder::default().with_reader(move || {
-        Box::new(EmbeddedAndroidAssetReader {
-            asset_manager: asset_manager.clone(),
-        })
-    });
+    let source = AssetSourceBuilder::default().with_reader(move || Box::new(reader.clone()));
 
     // Register it as the default source using the proper API
     app.register_asset_source(AssetSourceId::Default, source);
@@ -352,52 +458,47 @@ fn android_main(android_app: bevy::android::android_activity::AndroidApp) {
     info!("android_main: Activity initialized, waiting for JNI calls...");
 }
 
-// ============================================================================
-// JNI Entry Points
-// ============================================================================
-
-#[unsafe(no_mangle)]
-pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCreateApp(
-    mut env: JNIEnv,
-    _class: JClass,
-    activity: JObject,
-    surface: JObject,
-    width: jint,
-    height: jint,
-    scale_factor: jfloat,
-) -> jlong {
-    debug!(
-        "nativeCreateApp called: {}x{} @ {}x",
-        width, height, scale_factor
-    );
-
+/// Gives the app access to the activity's bundled assets and JNI context
+///
+/// Must run before any app is created. Returns false if the activity has no
+/// AssetManager.
+fn init_activity_context(env: &mut JNIEnv, activity: &JObject) -> bool {
     // Get AssetManager from Activity
-    let asset_manager_ptr = unsafe {
-        let assets_obj = env
-            .call_method(
-                &activity,
-                "getAssets",
-                "()Landroid/content/res/AssetManager;",
-                &[],
-            )
-            .expect("Failed to get AssetManager")
-            .l()
-            .expect("AssetManager is null");
-        ndk_sys::AAssetManager_fromJava(env.get_raw(), assets_obj.as_raw())
+    let assets = env
+        .call_method(
+            activity,
+            "getAssets",
+            "()Landroid/content/res/AssetManager;",
+            &[],
+        )
+        .and_then(|assets| assets.l());
+    let Some(assets_obj) = jni_checked(env, "Failed to get AssetManager", assets) else {
+        return false;
+    };
+    let asset_manager_ptr = if assets_obj.is_null() {
+        std::ptr::null_mut()
+    } else {
+        unsafe { ndk_sys::AAssetManager_fromJava(env.get_raw(), assets_obj.as_raw()) }
     };
 
     if asset_manager_ptr.is_null() {
-        error!("Failed to get AssetManager from Activity");
-        return 0;
+        report_jni_error("Failed to get AssetManager from Activity".to_string());
+        return false;
     } else {
         debug!("Got AssetManager: {:p}", asset_manager_ptr);
     }
 
     // Initialize ndk-context for JNI calls
+    let vm = env.get_java_vm();
+    let Some(vm) = jni_checked(env, "Failed to get the Java VM", vm) else {
+        return false;
+    };
     unsafe {
-        let vm = env.get_java_vm().unwrap().get_java_vm_pointer() as *mut c_void;
         let activity_ptr = activity.as_raw() as *mut c_void;
-        ndk_context::initialize_android_context(vm, activity_ptr);
+        ndk_context::initialize_android_context(
+            vm.get_java_vm_pointer() as *mut c_void,
+            activity_ptr,
+        );
     }
 
     // Initialize our custom embedded asset reader
@@ -405,34 +506,201 @@ pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCreateApp(
         init_embedded_asset_reader(asset_manager_ptr);
     }
     debug!("Initialized embedded asset reader");
+    true
+}
+
+// ============================================================================
+// JNI Errors
+// ============================================================================
+
+/// Takes the value of a JNI call, or stores why it failed for `nativeGetLastError`
+///
+/// Every JNI call's result goes through here. A Java exception the call left pending is
+/// cleared and becomes the stored error: further JNI calls with an exception pending
+/// abort the VM, and returning with one throws it into the host's calling code.
+fn jni_checked<T>(env: &mut JNIEnv, context: &str, result: jni::errors::Result<T>) -> Option<T> {
+    match (result, take_java_exception(env)) {
+        (Ok(value), None) => Some(value),
+        (_, Some(exception)) => {
+            report_jni_error(format!("{context}: {exception}"));
+            None
+        }
+        (Err(e), None) => {
+            report_jni_error(format!("{context}: {e}"));
+            None
+        }
+    }
+}
+
+/// Clears the pending Java exception, returning its description
+fn take_java_exception(env: &mut JNIEnv) -> Option<String> {
+    if !env.exception_check().unwrap_or(false) {
+        return None;
+    }
+    let throwable = env.exception_occurred();
+    let _ = env.exception_clear();
+
+    let description = throwable.ok().and_then(|throwable| {
+        let description = env
+            .call_method(&throwable, "toString", "()Ljava/lang/String;", &[])
+            .and_then(|description| description.l())
+            .and_then(|description| {
+                env.get_string(&JString::from(description))
+                    .map(String::from)
+            });
+        // Describing the exception may throw as well
+        if env.exception_check().unwrap_or(false) {
+            let _ = env.exception_clear();
+        }
+        description.ok()
+    });
+    Some(description.unwrap_or_else(|| "Java exception".to_string()))
+}
 
-    // Get ANativeWindow from Surface
-    let native_window_ptr = unsafe {
-        let surface_ptr = surface.as_raw();
-        ndk_sys::ANativeWindow_fromSurface(env.get_raw(), surface_ptr)
+fn report_jni_error(message: String) {
+    error!("{message}");
+    crate::store_error(message);
+}
+
+/// Address and capacity of a direct `ByteBuffer`
+fn direct_buffer(
+    env: &mut JNIEnv,
+    buffer: &JByteBuffer,
+    context: &str,
+) -> Option<(*mut u8, usize)> {
+    let address = env.get_direct_buffer_address(buffer);
+    let address = jni_checked(env, context, address)?;
+    let capacity = env.get_direct_buffer_capacity(buffer);
+    Some((address, jni_checked(env, context, capacity)?))
+}
+
+// ============================================================================
+// JNI Entry Points
+// ============================================================================
+
+/// Creates an empty creation config for `nativeCreateAppWithConfig`, `nativeCreateAppAsync` or
+/// `nativeSetPreloadTarget`; free it with `nativeFreeConfig`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeNewConfig(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jlong {
+    ffi::new_host_config() as jlong
+}
+
+/// Sets a creation config value in a config from `nativeNewConfig`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeConfigSet(
+    mut env: JNIEnv,
+    _class: JClass,
+    config: jlong,
+    key: JString,
+    value: JString,
+) {
+    let Some(config) = (unsafe { (config as *mut HostConfig).as_mut() }) else {
+        report_jni_error("nativeConfigSet called without a config".to_string());
+        return;
+    };
+    let key = env.get_string(&key).map(String::from);
+    let Some(key) = jni_checked(&mut env, "Invalid config key", key) else {
+        return;
+    };
+    let value = env.get_string(&value).map(String::from);
+    let Some(value) = jni_checked(&mut env, &format!("Invalid config value for {key}"), value)
+    else {
+        return;
     };
+    config.set(key, value);
+}
 
-    if native_window_ptr.is_null() {
-        error!("Failed to get native window from surface");
+/// Frees a config from `nativeNewConfig`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeFreeConfig(
+    _env: JNIEnv,
+    _class: JClass,
+    config: jlong,
+) {
+    unsafe { ffi::free_host_config(config as *mut HostConfig) };
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCreateApp(
+    env: JNIEnv,
+    _class: JClass,
+    activity: JObject,
+    surface: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+) -> jlong {
+    create_app(env, activity, surface, width, height, scale_factor, 0)
+}
+
+/// Like `nativeCreateApp`, with a creation config from `nativeNewConfig`, or 0
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCreateAppWithConfig(
+    env: JNIEnv,
+    _class: JClass,
+    activity: JObject,
+    surface: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+    config: jlong,
+) -> jlong {
+    create_app(env, activity, surface, width, height, scale_factor, config)
+}
+
+fn create_app(
+    mut env: JNIEnv,
+    activity: JObject,
+    surface: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+    config: jlong,
+) -> jlong {
+    debug!(
+        "nativeCreateApp called: {}x{} @ {}x",
+        width, height, scale_factor
+    );
+
+    if !init_activity_context(&mut env, &activity) {
         return 0;
     }
 
-    debug!("Got native window pointer: {:p}", native_window_ptr);
+    // A null surface requests a headless app; the app's EmbeddedConfig must agree
+    if surface.is_null() {
+        debug!("No surface provided, creating headless app");
+    } else {
+        // Get ANativeWindow from Surface
+        let native_window_ptr = unsafe {
+            let surface_ptr = surface.as_raw();
+            ndk_sys::ANativeWindow_fromSurface(env.get_raw(), surface_ptr)
+        };
+
+        if native_window_ptr.is_null() {
+            error!("Failed to get native window from surface");
+            return 0;
+        }
+
+        debug!("Got native window pointer: {:p}", native_window_ptr);
 
-    // Store surface info globally so create_window_from_host can access it
-    set_android_surface(AndroidSurfaceInfo {
-        native_window: native_window_ptr as *mut c_void,
-        width: width as u32,
-        height: height as u32,
-        scale_factor,
-    });
+        // Store surface info globally so create_window_from_host can access it
+        set_android_surface(AndroidSurfaceInfo {
+            native_window: native_window_ptr as *mut c_void,
+            width: width as u32,
+            height: height as u32,
+            scale_factor,
+        });
+    }
 
-    // Call the user's exported bevy_embedded_create_app function
+    // Call the user's exported bevy_embedded_create_app_with_config function
     unsafe extern "C" {
-        fn bevy_embedded_create_app() -> *mut App;
+        fn bevy_embedded_create_app_with_config(config: *const HostConfig) -> *mut App;
     }
 
-    let app_ptr = unsafe { bevy_embedded_create_app() };
+    let app_ptr = unsafe { bevy_embedded_create_app_with_config(config as *const HostConfig) };
 
     if app_ptr.is_null() {
         error!("Failed to create Bevy app");
@@ -460,6 +728,58 @@ pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeUpdate(
     unsafe { bevy_embedded_update(app_ptr as *mut App) as jint }
 }
 
+/// Like `nativeUpdate`, also writing the frame's `FrameInfo` into `info` as `frame,
+/// cpuMs, gpuMs, pendingMessages, needsRedraw, idle`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeUpdateWithInfo(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    info: JDoubleArray,
+) -> jint {
+    unsafe extern "C" {
+        fn bevy_embedded_update_with_info(app: *mut App, info: *mut crate::FrameInfo) -> u8;
+    }
+
+    // A null app returns 1 and leaves the info zeroed, like any update without a frame
+    let mut frame_info = crate::FrameInfo::default();
+    let result =
+        unsafe { bevy_embedded_update_with_info(app_ptr as *mut App, &mut frame_info) as jint };
+    let values = [
+        frame_info.frame as jdouble,
+        frame_info.cpu_ms,
+        frame_info.gpu_ms,
+        frame_info.pending_messages as jdouble,
+        frame_info.needs_redraw as jdouble,
+        frame_info.idle as jdouble,
+    ];
+    if !info.is_null() {
+        let written = env.set_double_array_region(&info, 0, &values);
+        jni_checked(&mut env, "Failed to write frame info", written);
+    }
+    result
+}
+
+/// Runs one part of a frame instead of a whole update; see `HostSchedule`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeRunSchedule(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    schedule: jint,
+) -> jint {
+    if app_ptr == 0 {
+        return 1; // Error: null app pointer
+    }
+
+    unsafe extern "C" {
+        fn bevy_embedded_run_schedule(app: *mut App, schedule: u8) -> u8;
+    }
+
+    let schedule = u8::try_from(schedule).unwrap_or(u8::MAX);
+    unsafe { bevy_embedded_run_schedule(app_ptr as *mut App, schedule) as jint }
+}
+
 #[unsafe(no_mangle)]
 pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeGetLastError<'local>(
     mut env: JNIEnv<'local>,
@@ -484,13 +804,10 @@ pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeGetLastError<'l
             .unwrap_or("Invalid UTF-8 in error message");
 
         // Convert to Java String and return
-        let result = match env.new_string(error_str) {
-            Ok(jstring) => jstring.into(),
-            Err(_) => JObject::null(),
-        };
-
+        let string = env.new_string(error_str);
         bevy_embedded_free_error(error_ptr);
-        result
+        jni_checked(&mut env, "Failed to return the last error", string)
+            .map_or_else(JObject::default, JObject::from)
     }
 }
 
@@ -517,122 +834,1805 @@ pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeDestroy(
     debug!("Bevy app destroyed");
 }
 
+/// Requests the app to exit; `nativeTeardownStep` then runs its final updates and destroys
+/// it
 #[unsafe(no_mangle)]
-pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeTouchEvent(
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeDestroyAsync(
     _env: JNIEnv,
     _class: JClass,
     app_ptr: jlong,
-    phase: jint,
-    x: jfloat,
-    y: jfloat,
-    id: jlong,
 ) {
-    let app = app_ptr as *mut App;
-    if app.is_null() {
+    if app_ptr == 0 {
         return;
     }
 
-    unsafe {
-        let app_ref = &mut *app;
-
-        if let Some(touch_phase) = crate::TouchPhase::from_u8(phase as u8) {
-            let mut input_events = app_ref
-                .world_mut()
-                .resource_mut::<crate::EmbeddedInputEvents>();
-            input_events.add_touch_event(crate::EmbeddedTouchEvent {
-                phase: touch_phase,
-                position: Vec2::new(x as f32, y as f32),
-                id: id as u64,
-            });
-        }
+    unsafe extern "C" {
+        fn bevy_embedded_destroy_async(
+            app: *mut App,
+            on_destroyed: Option<teardown::DestroyedCallback>,
+            user_data: *mut c_void,
+        );
     }
+
+    unsafe { bevy_embedded_destroy_async(app_ptr as *mut App, None, std::ptr::null_mut()) };
 }
 
+/// Runs the next final update after `nativeDestroyAsync`, returning true once the app is
+/// destroyed
 #[unsafe(no_mangle)]
-pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeResize(
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeTeardownStep(
     _env: JNIEnv,
     _class: JClass,
     app_ptr: jlong,
-    width: jint,
-    height: jint,
-    scale_factor: jfloat,
-) {
-    let app = app_ptr as *mut App;
-    if app.is_null() {
-        return;
+) -> jboolean {
+    teardown_step(app_ptr as *mut App) as jboolean
+}
+
+/// Runs the next final update on the app's thread, destroying the app after the last one
+fn teardown_step(app: *mut App) -> bool {
+    let app_addr = app as usize;
+    if let Some(result) = app_thread::marshal(app, move || teardown_step(app_addr as *mut App)) {
+        return result.unwrap_or_else(|violation| {
+            crate::store_error(violation.to_string());
+            false
+        });
     }
 
-    debug!(
-        "Android resize: {}x{} @ {}x scale",
-        width, height, scale_factor
-    );
+    let finished = match unsafe { ffi::app_mut(app.cast()) } {
+        Ok(mut app) => teardown::in_progress(&app) && teardown::step(&mut app),
+        Err(_) => false,
+    };
+    if !finished {
+        return false;
+    }
 
-    unsafe {
-        if let Some(mut world) = (*app).world_mut().into() {
-            if let Some(mut window) = world.query::<&mut Window>().iter_mut(&mut world).next() {
-                window.resolution.set(width as f32, height as f32);
-                window
-                    .resolution
-                    .set_scale_factor_override(Some(scale_factor));
-            }
-        }
+    unsafe extern "C" {
+        fn bevy_embedded_destroy(app: *mut App);
     }
+
+    unsafe { bevy_embedded_destroy(app) };
+    debug!("Bevy app destroyed after its final updates");
+    true
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSendMessage(
-    env: JNIEnv,
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeTouchEvent(
+    _env: JNIEnv,
     _class: JClass,
     app_ptr: jlong,
-    data: JByteArray,
+    phase: jint,
+    x: jfloat,
+    y: jfloat,
+    id: jlong,
 ) {
-    let app = app_ptr as *mut App;
-    if app.is_null() {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
         return;
-    }
-
-    // Convert Java byte array to Rust Vec<u8>
-    let bytes = match env.convert_byte_array(data) {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            error!("Failed to convert byte array: {:?}", e);
-            return;
-        }
     };
 
-    unsafe {
-        let app_ref = &mut *app;
-        if let Some(channel) = app_ref.world().get_resource::<HostChannel>() {
-            channel.send(bytes);
-        }
-    }
+    // Out-of-range phases must not wrap around into valid ones
+    let phase = u8::try_from(phase).unwrap_or(u8::MAX);
+    let _ = ffi::touch_event(&mut app, phase, x, y, id as u64);
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeReceiveMessage(
-    env: JNIEnv,
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeTouchEventWithAxes(
+    _env: JNIEnv,
     _class: JClass,
     app_ptr: jlong,
-) -> jbyteArray {
-    let app = app_ptr as *mut App;
-    if app.is_null() {
-        return JObject::null().into_raw() as jbyteArray;
-    }
-
-    unsafe {
-        let app_ref = &mut *app;
-        if let Some(channel) = app_ref.world().get_resource::<HostChannel>() {
-            if let Some(message) = channel.receive() {
-                // Convert Rust Vec<u8> to Java byte array
-                match env.byte_array_from_slice(&message) {
-                    Ok(array) => return array.into_raw(),
-                    Err(e) => {
-                        error!("Failed to create byte array: {:?}", e);
-                    }
-                }
-            }
-        }
-    }
+    phase: jint,
+    x: jfloat,
+    y: jfloat,
+    id: jlong,
+    pressure: jfloat,
+    touch_major: jfloat,
+    touch_minor: jfloat,
+    orientation: jfloat,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let phase = u8::try_from(phase).unwrap_or(u8::MAX);
+    let contact = TouchContact {
+        pressure,
+        touch_major,
+        touch_minor,
+        orientation,
+    };
+    let _ = ffi::touch_event_with_contact(&mut app, phase, x, y, id as u64, contact);
+}
+
+/// Cancels every touch that is down, e.g. on `ACTION_CANCEL`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCancelTouches(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    ffi::cancel_touches(&mut app);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativePredictedTouch(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    x: jfloat,
+    y: jfloat,
+    id: jlong,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::predicted_touch(&mut app, x, y, id as u64);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeRotaryEvent(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    delta: jfloat,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::rotary_event(&mut app, delta);
+}
+
+/// Modifiers held according to a `KeyEvent` meta state
+fn modifiers_from_meta_state(meta_state: jint) -> KeyModifiers {
+    [
+        (0x1, KeyModifiers::SHIFT),
+        (0x1000, KeyModifiers::CONTROL),
+        (0x2, KeyModifiers::ALT),
+        (0x10000, KeyModifiers::SUPER),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| meta_state & flag != 0)
+    .fold(KeyModifiers::NONE, |held, (_, modifier)| held | modifier)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeKeyEvent(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    key_code: jint,
+    action: jint,
+    repeat_count: jint,
+    meta_state: jint,
+    unicode_char: jint,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    // KeyEvent.ACTION_DOWN = 0, ACTION_UP = 1
+    let pressed = match action {
+        0 => true,
+        1 => false,
+        _ => return,
+    };
+    let text = u32::try_from(unicode_char)
+        .ok()
+        .filter(|&code| code != 0)
+        .and_then(char::from_u32)
+        .map(String::from);
+    ffi::key_event(
+        &mut app,
+        key_code_from_android(key_code),
+        pressed,
+        repeat_count > 0,
+        modifiers_from_meta_state(meta_state),
+        text.as_deref(),
+    );
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeKeyboardFocus(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    focused: jboolean,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    ffi::keyboard_focus(&mut app, focused != 0);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeResize(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    debug!(
+        "Android resize: {}x{} @ {}x scale",
+        width, height, scale_factor
+    );
+
+    // Negative sizes become 0, which strict validation rejects
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    let _ = ffi::resize(&mut app, width, height, scale_factor);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeReplaceSurface(
+    env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    surface: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+    presentation: jint,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+    if surface.is_null() {
+        error!("Host did not provide a valid surface");
+        return;
+    }
+
+    let native_window_ptr =
+        unsafe { ndk_sys::ANativeWindow_fromSurface(env.get_raw(), surface.as_raw()) };
+    let Some(handle) = native_window_handle(native_window_ptr as *mut c_void) else {
+        error!("Failed to get native window from surface");
+        return;
+    };
+
+    debug!(
+        "Android replace surface: {}x{} @ {}x scale",
+        width, height, scale_factor
+    );
+
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    let presentation = u8::try_from(presentation).unwrap_or(u8::MAX);
+    let _ = ffi::replace_surface(&mut app, handle, width, height, scale_factor, presentation);
+}
+
+/// Asks the app to close its window before it is destroyed, returning false if it has none
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCloseWindow(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jboolean {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    ffi::close_window(&mut app) as jboolean
+}
+
+/// Whether the app's window is closed
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeIsWindowClosed(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jboolean {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    ffi::window_closed(&mut app) as jboolean
+}
+
+/// Opens a new window on a surface after the app's window was closed, returning false if
+/// the app still has a window or the surface is invalid
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeOpenWindow(
+    env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    surface: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+) -> jboolean {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    if surface.is_null() {
+        error!("Host did not provide a valid surface");
+        return 0;
+    }
+
+    let native_window_ptr =
+        unsafe { ndk_sys::ANativeWindow_fromSurface(env.get_raw(), surface.as_raw()) };
+    let Some(handle) = native_window_handle(native_window_ptr as *mut c_void) else {
+        error!("Failed to get native window from surface");
+        return 0;
+    };
+
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    ffi::open_window(&mut app, handle, width, height, scale_factor).is_ok() as jboolean
+}
+
+/// Sets how many apps to keep preloaded for surfaces of the given size
+///
+/// The preloaded apps are created with a copy of `config` (a config from
+/// `nativeNewConfig`, or 0).
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetPreloadTarget(
+    _env: JNIEnv,
+    _class: JClass,
+    count: jint,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+    config: jlong,
+) {
+    let count = u32::try_from(count).unwrap_or(0);
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    let config = unsafe { ffi::host_config(config as *const HostConfig) };
+    let _ = ffi::set_preload_target(count, width, height, scale_factor, config);
+}
+
+/// Creates or destroys one app of the preload pool; returns 1 if there was work to do
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativePreloadStep(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jint {
+    unsafe extern "C" {
+        fn bevy_embedded_preload_step() -> u8;
+    }
+
+    unsafe { bevy_embedded_preload_step() as jint }
+}
+
+/// Destroys every app waiting in the preload pool
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeClearPreloaded(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    unsafe extern "C" {
+        fn bevy_embedded_clear_preloaded();
+    }
+
+    unsafe { bevy_embedded_clear_preloaded() }
+}
+
+/// Takes a preloaded app and attaches it to `surface`; returns 0 if none is preloaded
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeTakePreloadedApp(
+    env: JNIEnv,
+    _class: JClass,
+    surface: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+) -> jlong {
+    if surface.is_null() {
+        error!("Host did not provide a valid surface");
+        return 0;
+    }
+    let native_window_ptr =
+        unsafe { ndk_sys::ANativeWindow_fromSurface(env.get_raw(), surface.as_raw()) };
+    let Some(handle) = native_window_handle(native_window_ptr as *mut c_void) else {
+        error!("Failed to get native window from surface");
+        return 0;
+    };
+    let Some(app_ptr) = preload::take_preloaded() else {
+        return 0;
+    };
+
+    let app = unsafe { &mut *app_ptr };
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    if ffi::activate_preloaded(app, handle, width, height, scale_factor).is_err() {
+        preload::push_preloaded(app_ptr);
+        return 0;
+    }
+    app_ptr as jlong
+}
+
+/// Starts creating the app on a worker thread and returns its pending handle, or 0
+///
+/// Poll `nativeAsyncAppStatus` until it is ready, then take the app with
+/// `nativeTakeAsyncApp`.
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCreateAppAsync(
+    mut env: JNIEnv,
+    _class: JClass,
+    activity: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+    config: jlong,
+) -> jlong {
+    if !init_activity_context(&mut env, &activity) {
+        return 0;
+    }
+
+    unsafe extern "C" {
+        fn bevy_embedded_create_app_async(
+            config: *const HostConfig,
+            width: u32,
+            height: u32,
+            scale_factor: f32,
+            on_ready: Option<pending::ReadyCallback>,
+            user_data: *mut c_void,
+        ) -> *const pending::PendingApp;
+    }
+
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    unsafe {
+        bevy_embedded_create_app_async(
+            config as *const HostConfig,
+            width,
+            height,
+            scale_factor,
+            None,
+            std::ptr::null_mut(),
+        ) as jlong
+    }
+}
+
+/// Progress of an app from `nativeCreateAppAsync`: 0 initializing, 1 ready, 2 taken
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeAsyncAppStatus(
+    _env: JNIEnv,
+    _class: JClass,
+    pending_ptr: jlong,
+) -> jint {
+    unsafe extern "C" {
+        fn bevy_embedded_pending_app_status(pending: *const pending::PendingApp) -> u8;
+    }
+
+    unsafe { bevy_embedded_pending_app_status(pending_ptr as *const pending::PendingApp) as jint }
+}
+
+/// Takes the app from `nativeCreateAppAsync` once it is ready and attaches it to
+/// `surface`; returns 0 if it is not ready or the surface could not be attached
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeTakeAsyncApp(
+    env: JNIEnv,
+    _class: JClass,
+    pending_ptr: jlong,
+    surface: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+) -> jlong {
+    let pending = pending_ptr as *const pending::PendingApp;
+    if pending.is_null() || surface.is_null() {
+        error!("Host did not provide a pending app and a valid surface");
+        return 0;
+    }
+    let native_window_ptr =
+        unsafe { ndk_sys::ANativeWindow_fromSurface(env.get_raw(), surface.as_raw()) };
+    let Some(handle) = native_window_handle(native_window_ptr as *mut c_void) else {
+        error!("Failed to get native window from surface");
+        return 0;
+    };
+    let Some(app_ptr) = (unsafe { pending::take(pending) }) else {
+        return 0;
+    };
+
+    let app = unsafe { &mut *app_ptr };
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    if ffi::activate_preloaded(app, handle, width, height, scale_factor).is_err() {
+        unsafe { pending::restore(pending, app_ptr) };
+        return 0;
+    }
+    app_ptr as jlong
+}
+
+/// Releases a handle from `nativeCreateAppAsync`, destroying the app unless it was taken
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeReleaseAsyncApp(
+    _env: JNIEnv,
+    _class: JClass,
+    pending_ptr: jlong,
+) {
+    unsafe extern "C" {
+        fn bevy_embedded_release_pending_app(pending: *const pending::PendingApp);
+    }
+
+    unsafe { bevy_embedded_release_pending_app(pending_ptr as *const pending::PendingApp) }
+}
+
+/// Returns how far the app's plugins have initialized: 0 = building, 1 = waiting for a
+/// surface, 2 = ready to finish in the next update, 3 = finished
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeInitProgress(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jint {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return InitProgress::Building as jint;
+    };
+    ffi::init_progress(&mut app) as jint
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeAttachExternalDisplay(
+    env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    surface: JObject,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+) -> jint {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    if surface.is_null() {
+        error!("Host did not provide a valid surface");
+        return 0;
+    }
+
+    let native_window_ptr =
+        unsafe { ndk_sys::ANativeWindow_fromSurface(env.get_raw(), surface.as_raw()) };
+    let Some(handle) = native_window_handle(native_window_ptr as *mut c_void) else {
+        error!("Failed to get native window from surface");
+        return 0;
+    };
+
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    ffi::attach_external_display(&mut app, handle, width, height, scale_factor)
+        .map_or(0, |id| id as jint)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeResizeExternalDisplay(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    display_id: jint,
+    width: jint,
+    height: jint,
+    scale_factor: jfloat,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let width = u32::try_from(width).unwrap_or(0);
+    let height = u32::try_from(height).unwrap_or(0);
+    let _ = ffi::resize_external_display(&mut app, display_id as u32, width, height, scale_factor);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeDetachExternalDisplay(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    display_id: jint,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::detach_external_display(&mut app, display_id as u32);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetLifecycle(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    state: jint,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let state = u8::try_from(state).unwrap_or(u8::MAX);
+    let _ = ffi::set_lifecycle(&mut app, state);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetAccessibility(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    reduce_motion: jboolean,
+    bold_text: jboolean,
+    font_scale: jfloat,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::set_accessibility(&mut app, reduce_motion != 0, bold_text != 0, font_scale);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetSafeArea(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    top: jfloat,
+    left: jfloat,
+    bottom: jfloat,
+    right: jfloat,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::set_safe_area(&mut app, top, left, bottom, right);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSyncClock(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    host_time: jdouble,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::sync_clock(&mut app, host_time);
+}
+
+/// Turns touch latency measurement on or off
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetLatencyMeasurement(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    enabled: jboolean,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    ffi::set_latency_measurement(&mut app, enabled != 0);
+}
+
+/// Reports the event time of the touch sent last, for latency measurement
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeInputTimestamp(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    host_time: jdouble,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::input_timestamp(&mut app, host_time);
+}
+
+/// Reports that the oldest unreported frame was presented, for latency measurement
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeFramePresented(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    host_time: jdouble,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::frame_presented(&mut app, host_time);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetSurfaceTransform(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    rotation: jint,
+    mirror: jboolean,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let rotation = u8::try_from(rotation).unwrap_or(u8::MAX);
+    let _ = ffi::set_surface_transform(&mut app, rotation, mirror != 0);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetCameraView(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    view: JFloatArray,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let mut matrix = [0.0; 16];
+    let len = env.get_array_length(&view);
+    if jni_checked(&mut env, "Failed to read the camera view", len) != Some(16) {
+        error!("Camera view must be a 16 element float array");
+        return;
+    }
+    let read = env.get_float_array_region(&view, 0, &mut matrix);
+    if jni_checked(&mut env, "Failed to read the camera view", read).is_none() {
+        return;
+    }
+    let _ = ffi::set_camera_view(&mut app, matrix);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetDynamicResolution(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    enabled: jboolean,
+    min_scale: jfloat,
+    max_scale: jfloat,
+    target_fps: jfloat,
+) {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return;
+    };
+
+    let _ = ffi::set_dynamic_resolution(&mut app, enabled != 0, min_scale, max_scale, target_fps);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSharedState(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jobject {
+    let Ok(block) = ffi::shared_state(app_ptr as *const c_void) else {
+        return JObject::null().into_raw();
+    };
+
+    // The block lives until the app is destroyed, as documented for the Kotlin side
+    let len = crate::SHARED_STATE_LEN * std::mem::size_of::<f32>();
+    let buffer = unsafe { env.new_direct_byte_buffer(block as *mut u8, len) };
+    jni_checked(&mut env, "Failed to wrap shared state block", buffer)
+        .map_or_else(|| JObject::null().into_raw(), |buffer| buffer.into_raw())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeRingSend(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    topic: jint,
+    buffer: JByteBuffer,
+    length: jint,
+) -> jboolean {
+    let Some((address, capacity)) = direct_buffer(
+        &mut env,
+        &buffer,
+        "nativeRingSend needs a direct ByteBuffer",
+    ) else {
+        return 0;
+    };
+    let Ok(len) = usize::try_from(length) else {
+        return 0;
+    };
+    if len > capacity {
+        return 0;
+    }
+
+    // Ring FFI goes through the instance registry like nativeSendMessage
+    let message = unsafe { std::slice::from_raw_parts(address, len) };
+    ffi::ring_send(app_ptr as *const c_void, topic as u16, message).unwrap_or(false) as jboolean
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeRingReceive(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    topic: jint,
+    buffer: JByteBuffer,
+) -> jint {
+    let Some((address, capacity)) = direct_buffer(
+        &mut env,
+        &buffer,
+        "nativeRingReceive needs a direct ByteBuffer",
+    ) else {
+        return 0;
+    };
+
+    let len = unsafe {
+        ffi::ring_receive_into(app_ptr as *const c_void, topic as u16, address, capacity)
+    };
+    len.unwrap_or(0) as jint
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativePushAudioInput(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    buffer: JByteBuffer,
+    sample_count: jint,
+    channels: jint,
+    sample_rate: jint,
+    timestamp: jdouble,
+) -> jboolean {
+    let Some((address, capacity)) = direct_buffer(
+        &mut env,
+        &buffer,
+        "nativePushAudioInput needs a direct ByteBuffer",
+    ) else {
+        return 0;
+    };
+    let Ok(sample_count) = usize::try_from(sample_count) else {
+        return 0;
+    };
+    if sample_count * size_of::<f32>() > capacity || !address.cast::<f32>().is_aligned() {
+        return 0;
+    }
+
+    // Goes through the instance registry and never allocates, so AudioRecord's reader
+    // thread can call it directly
+    let samples = unsafe { std::slice::from_raw_parts(address.cast::<f32>(), sample_count) };
+    ffi::push_audio_input(
+        app_ptr as *const c_void,
+        timestamp,
+        sample_rate as u32,
+        channels as u16,
+        samples,
+    )
+    .unwrap_or(false) as jboolean
+}
+
+#[cfg(feature = "host_audio")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeRenderAudio(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    buffer: JByteBuffer,
+    sample_count: jint,
+    channels: jint,
+    sample_rate: jint,
+) -> jboolean {
+    let Some((address, capacity)) = direct_buffer(
+        &mut env,
+        &buffer,
+        "nativeRenderAudio needs a direct ByteBuffer",
+    ) else {
+        return 0;
+    };
+    let Ok(sample_count) = usize::try_from(sample_count) else {
+        return 0;
+    };
+    if sample_count * size_of::<f32>() > capacity || !address.cast::<f32>().is_aligned() {
+        return 0;
+    }
+
+    // Called from the audio render thread; goes through the instance registry
+    let out = unsafe { std::slice::from_raw_parts_mut(address.cast::<f32>(), sample_count) };
+    ffi::render_audio(
+        app_ptr as *const c_void,
+        sample_rate as u32,
+        channels as u16,
+        out,
+    )
+    .is_ok() as jboolean
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSendMessage(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    data: JByteArray,
+) {
+    // Message FFI goes through the instance registry and never dereferences app_ptr,
+    // so it is safe against a concurrent nativeDestroy
+
+    // Convert Java byte array to Rust Vec<u8>
+    let bytes = env.convert_byte_array(data);
+    let Some(bytes) = jni_checked(&mut env, "Failed to convert byte array", bytes) else {
+        return;
+    };
+
+    let _ = ffi::send_message(app_ptr as *const c_void, bytes);
+}
+
+/// Sends `length` bytes from position 0 of a direct `ByteBuffer`, so hosts sending at a
+/// high rate can reuse one buffer instead of allocating a byte array per message
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSendMessageBuffer(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    buffer: JByteBuffer,
+    length: jint,
+) -> jboolean {
+    let Some((address, capacity)) = direct_buffer(
+        &mut env,
+        &buffer,
+        "nativeSendMessageBuffer needs a direct ByteBuffer",
+    ) else {
+        return 0;
+    };
+    let Ok(len) = usize::try_from(length) else {
+        return 0;
+    };
+    if len > capacity {
+        return 0;
+    }
+
+    let message = unsafe { std::slice::from_raw_parts(address, len) };
+    ffi::send_message(app_ptr as *const c_void, message.to_vec()).is_ok() as jboolean
+}
+
+/// Writes the regions that changed in the last update into `rects` as `x, y, width,
+/// height` in physical pixels, returning the number of rects, 0 if nothing changed, or -1
+/// if the whole frame must be recomposited
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeFrameDamage(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    rects: JIntArray,
+) -> jint {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return -1;
+    };
+    let capacity = if rects.is_null() {
+        0
+    } else {
+        let len = env.get_array_length(&rects);
+        jni_checked(&mut env, "Failed to read damage rects", len)
+            .unwrap_or(0)
+            .max(0) as usize
+    };
+    let mut out = vec![0u32; capacity - capacity % 4];
+    let count = ffi::write_frame_damage(&mut app, &mut out);
+    if count > 0 {
+        let values: Vec<jint> = out[..count as usize * 4]
+            .iter()
+            .map(|&v| v as jint)
+            .collect();
+        let written = env.set_int_array_region(&rects, 0, &values);
+        if jni_checked(&mut env, "Failed to write damage rects", written).is_none() {
+            return -1;
+        }
+    }
+    count
+}
+
+/// Returns the GPU time of the most recently measured frame in milliseconds, or -1 if
+/// the app doesn't measure it or the device has no timestamp queries
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeGpuFrameTimeMs(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jdouble {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return -1.0;
+    };
+    ffi::gpu_frame_time(&app).map_or(-1.0, |time| time.as_secs_f64() * 1000.0)
+}
+
+/// Writes when a startup phase started and how long it took, in milliseconds, to `out`;
+/// returns false if the phase hasn't ended or is unknown. See `StartupTimings`.
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeStartupPhase(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    phase: jint,
+    out: JDoubleArray,
+) -> jboolean {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    let Ok(Some(timing)) = ffi::startup_phase(&app, phase as u8) else {
+        return 0;
+    };
+    let values = [
+        timing.start.as_secs_f64() * 1000.0,
+        timing.duration.as_secs_f64() * 1000.0,
+    ];
+    if out.is_null() {
+        return 0;
+    }
+    let written = env.set_double_array_region(&out, 0, &values);
+    jni_checked(&mut env, "Failed to write startup phase", written).is_some() as jboolean
+}
+
+/// Returns the GPU adapter and limits the app renders with as JSON, or null if it
+/// doesn't render; see `GpuReport`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeGpuReport<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass,
+    app_ptr: jlong,
+) -> JObject<'local> {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return JObject::null();
+    };
+    let Some(report) = ffi::gpu_report(&app) else {
+        return JObject::null();
+    };
+    let string = env.new_string(report);
+    jni_checked(&mut env, "Failed to return the GPU report", string)
+        .map_or_else(JObject::default, JObject::from)
+}
+
+/// Returns the error that froze the app, or null while it is running; see
+/// `RecoveryPolicy`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeFrozenError<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass,
+    app_ptr: jlong,
+) -> JObject<'local> {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return JObject::null();
+    };
+    let Some(error) = ffi::frozen_error(&app) else {
+        return JObject::null();
+    };
+    let string = env.new_string(error);
+    jni_checked(&mut env, "Failed to return the frozen error", string)
+        .map_or_else(JObject::default, JObject::from)
+}
+
+/// Sets the image presented when the app freezes without a frame it can render, as
+/// `width * height` RGBA8 sRGB pixels, or clears it with null `pixels`; returns false if
+/// the pixels don't match the dimensions. See `frozen_frame`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetFallbackFrame(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    pixels: JByteArray,
+    width: jint,
+    height: jint,
+) -> jboolean {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    let pixels = if pixels.is_null() {
+        None
+    } else {
+        let pixels = env.convert_byte_array(pixels);
+        let Some(pixels) = jni_checked(&mut env, "Failed to convert fallback frame", pixels) else {
+            return 0;
+        };
+        Some(pixels)
+    };
+    ffi::set_fallback_frame(&mut app, pixels.as_deref(), width as u32, height as u32).is_ok()
+        as jboolean
+}
+
+/// Releases the GPU memory of dropped render resources, e.g. from `onTrimMemory`,
+/// returning false if the app has no render device; see `RenderGcPlugin`
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeFlushRenderResources(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jboolean {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    ffi::flush_render_resources(&mut app) as jboolean
+}
+
+/// Captures the next frame the app renders in RenderDoc, returning false if the app
+/// doesn't render; see `GpuCapture`
+#[cfg(feature = "gpu_capture")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCaptureGpuFrame(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jboolean {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    ffi::capture_gpu_frame(app) as jboolean
+}
+
+/// Starts recording a Chrome trace of every app's schedules and systems into a new file
+/// at `path`, returning false if a trace is already recording or the file can't be created
+#[cfg(feature = "chrome_trace")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeStartChromeTrace(
+    mut env: JNIEnv,
+    _class: JClass,
+    path: JString,
+) -> jboolean {
+    let path = env.get_string(&path).map(String::from);
+    let Some(path) = jni_checked(&mut env, "Invalid Chrome trace path", path) else {
+        return 0;
+    };
+    ffi::start_chrome_trace(&path) as jboolean
+}
+
+/// Stops recording the Chrome trace and completes its file, returning false if no trace
+/// was recording or the file couldn't be written
+#[cfg(feature = "chrome_trace")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeStopChromeTrace(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jboolean {
+    ffi::stop_chrome_trace() as jboolean
+}
+
+/// Switches a debug renderer on or off, returning false if the app doesn't have it; see
+/// `DebugToggle`
+#[cfg(feature = "debug_toggles")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSetDebugToggle(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    toggle: jint,
+    enabled: jboolean,
+) -> jboolean {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    let Ok(toggle) = u8::try_from(toggle) else {
+        return 0;
+    };
+    ffi::set_debug_toggle(app, toggle, enabled != 0).unwrap_or(false) as jboolean
+}
+
+/// Returns 1 if a debug renderer is on, 0 if off, or -1 if the app doesn't have it
+#[cfg(feature = "debug_toggles")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeDebugToggle(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    toggle: jint,
+) -> jint {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return -1;
+    };
+    let Ok(toggle) = u8::try_from(toggle) else {
+        return -1;
+    };
+    match ffi::debug_toggle(app, toggle) {
+        Ok(Some(enabled)) => enabled as jint,
+        _ => -1,
+    }
+}
+
+/// Sends the host's protocol version, message schema hash and comma-separated codecs
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSendHandshake(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    version: jint,
+    schema_hash: jlong,
+    codecs: JString,
+) {
+    let codecs = if codecs.is_null() {
+        String::new()
+    } else {
+        let codecs = env.get_string(&codecs).map(String::from);
+        let Some(codecs) = jni_checked(&mut env, "Invalid handshake codecs", codecs) else {
+            return;
+        };
+        codecs
+    };
+    let version = u16::try_from(version).unwrap_or(u16::MAX);
+    let _ = ffi::send_handshake(
+        app_ptr as *const c_void,
+        version,
+        schema_hash as u64,
+        &codecs,
+    );
+}
+
+/// Returns the state of the protocol handshake: 0 = pending, 1 = compatible,
+/// 2 = mismatched
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeHandshakeStatus(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jint {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return HandshakeStatus::Pending as jint;
+    };
+    ffi::handshake_status(&app) as jint
+}
+
+/// Returns why the protocol handshake failed, or null if it hasn't
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeHandshakeError<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass,
+    app_ptr: jlong,
+) -> JObject<'local> {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return JObject::null();
+    };
+    let Some(error) = ffi::handshake_error(&app) else {
+        return JObject::null();
+    };
+    let string = env.new_string(error);
+    jni_checked(&mut env, "Failed to return the handshake error", string)
+        .map_or_else(JObject::default, JObject::from)
+}
+
+/// Submits a job of `kind`, returning the token its JobResult message carries, or 0 on
+/// failure
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSubmitJob(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    kind: JString,
+    input: JByteArray,
+) -> jlong {
+    let kind = env.get_string(&kind).map(String::from);
+    let Some(kind) = jni_checked(&mut env, "Invalid job kind", kind) else {
+        return 0;
+    };
+    let input = env.convert_byte_array(input);
+    let Some(input) = jni_checked(&mut env, "Failed to convert job input", input) else {
+        return 0;
+    };
+    ffi::submit_job(app_ptr as *const c_void, &kind, &input).unwrap_or(0) as jlong
+}
+
+/// Cancels the job with `token`; a Canceled result follows unless it already finished
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeCancelJob(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    token: jlong,
+) {
+    let _ = ffi::cancel_job(app_ptr as *const c_void, token as u64);
+}
+
+/// Starts recording host input for replay, discarding a recording in progress
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeStartRecording(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app_ptr as *mut c_void) } {
+        replay::start_recording(&mut app);
+    }
+}
+
+/// Stops recording and returns the log, or null if the app wasn't recording
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeStopRecording(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jbyteArray {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let Some(log) = replay::stop_recording(&mut app) else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let array = env.byte_array_from_slice(&log.encode());
+    jni_checked(&mut env, "Failed to create replay log array", array).map_or_else(
+        || JObject::null().into_raw() as jbyteArray,
+        |array| array.into_raw(),
+    )
+}
+
+/// Replays a recorded log, returning the number of frames run or -1 if it is malformed
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeReplay(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    log: JByteArray,
+) -> jint {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return -1;
+    };
+    let log = env.convert_byte_array(log);
+    let Some(log) = jni_checked(&mut env, "Failed to convert replay log", log) else {
+        return -1;
+    };
+    ffi::replay(&mut app, &log).map_or(-1, |frames| frames as jint)
+}
+
+/// Saves the app's state and destroys the app, returning the state or null on failure
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeHibernate(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jbyteArray {
+    // The borrow ends before the app is destroyed
+    let Ok(state) =
+        (unsafe { ffi::app_mut(app_ptr as *mut c_void) }).map(|mut app| ffi::hibernate(&mut app))
+    else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let array = env.byte_array_from_slice(&state);
+    // The app stays alive on failure, so the host can destroy it as usual
+    let Some(array) = jni_checked(&mut env, "Failed to create hibernated state array", array)
+    else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+
+    unsafe extern "C" {
+        fn bevy_embedded_destroy(app: *mut App);
+    }
+
+    unsafe { bevy_embedded_destroy(app_ptr as *mut App) };
+    array.into_raw()
+}
+
+/// Restores state from `nativeHibernate` in the app's next update, returning false if it
+/// is malformed
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeRehydrate(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    state: JByteArray,
+) -> jboolean {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    let state = env.convert_byte_array(state);
+    let Some(state) = jni_checked(&mut env, "Failed to convert hibernated state", state) else {
+        return 0;
+    };
+    ffi::rehydrate(&mut app, &state).is_ok() as jboolean
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeReceiveMessage(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+) -> jbyteArray {
+    if let Ok(Some(message)) = ffi::receive_message(app_ptr as *const c_void) {
+        // Convert Rust Vec<u8> to Java byte array
+        let array = env.byte_array_from_slice(&message);
+        if let Some(array) = jni_checked(&mut env, "Failed to create byte array", array) {
+            return array.into_raw();
+        }
+    }
+
+    JObject::null().into_raw() as jbyteArray
+}
+
+/// Copies the next message into a direct `ByteBuffer` from position 0, returning its
+/// length, or 0 if none is waiting; a message longer than the buffer's capacity is not
+/// copied and stays queued
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeReceiveMessageInto(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    buffer: JByteBuffer,
+) -> jint {
+    let Some((address, capacity)) = direct_buffer(
+        &mut env,
+        &buffer,
+        "nativeReceiveMessageInto needs a direct ByteBuffer",
+    ) else {
+        return 0;
+    };
+
+    let len =
+        unsafe { ffi::receive_whole_message_into(app_ptr as *const c_void, address, capacity) };
+    len.map_or(0, |len| len.min(jint::MAX as usize) as jint)
+}
+
+/// Copies up to `max_messages` messages into a direct `ByteBuffer` from position 0, each
+/// framed as a little-endian `u32` length and its bytes, returning the bytes written, or 0
+/// if none is waiting; if not even the first message fits, nothing is copied and the
+/// returned length exceeds the buffer's capacity
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeReceiveMessages(
+    mut env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    buffer: JByteBuffer,
+    max_messages: jint,
+) -> jint {
+    let Some((address, capacity)) = direct_buffer(
+        &mut env,
+        &buffer,
+        "nativeReceiveMessages needs a direct ByteBuffer",
+    ) else {
+        return 0;
+    };
+    let Ok(max_messages) = usize::try_from(max_messages) else {
+        return 0;
+    };
+
+    let len = unsafe {
+        ffi::receive_messages_into(app_ptr as *const c_void, address, capacity, max_messages)
+    };
+    len.map_or(0, |len| len.min(jint::MAX as usize) as jint)
+}
+
+/// Seals a message with the app's channel key before nativeSendMessage; returns null if
+/// the key is not 32 bytes
+#[cfg(feature = "channel_encryption")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeSealMessage(
+    mut env: JNIEnv,
+    _class: JClass,
+    key: JByteArray,
+    data: JByteArray,
+) -> jbyteArray {
+    let key = env.convert_byte_array(key);
+    let Some(key) = jni_checked(&mut env, "Failed to convert channel key", key) else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let data = env.convert_byte_array(data);
+    let Some(data) = jni_checked(&mut env, "Failed to convert message", data) else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let Ok(sealed) = ffi::seal_message(&key, &data) else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let array = env.byte_array_from_slice(&sealed);
+    jni_checked(&mut env, "Failed to create sealed message array", array).map_or_else(
+        || JObject::null().into_raw() as jbyteArray,
+        |array| array.into_raw(),
+    )
+}
+
+/// Opens a message from nativeReceiveMessage with the app's channel key; returns null if
+/// it failed to authenticate
+#[cfg(feature = "channel_encryption")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeOpenMessage(
+    mut env: JNIEnv,
+    _class: JClass,
+    key: JByteArray,
+    sealed: JByteArray,
+) -> jbyteArray {
+    let key = env.convert_byte_array(key);
+    let Some(key) = jni_checked(&mut env, "Failed to convert channel key", key) else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let sealed = env.convert_byte_array(sealed);
+    let Some(sealed) = jni_checked(&mut env, "Failed to convert sealed message", sealed) else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let Ok(message) = ffi::open_message(&key, &sealed) else {
+        return JObject::null().into_raw() as jbyteArray;
+    };
+    let array = env.byte_array_from_slice(&message);
+    jni_checked(&mut env, "Failed to create opened message array", array).map_or_else(
+        || JObject::null().into_raw() as jbyteArray,
+        |array| array.into_raw(),
+    )
+}
+
+/// Returns the oldest lifecycle telemetry event of any app, or null if none is queued
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeReceiveTelemetry(
+    mut env: JNIEnv,
+    _class: JClass,
+) -> jbyteArray {
+    if let Some(event) = crate::telemetry::receive_telemetry() {
+        let array = env.byte_array_from_slice(&event);
+        if let Some(array) = jni_checked(&mut env, "Failed to create byte array", array) {
+            return array.into_raw();
+        }
+    }
 
     JObject::null().into_raw() as jbyteArray
 }
+
+/// Render apps created from now on with the host's `VkDevice` instead of a device of
+/// their own
+///
+/// For native (NDK) engines that already render with Vulkan; call it before creating
+/// apps whose `RenderPlugin` uses the host device (see
+/// `HostDevicePlugins::with_host_device`). Returns false if the device cannot be used;
+/// the reason is available from `bevy_embedded_get_last_error`.
+///
+/// # Safety
+///
+/// `host` must be null or point to a valid [`HostVulkanDevice`](crate::HostVulkanDevice)
+/// whose handles outlive every app rendering with them.
+#[cfg(feature = "gpu_interop")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_android_set_host_device(
+    host: *const crate::HostVulkanDevice,
+) -> bool {
+    let Some(host) = (unsafe { host.as_ref() }) else {
+        error!("Null host device pointer");
+        crate::store_error("Null host device pointer".to_string());
+        return false;
+    };
+    match unsafe { crate::set_host_vulkan_device(host) } {
+        Ok(()) => true,
+        Err(e) => {
+            error!("{e}");
+            crate::store_error(e.to_string());
+            false
+        }
+    }
+}
+
+/// The timeline `VkSemaphore` Bevy signals when a frame is done, or 0 if the app does not
+/// render with Vulkan
+///
+/// The semaphore belongs to Bevy's `VkDevice`, so only hosts sharing their device (see
+/// `bevy_embedded_android_set_host_device`) can wait on it in their own submissions. It
+/// is valid until the app is destroyed.
+///
+/// # Safety
+///
+/// `app` must be a valid pointer to the App
+#[cfg(feature = "gpu_interop")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_android_frame_fence_semaphore(app: *mut c_void) -> u64 {
+    use ash::vk::Handle;
+
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return 0;
+    };
+    ffi::frame_fence(app).map_or(0, |fence| fence.semaphore().as_raw())
+}
+
+/// The value the frame fence reaches once the latest updated frame is done
+///
+/// # Safety
+///
+/// `app` must be a valid pointer to the App
+#[cfg(feature = "gpu_interop")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_android_frame_fence_value(app: *mut c_void) -> u64 {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return 0;
+    };
+    ffi::frame_fence(app).map_or(0, |fence| fence.value())
+}
+
+/// Block until the frame fence reaches `value`, for at most `timeout_ms` milliseconds
+///
+/// Returns false on timeout or if the app has no fence.
+///
+/// # Safety
+///
+/// `app` must be a valid pointer to the App
+#[cfg(feature = "gpu_interop")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_android_wait_frame_fence(
+    app: *mut c_void,
+    value: u64,
+    timeout_ms: u32,
+) -> bool {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    ffi::frame_fence(app)
+        .is_ok_and(|fence| fence.wait(value, std::time::Duration::from_millis(timeout_ms.into())))
+}
+
+/// Block until the latest updated frame is done on the GPU, for at most `timeout_ms`
+/// milliseconds, before compositing a shared buffer Bevy rendered into
+#[cfg(feature = "gpu_interop")]
+#[unsafe(no_mangle)]
+pub extern "C" fn Java_com_example_bevyembedded_BevyNative_nativeWaitFrameFence(
+    _env: JNIEnv,
+    _class: JClass,
+    app_ptr: jlong,
+    timeout_ms: jint,
+) -> jboolean {
+    let Ok(app) = (unsafe { ffi::app_mut(app_ptr as *mut c_void) }) else {
+        return 0;
+    };
+    let timeout = std::time::Duration::from_millis(timeout_ms.max(0) as u64);
+    ffi::frame_fence(app).is_ok_and(|fence| fence.wait(fence.value(), timeout)) as jboolean
+}
+
+/// Render the next frames into a `VkImage` Bevy allocates, returning the image, or 0 if
+/// the settings are invalid or the app does not render with Vulkan
+///
+/// `format` is an `OffscreenFormat` (0 RGBA8, 1 BGRA8, 2 RGB10A2, 3 RGBA16Float), and
+/// `srgb` selects sRGB encoding for the 8-bit formats. `mip_levels` of 0 allocates a full
+/// chain; Bevy renders level 0 only. Call it again to resize or change the format; the
+/// previous image must not be used afterwards. The image belongs to Bevy's `VkDevice`
+/// and is valid until the next call or until the app is destroyed.
+///
+/// # Safety
+///
+/// `app` must be a valid pointer to the App
+#[cfg(feature = "gpu_interop")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_android_set_offscreen_target(
+    app: *mut c_void,
+    width: u32,
+    height: u32,
+    format: u8,
+    srgb: bool,
+    mip_levels: u32,
+    scale_factor: f32,
+) -> u64 {
+    use ash::vk::Handle;
+
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return 0;
+    };
+    let Ok(texture) =
+        ffi::set_offscreen_target(app, width, height, format, srgb, mip_levels, scale_factor)
+    else {
+        return 0;
+    };
+    match unsafe { texture.as_hal::<wgpu::hal::api::Vulkan>() } {
+        Some(hal_texture) => unsafe { hal_texture.raw_handle() }.as_raw(),
+        None => 0,
+    }
+}
+
+/// A `VkImage` of the host that Bevy renders host frames into
+///
+/// The image must be 2D with one mip level and one sample, created on the device shared
+/// with `bevy_embedded_android_set_host_device` with color attachment and sampled usage.
+#[cfg(feature = "gpu_interop")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct HostVulkanImage {
+    /// `VkImage`
+    pub image: u64,
+    /// An `OffscreenFormat` (0 RGBA8, 1 BGRA8, 2 RGB10A2, 3 RGBA16Float)
+    pub format: u8,
+    /// Whether an 8-bit format is sRGB encoded
+    pub srgb: bool,
+    /// Width of the image in pixels
+    pub width: u32,
+    /// Height of the image in pixels
+    pub height: u32,
+}
+
+/// Render the next frames into the host's `VkImage` at a viewport, for native engines
+/// that encode the frame themselves
+///
+/// Call it before each `bevy_embedded_update`, then make the host's submission that reads
+/// the image wait on the frame fence (`bevy_embedded_android_frame_fence_semaphore`) at
+/// `bevy_embedded_android_frame_fence_value`. Returns false if the image is invalid or the
+/// app does not render with Vulkan.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `image` must be null or point to a valid [`HostVulkanImage`], whose image stays alive
+///   until the frame fence passes the frames rendered into it
+#[cfg(feature = "gpu_interop")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_android_set_host_frame(
+    app: *mut c_void,
+    image: *const HostVulkanImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> bool {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    let Some(image) = (unsafe { image.as_ref() }).filter(|image| image.image != 0) else {
+        error!("Host did not provide a valid frame image");
+        return false;
+    };
+    let Some(format) = crate::OffscreenFormat::from_u8(image.format)
+        .and_then(|format| format.texture_format(image.srgb))
+    else {
+        error!(
+            "Unsupported host frame format {} (sRGB: {})",
+            image.format, image.srgb
+        );
+        return false;
+    };
+    let Ok(device) = ffi::render_device(app) else {
+        return false;
+    };
+    let Some(texture) =
+        (unsafe { wrap_image(&device, image.image, format, image.width, image.height) })
+    else {
+        error!("Host frames need Bevy to render with Vulkan");
+        return false;
+    };
+    let viewport = bevy::math::URect::new(x, y, x.saturating_add(width), y.saturating_add(height));
+    ffi::set_host_frame(app, &texture, viewport, scale_factor).is_ok()
+}
+
+/// Wraps a host `VkImage` as a texture of Bevy's device
+///
+/// # Safety
+///
+/// `image` must be a valid 2D `VkImage` of `format` and `width`x`height` with one mip
+/// level and one sample, created on Bevy's `VkDevice` with color attachment and sampled
+/// usage, that outlives the texture.
+#[cfg(feature = "gpu_interop")]
+unsafe fn wrap_image(
+    device: &bevy::render::renderer::RenderDevice,
+    image: u64,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> Option<wgpu::Texture> {
+    use ash::vk::Handle;
+    use wgpu::hal::api::Vulkan;
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let hal_texture = {
+        let hal_device = unsafe { device.wgpu_device().as_hal::<Vulkan>() }?;
+        unsafe {
+            hal_device.texture_from_raw(
+                ash::vk::Image::from_raw(image),
+                &wgpu::hal::TextureDescriptor {
+                    label: Some("host_frame"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUses::COLOR_TARGET | wgpu::TextureUses::RESOURCE,
+                    memory_flags: wgpu::hal::MemoryFlags::empty(),
+                    view_formats: Vec::new(),
+                },
+                // The host owns the image
+                None,
+            )
+        }
+    };
+    let texture = unsafe {
+        device.wgpu_device().create_texture_from_hal::<Vulkan>(
+            hal_texture,
+            &wgpu::TextureDescriptor {
+                label: Some("host_frame"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        )
+    };
+    Some(texture)
+}
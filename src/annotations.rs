@@ -0,0 +1,372 @@
+//! Annotations drawn over the scene by the host
+//!
+//! Native tooling (measurement apps, inspectors, review tools) can mark up the 3D scene
+//! without Rust changes by sending [`ReservedTopic::Annotations`] commands. Each command
+//! payload is a sequence of operations, each starting with an [`AnnotationOp`] byte:
+//!
+//! ```text
+//! Line / Arrow: id: u32 | start: 3 × f32 | end: 3 × f32 | color: u32 RGBA
+//! Label:        id: u32 | position: 3 × f32 | color: u32 RGBA | text: u32 len + UTF-8
+//! Highlight:    id: u32 | entity: u64 (Entity::to_bits) | color: u32 RGBA
+//! Remove:       id: u32
+//! Clear
+//! ```
+//!
+//! Commands are handled once [`AnnotationsPlugin`] is added. Annotations persist until
+//! removed; adding one with an existing id replaces it.
+//! Positions are in world space and colors are packed as `0xRRGGBBAA`. The current set is
+//! kept in the [`Annotations`] resource. With the `annotations` cargo feature, lines,
+//! arrows and highlight boxes are drawn with gizmos (when the app has `GizmoPlugin`) and
+//! labels are bevy_ui text (when the app has `UiPlugin`), using the default font; apps can
+//! restyle label entities through their [`AnnotationLabel`] marker.
+
+use std::collections::BTreeMap;
+
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    color::{Color, Srgba},
+    ecs::{entity::Entity, message::MessageReader, resource::Resource, system::ResMut},
+    math::Vec3,
+};
+
+use crate::protocol::{ByteReader, HostCommand, ReservedTopic};
+
+/// Keeps and draws the annotations sent by the host; see the [module docs](self)
+#[derive(Default)]
+pub struct AnnotationsPlugin;
+
+impl Plugin for AnnotationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Annotations>()
+            .add_systems(PreUpdate, receive_annotations);
+    }
+
+    // Annotations are drawn only if the app renders gizmos and UI
+    #[cfg(feature = "annotations")]
+    fn finish(&self, app: &mut App) {
+        use bevy::app::PostUpdate;
+
+        if app.is_plugin_added::<bevy::gizmos::GizmoPlugin>() {
+            app.add_systems(PostUpdate, draw_annotations);
+        }
+        if app.is_plugin_added::<bevy::ui::UiPlugin>() {
+            app.add_systems(PostUpdate, sync_annotation_labels);
+        }
+    }
+}
+
+/// Operations that can appear in an annotations command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AnnotationOp {
+    /// Add or replace a line segment
+    Line = 0,
+    /// Add or replace an arrow
+    Arrow = 1,
+    /// Add or replace a text label
+    Label = 2,
+    /// Add or replace an outline around an entity's bounds
+    Highlight = 3,
+    /// Remove one annotation
+    Remove = 4,
+    /// Remove all annotations
+    Clear = 5,
+}
+
+impl AnnotationOp {
+    /// Create an AnnotationOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(AnnotationOp::Line),
+            1 => Some(AnnotationOp::Arrow),
+            2 => Some(AnnotationOp::Label),
+            3 => Some(AnnotationOp::Highlight),
+            4 => Some(AnnotationOp::Remove),
+            5 => Some(AnnotationOp::Clear),
+            _ => None,
+        }
+    }
+}
+
+/// A single annotation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    /// A line segment in world space
+    Line {
+        /// Start point
+        start: Vec3,
+        /// End point
+        end: Vec3,
+        /// Line color
+        color: Color,
+    },
+    /// An arrow in world space, pointing at `end`
+    Arrow {
+        /// Tail of the arrow
+        start: Vec3,
+        /// Tip of the arrow
+        end: Vec3,
+        /// Arrow color
+        color: Color,
+    },
+    /// A text label anchored at a world position
+    Label {
+        /// Anchor point
+        position: Vec3,
+        /// Label text
+        text: String,
+        /// Text color
+        color: Color,
+    },
+    /// An outline around an entity's bounding box
+    Highlight {
+        /// Entity to outline
+        entity: Entity,
+        /// Outline color
+        color: Color,
+    },
+}
+
+/// Annotations currently shown, by host-assigned id
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Annotations {
+    annotations: BTreeMap<u32, Annotation>,
+}
+
+impl Annotations {
+    /// Adds an annotation, replacing any with the same id
+    pub fn insert(&mut self, id: u32, annotation: Annotation) {
+        self.annotations.insert(id, annotation);
+    }
+
+    /// Removes an annotation
+    pub fn remove(&mut self, id: u32) -> Option<Annotation> {
+        self.annotations.remove(&id)
+    }
+
+    /// Removes all annotations
+    pub fn clear(&mut self) {
+        self.annotations.clear();
+    }
+
+    /// Gets an annotation by id
+    pub fn get(&self, id: u32) -> Option<&Annotation> {
+        self.annotations.get(&id)
+    }
+
+    /// Iterates over annotations in id order
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Annotation)> {
+        self.annotations
+            .iter()
+            .map(|(id, annotation)| (*id, annotation))
+    }
+
+    /// Returns true if there are no annotations
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+}
+
+fn read_vec3(reader: &mut ByteReader) -> Option<Vec3> {
+    Some(Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?))
+}
+
+fn read_color(reader: &mut ByteReader) -> Option<Color> {
+    let [r, g, b, a] = reader.u32()?.to_be_bytes();
+    Some(Srgba::rgba_u8(r, g, b, a).into())
+}
+
+/// Applies one operation, returning `None` if the payload is malformed
+fn apply_op(annotations: &mut Annotations, reader: &mut ByteReader) -> Option<()> {
+    let op = reader.u8()?;
+    let Some(op) = AnnotationOp::from_u8(op) else {
+        log::warn!("Ignoring annotations command with unknown operation {op}");
+        return None;
+    };
+    match op {
+        AnnotationOp::Line | AnnotationOp::Arrow => {
+            let id = reader.u32()?;
+            let (start, end, color) = (read_vec3(reader)?, read_vec3(reader)?, read_color(reader)?);
+            let annotation = if op == AnnotationOp::Line {
+                Annotation::Line { start, end, color }
+            } else {
+                Annotation::Arrow { start, end, color }
+            };
+            annotations.insert(id, annotation);
+        }
+        AnnotationOp::Label => {
+            let id = reader.u32()?;
+            let (position, color) = (read_vec3(reader)?, read_color(reader)?);
+            let text = reader.string()?.to_owned();
+            annotations.insert(
+                id,
+                Annotation::Label {
+                    position,
+                    text,
+                    color,
+                },
+            );
+        }
+        AnnotationOp::Highlight => {
+            let id = reader.u32()?;
+            let entity = Entity::try_from_bits(reader.u64()?)?;
+            let color = read_color(reader)?;
+            annotations.insert(id, Annotation::Highlight { entity, color });
+        }
+        AnnotationOp::Remove => {
+            annotations.remove(reader.u32()?);
+        }
+        AnnotationOp::Clear => annotations.clear(),
+    }
+    Some(())
+}
+
+/// Applies annotations commands from the host
+fn receive_annotations(
+    mut commands: MessageReader<HostCommand>,
+    mut annotations: ResMut<Annotations>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::Annotations {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while !reader.is_empty() {
+            if apply_op(&mut annotations, &mut reader).is_none() {
+                log::warn!("Ignoring malformed annotations command");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "annotations")]
+pub use draw::*;
+
+#[cfg(feature = "annotations")]
+mod draw {
+    use bevy::{
+        camera::{Camera, primitives::Aabb},
+        ecs::{
+            component::Component,
+            entity::Entity,
+            system::{Commands, Query, Res},
+        },
+        gizmos::gizmos::Gizmos,
+        math::Vec3,
+        text::{TextColor, TextFont},
+        transform::components::{GlobalTransform, Transform},
+        ui::{Display, Node, PositionType, Val, widget::Text},
+    };
+
+    use super::{Annotation, Annotations};
+
+    /// Marks the UI text entity showing a label annotation
+    #[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct AnnotationLabel(pub u32);
+
+    /// Draws line, arrow and highlight annotations with gizmos
+    pub(crate) fn draw_annotations(
+        mut gizmos: Gizmos,
+        annotations: Res<Annotations>,
+        bounds: Query<(&GlobalTransform, Option<&Aabb>)>,
+    ) {
+        for (_, annotation) in annotations.iter() {
+            match annotation {
+                Annotation::Line { start, end, color } => gizmos.line(*start, *end, *color),
+                Annotation::Arrow { start, end, color } => {
+                    gizmos.arrow(*start, *end, *color);
+                }
+                Annotation::Highlight { entity, color } => {
+                    let Ok((transform, aabb)) = bounds.get(*entity) else {
+                        continue;
+                    };
+                    let (center, size) = aabb.map_or((Vec3::ZERO, Vec3::ONE), |aabb| {
+                        (aabb.center.into(), Vec3::from(aabb.half_extents) * 2.0)
+                    });
+                    let local = Transform::from_translation(center).with_scale(size);
+                    gizmos.cuboid(transform.mul_transform(local), *color);
+                }
+                Annotation::Label { .. } => {}
+            }
+        }
+    }
+
+    /// Keeps one UI text entity per label annotation, positioned over its anchor
+    pub(crate) fn sync_annotation_labels(
+        mut commands: Commands,
+        annotations: Res<Annotations>,
+        cameras: Query<(&Camera, &GlobalTransform)>,
+        mut labels: Query<(
+            Entity,
+            &AnnotationLabel,
+            &mut Node,
+            &mut Text,
+            &mut TextColor,
+        )>,
+    ) {
+        // Labels are placed using the first active camera, normally the main 3D view
+        let camera = cameras.iter().find(|(camera, _)| camera.is_active);
+        let viewport_position = |position: Vec3| {
+            camera
+                .and_then(|(camera, transform)| camera.world_to_viewport(transform, position).ok())
+        };
+
+        let mut shown = Vec::new();
+        for (entity, label, mut node, mut text, mut text_color) in &mut labels {
+            let Some(Annotation::Label {
+                position,
+                text: label_text,
+                color,
+            }) = annotations.get(label.0)
+            else {
+                commands.entity(entity).despawn();
+                continue;
+            };
+            shown.push(label.0);
+
+            match viewport_position(*position) {
+                Some(point) => {
+                    node.display = Display::Flex;
+                    node.left = Val::Px(point.x);
+                    node.top = Val::Px(point.y);
+                }
+                None => node.display = Display::None,
+            }
+            if text.0 != *label_text {
+                text.0.clone_from(label_text);
+            }
+            if text_color.0 != *color {
+                text_color.0 = *color;
+            }
+        }
+
+        for (id, annotation) in annotations.iter() {
+            let Annotation::Label {
+                position,
+                text,
+                color,
+            } = annotation
+            else {
+                continue;
+            };
+            if shown.contains(&id) {
+                continue;
+            }
+            let point = viewport_position(*position).unwrap_or_default();
+            commands.spawn((
+                AnnotationLabel(id),
+                Text::new(text.clone()),
+                TextFont::default(),
+                TextColor(*color),
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(point.x),
+                    top: Val::Px(point.y),
+                    ..Default::default()
+                },
+            ));
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! The thread an app runs on
+//!
+//! Bevy apps are tied to a thread: non-send resources panic on any other, and GL
+//! contexts and some wgpu surfaces are only current on the thread that made them. Hosts
+//! that call `bevy_embedded_update` from whichever thread a coroutine dispatcher or
+//! dispatch queue picks get crashes deep inside wgpu instead of an error.
+//!
+//! Each app has an owning thread, the one that created it, or that took it from the
+//! [preload pool](crate::preload) or a [pending](crate::pending) handle. What happens to
+//! calls from other threads depends on [`EmbeddedConfig::thread_policy`] (the
+//! `thread_policy` creation config key):
+//!
+//! - [`ThreadPolicy::Enforce`]: calls that run or borrow the app are rejected with
+//!   [`FfiViolation::WrongThread`](crate::ffi::FfiViolation::WrongThread). The error is
+//!   stored for `bevy_embedded_get_last_error` and `bevy_embedded_update` returns 1.
+//! - [`ThreadPolicy::Marshal`]: the app gets a thread of its own at creation, and
+//!   `bevy_embedded_update`, `bevy_embedded_run_schedule` and destroying the app run
+//!   there, blocking the caller until they return. Other calls that borrow the app, such
+//!   as input, are lent it: a job queued behind the marshalled calls holds the app's
+//!   thread until the borrow ends, so the two never overlap. As with
+//!   [asynchronous creation](crate::pending), plugins must not insert non-send resources
+//!   while the app is built.
+//!
+//! Message and audio FFI go through the [`registry`] and may be called from any thread.
+//!
+//! [`EmbeddedConfig::thread_policy`]: crate::EmbeddedConfig::thread_policy
+
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::ThreadId,
+};
+
+use bevy::app::App;
+use crossbeam_channel::{Sender, bounded, unbounded};
+
+use crate::{ffi::FfiViolation, registry};
+
+/// What happens to calls from a thread other than the app's
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThreadPolicy {
+    /// Reject them with an error
+    #[default]
+    Enforce = 0,
+    /// Run lifecycle calls on a thread of the app's own
+    Marshal = 1,
+}
+
+impl ThreadPolicy {
+    /// Create a ThreadPolicy from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ThreadPolicy::Enforce),
+            1 => Some(ThreadPolicy::Marshal),
+            _ => None,
+        }
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The thread of a registered app
+pub(crate) struct AppThread {
+    owner: Mutex<ThreadId>,
+    /// Runs jobs on the app's own thread with [`ThreadPolicy::Marshal`]
+    marshal: Option<Sender<Job>>,
+    /// Whether a call from another thread was logged
+    reported: AtomicBool,
+}
+
+impl AppThread {
+    /// The thread of an app created on this thread
+    pub(crate) fn new(policy: ThreadPolicy) -> Self {
+        let current = std::thread::current().id();
+        let (owner, marshal) = match policy {
+            ThreadPolicy::Enforce => (current, None),
+            ThreadPolicy::Marshal => match spawn_app_thread() {
+                Some((owner, jobs)) => (owner, Some(jobs)),
+                None => (current, None),
+            },
+        };
+        Self {
+            owner: Mutex::new(owner),
+            marshal,
+            reported: AtomicBool::new(false),
+        }
+    }
+
+    fn owner(&self) -> ThreadId {
+        *self.owner.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn is_current(&self) -> bool {
+        self.owner() == std::thread::current().id()
+    }
+
+    fn reject(&self) -> FfiViolation {
+        if !self.reported.swap(true, Ordering::Relaxed) {
+            log::error!(
+                "App called from {:?}, but it runs on {:?}; see ThreadPolicy",
+                std::thread::current().id(),
+                self.owner()
+            );
+        }
+        FfiViolation::WrongThread
+    }
+}
+
+/// Spawns a thread running jobs until the app is unregistered
+fn spawn_app_thread() -> Option<(ThreadId, Sender<Job>)> {
+    let (jobs, received) = unbounded::<Job>();
+    let spawned = std::thread::Builder::new()
+        .name("bevy_embedded app".to_string())
+        .spawn(move || {
+            while let Ok(job) = received.recv() {
+                job();
+            }
+        });
+    match spawned {
+        Ok(thread) => Some((thread.thread().id(), jobs)),
+        Err(error) => {
+            log::error!("Failed to spawn the app's thread, running it on the host's: {error}");
+            None
+        }
+    }
+}
+
+/// Holds the app's own thread while another thread borrows the app
+///
+/// The borrow ends, and the app's thread goes on with its jobs, when this is dropped.
+pub(crate) struct Lease {
+    _release: Sender<()>,
+}
+
+/// Checks that the app may be borrowed on this thread
+///
+/// Returns `None` on the app's thread. On another thread, apps with
+/// [`ThreadPolicy::Marshal`] are lent out: a job waits on the app's thread, after any
+/// marshalled calls queued before it, until the returned [`Lease`] is dropped. Fails with
+/// [`ThreadPolicy::Enforce`] on another thread.
+pub(crate) fn lend(app: *const App) -> Result<Option<Lease>, FfiViolation> {
+    let Some(instance) = registry::get(app) else {
+        return Ok(None);
+    };
+    let thread = instance.thread();
+    if thread.is_current() {
+        return Ok(None);
+    }
+    let Some(jobs) = &thread.marshal else {
+        return Err(thread.reject());
+    };
+
+    let (entered, waiting) = bounded(1);
+    let (release, released) = bounded::<()>(0);
+    let job: Job = Box::new(move || {
+        if entered.send(()).is_ok() {
+            // Returns once the lease is dropped
+            let _ = released.recv();
+        }
+    });
+    if jobs.send(job).is_err() || waiting.recv().is_err() {
+        return Err(FfiViolation::UnknownInstance);
+    }
+    Ok(Some(Lease { _release: release }))
+}
+
+/// Runs a lifecycle call on the app's thread
+///
+/// Returns `None` if this is the app's thread, so the caller goes on, or the result of
+/// `call` run on the app's own thread with [`ThreadPolicy::Marshal`]. Fails with
+/// [`ThreadPolicy::Enforce`] on another thread.
+#[doc(hidden)]
+pub fn marshal<R: Send + 'static>(
+    app: *const App,
+    call: impl FnOnce() -> R + Send + 'static,
+) -> Option<Result<R, FfiViolation>> {
+    let instance = registry::get(app)?;
+    let thread = instance.thread();
+    if thread.is_current() {
+        return None;
+    }
+    let Some(jobs) = &thread.marshal else {
+        return Some(Err(thread.reject()));
+    };
+
+    let (result, returned) = bounded(1);
+    let job: Job = Box::new(move || {
+        let _ = result.send(call());
+    });
+    if jobs.send(job).is_err() {
+        return Some(Err(FfiViolation::UnknownInstance));
+    }
+    Some(returned.recv().map_err(|_| FfiViolation::UnknownInstance))
+}
+
+/// Makes this thread the owner of an app built elsewhere, such as a preloaded app
+///
+/// Apps with their own thread keep it.
+#[doc(hidden)]
+pub fn adopt(app: *const App) {
+    let Some(instance) = registry::get(app) else {
+        return;
+    };
+    let thread = instance.thread();
+    if thread.marshal.is_none() {
+        *thread
+            .owner
+            .lock()
+            .unwrap_or_else(|error| error.into_inner()) = std::thread::current().id();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, atomic::AtomicUsize},
+        time::Duration,
+    };
+
+    use bevy::ecs::resource::Resource;
+
+    use super::*;
+    use crate::{
+        EmbeddedConfig, ffi,
+        test::{MockHost, MockSurface},
+    };
+
+    #[derive(Resource, Default)]
+    struct Counter(usize);
+
+    fn host(policy: ThreadPolicy) -> MockHost {
+        MockHost::from_setup(MockSurface::default(), |app| {
+            app.init_resource::<Counter>();
+            app.world_mut()
+                .resource_mut::<EmbeddedConfig>()
+                .thread_policy = policy;
+        })
+    }
+
+    /// Increments the counter in two steps, so overlapping borrows lose increments
+    fn increment(app: &mut App) {
+        let count = app.world().resource::<Counter>().0;
+        std::thread::yield_now();
+        app.world_mut().resource_mut::<Counter>().0 = count + 1;
+    }
+
+    #[test]
+    fn enforce_rejects_other_threads() {
+        let mut host = host(ThreadPolicy::Enforce);
+        let app = host.app_ptr() as usize;
+        assert!(unsafe { ffi::app_mut(app as *mut _) }.is_ok());
+
+        let other = std::thread::spawn(move || {
+            let borrowed = unsafe { ffi::app_mut(app as *mut _) }.map(|_| ());
+            let marshalled = marshal(app as *const App, || ()).map(|result| result.map(|_| ()));
+            (borrowed, marshalled)
+        });
+        let (borrowed, marshalled) = other.join().unwrap();
+        assert_eq!(borrowed, Err(FfiViolation::WrongThread));
+        assert_eq!(marshalled, Some(Err(FfiViolation::WrongThread)));
+    }
+
+    #[test]
+    fn marshal_lends_the_app_between_jobs() {
+        let mut host = host(ThreadPolicy::Marshal);
+        let app = host.app_ptr() as usize;
+
+        // Jobs run on the app's thread, where borrows need no lease
+        let on_app_thread = marshal(app as *const App, move || {
+            let borrowed = unsafe { ffi::app_mut(app as *mut _) }.is_ok();
+            (std::thread::current().id(), borrowed)
+        });
+        let (thread, borrowed) = on_app_thread.unwrap().unwrap();
+        assert_ne!(thread, std::thread::current().id());
+        assert!(borrowed);
+
+        // A job queued while the app is lent waits for the borrow to end
+        let ran = Arc::new(AtomicBool::new(false));
+        let lent = unsafe { ffi::app_mut(app as *mut _) }.unwrap();
+        let job = std::thread::spawn({
+            let ran = ran.clone();
+            move || marshal(app as *const App, move || ran.store(true, Ordering::SeqCst))
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!ran.load(Ordering::SeqCst));
+        drop(lent);
+        assert_eq!(job.join().unwrap(), Some(Ok(())));
+        assert!(ran.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn marshal_never_overlaps_borrows() {
+        const THREADS: usize = 4;
+        const CALLS: usize = 50;
+
+        let mut host = host(ThreadPolicy::Marshal);
+        let app = host.app_ptr() as usize;
+        let marshalled = Arc::new(AtomicUsize::new(0));
+
+        let threads: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let marshalled = marshalled.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..CALLS {
+                        if thread % 2 == 0 {
+                            let mut borrowed = unsafe { ffi::app_mut(app as *mut _) }.unwrap();
+                            increment(&mut borrowed);
+                        } else {
+                            let address = registry::resolve(app as *mut App).unwrap() as usize;
+                            marshal(app as *const App, move || {
+                                increment(unsafe { &mut *(address as *mut App) })
+                            })
+                            .unwrap()
+                            .unwrap();
+                            marshalled.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(marshalled.load(Ordering::Relaxed), THREADS / 2 * CALLS);
+        assert_eq!(host.app().world().resource::<Counter>().0, THREADS * CALLS);
+    }
+}
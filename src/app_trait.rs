@@ -7,6 +7,8 @@
 use bevy::app::App;
 use std::sync::Mutex;
 
+use crate::EmbeddedConfig;
+
 /// Stores the last error that occurred in the embedded app
 static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
 
@@ -18,6 +20,15 @@ pub fn store_error(message: String) {
     }
 }
 
+/// Log an error from a Bevy system and store it like [`store_error`]
+///
+/// Logs through the crate, so apps don't need Bevy's `bevy_log` feature.
+#[doc(hidden)]
+pub fn store_system_error(message: String) {
+    log::error!("{message}");
+    store_error(message);
+}
+
 /// Retrieve and clear the last error message
 #[doc(hidden)]
 pub fn take_last_error() -> Option<String> {
@@ -33,18 +44,24 @@ pub fn take_last_error() -> Option<String> {
 ///
 /// ```no_run
 /// use bevy::prelude::*;
-/// use bevy_embedded::prelude::*;
+/// use bevy_embedded::{export_embedded_app, prelude::*};
 ///
 /// struct MyEmbeddedApp;
 ///
 /// impl EmbeddedApp for MyEmbeddedApp {
 ///     fn setup(app: &mut App) {
-///         app.add_plugins(DefaultPlugins.build().disable::<WinitPlugin>())
+///         // With Bevy's `bevy_winit` feature, also disable `bevy::winit::WinitPlugin`
+///         app.add_plugins(DefaultPlugins)
 ///             .add_systems(Startup, setup_scene);
 ///     }
 /// }
 ///
+/// fn setup_scene(mut commands: Commands) {
+///     commands.spawn(Transform::default());
+/// }
+///
 /// export_embedded_app!(MyEmbeddedApp);
+/// # fn main() {}
 /// ```
 pub trait EmbeddedApp {
     /// Configure the Bevy app with plugins and systems
@@ -64,6 +81,14 @@ pub trait EmbeddedApp {
     /// Use this for any initialization that needs to happen after
     /// the App exists but before plugins are configured.
     fn post_init(_app: &mut App) {}
+
+    /// Optional: Creation-time configuration for this app
+    ///
+    /// The returned config is inserted as a resource before any plugins are added.
+    /// Return [`EmbeddedConfig::headless`] to run without a host surface.
+    fn config() -> EmbeddedConfig {
+        EmbeddedConfig::default()
+    }
 }
 
 /// Export an embedded app implementation
@@ -71,23 +96,42 @@ pub trait EmbeddedApp {
 /// This macro generates the necessary FFI entry points for your embedded app.
 /// The generated functions are:
 /// - `bevy_embedded_create_app()` - Creates and initializes the app
+/// - `bevy_embedded_create_app_with_config()` - Creates and initializes the app with the
+///   host's creation config (see [`HostConfig`](crate::HostConfig))
 /// - `bevy_embedded_update()` - Updates the app each frame
-/// - `bevy_embedded_destroy()` - Cleans up and destroys the app
+/// - `bevy_embedded_update_with_info()` - Updates the app, reporting a
+///   [`FrameInfo`](crate::FrameInfo)
+/// - `bevy_embedded_run_schedule()` - Runs one part of a frame, for hosts composing
+///   frames themselves (see [`HostSchedule`](crate::HostSchedule))
+/// - `bevy_embedded_destroy()` - Shuts the app down and destroys it
+/// - `bevy_embedded_destroy_async()` - Requests the app to exit and destroys it in a later
+///   update (see [`teardown`](crate::teardown))
+/// - `bevy_embedded_preload_step()` - Creates or destroys one app of the preload pool
+///   (see [`preload`](crate::preload))
+/// - `bevy_embedded_clear_preloaded()` - Destroys every app in the preload pool
+/// - `bevy_embedded_create_app_async()` - Creates the app on a worker thread (see
+///   [`pending`](crate::pending))
+/// - `bevy_embedded_pending_app_status()` / `bevy_embedded_release_pending_app()` - Poll
+///   or give up an app created asynchronously
+/// - `bevy_embedded_ios_create_app_for_scene()` - On iOS, creates the app for one window
+///   of a multi-window host
 ///
 /// # Example
 ///
 /// ```no_run
-/// use bevy_embedded::prelude::*;
+/// use bevy::app::App;
+/// use bevy_embedded::{export_embedded_app, prelude::*};
 ///
 /// struct MyApp;
 ///
 /// impl EmbeddedApp for MyApp {
-///     fn setup(app: &mut App) {
+///     fn setup(_app: &mut App) {
 ///         // Configure your app
 ///     }
 /// }
 ///
 /// export_embedded_app!(MyApp);
+/// # fn main() {}
 /// ```
 #[macro_export]
 macro_rules! export_embedded_app {
@@ -96,8 +140,22 @@ macro_rules! export_embedded_app {
         /// This is called AFTER the host has set up the surface info
         #[unsafe(no_mangle)]
         pub extern "C" fn bevy_embedded_create_app() -> *mut bevy::app::App {
-            use bevy::app::{App, PluginsState};
-            use bevy::tasks::tick_global_task_pools_on_main_thread;
+            bevy_embedded_build_app($crate::HostConfig::new())
+        }
+
+        /// Like `bevy_embedded_create_app`, with the host's creation config values
+        /// `config` (may be null) is copied; the host keeps it and may free it once this
+        /// returns
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_create_app_with_config(
+            config: *const $crate::HostConfig,
+        ) -> *mut bevy::app::App {
+            bevy_embedded_build_app(unsafe { $crate::ffi::host_config(config) })
+        }
+
+        /// Creates the app with the host's creation config values
+        fn bevy_embedded_build_app(host_config: $crate::HostConfig) -> *mut bevy::app::App {
+            use bevy::app::App;
             use $crate::EmbeddedApp;
 
             // Call pre-init hook
@@ -105,91 +163,204 @@ macro_rules! export_embedded_app {
 
             let mut app = App::new();
 
+            // Creation config must be in place before EmbeddedPlugin builds
+            let mut config = <$app_type>::config();
+            config.apply_host_config(host_config);
+            let headless = config.headless;
+            app.insert_resource(config);
+
             // Set error handler to capture errors from Bevy systems
             app.set_error_handler(|error, context| {
                 use std::fmt::Write;
                 let mut message = String::new();
                 let _ = write!(message, "{}: {}", context, error);
-                $crate::store_error(message);
-                bevy::log::error!("{}: {}", context, error);
+                $crate::store_system_error(message);
             });
 
             // Add the EmbeddedPlugin first so it can create the window before RenderPlugin builds
             app.add_plugins($crate::EmbeddedPlugin);
 
-            // Create the window by requesting it from the host before adding other plugins
-            #[cfg(target_os = "ios")]
-            $crate::ios::create_window_from_host(&mut app);
+            // Create the window by requesting it from the host before adding other plugins,
+            // unless the app is being preloaded and gets its surface later
+            if !headless && !$crate::preload::spawn_preloaded_window(&mut app) {
+                #[cfg(target_os = "ios")]
+                $crate::ios::create_window_from_host(&mut app);
 
-            #[cfg(target_os = "android")]
-            $crate::android::create_window_from_host(&mut app);
+                #[cfg(target_os = "android")]
+                $crate::android::create_window_from_host(&mut app);
 
-            // Configure embedded asset source for Android (must be before plugins)
-            #[cfg(target_os = "android")]
-            $crate::android::configure_embedded_asset_source(&mut app);
+                #[cfg(not(any(target_os = "ios", target_os = "android")))]
+                $crate::desktop::create_window_from_host(&mut app);
+            }
+
+            // Configure the default asset source (must be before plugins)
+            $crate::configure_asset_source(&mut app);
 
             // Call post-init hook
             <$app_type>::post_init(&mut app);
 
             // User-defined setup
             <$app_type>::setup(&mut app);
+            $crate::record_build_end(&app);
 
-            // Finish and cleanup to initialize all plugins
-            app.finish();
-            app.cleanup();
+            // Plugins finish in the first update after the surface is attached, see
+            // `InitProgress`
 
-            Box::into_raw(Box::new(app))
+            // Register at the final boxed address; the host holds the handle
+            let app = Box::into_raw(Box::new(app));
+            let handle = $crate::registry::register(unsafe { &*app });
+            $crate::telemetry::report_created(unsafe { &mut *app }, handle);
+            handle
+        }
+
+        /// Creates an app for one host scene (an iPad window), rendering into `surface`
+        /// instead of the surface from `bevy_embedded_get_surface`
+        /// Returns null if `surface` is null or the scene already has an app, which
+        /// `bevy_embedded_ios_app_for_scene` finds for `bevy_embedded_ios_scene_connected`
+        #[cfg(target_os = "ios")]
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_ios_create_app_for_scene(
+            scene: u64,
+            surface: *const $crate::ios::EmbeddedSurfaceInfo,
+            config: *const $crate::HostConfig,
+        ) -> *mut bevy::app::App {
+            if surface.is_null() {
+                $crate::store_error("Null scene surface pointer".to_string());
+                return std::ptr::null_mut();
+            }
+            if $crate::registry::scene_app(scene).is_some() {
+                $crate::store_error(format!("Scene {scene} already has an app"));
+                return std::ptr::null_mut();
+            }
+
+            let surface = unsafe { *surface };
+            let host_config = unsafe { $crate::ffi::host_config(config) };
+            $crate::ios::with_scene_surface(scene, surface, || bevy_embedded_build_app(host_config))
         }
 
         /// Update the app (called every frame by host)
-        /// Returns 0 on success, non-zero error code if the app should exit with an error
+        /// Returns 0 on success or after recovering from an error (see `RecoveryPolicy`),
+        /// non-zero error code if the app should exit with an error.
+        /// After `bevy_embedded_destroy_async`, runs the app's final updates instead and
+        /// destroys it in the last one.
         #[unsafe(no_mangle)]
         pub unsafe extern "C" fn bevy_embedded_update(app: *mut bevy::app::App) -> u8 {
-            use bevy::app::PluginsState;
-            use bevy::tasks::tick_global_task_pools_on_main_thread;
+            unsafe { bevy_embedded_update_with_info(app, std::ptr::null_mut()) }
+        }
 
+        /// Like `bevy_embedded_update`, also reporting the frame's scheduling signals
+        /// If `info` is not null, they are written to it, or zeroes if the call returns
+        /// without running a frame.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_update_with_info(
+            app: *mut bevy::app::App,
+            info: *mut $crate::FrameInfo,
+        ) -> u8 {
+            // Calls that return without running a frame leave `info` zeroed
+            unsafe { $crate::ffi::clear_frame_info(info) };
             if app.is_null() {
                 $crate::store_error("Null app pointer".to_string());
                 return 1;
             }
 
-            unsafe {
-                let app = &mut *app;
+            // Run on the app's thread, see `ThreadPolicy`
+            let (app_addr, info_addr) = (app as usize, info as usize);
+            if let Some(result) = $crate::app_thread::marshal(app, move || unsafe {
+                bevy_embedded_update_with_info(
+                    app_addr as *mut bevy::app::App,
+                    info_addr as *mut $crate::FrameInfo,
+                )
+            }) {
+                return result.unwrap_or_else(|violation| {
+                    $crate::store_error(violation.to_string());
+                    1
+                });
+            }
+
+            // Handles of destroyed apps resolve to nothing, see `registry`
+            let Some(address) = $crate::registry::resolve(app) else {
+                $crate::store_error("App was destroyed".to_string());
+                return 1;
+            };
 
-                let plugins_state = app.plugins_state();
-                if plugins_state != PluginsState::Cleaned {
-                    while app.plugins_state() == PluginsState::Adding {
-                        tick_global_task_pools_on_main_thread();
+            unsafe {
+                if $crate::teardown::in_progress(&*address) {
+                    if $crate::teardown::step(&mut *address) {
+                        bevy_embedded_destroy(app);
+                        return 0;
                     }
-                    app.finish();
-                    app.cleanup();
+                    $crate::ffi::write_frame_info(&mut *address, info, true);
+                    return 0;
                 }
 
-                // Update the app
-                app.update();
-
-                // Check if the app should exit (e.g., render thread crashed)
-                if let Some(exit) = app.should_exit() {
-                    if exit.is_error() {
-                        // If we don't have a stored error message, create a generic one
-                        if $crate::take_last_error().is_none() {
-                            $crate::store_error("Bevy app exited with an error".to_string());
-                        }
-                        bevy::log::error!("App exiting with error: {:?}", exit);
-                        return match exit {
-                            bevy::app::AppExit::Error(code) => code.get(),
-                            _ => 1,
-                        };
-                    }
+                let app = &mut *address;
+
+                // Finish plugins in the first update that has a surface to render to
+                if $crate::InitProgress::of(app) == $crate::InitProgress::WaitingForSurface {
+                    $crate::ffi::write_frame_info(app, info, false);
+                    return 0;
                 }
+                $crate::ffi::finish_plugins(app);
+
+                // Update the app, unless picture-in-picture pacing skips this frame or an
+                // error froze it
+                let updated = $crate::ffi::update(app);
+                $crate::ffi::write_frame_info(app, info, updated);
+
+                // Apply the recovery policy to errors and panics of the update, and exit
+                // if the app requested it with an error
+                $crate::ffi::recover(app)
+            }
+        }
+
+        /// Run one part of a frame (see `HostSchedule`) instead of a whole update
+        /// Returns 0 if it ran, 1 on error, 2 if the app doesn't have the schedule
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_run_schedule(
+            app: *mut bevy::app::App,
+            schedule: u8,
+        ) -> u8 {
+            if app.is_null() {
+                $crate::store_error("Null app pointer".to_string());
+                return 1;
+            }
+
+            let app_addr = app as usize;
+            if let Some(result) = $crate::app_thread::marshal(app, move || unsafe {
+                bevy_embedded_run_schedule(app_addr as *mut bevy::app::App, schedule)
+            }) {
+                return result.unwrap_or_else(|violation| {
+                    $crate::store_error(violation.to_string());
+                    1
+                });
+            }
+
+            let Some(address) = $crate::registry::resolve(app) else {
+                $crate::store_error("App was destroyed".to_string());
+                return 1;
+            };
+            let app = unsafe { &mut *address };
+            if $crate::InitProgress::of(app) == $crate::InitProgress::WaitingForSurface {
+                return 0;
+            }
+            $crate::ffi::finish_plugins(app);
 
-                // Check if an error was stored during the update (without AppExit)
-                if $crate::take_last_error().is_some() {
+            match $crate::ffi::run_schedule(app, schedule) {
+                Ok(true) => {}
+                Ok(false) => return 2,
+                Err(violation) => {
+                    $crate::store_error(violation.to_string());
                     return 1;
                 }
+            }
 
-                0 // Success
+            if let Some(bevy::app::AppExit::Error(code)) = app.should_exit() {
+                if $crate::take_last_error().is_none() {
+                    $crate::store_error("Bevy app exited with an error".to_string());
+                }
+                return code.get();
             }
+            0
         }
 
         /// Get the last error message (if any) and clear it
@@ -217,13 +388,175 @@ macro_rules! export_embedded_app {
             }
         }
 
-        /// Cleanup and destroy the app
+        /// Shut the app down and destroy it
+        /// Requests exit, runs the final updates (bounded by the teardown timeout) and
+        /// waits for the GPU before dropping the app. Calls the `on_destroyed` callback
+        /// of an earlier `bevy_embedded_destroy_async` once dropped.
         #[unsafe(no_mangle)]
         pub unsafe extern "C" fn bevy_embedded_destroy(app: *mut bevy::app::App) {
             if !app.is_null() {
+                let app_addr = app as usize;
+                if let Some(result) = $crate::app_thread::marshal(app, move || unsafe {
+                    bevy_embedded_destroy(app_addr as *mut bevy::app::App)
+                }) {
+                    if let Err(violation) = result {
+                        $crate::store_error(violation.to_string());
+                    }
+                    return;
+                }
+
+                let Some(address) = $crate::registry::resolve(app) else {
+                    $crate::store_error("App was already destroyed".to_string());
+                    return;
+                };
+                let notice = $crate::teardown::shutdown(unsafe { &mut *address });
+                $crate::telemetry::report_destroyed(unsafe { &*address });
+                // Unregister first so concurrent message FFI stops resolving this app
+                $crate::registry::unregister(app);
                 unsafe {
-                    let _ = Box::from_raw(app);
+                    let _ = Box::from_raw(address);
                 }
+                if let Some(notice) = notice {
+                    unsafe { notice.notify() };
+                }
+            }
+        }
+
+        /// Request the app to exit and destroy it once its final updates ran
+        /// Keep calling `bevy_embedded_update`; the call that destroys the app calls
+        /// `on_destroyed` (if any) with `user_data`, after which `app` is invalid.
+        /// `bevy_embedded_destroy` finishes the teardown right away.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_destroy_async(
+            app: *mut bevy::app::App,
+            on_destroyed: Option<$crate::teardown::DestroyedCallback>,
+            user_data: *mut std::ffi::c_void,
+        ) {
+            if app.is_null() {
+                $crate::store_error("Null app pointer".to_string());
+                return;
+            }
+
+            let (app_addr, user_data_addr) = (app as usize, user_data as usize);
+            if let Some(result) = $crate::app_thread::marshal(app, move || unsafe {
+                bevy_embedded_destroy_async(
+                    app_addr as *mut bevy::app::App,
+                    on_destroyed,
+                    user_data_addr as *mut std::ffi::c_void,
+                )
+            }) {
+                if let Err(violation) = result {
+                    $crate::store_error(violation.to_string());
+                }
+                return;
+            }
+            let Some(address) = $crate::registry::resolve(app) else {
+                $crate::store_error("App was destroyed".to_string());
+                return;
+            };
+            $crate::teardown::begin(unsafe { &mut *address }, on_destroyed, user_data);
+        }
+
+        /// Create or destroy one app to bring the preload pool to its target, called by
+        /// the host when it is idle
+        /// Returns 1 if it did some work, 0 if the pool is already at its target
+        /// Apps are created with the config given with the target. An app whose first
+        /// update fails is destroyed instead of pooled, and its error is stored.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_preload_step() -> u8 {
+            match $crate::preload::next_step() {
+                Some($crate::preload::PreloadStep::Create(surface, host_config)) => {
+                    let app = $crate::preload::with_preloading(surface, || {
+                        bevy_embedded_build_app(host_config)
+                    });
+                    let Some(address) = $crate::registry::resolve(app) else {
+                        return 1;
+                    };
+                    // Finish plugins and run Startup now so the scene and its assets start
+                    // loading
+                    let preloaded = unsafe { &mut *address };
+                    $crate::ffi::finish_plugins(preloaded);
+                    let updated = $crate::ffi::update(preloaded);
+                    let failed = $crate::ffi::recover(preloaded) != 0;
+                    if !updated || failed || $crate::ffi::frozen_error(preloaded).is_some() {
+                        let error = $crate::take_last_error()
+                            .or_else(|| $crate::ffi::frozen_error(preloaded))
+                            .unwrap_or_else(|| "first update failed".to_string());
+                        unsafe { bevy_embedded_destroy(app) };
+                        $crate::store_error(format!("Failed to preload an app: {error}"));
+                        return 1;
+                    }
+                    $crate::preload::push_preloaded(app);
+                    1
+                }
+                Some($crate::preload::PreloadStep::Destroy(app)) => {
+                    unsafe { bevy_embedded_destroy(app) };
+                    1
+                }
+                None => 0,
+            }
+        }
+
+        /// Destroy every app waiting in the preload pool
+        ///
+        /// Does not change the target, so later preload steps refill the pool.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_clear_preloaded() {
+            for app in $crate::preload::drain_preloaded() {
+                unsafe { bevy_embedded_destroy(app) };
+            }
+        }
+
+        /// Start creating the app on a worker thread, at a surface size of
+        /// `width`x`height` physical pixels
+        /// Returns a handle in the initializing state right away, or null if the thread
+        /// could not be spawned. `on_ready` (if any) is called with `user_data` and the
+        /// handle on the worker thread once the app can be taken. `config` (may be null) is
+        /// copied before this returns.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_create_app_async(
+            config: *const $crate::HostConfig,
+            width: u32,
+            height: u32,
+            scale_factor: f32,
+            on_ready: Option<$crate::pending::ReadyCallback>,
+            user_data: *mut std::ffi::c_void,
+        ) -> *const $crate::pending::PendingApp {
+            let surface = $crate::preload::PreloadSurface {
+                width,
+                height,
+                scale_factor,
+            };
+            $crate::pending::spawn(
+                surface,
+                unsafe { $crate::ffi::host_config(config) },
+                bevy_embedded_build_app,
+                bevy_embedded_destroy,
+                on_ready,
+                user_data,
+            )
+        }
+
+        /// Progress of an app created with `bevy_embedded_create_app_async`
+        /// Returns 0 while initializing, 1 once ready, 2 once taken
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_pending_app_status(
+            pending: *const $crate::pending::PendingApp,
+        ) -> u8 {
+            if pending.is_null() {
+                return $crate::pending::PendingStatus::Taken as u8;
+            }
+            unsafe { $crate::pending::status(pending) as u8 }
+        }
+
+        /// Release a handle from `bevy_embedded_create_app_async`, destroying the app
+        /// unless it was taken
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn bevy_embedded_release_pending_app(
+            pending: *const $crate::pending::PendingApp,
+        ) {
+            if !pending.is_null() {
+                unsafe { $crate::pending::release(pending) };
             }
         }
     };
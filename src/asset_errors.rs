@@ -0,0 +1,91 @@
+//! Asset load failures reported to the host
+//!
+//! A failed asset otherwise only shows up as missing or pink content in the widget. Each
+//! failed load is sent to the host on [`ReservedTopic::AssetLoadFailed`], so it can show
+//! native error UI or retry:
+//!
+//! ```text
+//! kind: u8 | path: string | error: string
+//! ```
+//!
+//! `kind` is an [`AssetErrorKind`], `path` the asset path as passed to the asset server
+//! (including its source and label, e.g. `host://level.glb#Scene0`) and `error` Bevy's
+//! description of the failure. Strings are `u32 len + UTF-8`.
+//!
+//! Failures are also reported as telemetry errors.
+
+use bevy::{
+    asset::{AssetLoadError, UntypedAssetLoadFailedEvent, io::AssetReaderError},
+    ecs::{message::MessageReader, system::Res},
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteWriter, ReservedTopic},
+};
+
+/// Why an asset failed to load
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AssetErrorKind {
+    /// The file, its asset source or a labeled sub-asset doesn't exist
+    NotFound = 0,
+    /// Reading the file failed, e.g. an I/O or HTTP error
+    Io = 1,
+    /// The file was read but its contents or meta file couldn't be decoded
+    Decode = 2,
+    /// No loader is registered for the file's extension or the requested asset type
+    Unsupported = 3,
+    /// Any other failure
+    Other = 4,
+}
+
+impl AssetErrorKind {
+    /// Create an AssetErrorKind from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(AssetErrorKind::NotFound),
+            1 => Some(AssetErrorKind::Io),
+            2 => Some(AssetErrorKind::Decode),
+            3 => Some(AssetErrorKind::Unsupported),
+            4 => Some(AssetErrorKind::Other),
+            _ => None,
+        }
+    }
+
+    /// The kind of a load error
+    pub fn of(error: &AssetLoadError) -> Self {
+        match error {
+            AssetLoadError::AssetReaderError(AssetReaderError::NotFound(_))
+            | AssetLoadError::MissingAssetSourceError(_)
+            | AssetLoadError::MissingLabel { .. } => AssetErrorKind::NotFound,
+            AssetLoadError::AssetReaderError(_)
+            | AssetLoadError::MissingProcessedAssetReaderError(_)
+            | AssetLoadError::AssetMetaReadError => AssetErrorKind::Io,
+            AssetLoadError::AssetLoaderError(_)
+            | AssetLoadError::AssetLoaderPanic { .. }
+            | AssetLoadError::DeserializeMeta { .. } => AssetErrorKind::Decode,
+            AssetLoadError::MissingAssetLoader { .. }
+            | AssetLoadError::MissingAssetLoaderForExtension(_)
+            | AssetLoadError::MissingAssetLoaderForTypeName(_)
+            | AssetLoadError::MissingAssetLoaderForTypeIdError(_)
+            | AssetLoadError::RequestedHandleTypeMismatch { .. } => AssetErrorKind::Unsupported,
+            _ => AssetErrorKind::Other,
+        }
+    }
+}
+
+/// Sends failed asset loads to the host
+pub(crate) fn send_asset_errors(
+    mut failures: MessageReader<UntypedAssetLoadFailedEvent>,
+    channel: Res<HostChannel>,
+) {
+    for failure in failures.read() {
+        let mut payload = ByteWriter::new();
+        payload
+            .u8(AssetErrorKind::of(&failure.error) as u8)
+            .string(&failure.path.to_string())
+            .string(&failure.error.to_string());
+        channel.send_reserved(ReservedTopic::AssetLoadFailed, &payload.finish());
+    }
+}
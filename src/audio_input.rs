@@ -0,0 +1,179 @@
+//! PCM audio captured by the host
+//!
+//! The host owns the microphone and its audio session (`AVAudioEngine` input tap,
+//! `AudioRecord`) and streams what it captures into Bevy with
+//! `bevy_embedded_ios_push_audio_input` / `nativePushAudioInput`. Buffers go through a
+//! [`RingBuffer`] rather than the message channel, since audio callbacks run on a
+//! realtime thread and must not allocate or block on Bevy.
+//!
+//! With [`AudioInputPlugin`] added, the buffers pushed since the last frame are moved into
+//! the [`HostAudioInput`] resource at the start of each frame:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::HostAudioInput;
+//!
+//! fn level_meter(input: Res<HostAudioInput>, mut level: Local<f32>) {
+//!     for buffer in input.buffers() {
+//!         *level = buffer.rms();
+//!     }
+//! }
+//! ```
+//!
+//! If Bevy falls behind (or is paused) the ring fills up and further buffers are dropped
+//! until it catches up.
+
+use std::sync::Arc;
+
+use bevy::{
+    app::{App, Plugin},
+    ecs::{resource::Resource, system::ResMut},
+};
+
+use crate::{EmbeddedInput, RingBuffer};
+
+/// Receives PCM pushed by the host into [`HostAudioInput`]; see the [module docs](self)
+#[derive(Default)]
+pub struct AudioInputPlugin;
+
+impl Plugin for AudioInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostAudioInput>()
+            .add_systems(EmbeddedInput, receive_audio_input);
+    }
+}
+
+/// Bytes of PCM the host can queue between two frames, about 5 s of 48 kHz mono audio
+const AUDIO_INPUT_CAPACITY: usize = 1 << 20;
+
+/// Size of the timestamp, sample rate and channel count written before each buffer
+const BUFFER_HEADER_LEN: usize = 16;
+
+/// A buffer of interleaved PCM samples captured by the host
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioInputBuffer {
+    /// Capture time of the first sample, in seconds on the host's monotonic clock
+    pub timestamp: f64,
+    /// Frames per second
+    pub sample_rate: u32,
+    /// Number of interleaved channels
+    pub channels: u16,
+    /// Interleaved samples in `-1.0..=1.0`
+    pub samples: Vec<f32>,
+}
+
+impl AudioInputBuffer {
+    /// Number of frames, i.e. samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    /// Length of the buffer in seconds
+    pub fn duration(&self) -> f64 {
+        self.frames() as f64 / self.sample_rate.max(1) as f64
+    }
+
+    /// Root mean square of all samples, a simple loudness measure
+    pub fn rms(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = self.samples.iter().map(|sample| sample * sample).sum();
+        (sum / self.samples.len() as f32).sqrt()
+    }
+
+    /// Encodes the header written before the samples in the ring
+    fn header(timestamp: f64, sample_rate: u32, channels: u16) -> [u8; BUFFER_HEADER_LEN] {
+        let mut header = [0; BUFFER_HEADER_LEN];
+        header[..8].copy_from_slice(&timestamp.to_le_bytes());
+        header[8..12].copy_from_slice(&sample_rate.to_le_bytes());
+        header[12..14].copy_from_slice(&channels.to_le_bytes());
+        header
+    }
+
+    /// Decodes a buffer read from the ring
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (header, samples) = bytes.split_at_checked(BUFFER_HEADER_LEN)?;
+        Some(Self {
+            timestamp: f64::from_le_bytes(header[..8].try_into().ok()?),
+            sample_rate: u32::from_le_bytes(header[8..12].try_into().ok()?),
+            channels: u16::from_le_bytes(header[12..14].try_into().ok()?),
+            samples: samples
+                .chunks_exact(4)
+                .map(|sample| f32::from_ne_bytes([sample[0], sample[1], sample[2], sample[3]]))
+                .collect(),
+        })
+    }
+}
+
+/// Audio the host captured since the last frame
+#[derive(Resource)]
+pub struct HostAudioInput {
+    ring: Arc<RingBuffer>,
+    buffers: Vec<AudioInputBuffer>,
+}
+
+impl Default for HostAudioInput {
+    fn default() -> Self {
+        Self {
+            ring: Arc::new(RingBuffer::new(AUDIO_INPUT_CAPACITY)),
+            buffers: Vec::new(),
+        }
+    }
+}
+
+impl HostAudioInput {
+    /// Buffers received this frame, oldest first
+    pub fn buffers(&self) -> &[AudioInputBuffer] {
+        &self.buffers
+    }
+
+    /// Returns true if the host delivered audio this frame
+    pub fn is_active(&self) -> bool {
+        !self.buffers.is_empty()
+    }
+
+    /// The ring the host pushes buffers into
+    pub(crate) fn ring(&self) -> &Arc<RingBuffer> {
+        &self.ring
+    }
+}
+
+/// Queues a buffer of interleaved samples from the host for the next frame
+///
+/// Returns false if the ring is full. Never allocates, so it is safe to call from an
+/// audio callback.
+pub(crate) fn push_audio_input(
+    ring: &RingBuffer,
+    timestamp: f64,
+    sample_rate: u32,
+    channels: u16,
+    samples: &[f32],
+) -> bool {
+    let header = AudioInputBuffer::header(timestamp, sample_rate, channels);
+    // SAFETY: any initialized f32 slice is also a valid byte slice of four times the length
+    let samples = unsafe {
+        std::slice::from_raw_parts(
+            samples.as_ptr() as *const u8,
+            std::mem::size_of_val(samples),
+        )
+    };
+    ring.push_parts(&[&header, samples])
+}
+
+/// Moves the buffers pushed since the last frame into [`HostAudioInput`]
+fn receive_audio_input(mut input: ResMut<HostAudioInput>) {
+    if input.buffers.is_empty() && input.ring.is_empty() {
+        return;
+    }
+
+    let input = &mut *input;
+    input.buffers.clear();
+    let mut bytes = Vec::new();
+    while input.ring.pop_vec(&mut bytes) {
+        match AudioInputBuffer::decode(&bytes) {
+            Some(buffer) => input.buffers.push(buffer),
+            None => log::warn!("Ignoring malformed audio input buffer"),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Bevy audio played through the host's audio engine
+//!
+//! Bevy's `AudioPlugin` opens its own output stream, which fights with the host over the
+//! audio session: the host can't mix the widget with its own playback, duck it under a
+//! voice prompt or pause it with the rest of the app. [`HostAudioPlugin`] replaces it and
+//! mixes every playing `AudioPlayer` into a buffer the host pulls from its render
+//! callback (an `AVAudioSourceNode`, an `AudioTrack` or Oboe stream) with
+//! `bevy_embedded_ios_render_audio` / `nativeRenderAudio`:
+//!
+//! ```no_run
+//! use bevy::{audio::AudioPlugin, prelude::*};
+//! use bevy_embedded::HostAudioPlugin;
+//!
+//! fn setup(app: &mut App) {
+//!     app.add_plugins(DefaultPlugins.build().disable::<AudioPlugin>())
+//!         .add_plugins(HostAudioPlugin::default());
+//! }
+//! ```
+//!
+//! `AudioPlayer`, `PlaybackSettings` and `AudioSink` work as usual. Spatial playback is
+//! not supported yet and falls back to plain stereo.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    asset::{AssetApp, Assets},
+    audio::{
+        AudioLoader, AudioPlayer, AudioPlugin, AudioSink, AudioSinkPlayback, AudioSource,
+        Decodable, GlobalVolume, PlaybackMode, PlaybackSettings, Source,
+    },
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::Without,
+        resource::Resource,
+        system::{Commands, Query, Res},
+    },
+};
+use rodio::{
+    Sample, Sink,
+    cpal::FromSample,
+    dynamic_mixer::{self, DynamicMixer, DynamicMixerController},
+};
+
+/// Plays Bevy audio through the host instead of an output stream of its own
+///
+/// Add it in place of Bevy's `AudioPlugin`.
+pub struct HostAudioPlugin {
+    /// Volume applied to every sound
+    pub global_volume: GlobalVolume,
+    /// Sample rate the host renders at
+    pub sample_rate: u32,
+    /// Number of interleaved channels the host renders
+    pub channels: u16,
+}
+
+impl Default for HostAudioPlugin {
+    fn default() -> Self {
+        Self {
+            global_volume: GlobalVolume::default(),
+            sample_rate: 48_000,
+            channels: 2,
+        }
+    }
+}
+
+impl Plugin for HostAudioPlugin {
+    fn build(&self, app: &mut App) {
+        if app.is_plugin_added::<AudioPlugin>() {
+            log::warn!("HostAudioPlugin added alongside AudioPlugin; sounds will play twice");
+        }
+
+        let (controller, mixer) = dynamic_mixer::mixer(self.channels, self.sample_rate);
+        app.insert_resource(self.global_volume)
+            .insert_resource(HostAudioOutput {
+                controller,
+                renderer: Arc::new(AudioRenderer {
+                    mixer: Mutex::new(mixer),
+                    sample_rate: self.sample_rate,
+                    channels: self.channels,
+                }),
+            })
+            .init_asset::<AudioSource>()
+            .init_asset_loader::<AudioLoader>()
+            .add_systems(PostUpdate, (play_host_audio, cleanup_host_audio));
+    }
+}
+
+/// Mixed output of every playing sound, pulled by the host's render callback
+pub struct AudioRenderer {
+    mixer: Mutex<DynamicMixer<f32>>,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl AudioRenderer {
+    /// Sample rate the output is mixed at
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of interleaved channels in the output
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Fills `out` with the next interleaved samples, padding with silence
+    pub fn render(&self, out: &mut [f32]) {
+        let mut mixer = self.mixer.lock().unwrap_or_else(|e| e.into_inner());
+        for sample in out {
+            *sample = mixer.next().unwrap_or(0.0);
+        }
+    }
+}
+
+/// The mixer sounds are played into
+#[derive(Resource)]
+pub struct HostAudioOutput {
+    controller: Arc<DynamicMixerController<f32>>,
+    renderer: Arc<AudioRenderer>,
+}
+
+impl HostAudioOutput {
+    /// The output the host pulls from
+    pub fn renderer(&self) -> &Arc<AudioRenderer> {
+        &self.renderer
+    }
+}
+
+/// What happens to a sound's entity once it finishes
+#[derive(Component)]
+struct HostAudioPlayback(PlaybackMode);
+
+/// Appends `source` to `sink` as the playback settings describe
+fn append_clip<S>(sink: &Sink, source: S, settings: &PlaybackSettings)
+where
+    S: Source + Send + 'static,
+    S::Item: Sample + Send + Sync,
+    f32: FromSample<S::Item>,
+{
+    match (settings.start_position, settings.duration) {
+        (Some(start), Some(duration)) => append_repeating(
+            sink,
+            source.skip_duration(start).take_duration(duration),
+            settings,
+        ),
+        (Some(start), None) => append_repeating(sink, source.skip_duration(start), settings),
+        (None, Some(duration)) => append_repeating(sink, source.take_duration(duration), settings),
+        (None, None) => append_repeating(sink, source, settings),
+    }
+}
+
+fn append_repeating<S>(sink: &Sink, source: S, settings: &PlaybackSettings)
+where
+    S: Source + Send + 'static,
+    S::Item: Sample + Send + Sync,
+    f32: FromSample<S::Item>,
+{
+    match settings.mode {
+        PlaybackMode::Loop => sink.append(source.repeat_infinite()),
+        PlaybackMode::Once | PlaybackMode::Despawn | PlaybackMode::Remove => sink.append(source),
+    }
+}
+
+/// Starts sounds whose audio has loaded, mixing them into the host output
+fn play_host_audio(
+    output: Res<HostAudioOutput>,
+    audio_sources: Res<Assets<AudioSource>>,
+    global_volume: Res<GlobalVolume>,
+    players: Query<(Entity, &AudioPlayer, &PlaybackSettings), Without<AudioSink>>,
+    mut commands: Commands,
+) {
+    for (entity, player, settings) in &players {
+        let Some(audio_source) = audio_sources.get(&player.0) else {
+            continue;
+        };
+        if settings.spatial {
+            log::warn!("Spatial audio is not supported by HostAudioPlugin; playing in stereo");
+        }
+
+        let (sink, queue) = Sink::new_idle();
+        output.controller.add(queue);
+        append_clip(&sink, audio_source.decoder(), settings);
+
+        let mut sink = AudioSink::new(sink);
+        if settings.muted {
+            sink.mute();
+        }
+        sink.set_speed(settings.speed);
+        sink.set_volume(settings.volume * global_volume.volume);
+        if settings.paused {
+            sink.pause();
+        }
+        commands
+            .entity(entity)
+            .insert((sink, HostAudioPlayback(settings.mode)));
+    }
+}
+
+/// Despawns or cleans up entities whose sounds finished, as their playback mode asks
+fn cleanup_host_audio(
+    sounds: Query<(Entity, &AudioSink, &HostAudioPlayback)>,
+    mut commands: Commands,
+) {
+    for (entity, sink, playback) in &sounds {
+        if !sink.empty() {
+            continue;
+        }
+        match playback.0 {
+            PlaybackMode::Despawn => {
+                commands.entity(entity).despawn();
+            }
+            PlaybackMode::Remove => {
+                commands.entity(entity).remove::<(
+                    AudioPlayer,
+                    AudioSink,
+                    PlaybackSettings,
+                    HostAudioPlayback,
+                )>();
+            }
+            PlaybackMode::Once | PlaybackMode::Loop => {}
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! Simulated phone host for developing embedded apps on a desktop
+//!
+//! Loads an app's cdylib built for the desktop and calls its FFI the way the iOS and
+//! Android hosts do: it creates the app on a window's surface, updates it every frame,
+//! turns left mouse drags into touches and forwards resizes and focus changes. Lines
+//! typed on stdin are sent on the binary channel, and messages from Bevy are printed.
+//!
+//! ```text
+//! cargo build -p bevy_mobile_embedded_example
+//! cargo run --features simulated_host --bin simulated_host -- \
+//!     target/debug/libbevy_mobile_embedded_example.so
+//! > text hello       sends "hello" as UTF-8
+//! > hex 01 ff 20     sends raw bytes
+//! > lifecycle 2      moves the app to the background (0 = active, 1 = inactive)
+//! > quit
+//! ```
+
+use std::{
+    ffi::{CStr, c_char, c_void},
+    io::BufRead,
+    path::PathBuf,
+    process::ExitCode,
+    ptr::{self, NonNull},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use bevy_embedded::{
+    DesktopSurfaceInfo, DesktopSurfaceKind, TouchPhase,
+    protocol::{ReservedTopic, decode_reserved},
+};
+use libloading::Library;
+use winit::{
+    application::ApplicationHandler,
+    dpi::{LogicalSize, PhysicalPosition},
+    event::{ElementState, MouseButton, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle},
+    window::{Window, WindowId},
+};
+
+/// Largest message received from the app; longer ones are truncated
+const RECEIVE_BUFFER_LEN: usize = 16 * 1024 * 1024;
+
+/// Logical size of the simulated phone screen
+const SCREEN_SIZE: LogicalSize<f64> = LogicalSize::new(390.0, 844.0);
+
+type AppPtr = *mut c_void;
+
+/// Entry points of the loaded cdylib
+struct HostFfi {
+    create_app: unsafe extern "C" fn() -> AppPtr,
+    update: unsafe extern "C" fn(AppPtr) -> u8,
+    destroy: unsafe extern "C" fn(AppPtr),
+    get_last_error: unsafe extern "C" fn() -> *mut c_char,
+    free_error: unsafe extern "C" fn(*mut c_char),
+    set_surface: unsafe extern "C" fn(*const DesktopSurfaceInfo),
+    touch_event: unsafe extern "C" fn(AppPtr, u8, f32, f32, u64),
+    resize: unsafe extern "C" fn(AppPtr, u32, u32, f32),
+    set_lifecycle: unsafe extern "C" fn(AppPtr, u8),
+    send_message: unsafe extern "C" fn(AppPtr, *const u8, usize),
+    receive_message: unsafe extern "C" fn(AppPtr, *mut u8, usize) -> usize,
+    /// Keeps the entry points loaded
+    _library: Library,
+}
+
+impl HostFfi {
+    fn load(path: &PathBuf) -> Result<Self, libloading::Error> {
+        // SAFETY: the library is an embedded app built against this version of the crate,
+        // so the symbols have the signatures above
+        unsafe {
+            let library = Library::new(path)?;
+            Ok(Self {
+                create_app: *library.get(b"bevy_embedded_create_app\0")?,
+                update: *library.get(b"bevy_embedded_update\0")?,
+                destroy: *library.get(b"bevy_embedded_destroy\0")?,
+                get_last_error: *library.get(b"bevy_embedded_get_last_error\0")?,
+                free_error: *library.get(b"bevy_embedded_free_error\0")?,
+                set_surface: *library.get(b"bevy_embedded_desktop_set_surface\0")?,
+                touch_event: *library.get(b"bevy_embedded_desktop_touch_event\0")?,
+                resize: *library.get(b"bevy_embedded_desktop_resize\0")?,
+                set_lifecycle: *library.get(b"bevy_embedded_desktop_set_lifecycle\0")?,
+                send_message: *library.get(b"bevy_embedded_desktop_send_message\0")?,
+                receive_message: *library.get(b"bevy_embedded_desktop_receive_message\0")?,
+                _library: library,
+            })
+        }
+    }
+
+    fn last_error(&self) -> Option<String> {
+        let error = unsafe { (self.get_last_error)() };
+        if error.is_null() {
+            return None;
+        }
+        let message = unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe { (self.free_error)(error) };
+        Some(message)
+    }
+}
+
+/// A line typed into the channel REPL
+enum Command {
+    Send(Vec<u8>),
+    Lifecycle(u8),
+    Quit,
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+    match command {
+        "text" => Ok(Command::Send(argument.as_bytes().to_vec())),
+        "hex" => argument
+            .split_whitespace()
+            .map(|byte| u8::from_str_radix(byte, 16).map_err(|_| format!("invalid byte {byte:?}")))
+            .collect::<Result<_, _>>()
+            .map(Command::Send),
+        "lifecycle" => argument
+            .trim()
+            .parse()
+            .map(Command::Lifecycle)
+            .map_err(|_| format!("invalid lifecycle state {argument:?}")),
+        "quit" => Ok(Command::Quit),
+        _ => Err(format!(
+            "unknown command {command:?}; use text, hex, lifecycle or quit"
+        )),
+    }
+}
+
+/// Reads commands from stdin on its own thread
+fn spawn_repl() -> Receiver<Command> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_command(line.trim()) {
+                Ok(command) => {
+                    if sender.send(command).is_err() {
+                        break;
+                    }
+                }
+                Err(error) => eprintln!("{error}"),
+            }
+        }
+    });
+    receiver
+}
+
+fn print_message(message: &[u8]) {
+    if let Some((topic, payload)) = decode_reserved(message) {
+        match ReservedTopic::from_u16(topic) {
+            Some(topic) => println!("< {topic:?}: {} bytes", payload.len()),
+            None => println!("< reserved topic {topic}: {} bytes", payload.len()),
+        }
+    } else if let Ok(text) = std::str::from_utf8(message)
+        && !text.chars().any(char::is_control)
+    {
+        println!("< text {text}");
+    } else {
+        let hex: Vec<String> = message.iter().map(|byte| format!("{byte:02x}")).collect();
+        println!("< hex {}", hex.join(" "));
+    }
+}
+
+/// Describes the window's native handles for the app
+fn surface_info(window: &Window) -> Option<DesktopSurfaceInfo> {
+    let window_handle = window.window_handle().ok()?.as_raw();
+    let display_handle = window.display_handle().ok()?.as_raw();
+    let (kind, native_window, display) = match (window_handle, display_handle) {
+        (RawWindowHandle::AppKit(handle), _) => (
+            DesktopSurfaceKind::AppKit,
+            handle.ns_view.as_ptr(),
+            ptr::null_mut(),
+        ),
+        (RawWindowHandle::Win32(handle), _) => (
+            DesktopSurfaceKind::Win32,
+            handle.hwnd.get() as *mut c_void,
+            ptr::null_mut(),
+        ),
+        (RawWindowHandle::Xlib(handle), RawDisplayHandle::Xlib(display)) => (
+            DesktopSurfaceKind::Xlib,
+            handle.window as usize as *mut c_void,
+            display.display.map_or(ptr::null_mut(), NonNull::as_ptr),
+        ),
+        (RawWindowHandle::Wayland(handle), RawDisplayHandle::Wayland(display)) => (
+            DesktopSurfaceKind::Wayland,
+            handle.surface.as_ptr(),
+            display.display.as_ptr(),
+        ),
+        _ => return None,
+    };
+
+    let size = window.inner_size();
+    Some(DesktopSurfaceInfo {
+        kind: kind as u8,
+        window: native_window,
+        display,
+        width: size.width,
+        height: size.height,
+        scale_factor: window.scale_factor() as f32,
+    })
+}
+
+struct SimulatedHost {
+    ffi: HostFfi,
+    commands: Receiver<Command>,
+    window: Option<Window>,
+    app: AppPtr,
+    cursor: PhysicalPosition<f64>,
+    touching: bool,
+    buffer: Vec<u8>,
+    result: ExitCode,
+}
+
+impl SimulatedHost {
+    fn touch(&self, phase: TouchPhase) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        // Like UIKit, positions are passed in logical pixels
+        let position = self.cursor.to_logical::<f32>(window.scale_factor());
+        unsafe { (self.ffi.touch_event)(self.app, phase as u8, position.x, position.y, 0) };
+    }
+
+    fn run_commands(&mut self, event_loop: &ActiveEventLoop) {
+        while let Ok(command) = self.commands.try_recv() {
+            match command {
+                Command::Send(message) => unsafe {
+                    (self.ffi.send_message)(self.app, message.as_ptr(), message.len());
+                },
+                Command::Lifecycle(state) => unsafe { (self.ffi.set_lifecycle)(self.app, state) },
+                Command::Quit => event_loop.exit(),
+            }
+        }
+    }
+
+    fn frame(&mut self, event_loop: &ActiveEventLoop) {
+        self.run_commands(event_loop);
+
+        if unsafe { (self.ffi.update)(self.app) } != 0 {
+            let error = self.ffi.last_error().unwrap_or_default();
+            eprintln!("App exited with an error: {error}");
+            self.result = ExitCode::FAILURE;
+            event_loop.exit();
+            return;
+        }
+
+        loop {
+            let len = unsafe {
+                (self.ffi.receive_message)(self.app, self.buffer.as_mut_ptr(), self.buffer.len())
+            };
+            if len == 0 {
+                break;
+            }
+            print_message(&self.buffer[..len]);
+        }
+    }
+}
+
+impl ApplicationHandler for SimulatedHost {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+        let attributes = Window::default_attributes()
+            .with_title("Simulated host")
+            .with_inner_size(SCREEN_SIZE);
+        let window = match event_loop.create_window(attributes) {
+            Ok(window) => window,
+            Err(error) => {
+                eprintln!("Failed to create window: {error}");
+                self.result = ExitCode::FAILURE;
+                return event_loop.exit();
+            }
+        };
+        let Some(surface) = surface_info(&window) else {
+            eprintln!("Unsupported windowing system");
+            self.result = ExitCode::FAILURE;
+            return event_loop.exit();
+        };
+
+        unsafe { (self.ffi.set_surface)(&surface) };
+        self.app = unsafe { (self.ffi.create_app)() };
+        if self.app.is_null() {
+            let error = self.ffi.last_error().unwrap_or_default();
+            eprintln!("Failed to create app: {error}");
+            self.result = ExitCode::FAILURE;
+            return event_loop.exit();
+        }
+        self.window = Some(window);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        if self.app.is_null() {
+            return;
+        }
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::RedrawRequested => self.frame(event_loop),
+            WindowEvent::Resized(size) => {
+                if let Some(window) = &self.window
+                    && size.width > 0
+                    && size.height > 0
+                {
+                    let scale_factor = window.scale_factor() as f32;
+                    unsafe { (self.ffi.resize)(self.app, size.width, size.height, scale_factor) };
+                }
+            }
+            WindowEvent::Focused(focused) => {
+                let state = if focused { 0 } else { 1 };
+                unsafe { (self.ffi.set_lifecycle)(self.app, state) };
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = position;
+                if self.touching {
+                    self.touch(TouchPhase::Moved);
+                }
+            }
+            WindowEvent::CursorLeft { .. } if self.touching => {
+                self.touching = false;
+                self.touch(TouchPhase::Cancelled);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.touching = state == ElementState::Pressed;
+                self.touch(if self.touching {
+                    TouchPhase::Started
+                } else {
+                    TouchPhase::Ended
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if !self.app.is_null() {
+            unsafe { (self.ffi.destroy)(self.app) };
+            self.app = ptr::null_mut();
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args_os().nth(1).map(PathBuf::from) else {
+        eprintln!("Usage: simulated_host <path to the app's cdylib>");
+        return ExitCode::FAILURE;
+    };
+    let ffi = match HostFfi::load(&path) {
+        Ok(ffi) => ffi,
+        Err(error) => {
+            eprintln!("Failed to load {}: {error}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let event_loop = match EventLoop::new() {
+        Ok(event_loop) => event_loop,
+        Err(error) => {
+            eprintln!("Failed to create event loop: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    let mut host = SimulatedHost {
+        ffi,
+        commands: spawn_repl(),
+        window: None,
+        app: ptr::null_mut(),
+        cursor: PhysicalPosition::default(),
+        touching: false,
+        buffer: vec![0; RECEIVE_BUFFER_LEN],
+        result: ExitCode::SUCCESS,
+    };
+    if let Err(error) = event_loop.run_app(&mut host) {
+        eprintln!("Event loop failed: {error}");
+        return ExitCode::FAILURE;
+    }
+    host.result
+}
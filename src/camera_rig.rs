@@ -0,0 +1,358 @@
+//! Touch-controlled orbit camera
+//!
+//! With [`CameraRigPlugin`] added, add [`OrbitCamera`] to a camera entity to orbit around
+//! a target with one finger, pan with two, and zoom by pinching. Movement coasts to a stop
+//! after the fingers lift, slowed by [`OrbitCamera::damping`]. The rig writes the camera's
+//! `Transform` every frame it is enabled.
+//!
+//! Hosts adjust the rig with [`ReservedTopic::CameraRig`] commands, a sequence of
+//! operations each starting with a [`CameraRigOp`] byte:
+//!
+//! ```text
+//! SetTarget:         target: 3 × f32
+//! SetOrbit:          yaw: f32 | pitch: f32 | distance: f32
+//! SetDistanceLimits: min: f32 | max: f32
+//! SetPitchLimits:    min: f32 | max: f32 (radians)
+//! SetDamping:        damping: f32
+//! SetEnabled:        enabled: u8
+//! ```
+//!
+//! Commands apply to every orbit camera. To place the camera exactly, the host can pass
+//! a view matrix with [`set_camera_view`](crate::ffi::set_camera_view), which disables
+//! the rig until it is enabled again.
+
+use std::collections::BTreeMap;
+
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate},
+    ecs::{
+        change_detection::DetectChangesMut,
+        component::Component,
+        message::MessageReader,
+        schedule::IntoScheduleConfigs,
+        system::{Local, Query, Res},
+    },
+    input::touch::{TouchInput, TouchPhase},
+    math::{Quat, Vec2, Vec3},
+    time::Time,
+    transform::{TransformSystems, components::Transform},
+};
+
+use crate::{
+    AccessibilitySettings,
+    protocol::{ByteReader, HostCommand, ReservedTopic},
+};
+
+/// Velocities below this are treated as stopped
+const REST_VELOCITY: f32 = 1e-4;
+
+/// Drives [`OrbitCamera`]s from touches and host commands; see the [module docs](self)
+#[derive(Default)]
+pub struct CameraRigPlugin;
+
+impl Plugin for CameraRigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, receive_camera_rig_commands)
+            .add_systems(
+                PostUpdate,
+                update_orbit_cameras.before(TransformSystems::Propagate),
+            );
+    }
+}
+
+/// Operations that can appear in a camera rig command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CameraRigOp {
+    /// Set the point the camera orbits around
+    SetTarget = 0,
+    /// Set yaw, pitch and distance
+    SetOrbit = 1,
+    /// Set the zoom limits
+    SetDistanceLimits = 2,
+    /// Set the pitch limits
+    SetPitchLimits = 3,
+    /// Set how quickly movement slows down after a gesture
+    SetDamping = 4,
+    /// Enable or disable the rig
+    SetEnabled = 5,
+}
+
+impl CameraRigOp {
+    /// Create a CameraRigOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(CameraRigOp::SetTarget),
+            1 => Some(CameraRigOp::SetOrbit),
+            2 => Some(CameraRigOp::SetDistanceLimits),
+            3 => Some(CameraRigOp::SetPitchLimits),
+            4 => Some(CameraRigOp::SetDamping),
+            5 => Some(CameraRigOp::SetEnabled),
+            _ => None,
+        }
+    }
+}
+
+/// Orbit/pan/zoom camera controlled by touch
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct OrbitCamera {
+    /// Whether touches move the camera and the rig writes its transform
+    pub enabled: bool,
+    /// Point the camera looks at
+    pub target: Vec3,
+    /// Rotation around the Y axis in radians
+    pub yaw: f32,
+    /// Elevation above the target's horizontal plane in radians
+    pub pitch: f32,
+    /// Distance from the target
+    pub distance: f32,
+    /// Closest zoom
+    pub min_distance: f32,
+    /// Farthest zoom
+    pub max_distance: f32,
+    /// Lowest pitch
+    pub min_pitch: f32,
+    /// Highest pitch
+    pub max_pitch: f32,
+    /// Exponential decay rate of inertia per second; 0 coasts forever
+    pub damping: f32,
+    /// Radians of rotation per logical pixel dragged
+    pub orbit_sensitivity: f32,
+    /// Pan per logical pixel dragged, as a fraction of the distance
+    pub pan_sensitivity: f32,
+    /// Stop without coasting while the host asks to reduce motion
+    pub respect_reduce_motion: bool,
+    orbit_velocity: Vec2,
+    pan_velocity: Vec2,
+    zoom_velocity: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            target: Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.3,
+            distance: 5.0,
+            min_distance: 0.5,
+            max_distance: 50.0,
+            min_pitch: -1.5,
+            max_pitch: 1.5,
+            damping: 6.0,
+            orbit_sensitivity: 0.01,
+            pan_sensitivity: 0.002,
+            respect_reduce_motion: true,
+            orbit_velocity: Vec2::ZERO,
+            pan_velocity: Vec2::ZERO,
+            zoom_velocity: 0.0,
+        }
+    }
+}
+
+impl OrbitCamera {
+    /// Creates a rig with the camera at `eye` looking at `target`
+    pub fn looking_at(eye: Vec3, target: Vec3) -> Self {
+        let offset = eye - target;
+        let distance = offset.length();
+        Self {
+            target,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).asin(),
+            distance,
+            ..Default::default()
+        }
+    }
+
+    /// Stops any movement left over from a gesture
+    pub fn stop(&mut self) {
+        self.orbit_velocity = Vec2::ZERO;
+        self.pan_velocity = Vec2::ZERO;
+        self.zoom_velocity = 0.0;
+    }
+
+    /// The camera transform for the current target, angles and distance
+    pub fn transform(&self) -> Transform {
+        let rotation = Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(-self.pitch);
+        Transform {
+            translation: self.target + rotation * Vec3::Z * self.distance,
+            rotation,
+            ..Default::default()
+        }
+    }
+
+    /// Moves the rig by a gesture: orbit and pan in logical pixels, zoom as a log scale
+    fn apply(&mut self, orbit: Vec2, pan: Vec2, zoom: f32) {
+        self.yaw -= orbit.x * self.orbit_sensitivity;
+        self.pitch =
+            (self.pitch + orbit.y * self.orbit_sensitivity).clamp(self.min_pitch, self.max_pitch);
+
+        let rotation = self.transform().rotation;
+        let pan = pan * self.pan_sensitivity * self.distance;
+        self.target += rotation * Vec3::new(-pan.x, pan.y, 0.0);
+
+        self.distance = (self.distance * zoom.exp()).clamp(self.min_distance, self.max_distance);
+    }
+
+    /// Applies one command operation, returning `None` if the payload is malformed
+    fn apply_op(&mut self, op: CameraRigOp, reader: &mut ByteReader) -> Option<()> {
+        match op {
+            CameraRigOp::SetTarget => {
+                self.target = Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?);
+                self.stop();
+            }
+            CameraRigOp::SetOrbit => {
+                let (yaw, pitch, distance) = (reader.f32()?, reader.f32()?, reader.f32()?);
+                self.yaw = yaw;
+                self.pitch = pitch.clamp(self.min_pitch, self.max_pitch);
+                self.distance = distance.clamp(self.min_distance, self.max_distance);
+                self.stop();
+            }
+            CameraRigOp::SetDistanceLimits => {
+                let (min, max) = (reader.f32()?, reader.f32()?);
+                if !(min > 0.0 && min <= max) {
+                    log::warn!("Ignoring invalid camera distance limits {min}..={max}");
+                    return Some(());
+                }
+                (self.min_distance, self.max_distance) = (min, max);
+                self.distance = self.distance.clamp(min, max);
+            }
+            CameraRigOp::SetPitchLimits => {
+                let (min, max) = (reader.f32()?, reader.f32()?);
+                if min.is_nan() || max.is_nan() || min > max {
+                    log::warn!("Ignoring invalid camera pitch limits {min}..={max}");
+                    return Some(());
+                }
+                (self.min_pitch, self.max_pitch) = (min, max);
+                self.pitch = self.pitch.clamp(min, max);
+            }
+            CameraRigOp::SetDamping => self.damping = reader.f32()?.max(0.0),
+            CameraRigOp::SetEnabled => {
+                self.enabled = reader.bool()?;
+                self.stop();
+            }
+        }
+        Some(())
+    }
+}
+
+/// Applies camera rig commands from the host
+fn receive_camera_rig_commands(
+    mut commands: MessageReader<HostCommand>,
+    mut rigs: Query<&mut OrbitCamera>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::CameraRig {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while let Some(op) = reader.u8() {
+            let Some(op) = CameraRigOp::from_u8(op) else {
+                log::warn!("Ignoring camera rig command with unknown operation {op}");
+                break;
+            };
+            // Validate and skip the operands once, then apply them to every rig
+            let operands = reader.clone();
+            if OrbitCamera::default().apply_op(op, &mut reader).is_none() {
+                log::warn!("Ignoring malformed camera rig command");
+                break;
+            }
+            for mut rig in &mut rigs {
+                rig.apply_op(op, &mut operands.clone());
+            }
+        }
+    }
+}
+
+/// Turns touches into orbit, pan and zoom, and writes rig transforms
+fn update_orbit_cameras(
+    time: Res<Time>,
+    mut touches: MessageReader<TouchInput>,
+    mut active: Local<BTreeMap<u64, Vec2>>,
+    mut rigs: Query<(&mut OrbitCamera, &mut Transform)>,
+    accessibility: Option<Res<AccessibilitySettings>>,
+) {
+    let previous = active.clone();
+    let mut started = false;
+    for touch in touches.read() {
+        match touch.phase {
+            TouchPhase::Started => {
+                started = true;
+                active.insert(touch.id, touch.position);
+            }
+            TouchPhase::Moved => {
+                active.insert(touch.id, touch.position);
+            }
+            TouchPhase::Ended | TouchPhase::Canceled => {
+                active.remove(&touch.id);
+            }
+        }
+    }
+
+    // Fingers down both before and after this frame make up the gesture
+    let moved: Vec<(Vec2, Vec2)> = active
+        .iter()
+        .filter_map(|(id, position)| Some((*previous.get(id)?, *position)))
+        .collect();
+    let gesture = match moved.as_slice() {
+        [] => None,
+        [(from, to)] => Some((*to - *from, Vec2::ZERO, 0.0)),
+        [(from_a, to_a), (from_b, to_b), ..] => {
+            let pan = (*to_a + *to_b - *from_a - *from_b) / 2.0;
+            let spread = from_a.distance(*from_b) / to_a.distance(*to_b).max(1.0);
+            Some((Vec2::ZERO, pan, spread.max(f32::EPSILON).ln()))
+        }
+    };
+
+    let dt = time.delta_secs();
+    for (mut rig, mut transform) in &mut rigs {
+        if !rig.enabled {
+            continue;
+        }
+        if started {
+            rig.stop();
+        }
+
+        match gesture {
+            Some((orbit, pan, zoom)) => {
+                if dt > 0.0 {
+                    rig.orbit_velocity = orbit / dt;
+                    rig.pan_velocity = pan / dt;
+                    rig.zoom_velocity = zoom / dt;
+                }
+                rig.apply(orbit, pan, zoom);
+            }
+            None if active.is_empty()
+                && rig.respect_reduce_motion
+                && accessibility
+                    .as_ref()
+                    .is_some_and(|settings| settings.reduce_motion) =>
+            {
+                rig.stop();
+            }
+            None if active.is_empty() => {
+                let (orbit, pan, zoom) = (
+                    rig.orbit_velocity * dt,
+                    rig.pan_velocity * dt,
+                    rig.zoom_velocity * dt,
+                );
+                rig.apply(orbit, pan, zoom);
+
+                let decay = (-rig.damping * dt).exp();
+                rig.orbit_velocity *= decay;
+                rig.pan_velocity *= decay;
+                rig.zoom_velocity *= decay;
+                if rig.orbit_velocity.length() < REST_VELOCITY
+                    && rig.pan_velocity.length() < REST_VELOCITY
+                    && rig.zoom_velocity.abs() < REST_VELOCITY
+                {
+                    rig.stop();
+                }
+            }
+            None => {}
+        }
+
+        transform.set_if_neq(rig.transform());
+    }
+}
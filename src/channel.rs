@@ -1,8 +1,16 @@
 //! Binary channel communication between Bevy and the host application
 
+use std::sync::{Arc, Mutex, PoisonError};
+
 use bevy::ecs::resource::Resource;
 use crossbeam_channel::{Receiver, Sender, unbounded};
 
+use crate::{
+    SendPolicy,
+    protocol::{ReservedTopic, encode_reserved},
+    send_policy::SendPolicies,
+};
+
 /// Trait for bidirectional binary message passing
 pub trait BinaryChannel: Send + Sync {
     /// Send a binary message to the other end
@@ -12,36 +20,153 @@ pub trait BinaryChannel: Send + Sync {
     fn receive(&self) -> Option<Vec<u8>>;
 }
 
-/// Resource wrapping a platform-specific channel implementation
+/// Resource wrapping the Bevy side of the channel to the host
+///
+/// Messages sent here are delivered to the host, and messages the host sends through
+/// its [`HostEndpoint`] are received here. The two directions are independent queues.
+///
+/// Reserved messages (see [`protocol`](crate::protocol)) are routed to `HostCommand`
+/// at the start of each frame, so `receive` only yields the app's own messages.
 #[derive(Resource)]
 pub struct HostChannel {
     sender: Sender<Vec<u8>>,
     receiver: Receiver<Vec<u8>>,
+    app_sender: Sender<Vec<u8>>,
+    app_receiver: Receiver<Vec<u8>>,
+    endpoint: HostEndpoint,
+    policies: SendPolicies,
+    #[cfg(feature = "channel_encryption")]
+    cipher: Option<crate::ChannelCipher>,
 }
 
 impl Default for HostChannel {
     fn default() -> Self {
-        let (sender, receiver) = unbounded();
-        Self { sender, receiver }
+        let (to_host_sender, to_host_receiver) = unbounded();
+        let (to_bevy_sender, to_bevy_receiver) = unbounded();
+        let (app_sender, app_receiver) = unbounded();
+        Self {
+            sender: to_host_sender,
+            receiver: to_bevy_receiver,
+            app_sender,
+            app_receiver,
+            endpoint: HostEndpoint {
+                sender: to_bevy_sender,
+                receiver: to_host_receiver,
+                held: Arc::default(),
+            },
+            policies: SendPolicies::default(),
+            #[cfg(feature = "channel_encryption")]
+            cipher: None,
+        }
     }
 }
 
 impl HostChannel {
     /// Creates a new host channel with the given sender and receiver
+    ///
+    /// Bevy sends on `sender` and receives from `receiver`, so the host talks to Bevy
+    /// through their other ends. The [`endpoint`](Self::endpoint) of this channel is not
+    /// connected to them, so the message FFI can't reach an app using it.
+    #[deprecated(note = "use `HostChannel::default()` and talk to Bevy through `endpoint()`")]
     pub fn new(sender: Sender<Vec<u8>>, receiver: Receiver<Vec<u8>>) -> Self {
-        Self { sender, receiver }
+        Self {
+            sender,
+            receiver,
+            ..Self::default()
+        }
     }
 
     /// Send a message to the host
     pub fn send(&self, data: Vec<u8>) {
+        #[cfg(feature = "channel_encryption")]
+        let Some(data) = self.seal(data) else {
+            return;
+        };
         let _ = self.sender.send(data);
     }
 
+    /// Send a message to the host under the [`SendPolicy`] of `topic`
+    ///
+    /// See [`send_policy`](crate::send_policy).
+    pub fn send_topic(&self, topic: u16, data: Vec<u8>) {
+        self.policies.send(topic, data, |data| self.send(data));
+    }
+
+    /// Set how messages sent with [`send_topic`](Self::send_topic) on `topic` reach the host
+    pub fn set_send_policy(&self, topic: u16, policy: SendPolicy) {
+        self.policies.set(topic, policy);
+    }
+
+    /// Number of messages on `topic` its send policy dropped or coalesced away
+    pub fn dropped_messages(&self, topic: u16) -> u64 {
+        self.policies.dropped(topic)
+    }
+
+    /// Send the messages send policies held back that are due
+    pub(crate) fn flush_topics(&self) {
+        self.policies.flush(|data| self.send(data));
+    }
+
     /// Receive a message from the host (non-blocking)
     pub fn receive(&self) -> Option<Vec<u8>> {
+        self.app_receiver.try_recv().ok()
+    }
+
+    /// Send a reserved message to the host
+    pub fn send_reserved(&self, topic: ReservedTopic, payload: &[u8]) {
+        self.send(encode_reserved(topic, payload));
+    }
+
+    /// Receive the next message from the host before reserved messages are routed
+    pub(crate) fn receive_raw(&self) -> Option<Vec<u8>> {
+        #[cfg(feature = "channel_encryption")]
+        if let Some(cipher) = &self.cipher {
+            use crate::ChannelDirection;
+
+            // Messages that fail to open were not sent by the key's holder
+            while let Ok(sealed) = self.receiver.try_recv() {
+                match cipher.open(ChannelDirection::ToBevy, &sealed) {
+                    Some(message) => return Some(message),
+                    None => log::warn!("Dropping host message that failed to authenticate"),
+                }
+            }
+            return None;
+        }
         self.receiver.try_recv().ok()
     }
 
+    /// Seal and open messages with `cipher` from now on
+    ///
+    /// See [`encryption`](crate::encryption).
+    #[cfg(feature = "channel_encryption")]
+    pub fn set_cipher(&mut self, cipher: crate::ChannelCipher) {
+        self.cipher = Some(cipher);
+    }
+
+    /// Whether messages are sealed with a channel key
+    #[cfg(feature = "channel_encryption")]
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    #[cfg(feature = "channel_encryption")]
+    fn seal(&self, data: Vec<u8>) -> Option<Vec<u8>> {
+        match &self.cipher {
+            Some(cipher) => cipher.seal(crate::ChannelDirection::ToHost, &data),
+            None => Some(data),
+        }
+    }
+
+    /// Make a routed message available to `receive`
+    pub(crate) fn queue_app_message(&self, data: Vec<u8>) {
+        let _ = self.app_sender.send(data);
+    }
+
+    /// Get the host side of this channel for use in FFI
+    pub fn endpoint(&self) -> HostEndpoint {
+        self.endpoint.clone()
+    }
+
     /// Get a clone of the sender for use in FFI
     pub fn get_sender(&self) -> Sender<Vec<u8>> {
         self.sender.clone()
@@ -62,3 +187,162 @@ impl BinaryChannel for HostChannel {
         self.receive()
     }
 }
+
+/// The host side of a [`HostChannel`]
+///
+/// Sending here delivers to Bevy systems reading `HostChannel::receive`, and receiving
+/// here yields what Bevy systems passed to `HostChannel::send`.
+#[derive(Clone)]
+pub struct HostEndpoint {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+    /// A message too large for the buffer passed to `receive_into`, received next
+    held: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl HostEndpoint {
+    /// Send a message to Bevy
+    pub fn send(&self, data: Vec<u8>) {
+        let _ = self.sender.send(data);
+    }
+
+    /// Send a reserved command to Bevy
+    pub fn send_reserved(&self, topic: ReservedTopic, payload: &[u8]) {
+        self.send(encode_reserved(topic, payload));
+    }
+
+    /// Receive a message from Bevy (non-blocking)
+    pub fn receive(&self) -> Option<Vec<u8>> {
+        self.held
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .or_else(|| self.receiver.try_recv().ok())
+    }
+
+    /// Copy the next message from Bevy into `buffer` (non-blocking)
+    ///
+    /// Returns the message length, or 0 if none is waiting. If it exceeds the buffer,
+    /// nothing is copied and the message stays queued for a larger buffer.
+    pub fn receive_into(&self, buffer: &mut [u8]) -> usize {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(message) = held.take().or_else(|| self.receiver.try_recv().ok()) else {
+            return 0;
+        };
+        let len = message.len();
+        match buffer.get_mut(..len) {
+            Some(buffer) => buffer.copy_from_slice(&message),
+            None => *held = Some(message),
+        }
+        len
+    }
+
+    /// Copy up to `max_messages` messages from Bevy into `buffer` (non-blocking), each
+    /// framed as `len: u32 | len × u8`
+    ///
+    /// Returns the number of bytes written, or 0 if no message is waiting. Messages that
+    /// don't fit stay queued for the next call. If not even the first one fits, nothing is
+    /// copied and its framed length, larger than the buffer, is returned.
+    pub fn receive_batch_into(&self, buffer: &mut [u8], max_messages: usize) -> usize {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut written = 0;
+        for _ in 0..max_messages {
+            let Some(message) = held.take().or_else(|| self.receiver.try_recv().ok()) else {
+                break;
+            };
+            let end = written + size_of::<u32>() + message.len();
+            let Some(frame) = buffer.get_mut(written..end) else {
+                *held = Some(message);
+                return if written == 0 { end } else { written };
+            };
+            let (len, bytes) = frame.split_at_mut(size_of::<u32>());
+            len.copy_from_slice(&(message.len() as u32).to_le_bytes());
+            bytes.copy_from_slice(&message);
+            written = end;
+        }
+        written
+    }
+
+    /// Number of messages from Bevy waiting to be received
+    pub fn pending(&self) -> usize {
+        let held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+        self.receiver.len() + held.is_some() as usize
+    }
+}
+
+impl BinaryChannel for HostEndpoint {
+    fn send(&self, data: Vec<u8>) {
+        self.send(data);
+    }
+
+    fn receive(&self) -> Option<Vec<u8>> {
+        self.receive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directions_are_separate_queues() {
+        let channel = HostChannel::default();
+        let endpoint = channel.endpoint();
+
+        channel.send(b"to host".to_vec());
+        endpoint.send(b"to bevy".to_vec());
+
+        assert_eq!(endpoint.receive(), Some(b"to host".to_vec()));
+        assert_eq!(endpoint.receive(), None);
+        assert_eq!(channel.receive_raw(), Some(b"to bevy".to_vec()));
+        assert_eq!(channel.receive_raw(), None);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn new_uses_the_given_queues() {
+        let (to_host, from_bevy) = unbounded();
+        let (to_bevy, from_host) = unbounded();
+        let channel = HostChannel::new(to_host, from_host);
+
+        channel.send(b"to host".to_vec());
+        to_bevy.send(b"to bevy".to_vec()).unwrap();
+
+        assert_eq!(from_bevy.try_recv().ok(), Some(b"to host".to_vec()));
+        assert_eq!(channel.receive_raw(), Some(b"to bevy".to_vec()));
+
+        // The getters return Bevy's own ends
+        channel.get_sender().send(b"again".to_vec()).unwrap();
+        assert_eq!(from_bevy.try_recv().ok(), Some(b"again".to_vec()));
+        to_bevy.send(b"queued".to_vec()).unwrap();
+        assert_eq!(
+            channel.get_receiver().try_recv().ok(),
+            Some(b"queued".to_vec())
+        );
+    }
+
+    #[test]
+    fn batch_keeps_messages_that_dont_fit() {
+        let channel = HostChannel::default();
+        let endpoint = channel.endpoint();
+        channel.send(b"one".to_vec());
+        channel.send(b"three".to_vec());
+
+        // Only the first frame fits
+        let mut buffer = [0; 10];
+        assert_eq!(endpoint.receive_batch_into(&mut buffer, 8), 7);
+        assert_eq!(&buffer[..7], b"\x03\0\0\0one");
+        assert_eq!(endpoint.pending(), 1);
+
+        // Not even the first frame fits: its length is returned and nothing is copied
+        let mut small = [0; 4];
+        assert_eq!(endpoint.receive_batch_into(&mut small, 8), 9);
+        assert_eq!(small, [0; 4]);
+        assert_eq!(endpoint.pending(), 1);
+
+        let mut buffer = [0; 9];
+        assert_eq!(endpoint.receive_batch_into(&mut buffer, 8), 9);
+        assert_eq!(&buffer, b"\x05\0\0\0three");
+        assert_eq!(endpoint.receive_batch_into(&mut buffer, 8), 0);
+    }
+}
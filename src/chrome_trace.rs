@@ -0,0 +1,261 @@
+//! Chrome traces of schedules and systems, recorded on the device
+//!
+//! Profilers attached over USB rarely work inside a host app's release-like build, and a
+//! widget's frame is hard to find in a capture of the whole process. With the
+//! `chrome_trace` cargo feature, the host starts and stops a trace of the app's spans
+//! (every schedule and system, and the app's own `info_span!`s) with
+//! `bevy_embedded_ios_start_chrome_trace(path)` / `nativeStartChromeTrace(path)` and
+//! `bevy_embedded_ios_stop_chrome_trace()` / `nativeStopChromeTrace()`. The file at the
+//! host's path is a JSON trace to pull off the device and open in `chrome://tracing` or
+//! [Perfetto](https://ui.perfetto.dev).
+//!
+//! Spans reach the trace through [`ChromeTraceLayer`]. Without a tracing subscriber, the
+//! crate installs one when a trace starts. Apps using Bevy's `LogPlugin` add the layer to
+//! theirs, as the global subscriber can't be replaced:
+//!
+//! ```ignore
+//! app.add_plugins(DefaultPlugins.set(LogPlugin {
+//!     custom_layer: bevy_embedded::chrome_trace_layer,
+//!     ..default()
+//! }));
+//! ```
+//!
+//! Tracing is process-wide: a trace records every app in the process.
+
+use std::{
+    cell::Cell,
+    fmt::{self, Write as _},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+
+use bevy::app::App;
+use tracing::{
+    Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id},
+};
+use tracing_subscriber::{Layer, Registry, layer::Context, prelude::*, registry::LookupSpan};
+
+/// Whether a trace is being recorded, checked before taking the lock
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// Whether a [`ChromeTraceLayer`] was added to a subscriber
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+static TRACE: Mutex<Option<TraceFile>> = Mutex::new(None);
+
+/// Next id given to a thread that records
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Id of this thread in traces, and the trace it was named in
+    static THREAD: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+
+/// Increments with every trace, so threads are named once in each
+static TRACE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+struct TraceFile {
+    writer: BufWriter<File>,
+    started: Instant,
+    generation: u64,
+    events: u64,
+}
+
+impl TraceFile {
+    fn write_event(&mut self, event: fmt::Arguments) {
+        let separator = if self.events == 0 { "" } else { ",\n" };
+        self.events += 1;
+        // A failed write is reported when the trace stops
+        let _ = write!(self.writer, "{separator}{event}");
+    }
+}
+
+/// Records spans into the running Chrome trace; see the [module docs](self)
+#[derive(Debug)]
+pub struct ChromeTraceLayer {
+    _private: (),
+}
+
+impl Default for ChromeTraceLayer {
+    fn default() -> Self {
+        INSTALLED.store(true, Ordering::Release);
+        Self { _private: () }
+    }
+}
+
+/// The layer for `LogPlugin::custom_layer`
+pub fn chrome_trace_layer(_app: &mut App) -> Option<Box<dyn Layer<Registry> + Send + Sync>> {
+    Some(Box::new(ChromeTraceLayer::default()))
+}
+
+/// Name of a span shown in the trace: the `name` field of Bevy's schedule and system spans
+struct SpanName(String);
+
+struct NameVisitor(Option<String>);
+
+impl Visit for NameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+impl<S> Layer<S> for ChromeTraceLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    // Systems create their span once, long before a trace starts, so names are always kept
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = NameVisitor(None);
+        attrs.record(&mut visitor);
+        if let (Some(name), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(SpanName(name));
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        record_span(id, &ctx, 'B');
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        record_span(id, &ctx, 'E');
+    }
+}
+
+fn record_span<S>(id: &Id, ctx: &Context<'_, S>, phase: char)
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+    let Some(span) = ctx.span(id) else {
+        return;
+    };
+    let timestamp = Instant::now();
+    let Ok(mut trace) = TRACE.lock() else {
+        return;
+    };
+    let Some(trace) = trace.as_mut() else {
+        return;
+    };
+
+    let (thread, named_in) = THREAD.get();
+    let thread = if thread == 0 {
+        NEXT_THREAD.fetch_add(1, Ordering::Relaxed)
+    } else {
+        thread
+    };
+    if named_in != trace.generation {
+        THREAD.set((thread, trace.generation));
+        let name = std::thread::current()
+            .name()
+            .map_or_else(|| format!("thread {thread}"), str::to_owned);
+        trace.write_event(format_args!(
+            r#"{{"ph":"M","name":"thread_name","pid":1,"tid":{thread},"args":{{"name":"{}"}}}}"#,
+            Escaped(&name)
+        ));
+    }
+
+    let metadata = span.metadata();
+    let extensions = span.extensions();
+    let name = extensions
+        .get::<SpanName>()
+        .map_or(metadata.name(), |name| name.0.as_str());
+    let micros = timestamp
+        .saturating_duration_since(trace.started)
+        .as_secs_f64()
+        * 1e6;
+    trace.write_event(format_args!(
+        r#"{{"ph":"{phase}","name":"{}","cat":"{}","ts":{micros:.3},"pid":1,"tid":{thread}}}"#,
+        Escaped(name),
+        Escaped(metadata.target())
+    ));
+}
+
+/// A string escaped for a JSON string literal
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Starts recording a trace into a new file at `path`
+///
+/// Fails if a trace is already recording, the file can't be created, or the process
+/// has a tracing subscriber without a [`ChromeTraceLayer`].
+pub fn start_chrome_trace(path: &Path) -> io::Result<()> {
+    if !INSTALLED.load(Ordering::Acquire) {
+        let subscriber = Registry::default().with(ChromeTraceLayer::default());
+        if tracing::subscriber::set_global_default(subscriber).is_err() {
+            INSTALLED.store(false, Ordering::Release);
+            return Err(io::Error::other(
+                "the tracing subscriber has no ChromeTraceLayer",
+            ));
+        }
+    }
+
+    let mut trace = TRACE
+        .lock()
+        .map_err(|_| io::Error::other("trace lock poisoned"))?;
+    if trace.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "a trace is already recording",
+        ));
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"[\n")?;
+    *trace = Some(TraceFile {
+        writer,
+        started: Instant::now(),
+        generation: TRACE_GENERATION.fetch_add(1, Ordering::Relaxed) + 1,
+        events: 0,
+    });
+    RECORDING.store(true, Ordering::Relaxed);
+    log::info!("Recording Chrome trace to {}", path.display());
+    Ok(())
+}
+
+/// Stops recording and completes the trace file
+///
+/// Returns the number of events written, or `None` if no trace was recording.
+pub fn stop_chrome_trace() -> io::Result<Option<u64>> {
+    RECORDING.store(false, Ordering::Relaxed);
+    let trace = TRACE
+        .lock()
+        .map_err(|_| io::Error::other("trace lock poisoned"))?
+        .take();
+    let Some(mut trace) = trace else {
+        return Ok(None);
+    };
+    trace.writer.write_all(b"\n]\n")?;
+    trace.writer.flush()?;
+    log::info!("Stopped Chrome trace with {} events", trace.events);
+    Ok(Some(trace.events))
+}
@@ -0,0 +1,241 @@
+//! Correlating the host's monotonic clock with Bevy's
+//!
+//! Host messages, sensor samples and audio buffers carry timestamps from the host's clock
+//! (`CACurrentMediaTime`, `SystemClock.elapsedRealtimeNanos`), which shares no epoch with
+//! the `Instant`s behind Bevy's `Time`. The host reports its current time with
+//! `bevy_embedded_ios_sync_clock` / `nativeSyncClock`, typically right before each
+//! update, and [`HostClock`] fits an offset and a drift to the recent samples so any host
+//! timestamp can be converted. The resource is added by the first report:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{HostAudioInput, HostClock};
+//!
+//! fn audio_age(
+//!     input: Res<HostAudioInput>,
+//!     clock: Option<Res<HostClock>>,
+//!     time: Res<Time<Real>>,
+//! ) {
+//!     let Some(clock) = clock else {
+//!         return;
+//!     };
+//!     for buffer in input.buffers() {
+//!         if let Some(captured) = clock.to_real_elapsed(buffer.timestamp, &time) {
+//!             let age = time.elapsed().saturating_sub(captured);
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    ecs::resource::Resource,
+    time::{Real, Time},
+};
+
+/// Number of recent samples the estimate is fitted to
+const MAX_SAMPLES: usize = 64;
+
+/// Seconds between kept samples; the newest sample is replaced by readings until they are
+/// this far from the one before it, so the samples cover about 16 s rather than a second
+/// of frames
+const SAMPLE_INTERVAL: f64 = 0.25;
+
+/// Shortest span of samples, in seconds, before drift is estimated
+const MIN_DRIFT_SPAN: f64 = 4.0;
+
+/// Mapping between the host's monotonic clock and Bevy's
+///
+/// Host times are in seconds. The estimate is `host = local * (1 + drift) + offset`, where
+/// `local` is the seconds since this resource was created.
+#[derive(Resource, Debug, Clone)]
+pub struct HostClock {
+    anchor: Instant,
+    /// `(local, host)` pairs, oldest first
+    samples: VecDeque<(f64, f64)>,
+    offset: f64,
+    drift: f64,
+}
+
+impl Default for HostClock {
+    fn default() -> Self {
+        Self {
+            anchor: Instant::now(),
+            samples: VecDeque::with_capacity(MAX_SAMPLES),
+            offset: 0.0,
+            drift: 0.0,
+        }
+    }
+}
+
+impl HostClock {
+    /// Records that the host clock read `host_time` seconds at `now`
+    pub fn record(&mut self, host_time: f64, now: Instant) {
+        let local = self.local_seconds(now);
+        let previous = self.samples.len().checked_sub(2);
+        if let Some(&(previous, _)) = previous.and_then(|index| self.samples.get(index))
+            && let Some(&(newest, _)) = self.samples.back()
+            && newest - previous < SAMPLE_INTERVAL
+        {
+            // The newest sample is still too close to the one before; it moves forward
+            self.samples.pop_back();
+        } else if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((local, host_time));
+        self.fit();
+    }
+
+    /// Returns true once the host has reported its time at least once
+    pub fn is_synced(&self) -> bool {
+        !self.samples.is_empty()
+    }
+
+    /// Host time, in seconds, when this resource was created
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// How much faster the host clock runs, e.g. `1e-5` for 10 ppm
+    pub fn drift(&self) -> f64 {
+        self.drift
+    }
+
+    /// Converts a host timestamp into a local `Instant`
+    pub fn to_instant(&self, host_time: f64) -> Option<Instant> {
+        if !self.is_synced() {
+            return None;
+        }
+        let local = (host_time - self.offset) / (1.0 + self.drift);
+        if local >= 0.0 {
+            self.anchor
+                .checked_add(Duration::try_from_secs_f64(local).ok()?)
+        } else {
+            self.anchor
+                .checked_sub(Duration::try_from_secs_f64(-local).ok()?)
+        }
+    }
+
+    /// Converts a local `Instant` into a host timestamp
+    pub fn to_host(&self, instant: Instant) -> Option<f64> {
+        self.is_synced()
+            .then(|| self.local_seconds(instant) * (1.0 + self.drift) + self.offset)
+    }
+
+    /// Converts a host timestamp into the matching `Time<Real>::elapsed`
+    ///
+    /// Returns `None` before the first sync or for timestamps before the app started.
+    pub fn to_real_elapsed(&self, host_time: f64, time: &Time<Real>) -> Option<Duration> {
+        self.to_instant(host_time)?
+            .checked_duration_since(time.startup())
+    }
+
+    fn local_seconds(&self, instant: Instant) -> f64 {
+        match instant.checked_duration_since(self.anchor) {
+            Some(since) => since.as_secs_f64(),
+            None => -self.anchor.duration_since(instant).as_secs_f64(),
+        }
+    }
+
+    /// Least squares fit of the samples; drift stays zero until they span long enough
+    fn fit(&mut self) {
+        let count = self.samples.len() as f64;
+        let mean_local = self.samples.iter().map(|(local, _)| local).sum::<f64>() / count;
+        let mean_host = self.samples.iter().map(|(_, host)| host).sum::<f64>() / count;
+
+        let span = self.samples.back().map_or(0.0, |(local, _)| *local)
+            - self.samples.front().map_or(0.0, |(local, _)| *local);
+        let rate = if span >= MIN_DRIFT_SPAN {
+            let (covariance, variance) =
+                self.samples
+                    .iter()
+                    .fold((0.0, 0.0), |(covariance, variance), (local, host)| {
+                        let dx = local - mean_local;
+                        (covariance + dx * (host - mean_host), variance + dx * dx)
+                    });
+            covariance / variance
+        } else {
+            1.0
+        };
+
+        self.drift = rate - 1.0;
+        self.offset = mean_host - rate * mean_local;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFSET: f64 = 1000.0;
+    const DRIFT: f64 = 2e-5;
+
+    /// Records readings of a host clock with [`OFFSET`] and [`DRIFT`] every frame at 60 Hz
+    /// for `seconds`
+    fn synced_clock(seconds: f64) -> HostClock {
+        let mut clock = HostClock::default();
+        let frames = (seconds * 60.0) as u32;
+        for frame in 0..=frames {
+            let local = frame as f64 / 60.0;
+            let now = clock.anchor + Duration::from_secs_f64(local);
+            clock.record(local * (1.0 + DRIFT) + OFFSET, now);
+        }
+        clock
+    }
+
+    #[test]
+    fn fits_offset_and_drift() {
+        let clock = synced_clock(10.0);
+        assert!((clock.offset() - OFFSET).abs() < 1e-6, "{}", clock.offset());
+        assert!((clock.drift() - DRIFT).abs() < 1e-9, "{}", clock.drift());
+    }
+
+    #[test]
+    fn drift_waits_for_a_long_enough_span() {
+        let clock = synced_clock(MIN_DRIFT_SPAN / 2.0);
+        assert_eq!(clock.drift(), 0.0);
+        assert!((clock.offset() - OFFSET).abs() < 1e-3, "{}", clock.offset());
+    }
+
+    #[test]
+    fn conversions_round_trip() {
+        let clock = synced_clock(10.0);
+        let seconds = |instant: Instant| clock.local_seconds(instant);
+        for local in [-5.0, 0.0, 3.5, 10.0, 3600.0] {
+            let host = local * (1.0 + DRIFT) + OFFSET;
+            let instant = clock.to_instant(host).unwrap();
+            assert!((seconds(instant) - local).abs() < 1e-6, "{local}");
+            assert!(
+                (clock.to_host(instant).unwrap() - host).abs() < 1e-6,
+                "{local}"
+            );
+        }
+    }
+
+    #[test]
+    fn conversions_wait_for_the_first_sample() {
+        let clock = HostClock::default();
+        assert!(!clock.is_synced());
+        assert_eq!(clock.to_instant(OFFSET), None);
+        assert_eq!(clock.to_host(Instant::now()), None);
+    }
+
+    #[test]
+    fn samples_are_spaced_and_bounded() {
+        let clock = synced_clock(30.0);
+        assert_eq!(clock.samples.len(), MAX_SAMPLES);
+        // All but the newest sample are at least an interval apart
+        let kept = clock.samples.iter().take(MAX_SAMPLES - 1);
+        for (before, after) in kept.clone().zip(kept.skip(1)) {
+            assert!(after.0 - before.0 >= SAMPLE_INTERVAL - 1e-9);
+        }
+        // The newest sample follows the latest reading
+        assert!((clock.samples.back().unwrap().0 - 30.0).abs() < 1e-9);
+        let span = clock.samples.back().unwrap().0 - clock.samples.front().unwrap().0;
+        assert!(span > (MAX_SAMPLES - 2) as f64 * SAMPLE_INTERVAL, "{span}");
+    }
+}
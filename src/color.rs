@@ -0,0 +1,40 @@
+//! Color spaces the host surface can present in
+//!
+//! The host picks one at creation with the `color_space` creation config key. Bevy
+//! presents windows in sRGB; the wide color spaces are presented by
+//! `ColorOutputPlugin`, which needs the `wide_color` feature.
+
+/// Color space of the host surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum SurfaceColorSpace {
+    /// Standard dynamic range sRGB
+    #[default]
+    Srgb = 0,
+    /// Display P3 wide color gamut
+    DisplayP3 = 1,
+    /// Display P3 with highlights brighter than SDR white, on HDR-capable displays
+    Hdr = 2,
+}
+
+impl SurfaceColorSpace {
+    /// Create a SurfaceColorSpace from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SurfaceColorSpace::Srgb),
+            1 => Some(SurfaceColorSpace::DisplayP3),
+            2 => Some(SurfaceColorSpace::Hdr),
+            _ => None,
+        }
+    }
+
+    /// Parses the names accepted in host creation config (`srgb`, `display_p3`, `hdr`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "srgb" => Some(SurfaceColorSpace::Srgb),
+            "display_p3" => Some(SurfaceColorSpace::DisplayP3),
+            "hdr" => Some(SurfaceColorSpace::Hdr),
+            _ => None,
+        }
+    }
+}
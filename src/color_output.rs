@@ -0,0 +1,708 @@
+//! Presenting the primary window in Display P3 and HDR
+//!
+//! Bevy 0.17 presents windows through an 8-bit sRGB swapchain, which can't show colors
+//! outside sRGB or brighter than SDR white. For an app created with a
+//! [`SurfaceColorSpace`] other than sRGB, [`ColorOutputPlugin`] presents the primary
+//! window itself:
+//!
+//! - Cameras rendering to the primary window render into an `Rgba16Float` texture in
+//!   place of the swapchain image. They get Bevy's [`Hdr`] marker, so their output keeps
+//!   colors outside sRGB and above 1.0, and [`Tonemapping::None`], as the output pass
+//!   maps the result to the display instead.
+//! - An output pass draws that texture into an `Rgba16Float` swapchain, which the
+//!   platform composites as extended linear sRGB (`kCGColorSpaceExtendedLinearSRGB` on
+//!   iOS, `VK_COLOR_SPACE_EXTENDED_SRGB_LINEAR_EXT` on Android). It clips colors to the
+//!   Display P3 gamut and clamps them to SDR white for [`SurfaceColorSpace::DisplayP3`],
+//!   or rolls highlights off towards [`ColorOutput::headroom`] for
+//!   [`SurfaceColorSpace::Hdr`].
+//! - Surfaces without a float format get an 8-bit sRGB swapchain, and the pass clips to
+//!   sRGB. GLES windows are left to Bevy.
+//!
+//! Add the plugin after `DefaultPlugins`, so the render world exists:
+//!
+//! ```ignore
+//! app.add_plugins((DefaultPlugins.build().disable::<WinitPlugin>(), EmbeddedPlugin))
+//!     .add_plugins(ColorOutputPlugin);
+//! ```
+//!
+//! Cameras rendering into [host frames](crate::HostFrame) and offscreen targets bypass
+//! the output pass. A [frozen](crate::RecoveryAction::FreezeFrame) app keeps its last
+//! frame on screen instead of presenting its fallback.
+
+use std::{borrow::Cow, collections::HashMap, num::NonZero};
+
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    camera::{Camera, RenderTarget},
+    color::LinearRgba,
+    core_pipeline::tonemapping::Tonemapping,
+    ecs::{
+        entity::Entity,
+        query::{Changed, Has, With},
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Commands, Local, Query, Res, ResMut},
+    },
+    render::{
+        Extract, ExtractSchedule, Render, RenderApp, RenderSystems,
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_resource::{
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BufferInitDescriptor,
+            BufferUsages, ColorTargetState, ColorWrites, CommandEncoderDescriptor, Extent3d,
+            FilterMode, LoadOp, Operations, PipelineLayoutDescriptor, RawFragmentState,
+            RawRenderPipelineDescriptor, RawVertexState, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipeline, Sampler, SamplerBindingType, SamplerDescriptor,
+            ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, StoreOp, Texture,
+            TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+            TextureView,
+            binding_types::{sampler, texture_2d, uniform_buffer_sized},
+        },
+        renderer::{
+            RenderAdapter, RenderAdapterInfo, RenderDevice, RenderInstance, RenderQueue,
+            WgpuWrapper, render_system,
+        },
+        view::{
+            ExtractedWindow, ExtractedWindows, Hdr, prepare_view_attachments, prepare_view_targets,
+            window::{create_surfaces, prepare_windows},
+        },
+    },
+    window::{CompositeAlphaMode, PresentMode, PrimaryWindow, RawHandleWrapper, WindowRef},
+};
+
+use crate::{EmbeddedConfig, SurfaceColorSpace, frozen_frame::FrozenRender};
+
+/// Headroom of [`SurfaceColorSpace::Hdr`] output if the host doesn't set `hdr_headroom`
+pub const DEFAULT_HDR_HEADROOM: f32 = 2.0;
+
+/// Format cameras of the primary window render into, and of swapchains that present it
+const OUTPUT_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Presents the primary window in the app's wide color space; see the
+/// [module docs](self)
+#[derive(Default)]
+pub struct ColorOutputPlugin;
+
+impl Plugin for ColorOutputPlugin {
+    fn build(&self, app: &mut App) {
+        let color_output = app
+            .world()
+            .get_resource::<EmbeddedConfig>()
+            .map(|config| ColorOutput {
+                color_space: config.color_space,
+                headroom: config.hdr_headroom.unwrap_or(DEFAULT_HDR_HEADROOM),
+            })
+            .unwrap_or_default();
+        app.insert_resource(color_output)
+            .add_plugins(ExtractResourcePlugin::<ColorOutput>::default());
+
+        // Bevy presents sRGB windows itself
+        if color_output.color_space == SurfaceColorSpace::Srgb {
+            return;
+        }
+
+        app.add_systems(PostUpdate, mark_hdr_cameras);
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<OutputSurface>()
+                .init_resource::<OutputPipelines>()
+                .add_systems(ExtractSchedule, extract_output_window)
+                .add_systems(
+                    Render,
+                    (
+                        take_output_window
+                            .after(crate::surface_epoch::cancel_stale_frame)
+                            .before(create_surfaces)
+                            .before(prepare_windows),
+                        restore_output_window
+                            .in_set(RenderSystems::ManageViews)
+                            .after(prepare_windows)
+                            .before(prepare_view_attachments)
+                            .before(prepare_view_targets),
+                        present_color_output
+                            .in_set(RenderSystems::Render)
+                            .after(render_system),
+                    ),
+                )
+                // Frozen apps don't render, but Bevy mustn't create a second surface
+                .add_systems(FrozenRender, take_output_window.before(create_surfaces));
+        }
+    }
+}
+
+/// The color space the primary window presents in
+#[derive(Resource, ExtractResource, Debug, Clone, Copy, PartialEq)]
+pub struct ColorOutput {
+    color_space: SurfaceColorSpace,
+    /// Brightest output of [`SurfaceColorSpace::Hdr`], as a multiple of SDR white
+    ///
+    /// Highlights roll off towards it; values below 1.0 are treated as 1.0.
+    pub headroom: f32,
+}
+
+impl Default for ColorOutput {
+    fn default() -> Self {
+        Self {
+            color_space: SurfaceColorSpace::Srgb,
+            headroom: DEFAULT_HDR_HEADROOM,
+        }
+    }
+}
+
+impl ColorOutput {
+    /// The color space the host created the app with
+    pub fn color_space(&self) -> SurfaceColorSpace {
+        self.color_space
+    }
+}
+
+/// Marks cameras that start rendering to the primary window as HDR, and leaves
+/// tonemapping to the output pass
+fn mark_hdr_cameras(
+    mut commands: Commands,
+    cameras: Query<(Entity, &Camera, Has<Hdr>), Changed<Camera>>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+) {
+    let primary = primary_window.single().ok();
+    for (entity, camera, hdr) in &cameras {
+        if hdr {
+            continue;
+        }
+        let targets_primary = match &camera.target {
+            RenderTarget::Window(WindowRef::Primary) => true,
+            RenderTarget::Window(WindowRef::Entity(window)) => Some(*window) == primary,
+            _ => false,
+        };
+        if targets_primary {
+            commands.entity(entity).insert((Hdr, Tonemapping::None));
+        }
+    }
+}
+
+/// The surface of the primary window and the texture its cameras render into
+#[derive(Resource, Default)]
+struct OutputSurface {
+    surface: Option<SurfaceData>,
+    target: Option<(Texture, TextureView)>,
+    /// The primary window while Bevy's window systems run without it
+    window: Option<ExtractedWindow>,
+    /// Whether cameras rendered into the target this frame
+    rendered: bool,
+}
+
+struct SurfaceData {
+    handle: RawHandleWrapper,
+    surface: WgpuWrapper<wgpu::Surface<'static>>,
+    configuration: wgpu::SurfaceConfiguration,
+    /// Whether `configuration` was applied to the surface
+    configured: bool,
+}
+
+/// Drops the surface once the primary window loses or replaces its host surface
+fn extract_output_window(
+    mut output: ResMut<OutputSurface>,
+    primary_window: Extract<Query<&RawHandleWrapper, With<PrimaryWindow>>>,
+) {
+    let Some(surface) = &output.surface else {
+        return;
+    };
+    let handle = primary_window
+        .single()
+        .ok()
+        .map(RawHandleWrapper::get_window_handle);
+    if handle != Some(surface.handle.get_window_handle()) {
+        log::debug!("Dropping the color output surface of a replaced window surface");
+        output.surface = None;
+    }
+}
+
+/// Takes the primary window out of the extracted windows, so Bevy doesn't create a
+/// surface for it, and prepares the surface and target the frame is presented through
+fn take_output_window(
+    // Surfaces must be created on the main thread on Apple platforms
+    #[cfg(any(target_os = "macos", target_os = "ios"))] _marker: bevy::ecs::system::NonSendMarker,
+    mut windows: ResMut<ExtractedWindows>,
+    mut output: ResMut<OutputSurface>,
+    render_instance: Res<RenderInstance>,
+    render_adapter: Res<RenderAdapter>,
+    adapter_info: Res<RenderAdapterInfo>,
+    render_device: Res<RenderDevice>,
+    mut gles_warned: Local<bool>,
+) {
+    output.window = None;
+    if adapter_info.backend == wgpu::Backend::Gl {
+        if !*gles_warned {
+            log::warn!("GLES surfaces have no float formats; presenting sRGB");
+            *gles_warned = true;
+        }
+        return;
+    }
+    let Some(window) = windows
+        .primary
+        .and_then(|primary| windows.windows.remove(&primary))
+    else {
+        return;
+    };
+
+    if output.surface.is_none() {
+        output.surface = create_surface(&window, &render_instance, &render_adapter);
+    }
+    let Some(surface) = output.surface.as_mut() else {
+        return;
+    };
+    let configuration = &mut surface.configuration;
+    let present_mode = wgpu_present_mode(window.present_mode);
+    if configuration.width != window.physical_width
+        || configuration.height != window.physical_height
+        || configuration.present_mode != present_mode
+        || !surface.configured
+    {
+        configuration.width = window.physical_width;
+        configuration.height = window.physical_height;
+        configuration.present_mode = present_mode;
+        render_device.configure_surface(&surface.surface, configuration);
+        surface.configured = true;
+    }
+
+    let size = (window.physical_width, window.physical_height);
+    if output
+        .target
+        .as_ref()
+        .is_none_or(|(texture, _)| (texture.width(), texture.height()) != size)
+    {
+        let texture = render_device.create_texture(&TextureDescriptor {
+            label: Some("color_output_target"),
+            size: Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: OUTPUT_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+        output.target = Some((texture, view));
+    }
+    output.window = Some(window);
+}
+
+/// Creates the surface of `window` and its configuration, in `Rgba16Float` if the surface
+/// can present it
+fn create_surface(
+    window: &ExtractedWindow,
+    render_instance: &RenderInstance,
+    render_adapter: &RenderAdapter,
+) -> Option<SurfaceData> {
+    let target = wgpu::SurfaceTargetUnsafe::RawHandle {
+        raw_display_handle: window.handle.get_display_handle(),
+        raw_window_handle: window.handle.get_window_handle(),
+    };
+    // SAFETY: the host keeps the surface of an extracted window alive until the app
+    // replaces it, which drops this surface first
+    let surface = match unsafe { render_instance.create_surface_unsafe(target) } {
+        Ok(surface) => surface,
+        Err(error) => {
+            log::error!("Failed to create the color output surface: {error}");
+            return None;
+        }
+    };
+
+    let formats = surface.get_capabilities(render_adapter).formats;
+    let format = if formats.contains(&OUTPUT_FORMAT) {
+        OUTPUT_FORMAT
+    } else {
+        log::warn!("Surface has no {OUTPUT_FORMAT:?} format; presenting sRGB");
+        formats
+            .iter()
+            .copied()
+            .find(|format| {
+                matches!(
+                    format,
+                    TextureFormat::Rgba8UnormSrgb | TextureFormat::Bgra8UnormSrgb
+                )
+            })
+            .or_else(|| formats.first().copied())?
+    };
+
+    #[cfg(target_os = "ios")]
+    if format == OUTPUT_FORMAT
+        && let raw_window_handle::RawWindowHandle::UiKit(handle) = window.handle.get_window_handle()
+    {
+        // SAFETY: the handle is the host's UIView, and this runs on the main thread
+        unsafe { crate::ios::tag_extended_linear_srgb(handle.ui_view.as_ptr()) };
+    }
+
+    let configuration = wgpu::SurfaceConfiguration {
+        usage: TextureUsages::RENDER_ATTACHMENT,
+        format,
+        width: window.physical_width,
+        height: window.physical_height,
+        present_mode: wgpu_present_mode(window.present_mode),
+        desired_maximum_frame_latency: window.desired_maximum_frame_latency.map_or(2, NonZero::get),
+        alpha_mode: match window.alpha_mode {
+            CompositeAlphaMode::Auto => wgpu::CompositeAlphaMode::Auto,
+            CompositeAlphaMode::Opaque => wgpu::CompositeAlphaMode::Opaque,
+            CompositeAlphaMode::PreMultiplied => wgpu::CompositeAlphaMode::PreMultiplied,
+            CompositeAlphaMode::PostMultiplied => wgpu::CompositeAlphaMode::PostMultiplied,
+            CompositeAlphaMode::Inherit => wgpu::CompositeAlphaMode::Inherit,
+        },
+        view_formats: if format.is_srgb() {
+            vec![]
+        } else {
+            vec![format.add_srgb_suffix()]
+        },
+    };
+    Some(SurfaceData {
+        handle: window.handle.clone(),
+        surface: WgpuWrapper::new(surface),
+        configuration,
+        configured: false,
+    })
+}
+
+fn wgpu_present_mode(present_mode: PresentMode) -> wgpu::PresentMode {
+    match present_mode {
+        PresentMode::Fifo => wgpu::PresentMode::Fifo,
+        PresentMode::FifoRelaxed => wgpu::PresentMode::FifoRelaxed,
+        PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+        PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        PresentMode::AutoVsync => wgpu::PresentMode::AutoVsync,
+        PresentMode::AutoNoVsync => wgpu::PresentMode::AutoNoVsync,
+    }
+}
+
+/// Puts the primary window back with the target as its swapchain image, for its cameras
+/// to render into
+fn restore_output_window(mut windows: ResMut<ExtractedWindows>, mut output: ResMut<OutputSurface>) {
+    let Some(mut window) = output.window.take() else {
+        return;
+    };
+    let Some((_, view)) = &output.target else {
+        return;
+    };
+    window.swap_chain_texture_view = Some(view.clone());
+    window.swap_chain_texture_format = Some(OUTPUT_FORMAT);
+    window.swap_chain_texture = None;
+    windows.insert(window.entity, window);
+    output.rendered = true;
+}
+
+const OUTPUT_SHADER: &str = r"
+@group(0) @binding(0) var frame: texture_2d<f32>;
+@group(0) @binding(1) var frame_sampler: sampler;
+
+struct Output {
+    // Brightest value to present, as a multiple of SDR white
+    peak: f32,
+    // 1 to clip to Display P3, 0 to sRGB
+    gamut: u32,
+    _padding: vec2<f32>,
+}
+@group(0) @binding(2) var<uniform> output: Output;
+
+// Linear sRGB and Display P3 share their white point and transfer function
+const SRGB_TO_P3 = mat3x3<f32>(
+    vec3<f32>(0.8224621, 0.0331941, 0.0170827),
+    vec3<f32>(0.1775380, 0.9668058, 0.0723974),
+    vec3<f32>(0.0, 0.0, 0.9105199),
+);
+const P3_TO_SRGB = mat3x3<f32>(
+    vec3<f32>(1.2249401, -0.0420569, -0.0196376),
+    vec3<f32>(-0.2249404, 1.0420571, -0.0786361),
+    vec3<f32>(0.0, 0.0, 1.0982735),
+);
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// One triangle covering the viewport
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return VertexOutput(vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0), uv);
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(frame, frame_sampler, in.uv);
+    var rgb = color.rgb;
+    if output.gamut == 1u {
+        rgb = SRGB_TO_P3 * rgb;
+    }
+    rgb = max(rgb, vec3<f32>(0.0));
+
+    // Roll the brightest channel off towards the peak, keeping the hue
+    let brightest = max(rgb.r, max(rgb.g, rgb.b));
+    let range = max(output.peak - 1.0, 0.0);
+    if brightest > 1.0 {
+        var mapped = 1.0;
+        if range > 0.0 {
+            mapped = 1.0 + range * (1.0 - exp((1.0 - brightest) / range));
+        }
+        rgb *= mapped / brightest;
+    }
+
+    if output.gamut == 1u {
+        rgb = P3_TO_SRGB * rgb;
+    }
+    return vec4<f32>(rgb, color.a);
+}
+";
+
+/// Pipelines of the output pass, one per surface format
+#[derive(Resource, Default)]
+struct OutputPipelines {
+    shared: Option<(ShaderModule, BindGroupLayout, Sampler)>,
+    pipelines: HashMap<TextureFormat, RenderPipeline>,
+}
+
+impl OutputPipelines {
+    fn shared(
+        &mut self,
+        render_device: &RenderDevice,
+    ) -> &(ShaderModule, BindGroupLayout, Sampler) {
+        self.shared.get_or_insert_with(|| {
+            let shader = render_device.create_and_validate_shader_module(ShaderModuleDescriptor {
+                label: Some("color_output_shader"),
+                source: ShaderSource::Wgsl(Cow::Borrowed(OUTPUT_SHADER)),
+            });
+            let layout = render_device.create_bind_group_layout(
+                "color_output_layout",
+                &BindGroupLayoutEntries::sequential(
+                    ShaderStages::FRAGMENT,
+                    (
+                        texture_2d(TextureSampleType::Float { filterable: true }),
+                        sampler(SamplerBindingType::Filtering),
+                        uniform_buffer_sized(false, NonZero::new(16)),
+                    ),
+                ),
+            );
+            let sampler = render_device.create_sampler(&SamplerDescriptor {
+                label: Some("color_output_sampler"),
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            });
+            (shader, layout, sampler)
+        })
+    }
+
+    fn pipeline(&mut self, render_device: &RenderDevice, format: TextureFormat) -> RenderPipeline {
+        if let Some(pipeline) = self.pipelines.get(&format) {
+            return pipeline.clone();
+        }
+        let (shader, layout, _) = self.shared(render_device);
+        let pipeline_layout = render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("color_output_pipeline_layout"),
+            bind_group_layouts: &[layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = render_device.create_render_pipeline(&RawRenderPipelineDescriptor {
+            label: Some("color_output_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: RawVertexState {
+                module: shader,
+                entry_point: Some("vertex"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            fragment: Some(RawFragmentState {
+                module: shader,
+                entry_point: Some("fragment"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        self.pipelines.insert(format, pipeline.clone());
+        pipeline
+    }
+}
+
+/// Draws the frame the cameras rendered into the surface and presents it
+fn present_color_output(
+    mut output: ResMut<OutputSurface>,
+    color_output: Res<ColorOutput>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut pipelines: ResMut<OutputPipelines>,
+) {
+    if !std::mem::take(&mut output.rendered) {
+        return;
+    }
+    let OutputSurface {
+        surface: Some(surface_data),
+        target: Some((_, source)),
+        ..
+    } = &mut *output
+    else {
+        return;
+    };
+
+    let surface = &surface_data.surface;
+    let frame = match surface.get_current_texture() {
+        Ok(frame) => frame,
+        Err(wgpu::SurfaceError::Outdated) => {
+            render_device.configure_surface(surface, &surface_data.configuration);
+            match surface.get_current_texture() {
+                Ok(frame) => frame,
+                Err(error) => {
+                    log::warn!("Couldn't get a color output texture after configuring: {error}");
+                    return;
+                }
+            }
+        }
+        Err(wgpu::SurfaceError::Lost) => {
+            log::warn!("Color output surface lost, creating it again");
+            output.surface = None;
+            return;
+        }
+        Err(error) => {
+            log::warn!("Couldn't get a color output texture: {error}");
+            return;
+        }
+    };
+
+    let format = surface_data.configuration.format.add_srgb_suffix();
+    let target = frame.texture.create_view(&wgpu::TextureViewDescriptor {
+        format: Some(format),
+        ..Default::default()
+    });
+    // 8-bit swapchains only present sRGB
+    let (peak, gamut) = match (format, color_output.color_space) {
+        (OUTPUT_FORMAT, SurfaceColorSpace::Hdr) => (color_output.headroom.max(1.0), 1u32),
+        (OUTPUT_FORMAT, _) => (1.0, 1),
+        _ => (1.0, 0),
+    };
+    let mut uniform = [0; 16];
+    uniform[..4].copy_from_slice(&peak.to_le_bytes());
+    uniform[4..8].copy_from_slice(&gamut.to_le_bytes());
+    let uniform = render_device.create_buffer_with_data(&BufferInitDescriptor {
+        label: Some("color_output_uniform"),
+        contents: &uniform,
+        usage: BufferUsages::UNIFORM,
+    });
+
+    let pipeline = pipelines.pipeline(&render_device, format);
+    let (_, layout, sampler) = pipelines.shared(&render_device);
+    let bind_group = render_device.create_bind_group(
+        "color_output_bind_group",
+        layout,
+        &BindGroupEntries::sequential((&*source, sampler, uniform.as_entire_binding())),
+    );
+
+    let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("color_output"),
+    });
+    let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("color_output"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view: &target,
+            depth_slice: None,
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Clear(LinearRgba::BLACK.into()),
+                store: StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    pass.set_pipeline(&pipeline);
+    pass.set_bind_group(0, &*bind_group, &[]);
+    pass.draw(0..3, 0..1);
+    drop(pass);
+
+    render_queue.submit([encoder.finish()]);
+    frame.present();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{MinimalPlugins, camera::ManualTextureViewHandle};
+
+    use super::*;
+    use crate::{
+        HostConfig,
+        test::{MockHost, MockSurface},
+    };
+
+    fn host(color_space: &str) -> MockHost {
+        MockHost::from_setup(MockSurface::default(), |app| {
+            let mut host_config = HostConfig::new();
+            host_config
+                .set("color_space", color_space)
+                .set("hdr_headroom", "4");
+            app.world_mut()
+                .resource_mut::<EmbeddedConfig>()
+                .apply_host_config(host_config);
+            app.add_plugins((MinimalPlugins, ColorOutputPlugin));
+        })
+    }
+
+    fn spawn_cameras(host: &mut MockHost) -> (Entity, Entity) {
+        let world = host.app_mut().world_mut();
+        let window = world.spawn(Camera::default()).id();
+        let image = world
+            .spawn(Camera {
+                target: RenderTarget::TextureView(ManualTextureViewHandle(0)),
+                ..Default::default()
+            })
+            .id();
+        (window, image)
+    }
+
+    #[test]
+    fn cameras_of_the_primary_window_render_hdr() {
+        let mut host = host("hdr");
+        let (window, image) = spawn_cameras(&mut host);
+        host.update();
+
+        let world = host.app().world();
+        let output = world.resource::<ColorOutput>();
+        assert_eq!(output.color_space(), SurfaceColorSpace::Hdr);
+        assert_eq!(output.headroom, 4.0);
+        assert!(world.entity(window).contains::<Hdr>());
+        assert_eq!(
+            world.entity(window).get::<Tonemapping>(),
+            Some(&Tonemapping::None)
+        );
+        assert!(!world.entity(image).contains::<Hdr>());
+
+        // Retargeted cameras are marked too
+        let world = host.app_mut().world_mut();
+        world.entity_mut(image).get_mut::<Camera>().unwrap().target =
+            RenderTarget::Window(WindowRef::Primary);
+        host.update();
+        assert!(host.app().world().entity(image).contains::<Hdr>());
+    }
+
+    #[test]
+    fn srgb_apps_keep_their_cameras() {
+        let mut host = host("srgb");
+        let (window, _) = spawn_cameras(&mut host);
+        host.update();
+
+        let world = host.app().world();
+        assert_eq!(
+            world.resource::<ColorOutput>().color_space(),
+            SurfaceColorSpace::Srgb
+        );
+        assert!(!world.entity(window).contains::<Hdr>());
+    }
+}
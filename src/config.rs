@@ -0,0 +1,375 @@
+//! Creation-time configuration for embedded apps
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::app::App;
+use bevy::asset::{
+    AssetApp,
+    io::{AssetSourceBuilder, AssetSourceId, file::FileAssetReader},
+};
+use bevy::ecs::resource::Resource;
+
+use crate::{
+    DiagnosticsStream, DynamicResolutionSettings, GpuBackend, GpuLimits, GpuPreferences,
+    HostCoordinateSpace, LaunchParameters, PictureInPictureSettings, ProtocolSchema,
+    RecoveryPolicy, SurfaceColorSpace, SurfaceTransform, TaskPoolSizes, ThreadPolicy, TimeLimits,
+};
+
+/// Creation config values from the host, in the order they were set
+///
+/// Hosts build one through a handle (`bevy_embedded_ios_new_config`, `nativeNewConfig`)
+/// and pass it with the call creating the app, so apps created concurrently, on worker
+/// threads or for several scenes each get their own values. See
+/// [`EmbeddedConfig::apply_host_config`] for the keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostConfig {
+    values: Vec<(String, String)>,
+}
+
+impl HostConfig {
+    /// Creates an empty config
+    pub const fn new() -> Self {
+        Self { values: Vec::new() }
+    }
+
+    /// Sets `key` to `value`; a later value of the same key takes precedence
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.values.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// Configuration for an embedded app instance
+///
+/// Returned by [`EmbeddedApp::config`](crate::EmbeddedApp::config) and inserted as a
+/// resource before `EmbeddedPlugin` is built, so plugins and systems can inspect how
+/// the instance was created.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct EmbeddedConfig {
+    /// Run without a window or surface
+    ///
+    /// In headless mode the host does not provide a surface and no `Window` entity is
+    /// created. Bevy can still be used as an ECS, simulation, or asset engine behind a
+    /// native renderer. Leave `RenderPlugin` out of the app (or configure it for
+    /// offscreen rendering only) when running headless.
+    pub headless: bool,
+
+    /// Coordinate space of touch positions passed by the host
+    ///
+    /// Defaults to the platform's native space (points on iOS, pixels on Android).
+    /// Positions are converted to logical pixels before reaching `TouchInput`.
+    pub touch_coordinates: HostCoordinateSpace,
+
+    /// Initial rotation and mirroring between the content and the host surface
+    ///
+    /// Copied into the [`SurfaceTransform`] resource when `EmbeddedPlugin` is built.
+    pub surface_transform: SurfaceTransform,
+
+    /// Resolution and frame rate used while presented picture-in-picture
+    pub picture_in_picture: PictureInPictureSettings,
+
+    /// Color space the primary window presents in
+    ///
+    /// Anything but sRGB is presented by `ColorOutputPlugin`, which needs the
+    /// `wide_color` feature; see [`SurfaceColorSpace`].
+    pub color_space: SurfaceColorSpace,
+
+    /// Brightest [`SurfaceColorSpace::Hdr`] output as a multiple of SDR white, or `None`
+    /// for `DEFAULT_HDR_HEADROOM`
+    pub hdr_headroom: Option<f32>,
+
+    /// Initial dynamic resolution scaling limits, used by
+    /// [`DynamicResolutionPlugin`](crate::DynamicResolutionPlugin); the host can change them
+    /// at runtime
+    pub dynamic_resolution: DynamicResolutionSettings,
+
+    /// How far one late frame advances the simulation
+    ///
+    /// Inserted as the [`TimeLimits`] resource when `EmbeddedPlugin` is built.
+    pub time_limits: TimeLimits,
+
+    /// Threads of Bevy's task pools, if this is the first app in the process
+    ///
+    /// See [`TaskPoolSizes`].
+    pub task_pools: TaskPoolSizes,
+
+    /// What happens to calls from a thread other than the app's
+    ///
+    /// See [`app_thread`](crate::app_thread).
+    pub thread_policy: ThreadPolicy,
+
+    /// Deep link and context to open the scene with
+    ///
+    /// Inserted as the [`LaunchParameters`] resource when `EmbeddedPlugin` is built.
+    pub launch: LaunchParameters,
+
+    /// Folder inside the bundled assets that this instance loads its assets from
+    ///
+    /// Lets instances with different content packs coexist in one process: with
+    /// `Some("packs/winter")`, `models/tree.glb` loads from `packs/winter/models/tree.glb`.
+    /// On Android the folder is inside the APK's assets, elsewhere inside the `assets`
+    /// folder Bevy reads by default, or inside [`asset_path`](Self::asset_path) if set.
+    pub asset_root: Option<String>,
+
+    /// Directory on the file system to load assets from instead of the bundled assets
+    ///
+    /// Typically content the host downloaded and unpacked into the app's files or caches
+    /// directory, so content can be updated without an app update. Relative paths are
+    /// resolved against the folder Bevy finds its `assets` folder in.
+    pub asset_path: Option<PathBuf>,
+
+    /// Time from creation to the first frame the host allows
+    ///
+    /// A slower start is logged with the duration of each phase.
+    /// See [`StartupTimings`](crate::StartupTimings).
+    pub startup_budget: Option<Duration>,
+
+    /// Time without updates after which the app counts as stalled, or `None` to not
+    /// detect stalls
+    ///
+    /// See [`Heartbeat`](crate::Heartbeat).
+    pub heartbeat_timeout: Option<Duration>,
+
+    /// Time the final updates may take when the app is destroyed, or `None` for
+    /// [`DEFAULT_TEARDOWN_TIMEOUT`](crate::teardown::DEFAULT_TEARDOWN_TIMEOUT)
+    ///
+    /// See [`teardown`](crate::teardown).
+    pub teardown_timeout: Option<Duration>,
+
+    /// Adapter, limits and graphics API to render with
+    ///
+    /// Applied by [`with_gpu_preferences`](crate::GpuPreferencePlugins::with_gpu_preferences).
+    pub gpu: GpuPreferences,
+
+    /// What the app does after errors and panics; see [`recovery`](crate::recovery)
+    pub recovery: RecoveryPolicy,
+
+    /// Bevy diagnostics sent to the host, or `None` to not stream them
+    ///
+    /// Inserted as the [`DiagnosticsStream`] resource when `EmbeddedPlugin` is built.
+    pub diagnostics: Option<DiagnosticsStream>,
+
+    /// Message schema and codecs the app announces in the [handshake](crate::Handshake)
+    pub protocol: ProtocolSchema,
+
+    /// Key sealing the messages of this instance's channel, if the host gave one
+    ///
+    /// See [`encryption`](crate::encryption).
+    #[cfg(feature = "channel_encryption")]
+    pub channel_key: Option<crate::ChannelKey>,
+
+    /// Host-provided values the crate does not interpret, for the app to read
+    pub values: BTreeMap<String, String>,
+}
+
+impl EmbeddedConfig {
+    /// Creates a configuration for a headless (window-less) app
+    pub fn headless() -> Self {
+        Self {
+            headless: true,
+            ..Default::default()
+        }
+    }
+
+    /// Applies values the host set for this app
+    ///
+    /// Recognised keys are `headless` (`true`/`false`), `touch_coordinates`
+    /// (`logical`/`physical`), `color_space` (`srgb`/`display_p3`/`hdr`), `hdr_headroom`,
+    /// `launch_url`, `launch_context`, `asset_root`, `asset_path`, `gpu_power`
+    /// (`low_power`/`high_performance`), `gpu_limits` (`adapter`/`downlevel`/`webgl2`),
+    /// `gpu_backend` (`auto`/`primary`/`gles`), `vulkan_blocklist` (comma-separated),
+    /// `startup_budget_ms`, `max_delta_ms`, `max_fixed_steps`, `thread_policy`
+    /// (`enforce`/`marshal`), `compute_threads`, `async_compute_threads`, `io_threads`,
+    /// `heartbeat_timeout_ms` (0 to turn off), `teardown_timeout_ms`,
+    /// `diagnostics_interval_ms`, `diagnostics` (comma-separated paths) and, with the
+    /// `channel_encryption` feature, `channel_key` (64 hex digits). Anything else is
+    /// stored in [`values`](Self::values).
+    pub fn apply_host_config(&mut self, host_config: HostConfig) {
+        for (key, value) in host_config.values {
+            let applied = match key.as_str() {
+                "headless" => value
+                    .parse()
+                    .map(|headless| self.headless = headless)
+                    .is_ok(),
+                "touch_coordinates" => match value.as_str() {
+                    "logical" => {
+                        self.touch_coordinates = HostCoordinateSpace::Logical;
+                        true
+                    }
+                    "physical" => {
+                        self.touch_coordinates = HostCoordinateSpace::Physical;
+                        true
+                    }
+                    _ => false,
+                },
+                "color_space" => SurfaceColorSpace::from_name(&value)
+                    .map(|color_space| self.color_space = color_space)
+                    .is_some(),
+                "hdr_headroom" => value
+                    .parse()
+                    .ok()
+                    .filter(|headroom: &f32| headroom.is_finite() && *headroom >= 1.0)
+                    .map(|headroom| self.hdr_headroom = Some(headroom))
+                    .is_some(),
+                "launch_url" => {
+                    self.launch.url = Some(value);
+                    continue;
+                }
+                "launch_context" => {
+                    self.launch.context = Some(value);
+                    continue;
+                }
+                "asset_root" => {
+                    self.asset_root = Some(value);
+                    continue;
+                }
+                "asset_path" => {
+                    self.asset_path = Some(value.into());
+                    continue;
+                }
+                "gpu_power" => GpuPreferences::power_preference_from_name(&value)
+                    .map(|power| self.gpu.power_preference = Some(power))
+                    .is_some(),
+                "gpu_limits" => GpuLimits::from_name(&value)
+                    .map(|limits| self.gpu.limits = limits)
+                    .is_some(),
+                "gpu_backend" => GpuBackend::from_name(&value)
+                    .map(|backend| self.gpu.backend = backend)
+                    .is_some(),
+                "startup_budget_ms" => value
+                    .parse()
+                    .map(|ms| self.startup_budget = Some(Duration::from_millis(ms)))
+                    .is_ok(),
+                "max_delta_ms" => value
+                    .parse()
+                    .ok()
+                    .filter(|&ms| ms > 0)
+                    .map(|ms| self.time_limits.max_delta = Duration::from_millis(ms))
+                    .is_some(),
+                "max_fixed_steps" => value
+                    .parse()
+                    .ok()
+                    .filter(|&steps| steps > 0)
+                    .map(|steps| self.time_limits.max_fixed_steps = steps)
+                    .is_some(),
+                "thread_policy" => match value.as_str() {
+                    "enforce" => {
+                        self.thread_policy = ThreadPolicy::Enforce;
+                        true
+                    }
+                    "marshal" => {
+                        self.thread_policy = ThreadPolicy::Marshal;
+                        true
+                    }
+                    _ => false,
+                },
+                "compute_threads" => value
+                    .parse()
+                    .ok()
+                    .filter(|&threads| threads > 0)
+                    .map(|threads| self.task_pools.compute = threads)
+                    .is_some(),
+                "async_compute_threads" => value
+                    .parse()
+                    .ok()
+                    .filter(|&threads| threads > 0)
+                    .map(|threads| self.task_pools.async_compute = threads)
+                    .is_some(),
+                "io_threads" => value
+                    .parse()
+                    .ok()
+                    .filter(|&threads| threads > 0)
+                    .map(|threads| self.task_pools.io = threads)
+                    .is_some(),
+                "heartbeat_timeout_ms" => value
+                    .parse()
+                    .map(|ms| {
+                        self.heartbeat_timeout = (ms > 0).then(|| Duration::from_millis(ms));
+                    })
+                    .is_ok(),
+                "teardown_timeout_ms" => value
+                    .parse()
+                    .map(|ms| self.teardown_timeout = Some(Duration::from_millis(ms)))
+                    .is_ok(),
+                "diagnostics_interval_ms" => value
+                    .parse()
+                    .map(|ms| {
+                        self.diagnostics.get_or_insert_default().interval =
+                            Duration::from_millis(ms);
+                    })
+                    .is_ok(),
+                "diagnostics" => {
+                    self.diagnostics.get_or_insert_default().paths =
+                        DiagnosticsStream::paths_from_list(&value);
+                    continue;
+                }
+                "vulkan_blocklist" => {
+                    self.gpu.vulkan_blocklist = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|entry| !entry.is_empty())
+                        .map(str::to_owned)
+                        .collect();
+                    continue;
+                }
+                #[cfg(feature = "channel_encryption")]
+                "channel_key" => match crate::ChannelKey::from_hex(&value) {
+                    Some(key) => {
+                        self.channel_key = Some(key);
+                        continue;
+                    }
+                    None => {
+                        // Don't log the value
+                        log::warn!("Ignoring invalid host config value for channel_key");
+                        continue;
+                    }
+                },
+                _ => {
+                    self.values.insert(key, value);
+                    continue;
+                }
+            };
+            if !applied {
+                log::warn!("Ignoring invalid host config value {key}={value}");
+            }
+        }
+    }
+}
+
+/// Registers the default asset source for the app's [`EmbeddedConfig`]
+///
+/// Reads from [`asset_path`](EmbeddedConfig::asset_path) if the host gave one, and
+/// otherwise from the bundled assets. Must run before `AssetPlugin` is added.
+#[doc(hidden)]
+pub fn configure_asset_source(app: &mut App) {
+    let (asset_path, asset_root) = app
+        .world()
+        .get_resource::<EmbeddedConfig>()
+        .map(|config| (config.asset_path.clone(), config.asset_root.clone()))
+        .unwrap_or_default();
+    let root = asset_root.unwrap_or_default();
+    let root = root.trim_matches('/');
+
+    if let Some(asset_path) = asset_path {
+        let directory = asset_path.join(root);
+        log::debug!("Loading assets from {}", directory.display());
+        let source = AssetSourceBuilder::default()
+            .with_reader(move || Box::new(FileAssetReader::new(&directory)));
+        app.register_asset_source(AssetSourceId::Default, source);
+        return;
+    }
+
+    // The APK's assets aren't files, and Bevy's Android reader needs a native activity
+    #[cfg(target_os = "android")]
+    crate::android::configure_embedded_asset_source(app);
+
+    #[cfg(not(target_os = "android"))]
+    if !root.is_empty() {
+        app.register_asset_source(
+            AssetSourceId::Default,
+            AssetSourceBuilder::platform_default(&format!("assets/{root}"), None),
+        );
+    }
+}
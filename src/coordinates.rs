@@ -0,0 +1,74 @@
+//! Conversions between host and Bevy coordinate spaces
+//!
+//! Bevy's `TouchInput` positions and `Window` sizes are in logical pixels. Hosts report
+//! positions differently: UIKit uses points (already logical), while Android's
+//! `MotionEvent` uses physical pixels. [`HostCoordinateSpace`] records which one the host
+//! uses so positions can be converted with the host's scale factor.
+//!
+//! The host's scale factor is the one of the [`HostSurface`], not the window's: dynamic
+//! resolution and picture-in-picture lower the window's scale factor along with its
+//! physical resolution, while the host keeps reporting positions on the full surface.
+
+use bevy::{math::Vec2, window::Window};
+
+use crate::HostSurface;
+
+/// Coordinate space the host uses for positions it passes over FFI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostCoordinateSpace {
+    /// Logical pixels (iOS points, Android dp); identical to Bevy's window coordinates
+    Logical,
+    /// Physical pixels; divided by the host scale factor on the way into Bevy
+    Physical,
+}
+
+impl HostCoordinateSpace {
+    /// The space the platform's native touch APIs report in
+    pub const fn platform_default() -> Self {
+        if cfg!(target_os = "android") {
+            Self::Physical
+        } else {
+            Self::Logical
+        }
+    }
+
+    /// Converts a host position into Bevy logical pixels
+    pub fn to_logical(self, position: Vec2, scale_factor: f32) -> Vec2 {
+        match self {
+            Self::Logical => position,
+            Self::Physical => position / scale_factor,
+        }
+    }
+
+    /// Converts a Bevy logical position into this host space
+    pub fn from_logical(self, position: Vec2, scale_factor: f32) -> Vec2 {
+        match self {
+            Self::Logical => position,
+            Self::Physical => position * scale_factor,
+        }
+    }
+}
+
+impl Default for HostCoordinateSpace {
+    fn default() -> Self {
+        Self::platform_default()
+    }
+}
+
+/// Converts a position reported by the host into logical window coordinates
+pub fn host_to_window(position: Vec2, surface: &HostSurface, space: HostCoordinateSpace) -> Vec2 {
+    space.to_logical(position, surface.scale_factor)
+}
+
+/// Converts a logical window position into the host's coordinate space
+///
+/// Use this when sending positions (e.g. projected entity locations) back to the host.
+pub fn window_to_host(position: Vec2, surface: &HostSurface, space: HostCoordinateSpace) -> Vec2 {
+    space.from_logical(position, surface.scale_factor)
+}
+
+/// The host surface of `window`, or the window's own size and scale factor if the host
+/// hasn't reported one
+pub fn host_surface_of(surface: Option<&HostSurface>, window: &Window) -> HostSurface {
+    surface.copied().unwrap_or_else(|| HostSurface::of(window))
+}
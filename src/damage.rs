@@ -0,0 +1,102 @@
+//! Damage regions of each frame
+//!
+//! Mostly static content, such as a widget whose UI changes over a fixed 3D background,
+//! doesn't need the whole frame recomposited. Systems declare what changed in
+//! [`FrameDamage`] during the frame, and the host reads the damage after the update
+//! (`bevy_embedded_ios_frame_damage`, `nativeFrameDamage`) to composite only those
+//! regions, e.g. with `ASurfaceTransaction_setDamageRegion` or by invalidating part of a
+//! layer. wgpu can't present with damage rects, so the swapchain itself is still
+//! presented in full.
+//!
+//! Frames where no system declared anything are fully damaged, so apps that don't use
+//! the API keep working. A frame declared [unchanged](FrameDamage::unchanged) that
+//! renders into an [`OffscreenTarget`](crate::OffscreenTarget) is not rendered at all:
+//! the target keeps the previous frame.
+
+use bevy::{
+    ecs::{resource::Resource, system::ResMut},
+    math::{Rect, URect, Vec2},
+};
+
+/// Most rects reported to the host; more are merged into their bounding box
+pub const MAX_DAMAGE_RECTS: usize = 8;
+
+/// What changed in the current frame, in logical pixels of the primary window
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct FrameDamage {
+    declared: bool,
+    full: bool,
+    rects: Vec<Rect>,
+}
+
+impl FrameDamage {
+    /// Marks `rect` as changed
+    pub fn add(&mut self, rect: Rect) {
+        self.declared = true;
+        if !rect.is_empty() {
+            self.rects.push(rect);
+        }
+    }
+
+    /// Marks the whole frame as changed
+    pub fn full(&mut self) {
+        self.declared = true;
+        self.full = true;
+    }
+
+    /// Declares the frame unchanged unless another system adds damage
+    pub fn unchanged(&mut self) {
+        self.declared = true;
+    }
+
+    /// Returns true if the whole frame must be recomposited
+    pub fn is_full(&self) -> bool {
+        !self.declared || self.full
+    }
+
+    /// Returns true if the frame was declared unchanged and nothing was added
+    pub fn is_unchanged(&self) -> bool {
+        self.declared && !self.full && self.rects.is_empty()
+    }
+
+    /// The changed regions, or `None` if the whole frame changed
+    pub fn rects(&self) -> Option<&[Rect]> {
+        (!self.is_full()).then_some(self.rects.as_slice())
+    }
+
+    /// The changed regions in physical pixels, clipped to `size`, or `None` if the whole
+    /// frame changed
+    ///
+    /// More than [`MAX_DAMAGE_RECTS`] rects are merged into their bounding box.
+    pub fn physical_rects(&self, scale_factor: f32, size: Vec2) -> Option<Vec<URect>> {
+        let rects = self.rects()?;
+        let bounds = Rect::from_corners(Vec2::ZERO, size);
+        let mut physical: Vec<URect> = rects
+            .iter()
+            .map(|rect| {
+                let scaled = Rect {
+                    min: (rect.min * scale_factor).floor(),
+                    max: (rect.max * scale_factor).ceil(),
+                };
+                scaled.intersect(bounds)
+            })
+            .filter(|rect| !rect.is_empty())
+            .map(|rect| URect::from_corners(rect.min.as_uvec2(), rect.max.as_uvec2()))
+            .collect();
+
+        if physical.len() > MAX_DAMAGE_RECTS {
+            let bounding = physical
+                .iter()
+                .fold(physical[0], |bounding, rect| bounding.union(*rect));
+            physical = vec![bounding];
+        }
+        Some(physical)
+    }
+}
+
+/// Starts every frame without damage declarations
+pub(crate) fn reset_frame_damage(mut damage: ResMut<FrameDamage>) {
+    if *damage != FrameDamage::default() {
+        *damage = FrameDamage::default();
+    }
+}
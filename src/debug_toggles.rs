@@ -0,0 +1,100 @@
+//! Toggles for Bevy's debug renderers
+//!
+//! Host developer menus can flip visual debugging on a device without a debug build of
+//! the Rust code: `bevy_embedded_ios_set_debug_toggle` / `nativeSetDebugToggle` switch
+//! a [`DebugToggle`] on or off in a running app.
+//!
+//! Each toggle needs its renderer in the app, and reports whether it applied:
+//!
+//! - [`DebugToggle::Aabbs`] and [`DebugToggle::Lights`] draw gizmos, which need
+//!   `GizmoPlugin` (part of `DefaultPlugins`)
+//! - [`DebugToggle::Wireframe`] needs `WireframePlugin` and a GPU with line polygon mode
+//! - [`DebugToggle::UiOverlay`] outlines `bevy_ui` nodes and needs `UiRenderPlugin`
+//!
+//! The toggles are behind the `debug_toggles` cargo feature, which pulls in the gizmo,
+//! PBR and UI renderers.
+
+use bevy::{
+    ecs::world::World,
+    gizmos::{aabb::AabbGizmoConfigGroup, config::GizmoConfigStore, light::LightGizmoConfigGroup},
+    pbr::wireframe::WireframeConfig,
+    ui_render::UiDebugOptions,
+};
+
+/// A debug renderer the host can switch on or off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DebugToggle {
+    /// Bounding boxes of every entity with an `Aabb`
+    Aabbs = 0,
+    /// Every mesh drawn as a wireframe
+    Wireframe = 1,
+    /// Outlines of `bevy_ui` nodes
+    UiOverlay = 2,
+    /// Shapes and ranges of every light
+    Lights = 3,
+}
+
+impl DebugToggle {
+    /// Create a DebugToggle from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(DebugToggle::Aabbs),
+            1 => Some(DebugToggle::Wireframe),
+            2 => Some(DebugToggle::UiOverlay),
+            3 => Some(DebugToggle::Lights),
+            _ => None,
+        }
+    }
+
+    /// Switches the renderer on or off, returning false if the app doesn't have it
+    pub fn set(self, world: &mut World, enabled: bool) -> bool {
+        match self {
+            DebugToggle::Aabbs => set_gizmo_group(world, |group: &mut AabbGizmoConfigGroup| {
+                group.draw_all = enabled;
+            }),
+            DebugToggle::Lights => set_gizmo_group(world, |group: &mut LightGizmoConfigGroup| {
+                group.draw_all = enabled;
+            }),
+            DebugToggle::Wireframe => world
+                .get_resource_mut::<WireframeConfig>()
+                .map(|mut config| config.global = enabled)
+                .is_some(),
+            DebugToggle::UiOverlay => world
+                .get_resource_mut::<UiDebugOptions>()
+                .map(|mut options| options.enabled = enabled)
+                .is_some(),
+        }
+    }
+
+    /// Whether the renderer is on, or `None` if the app doesn't have it
+    pub fn get(self, world: &World) -> Option<bool> {
+        match self {
+            DebugToggle::Aabbs => gizmo_group(world, |group: &AabbGizmoConfigGroup| group.draw_all),
+            DebugToggle::Lights => {
+                gizmo_group(world, |group: &LightGizmoConfigGroup| group.draw_all)
+            }
+            DebugToggle::Wireframe => Some(world.get_resource::<WireframeConfig>()?.global),
+            DebugToggle::UiOverlay => Some(world.get_resource::<UiDebugOptions>()?.enabled),
+        }
+    }
+}
+
+/// Edits a gizmo config group, returning false if the app hasn't registered it
+fn set_gizmo_group<T: 'static>(world: &mut World, edit: impl FnOnce(&mut T)) -> bool {
+    let Some(mut store) = world.get_resource_mut::<GizmoConfigStore>() else {
+        return false;
+    };
+    let group = store
+        .get_config_mut_dyn(&std::any::TypeId::of::<T>())
+        .and_then(|(_, group)| group.as_any_mut().downcast_mut::<T>());
+    group.map(edit).is_some()
+}
+
+/// Reads a gizmo config group the app has registered
+fn gizmo_group<T: 'static, R>(world: &World, read: impl FnOnce(&T) -> R) -> Option<R> {
+    let (_, group) = world
+        .get_resource::<GizmoConfigStore>()?
+        .get_config_dyn(&std::any::TypeId::of::<T>())?;
+    group.as_any().downcast_ref::<T>().map(read)
+}
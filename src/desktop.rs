@@ -0,0 +1,264 @@
+//! Desktop host FFI, used by the simulated host
+//!
+//! The `simulated_host` binary loads an app's cdylib on a desktop and drives it through
+//! the same C entry points a phone host calls, so the FFI contract itself can be
+//! developed and debugged without a device. Desktop builds of the library export the
+//! entry points below alongside those of [`export_embedded_app!`](crate::export_embedded_app);
+//! the host describes its native window with [`DesktopSurfaceInfo`] before creating the
+//! app.
+
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::num::NonZeroIsize;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+use bevy::app::App;
+use bevy::window::{
+    PrimaryWindow, RawHandleWrapper, RawHandleWrapperHolder, Window, WindowResolution,
+    WindowWrapper,
+};
+use raw_window_handle::{
+    AppKitDisplayHandle, AppKitWindowHandle, DisplayHandle, HandleError, HasDisplayHandle,
+    HasWindowHandle, RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+    Win32WindowHandle, WindowHandle, WindowsDisplayHandle, XlibDisplayHandle, XlibWindowHandle,
+};
+
+use crate::ffi;
+
+/// Windowing system of a desktop host surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DesktopSurfaceKind {
+    /// macOS; `window` is an `NSView`
+    AppKit = 0,
+    /// Windows; `window` is an `HWND`
+    Win32 = 1,
+    /// X11; `window` is the window id and `display` the Xlib `Display`
+    Xlib = 2,
+    /// Wayland; `window` is a `wl_surface` and `display` the `wl_display`
+    Wayland = 3,
+}
+
+impl DesktopSurfaceKind {
+    /// Create a DesktopSurfaceKind from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(DesktopSurfaceKind::AppKit),
+            1 => Some(DesktopSurfaceKind::Win32),
+            2 => Some(DesktopSurfaceKind::Xlib),
+            3 => Some(DesktopSurfaceKind::Wayland),
+            _ => None,
+        }
+    }
+}
+
+/// Native window of a desktop host, passed to `bevy_embedded_desktop_set_surface`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DesktopSurfaceInfo {
+    /// [`DesktopSurfaceKind`] of the window
+    pub kind: u8,
+    /// Window handle; see [`DesktopSurfaceKind`]
+    pub window: *mut c_void,
+    /// Display connection, or null where the platform has none
+    pub display: *mut c_void,
+    /// Physical width in pixels
+    pub width: u32,
+    /// Physical height in pixels
+    pub height: u32,
+    /// Physical pixels per logical pixel
+    pub scale_factor: f32,
+}
+
+thread_local! {
+    /// Surface for the next app created on this thread
+    static HOST_SURFACE: Cell<Option<DesktopSurfaceInfo>> = const { Cell::new(None) };
+}
+
+/// Raw handles of a desktop host window
+struct DesktopWindowWrapper {
+    window_handle: RawWindowHandle,
+    display_handle: RawDisplayHandle,
+}
+
+// SAFETY: the host keeps the window alive and only uses it from its main thread, like
+// the phone hosts' views
+unsafe impl Send for DesktopWindowWrapper {}
+unsafe impl Sync for DesktopWindowWrapper {}
+
+impl HasWindowHandle for DesktopWindowWrapper {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Ok(unsafe { WindowHandle::borrow_raw(self.window_handle) })
+    }
+}
+
+impl HasDisplayHandle for DesktopWindowWrapper {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Ok(unsafe { DisplayHandle::borrow_raw(self.display_handle) })
+    }
+}
+
+/// Creates the raw handle for a desktop host window
+fn surface_handle(surface: &DesktopSurfaceInfo) -> Option<RawHandleWrapper> {
+    let (window_handle, display_handle) = match DesktopSurfaceKind::from_u8(surface.kind)? {
+        DesktopSurfaceKind::AppKit => (
+            RawWindowHandle::AppKit(AppKitWindowHandle::new(NonNull::new(surface.window)?)),
+            RawDisplayHandle::AppKit(AppKitDisplayHandle::new()),
+        ),
+        DesktopSurfaceKind::Win32 => (
+            RawWindowHandle::Win32(Win32WindowHandle::new(NonZeroIsize::new(
+                surface.window as isize,
+            )?)),
+            RawDisplayHandle::Windows(WindowsDisplayHandle::new()),
+        ),
+        DesktopSurfaceKind::Xlib => (
+            RawWindowHandle::Xlib(XlibWindowHandle::new(surface.window as _)),
+            RawDisplayHandle::Xlib(XlibDisplayHandle::new(NonNull::new(surface.display), 0)),
+        ),
+        DesktopSurfaceKind::Wayland => (
+            RawWindowHandle::Wayland(WaylandWindowHandle::new(NonNull::new(surface.window)?)),
+            RawDisplayHandle::Wayland(WaylandDisplayHandle::new(NonNull::new(surface.display)?)),
+        ),
+    };
+
+    let window_wrapper = WindowWrapper::new(DesktopWindowWrapper {
+        window_handle,
+        display_handle,
+    });
+    RawHandleWrapper::new(&window_wrapper).ok()
+}
+
+/// Creates the primary window on the surface set with `bevy_embedded_desktop_set_surface`
+///
+/// Does nothing if the host didn't set one, e.g. when an app is created by tests.
+pub fn create_window_from_host(app: &mut App) {
+    let Some(surface) = HOST_SURFACE.with(Cell::take) else {
+        return;
+    };
+    let Some(handle_wrapper) = surface_handle(&surface) else {
+        log::error!("Host did not provide a valid surface");
+        return;
+    };
+
+    log::info!(
+        "Creating embedded window: {}x{} @ {}x scale",
+        surface.width,
+        surface.height,
+        surface.scale_factor
+    );
+
+    let handle_holder = RawHandleWrapperHolder(Arc::new(Mutex::new(Some(handle_wrapper.clone()))));
+    let window = Window {
+        resolution: WindowResolution::new(surface.width, surface.height)
+            .with_scale_factor_override(surface.scale_factor),
+        ..Default::default()
+    };
+    app.world_mut()
+        .spawn((window, handle_wrapper, handle_holder, PrimaryWindow));
+}
+
+/// Set the window the next `bevy_embedded_create_app` call on this thread renders to
+///
+/// # Safety
+///
+/// - `surface` must be null or a valid pointer to a `DesktopSurfaceInfo`
+/// - The window must stay alive until the app is destroyed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_desktop_set_surface(surface: *const DesktopSurfaceInfo) {
+    let surface = unsafe { surface.as_ref() }.copied();
+    HOST_SURFACE.with(|current| current.set(surface));
+}
+
+/// Queue a touch event; positions are in logical pixels
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_desktop_touch_event(
+    app: *mut c_void,
+    phase: u8,
+    x: f32,
+    y: f32,
+    id: u64,
+) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app) } {
+        let _ = ffi::touch_event(&mut app, phase, x, y, id);
+    }
+}
+
+/// Cancel every touch that is down
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_desktop_cancel_touches(app: *mut c_void) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app) } {
+        ffi::cancel_touches(&mut app);
+    }
+}
+
+/// Report a new physical size of the window
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_desktop_resize(
+    app: *mut c_void,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app) } {
+        let _ = ffi::resize(&mut app, width, height, scale_factor);
+    }
+}
+
+/// Update the host lifecycle state (0 = active, 1 = inactive, 2 = background)
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_desktop_set_lifecycle(app: *mut c_void, state: u8) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app) } {
+        let _ = ffi::set_lifecycle(&mut app, state);
+    }
+}
+
+/// Send a binary message to Bevy
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `data` must be a valid pointer to `len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_desktop_send_message(
+    app: *mut c_void,
+    data: *const u8,
+    len: usize,
+) {
+    if let Ok(message) = unsafe { ffi::message_slice(data, len) } {
+        let _ = ffi::send_message(app, message.to_vec());
+    }
+}
+
+/// Receive a binary message from Bevy (non-blocking poll)
+///
+/// Returns the number of bytes read, or 0 if no message is available.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `buffer` must be a valid pointer to at least `buffer_len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_desktop_receive_message(
+    app: *mut c_void,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> usize {
+    unsafe { ffi::receive_message_into(app, buffer, buffer_len) }.unwrap_or(0)
+}
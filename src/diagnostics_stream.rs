@@ -0,0 +1,108 @@
+//! Streaming Bevy diagnostics to the host
+//!
+//! Host apps already chart the health of their own screens in dashboards. While a
+//! [`DiagnosticsStream`] is present, the latest value of every selected Bevy diagnostic
+//! is sent on [`ReservedTopic::Diagnostics`] every [`DiagnosticsStream::interval`], so
+//! the widget's frame time and entity count, and any diagnostic the app registers itself,
+//! show up there without custom Rust:
+//!
+//! ```text
+//! diagnostics: u16 × (path: string | suffix: string | value: f64 | average: f64)
+//! ```
+//!
+//! Diagnostics without a measurement yet are left out. Hosts turn streaming on with the
+//! `diagnostics_interval_ms` creation config key and select diagnostics by path with
+//! `diagnostics` (comma-separated, e.g. `fps,frame_time,entity_count`); apps set
+//! [`EmbeddedConfig::diagnostics`](crate::EmbeddedConfig::diagnostics). `EmbeddedPlugin`
+//! adds `FrameTimeDiagnosticsPlugin` and `EntityCountDiagnosticsPlugin` when streaming,
+//! so apps that configure those plugins themselves add them first.
+
+use std::time::{Duration, Instant};
+
+use bevy::{
+    diagnostic::{DiagnosticPath, DiagnosticsStore},
+    ecs::{
+        resource::Resource,
+        system::{Local, Res},
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteWriter, ReservedTopic},
+};
+
+/// Which diagnostics are sent to the host and how often; streaming is on while this
+/// resource exists
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct DiagnosticsStream {
+    /// Time between reports
+    pub interval: Duration,
+    /// Paths of the diagnostics sent, or empty for every registered diagnostic
+    pub paths: Vec<DiagnosticPath>,
+}
+
+impl Default for DiagnosticsStream {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            paths: Vec::new(),
+        }
+    }
+}
+
+impl DiagnosticsStream {
+    /// Parses a comma-separated list of diagnostic paths
+    pub fn paths_from_list(list: &str) -> Vec<DiagnosticPath> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(|path| DiagnosticPath::new(path.to_owned()))
+            .collect()
+    }
+}
+
+/// Sends the selected diagnostics every interval
+pub(crate) fn stream_diagnostics(
+    stream: Option<Res<DiagnosticsStream>>,
+    store: Option<Res<DiagnosticsStore>>,
+    channel: Res<HostChannel>,
+    mut last_report: Local<Option<Instant>>,
+) {
+    let (Some(stream), Some(store)) = (stream, store) else {
+        return;
+    };
+    let now = Instant::now();
+    if last_report.is_some_and(|last| now.duration_since(last) < stream.interval) {
+        return;
+    }
+    *last_report = Some(now);
+
+    let mut count = 0u16;
+    let mut entries = ByteWriter::new();
+    let selected = store
+        .iter()
+        .filter(|diagnostic| diagnostic.is_enabled)
+        .filter(|diagnostic| stream.paths.is_empty() || stream.paths.contains(diagnostic.path()));
+    for diagnostic in selected {
+        let Some(value) = diagnostic.value() else {
+            continue;
+        };
+        entries
+            .string(diagnostic.path().as_str())
+            .string(&diagnostic.suffix)
+            .f64(value)
+            .f64(diagnostic.average().unwrap_or(value));
+        count = count.saturating_add(1);
+        if count == u16::MAX {
+            break;
+        }
+    }
+    if count == 0 {
+        return;
+    }
+
+    let mut payload = ByteWriter::new();
+    payload.u16(count).bytes(&entries.finish());
+    channel.send_reserved(ReservedTopic::Diagnostics, &payload.finish());
+}
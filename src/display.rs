@@ -0,0 +1,79 @@
+//! Secondary windows on external displays
+//!
+//! A host can attach a surface that lives on another screen (an AirPlay display, a
+//! USB-C monitor) alongside the embedded widget. It becomes an extra `Window` entity
+//! with its own resolution and scale factor, tagged with [`ExternalDisplay`]. Apps spawn
+//! a camera for it when it appears:
+//!
+//! ```ignore
+//! fn on_display_attached(
+//!     mut commands: Commands,
+//!     displays: Query<Entity, Added<ExternalDisplay>>,
+//! ) {
+//!     for window in &displays {
+//!         commands.spawn((
+//!             Camera2d,
+//!             Camera {
+//!                 target: RenderTarget::Window(WindowRef::Entity(window)),
+//!                 ..default()
+//!             },
+//!         ));
+//!     }
+//! }
+//! ```
+//!
+//! When the host detaches the display the window entity is despawned; use
+//! `RemovedComponents<ExternalDisplay>` to clean up cameras that targeted it.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    ecs::{component::Component, entity::Entity, resource::Resource, world::World},
+    window::{RawHandleWrapper, RawHandleWrapperHolder, Window, WindowResolution},
+};
+
+/// Marks a window that belongs to an external display attached by the host
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExternalDisplay {
+    /// Identifier the host uses to resize or detach this display
+    pub id: u32,
+}
+
+/// Source of display ids; ids are never reused within an app
+#[derive(Resource, Default)]
+struct ExternalDisplayIds {
+    last: u32,
+}
+
+/// Spawns a window for an external display surface and returns its id and entity
+pub(crate) fn spawn_external_display(
+    world: &mut World,
+    handle: RawHandleWrapper,
+    resolution: WindowResolution,
+) -> (u32, Entity) {
+    let mut ids = world.get_resource_or_init::<ExternalDisplayIds>();
+    ids.last += 1;
+    let id = ids.last;
+
+    let holder = RawHandleWrapperHolder(Arc::new(Mutex::new(Some(handle.clone()))));
+    let window = Window {
+        title: format!("External display {id}"),
+        resolution,
+        ..Default::default()
+    };
+    let entity = world
+        .spawn((window, handle, holder, ExternalDisplay { id }))
+        .id();
+
+    log::info!("Attached external display {id} as window {entity}");
+    (id, entity)
+}
+
+/// Finds the window entity for an external display id
+pub(crate) fn find_external_display(world: &mut World, id: u32) -> Option<Entity> {
+    let mut query = world.query::<(Entity, &ExternalDisplay)>();
+    query
+        .iter(world)
+        .find(|(_, display)| display.id == id)
+        .map(|(entity, _)| entity)
+}
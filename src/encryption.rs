@@ -0,0 +1,222 @@
+//! Authenticated encryption of channel messages
+//!
+//! The channel's FFI entry points are exported symbols, so any SDK loaded into the host
+//! process can call `bevy_embedded_ios_send_message` to inject commands or poll
+//! `bevy_embedded_ios_receive_message` to read the app's messages. Hosts that need to
+//! prevent this give the app a 32-byte key at creation ([`EmbeddedConfig::channel_key`],
+//! or the `channel_key` host config value as 64 hex digits). The app then seals every
+//! message it sends and drops every message from the host that fails to open. The host
+//! seals and opens with the same key (`bevy_embedded_ios_seal_message` /
+//! `bevy_embedded_ios_open_message`, or `nativeSealMessage` / `nativeOpenMessage`).
+//! Those helpers are useless to code that doesn't hold the key.
+//!
+//! Messages are sealed with XChaCha20-Poly1305 under a random nonce. The direction is
+//! authenticated as associated data, so a message can't be reflected back to its sender:
+//!
+//! ```text
+//! nonce: 24 random bytes | ciphertext | tag: 16 bytes
+//! ```
+//!
+//! Sealing covers the message channel, including reserved messages. Ring channels,
+//! shared state and telemetry are not encrypted. Replayed messages are not detected.
+//!
+//! [`EmbeddedConfig::channel_key`]: crate::EmbeddedConfig::channel_key
+
+use std::fmt;
+
+use chacha20poly1305::{
+    KeyInit, XChaCha20Poly1305,
+    aead::{Aead, Payload},
+};
+
+/// Length of a channel key
+pub const CHANNEL_KEY_LEN: usize = 32;
+
+/// Bytes a sealed message is longer than its plaintext
+pub const SEALED_OVERHEAD: usize = NONCE_LEN + TAG_LEN;
+
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+/// Which way a message travels, authenticated with it
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelDirection {
+    /// Sent by the host to the app
+    ToBevy = 0,
+    /// Sent by the app to the host
+    ToHost = 1,
+}
+
+/// A key shared by the host and one app instance
+///
+/// Its `Debug` output does not show the key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ChannelKey([u8; CHANNEL_KEY_LEN]);
+
+impl ChannelKey {
+    /// A key from its raw bytes
+    pub fn new(bytes: [u8; CHANNEL_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// A key from raw bytes, or `None` if there are not exactly [`CHANNEL_KEY_LEN`]
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(Self)
+    }
+
+    /// A key from 64 hex digits
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.as_bytes();
+        // `from_str_radix` would also take a sign
+        if hex.len() != CHANNEL_KEY_LEN * 2 || !hex.iter().all(u8::is_ascii_hexdigit) {
+            return None;
+        }
+        let mut bytes = [0; CHANNEL_KEY_LEN];
+        for (byte, pair) in bytes.iter_mut().zip(hex.chunks_exact(2)) {
+            let pair = std::str::from_utf8(pair).ok()?;
+            *byte = u8::from_str_radix(pair, 16).ok()?;
+        }
+        Some(Self(bytes))
+    }
+}
+
+impl fmt::Debug for ChannelKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChannelKey(..)")
+    }
+}
+
+/// Seals and opens channel messages under a [`ChannelKey`]
+#[derive(Clone)]
+pub struct ChannelCipher(XChaCha20Poly1305);
+
+impl fmt::Debug for ChannelCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChannelCipher(..)")
+    }
+}
+
+impl ChannelCipher {
+    /// A cipher for messages under `key`
+    pub fn new(key: &ChannelKey) -> Self {
+        Self(XChaCha20Poly1305::new(&key.0.into()))
+    }
+
+    /// Encrypts and authenticates `plaintext` for sending in `direction`
+    ///
+    /// Returns `None` if the system has no random number generator for the nonce.
+    pub fn seal(&self, direction: ChannelDirection, plaintext: &[u8]) -> Option<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        if let Err(error) = getrandom::fill(&mut nonce) {
+            log::error!("Failed to generate a channel nonce: {error}");
+            return None;
+        }
+
+        let payload = Payload {
+            msg: plaintext,
+            aad: &[direction as u8],
+        };
+        let ciphertext = self.0.encrypt(&nonce.into(), payload).ok()?;
+        let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Some(sealed)
+    }
+
+    /// Authenticates and decrypts a message received from `direction`
+    ///
+    /// Returns `None` if it was not sealed with this key for this direction, or was
+    /// altered.
+    pub fn open(&self, direction: ChannelDirection, sealed: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < SEALED_OVERHEAD {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().ok()?;
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &[direction as u8],
+        };
+        self.0.decrypt(&nonce.into(), payload).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> ChannelCipher {
+        ChannelCipher::new(&ChannelKey::new([7; CHANNEL_KEY_LEN]))
+    }
+
+    #[test]
+    fn sealed_messages_open_in_their_direction() {
+        let cipher = cipher();
+        for message in [&b""[..], b"hello", &[0xAB; 1000]] {
+            let sealed = cipher.seal(ChannelDirection::ToHost, message).unwrap();
+            assert_eq!(sealed.len(), message.len() + SEALED_OVERHEAD);
+            assert_eq!(
+                cipher.open(ChannelDirection::ToHost, &sealed).as_deref(),
+                Some(message)
+            );
+        }
+    }
+
+    #[test]
+    fn messages_do_not_open_in_the_other_direction() {
+        let cipher = cipher();
+        let sealed = cipher.seal(ChannelDirection::ToBevy, b"command").unwrap();
+        assert_eq!(cipher.open(ChannelDirection::ToHost, &sealed), None);
+    }
+
+    #[test]
+    fn messages_do_not_open_under_another_key() {
+        let sealed = cipher().seal(ChannelDirection::ToBevy, b"command").unwrap();
+        let other = ChannelCipher::new(&ChannelKey::new([8; CHANNEL_KEY_LEN]));
+        assert_eq!(other.open(ChannelDirection::ToBevy, &sealed), None);
+    }
+
+    #[test]
+    fn tampered_messages_do_not_open() {
+        let cipher = cipher();
+        let sealed = cipher.seal(ChannelDirection::ToBevy, b"command").unwrap();
+        for i in 0..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 0x01;
+            assert_eq!(cipher.open(ChannelDirection::ToBevy, &tampered), None);
+        }
+    }
+
+    #[test]
+    fn truncated_messages_do_not_open() {
+        let cipher = cipher();
+        let sealed = cipher.seal(ChannelDirection::ToBevy, b"").unwrap();
+        for len in 0..sealed.len() {
+            assert_eq!(cipher.open(ChannelDirection::ToBevy, &sealed[..len]), None);
+        }
+        assert_eq!(
+            cipher.open(ChannelDirection::ToBevy, &[0; SEALED_OVERHEAD - 1]),
+            None
+        );
+    }
+
+    #[test]
+    fn keys_parse_from_64_hex_digits() {
+        let hex = "000102030405060708090a0b0c0d0e0f101112131415161718191A1B1C1D1E1F";
+        let expected: [u8; CHANNEL_KEY_LEN] = std::array::from_fn(|i| i as u8);
+        assert_eq!(ChannelKey::from_hex(hex), Some(ChannelKey::new(expected)));
+
+        // Odd length
+        assert_eq!(ChannelKey::from_hex(&hex[1..]), None);
+        assert_eq!(ChannelKey::from_hex(&format!("{hex}0")), None);
+        // Wrong length
+        assert_eq!(ChannelKey::from_hex(&hex[2..]), None);
+        assert_eq!(ChannelKey::from_hex(&format!("{hex}00")), None);
+        assert_eq!(ChannelKey::from_hex(""), None);
+        // Not hex
+        assert_eq!(ChannelKey::from_hex(&hex.replacen("0a", "0g", 1)), None);
+        assert_eq!(ChannelKey::from_hex(&hex.replacen("00", "+0", 1)), None);
+        assert_eq!(ChannelKey::from_hex(&hex.replacen("00", "é", 1)), None);
+    }
+}
@@ -0,0 +1,547 @@
+//! Serde encoding of host events
+//!
+//! Events are encoded like the crate's own payloads, so the host's generated code reads
+//! them with the same few helpers: little-endian integers and floats, `bool` as one byte,
+//! strings, bytes and sequences prefixed with their `u32` length, options as a `u8` tag (1
+//! followed by the value, or 0) and enum variants as their `u32` index. Struct fields
+//! follow each other in declaration order without names.
+//!
+//! The encoding isn't self-describing, and only the shapes [`EventField`](crate::EventField)
+//! describes are supported; maps, tuples, variant data, `char` and 8, 16 and 128-bit
+//! signed integers are refused.
+
+use std::fmt;
+
+use serde::{
+    Deserialize, Serialize,
+    de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, SeqAccess, VariantAccess, Visitor},
+    ser::{self, Impossible},
+};
+
+use crate::protocol::{ByteReader, ByteWriter};
+
+/// Error encoding or decoding a host event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventCodecError(String);
+
+impl fmt::Display for EventCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for EventCodecError {}
+
+impl ser::Error for EventCodecError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self(message.to_string())
+    }
+}
+
+impl de::Error for EventCodecError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self(message.to_string())
+    }
+}
+
+fn unsupported(what: &str) -> EventCodecError {
+    EventCodecError(format!("{what} can't be sent in host events"))
+}
+
+fn truncated() -> EventCodecError {
+    EventCodecError("the event ends early".to_string())
+}
+
+/// Writes `value` in the event encoding
+pub(crate) fn write<T: Serialize + ?Sized>(
+    value: &T,
+    writer: &mut ByteWriter,
+) -> Result<(), EventCodecError> {
+    value.serialize(&mut EventSerializer { writer })
+}
+
+/// Reads a `T` in the event encoding, leaving `reader` after it
+pub(crate) fn read<'de, T: Deserialize<'de>>(
+    reader: &mut ByteReader<'de>,
+) -> Result<T, EventCodecError> {
+    let mut deserializer = EventDeserializer {
+        reader: reader.clone(),
+    };
+    let value = T::deserialize(&mut deserializer)?;
+    *reader = deserializer.reader;
+    Ok(value)
+}
+
+struct EventSerializer<'w> {
+    writer: &'w mut ByteWriter,
+}
+
+impl<'w> ser::Serializer for &mut EventSerializer<'w> {
+    type Ok = ();
+    type Error = EventCodecError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Impossible<(), EventCodecError>;
+    type SerializeTupleStruct = Impossible<(), EventCodecError>;
+    type SerializeTupleVariant = Impossible<(), EventCodecError>;
+    type SerializeMap = Impossible<(), EventCodecError>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), EventCodecError>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, value: bool) -> Result<(), EventCodecError> {
+        self.writer.bool(value);
+        Ok(())
+    }
+
+    fn serialize_i8(self, _value: i8) -> Result<(), EventCodecError> {
+        Err(unsupported("i8"))
+    }
+
+    fn serialize_i16(self, _value: i16) -> Result<(), EventCodecError> {
+        Err(unsupported("i16"))
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<(), EventCodecError> {
+        self.writer.u32(value as u32);
+        Ok(())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<(), EventCodecError> {
+        self.writer.u64(value as u64);
+        Ok(())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<(), EventCodecError> {
+        self.writer.u8(value);
+        Ok(())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<(), EventCodecError> {
+        self.writer.u16(value);
+        Ok(())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<(), EventCodecError> {
+        self.writer.u32(value);
+        Ok(())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<(), EventCodecError> {
+        self.writer.u64(value);
+        Ok(())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<(), EventCodecError> {
+        self.writer.f32(value);
+        Ok(())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), EventCodecError> {
+        self.writer.f64(value);
+        Ok(())
+    }
+
+    fn serialize_char(self, _value: char) -> Result<(), EventCodecError> {
+        Err(unsupported("char"))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), EventCodecError> {
+        self.writer.string(value);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), EventCodecError> {
+        self.writer.u32(value.len() as u32).bytes(value);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), EventCodecError> {
+        self.writer.u8(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), EventCodecError> {
+        self.writer.u8(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), EventCodecError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), EventCodecError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), EventCodecError> {
+        self.writer.u32(variant_index);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), EventCodecError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), EventCodecError> {
+        Err(unsupported("Enum variants with data"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, EventCodecError> {
+        let len = len.ok_or_else(|| unsupported("Sequences of unknown length"))?;
+        let len = u32::try_from(len).map_err(|_| unsupported("Sequences this long"))?;
+        self.writer.u32(len);
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, EventCodecError> {
+        Err(unsupported("Tuples"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, EventCodecError> {
+        Err(unsupported("Tuple structs"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, EventCodecError> {
+        Err(unsupported("Enum variants with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, EventCodecError> {
+        Err(unsupported("Maps"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, EventCodecError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, EventCodecError> {
+        Err(unsupported("Enum variants with data"))
+    }
+}
+
+impl<'w> ser::SerializeSeq for &mut EventSerializer<'w> {
+    type Ok = ();
+    type Error = EventCodecError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), EventCodecError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), EventCodecError> {
+        Ok(())
+    }
+}
+
+impl<'w> ser::SerializeStruct for &mut EventSerializer<'w> {
+    type Ok = ();
+    type Error = EventCodecError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), EventCodecError> {
+        value.serialize(&mut **self)
+    }
+
+    // Fields are found by position, so the host would read the next field in its place
+    fn skip_field(&mut self, key: &'static str) -> Result<(), EventCodecError> {
+        Err(EventCodecError(format!("Field {key} can't be skipped")))
+    }
+
+    fn end(self) -> Result<(), EventCodecError> {
+        Ok(())
+    }
+}
+
+struct EventDeserializer<'de> {
+    reader: ByteReader<'de>,
+}
+
+impl<'de> de::Deserializer<'de> for &mut EventDeserializer<'de> {
+    type Error = EventCodecError;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, EventCodecError> {
+        Err(EventCodecError(
+            "The event encoding isn't self-describing".to_string(),
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_bool(self.reader.bool().ok_or_else(truncated)?)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, EventCodecError> {
+        Err(unsupported("i8"))
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, EventCodecError> {
+        Err(unsupported("i16"))
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_i32(self.reader.u32().ok_or_else(truncated)? as i32)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_i64(self.reader.u64().ok_or_else(truncated)? as i64)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_u8(self.reader.u8().ok_or_else(truncated)?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_u16(self.reader.u16().ok_or_else(truncated)?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_u32(self.reader.u32().ok_or_else(truncated)?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_u64(self.reader.u64().ok_or_else(truncated)?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_f32(self.reader.f32().ok_or_else(truncated)?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_f64(self.reader.f64().ok_or_else(truncated)?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, EventCodecError> {
+        Err(unsupported("char"))
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        let len = self.reader.u32().ok_or_else(truncated)? as usize;
+        let bytes = self.reader.bytes(len).ok_or_else(truncated)?;
+        let value = std::str::from_utf8(bytes)
+            .map_err(|_| EventCodecError("A string isn't UTF-8".to_string()))?;
+        visitor.visit_borrowed_str(value)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        let len = self.reader.u32().ok_or_else(truncated)? as usize;
+        visitor.visit_borrowed_bytes(self.reader.bytes(len).ok_or_else(truncated)?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        match self.reader.u8().ok_or_else(truncated)? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            tag => Err(EventCodecError(format!("Invalid option tag {tag}"))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, EventCodecError> {
+        let remaining = self.reader.u32().ok_or_else(truncated)? as usize;
+        visitor.visit_seq(Elements {
+            deserializer: self,
+            remaining,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        Err(unsupported("Tuples"))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        Err(unsupported("Tuple structs"))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, EventCodecError> {
+        Err(unsupported("Maps"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        visitor.visit_seq(Elements {
+            deserializer: self,
+            remaining: fields.len(),
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        Err(EventCodecError(
+            "The event encoding has no identifiers".to_string(),
+        ))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        Err(EventCodecError(
+            "The event encoding can't skip values".to_string(),
+        ))
+    }
+}
+
+/// The elements of a sequence, or the fields of a struct
+struct Elements<'a, 'de> {
+    deserializer: &'a mut EventDeserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = EventCodecError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, EventCodecError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.deserializer).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> EnumAccess<'de> for &mut EventDeserializer<'de> {
+    type Error = EventCodecError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), EventCodecError> {
+        let index = self.reader.u32().ok_or_else(truncated)?;
+        let variant = seed.deserialize(index.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for &mut EventDeserializer<'de> {
+    type Error = EventCodecError;
+
+    fn unit_variant(self) -> Result<(), EventCodecError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        _seed: T,
+    ) -> Result<T::Value, EventCodecError> {
+        Err(unsupported("Enum variants with data"))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        Err(unsupported("Enum variants with data"))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, EventCodecError> {
+        Err(unsupported("Enum variants with data"))
+    }
+}
@@ -0,0 +1,488 @@
+//! Exporting entities to glTF for the host
+//!
+//! Lets hosts offer "export what I built" by asking for the current scene as a GLB file.
+//! A [`ReservedTopic::ExportScene`] command names the entities to export:
+//!
+//! ```text
+//! request: u32 | count: u32 | entities: count × u64 (Entity::to_bits)
+//! ```
+//!
+//! A count of zero exports the entities marked [`Selected`], with the materials they had
+//! before the selection tint. Each entity is exported with its descendants, placed at its
+//! world transform. The host receives [`ReservedTopic::SceneExported`]:
+//!
+//! ```text
+//! request: u32 | status: u8 (0 exported, 1 nothing to export) | GLB bytes ...
+//! ```
+//!
+//! Meshes keep positions, normals, the first UV set and indices; `StandardMaterial`s keep
+//! their base color, metallic, roughness, emissive and alpha settings. Textures, skins,
+//! morph targets and non-triangle meshes are not exported. Apps can build the same file
+//! with [`GlbExport`].
+
+use std::{collections::HashMap, fmt::Write};
+
+use bevy::{
+    asset::{AssetId, Assets},
+    color::ColorToComponents,
+    ecs::{
+        entity::Entity,
+        hierarchy::{ChildOf, Children},
+        message::MessageReader,
+        name::Name,
+        query::With,
+        system::{Query, Res, SystemParam},
+    },
+    mesh::{Indices, Mesh, Mesh3d, PrimitiveTopology, VertexAttributeValues},
+    pbr::{MeshMaterial3d, StandardMaterial},
+    render::alpha::AlphaMode,
+    transform::components::{GlobalTransform, Transform},
+};
+
+use crate::{
+    HostChannel, Selected,
+    gpu_preferences::json_string,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+    selection::UntintedMaterial,
+};
+
+/// GLB chunk type of the JSON chunk
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+/// GLB chunk type of the binary chunk
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+/// glTF accessor component types
+const FLOAT: u32 = 5126;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+
+/// glTF buffer view targets
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Components of an entity that end up in its glTF node
+type NodeComponents = (
+    Option<&'static Name>,
+    Option<&'static Transform>,
+    Option<&'static GlobalTransform>,
+    Option<&'static Mesh3d>,
+    Option<&'static MeshMaterial3d<StandardMaterial>>,
+    Option<&'static UntintedMaterial>,
+    Option<&'static Children>,
+);
+
+/// A mesh with the material it is drawn with, which together make a glTF mesh
+type MeshKey = (AssetId<Mesh>, Option<AssetId<StandardMaterial>>);
+
+/// Builds GLB files from entities in the world
+#[derive(SystemParam)]
+pub struct GlbExport<'w, 's> {
+    meshes: Option<Res<'w, Assets<Mesh>>>,
+    materials: Option<Res<'w, Assets<StandardMaterial>>>,
+    nodes: Query<'w, 's, NodeComponents>,
+    parents: Query<'w, 's, &'static ChildOf>,
+}
+
+impl GlbExport<'_, '_> {
+    /// Exports `roots` and their descendants as a GLB file
+    ///
+    /// Roots are placed at their world transform. Roots that are descendants of other
+    /// roots are only exported once, and missing entities are skipped. Returns `None` if
+    /// none of the roots exist.
+    pub fn export(&self, roots: &[Entity]) -> Option<Vec<u8>> {
+        let mut builder = GlbBuilder::default();
+        let mut scene = Vec::new();
+        for &root in roots {
+            let nested = self
+                .parents
+                .iter_ancestors(root)
+                .any(|ancestor| roots.contains(&ancestor));
+            if nested || !self.nodes.contains(root) {
+                continue;
+            }
+            if let Some(node) = self.add_node(&mut builder, root, true) {
+                scene.push(node);
+            }
+        }
+
+        if scene.is_empty() {
+            return None;
+        }
+        Some(builder.finish(&scene))
+    }
+
+    /// Adds `entity` and its descendants, returning its node index
+    fn add_node(&self, builder: &mut GlbBuilder, entity: Entity, root: bool) -> Option<usize> {
+        let (name, local, global, mesh, material, untinted, children) =
+            self.nodes.get(entity).ok()?;
+        let transform = match (root, global) {
+            (true, Some(global)) => global.compute_transform(),
+            _ => local.copied().unwrap_or_default(),
+        };
+
+        let index = builder.nodes.len();
+        builder.nodes.push(String::new());
+
+        // Selected meshes export the material they had before the selection tint
+        let mesh = mesh.and_then(|mesh| {
+            let material = match untinted {
+                Some(UntintedMaterial(original)) => Some(original.id()),
+                None => material.map(|material| material.id()),
+            };
+            self.add_mesh(builder, mesh.id(), material)
+        });
+        let children: Vec<usize> = children
+            .into_iter()
+            .flatten()
+            .filter_map(|&child| self.add_node(builder, child, false))
+            .collect();
+
+        let mut node = String::from("{");
+        if let Some(name) = name {
+            let _ = write!(node, "\"name\":{},", json_string(name.as_str()));
+        }
+        let _ = write!(
+            node,
+            "\"translation\":{},\"rotation\":{},\"scale\":{}",
+            json_numbers(&transform.translation.to_array()),
+            json_numbers(&transform.rotation.to_array()),
+            json_numbers(&transform.scale.to_array()),
+        );
+        if let Some(mesh) = mesh {
+            let _ = write!(node, ",\"mesh\":{mesh}");
+        }
+        if !children.is_empty() {
+            let _ = write!(node, ",\"children\":{}", json_indices(&children));
+        }
+        node.push('}');
+        builder.nodes[index] = node;
+
+        Some(index)
+    }
+
+    /// Adds a mesh with its material, returning its index or `None` if it can't be exported
+    fn add_mesh(
+        &self,
+        builder: &mut GlbBuilder,
+        id: AssetId<Mesh>,
+        material: Option<AssetId<StandardMaterial>>,
+    ) -> Option<usize> {
+        if let Some(&index) = builder.mesh_indices.get(&(id, material)) {
+            return index;
+        }
+        let index = self.build_mesh(builder, id, material);
+        if index.is_none() {
+            log::debug!("Skipping mesh {id} that cannot be exported to glTF");
+        }
+        builder.mesh_indices.insert((id, material), index);
+        index
+    }
+
+    fn build_mesh(
+        &self,
+        builder: &mut GlbBuilder,
+        id: AssetId<Mesh>,
+        material: Option<AssetId<StandardMaterial>>,
+    ) -> Option<usize> {
+        let mesh = self.meshes.as_ref()?.get(id)?;
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return None;
+        };
+
+        let mut attributes = format!("\"POSITION\":{}", builder.vec3_accessor(positions, true));
+        if let Some(VertexAttributeValues::Float32x3(normals)) =
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+        {
+            let _ = write!(
+                attributes,
+                ",\"NORMAL\":{}",
+                builder.vec3_accessor(normals, false)
+            );
+        }
+        if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            let _ = write!(attributes, ",\"TEXCOORD_0\":{}", builder.vec2_accessor(uvs));
+        }
+
+        let mut primitive = format!("{{\"attributes\":{{{attributes}}},\"mode\":4");
+        if let Some(indices) = mesh.indices() {
+            let _ = write!(
+                primitive,
+                ",\"indices\":{}",
+                builder.index_accessor(indices)
+            );
+        }
+        if let Some(material) = material.and_then(|id| self.add_material(builder, id)) {
+            let _ = write!(primitive, ",\"material\":{material}");
+        }
+        primitive.push('}');
+
+        builder
+            .meshes
+            .push(format!("{{\"primitives\":[{primitive}]}}"));
+        Some(builder.meshes.len() - 1)
+    }
+
+    /// Adds a material, returning its index or `None` if it no longer exists
+    fn add_material(
+        &self,
+        builder: &mut GlbBuilder,
+        id: AssetId<StandardMaterial>,
+    ) -> Option<usize> {
+        if let Some(&index) = builder.material_indices.get(&id) {
+            return Some(index);
+        }
+        let material = self.materials.as_ref()?.get(id)?;
+
+        let base_color = material.base_color.to_linear().to_f32_array();
+        let emissive = material.emissive.to_f32_array();
+        let emissive = [emissive[0], emissive[1], emissive[2]].map(|value| value.clamp(0.0, 1.0));
+        let mut json = format!(
+            "{{\"pbrMetallicRoughness\":{{\"baseColorFactor\":{},\"metallicFactor\":{},\"roughnessFactor\":{}}},\"emissiveFactor\":{},\"doubleSided\":{}",
+            json_numbers(&base_color),
+            json_number(material.metallic),
+            json_number(material.perceptual_roughness),
+            json_numbers(&emissive),
+            material.double_sided,
+        );
+        match material.alpha_mode {
+            AlphaMode::Opaque => {}
+            AlphaMode::Mask(cutoff) => {
+                let _ = write!(
+                    json,
+                    ",\"alphaMode\":\"MASK\",\"alphaCutoff\":{}",
+                    json_number(cutoff)
+                );
+            }
+            _ => json.push_str(",\"alphaMode\":\"BLEND\""),
+        }
+        json.push('}');
+
+        builder.materials.push(json);
+        let index = builder.materials.len() - 1;
+        builder.material_indices.insert(id, index);
+        Some(index)
+    }
+}
+
+/// JSON and binary data of a GLB file being built
+#[derive(Default)]
+struct GlbBuilder {
+    nodes: Vec<String>,
+    meshes: Vec<String>,
+    materials: Vec<String>,
+    accessors: Vec<String>,
+    buffer_views: Vec<String>,
+    bin: Vec<u8>,
+    mesh_indices: HashMap<MeshKey, Option<usize>>,
+    material_indices: HashMap<AssetId<StandardMaterial>, usize>,
+}
+
+impl GlbBuilder {
+    /// Appends a buffer view, returning its index
+    fn buffer_view(&mut self, bytes: &[u8], target: u32) -> usize {
+        // Accessor data must be aligned to its component size
+        while !self.bin.len().is_multiple_of(4) {
+            self.bin.push(0);
+        }
+        self.buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{target}}}",
+            self.bin.len(),
+            bytes.len()
+        ));
+        self.bin.extend_from_slice(bytes);
+        self.buffer_views.len() - 1
+    }
+
+    /// Appends an accessor, returning its index
+    fn accessor(
+        &mut self,
+        bytes: &[u8],
+        target: u32,
+        component_type: u32,
+        count: usize,
+        kind: &str,
+        bounds: Option<([f32; 3], [f32; 3])>,
+    ) -> usize {
+        let view = self.buffer_view(bytes, target);
+        let mut json = format!(
+            "{{\"bufferView\":{view},\"componentType\":{component_type},\"count\":{count},\"type\":\"{kind}\""
+        );
+        if let Some((min, max)) = bounds {
+            let _ = write!(
+                json,
+                ",\"min\":{},\"max\":{}",
+                json_numbers(&min),
+                json_numbers(&max)
+            );
+        }
+        json.push('}');
+        self.accessors.push(json);
+        self.accessors.len() - 1
+    }
+
+    fn vec3_accessor(&mut self, values: &[[f32; 3]], bounds: bool) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        // glTF requires bounds on positions
+        let bounds = bounds.then(|| {
+            values.iter().fold(
+                ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]),
+                |(min, max), value| {
+                    (
+                        std::array::from_fn(|i| min[i].min(value[i])),
+                        std::array::from_fn(|i| max[i].max(value[i])),
+                    )
+                },
+            )
+        });
+        self.accessor(&bytes, ARRAY_BUFFER, FLOAT, values.len(), "VEC3", bounds)
+    }
+
+    fn vec2_accessor(&mut self, values: &[[f32; 2]]) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        self.accessor(&bytes, ARRAY_BUFFER, FLOAT, values.len(), "VEC2", None)
+    }
+
+    fn index_accessor(&mut self, indices: &Indices) -> usize {
+        let (bytes, component_type): (Vec<u8>, _) = match indices {
+            Indices::U16(indices) => (
+                indices.iter().flat_map(|i| i.to_le_bytes()).collect(),
+                UNSIGNED_SHORT,
+            ),
+            Indices::U32(indices) => (
+                indices.iter().flat_map(|i| i.to_le_bytes()).collect(),
+                UNSIGNED_INT,
+            ),
+        };
+        self.accessor(
+            &bytes,
+            ELEMENT_ARRAY_BUFFER,
+            component_type,
+            indices.len(),
+            "SCALAR",
+            None,
+        )
+    }
+
+    /// Writes the GLB file with `scene` as its only scene
+    fn finish(mut self, scene: &[usize]) -> Vec<u8> {
+        let mut json = format!(
+            "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"bevy_embedded\"}},\"scene\":0,\"scenes\":[{{\"nodes\":{}}}]",
+            json_indices(scene)
+        );
+        // glTF forbids empty arrays
+        for (key, items) in [
+            ("nodes", &self.nodes),
+            ("meshes", &self.meshes),
+            ("materials", &self.materials),
+            ("accessors", &self.accessors),
+            ("bufferViews", &self.buffer_views),
+        ] {
+            if !items.is_empty() {
+                let _ = write!(json, ",\"{key}\":[{}]", items.join(","));
+            }
+        }
+        if !self.bin.is_empty() {
+            let _ = write!(json, ",\"buffers\":[{{\"byteLength\":{}}}]", self.bin.len());
+        }
+        json.push('}');
+
+        let mut json = json.into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        while !self.bin.len().is_multiple_of(4) {
+            self.bin.push(0);
+        }
+
+        let mut length = 12 + 8 + json.len();
+        if !self.bin.is_empty() {
+            length += 8 + self.bin.len();
+        }
+        let mut glb = ByteWriter::new();
+        glb.bytes(b"glTF").u32(2).u32(length as u32);
+        glb.u32(json.len() as u32).u32(CHUNK_JSON).bytes(&json);
+        if !self.bin.is_empty() {
+            glb.u32(self.bin.len() as u32)
+                .u32(CHUNK_BIN)
+                .bytes(&self.bin);
+        }
+        glb.finish()
+    }
+}
+
+/// Formats a number as JSON, which has no NaN or infinity
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "0".to_string()
+    }
+}
+
+fn json_numbers(values: &[f32]) -> String {
+    let values: Vec<String> = values.iter().copied().map(json_number).collect();
+    format!("[{}]", values.join(","))
+}
+
+fn json_indices(values: &[usize]) -> String {
+    let values: Vec<String> = values.iter().map(usize::to_string).collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Exports entities requested by the host
+///
+/// Runs in `Last`, so exported transforms include this frame's changes.
+pub(crate) fn receive_export_commands(
+    mut host_commands: MessageReader<HostCommand>,
+    channel: Res<HostChannel>,
+    export: GlbExport,
+    selected: Query<Entity, With<Selected>>,
+) {
+    for command in host_commands.read() {
+        if command.topic != ReservedTopic::ExportScene {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        let (Some(request_id), Some(count)) = (reader.u32(), reader.u32()) else {
+            log::warn!("Ignoring malformed export scene command");
+            continue;
+        };
+        let roots: Option<Vec<Entity>> = if count == 0 {
+            Some(selected.iter().collect())
+        } else {
+            (0..count)
+                .map(|_| reader.u64().map(Entity::try_from_bits))
+                .collect::<Option<Vec<_>>>()
+                .map(|entities| entities.into_iter().flatten().collect())
+        };
+        let Some(roots) = roots else {
+            log::warn!("Ignoring malformed export scene command");
+            continue;
+        };
+
+        let mut payload = ByteWriter::new();
+        payload.u32(request_id);
+        match export.export(&roots) {
+            Some(glb) => payload.u8(0).bytes(&glb),
+            None => {
+                log::warn!("Nothing to export for host request {request_id}");
+                payload.u8(1)
+            }
+        };
+        channel.send_reserved(ReservedTopic::SceneExported, &payload.finish());
+    }
+}
@@ -0,0 +1,2030 @@
+//! Platform-independent implementations of the host FFI entry points
+//!
+//! The iOS C functions and Android JNI functions are thin wrappers around these, so
+//! input validation is shared between platforms and can be exercised (and fuzzed) on
+//! desktop. Null pointers and unknown touch phases are always rejected; with the
+//! `ffi_validation` feature every input is checked strictly and violations are logged.
+
+use std::ffi::{CStr, c_char, c_void};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "gpu_interop")]
+use bevy::render::{
+    renderer::RenderDevice,
+    texture::{ManualTextureView, ManualTextureViews},
+};
+use bevy::{
+    app::{App, PluginsState},
+    camera::Camera,
+    ecs::{
+        change_detection::DetectChangesMut,
+        entity::Entity,
+        query::{Has, With},
+    },
+    input::{
+        ButtonInput,
+        keyboard::{Key, KeyCode},
+        mouse::MouseButton,
+    },
+    math::{Mat4, URect, UVec2, Vec2},
+    tasks::tick_global_task_pools_on_main_thread,
+    transform::components::Transform,
+    window::{
+        PrimaryWindow, RawHandleWrapper, RawHandleWrapperHolder, Window, WindowCloseRequested,
+        WindowCreated, WindowEvent, WindowResolution,
+    },
+};
+
+use crate::{
+    AccessibilitySettings, AccessibilitySettingsChanged, EmbeddedInputEvents, EmbeddedKeyEvent,
+    EmbeddedKeyboardEvent, EmbeddedPredictedTouch, EmbeddedTouchEvent, FallbackFrame, FrameDamage,
+    FrameInfo, GpuTiming, Handshake, HandshakeStatus, HibernatedState, HostChannel, HostClock,
+    HostConfig, HostLifecycle, HostLifecycleChanged, HostScene, HostSchedule, InitProgress,
+    InputLatency, KeyModifiers, KeyboardModifiers, OrbitCamera, PhaseTiming, PredictedTouches,
+    ProtocolSchema, RenderGc, RingEndpoint, SafeArea, SafeAreaChanged, StartupPhase, SurfaceEpoch,
+    SurfacePresentation, SurfaceRotation, SurfaceTransform, TouchContact, TouchIds, TouchPhase,
+    app_thread, audio_input,
+    display::{find_external_display, spawn_external_display},
+    frame_info::{self, FrameStats},
+    frozen_frame, heartbeat, hibernation, host_jobs, input_latency, lifecycle,
+    preload::{self, PreloadSurface},
+    protocol::{ReservedTopic, encode_reserved},
+    recovery, registry, release_window_surface, render_gc, replace_window_surface,
+    replay::{self, ReplayEvent},
+    resolution::{
+        DynamicResolution, DynamicResolutionSettings, HostSurface, apply_window_resolution,
+        record_frame_time,
+    },
+    startup,
+    surface_epoch::SURFACE_WAIT_TIMEOUT,
+    telemetry,
+};
+#[cfg(feature = "channel_encryption")]
+use crate::{ChannelCipher, ChannelDirection, ChannelKey};
+#[cfg(feature = "gpu_interop")]
+use crate::{
+    FrameFence, HOST_FRAME_VIEW, HostFrame, OffscreenFormat, OffscreenTarget,
+    OffscreenTargetSettings,
+};
+
+/// Largest message accepted from the host when strict validation is enabled
+pub const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+/// Largest surface dimension accepted when strict validation is enabled
+pub const MAX_SURFACE_DIMENSION: u32 = 16384;
+
+/// Reason an FFI call from the host was rejected
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FfiViolation {
+    /// A required pointer argument was null
+    NullPointer(&'static str),
+    /// A pointer argument was not aligned for its type
+    MisalignedPointer(&'static str),
+    /// The touch phase was not one of the known values
+    InvalidTouchPhase(u8),
+    /// The surface rotation was not one of the known values
+    InvalidRotation(u8),
+    /// The surface presentation was not one of the known values
+    InvalidPresentation(u8),
+    /// The app has no primary window to attach a surface to
+    NoWindow,
+    /// The app already has a primary window, so another can't be opened
+    WindowOpen,
+    /// No external display with this id is attached
+    UnknownDisplay(u32),
+    /// The lifecycle state was not one of the known values
+    InvalidLifecycle(u8),
+    /// A string argument was not valid UTF-8
+    InvalidUtf8(&'static str),
+    /// A coordinate was NaN or infinite
+    NonFiniteCoordinate,
+    /// The surface dimensions were zero or unreasonably large
+    InvalidDimensions {
+        /// Requested width
+        width: u32,
+        /// Requested height
+        height: u32,
+    },
+    /// The scale factor was not a positive finite number
+    InvalidScaleFactor(f32),
+    /// The dynamic resolution scale limits were not within `0 < min <= max <= 1`
+    InvalidScaleRange {
+        /// Requested minimum scale
+        min: f32,
+        /// Requested maximum scale
+        max: f32,
+    },
+    /// The target frame rate was not a positive finite number
+    InvalidFrameRate(f32),
+    /// The view matrix was not finite and invertible
+    InvalidMatrix,
+    /// There is no camera to apply the call to
+    NoCamera,
+    /// The message exceeded [`MAX_MESSAGE_LEN`]
+    MessageTooLarge(usize),
+    /// The app pointer does not refer to a live instance (e.g. it was destroyed)
+    UnknownInstance,
+    /// The app has not opened a ring channel for this topic
+    UnknownTopic(u16),
+    /// The host timestamp was NaN or infinite
+    InvalidTimestamp(f64),
+    /// The preferred font scale was not finite and positive
+    InvalidFontScale(f32),
+    /// A safe area inset was negative or not finite
+    InvalidSafeArea,
+    /// The audio sample rate or channel count was zero
+    InvalidAudioFormat {
+        /// Frames per second
+        sample_rate: u32,
+        /// Interleaved channels
+        channels: u16,
+    },
+    /// The viewport was empty or not within the host texture
+    InvalidViewport {
+        /// Requested viewport
+        viewport: URect,
+        /// Size of the host texture
+        size: UVec2,
+    },
+    /// The app has no render device, e.g. because it does not render
+    NoRenderer,
+    /// The render backend has no fences that can be shared with the host
+    NoFrameFence,
+    /// The schedule was not one of the known values
+    InvalidSchedule(u8),
+    /// The offscreen format was unknown, or has no sRGB variant but sRGB was requested
+    InvalidOffscreenFormat {
+        /// Requested format
+        format: u8,
+        /// Whether sRGB encoding was requested
+        srgb: bool,
+    },
+    /// The channel key was not [`CHANNEL_KEY_LEN`](crate::encryption::CHANNEL_KEY_LEN)
+    /// bytes long
+    InvalidChannelKey(usize),
+    /// The message was not sealed with the channel key, or was altered
+    MessageNotAuthentic,
+    /// The message could not be sealed, e.g. because no random nonce was available
+    SealFailed,
+    /// The output buffer cannot hold the result
+    BufferTooSmall {
+        /// Bytes needed
+        needed: usize,
+        /// Bytes available
+        len: usize,
+    },
+    /// The bytes were not a [`ReplayLog`](crate::ReplayLog) this version can read
+    InvalidReplayLog,
+    /// The app was called from a thread other than its own; see
+    /// [`ThreadPolicy`](crate::app_thread::ThreadPolicy)
+    WrongThread,
+    /// The bytes were not a [`HibernatedState`](crate::HibernatedState) this version can
+    /// read
+    InvalidHibernatedState,
+    /// The debug toggle was not one of the known values
+    InvalidDebugToggle(u8),
+    /// The startup phase was not one of the known values
+    InvalidStartupPhase(u8),
+    /// The fallback frame was empty, or its pixels were not `width * height` RGBA8 values
+    InvalidFallbackFrame {
+        /// Width in pixels
+        width: u32,
+        /// Height in pixels
+        height: u32,
+        /// Bytes of pixel data
+        len: usize,
+    },
+    /// The call needs a plugin the app did not add
+    MissingPlugin(&'static str),
+}
+
+impl fmt::Display for FfiViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NullPointer(name) => write!(f, "`{name}` is null"),
+            Self::MisalignedPointer(name) => write!(f, "`{name}` is misaligned"),
+            Self::InvalidTouchPhase(phase) => write!(f, "invalid touch phase {phase}"),
+            Self::InvalidRotation(rotation) => write!(f, "invalid surface rotation {rotation}"),
+            Self::InvalidPresentation(presentation) => {
+                write!(f, "invalid surface presentation {presentation}")
+            }
+            Self::NoWindow => write!(f, "app has no primary window"),
+            Self::WindowOpen => write!(f, "app already has a primary window"),
+            Self::UnknownDisplay(id) => write!(f, "no external display with id {id}"),
+            Self::InvalidLifecycle(state) => write!(f, "invalid lifecycle state {state}"),
+            Self::InvalidUtf8(name) => write!(f, "`{name}` is not valid UTF-8"),
+            Self::NonFiniteCoordinate => write!(f, "coordinate is not finite"),
+            Self::InvalidDimensions { width, height } => {
+                write!(f, "invalid surface dimensions {width}x{height}")
+            }
+            Self::InvalidScaleFactor(scale) => write!(f, "invalid scale factor {scale}"),
+            Self::InvalidScaleRange { min, max } => {
+                write!(f, "invalid resolution scale range {min}..={max}")
+            }
+            Self::InvalidFrameRate(fps) => write!(f, "invalid target frame rate {fps}"),
+            Self::InvalidMatrix => write!(f, "matrix is not finite and invertible"),
+            Self::NoCamera => write!(f, "app has no camera"),
+            Self::MessageTooLarge(len) => write!(f, "message of {len} bytes is too large"),
+            Self::UnknownInstance => write!(f, "app is not a live instance"),
+            Self::UnknownTopic(topic) => write!(f, "no ring channel for topic {topic}"),
+            Self::InvalidTimestamp(time) => write!(f, "invalid host timestamp {time}"),
+            Self::InvalidFontScale(scale) => write!(f, "invalid font scale {scale}"),
+            Self::InvalidSafeArea => write!(f, "invalid safe area insets"),
+            Self::InvalidAudioFormat {
+                sample_rate,
+                channels,
+            } => write!(f, "invalid audio format {sample_rate} Hz x {channels}"),
+            Self::InvalidViewport { viewport, size } => write!(
+                f,
+                "viewport {}..{} is not within the {}x{} texture",
+                viewport.min, viewport.max, size.x, size.y
+            ),
+            Self::NoRenderer => write!(f, "app has no render device"),
+            Self::NoFrameFence => write!(f, "render backend has no shareable fences"),
+            Self::InvalidSchedule(schedule) => write!(f, "invalid schedule {schedule}"),
+            Self::InvalidOffscreenFormat { format, srgb } => {
+                write!(f, "invalid offscreen format {format} (sRGB: {srgb})")
+            }
+            Self::InvalidChannelKey(len) => write!(f, "channel key of {len} bytes is invalid"),
+            Self::MessageNotAuthentic => write!(f, "message failed to authenticate"),
+            Self::SealFailed => write!(f, "message could not be sealed"),
+            Self::BufferTooSmall { needed, len } => {
+                write!(f, "buffer of {len} bytes cannot hold {needed} bytes")
+            }
+            Self::InvalidReplayLog => write!(f, "replay log is malformed or unsupported"),
+            Self::WrongThread => write!(f, "app called from a thread other than its own"),
+            Self::InvalidHibernatedState => {
+                write!(f, "hibernated state is malformed or unsupported")
+            }
+            Self::InvalidDebugToggle(toggle) => write!(f, "invalid debug toggle {toggle}"),
+            Self::InvalidStartupPhase(phase) => write!(f, "invalid startup phase {phase}"),
+            Self::InvalidFallbackFrame { width, height, len } => {
+                write!(
+                    f,
+                    "{len} bytes are not a {width}x{height} RGBA8 fallback frame"
+                )
+            }
+            Self::MissingPlugin(plugin) => write!(f, "app did not add `{plugin}`"),
+        }
+    }
+}
+
+impl std::error::Error for FfiViolation {}
+
+/// Logs a violation (with strict validation enabled) and hands it back for returning
+fn reject<T>(violation: FfiViolation) -> Result<T, FfiViolation> {
+    #[cfg(feature = "ffi_validation")]
+    log::warn!("Rejected FFI call: {violation}");
+    Err(violation)
+}
+
+/// An app borrowed by [`app_mut`]
+///
+/// Off the app's thread with [`ThreadPolicy::Marshal`](crate::ThreadPolicy::Marshal), the
+/// app's thread waits until this is dropped, see [`app_thread`](crate::app_thread).
+pub struct AppMut<'a> {
+    app: &'a mut App,
+    _lease: Option<app_thread::Lease>,
+}
+
+impl Deref for AppMut<'_> {
+    type Target = App;
+
+    fn deref(&self) -> &App {
+        self.app
+    }
+}
+
+impl DerefMut for AppMut<'_> {
+    fn deref_mut(&mut self) -> &mut App {
+        self.app
+    }
+}
+
+/// Converts the opaque app pointer passed by the host into an `App`
+///
+/// The pointer is a handle from the [`registry`](crate::registry); handles of destroyed
+/// apps are rejected.
+///
+/// # Safety
+///
+/// The app must not be aliased for the returned lifetime.
+///
+/// Fails on a thread other than the app's, unless the app runs on its own thread and is
+/// lent to this one, see [`app_thread`](crate::app_thread).
+pub unsafe fn app_mut<'a>(app: *mut c_void) -> Result<AppMut<'a>, FfiViolation> {
+    if app.is_null() {
+        return reject(FfiViolation::NullPointer("app"));
+    }
+    let Some(address) = registry::resolve(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    let lease = app_thread::lend(app as *const App)?;
+    Ok(AppMut {
+        app: unsafe { &mut *address },
+        _lease: lease,
+    })
+}
+
+/// Borrows a message buffer passed by the host
+///
+/// # Safety
+///
+/// `data` must be null or valid for reads of `len` bytes for the returned lifetime.
+pub unsafe fn message_slice<'a>(data: *const u8, len: usize) -> Result<&'a [u8], FfiViolation> {
+    if data.is_null() {
+        return reject(FfiViolation::NullPointer("data"));
+    }
+    if cfg!(feature = "ffi_validation") && len > MAX_MESSAGE_LEN {
+        return reject(FfiViolation::MessageTooLarge(len));
+    }
+    Ok(unsafe { std::slice::from_raw_parts(data, len) })
+}
+
+/// Reads a column-major 4x4 matrix passed by the host
+///
+/// # Safety
+///
+/// `data` must be null or valid for reads of 16 `f32`s.
+pub unsafe fn matrix(data: *const f32) -> Result<[f32; 16], FfiViolation> {
+    if data.is_null() {
+        return reject(FfiViolation::NullPointer("matrix"));
+    }
+    if cfg!(feature = "ffi_validation") && !data.is_aligned() {
+        return reject(FfiViolation::MisalignedPointer("matrix"));
+    }
+    Ok(unsafe { std::ptr::read(data as *const [f32; 16]) })
+}
+
+/// Borrows a NUL-terminated UTF-8 string passed by the host
+///
+/// # Safety
+///
+/// `string` must be null or point to a NUL-terminated string valid for the returned
+/// lifetime.
+pub unsafe fn c_str<'a>(
+    string: *const c_char,
+    name: &'static str,
+) -> Result<&'a str, FfiViolation> {
+    if string.is_null() {
+        return reject(FfiViolation::NullPointer(name));
+    }
+    match unsafe { CStr::from_ptr(string) }.to_str() {
+        Ok(string) => Ok(string),
+        Err(_) => reject(FfiViolation::InvalidUtf8(name)),
+    }
+}
+
+/// Creates an empty [`HostConfig`] for the host to fill and pass to a create call
+///
+/// The host frees it with [`free_host_config`] once it no longer needs it.
+pub fn new_host_config() -> *mut HostConfig {
+    Box::into_raw(Box::default())
+}
+
+/// Sets a creation config value in a config from [`new_host_config`]
+///
+/// # Safety
+///
+/// `config` must be null or a config from [`new_host_config`] that was not freed, and
+/// `key` and `value` null or NUL-terminated strings.
+pub unsafe fn host_config_set(
+    config: *mut HostConfig,
+    key: *const c_char,
+    value: *const c_char,
+) -> Result<(), FfiViolation> {
+    let Some(config) = (unsafe { config.as_mut() }) else {
+        return reject(FfiViolation::NullPointer("config"));
+    };
+    let key = unsafe { c_str(key, "key") }?;
+    let value = unsafe { c_str(value, "value") }?;
+    config.set(key, value);
+    Ok(())
+}
+
+/// Frees a config from [`new_host_config`]
+///
+/// # Safety
+///
+/// `config` must be null or a config from [`new_host_config`] that was not freed.
+pub unsafe fn free_host_config(config: *mut HostConfig) {
+    if !config.is_null() {
+        drop(unsafe { Box::from_raw(config) });
+    }
+}
+
+/// A copy of the config a host passed to a create call, or an empty one for null
+///
+/// # Safety
+///
+/// `config` must be null or a config from [`new_host_config`] that was not freed.
+#[doc(hidden)]
+pub unsafe fn host_config(config: *const HostConfig) -> HostConfig {
+    unsafe { config.as_ref() }.cloned().unwrap_or_default()
+}
+
+/// Queues a touch event from the host
+pub fn touch_event(app: &mut App, phase: u8, x: f32, y: f32, id: u64) -> Result<(), FfiViolation> {
+    queue_touch(app, phase, x, y, id, None)
+}
+
+/// Queues a touch event carrying contact data such as Android `MotionEvent` axes
+pub fn touch_event_with_contact(
+    app: &mut App,
+    phase: u8,
+    x: f32,
+    y: f32,
+    id: u64,
+    contact: TouchContact,
+) -> Result<(), FfiViolation> {
+    let TouchContact {
+        pressure,
+        touch_major,
+        touch_minor,
+        orientation,
+    } = contact;
+    if cfg!(feature = "ffi_validation")
+        && ![pressure, touch_major, touch_minor, orientation]
+            .iter()
+            .all(|value| value.is_finite())
+    {
+        return reject(FfiViolation::NonFiniteCoordinate);
+    }
+    queue_touch(app, phase, x, y, id, Some(contact))
+}
+
+/// Cancels every touch that is down, e.g. when the host view loses the gesture
+///
+/// Touches are cancelled at their last position, including those queued this frame. See
+/// [`TouchIds`].
+pub fn cancel_touches(app: &mut App) {
+    let world = app.world();
+    let down = world.get_resource::<TouchIds>().map(|touch_ids| {
+        touch_ids.down_after(&world.resource::<EmbeddedInputEvents>().touch_events)
+    });
+    for (id, position) in down.unwrap_or_default() {
+        let _ = queue_touch(
+            app,
+            TouchPhase::Cancelled as u8,
+            position.x,
+            position.y,
+            id,
+            None,
+        );
+    }
+}
+
+/// Cancels every touch and releases every pressed key and button
+///
+/// [`set_lifecycle`] calls it when the app stops being active, so no touch or key stays
+/// down through a trip to the background.
+pub fn cancel_input(app: &mut App) {
+    cancel_touches(app);
+    let world = app.world_mut();
+    if let Some(mut keys) = world.get_resource_mut::<ButtonInput<KeyCode>>() {
+        keys.reset_all();
+    }
+    if let Some(mut keys) = world.get_resource_mut::<ButtonInput<Key>>() {
+        keys.reset_all();
+    }
+    if let Some(mut buttons) = world.get_resource_mut::<ButtonInput<MouseButton>>() {
+        buttons.reset_all();
+    }
+    if let Some(mut modifiers) = world.get_resource_mut::<KeyboardModifiers>() {
+        *modifiers = KeyboardModifiers::default();
+    }
+}
+
+/// Queues a predicted sample extending touch `id` for this frame; see [`PredictedTouches`]
+pub fn predicted_touch(app: &mut App, x: f32, y: f32, id: u64) -> Result<(), FfiViolation> {
+    if cfg!(feature = "ffi_validation") && !(x.is_finite() && y.is_finite()) {
+        return reject(FfiViolation::NonFiniteCoordinate);
+    }
+    if !app.world().contains_resource::<PredictedTouches>() {
+        return reject(FfiViolation::MissingPlugin("PredictedTouchesPlugin"));
+    }
+
+    replay::record(app.world_mut(), || ReplayEvent::PredictedTouch {
+        position: Vec2::new(x, y),
+        id,
+    });
+    let mut input_events = app.world_mut().resource_mut::<EmbeddedInputEvents>();
+    input_events.add_predicted_touch(EmbeddedPredictedTouch {
+        position: Vec2::new(x, y),
+        id,
+    });
+    Ok(())
+}
+
+/// Queues rotary input; `delta` is a scroll distance, positive for clockwise rotation
+///
+/// See [`crate::RotaryInput`].
+pub fn rotary_event(app: &mut App, delta: f32) -> Result<(), FfiViolation> {
+    if cfg!(feature = "ffi_validation") && !delta.is_finite() {
+        return reject(FfiViolation::NonFiniteCoordinate);
+    }
+
+    replay::record(app.world_mut(), || ReplayEvent::Rotary(delta));
+    let mut input_events = app.world_mut().resource_mut::<EmbeddedInputEvents>();
+    input_events.add_rotary_event(delta);
+    Ok(())
+}
+
+/// Queues a hardware key press or release; see [`crate::KeyboardModifiers`]
+pub fn key_event(
+    app: &mut App,
+    key_code: KeyCode,
+    pressed: bool,
+    repeat: bool,
+    modifiers: KeyModifiers,
+    text: Option<&str>,
+) {
+    replay::record(app.world_mut(), || ReplayEvent::Key {
+        key_code,
+        pressed,
+        repeat,
+        modifiers,
+        text: text.map(str::to_string),
+    });
+    let mut input_events = app.world_mut().resource_mut::<EmbeddedInputEvents>();
+    input_events.add_keyboard_event(EmbeddedKeyboardEvent::Key(EmbeddedKeyEvent {
+        key_code,
+        pressed,
+        repeat,
+        modifiers,
+        text: text.map(str::to_string),
+    }));
+}
+
+/// Queues the widget gaining or losing keyboard focus
+///
+/// Losing focus releases every key, as keys let go while the widget is unfocused are
+/// never reported.
+pub fn keyboard_focus(app: &mut App, focused: bool) {
+    replay::record(app.world_mut(), || ReplayEvent::KeyboardFocus(focused));
+    let mut input_events = app.world_mut().resource_mut::<EmbeddedInputEvents>();
+    input_events.add_keyboard_event(EmbeddedKeyboardEvent::Focus(focused));
+}
+
+fn queue_touch(
+    app: &mut App,
+    phase: u8,
+    x: f32,
+    y: f32,
+    id: u64,
+    contact: Option<TouchContact>,
+) -> Result<(), FfiViolation> {
+    let Some(phase) = TouchPhase::from_u8(phase) else {
+        return reject(FfiViolation::InvalidTouchPhase(phase));
+    };
+    if cfg!(feature = "ffi_validation") && !(x.is_finite() && y.is_finite()) {
+        return reject(FfiViolation::NonFiniteCoordinate);
+    }
+
+    replay::record(app.world_mut(), || ReplayEvent::Touch {
+        phase,
+        position: Vec2::new(x, y),
+        id,
+        contact,
+    });
+    input_latency::touch_queued(app.world_mut());
+    let mut input_events = app.world_mut().resource_mut::<EmbeddedInputEvents>();
+    input_events.add_touch_event(EmbeddedTouchEvent {
+        phase,
+        position: Vec2::new(x, y),
+        id,
+        contact,
+    });
+    Ok(())
+}
+
+/// Strictly validates a surface size reported by the host
+fn check_surface_size(width: u32, height: u32, scale_factor: f32) -> Result<(), FfiViolation> {
+    if cfg!(feature = "ffi_validation") {
+        if width == 0
+            || height == 0
+            || width > MAX_SURFACE_DIMENSION
+            || height > MAX_SURFACE_DIMENSION
+        {
+            return reject(FfiViolation::InvalidDimensions { width, height });
+        }
+        if !(scale_factor.is_finite() && scale_factor > 0.0) {
+            return reject(FfiViolation::InvalidScaleFactor(scale_factor));
+        }
+    }
+    Ok(())
+}
+
+/// Starts a new [`SurfaceEpoch`] and waits until no frame of the previous one is using
+/// the host's surfaces, on the CPU or, with a [`FrameFence`], on the GPU
+///
+/// Called before every host surface change, so frames rendering on the render thread
+/// with pipelined rendering never race the change.
+pub fn retire_surface_frames(app: &mut App) {
+    let Some(surface_epoch) = app.world().get_resource::<SurfaceEpoch>() else {
+        return;
+    };
+    surface_epoch.advance();
+    if !surface_epoch.wait_idle(SURFACE_WAIT_TIMEOUT) {
+        log::warn!("Timed out waiting for in-flight frames before a surface change");
+    }
+
+    #[cfg(feature = "gpu_interop")]
+    if let Some(fence) = app.world().get_resource::<FrameFence>()
+        && !fence.wait(fence.value(), SURFACE_WAIT_TIMEOUT)
+    {
+        log::warn!("Timed out waiting for the GPU before a surface change");
+    }
+}
+
+/// Applies a surface resize from the host to the primary window
+///
+/// `width` and `height` are the physical size of the host surface; they are swapped
+/// when the [`SurfaceTransform`] rotates the content by 90 or 270 degrees.
+pub fn resize(
+    app: &mut App,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> Result<(), FfiViolation> {
+    check_surface_size(width, height, scale_factor)?;
+    replay::record(app.world_mut(), || ReplayEvent::Resize {
+        width,
+        height,
+        scale_factor,
+    });
+
+    let unchanged = app.world().get_resource::<HostSurface>()
+        == Some(&HostSurface {
+            width,
+            height,
+            scale_factor,
+        });
+    if !unchanged {
+        retire_surface_frames(app);
+    }
+
+    app.insert_resource(HostSurface {
+        width,
+        height,
+        scale_factor,
+    });
+    apply_window_resolution(app.world_mut());
+    Ok(())
+}
+
+/// Moves the primary window to a new host surface, e.g. for a full-screen takeover
+///
+/// The app keeps running; the swapchain is recreated for `handle` over the next two
+/// frames. `width` and `height` are the physical size of the new surface.
+pub fn replace_surface(
+    app: &mut App,
+    handle: RawHandleWrapper,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+    presentation: u8,
+) -> Result<(), FfiViolation> {
+    let Some(presentation) = SurfacePresentation::from_u8(presentation) else {
+        return reject(FfiViolation::InvalidPresentation(presentation));
+    };
+
+    let mut query = app
+        .world_mut()
+        .query_filtered::<Entity, With<PrimaryWindow>>();
+    let Ok(window) = query.single(app.world()) else {
+        return reject(FfiViolation::NoWindow);
+    };
+
+    check_surface_size(width, height, scale_factor)?;
+    retire_surface_frames(app);
+    // Set first so the resize below applies the picture-in-picture budget
+    app.world_mut()
+        .resource_mut::<SurfacePresentation>()
+        .set_if_neq(presentation);
+    resize(app, width, height, scale_factor)?;
+    replace_window_surface(app.world_mut(), window, handle);
+
+    log::info!("Moved primary window to a new surface ({presentation:?})");
+    Ok(())
+}
+
+/// Asks the app to close its primary window, as the close button of a desktop window
+/// would
+///
+/// Writes `WindowCloseRequested` and cancels the input in progress. Keep updating the
+/// app until [`window_closed`], so its teardown systems run. Destroying the app closes
+/// the window first, see [`teardown`](crate::teardown). Returns false if the app has no
+/// window. See [`surface`](crate::surface).
+pub fn close_window(app: &mut App) -> bool {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<Entity, With<PrimaryWindow>>();
+    let Ok(window) = query.single(app.world()) else {
+        return false;
+    };
+    cancel_input(app);
+    let world = app.world_mut();
+    world.write_message(WindowCloseRequested { window });
+    world.write_message(WindowEvent::WindowCloseRequested(WindowCloseRequested {
+        window,
+    }));
+    log::info!("Host requested closing the primary window");
+    true
+}
+
+/// Whether the app has no primary window, e.g. once [`close_window`] closed it
+pub fn window_closed(app: &mut App) -> bool {
+    let mut query = app.world_mut().query_filtered::<(), With<PrimaryWindow>>();
+    query.iter(app.world()).next().is_none()
+}
+
+/// Opens a new primary window on a host surface after the last one was closed
+///
+/// `width` and `height` are the physical size of the surface. Writes `WindowCreated`.
+pub fn open_window(
+    app: &mut App,
+    handle: RawHandleWrapper,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> Result<(), FfiViolation> {
+    if !window_closed(app) {
+        return reject(FfiViolation::WindowOpen);
+    }
+    check_surface_size(width, height, scale_factor)?;
+
+    let holder = RawHandleWrapperHolder(Arc::new(Mutex::new(Some(handle.clone()))));
+    let window = app
+        .world_mut()
+        .spawn((Window::default(), handle, holder, PrimaryWindow))
+        .id();
+    resize(app, width, height, scale_factor)?;
+    let world = app.world_mut();
+    world.write_message(WindowCreated { window });
+    world.write_message(WindowEvent::WindowCreated(WindowCreated { window }));
+    log::info!("Opened primary window {window}");
+    Ok(())
+}
+
+/// Sets how many apps to keep preloaded, created at the given surface size with a copy
+/// of `config`
+///
+/// See [`preload`](crate::preload).
+pub fn set_preload_target(
+    count: u32,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+    config: HostConfig,
+) -> Result<(), FfiViolation> {
+    check_surface_size(width, height, scale_factor)?;
+    preload::set_preload_target(
+        count as usize,
+        PreloadSurface {
+            width,
+            height,
+            scale_factor,
+        },
+        config,
+    );
+    Ok(())
+}
+
+/// Attaches a preloaded app to its surface and makes it active
+///
+/// Call it on an app taken with [`preload::take_preloaded`] or
+/// [`pending::take`](crate::pending::take) before its first update.
+pub fn activate_preloaded(
+    app: &mut App,
+    handle: RawHandleWrapper,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> Result<(), FfiViolation> {
+    replace_surface(
+        app,
+        handle,
+        width,
+        height,
+        scale_factor,
+        SurfacePresentation::Inline as u8,
+    )?;
+    set_lifecycle(app, HostLifecycle::Active as u8)
+}
+
+/// Detaches the app from a host scene the host disconnected
+///
+/// Releases the primary window's surface so the host can free its view, and moves the
+/// app to the background until [`connect_scene`] gives it a new one.
+pub fn disconnect_scene(app: &mut App) -> Result<(), FfiViolation> {
+    let mut query = app
+        .world_mut()
+        .query_filtered::<Entity, With<PrimaryWindow>>();
+    let Ok(window) = query.single(app.world()) else {
+        return reject(FfiViolation::NoWindow);
+    };
+
+    retire_surface_frames(app);
+    release_window_surface(app.world_mut(), window);
+    if let Some(mut scene) = app.world_mut().get_resource_mut::<HostScene>() {
+        scene.set_connected(false);
+    }
+    set_lifecycle(app, HostLifecycle::Background as u8)?;
+
+    log::info!("Host scene disconnected");
+    Ok(())
+}
+
+/// Attaches the app to the surface of a reconnected host scene and makes it active
+pub fn connect_scene(
+    app: &mut App,
+    handle: RawHandleWrapper,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> Result<(), FfiViolation> {
+    replace_surface(
+        app,
+        handle,
+        width,
+        height,
+        scale_factor,
+        SurfacePresentation::Inline as u8,
+    )?;
+    if let Some(mut scene) = app.world_mut().get_resource_mut::<HostScene>() {
+        scene.set_connected(true);
+    }
+    set_lifecycle(app, HostLifecycle::Active as u8)?;
+
+    log::info!("Host scene connected");
+    Ok(())
+}
+
+/// The app's render device, for wrapping host GPU resources
+///
+/// Finishes the app's plugins first if it hasn't run its first update yet, since the
+/// device is only installed then.
+#[cfg(feature = "gpu_interop")]
+pub fn render_device(app: &mut App) -> Result<RenderDevice, FfiViolation> {
+    finish_plugins(app);
+    match app.world().get_resource::<RenderDevice>() {
+        Some(device) => Ok(device.clone()),
+        None => reject(FfiViolation::NoRenderer),
+    }
+}
+
+/// Makes the app render its next frames into `texture` at `viewport` instead of its
+/// window surface
+///
+/// Creates a primary window without a surface, sized to the viewport, if the app has
+/// none. `texture` must have been created on the app's [`render_device`] with
+/// `RENDER_ATTACHMENT` usage.
+#[cfg(feature = "gpu_interop")]
+pub fn set_host_frame(
+    app: &mut App,
+    texture: &wgpu::Texture,
+    viewport: URect,
+    scale_factor: f32,
+) -> Result<(), FfiViolation> {
+    let size = UVec2::new(texture.width(), texture.height());
+    if viewport.is_empty() || viewport.max.x > size.x || viewport.max.y > size.y {
+        return reject(FfiViolation::InvalidViewport { viewport, size });
+    }
+
+    let world = app.world_mut();
+    let Some(mut views) = world.get_resource_mut::<ManualTextureViews>() else {
+        return reject(FfiViolation::NoRenderer);
+    };
+    // Only the first mip level can be a render attachment
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        mip_level_count: Some(1),
+        ..Default::default()
+    });
+    views.insert(
+        HOST_FRAME_VIEW,
+        ManualTextureView {
+            texture_view: view.into(),
+            size,
+            format: texture.format(),
+        },
+    );
+
+    let mut query = world.query_filtered::<Entity, With<PrimaryWindow>>();
+    if query.single(world).is_err() {
+        world.spawn((Window::default(), PrimaryWindow));
+        log::info!("Created a primary window for host frames");
+    }
+    world.insert_resource(HostFrame { size, viewport });
+    world.remove_resource::<OffscreenTarget>();
+    resize(app, viewport.width(), viewport.height(), scale_factor)?;
+
+    // Create the fence before the first frame so the host can wait for every frame
+    frame_fence(app).map(|_| ())
+}
+
+/// Makes the app render its next frames into a texture it allocates for the host
+///
+/// Calling it again with different settings replaces the texture; with the same
+/// settings it returns the current one. `mip_levels` of 0 allocates a full mip chain.
+/// See [`OffscreenTarget`].
+#[cfg(feature = "gpu_interop")]
+pub fn set_offscreen_target(
+    app: &mut App,
+    width: u32,
+    height: u32,
+    format: u8,
+    srgb: bool,
+    mip_levels: u32,
+    scale_factor: f32,
+) -> Result<wgpu::Texture, FfiViolation> {
+    let device = render_device(app)?;
+    let max_dimension = device.limits().max_texture_dimension_2d;
+    if width == 0 || height == 0 || width > max_dimension || height > max_dimension {
+        return reject(FfiViolation::InvalidDimensions { width, height });
+    }
+    let Some((offscreen_format, texture_format)) = OffscreenFormat::from_u8(format)
+        .and_then(|offscreen| Some((offscreen, offscreen.texture_format(srgb)?)))
+    else {
+        return reject(FfiViolation::InvalidOffscreenFormat { format, srgb });
+    };
+
+    let size = UVec2::new(width, height);
+    let full_chain = OffscreenTargetSettings::full_mip_chain(size);
+    let settings = OffscreenTargetSettings {
+        size,
+        format: offscreen_format,
+        srgb,
+        mip_levels: if mip_levels == 0 {
+            full_chain
+        } else {
+            mip_levels.min(full_chain)
+        },
+    };
+    if let Some(target) = app.world().get_resource::<OffscreenTarget>()
+        && target.settings == settings
+    {
+        return Ok(target.texture.clone());
+    }
+
+    // Hosts composite in either encoding, so 8-bit targets can be viewed as both
+    let counterpart = if texture_format.is_srgb() {
+        texture_format.remove_srgb_suffix()
+    } else {
+        texture_format.add_srgb_suffix()
+    };
+    let view_formats: &[_] = if counterpart == texture_format {
+        &[]
+    } else {
+        &[counterpart]
+    };
+    let texture = device
+        .wgpu_device()
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen_target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: settings.mip_levels,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats,
+        });
+
+    set_host_frame(
+        app,
+        &texture,
+        URect::from_corners(UVec2::ZERO, size),
+        scale_factor,
+    )?;
+    app.insert_resource(OffscreenTarget {
+        settings,
+        texture: texture.clone(),
+    });
+    log::info!("Rendering offscreen at {width}x{height} {texture_format:?}");
+    Ok(texture)
+}
+
+/// The app's frame fence, created on first use
+///
+/// The fence advances with every frame rendered from then on; see [`FrameFence`].
+#[cfg(feature = "gpu_interop")]
+pub fn frame_fence(app: &mut App) -> Result<FrameFence, FfiViolation> {
+    if let Some(fence) = app.world().get_resource::<FrameFence>() {
+        return Ok(fence.clone());
+    }
+
+    let device = render_device(app)?;
+    let Some(fence) = FrameFence::new(&device) else {
+        return reject(FfiViolation::NoFrameFence);
+    };
+    // The render world picks it up during the next extract
+    app.insert_resource(fence.clone());
+    Ok(fence)
+}
+
+/// Updates the host lifecycle state, sending [`HostLifecycleChanged`] if it changed
+pub fn set_lifecycle(app: &mut App, state: u8) -> Result<(), FfiViolation> {
+    let Some(current) = HostLifecycle::from_u8(state) else {
+        return reject(FfiViolation::InvalidLifecycle(state));
+    };
+
+    let world = app.world_mut();
+    replay::record(world, || ReplayEvent::Lifecycle(current));
+    let previous = std::mem::replace(&mut *world.resource_mut::<HostLifecycle>(), current);
+    if previous != current {
+        let picture_in_picture =
+            *world.resource::<SurfacePresentation>() == SurfacePresentation::PictureInPicture;
+        world.write_message(HostLifecycleChanged {
+            previous,
+            current,
+            picture_in_picture,
+        });
+        log::debug!("Host lifecycle changed from {previous:?} to {current:?}");
+    }
+
+    // The host stops delivering input, including the ends of touches that are down
+    if previous == HostLifecycle::Active && current != HostLifecycle::Active {
+        cancel_input(app);
+    }
+
+    // Backgrounded apps are the first the system kills for memory
+    if current == HostLifecycle::Background
+        && previous != HostLifecycle::Background
+        && app
+            .world()
+            .get_resource::<RenderGc>()
+            .is_some_and(|gc| gc.flush_in_background)
+    {
+        flush_render_resources(app);
+    }
+    Ok(())
+}
+
+/// Updates [`AccessibilitySettings`] with the user's preferences on the host
+pub fn set_accessibility(
+    app: &mut App,
+    reduce_motion: bool,
+    bold_text: bool,
+    font_scale: f32,
+) -> Result<(), FfiViolation> {
+    if !(font_scale.is_finite() && font_scale > 0.0) {
+        return reject(FfiViolation::InvalidFontScale(font_scale));
+    }
+
+    let current = AccessibilitySettings {
+        reduce_motion,
+        bold_text,
+        font_scale,
+    };
+    let world = app.world_mut();
+    let Some(mut settings) = world.get_resource_mut::<AccessibilitySettings>() else {
+        return reject(FfiViolation::MissingPlugin("AccessibilityPlugin"));
+    };
+    if *settings != current {
+        let previous = std::mem::replace(&mut *settings, current);
+        world.write_message(AccessibilitySettingsChanged { previous, current });
+        log::debug!("Host accessibility settings changed to {current:?}");
+    }
+    Ok(())
+}
+
+/// Updates [`SafeArea`] with the host view's insets, in logical pixels
+pub fn set_safe_area(
+    app: &mut App,
+    top: f32,
+    left: f32,
+    bottom: f32,
+    right: f32,
+) -> Result<(), FfiViolation> {
+    if ![top, left, bottom, right]
+        .iter()
+        .all(|inset| inset.is_finite() && *inset >= 0.0)
+    {
+        return reject(FfiViolation::InvalidSafeArea);
+    }
+
+    let current = SafeArea {
+        top,
+        left,
+        bottom,
+        right,
+    };
+    let world = app.world_mut();
+    let mut safe_area = world.resource_mut::<SafeArea>();
+    if *safe_area != current {
+        let previous = std::mem::replace(&mut *safe_area, current);
+        world.write_message(SafeAreaChanged { previous, current });
+        log::debug!("Host safe area changed to {current:?}");
+    }
+    Ok(())
+}
+
+/// Records the host's monotonic clock reading, in seconds, for [`HostClock`]
+///
+/// The first reading adds the [`HostClock`] resource.
+pub fn sync_clock(app: &mut App, host_time: f64) -> Result<(), FfiViolation> {
+    if !host_time.is_finite() {
+        return reject(FfiViolation::InvalidTimestamp(host_time));
+    }
+    let now = Instant::now();
+    app.world_mut()
+        .get_resource_or_init::<HostClock>()
+        .record(host_time, now);
+    Ok(())
+}
+
+/// Turns touch latency measurement on or off; see [`InputLatency`]
+pub fn set_latency_measurement(app: &mut App, enabled: bool) {
+    if enabled {
+        app.init_resource::<InputLatency>();
+    } else {
+        app.world_mut().remove_resource::<InputLatency>();
+    }
+}
+
+/// Reports when the touch queued last was captured, in seconds on the host clock passed
+/// to [`sync_clock`]
+pub fn input_timestamp(app: &mut App, host_time: f64) -> Result<(), FfiViolation> {
+    if !host_time.is_finite() {
+        return reject(FfiViolation::InvalidTimestamp(host_time));
+    }
+    input_latency::touch_captured(app.world_mut(), host_time);
+    Ok(())
+}
+
+/// Reports that the oldest frame not yet reported was presented at `host_time`, in
+/// seconds on the host clock passed to [`sync_clock`]
+pub fn frame_presented(app: &mut App, host_time: f64) -> Result<(), FfiViolation> {
+    if !host_time.is_finite() {
+        return reject(FfiViolation::InvalidTimestamp(host_time));
+    }
+    input_latency::frame_presented(app.world_mut(), host_time);
+    Ok(())
+}
+
+/// Returns false if this host frame should be skipped rather than updating the app
+///
+/// Used by [`update`] to lower the frame rate in picture-in-picture.
+pub fn frame_due(app: &mut App) -> bool {
+    lifecycle::frame_due(app.world_mut())
+}
+
+/// Finishes the app's plugins, which the first update after creation otherwise does
+///
+/// Waits for plugins still preparing ([`InitProgress::Building`]) and does nothing once
+/// they are finished.
+pub fn finish_plugins(app: &mut App) {
+    while app.plugins_state() == PluginsState::Adding {
+        tick_global_task_pools_on_main_thread();
+    }
+    if app.plugins_state() == PluginsState::Ready {
+        startup::finish_started(app.world());
+        app.finish();
+    }
+    if app.plugins_state() == PluginsState::Finished {
+        app.cleanup();
+        startup::finish_ended(app.world());
+    }
+}
+
+/// How far the app's plugins have initialized, as an [`InitProgress`] value
+pub fn init_progress(app: &mut App) -> u8 {
+    InitProgress::of(app) as u8
+}
+
+/// Runs one host frame
+///
+/// Skips frames dropped by picture-in-picture pacing and every frame of an app that is
+/// [frozen](crate::RecoveryAction::FreezeFrame), which only presents its frozen frame
+/// again after surface changes, then updates the app and feeds the frame time to
+/// [`DynamicResolution`]. Errors and panics of the update are recorded for [`recover`].
+/// Every call is a [heartbeat](crate::Heartbeat). Returns true if the app was updated.
+pub fn update(app: &mut App) -> bool {
+    heartbeat::beat(app);
+    if !frame_due(app) {
+        return false;
+    }
+    if recovery::is_frozen(app) {
+        frozen_frame::present_frozen(app);
+        return false;
+    }
+    startup::build_deferred_plugin(app);
+    let started = Instant::now();
+    if !recovery::update_guarded(app) {
+        return false;
+    }
+    let frame_time = started.elapsed();
+    startup::record_frame(app.world(), started);
+    replay::record_frame(app.world_mut());
+    input_latency::frame_submitted(app.world_mut());
+    record_frame_time(app.world_mut(), frame_time);
+    telemetry::record_frame(app.world_mut(), frame_time);
+    frame_info::record_frame(app.world_mut(), frame_time);
+    true
+}
+
+/// Applies the app's [`RecoveryPolicy`](crate::RecoveryPolicy) to the errors of the last
+/// updates
+///
+/// Returns 0 while the app keeps running or is frozen, or the code `bevy_embedded_update`
+/// returns once the host should destroy the app. See [`recovery`](crate::recovery).
+pub fn recover(app: &mut App) -> u8 {
+    recovery::recover(app)
+}
+
+/// The error that froze the app, or `None` while it is running
+pub fn frozen_error(app: &App) -> Option<String> {
+    app.world().get_resource::<crate::Recovery>()?.frozen()
+}
+
+/// Sets the RGBA8 sRGB image a frozen app presents when it can't render its world, or
+/// clears it with `None`
+///
+/// See [`frozen_frame`](crate::frozen_frame).
+pub fn set_fallback_frame(
+    app: &mut App,
+    pixels: Option<&[u8]>,
+    width: u32,
+    height: u32,
+) -> Result<(), FfiViolation> {
+    let fallback = match pixels {
+        Some(pixels) => {
+            let needed = (width as usize)
+                .checked_mul(height as usize)
+                .and_then(|count| count.checked_mul(4));
+            if width == 0 || height == 0 || needed != Some(pixels.len()) {
+                return reject(FfiViolation::InvalidFallbackFrame {
+                    width,
+                    height,
+                    len: pixels.len(),
+                });
+            }
+            Some(FallbackFrame {
+                width,
+                height,
+                pixels: pixels.into(),
+            })
+        }
+        None => None,
+    };
+    if let Some(recovery) = app.world().get_resource::<crate::Recovery>() {
+        recovery.set_fallback(fallback);
+    }
+    Ok(())
+}
+
+/// Scheduling signals after an update; `updated` is what [`update`] returned
+///
+/// See [`FrameInfo`].
+pub fn frame_info(app: &App, updated: bool) -> FrameInfo {
+    let world = app.world();
+    let stats = world.get_resource::<FrameStats>();
+    let pending_messages = world
+        .get_resource::<HostChannel>()
+        .map_or(0, |channel| channel.endpoint().pending());
+    let unchanged = world
+        .get_resource::<FrameDamage>()
+        .is_some_and(FrameDamage::is_unchanged);
+    FrameInfo {
+        frame: stats.map_or(0, |stats| stats.frame),
+        cpu_ms: stats.map_or(0.0, |stats| stats.cpu_time.as_secs_f64() * 1000.0),
+        gpu_ms: gpu_frame_time(app).map_or(-1.0, |time| time.as_secs_f64() * 1000.0),
+        pending_messages: pending_messages as u32,
+        needs_redraw: (updated && !unchanged) as u8,
+        idle: (unchanged && pending_messages == 0) as u8,
+    }
+}
+
+/// Writes [`frame_info`] to `out`, unless it is null
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes of a [`FrameInfo`].
+pub unsafe fn write_frame_info(app: &App, out: *mut FrameInfo, updated: bool) {
+    if let Some(out) = unsafe { out.as_mut() } {
+        *out = frame_info(app, updated);
+    }
+}
+
+/// Zeroes `out`, unless it is null, for updates that return without a frame
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes of a [`FrameInfo`].
+pub unsafe fn clear_frame_info(out: *mut FrameInfo) {
+    if let Some(out) = unsafe { out.as_mut() } {
+        *out = FrameInfo::default();
+    }
+}
+
+/// Length of the encoded log recorded so far, or 0 if the app isn't recording
+pub fn recording_len(app: &App) -> usize {
+    replay::recorded(app).map_or(0, |log| log.encode().len())
+}
+
+/// Stops recording and copies the encoded log into a host buffer
+///
+/// Returns 0 if the app wasn't recording. If the log doesn't fit, recording continues so
+/// the host can retry with a buffer of [`recording_len`] bytes.
+///
+/// # Safety
+///
+/// `buffer` must be null or valid for writes of `buffer_len` bytes.
+pub unsafe fn stop_recording(
+    app: &mut App,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> Result<usize, FfiViolation> {
+    let Some(log) = replay::recorded(app) else {
+        return Ok(0);
+    };
+    let written = unsafe { copy_to_buffer(&log.encode(), buffer, buffer_len) }?;
+    replay::stop_recording(app);
+    Ok(written)
+}
+
+/// Replays an encoded [`ReplayLog`](crate::ReplayLog) into the app, running one update
+/// per recorded frame
+///
+/// Returns the number of frames run. See [`replay`].
+pub fn replay(app: &mut App, log: &[u8]) -> Result<usize, FfiViolation> {
+    let Some(log) = replay::ReplayLog::decode(log) else {
+        return reject(FfiViolation::InvalidReplayLog);
+    };
+    Ok(replay::play(app, &log))
+}
+
+/// Runs the app's save systems and encodes its [`HibernatedState`](crate::HibernatedState)
+///
+/// The host destroys the app afterwards. See [`hibernation`](crate::hibernation).
+pub fn hibernate(app: &mut App) -> Vec<u8> {
+    hibernation::save(app.world_mut()).encode()
+}
+
+/// Restores encoded [`HibernatedState`](crate::HibernatedState) in the app's next update
+pub fn rehydrate(app: &mut App, state: &[u8]) -> Result<(), FfiViolation> {
+    let Some(state) = HibernatedState::decode(state) else {
+        return reject(FfiViolation::InvalidHibernatedState);
+    };
+    hibernation::rehydrate(app, state);
+    Ok(())
+}
+
+/// Runs one part of a frame instead of a whole update
+///
+/// Returns false if the app doesn't have the schedule, e.g. `Render` in an app without a
+/// render world. See [`HostSchedule`].
+pub fn run_schedule(app: &mut App, schedule: u8) -> Result<bool, FfiViolation> {
+    let Some(schedule) = HostSchedule::from_u8(schedule) else {
+        return reject(FfiViolation::InvalidSchedule(schedule));
+    };
+    heartbeat::beat(app);
+    Ok(schedule.run(app))
+}
+
+/// The regions of the primary window that changed in the last update, in physical
+/// pixels, or `None` if the whole frame changed
+///
+/// See [`FrameDamage`].
+pub fn frame_damage(app: &mut App) -> Option<Vec<URect>> {
+    let world = app.world_mut();
+    let mut windows = world.query_filtered::<&Window, With<PrimaryWindow>>();
+    let window = windows.single(world).ok()?;
+    let scale_factor = window.resolution.scale_factor();
+    let size = window.resolution.physical_size().as_vec2();
+    world
+        .get_resource::<FrameDamage>()?
+        .physical_rects(scale_factor, size)
+}
+
+/// Writes the last update's damage rects into `out` as `x, y, width, height` quads
+///
+/// Returns the number of rects, or -1 if the whole frame must be recomposited, which
+/// includes damage with more rects than fit into `out`.
+pub fn write_frame_damage(app: &mut App, out: &mut [u32]) -> i32 {
+    let Some(rects) = frame_damage(app) else {
+        return -1;
+    };
+    if rects.len() * 4 > out.len() {
+        return -1;
+    }
+    for (rect, quad) in rects.iter().zip(out.chunks_exact_mut(4)) {
+        quad.copy_from_slice(&[rect.min.x, rect.min.y, rect.width(), rect.height()]);
+    }
+    rects.len() as i32
+}
+
+/// GPU time of the most recently measured frame
+///
+/// `None` unless the app added [`GpuTimingPlugin`](crate::GpuTimingPlugin) and the
+/// device supports timestamp queries. See [`GpuTiming`].
+pub fn gpu_frame_time(app: &App) -> Option<Duration> {
+    app.world().get_resource::<GpuTiming>()?.frame_time()
+}
+
+/// The adapter and limits the app renders with, as JSON
+///
+/// `None` if the app does not render. See [`GpuReport`](crate::GpuReport).
+pub fn gpu_report(app: &App) -> Option<String> {
+    Some(app.world().get_resource::<crate::GpuReport>()?.to_json())
+}
+
+/// When a startup phase started and how long it took, or `None` until it has ended
+///
+/// See [`StartupTimings`](crate::StartupTimings).
+pub fn startup_phase(app: &App, phase: u8) -> Result<Option<PhaseTiming>, FfiViolation> {
+    let Some(phase) = StartupPhase::from_u8(phase) else {
+        return reject(FfiViolation::InvalidStartupPhase(phase));
+    };
+    Ok(app
+        .world()
+        .get_resource::<crate::StartupTimings>()
+        .and_then(|timings| timings.phase(phase)))
+}
+
+/// Releases the GPU memory of dropped render resources, waiting for the GPU
+///
+/// Returns false if the app has no render device. See [`RenderGcPlugin`](crate::RenderGcPlugin).
+pub fn flush_render_resources(app: &mut App) -> bool {
+    render_gc::flush_render_resources(app)
+}
+
+/// Captures the next frame the app renders in the attached graphics debugger; see
+/// [`GpuCapture`](crate::GpuCapture)
+///
+/// Returns false if the app doesn't render.
+#[cfg(feature = "gpu_capture")]
+pub fn capture_gpu_frame(app: &App) -> bool {
+    let Some(capture) = app.world().get_resource::<crate::GpuCapture>() else {
+        return false;
+    };
+    capture.capture_next_frame();
+    true
+}
+
+/// Starts recording a Chrome trace of every app's schedules and systems into a new file
+/// at `path`; see [`chrome_trace`](crate::chrome_trace)
+///
+/// Returns false if a trace is already recording or the file can't be created.
+#[cfg(feature = "chrome_trace")]
+pub fn start_chrome_trace(path: &str) -> bool {
+    match crate::chrome_trace::start_chrome_trace(std::path::Path::new(path)) {
+        Ok(()) => true,
+        Err(error) => {
+            log::warn!("Can't start Chrome trace at {path}: {error}");
+            false
+        }
+    }
+}
+
+/// Stops recording the Chrome trace and completes its file
+///
+/// Returns false if no trace was recording or the file couldn't be written.
+#[cfg(feature = "chrome_trace")]
+pub fn stop_chrome_trace() -> bool {
+    match crate::chrome_trace::stop_chrome_trace() {
+        Ok(written) => written.is_some(),
+        Err(error) => {
+            log::warn!("Can't complete Chrome trace: {error}");
+            false
+        }
+    }
+}
+
+/// Switches a debug renderer on or off; see [`DebugToggle`](crate::DebugToggle)
+///
+/// Returns false if the app doesn't have the toggle's renderer.
+#[cfg(feature = "debug_toggles")]
+pub fn set_debug_toggle(app: &mut App, toggle: u8, enabled: bool) -> Result<bool, FfiViolation> {
+    let Some(toggle) = crate::DebugToggle::from_u8(toggle) else {
+        return reject(FfiViolation::InvalidDebugToggle(toggle));
+    };
+    Ok(toggle.set(app.world_mut(), enabled))
+}
+
+/// Whether a debug renderer is on, or `None` if the app doesn't have it
+#[cfg(feature = "debug_toggles")]
+pub fn debug_toggle(app: &App, toggle: u8) -> Result<Option<bool>, FfiViolation> {
+    let Some(toggle) = crate::DebugToggle::from_u8(toggle) else {
+        return reject(FfiViolation::InvalidDebugToggle(toggle));
+    };
+    Ok(toggle.get(app.world()))
+}
+
+/// State of the protocol handshake with the host; see [`Handshake`]
+pub fn handshake_status(app: &App) -> HandshakeStatus {
+    app.world()
+        .get_resource::<Handshake>()
+        .map_or(HandshakeStatus::Pending, Handshake::status)
+}
+
+/// Why the protocol handshake failed, or `None` if it hasn't
+pub fn handshake_error(app: &App) -> Option<String> {
+    let error = app.world().get_resource::<Handshake>()?.error()?;
+    Some(error.to_string())
+}
+
+/// Changes the rotation and mirroring between the content and the host surface
+///
+/// If the change swaps the content's axes, the primary window is resized to match.
+pub fn set_surface_transform(
+    app: &mut App,
+    rotation: u8,
+    mirror: bool,
+) -> Result<(), FfiViolation> {
+    let Some(rotation) = SurfaceRotation::from_u8(rotation) else {
+        return reject(FfiViolation::InvalidRotation(rotation));
+    };
+    app.insert_resource(SurfaceTransform { rotation, mirror });
+    apply_window_resolution(app.world_mut());
+    Ok(())
+}
+
+/// Configures dynamic resolution scaling of the primary window
+///
+/// `min_scale` and `max_scale` bound the render scale (`0 < min <= max <= 1`) and
+/// `target_fps` is the frame rate the controller aims for. Disabling restores full
+/// resolution. Fails unless the app added
+/// [`DynamicResolutionPlugin`](crate::DynamicResolutionPlugin).
+pub fn set_dynamic_resolution(
+    app: &mut App,
+    enabled: bool,
+    min_scale: f32,
+    max_scale: f32,
+    target_fps: f32,
+) -> Result<(), FfiViolation> {
+    if !(min_scale > 0.0 && min_scale <= max_scale && max_scale <= 1.0) {
+        return reject(FfiViolation::InvalidScaleRange {
+            min: min_scale,
+            max: max_scale,
+        });
+    }
+    if !(target_fps.is_finite() && target_fps > 0.0) {
+        return reject(FfiViolation::InvalidFrameRate(target_fps));
+    }
+
+    let settings = DynamicResolutionSettings {
+        enabled,
+        target_fps,
+        min_scale,
+        max_scale,
+    };
+    let Some(mut dynamic) = app.world_mut().get_resource_mut::<DynamicResolution>() else {
+        return reject(FfiViolation::MissingPlugin("DynamicResolutionPlugin"));
+    };
+    dynamic.set_settings(settings);
+    apply_window_resolution(app.world_mut());
+    Ok(())
+}
+
+/// Places the camera with a world-to-view matrix from the host
+///
+/// `view` is column-major, as used by Metal, SceneKit and Android's `Matrix`. It is
+/// applied to a camera with an [`OrbitCamera`], whose rig is disabled so the view
+/// sticks until the host re-enables it. Without a rig any one camera is moved, so
+/// scenes with several cameras should give the host-driven one an [`OrbitCamera`].
+pub fn set_camera_view(app: &mut App, view: [f32; 16]) -> Result<(), FfiViolation> {
+    let view = Mat4::from_cols_array(&view);
+    if !view.is_finite() || view.determinant().abs() <= f32::EPSILON {
+        return reject(FfiViolation::InvalidMatrix);
+    }
+
+    let mut query = app
+        .world_mut()
+        .query_filtered::<(Entity, Has<OrbitCamera>), With<Camera>>();
+    let Some((camera, _)) = query.iter(app.world()).max_by_key(|(_, rig)| *rig) else {
+        return reject(FfiViolation::NoCamera);
+    };
+
+    let mut entity = app.world_mut().entity_mut(camera);
+    if let Some(mut rig) = entity.get_mut::<OrbitCamera>() {
+        rig.enabled = false;
+        rig.stop();
+    }
+    entity.insert(Transform::from_matrix(view.inverse()));
+    Ok(())
+}
+
+/// Attaches a surface on an external display as an additional window
+///
+/// Returns the display id the host passes to [`resize_external_display`] and
+/// [`detach_external_display`]. Ids start at 1.
+pub fn attach_external_display(
+    app: &mut App,
+    handle: RawHandleWrapper,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> Result<u32, FfiViolation> {
+    check_surface_size(width, height, scale_factor)?;
+
+    let resolution = WindowResolution::new(width, height).with_scale_factor_override(scale_factor);
+    let (id, _) = spawn_external_display(app.world_mut(), handle, resolution);
+    Ok(id)
+}
+
+/// Applies a resize from the host to an external display window
+pub fn resize_external_display(
+    app: &mut App,
+    id: u32,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> Result<(), FfiViolation> {
+    check_surface_size(width, height, scale_factor)?;
+
+    let Some(entity) = find_external_display(app.world_mut(), id) else {
+        return reject(FfiViolation::UnknownDisplay(id));
+    };
+    retire_surface_frames(app);
+    if let Some(mut window) = app.world_mut().get_mut::<Window>(entity) {
+        window.resolution.set_physical_resolution(width, height);
+        window
+            .resolution
+            .set_scale_factor_override(Some(scale_factor));
+    }
+    Ok(())
+}
+
+/// Removes an external display window; its surface is released on the next frame
+pub fn detach_external_display(app: &mut App, id: u32) -> Result<(), FfiViolation> {
+    let Some(entity) = find_external_display(app.world_mut(), id) else {
+        return reject(FfiViolation::UnknownDisplay(id));
+    };
+    retire_surface_frames(app);
+    app.world_mut().despawn(entity);
+    log::info!("Detached external display {id}");
+    Ok(())
+}
+
+/// Delivers a message from the host to Bevy
+///
+/// The instance is looked up in the [`registry`](crate::registry) rather than through
+/// the app pointer, so this is safe to call concurrently with destroy.
+pub fn send_message(app: *const c_void, message: Vec<u8>) -> Result<(), FfiViolation> {
+    if cfg!(feature = "ffi_validation") && message.len() > MAX_MESSAGE_LEN {
+        return reject(FfiViolation::MessageTooLarge(message.len()));
+    }
+    let Some(instance) = registry::get(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    instance.endpoint().send(message);
+    Ok(())
+}
+
+/// Sends the host's side of the protocol [handshake](crate::Handshake)
+///
+/// `codecs` lists the host's encodings separated by commas, most preferred first. The
+/// result is available after the next update. Like [`send_message`], this never
+/// dereferences the app pointer.
+pub fn send_handshake(
+    app: *const c_void,
+    version: u16,
+    schema_hash: u64,
+    codecs: &str,
+) -> Result<(), FfiViolation> {
+    let schema = ProtocolSchema::new(schema_hash).with_codecs(
+        codecs
+            .split(',')
+            .map(str::trim)
+            .filter(|codec| !codec.is_empty()),
+    );
+    send_message(
+        app,
+        encode_reserved(ReservedTopic::HostHandshake, &schema.encode(version)),
+    )
+}
+
+/// Submits a [job](crate::HostJobApp) of `kind` to the app
+///
+/// Returns the token its [`ReservedTopic::JobResult`] will carry. Tokens are unique
+/// within the process. Like [`send_message`], this never dereferences the app pointer.
+pub fn submit_job(app: *const c_void, kind: &str, input: &[u8]) -> Result<u64, FfiViolation> {
+    static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+    let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+    send_message(
+        app,
+        encode_reserved(
+            ReservedTopic::Job,
+            &host_jobs::encode_submit(token, kind, input),
+        ),
+    )?;
+    Ok(token)
+}
+
+/// Cancels the job with `token`; the host receives a `Canceled` result unless it
+/// already finished
+pub fn cancel_job(app: *const c_void, token: u64) -> Result<(), FfiViolation> {
+    send_message(
+        app,
+        encode_reserved(ReservedTopic::Job, &host_jobs::encode_cancel(token)),
+    )
+}
+
+/// Address of the app's shared state block, for the host to write scalar state into
+///
+/// The block holds [`SHARED_STATE_LEN`](crate::SHARED_STATE_LEN) `f32`s and stays valid
+/// until the app is destroyed. Like [`send_message`], this never dereferences the app
+/// pointer.
+pub fn shared_state(app: *const c_void) -> Result<*mut f32, FfiViolation> {
+    let Some(instance) = registry::get(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    match instance.shared_state() {
+        Some(block) => Ok(block.as_ptr()),
+        None => reject(FfiViolation::MissingPlugin("SharedStatePlugin")),
+    }
+}
+
+/// Looks up the host side of a ring channel without dereferencing the app pointer
+fn ring_endpoint(app: *const c_void, topic: u16) -> Result<RingEndpoint, FfiViolation> {
+    let Some(instance) = registry::get(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    match instance.ring_channels().endpoint(topic) {
+        Some(endpoint) => Ok(endpoint),
+        None => reject(FfiViolation::UnknownTopic(topic)),
+    }
+}
+
+/// Copies a message from the host into a topic's ring channel
+///
+/// Returns false if the ring is full. Like [`send_message`], this never dereferences the
+/// app pointer.
+pub fn ring_send(app: *const c_void, topic: u16, message: &[u8]) -> Result<bool, FfiViolation> {
+    Ok(ring_endpoint(app, topic)?.send(message))
+}
+
+/// Copies the next message Bevy sent on a topic's ring channel into a host buffer
+///
+/// Returns the message length, or 0 if no message is waiting. If the message is longer
+/// than `buffer_len` nothing is copied and it stays in the ring, so the host can retry
+/// with a buffer of the returned length.
+///
+/// # Safety
+///
+/// `buffer` must be null or valid for writes of `buffer_len` bytes.
+pub unsafe fn ring_receive_into(
+    app: *const c_void,
+    topic: u16,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> Result<usize, FfiViolation> {
+    if buffer.is_null() {
+        return reject(FfiViolation::NullPointer("buffer"));
+    }
+    let endpoint = ring_endpoint(app, topic)?;
+    let ring = endpoint.incoming();
+    let Some(len) = ring.next_len() else {
+        return Ok(0);
+    };
+    if len > buffer_len {
+        return Ok(len);
+    }
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer, buffer_len) };
+    Ok(ring.pop_into(buffer).unwrap_or(0))
+}
+
+/// Queues interleaved PCM samples captured by the host for the next frame
+///
+/// Returns false if Bevy has fallen behind and the buffer was dropped. Never allocates
+/// and never dereferences the app pointer, so it can be called from the host's audio
+/// thread.
+pub fn push_audio_input(
+    app: *const c_void,
+    timestamp: f64,
+    sample_rate: u32,
+    channels: u16,
+    samples: &[f32],
+) -> Result<bool, FfiViolation> {
+    if sample_rate == 0 || channels == 0 {
+        return reject(FfiViolation::InvalidAudioFormat {
+            sample_rate,
+            channels,
+        });
+    }
+    let Some(instance) = registry::get(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    match instance.audio_input() {
+        Some(ring) => Ok(audio_input::push_audio_input(
+            ring,
+            timestamp,
+            sample_rate,
+            channels,
+            samples,
+        )),
+        None => reject(FfiViolation::MissingPlugin("AudioInputPlugin")),
+    }
+}
+
+/// Fills `out` with the next interleaved samples of Bevy's mixed audio output
+///
+/// The host must render at the sample rate and channel count `HostAudioPlugin` was set
+/// up with. Never dereferences the app pointer, so it can be called from the host's
+/// audio render thread.
+#[cfg(feature = "host_audio")]
+pub fn render_audio(
+    app: *const c_void,
+    sample_rate: u32,
+    channels: u16,
+    out: &mut [f32],
+) -> Result<(), FfiViolation> {
+    let Some(instance) = registry::get(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    let Some(renderer) = instance.audio_output() else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    if renderer.sample_rate() != sample_rate || renderer.channels() != channels {
+        return reject(FfiViolation::InvalidAudioFormat {
+            sample_rate,
+            channels,
+        });
+    }
+    // A stalled app's sounds wait for its updates to resume
+    if instance
+        .heartbeat()
+        .is_some_and(crate::Heartbeat::is_stalled)
+    {
+        out.fill(0.0);
+        return Ok(());
+    }
+    renderer.render(out);
+    Ok(())
+}
+
+/// Takes the next message Bevy sent to the host, if any
+///
+/// Like [`send_message`], this never dereferences the app pointer.
+pub fn receive_message(app: *const c_void) -> Result<Option<Vec<u8>>, FfiViolation> {
+    let Some(instance) = registry::get(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    Ok(instance.endpoint().receive())
+}
+
+/// Copies the next message from Bevy into a host buffer
+///
+/// Returns the message length, or 0 if no message is available. Unlike
+/// [`receive_message_into`], a message longer than `buffer_len` is not truncated: nothing
+/// is copied and it stays queued, so the host can retry with a buffer of the returned
+/// length. Never dereferences the app pointer.
+///
+/// # Safety
+///
+/// `buffer` must be null or valid for writes of `buffer_len` bytes.
+pub unsafe fn receive_whole_message_into(
+    app: *const c_void,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> Result<usize, FfiViolation> {
+    if buffer.is_null() {
+        return reject(FfiViolation::NullPointer("buffer"));
+    }
+    let Some(instance) = registry::get(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer, buffer_len) };
+    Ok(instance.endpoint().receive_into(buffer))
+}
+
+/// Copies up to `max_messages` messages from Bevy into a host buffer in one call
+///
+/// Each message is framed as `len: u32 | len × u8` in little-endian. Returns the number
+/// of bytes written, or 0 if no message is available. See
+/// [`HostEndpoint::receive_batch_into`](crate::HostEndpoint::receive_batch_into) for
+/// messages that don't fit. Never dereferences the app pointer.
+///
+/// # Safety
+///
+/// `buffer` must be null or valid for writes of `buffer_len` bytes.
+pub unsafe fn receive_messages_into(
+    app: *const c_void,
+    buffer: *mut u8,
+    buffer_len: usize,
+    max_messages: usize,
+) -> Result<usize, FfiViolation> {
+    if buffer.is_null() {
+        return reject(FfiViolation::NullPointer("buffer"));
+    }
+    let Some(instance) = registry::get(app) else {
+        return reject(FfiViolation::UnknownInstance);
+    };
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer, buffer_len) };
+    Ok(instance.endpoint().receive_batch_into(buffer, max_messages))
+}
+
+/// Copies the oldest queued telemetry event into a host buffer, truncating if needed
+///
+/// Returns the number of bytes written, or 0 if no event is queued. See
+/// [`telemetry`](crate::telemetry).
+///
+/// # Safety
+///
+/// `buffer` must be null or valid for writes of `buffer_len` bytes.
+pub unsafe fn receive_telemetry_into(
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> Result<usize, FfiViolation> {
+    if buffer.is_null() {
+        return reject(FfiViolation::NullPointer("buffer"));
+    }
+    if buffer_len == 0 {
+        return Ok(0);
+    }
+
+    let Some(event) = telemetry::receive_telemetry() else {
+        return Ok(0);
+    };
+    let copy_len = event.len().min(buffer_len);
+    unsafe { std::ptr::copy_nonoverlapping(event.as_ptr(), buffer, copy_len) };
+    Ok(copy_len)
+}
+
+/// Seals a message for the app with its channel key, as the host sends it
+///
+/// See [`encryption`](crate::encryption).
+#[cfg(feature = "channel_encryption")]
+pub fn seal_message(key: &[u8], message: &[u8]) -> Result<Vec<u8>, FfiViolation> {
+    let Some(key) = ChannelKey::from_slice(key) else {
+        return reject(FfiViolation::InvalidChannelKey(key.len()));
+    };
+    match ChannelCipher::new(&key).seal(ChannelDirection::ToBevy, message) {
+        Some(sealed) => Ok(sealed),
+        None => reject(FfiViolation::SealFailed),
+    }
+}
+
+/// Opens a message the app sealed with its channel key
+#[cfg(feature = "channel_encryption")]
+pub fn open_message(key: &[u8], sealed: &[u8]) -> Result<Vec<u8>, FfiViolation> {
+    let Some(key) = ChannelKey::from_slice(key) else {
+        return reject(FfiViolation::InvalidChannelKey(key.len()));
+    };
+    match ChannelCipher::new(&key).open(ChannelDirection::ToHost, sealed) {
+        Some(message) => Ok(message),
+        None => reject(FfiViolation::MessageNotAuthentic),
+    }
+}
+
+/// Copies `bytes` into a host buffer that must hold all of them
+///
+/// # Safety
+///
+/// `buffer` must be null or valid for writes of `buffer_len` bytes.
+pub unsafe fn copy_to_buffer(
+    bytes: &[u8],
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> Result<usize, FfiViolation> {
+    if buffer.is_null() {
+        return reject(FfiViolation::NullPointer("buffer"));
+    }
+    if bytes.len() > buffer_len {
+        return reject(FfiViolation::BufferTooSmall {
+            needed: bytes.len(),
+            len: buffer_len,
+        });
+    }
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len()) };
+    Ok(bytes.len())
+}
+
+/// Copies the next message from Bevy into a host buffer, truncating if needed
+///
+/// Returns the number of bytes written, or 0 if no message is available.
+///
+/// # Safety
+///
+/// `buffer` must be null or valid for writes of `buffer_len` bytes.
+pub unsafe fn receive_message_into(
+    app: *const c_void,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> Result<usize, FfiViolation> {
+    if buffer.is_null() {
+        return reject(FfiViolation::NullPointer("buffer"));
+    }
+    if buffer_len == 0 {
+        return Ok(0);
+    }
+
+    let Some(message) = receive_message(app)? else {
+        return Ok(0);
+    };
+    let copy_len = message.len().min(buffer_len);
+    unsafe { std::ptr::copy_nonoverlapping(message.as_ptr(), buffer, copy_len) };
+    Ok(copy_len)
+}
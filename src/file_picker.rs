@@ -0,0 +1,176 @@
+//! Asking the host to pick a file
+//!
+//! "Import your own model/texture" features need the platform's document and photo
+//! pickers, which only the host can present. With [`FilePickerPlugin`] added,
+//! [`FilePicker::pick`] sends a request on [`ReservedTopic::PickFile`]:
+//!
+//! ```text
+//! request: u32 | kind: u8 (FilePickerKind) | count: u32 | types: count × (u32 len + UTF-8)
+//! ```
+//!
+//! `types` narrows the choice further with file extensions, MIME types or platform type
+//! identifiers, as the host understands them; it may be empty. The host answers on
+//! [`ReservedTopic::FilePicked`]:
+//!
+//! ```text
+//! request: u32 | status: u8 (0 picked, 1 cancelled) | name: u32 len + UTF-8 | bytes ...
+//! ```
+//!
+//! and the result is delivered as a [`FilePicked`] message. To load the file through the
+//! `AssetServer`, store its bytes in [`HostAssets`](crate::HostAssets) and load the
+//! returned path:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{FilePicked, HostAssets};
+//!
+//! fn import_picked(
+//!     mut picked: MessageReader<FilePicked>,
+//!     host_assets: Res<HostAssets>,
+//!     asset_server: Res<AssetServer>,
+//! ) {
+//!     for file in picked.read().filter_map(|picked| picked.file.as_ref()) {
+//!         let path = host_assets.insert(format!("picked/{}", file.name), file.bytes.clone());
+//!         let image: Handle<Image> = asset_server.load(path);
+//!     }
+//! }
+//! ```
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    ecs::{
+        message::{Message, MessageReader, MessageWriter},
+        resource::Resource,
+        system::{Res, ResMut},
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Sends [`FilePicker`] requests and delivers [`FilePicked`]; see the [module docs](self)
+#[derive(Default)]
+pub struct FilePickerPlugin;
+
+impl Plugin for FilePickerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FilePicker>()
+            .add_message::<FilePicked>()
+            .add_systems(PreUpdate, receive_picked_files)
+            .add_systems(Last, send_file_picker_requests);
+    }
+}
+
+/// Kind of picker the host should present
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum FilePickerKind {
+    /// Any document
+    #[default]
+    Any = 0,
+    /// Photos and images
+    Image = 1,
+    /// 3D models
+    Model = 2,
+}
+
+impl FilePickerKind {
+    /// Create a FilePickerKind from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(FilePickerKind::Any),
+            1 => Some(FilePickerKind::Image),
+            2 => Some(FilePickerKind::Model),
+            _ => None,
+        }
+    }
+}
+
+/// A file the user picked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickedFile {
+    /// File name, including its extension
+    pub name: String,
+    /// File contents
+    pub bytes: Vec<u8>,
+}
+
+/// Sent when the host answers a [`FilePicker::pick`] request
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct FilePicked {
+    /// Request id returned by `pick`
+    pub request_id: u32,
+    /// The picked file, or `None` if the user cancelled
+    pub file: Option<PickedFile>,
+}
+
+/// Requests file pickers from the host
+#[derive(Resource, Debug, Default)]
+pub struct FilePicker {
+    next_request: u32,
+    outgoing: Vec<Vec<u8>>,
+}
+
+impl FilePicker {
+    /// Asks the host to present a picker, returning the id of its [`FilePicked`] answer
+    pub fn pick(&mut self, kind: FilePickerKind, types: &[&str]) -> u32 {
+        let request_id = self.next_request;
+        self.next_request = self.next_request.wrapping_add(1);
+
+        let mut payload = ByteWriter::new();
+        payload
+            .u32(request_id)
+            .u8(kind as u8)
+            .u32(types.len() as u32);
+        for file_type in types {
+            payload.string(file_type);
+        }
+        self.outgoing.push(payload.finish());
+        request_id
+    }
+}
+
+/// Reads the host's answer, returning `None` if the payload is malformed
+fn read_answer(payload: &[u8]) -> Option<FilePicked> {
+    let mut reader = ByteReader::new(payload);
+    let request_id = reader.u32()?;
+    let file = match reader.u8()? {
+        0 => Some(PickedFile {
+            name: reader.string()?.to_string(),
+            bytes: reader.remaining().to_vec(),
+        }),
+        _ => None,
+    };
+    Some(FilePicked { request_id, file })
+}
+
+/// Delivers picked files from the host
+fn receive_picked_files(
+    mut commands: MessageReader<HostCommand>,
+    mut picked: MessageWriter<FilePicked>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::FilePicked {
+            continue;
+        }
+
+        match read_answer(&command.payload) {
+            Some(answer) => {
+                picked.write(answer);
+            }
+            None => log::warn!("Ignoring malformed file picked command"),
+        }
+    }
+}
+
+/// Sends the frame's picker requests to the host
+fn send_file_picker_requests(channel: Res<HostChannel>, mut picker: ResMut<FilePicker>) {
+    if picker.outgoing.is_empty() {
+        return;
+    }
+    for payload in picker.outgoing.drain(..) {
+        channel.send_reserved(ReservedTopic::PickFile, &payload);
+    }
+}
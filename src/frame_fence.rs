@@ -0,0 +1,337 @@
+//! GPU fences the host waits on before reading Bevy's frames
+//!
+//! When Bevy renders into textures the host composites (host frames, `IOSurface` or
+//! `AHardwareBuffer` backed textures), Bevy's frame is still executing on the GPU when
+//! `bevy_embedded_update` returns. A [`FrameFence`] is a GPU timeline Bevy advances with
+//! every frame it submits: a `MTLSharedEvent` on Metal, a timeline `VkSemaphore` on
+//! Vulkan. After an update the host reads the value of the latest frame and waits for
+//! the fence to reach it, on the GPU with its own command buffer or queue submission, or
+//! on the CPU with the `wait_frame_fence` entry points.
+//!
+//! Each frame's value is reserved while the render world extracts it, which happens
+//! before `bevy_embedded_update` returns. With pipelined rendering the frame is only
+//! submitted later on the render thread, so the host may wait on a value that has not
+//! been signaled yet; both Metal events and timeline semaphores allow that.
+//!
+//! The fence is created on the first FFI call that asks for it, so apps that never share
+//! frames don't signal anything.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use bevy::{
+    app::SubApp,
+    ecs::{
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Commands, Res},
+    },
+    render::{
+        Extract, ExtractSchedule, Render, RenderSystems,
+        renderer::{RenderDevice, RenderQueue, render_system},
+    },
+};
+
+/// GPU timeline advanced once per frame Bevy submits
+#[derive(Resource, Clone)]
+pub struct FrameFence {
+    inner: Arc<FenceInner>,
+}
+
+struct FenceInner {
+    /// Value the latest extracted frame signals
+    value: AtomicU64,
+    timeline: Timeline,
+}
+
+impl FrameFence {
+    /// Creates a fence on Bevy's device, or `None` if its backend has no shareable fences
+    pub(crate) fn new(device: &RenderDevice) -> Option<Self> {
+        Some(Self {
+            inner: Arc::new(FenceInner {
+                value: AtomicU64::new(0),
+                timeline: Timeline::new(device)?,
+            }),
+        })
+    }
+
+    /// The value the fence reaches once the latest updated frame is done
+    pub fn value(&self) -> u64 {
+        self.inner.value.load(Ordering::Acquire)
+    }
+
+    /// The value the GPU has reached
+    pub fn completed(&self) -> u64 {
+        self.inner.timeline.completed()
+    }
+
+    /// Blocks until the fence reaches `value`, returning false on timeout
+    pub fn wait(&self, value: u64, timeout: Duration) -> bool {
+        self.inner.timeline.wait(value, timeout)
+    }
+
+    /// The `MTLSharedEvent` of the fence
+    #[cfg(target_os = "ios")]
+    pub fn shared_event(&self) -> &metal::SharedEventRef {
+        &self.inner.timeline.event
+    }
+
+    /// The timeline `VkSemaphore` of the fence, created on Bevy's `VkDevice`
+    #[cfg(not(target_vendor = "apple"))]
+    pub fn semaphore(&self) -> ash::vk::Semaphore {
+        self.inner.timeline.semaphore
+    }
+}
+
+/// Value the frame being rendered signals, reserved when it was extracted
+#[derive(Resource)]
+struct FrameFenceValue(u64);
+
+/// Hands the fence created in the main world to the render world, which may be on the
+/// render thread with pipelined rendering, and reserves the extracted frame's value
+fn extract_frame_fence(
+    mut commands: Commands,
+    fence: Extract<Option<Res<FrameFence>>>,
+    extracted: Option<Res<FrameFence>>,
+) {
+    let Some(fence) = fence.as_deref() else {
+        return;
+    };
+    if extracted.is_none() {
+        commands.insert_resource(fence.clone());
+    }
+    let value = fence.inner.value.fetch_add(1, Ordering::AcqRel) + 1;
+    commands.insert_resource(FrameFenceValue(value));
+}
+
+/// Advances the fence with the frame the render world submits
+///
+/// Vulkan signals the semaphore as part of Bevy's submission, so it is queued before
+/// rendering; Metal signals the event with a command buffer committed after Bevy's.
+fn signal_frame_fence(
+    fence: Option<Res<FrameFence>>,
+    value: Option<Res<FrameFenceValue>>,
+    queue: Res<RenderQueue>,
+) {
+    let (Some(fence), Some(value)) = (fence, value) else {
+        return;
+    };
+    if !fence.inner.timeline.signal(&queue, value.0) {
+        log::error!("Failed to signal frame fence value {}", value.0);
+    }
+}
+
+/// Schedules signaling of the frame fence in the render world
+pub(crate) fn add_render_systems(render_app: &mut SubApp) {
+    render_app.add_systems(ExtractSchedule, extract_frame_fence);
+    #[cfg(not(target_vendor = "apple"))]
+    render_app.add_systems(
+        Render,
+        signal_frame_fence
+            .in_set(RenderSystems::Render)
+            .before(render_system),
+    );
+    #[cfg(target_vendor = "apple")]
+    render_app.add_systems(
+        Render,
+        signal_frame_fence
+            .in_set(RenderSystems::Render)
+            .after(render_system),
+    );
+}
+
+/// `MTLSharedEvent` signaled after each frame
+#[cfg(target_os = "ios")]
+struct Timeline {
+    event: metal::SharedEvent,
+}
+
+#[cfg(target_os = "ios")]
+impl Timeline {
+    fn new(device: &RenderDevice) -> Option<Self> {
+        let hal_device = unsafe { device.wgpu_device().as_hal::<wgpu::hal::api::Metal>() }?;
+        let event = hal_device.raw_device().lock().new_shared_event();
+        Some(Self { event })
+    }
+
+    fn signal(&self, queue: &RenderQueue, value: u64) -> bool {
+        let Some(hal_queue) = (unsafe { queue.as_hal::<wgpu::hal::api::Metal>() }) else {
+            return false;
+        };
+        // Command buffers of a queue run in order, so this one completes after the
+        // frame Bevy just committed
+        let raw_queue = hal_queue.as_raw().lock();
+        let signal = raw_queue.new_command_buffer();
+        signal.set_label("frame_fence");
+        signal.encode_signal_event(&self.event, value);
+        signal.commit();
+        true
+    }
+
+    fn completed(&self) -> u64 {
+        self.event.signaled_value()
+    }
+
+    fn wait(&self, value: u64, timeout: Duration) -> bool {
+        // Shared events only have a blocking wait on recent OS versions, so poll
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            if self.completed() >= value {
+                return true;
+            }
+            if std::time::Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_micros(100));
+        }
+    }
+}
+
+/// Timeline `VkSemaphore` signaled by each frame's submission
+#[cfg(not(target_vendor = "apple"))]
+struct Timeline {
+    /// Keeps the device alive until the semaphore is destroyed
+    device: RenderDevice,
+    semaphore: ash::vk::Semaphore,
+    functions: TimelineFunctions,
+}
+
+/// Timeline semaphore entry points, from Vulkan 1.2 or `VK_KHR_timeline_semaphore`
+#[cfg(not(target_vendor = "apple"))]
+enum TimelineFunctions {
+    Core(Box<ash::Device>),
+    Khr(ash::khr::timeline_semaphore::Device),
+}
+
+#[cfg(not(target_vendor = "apple"))]
+impl Timeline {
+    fn new(device: &RenderDevice) -> Option<Self> {
+        use ash::vk;
+
+        let hal_device = unsafe { device.wgpu_device().as_hal::<wgpu::hal::api::Vulkan>() }?;
+        let raw_device = hal_device.raw_device();
+        let instance = hal_device.shared_instance();
+
+        // wgpu enables timeline semaphores whenever the device supports them
+        let functions = if hal_device
+            .enabled_device_extensions()
+            .contains(&ash::khr::timeline_semaphore::NAME)
+        {
+            TimelineFunctions::Khr(ash::khr::timeline_semaphore::Device::new(
+                instance.raw_instance(),
+                raw_device,
+            ))
+        } else {
+            let properties = unsafe {
+                instance
+                    .raw_instance()
+                    .get_physical_device_properties(hal_device.raw_physical_device())
+            };
+            if instance.instance_api_version() < vk::API_VERSION_1_2
+                || properties.api_version < vk::API_VERSION_1_2
+            {
+                return None;
+            }
+            let mut timeline = vk::PhysicalDeviceTimelineSemaphoreFeatures::default();
+            let mut features = vk::PhysicalDeviceFeatures2::default().push_next(&mut timeline);
+            unsafe {
+                instance
+                    .raw_instance()
+                    .get_physical_device_features2(hal_device.raw_physical_device(), &mut features);
+            }
+            if timeline.timeline_semaphore == vk::FALSE {
+                return None;
+            }
+            TimelineFunctions::Core(Box::new(raw_device.clone()))
+        };
+
+        let mut semaphore_type = vk::SemaphoreTypeCreateInfo::default()
+            .semaphore_type(vk::SemaphoreType::TIMELINE)
+            .initial_value(0);
+        let info = vk::SemaphoreCreateInfo::default().push_next(&mut semaphore_type);
+        let semaphore = unsafe { raw_device.create_semaphore(&info, None) }.ok()?;
+
+        Some(Self {
+            device: device.clone(),
+            semaphore,
+            functions,
+        })
+    }
+
+    fn signal(&self, queue: &RenderQueue, value: u64) -> bool {
+        let Some(hal_queue) = (unsafe { queue.as_hal::<wgpu::hal::api::Vulkan>() }) else {
+            return false;
+        };
+        hal_queue.add_signal_semaphore(self.semaphore, Some(value));
+        true
+    }
+
+    fn completed(&self) -> u64 {
+        let result = match &self.functions {
+            TimelineFunctions::Core(device) => unsafe {
+                device.get_semaphore_counter_value(self.semaphore)
+            },
+            TimelineFunctions::Khr(device) => unsafe {
+                device.get_semaphore_counter_value(self.semaphore)
+            },
+        };
+        result.unwrap_or(0)
+    }
+
+    fn wait(&self, value: u64, timeout: Duration) -> bool {
+        let semaphores = [self.semaphore];
+        let values = [value];
+        let info = ash::vk::SemaphoreWaitInfo::default()
+            .semaphores(&semaphores)
+            .values(&values);
+        let timeout = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+        let result = match &self.functions {
+            TimelineFunctions::Core(device) => unsafe { device.wait_semaphores(&info, timeout) },
+            TimelineFunctions::Khr(device) => unsafe { device.wait_semaphores(&info, timeout) },
+        };
+        result.is_ok()
+    }
+}
+
+#[cfg(not(target_vendor = "apple"))]
+impl Drop for Timeline {
+    fn drop(&mut self) {
+        if let Some(hal_device) =
+            unsafe { self.device.wgpu_device().as_hal::<wgpu::hal::api::Vulkan>() }
+        {
+            unsafe {
+                hal_device
+                    .raw_device()
+                    .destroy_semaphore(self.semaphore, None)
+            };
+        }
+    }
+}
+
+/// Platforms without shareable fences
+#[cfg(all(target_vendor = "apple", not(target_os = "ios")))]
+enum Timeline {}
+
+#[cfg(all(target_vendor = "apple", not(target_os = "ios")))]
+impl Timeline {
+    fn new(_device: &RenderDevice) -> Option<Self> {
+        None
+    }
+
+    fn signal(&self, _queue: &RenderQueue, _value: u64) -> bool {
+        match *self {}
+    }
+
+    fn completed(&self) -> u64 {
+        match *self {}
+    }
+
+    fn wait(&self, _value: u64, _timeout: Duration) -> bool {
+        match *self {}
+    }
+}
@@ -0,0 +1,56 @@
+//! Scheduling signals returned by each update
+//!
+//! Hosts that drive Bevy from a display link want to know after every frame whether to
+//! present, whether they can slow down, and whether messages are waiting, without a round
+//! of FFI calls. `bevy_embedded_update_with_info` fills an optional [`FrameInfo`] with them:
+//!
+//! ```c
+//! typedef struct {
+//!     uint64_t frame;
+//!     double cpu_ms;
+//!     double gpu_ms;
+//!     uint32_t pending_messages;
+//!     uint8_t needs_redraw;
+//!     uint8_t idle;
+//! } FrameInfo;
+//! ```
+
+use std::time::Duration;
+
+use bevy::ecs::{resource::Resource, world::World};
+
+/// Signals about the last update, filled in by `bevy_embedded_update_with_info`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameInfo {
+    /// Number of frames the app has run, counting the last one
+    pub frame: u64,
+    /// Wall time of the last frame's update in milliseconds
+    pub cpu_ms: f64,
+    /// GPU time of the most recently measured frame in milliseconds, or -1 without
+    /// [`GpuTimingPlugin`](crate::GpuTimingPlugin)
+    pub gpu_ms: f64,
+    /// Messages from Bevy waiting to be received by the host
+    pub pending_messages: u32,
+    /// 1 if the call ran a frame that changed what is on screen, 0 if it was skipped
+    /// (picture-in-picture pacing, no surface yet) or declared
+    /// [unchanged](crate::FrameDamage::unchanged)
+    pub needs_redraw: u8,
+    /// 1 if the frame was unchanged and no messages are waiting, so the host may lower its
+    /// frame rate until the next input or message
+    pub idle: u8,
+}
+
+/// Frames run so far and the time of the last one
+#[derive(Resource, Debug, Default)]
+pub(crate) struct FrameStats {
+    pub(crate) frame: u64,
+    pub(crate) cpu_time: Duration,
+}
+
+/// Counts a frame that took `frame_time`
+pub(crate) fn record_frame(world: &mut World, frame_time: Duration) {
+    let mut stats = world.get_resource_or_init::<FrameStats>();
+    stats.frame += 1;
+    stats.cpu_time = frame_time;
+}
@@ -0,0 +1,337 @@
+//! What a frozen app keeps on screen
+//!
+//! An app [frozen](crate::RecoveryAction::FreezeFrame) by its recovery policy stops
+//! presenting, so the platform keeps showing the last frame it rendered while the host
+//! decides what to do. When the host changes the surface of a frozen app (resizes,
+//! rotates or replaces it), the crate renders the app's world once more, as the failed
+//! update left it, instead of leaving the new surface black. Time doesn't advance, so
+//! this is the last good frame at the new size.
+//!
+//! An app that froze on a render error, or before its first frame, can't render its
+//! world. It presents the host's [`FallbackFrame`] instead, scaled to fit the surface on
+//! black, and leaves the surface alone without one. Hosts set the fallback ahead of time
+//! as RGBA8 pixels:
+//!
+//! ```swift
+//! let pixels = placeholder.rgba8Pixels()  // width * height * 4 bytes
+//! bevyEmbeddedIosSetFallbackFrame(app, pixels, pixels.count, width, height)
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::Arc,
+};
+
+use bevy::{
+    app::{App, SubApp, SubApps},
+    color::LinearRgba,
+    ecs::{
+        entity::Entity,
+        query::With,
+        resource::Resource,
+        schedule::{IntoScheduleConfigs, ScheduleLabel},
+        system::{Commands, Query, Res, ResMut},
+    },
+    render::{
+        render_resource::{
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, ColorTargetState,
+            ColorWrites, CommandEncoderDescriptor, Extent3d, LoadOp, Operations,
+            PipelineLayoutDescriptor, RawFragmentState, RawRenderPipelineDescriptor,
+            RawVertexState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+            Sampler, SamplerBindingType, SamplerDescriptor, ShaderModule, ShaderModuleDescriptor,
+            ShaderSource, ShaderStages, StoreOp, TextureDataOrder, TextureDescriptor,
+            TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+            binding_types::{sampler, texture_2d},
+        },
+        renderer::{RenderDevice, RenderQueue},
+        sync_world::TemporaryRenderEntity,
+        view::{
+            ExtractedWindows,
+            window::{create_surfaces, need_surface_configuration, prepare_windows},
+        },
+    },
+};
+
+use crate::{Recovery, SurfaceEpoch};
+
+/// Image a frozen app presents when it can't render its world, in RGBA8 sRGB pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackFrame {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// `width * height` pixels, row by row
+    pub pixels: Arc<[u8]>,
+}
+
+/// Renders the frozen app's world, or its fallback, if the surface changed since it last
+/// did
+///
+/// Only the render world is updated; the app's own schedules don't run.
+pub(crate) fn present_frozen(app: &mut App) {
+    let Some(recovery) = app.world().get_resource::<Recovery>().cloned() else {
+        return;
+    };
+    let epoch = app
+        .world()
+        .get_resource::<SurfaceEpoch>()
+        .map_or(0, SurfaceEpoch::epoch);
+    if !recovery.frozen_present_due(epoch) {
+        return;
+    }
+
+    let SubApps { main, sub_apps } = app.sub_apps_mut();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        for sub_app in sub_apps.values_mut() {
+            sub_app.extract(main.world_mut());
+            sub_app.update();
+        }
+    }));
+    if result.is_err() {
+        recovery.frozen_present_failed();
+    }
+}
+
+/// Presents the fallback of a frozen app in place of the render schedule
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct FrozenRender;
+
+/// Adds the schedule presenting fallback frames to the render world
+pub(crate) fn add_render_systems(render_app: &mut SubApp) {
+    render_app.init_resource::<FallbackPipelines>().add_systems(
+        FrozenRender,
+        (
+            create_surfaces.run_if(need_surface_configuration),
+            prepare_windows,
+            present_fallback,
+            despawn_extracted,
+        )
+            .chain(),
+    );
+}
+
+const FALLBACK_SHADER: &str = r"
+@group(0) @binding(0) var fallback: texture_2d<f32>;
+@group(0) @binding(1) var fallback_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// One triangle covering the viewport
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return VertexOutput(vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0), uv);
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(fallback, fallback_sampler, in.uv);
+}
+";
+
+/// Pipelines drawing the fallback, one per surface format
+#[derive(Resource, Default)]
+struct FallbackPipelines {
+    shared: Option<(ShaderModule, BindGroupLayout, Sampler)>,
+    pipelines: HashMap<TextureFormat, RenderPipeline>,
+}
+
+impl FallbackPipelines {
+    fn shared(
+        &mut self,
+        render_device: &RenderDevice,
+    ) -> &(ShaderModule, BindGroupLayout, Sampler) {
+        self.shared.get_or_insert_with(|| {
+            let shader = render_device.create_and_validate_shader_module(ShaderModuleDescriptor {
+                label: Some("fallback_frame_shader"),
+                source: ShaderSource::Wgsl(Cow::Borrowed(FALLBACK_SHADER)),
+            });
+            let layout = render_device.create_bind_group_layout(
+                "fallback_frame_layout",
+                &BindGroupLayoutEntries::sequential(
+                    ShaderStages::FRAGMENT,
+                    (
+                        texture_2d(TextureSampleType::Float { filterable: true }),
+                        sampler(SamplerBindingType::Filtering),
+                    ),
+                ),
+            );
+            let sampler = render_device.create_sampler(&SamplerDescriptor {
+                label: Some("fallback_frame_sampler"),
+                mag_filter: bevy::render::render_resource::FilterMode::Linear,
+                min_filter: bevy::render::render_resource::FilterMode::Linear,
+                ..Default::default()
+            });
+            (shader, layout, sampler)
+        })
+    }
+
+    fn pipeline(&mut self, render_device: &RenderDevice, format: TextureFormat) -> RenderPipeline {
+        if let Some(pipeline) = self.pipelines.get(&format) {
+            return pipeline.clone();
+        }
+        let (shader, layout, _) = self.shared(render_device);
+        let pipeline_layout = render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fallback_frame_pipeline_layout"),
+            bind_group_layouts: &[layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = render_device.create_render_pipeline(&RawRenderPipelineDescriptor {
+            label: Some("fallback_frame_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: RawVertexState {
+                module: shader,
+                entry_point: Some("vertex"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            fragment: Some(RawFragmentState {
+                module: shader,
+                entry_point: Some("fragment"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+        self.pipelines.insert(format, pipeline.clone());
+        pipeline
+    }
+}
+
+/// The largest rect with the fallback's aspect ratio centered in the surface
+fn fit(fallback: &FallbackFrame, width: u32, height: u32) -> (f32, f32, f32, f32) {
+    let (width, height) = (width as f32, height as f32);
+    let scale = (width / fallback.width as f32).min(height / fallback.height as f32);
+    let (fit_width, fit_height) = (
+        fallback.width as f32 * scale,
+        fallback.height as f32 * scale,
+    );
+    (
+        (width - fit_width) / 2.0,
+        (height - fit_height) / 2.0,
+        fit_width,
+        fit_height,
+    )
+}
+
+/// Draws the fallback into every window's swapchain texture and presents it
+fn present_fallback(
+    recovery: Res<Recovery>,
+    mut windows: ResMut<ExtractedWindows>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut pipelines: ResMut<FallbackPipelines>,
+) {
+    let Some(fallback) = recovery.fallback() else {
+        return;
+    };
+    let max_size = render_device.limits().max_texture_dimension_2d;
+    if fallback.width > max_size || fallback.height > max_size {
+        log::warn!(
+            "Fallback frame of {}x{} exceeds the maximum texture size {max_size}",
+            fallback.width,
+            fallback.height
+        );
+        return;
+    }
+
+    let texture = render_device.create_texture_with_data(
+        &render_queue,
+        &TextureDescriptor {
+            label: Some("fallback_frame"),
+            size: Extent3d {
+                width: fallback.width,
+                height: fallback.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        TextureDataOrder::LayerMajor,
+        &fallback.pixels,
+    );
+    let view = texture.create_view(&Default::default());
+
+    let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("fallback_frame"),
+    });
+    let mut frames = Vec::new();
+    for window in windows.values_mut() {
+        let (Some(target), Some(format)) = (
+            window.swap_chain_texture_view.take(),
+            window.swap_chain_texture_format,
+        ) else {
+            continue;
+        };
+        // Swapchain views are created with the sRGB variant of the surface format
+        let pipeline = pipelines.pipeline(&render_device, format.add_srgb_suffix());
+        let (_, layout, sampler) = pipelines.shared(&render_device);
+        let bind_group = render_device.create_bind_group(
+            "fallback_frame_bind_group",
+            layout,
+            &BindGroupEntries::sequential((&view, sampler)),
+        );
+        let (x, y, width, height) = fit(&fallback, window.physical_width, window.physical_height);
+
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("fallback_frame"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &target,
+                depth_slice: None,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(LinearRgba::BLACK.into()),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &*bind_group, &[]);
+        pass.draw(0..3, 0..1);
+        drop(pass);
+
+        frames.extend(window.swap_chain_texture.take());
+    }
+
+    render_queue.submit([encoder.finish()]);
+    for frame in frames {
+        frame.present();
+    }
+}
+
+/// Despawns the entities extracted for the frame, which the render schedule otherwise
+/// does in its cleanup
+fn despawn_extracted(
+    mut commands: Commands,
+    extracted: Query<Entity, With<TemporaryRenderEntity>>,
+    surface_epoch: Option<Res<SurfaceEpoch>>,
+) {
+    for entity in &extracted {
+        commands.entity(entity).despawn();
+    }
+    if let Some(surface_epoch) = surface_epoch {
+        surface_epoch.finish_frames();
+    }
+}
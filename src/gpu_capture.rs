@@ -0,0 +1,105 @@
+//! GPU frame captures of the app's frames alone
+//!
+//! Capturing a frame from Xcode or RenderDoc records everything the host renders as well,
+//! and in a big host app the widget's work is hard to find. A host developer menu can
+//! instead call `bevy_embedded_ios_capture_gpu_frame` / `nativeCaptureGpuFrame`, and the
+//! render world wraps the next frame it renders, from its first command to presentation,
+//! in a capture of the attached graphics debugger:
+//!
+//! - Metal records the capture with `MTLCaptureManager`, which needs the app to be run
+//!   from Xcode or `MTL_CAPTURE_ENABLED=1`
+//! - Vulkan and GLES start a capture through the RenderDoc API when the app was launched
+//!   from RenderDoc or has it injected
+//!
+//! Without a debugger the request does nothing. Apps can also call
+//! [`GpuCapture::capture_next_frame`], e.g. from a debug key binding. Captures are behind
+//! the `gpu_capture` cargo feature, as graphics debuggers can crash the app they capture.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use bevy::{
+    app::SubApp,
+    ecs::{
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Res, ResMut},
+    },
+    render::{Render, RenderSystems, render_resource::PollType, renderer::RenderDevice},
+};
+
+/// Requests GPU frame captures from the render world; see the [module docs](self)
+///
+/// Shared by the main and render worlds.
+#[derive(Resource, Clone, Default)]
+pub struct GpuCapture {
+    requested: Arc<AtomicBool>,
+}
+
+impl GpuCapture {
+    /// Captures the next frame the render world starts in the attached graphics debugger
+    pub fn capture_next_frame(&self) {
+        self.requested.store(true, Ordering::Release);
+    }
+
+    /// Returns true while a requested capture hasn't started
+    pub fn is_pending(&self) -> bool {
+        self.requested.load(Ordering::Acquire)
+    }
+}
+
+/// Whether the render world started a capture it hasn't stopped
+#[derive(Resource, Default)]
+struct ActiveCapture(bool);
+
+/// Starts a requested capture before the frame records its first command
+fn start_capture(
+    capture: Res<GpuCapture>,
+    render_device: Res<RenderDevice>,
+    mut active: ResMut<ActiveCapture>,
+) {
+    let device = render_device.wgpu_device();
+    // A frame that panicked never reached its cleanup
+    if active.0 {
+        // SAFETY: the capture started by that frame is still active
+        unsafe { device.stop_graphics_debugger_capture() };
+        active.0 = false;
+    }
+    if !capture.requested.swap(false, Ordering::AcqRel) {
+        return;
+    }
+    // SAFETY: the render world only starts captures here, after stopping its last one
+    unsafe { device.start_graphics_debugger_capture() };
+    active.0 = true;
+    log::info!("Started GPU frame capture");
+}
+
+/// Stops the capture once the frame is presented and done on the GPU
+fn stop_capture(render_device: Res<RenderDevice>, mut active: ResMut<ActiveCapture>) {
+    if !active.0 {
+        return;
+    }
+    if let Err(error) = render_device.poll(PollType::Wait) {
+        log::warn!("Waiting for the captured frame failed: {error}");
+    }
+    // SAFETY: the capture was started by this frame
+    unsafe { render_device.wgpu_device().stop_graphics_debugger_capture() };
+    active.0 = false;
+    log::info!("Stopped GPU frame capture");
+}
+
+/// Wraps the frames the host requests in captures
+pub(crate) fn add_render_systems(render_app: &mut SubApp, capture: GpuCapture) {
+    render_app
+        .insert_resource(capture)
+        .init_resource::<ActiveCapture>()
+        .add_systems(
+            Render,
+            (
+                start_capture.in_set(RenderSystems::ExtractCommands),
+                stop_capture.in_set(RenderSystems::Cleanup),
+            ),
+        );
+}
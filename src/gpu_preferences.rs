@@ -0,0 +1,382 @@
+//! GPU adapter and limits selection
+//!
+//! The same embedded binary runs on old Android phones with barely conformant drivers and
+//! on M-series iPads. [`GpuPreferences`] in the [creation config](crate::EmbeddedConfig)
+//! picks the adapter Bevy prefers and caps the limits it claims, and [`GpuReport`] says
+//! what the instance got, so the app can scale its rendering to the device:
+//!
+//! ```ignore
+//! fn setup(app: &mut App) {
+//!     let gpu = app.world().resource::<EmbeddedConfig>().gpu.clone();
+//!     app.add_plugins(DefaultPlugins.build().disable::<WinitPlugin>().with_gpu_preferences(&gpu));
+//! }
+//!
+//! fn pick_quality(report: Res<GpuReport>, mut settings: ResMut<RenderSettings>) {
+//!     if !report.meets_requirements || !report.compute_shaders {
+//!         settings.shadows = false;
+//!     }
+//! }
+//! ```
+//!
+//! Hosts set the `gpu_power` (`low_power`/`high_performance`), `gpu_limits`
+//! (`adapter`/`downlevel`/`webgl2`), `gpu_backend` (`auto`/`primary`/`gles`) and
+//! `vulkan_blocklist` (comma-separated) config keys, and read the report as JSON after the
+//! app finished initializing (`bevy_embedded_ios_gpu_report`, `nativeGpuReport`).
+//!
+//! Android's Vulkan drivers range from solid to crashing on the first frame. With the
+//! `gles_fallback` feature, [`GpuBackend::Auto`] renders with GLES through EGL when a
+//! device has no Vulkan adapter wgpu can use, or only adapters on the host's
+//! [blocklist](GpuPreferences::vulkan_blocklist). Hosts that know a device's Vulkan driver
+//! is broken set `gpu_backend=gles` to skip Vulkan entirely.
+//!
+//! Bevy claims every limit the adapter supports by default. Capping them to
+//! [`GpuLimits::Downlevel`] makes high-end devices behave like low-end ones, which keeps
+//! rendering consistent across a fleet and lets low-end behavior be tested on any device.
+//! [`GpuPreferences::required_limits`] are never requested from the device, which would
+//! fail to start on devices below them; they are checked against what the device supports,
+//! and the app falls back when [`GpuReport::meets_requirements`] is false.
+
+use std::fmt::Write;
+
+use bevy::{
+    app::PluginGroupBuilder,
+    ecs::{resource::Resource, world::World},
+    render::{
+        RenderPlugin,
+        render_resource::{DownlevelFlags, WgpuLimits},
+        renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice},
+        settings::{Backends, PowerPreference, RenderCreation, WgpuSettings},
+    },
+};
+
+use crate::EmbeddedConfig;
+
+/// Limits Bevy claims from the adapter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum GpuLimits {
+    /// Everything the adapter supports
+    #[default]
+    Adapter = 0,
+    /// At most wgpu's downlevel defaults, which GLES 3.1 and Vulkan 1.0 devices support
+    Downlevel = 1,
+    /// At most the WebGL 2 defaults, for the oldest GLES 3.0 devices
+    WebGl2 = 2,
+}
+
+impl GpuLimits {
+    /// Create a GpuLimits from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(GpuLimits::Adapter),
+            1 => Some(GpuLimits::Downlevel),
+            2 => Some(GpuLimits::WebGl2),
+            _ => None,
+        }
+    }
+
+    /// Parses a host config value
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "adapter" => Some(GpuLimits::Adapter),
+            "downlevel" => Some(GpuLimits::Downlevel),
+            "webgl2" => Some(GpuLimits::WebGl2),
+            _ => None,
+        }
+    }
+
+    /// Name used in host config and reports
+    pub fn name(self) -> &'static str {
+        match self {
+            GpuLimits::Adapter => "adapter",
+            GpuLimits::Downlevel => "downlevel",
+            GpuLimits::WebGl2 => "webgl2",
+        }
+    }
+
+    /// Upper bounds of the claimed limits, or `None` for no bounds
+    pub fn constraints(self) -> Option<WgpuLimits> {
+        match self {
+            GpuLimits::Adapter => None,
+            GpuLimits::Downlevel => Some(WgpuLimits::downlevel_defaults()),
+            GpuLimits::WebGl2 => Some(WgpuLimits::downlevel_webgl2_defaults()),
+        }
+    }
+}
+
+/// Graphics API to render with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum GpuBackend {
+    /// The platform's primary API, with GLES where no Vulkan adapter is usable if the
+    /// `gles_fallback` feature is enabled
+    #[default]
+    Auto = 0,
+    /// The platform's primary API (Vulkan, Metal or DX12) only
+    Primary = 1,
+    /// GLES, with the `gles_fallback` feature on platforms other than Apple's
+    Gles = 2,
+}
+
+impl GpuBackend {
+    /// Create a GpuBackend from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(GpuBackend::Auto),
+            1 => Some(GpuBackend::Primary),
+            2 => Some(GpuBackend::Gles),
+            _ => None,
+        }
+    }
+
+    /// Parses a host config value
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(GpuBackend::Auto),
+            "primary" => Some(GpuBackend::Primary),
+            "gles" => Some(GpuBackend::Gles),
+            _ => None,
+        }
+    }
+
+    /// Name used in host config
+    pub fn name(self) -> &'static str {
+        match self {
+            GpuBackend::Auto => "auto",
+            GpuBackend::Primary => "primary",
+            GpuBackend::Gles => "gles",
+        }
+    }
+}
+
+/// GPU adapter and limits an instance asks for
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpuPreferences {
+    /// Adapter to prefer on devices with several, or `None` for Bevy's default
+    pub power_preference: Option<PowerPreference>,
+    /// Limits Bevy claims from the adapter
+    pub limits: GpuLimits,
+    /// Limits the app needs for its full rendering, checked into [`GpuReport`]
+    pub required_limits: Option<WgpuLimits>,
+    /// Graphics API to render with
+    pub backend: GpuBackend,
+    /// Vulkan adapters [`GpuBackend::Auto`] falls back to GLES on
+    ///
+    /// Each entry is matched case-insensitively against the adapter's name, driver and
+    /// driver version, e.g. `Mali-G71` or `v1.r26p0`.
+    pub vulkan_blocklist: Vec<String>,
+}
+
+impl GpuPreferences {
+    /// Parses a `gpu_power` host config value
+    pub fn power_preference_from_name(name: &str) -> Option<PowerPreference> {
+        match name {
+            "low_power" => Some(PowerPreference::LowPower),
+            "high_performance" => Some(PowerPreference::HighPerformance),
+            _ => None,
+        }
+    }
+
+    /// Wgpu settings for `RenderPlugin` with these preferences
+    pub fn wgpu_settings(&self) -> WgpuSettings {
+        let mut settings = WgpuSettings::default();
+        if let Some(power_preference) = self.power_preference {
+            settings.power_preference = power_preference;
+        }
+        settings.constrained_limits = self.limits.constraints();
+        if let Some(backends) = self.backends() {
+            settings.backends = Some(backends);
+        }
+        settings
+    }
+
+    /// Backends to create the renderer with, or `None` for Bevy's default
+    ///
+    /// `WGPU_BACKEND` in the environment takes precedence, as it does in Bevy.
+    pub fn backends(&self) -> Option<Backends> {
+        if Backends::from_env().is_some() {
+            return None;
+        }
+        match self.backend {
+            GpuBackend::Primary => Some(Backends::PRIMARY),
+            #[cfg(all(feature = "gles_fallback", not(target_vendor = "apple")))]
+            GpuBackend::Gles => Some(Backends::GL),
+            #[cfg(all(feature = "gles_fallback", not(target_vendor = "apple")))]
+            GpuBackend::Auto => Some(usable_primary_backends(&self.vulkan_blocklist)),
+            #[cfg(not(all(feature = "gles_fallback", not(target_vendor = "apple"))))]
+            GpuBackend::Gles => {
+                log::warn!(
+                    "GLES needs the gles_fallback feature and a platform other than Apple's"
+                );
+                None
+            }
+            #[cfg(not(all(feature = "gles_fallback", not(target_vendor = "apple"))))]
+            GpuBackend::Auto => None,
+        }
+    }
+
+    /// How `RenderPlugin` should create its resources
+    ///
+    /// Renders on the host's device if the host set one, which ignores the preferences.
+    pub fn render_creation(&self) -> RenderCreation {
+        #[cfg(feature = "gpu_interop")]
+        if crate::has_host_device() {
+            return crate::host_render_creation();
+        }
+        RenderCreation::Automatic(self.wgpu_settings())
+    }
+}
+
+/// Backends of the primary adapters that are not blocklisted, or GL if there are none
+#[cfg(all(feature = "gles_fallback", not(target_vendor = "apple")))]
+fn usable_primary_backends(vulkan_blocklist: &[String]) -> Backends {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: Backends::PRIMARY,
+        ..Default::default()
+    });
+    let blocklist: Vec<String> = vulkan_blocklist
+        .iter()
+        .map(|entry| entry.to_lowercase())
+        .collect();
+
+    let mut backends = Backends::empty();
+    for adapter in instance.enumerate_adapters(Backends::PRIMARY) {
+        let info = adapter.get_info();
+        let description = format!("{} {} {}", info.name, info.driver, info.driver_info);
+        let lowercase = description.to_lowercase();
+        if info.backend == wgpu::Backend::Vulkan
+            && blocklist.iter().any(|entry| lowercase.contains(entry))
+        {
+            log::info!("Vulkan adapter {description} is blocklisted");
+            continue;
+        }
+        backends |= Backends::from(info.backend);
+    }
+
+    if backends.is_empty() {
+        log::warn!("No usable Vulkan adapter, falling back to GLES");
+        return Backends::GL;
+    }
+    backends
+}
+
+/// Configures a plugin group's `RenderPlugin` with [`GpuPreferences`]
+pub trait GpuPreferencePlugins {
+    /// Replaces the group's `RenderPlugin` with one selecting the adapter and limits
+    ///
+    /// Does nothing if the group has no `RenderPlugin`. Other `RenderPlugin` settings are
+    /// reset to their defaults, so apps customizing the plugin should set
+    /// [`GpuPreferences::render_creation`] themselves.
+    fn with_gpu_preferences(self, preferences: &GpuPreferences) -> Self;
+}
+
+impl GpuPreferencePlugins for PluginGroupBuilder {
+    fn with_gpu_preferences(self, preferences: &GpuPreferences) -> Self {
+        if !self.contains::<RenderPlugin>() {
+            return self;
+        }
+        self.set(RenderPlugin {
+            render_creation: preferences.render_creation(),
+            ..Default::default()
+        })
+    }
+}
+
+/// The adapter and limits an instance got
+///
+/// Inserted once the app's plugins are finished, if it renders.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct GpuReport {
+    /// Name of the adapter, e.g. `Apple M2` or `Mali-G52`
+    pub adapter: String,
+    /// Graphics API, e.g. `metal`, `vulkan` or `gl`
+    pub backend: &'static str,
+    /// Kind of adapter, e.g. `IntegratedGpu` or `Cpu`
+    pub device_type: String,
+    /// Driver name and version, empty if unknown
+    pub driver: String,
+    /// Limits of the device Bevy created
+    pub limits: WgpuLimits,
+    /// Whether the adapter supports compute shaders
+    pub compute_shaders: bool,
+    /// Whether the adapter supports all of WebGPU; false for most GLES devices
+    pub webgpu_compliant: bool,
+    /// Whether the device meets [`GpuPreferences::required_limits`]
+    pub meets_requirements: bool,
+}
+
+impl GpuReport {
+    /// Builds the report from the render resources of a finished app
+    pub(crate) fn of(world: &World) -> Option<Self> {
+        let info = world.get_resource::<RenderAdapterInfo>()?;
+        let device = world.get_resource::<RenderDevice>()?;
+        let adapter = world.get_resource::<RenderAdapter>()?;
+        let downlevel = adapter.0.get_downlevel_capabilities();
+        let limits = device.limits();
+        let meets_requirements = world
+            .get_resource::<EmbeddedConfig>()
+            .and_then(|config| config.gpu.required_limits.as_ref())
+            .is_none_or(|required| required.check_limits(&limits));
+        let driver = [info.driver.as_str(), info.driver_info.as_str()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Some(Self {
+            adapter: info.name.clone(),
+            backend: info.backend.to_str(),
+            device_type: format!("{:?}", info.device_type),
+            driver,
+            limits,
+            compute_shaders: downlevel.flags.contains(DownlevelFlags::COMPUTE_SHADERS),
+            webgpu_compliant: downlevel.is_webgpu_compliant(),
+            meets_requirements,
+        })
+    }
+
+    /// The report as a JSON object for hosts, with the most telling limits
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+        let _ = write!(
+            json,
+            "\"adapter\":{},\"backend\":{},\"device_type\":{},\"driver\":{},",
+            json_string(&self.adapter),
+            json_string(self.backend),
+            json_string(&self.device_type),
+            json_string(&self.driver),
+        );
+        let _ = write!(
+            json,
+            "\"max_texture_dimension_2d\":{},\"max_storage_buffers_per_shader_stage\":{},\
+             \"max_sampled_textures_per_shader_stage\":{},",
+            self.limits.max_texture_dimension_2d,
+            self.limits.max_storage_buffers_per_shader_stage,
+            self.limits.max_sampled_textures_per_shader_stage,
+        );
+        let _ = write!(
+            json,
+            "\"compute_shaders\":{},\"webgpu_compliant\":{},\"meets_requirements\":{}}}",
+            self.compute_shaders, self.webgpu_compliant, self.meets_requirements,
+        );
+        json
+    }
+}
+
+/// Quotes and escapes a JSON string
+pub(crate) fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,136 @@
+//! GPU time of each frame, for adaptive quality and host-side profiling
+//!
+//! [`GpuTimingPlugin`] enables Bevy's render diagnostics, which write GPU timestamp
+//! queries around the render passes, and collects the results into [`GpuTiming`] and
+//! the [`GpuTiming::FRAME_TIME`] diagnostic. Apps read the resource to scale their own
+//! effects; hosts read the last frame's GPU time after an update
+//! (`bevy_embedded_ios_gpu_frame_time_ms`, `nativeGpuFrameTimeMs`) to log what each
+//! widget costs.
+//!
+//! Timestamps are read back a few frames after they were written, so the values trail
+//! the current frame slightly. Devices without timestamp query support, including Metal
+//! devices Bevy doesn't time yet, report no GPU time at all.
+//!
+//! Add the plugin after `DefaultPlugins`, so the render world exists:
+//!
+//! ```ignore
+//! app.add_plugins((DefaultPlugins.build().disable::<WinitPlugin>(), EmbeddedPlugin))
+//!     .add_plugins(GpuTimingPlugin);
+//! ```
+
+use std::time::{Duration, Instant};
+
+use bevy::{
+    app::{App, Plugin, Update},
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, RegisterDiagnostic},
+    ecs::{
+        resource::Resource,
+        system::{Local, Res, ResMut},
+    },
+    render::{RenderApp, diagnostic::RenderDiagnosticsPlugin},
+};
+
+/// Root of the paths render diagnostics are stored under
+const RENDER_ROOT: &str = "render";
+
+/// Last component of the paths of GPU elapsed times
+const ELAPSED_GPU: &str = "elapsed_gpu";
+
+/// Enables GPU timestamp queries and fills [`GpuTiming`]
+#[derive(Default)]
+pub struct GpuTimingPlugin;
+
+impl Plugin for GpuTimingPlugin {
+    fn build(&self, app: &mut App) {
+        // Timestamp queries need the render world
+        if app.get_sub_app(RenderApp).is_some() && !app.is_plugin_added::<RenderDiagnosticsPlugin>()
+        {
+            app.add_plugins(RenderDiagnosticsPlugin);
+        }
+        app.init_resource::<GpuTiming>()
+            .register_diagnostic(Diagnostic::new(GpuTiming::FRAME_TIME).with_suffix("ms"))
+            .add_systems(Update, collect_gpu_timing);
+    }
+}
+
+/// GPU time of the most recently measured frame
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct GpuTiming {
+    frame_time: Option<Duration>,
+    passes: Vec<(String, Duration)>,
+}
+
+impl GpuTiming {
+    /// Diagnostic with the GPU time of each measured frame, in milliseconds
+    pub const FRAME_TIME: DiagnosticPath = DiagnosticPath::const_new("embedded/gpu_frame_time");
+
+    /// Total GPU time of the frame's render passes, or `None` if nothing was measured
+    pub fn frame_time(&self) -> Option<Duration> {
+        self.frame_time
+    }
+
+    /// GPU time of each measured pass, by path such as `main_opaque_pass_3d`
+    ///
+    /// Nested spans are listed with their parents' names, e.g. `bloom/downsample`.
+    pub fn passes(&self) -> impl Iterator<Item = (&str, Duration)> {
+        self.passes
+            .iter()
+            .map(|(name, elapsed)| (name.as_str(), *elapsed))
+    }
+
+    /// GPU time of the pass at `name`, if it was measured
+    pub fn pass(&self, name: &str) -> Option<Duration> {
+        self.passes()
+            .find(|(pass, _)| *pass == name)
+            .map(|(_, elapsed)| elapsed)
+    }
+}
+
+/// Collects the GPU times render diagnostics synced since the last update
+fn collect_gpu_timing(
+    store: Res<DiagnosticsStore>,
+    mut timing: ResMut<GpuTiming>,
+    mut diagnostics: Diagnostics,
+    mut last_synced: Local<Option<Instant>>,
+) {
+    let mut synced = None;
+    let mut frame_time = Duration::ZERO;
+    let mut passes = Vec::new();
+    for diagnostic in store.iter() {
+        let mut components = diagnostic.path().components();
+        if components.next() != Some(RENDER_ROOT) {
+            continue;
+        }
+        let components: Vec<&str> = components.collect();
+        let Some((&ELAPSED_GPU, names)) = components.split_last() else {
+            continue;
+        };
+        // Render diagnostics keep their last value, so only take measurements that
+        // arrived since the previous update
+        let Some(measurement) = diagnostic.measurement() else {
+            continue;
+        };
+        if last_synced.is_some_and(|last| measurement.time <= last) {
+            continue;
+        }
+        synced = synced.max(Some(measurement.time));
+
+        let elapsed = Duration::from_secs_f64(measurement.value.max(0.0) / 1000.0);
+        // Nested spans are already part of their parent's time
+        if names.len() == 1 {
+            frame_time += elapsed;
+        }
+        passes.push((names.join("/"), elapsed));
+    }
+
+    if synced.is_none() {
+        return;
+    }
+    *last_synced = synced;
+    passes.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    *timing = GpuTiming {
+        frame_time: Some(frame_time),
+        passes,
+    };
+    diagnostics.add_measurement(&GpuTiming::FRAME_TIME, || frame_time.as_secs_f64() * 1000.0);
+}
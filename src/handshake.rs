@@ -0,0 +1,298 @@
+//! Protocol handshake between the host and the app
+//!
+//! A host built against one version of the app and shipped with another would otherwise
+//! keep sending bytes the app misreads. After creating the app the host sends its protocol
+//! on [`ReservedTopic::HostHandshake`], and the app answers with its own on
+//! [`ReservedTopic::Handshake`]; both carry the same payload:
+//!
+//! ```text
+//! version: u16 | schema hash: u64 | codec count: u16 | codecs: u32 len + UTF-8 ...
+//! ```
+//!
+//! `version` is the crate's [`PROTOCOL_VERSION`], the layout of reserved messages. The
+//! schema hash and codecs are the app's own, set with [`EmbeddedConfig::protocol`]: a
+//! hash of the app's message definitions, and the encodings (e.g. `json`, `cbor`) it
+//! accepts, most preferred first. A schema hash of 0 or an empty codec list on either
+//! side skips that check.
+//!
+//! The app compares the host's protocol with its own and records the outcome in the
+//! [`Handshake`] resource, announced with [`HandshakeCompleted`]. A mismatch is logged and
+//! stored as the last error, so the update that processes it fails, and hosts can query
+//! it (`bevy_embedded_ios_handshake_status`, `nativeHandshakeStatus`). Hosts that never
+//! send a handshake are not checked.
+//!
+//! [`EmbeddedConfig::protocol`]: crate::EmbeddedConfig::protocol
+
+use std::fmt;
+
+use bevy::ecs::{
+    message::{Message, MessageReader, MessageWriter},
+    resource::Resource,
+    system::{Res, ResMut},
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, PROTOCOL_VERSION, ReservedTopic},
+};
+
+/// Message schema and encodings of one side of the channel
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProtocolSchema {
+    /// Hash of the message definitions, or 0 to skip the check
+    pub schema_hash: u64,
+    /// Names of the supported message encodings, most preferred first
+    pub codecs: Vec<String>,
+}
+
+impl ProtocolSchema {
+    /// A schema identified by `schema_hash`, without codecs
+    pub fn new(schema_hash: u64) -> Self {
+        Self {
+            schema_hash,
+            codecs: Vec::new(),
+        }
+    }
+
+    /// Adds supported encodings, most preferred first
+    pub fn with_codecs<I, S>(mut self, codecs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.codecs.extend(codecs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Encodes a handshake payload announcing this schema at `version`
+    pub fn encode(&self, version: u16) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+        writer
+            .u16(version)
+            .u64(self.schema_hash)
+            .u16(self.codecs.len() as u16);
+        for codec in &self.codecs {
+            writer.string(codec);
+        }
+        writer.finish()
+    }
+
+    /// Decodes a handshake payload into its version and schema
+    pub fn decode(payload: &[u8]) -> Option<(u16, Self)> {
+        let mut reader = ByteReader::new(payload);
+        let version = reader.u16()?;
+        let schema_hash = reader.u64()?;
+        let count = reader.u16()?;
+        let codecs = (0..count)
+            .map(|_| reader.string().map(str::to_string))
+            .collect::<Option<Vec<_>>>()?;
+        Some((
+            version,
+            Self {
+                schema_hash,
+                codecs,
+            },
+        ))
+    }
+}
+
+/// State of the handshake with the host
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HandshakeStatus {
+    /// The host has not sent its handshake yet
+    #[default]
+    Pending = 0,
+    /// The host speaks the same protocol
+    Compatible = 1,
+    /// The host was built for a different protocol
+    Mismatched = 2,
+}
+
+/// Why the host's protocol doesn't match the app's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeError {
+    /// The host's handshake could not be decoded
+    Malformed,
+    /// The host uses a different layout of reserved messages
+    ProtocolVersion {
+        /// Version the host sent
+        host: u16,
+        /// Version of this crate
+        app: u16,
+    },
+    /// The host was built against different message definitions
+    SchemaHash {
+        /// Hash the host sent
+        host: u64,
+        /// Hash the app declared
+        app: u64,
+    },
+    /// None of the host's encodings is supported by the app
+    NoCommonCodec {
+        /// Encodings the host supports
+        host: Vec<String>,
+        /// Encodings the app supports
+        app: Vec<String>,
+    },
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "Malformed host handshake"),
+            Self::ProtocolVersion { host, app } => write!(
+                f,
+                "Host uses channel protocol version {host}, but the app uses version {app}"
+            ),
+            Self::SchemaHash { host, app } => write!(
+                f,
+                "Host message schema {host:016x} does not match the app's schema {app:016x}"
+            ),
+            Self::NoCommonCodec { host, app } => write!(
+                f,
+                "Host supports codecs [{}], but the app only supports [{}]",
+                host.join(", "),
+                app.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+/// Outcome of the handshake with the host
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Handshake {
+    schema: ProtocolSchema,
+    host: Option<ProtocolSchema>,
+    codec: Option<String>,
+    error: Option<HandshakeError>,
+}
+
+impl Handshake {
+    /// A pending handshake for the app's `schema`
+    pub fn new(schema: ProtocolSchema) -> Self {
+        Self {
+            schema,
+            ..Default::default()
+        }
+    }
+
+    /// Current state of the handshake
+    pub fn status(&self) -> HandshakeStatus {
+        match (&self.host, &self.error) {
+            (_, Some(_)) => HandshakeStatus::Mismatched,
+            (Some(_), None) => HandshakeStatus::Compatible,
+            (None, None) => HandshakeStatus::Pending,
+        }
+    }
+
+    /// The app's own schema, as sent to the host
+    pub fn schema(&self) -> &ProtocolSchema {
+        &self.schema
+    }
+
+    /// The schema the host sent, once it did
+    pub fn host_schema(&self) -> Option<&ProtocolSchema> {
+        self.host.as_ref()
+    }
+
+    /// The app's most preferred encoding the host supports
+    ///
+    /// `None` until the handshake succeeds, or if either side declared no codecs.
+    pub fn codec(&self) -> Option<&str> {
+        self.codec.as_deref()
+    }
+
+    /// Why the handshake failed, if it did
+    pub fn error(&self) -> Option<&HandshakeError> {
+        self.error.as_ref()
+    }
+
+    /// Compares the host's handshake payload with the app's schema
+    fn complete(&mut self, payload: &[u8]) -> Result<(), HandshakeError> {
+        self.codec = None;
+        self.error = None;
+        let result = self.check(payload);
+        if let Err(error) = &result {
+            self.error = Some(error.clone());
+        }
+        result
+    }
+
+    fn check(&mut self, payload: &[u8]) -> Result<(), HandshakeError> {
+        let (version, host) = ProtocolSchema::decode(payload).ok_or(HandshakeError::Malformed)?;
+        let schema = &self.schema;
+        let host = self.host.insert(host);
+
+        if version != PROTOCOL_VERSION {
+            return Err(HandshakeError::ProtocolVersion {
+                host: version,
+                app: PROTOCOL_VERSION,
+            });
+        }
+        if host.schema_hash != 0
+            && schema.schema_hash != 0
+            && host.schema_hash != schema.schema_hash
+        {
+            return Err(HandshakeError::SchemaHash {
+                host: host.schema_hash,
+                app: schema.schema_hash,
+            });
+        }
+        if host.codecs.is_empty() || schema.codecs.is_empty() {
+            return Ok(());
+        }
+        match schema
+            .codecs
+            .iter()
+            .find(|codec| host.codecs.contains(codec))
+        {
+            Some(codec) => {
+                self.codec = Some(codec.clone());
+                Ok(())
+            }
+            None => Err(HandshakeError::NoCommonCodec {
+                host: host.codecs.clone(),
+                app: schema.codecs.clone(),
+            }),
+        }
+    }
+}
+
+/// Sent when the host's handshake was processed
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeCompleted {
+    /// `Ok` if the host speaks the app's protocol
+    pub result: Result<(), HandshakeError>,
+}
+
+/// Checks the host's handshake against the app's protocol and answers with the app's
+pub(crate) fn receive_handshake(
+    mut commands: MessageReader<HostCommand>,
+    channel: Res<HostChannel>,
+    mut handshake: ResMut<Handshake>,
+    mut completed: MessageWriter<HandshakeCompleted>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::HostHandshake {
+            continue;
+        }
+
+        // Answer even on mismatch, so the host can report both sides
+        channel.send_reserved(
+            ReservedTopic::Handshake,
+            &handshake.schema().encode(PROTOCOL_VERSION),
+        );
+        let result = handshake.complete(&command.payload);
+        match &result {
+            Ok(()) => log::info!("Host handshake succeeded"),
+            Err(error) => {
+                log::error!("Host handshake failed: {error}");
+                crate::store_error(format!("Host handshake failed: {error}"));
+            }
+        }
+        completed.write(HandshakeCompleted { result });
+    }
+}
@@ -0,0 +1,141 @@
+//! Detecting hosts that stopped updating the app
+//!
+//! Hosts stop calling `bevy_embedded_update` without telling the app: the display link
+//! is paused behind a modal, the view is detached from its window, or the surface is
+//! destroyed while the app stays alive. Every update (and `bevy_embedded_run_schedule`)
+//! is a heartbeat. Once none arrived for [`EmbeddedConfig::heartbeat_timeout`], the app
+//! counts as stalled:
+//!
+//! - Host audio (`HostAudioPlugin`) renders silence from the audio thread, so sounds
+//!   pause where they were instead of playing on without the simulation
+//! - The first update after the stall drops the gap from virtual time, so `Time` and
+//!   `FixedUpdate` continue from where they stopped instead of catching up
+//!
+//! That update also sends [`UpdatesResumed`] to the app's systems and on
+//! [`ReservedTopic::UpdatesResumed`] to the host:
+//!
+//! ```text
+//! updates resumed: stalled_ms: u32
+//! ```
+//!
+//! Detection is off unless the host sets the `heartbeat_timeout_ms` creation config key
+//! or the app sets [`EmbeddedConfig::heartbeat_timeout`].
+//!
+//! [`EmbeddedConfig::heartbeat_timeout`]: crate::EmbeddedConfig::heartbeat_timeout
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::App,
+    ecs::{message::Message, resource::Resource},
+    time::{Real, Time, TimeUpdateStrategy},
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteWriter, ReservedTopic},
+};
+
+struct HeartbeatState {
+    origin: Instant,
+    /// Microseconds from `origin` to the last update, plus one; 0 before the first update
+    last_beat: AtomicU64,
+    timeout: Duration,
+}
+
+/// When the host last updated the app; see the [module docs](self)
+///
+/// Shared with the host's audio thread.
+#[derive(Resource, Clone)]
+pub struct Heartbeat {
+    state: Arc<HeartbeatState>,
+}
+
+impl Heartbeat {
+    /// Creates a heartbeat that stalls after `timeout` without updates
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            state: Arc::new(HeartbeatState {
+                origin: Instant::now(),
+                last_beat: AtomicU64::new(0),
+                timeout,
+            }),
+        }
+    }
+
+    /// Time without updates after which the app is stalled
+    pub fn timeout(&self) -> Duration {
+        self.state.timeout
+    }
+
+    /// Time since the last update, or `None` before the first one
+    pub fn since_last_update(&self) -> Option<Duration> {
+        let last_beat = self
+            .state
+            .last_beat
+            .load(Ordering::Acquire)
+            .checked_sub(1)?;
+        let last_beat = self.state.origin + Duration::from_micros(last_beat);
+        Some(Instant::now().saturating_duration_since(last_beat))
+    }
+
+    /// Whether the host stopped updating the app for longer than the timeout
+    ///
+    /// Apps that weren't updated yet, such as preloaded apps, aren't stalled.
+    pub fn is_stalled(&self) -> bool {
+        self.since_last_update()
+            .is_some_and(|since| since > self.state.timeout)
+    }
+
+    /// Records an update, returning how long the app was stalled before it
+    fn beat(&self) -> Option<Duration> {
+        let stalled = self
+            .since_last_update()
+            .filter(|&since| since > self.state.timeout);
+        let now = self.state.origin.elapsed().as_micros() as u64 + 1;
+        self.state.last_beat.store(now, Ordering::Release);
+        stalled
+    }
+}
+
+/// Sent by the first update after the host stopped updating the app for longer than
+/// [`Heartbeat::timeout`]
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdatesResumed {
+    /// Time between the last two updates
+    pub stalled_for: Duration,
+}
+
+/// Records a heartbeat before the host runs the app, resuming it after a stall
+pub(crate) fn beat(app: &mut App) {
+    let world = app.world_mut();
+    let Some(stalled_for) = world
+        .get_resource::<Heartbeat>()
+        .and_then(|heartbeat| heartbeat.beat())
+    else {
+        return;
+    };
+    log::info!("Updates resumed after {stalled_for:?}");
+
+    // Real time passes over the gap here, so the update's virtual delta starts now
+    if world
+        .get_resource::<TimeUpdateStrategy>()
+        .is_some_and(|strategy| matches!(strategy, TimeUpdateStrategy::Automatic))
+        && let Some(mut real) = world.get_resource_mut::<Time<Real>>()
+    {
+        real.update();
+    }
+
+    world.write_message(UpdatesResumed { stalled_for });
+    if let Some(channel) = world.get_resource::<HostChannel>() {
+        let mut payload = ByteWriter::new();
+        payload.u32(stalled_for.as_millis().min(u32::MAX as u128) as u32);
+        channel.send_reserved(ReservedTopic::UpdatesResumed, &payload.finish());
+    }
+}
@@ -0,0 +1,336 @@
+//! Suspending apps to bytes while they are off screen
+//!
+//! A feed or dashboard with a Bevy widget in every cell can't keep them all alive: each
+//! holds a surface, GPU memory and render pipelines. Hosts keep only the visible widgets
+//! alive and hibernate the others. Apps declare the state that survives hibernation,
+//! such as a camera position or the selected item, with a system saving it to bytes and
+//! one restoring it:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::HibernateApp;
+//!
+//! # let mut app = App::new();
+//! app.hibernate_state("camera", save_camera, restore_camera);
+//!
+//! fn save_camera(camera: Single<&Transform, With<Camera>>) -> Vec<u8> {
+//!     camera.translation.to_array().iter().flat_map(|v| v.to_le_bytes()).collect()
+//! }
+//!
+//! fn restore_camera(In(bytes): In<Vec<u8>>, mut camera: Single<&mut Transform, With<Camera>>) {
+//!     let mut values = bytes.chunks_exact(4).map(|v| f32::from_le_bytes(v.try_into().unwrap()));
+//!     if let (Some(x), Some(y), Some(z)) = (values.next(), values.next(), values.next()) {
+//!         camera.translation = Vec3::new(x, y, z);
+//!     }
+//! }
+//! ```
+//!
+//! Hibernating (`bevy_embedded_ios_hibernate` / `nativeHibernate`) runs every save system,
+//! encodes the results into a [`HibernatedState`] for the host to keep, and destroys the
+//! app, releasing its surface and GPU resources. To rehydrate, the host creates an app,
+//! preferably by taking one from the [preload pool](crate::preload), and passes it the
+//! bytes (`bevy_embedded_ios_rehydrate` / `nativeRehydrate`). The restore systems run in
+//! its next update, after `Startup` spawned the scene, so the first frame shows the
+//! restored state. State saved under a key the app no longer declares is skipped.
+//!
+//! Restoring is done by [`HibernationPlugin`], which declaring the first state adds.
+
+use bevy::{
+    app::{App, First, Plugin},
+    ecs::{
+        resource::Resource,
+        schedule::{IntoScheduleConfigs, common_conditions::resource_exists},
+        system::{In, IntoSystem, SystemId},
+        world::World,
+    },
+};
+
+use crate::protocol::{ByteReader, ByteWriter};
+
+/// Restores hibernated state passed by the host; see the [module docs](self)
+#[derive(Default)]
+pub struct HibernationPlugin;
+
+impl Plugin for HibernationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            First,
+            apply_rehydration.run_if(resource_exists::<Rehydration>),
+        );
+    }
+}
+
+/// Identifies encoded hibernated state
+const STATE_MAGIC: &[u8; 4] = b"BEHS";
+
+/// Version of the encoded state layout
+const STATE_VERSION: u16 = 1;
+
+/// State of a hibernated app, saved by the systems declared with [`HibernateApp`]
+///
+/// Encoded as:
+///
+/// ```text
+/// "BEHS" | version: u16 | u16 × (key: string | len: u32 | state: len × u8)
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HibernatedState {
+    /// Saved bytes by key, in the order the app declared them
+    pub entries: Vec<(String, Vec<u8>)>,
+}
+
+impl HibernatedState {
+    /// Encodes the state for the host to keep
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+        writer
+            .bytes(STATE_MAGIC)
+            .u16(STATE_VERSION)
+            .u16(self.entries.len().min(u16::MAX as usize) as u16);
+        for (key, state) in self.entries.iter().take(u16::MAX as usize) {
+            writer.string(key).u32(state.len() as u32).bytes(state);
+        }
+        writer.finish()
+    }
+
+    /// Decodes state, returning `None` if it is malformed or from another version
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.bytes(STATE_MAGIC.len())? != STATE_MAGIC || reader.u16()? != STATE_VERSION {
+            return None;
+        }
+        let count = reader.u16()?;
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let key = reader.string()?.to_owned();
+            let len = reader.u32()? as usize;
+            entries.push((key, reader.bytes(len)?.to_vec()));
+        }
+        reader.is_empty().then_some(Self { entries })
+    }
+}
+
+struct StateSystems {
+    key: String,
+    save: SystemId<(), Vec<u8>>,
+    restore: SystemId<In<Vec<u8>>>,
+}
+
+/// State declared with [`HibernateApp`]
+#[derive(Resource, Default)]
+struct HibernationRegistry {
+    states: Vec<StateSystems>,
+}
+
+/// State passed by the host, restored in the next update
+#[derive(Resource)]
+pub(crate) struct Rehydration(HibernatedState);
+
+/// Extension trait for declaring the state that survives hibernation
+pub trait HibernateApp {
+    /// Saves state under `key` with the one-shot system `save` when the app hibernates,
+    /// and restores it with `restore` when it is rehydrated
+    ///
+    /// Declaring a key again replaces its systems. Adds [`HibernationPlugin`] if needed.
+    fn hibernate_state<M1, M2>(
+        &mut self,
+        key: impl Into<String>,
+        save: impl IntoSystem<(), Vec<u8>, M1> + 'static,
+        restore: impl IntoSystem<In<Vec<u8>>, (), M2> + 'static,
+    ) -> &mut Self;
+}
+
+impl HibernateApp for App {
+    fn hibernate_state<M1, M2>(
+        &mut self,
+        key: impl Into<String>,
+        save: impl IntoSystem<(), Vec<u8>, M1> + 'static,
+        restore: impl IntoSystem<In<Vec<u8>>, (), M2> + 'static,
+    ) -> &mut Self {
+        if !self.is_plugin_added::<HibernationPlugin>() {
+            self.add_plugins(HibernationPlugin);
+        }
+        let world = self.world_mut();
+        let systems = StateSystems {
+            key: key.into(),
+            save: world.register_system(save),
+            restore: world.register_system(restore),
+        };
+        let mut registry = world.get_resource_or_init::<HibernationRegistry>();
+        match registry
+            .states
+            .iter_mut()
+            .find(|state| state.key == systems.key)
+        {
+            Some(state) => *state = systems,
+            None => registry.states.push(systems),
+        }
+        self
+    }
+}
+
+/// Runs every save system, collecting the app's state
+///
+/// A save system that fails is logged and left out.
+pub(crate) fn save(world: &mut World) -> HibernatedState {
+    let systems: Vec<_> = world
+        .get_resource::<HibernationRegistry>()
+        .map(|registry| {
+            registry
+                .states
+                .iter()
+                .map(|state| (state.key.clone(), state.save))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut entries = Vec::with_capacity(systems.len());
+    for (key, save) in systems {
+        match world.run_system(save) {
+            Ok(state) => entries.push((key, state)),
+            Err(error) => log::warn!("Saving `{key}` for hibernation failed: {error}"),
+        }
+    }
+    HibernatedState { entries }
+}
+
+/// Queues `state` to be restored in the app's next update
+///
+/// Without [`HibernationPlugin`] the app declares no state, so every key is skipped.
+pub(crate) fn rehydrate(app: &mut App, state: HibernatedState) {
+    if !app.is_plugin_added::<HibernationPlugin>() {
+        for (key, _) in &state.entries {
+            log::warn!("Skipping hibernated state `{key}` the app doesn't declare");
+        }
+        return;
+    }
+    app.insert_resource(Rehydration(state));
+}
+
+/// Runs the restore systems for state passed by the host
+fn apply_rehydration(world: &mut World) {
+    let Some(Rehydration(state)) = world.remove_resource::<Rehydration>() else {
+        return;
+    };
+
+    for (key, bytes) in state.entries {
+        let restore = world
+            .get_resource::<HibernationRegistry>()
+            .and_then(|registry| registry.states.iter().find(|state| state.key == key))
+            .map(|state| state.restore);
+        let Some(restore) = restore else {
+            log::warn!("Skipping hibernated state `{key}` the app doesn't declare");
+            continue;
+        };
+        if let Err(error) = world.run_system_with(restore, bytes) {
+            log::warn!("Restoring `{key}` after hibernation failed: {error}");
+        }
+    }
+    log::info!("Rehydrated app");
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        MinimalPlugins,
+        app::Update,
+        ecs::system::{Res, ResMut},
+    };
+
+    use super::*;
+    use crate::{
+        ffi,
+        test::{MockHost, MockSurface},
+    };
+
+    #[derive(Resource, Default)]
+    struct Selected(u32);
+
+    /// Frames in which `Selected` held the restored value
+    #[derive(Resource, Default)]
+    struct RestoredFrames(u32);
+
+    fn save_selected(selected: Res<Selected>) -> Vec<u8> {
+        selected.0.to_le_bytes().to_vec()
+    }
+
+    fn restore_selected(In(bytes): In<Vec<u8>>, mut selected: ResMut<Selected>) {
+        selected.0 = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    fn count_restored(selected: Res<Selected>, mut frames: ResMut<RestoredFrames>) {
+        frames.0 += (selected.0 == 42) as u32;
+    }
+
+    fn host() -> MockHost {
+        MockHost::from_setup(MockSurface::default(), |app| {
+            app.add_plugins(MinimalPlugins)
+                .init_resource::<Selected>()
+                .init_resource::<RestoredFrames>()
+                .hibernate_state("selected", save_selected, restore_selected)
+                .add_systems(Update, count_restored);
+        })
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let state = HibernatedState {
+            entries: vec![("camera".into(), vec![1, 2, 3]), ("empty".into(), vec![])],
+        };
+        let encoded = state.encode();
+        assert_eq!(HibernatedState::decode(&encoded), Some(state));
+        for len in 0..encoded.len() {
+            assert_eq!(
+                HibernatedState::decode(&encoded[..len]),
+                None,
+                "{len} bytes"
+            );
+        }
+        assert_eq!(
+            HibernatedState::decode(&[&encoded[..], &[0]].concat()),
+            None
+        );
+    }
+
+    #[test]
+    fn restores_state_in_a_new_app_before_its_first_frame() {
+        let mut hibernated = host();
+        hibernated.update();
+        hibernated
+            .app_mut()
+            .world_mut()
+            .resource_mut::<Selected>()
+            .0 = 42;
+        let state = ffi::hibernate(hibernated.app_mut());
+
+        let mut rehydrated = host();
+        let mut unknown = HibernatedState::decode(&state).unwrap();
+        unknown.entries.push(("removed".into(), vec![1]));
+        ffi::rehydrate(rehydrated.app_mut(), &unknown.encode()).unwrap();
+        rehydrated.update();
+        assert_eq!(rehydrated.app().world().resource::<RestoredFrames>().0, 1);
+        assert!(!rehydrated.app().world().contains_resource::<Rehydration>());
+
+        assert_eq!(
+            ffi::rehydrate(rehydrated.app_mut(), b"not state"),
+            Err(ffi::FfiViolation::InvalidHibernatedState)
+        );
+    }
+
+    #[test]
+    fn apps_without_declared_state_skip_it() {
+        let mut host = MockHost::from_setup(MockSurface::default(), |app| {
+            app.add_plugins(MinimalPlugins);
+        });
+        let state = HibernatedState {
+            entries: vec![("selected".into(), vec![0; 4])],
+        };
+        assert_eq!(
+            ffi::hibernate(host.app_mut()),
+            HibernatedState::default().encode()
+        );
+        ffi::rehydrate(host.app_mut(), &state.encode()).unwrap();
+        assert!(!host.app().world().contains_resource::<Rehydration>());
+        host.update();
+    }
+}
@@ -0,0 +1,343 @@
+//! World snapshots for undo and redo
+//!
+//! Apps register the components and resources that make up their document state with
+//! [`SnapshotApp`]. A [`WorldSnapshot`] holds a copy of every registered value and can
+//! put them back later, and [`UndoHistory`] keeps the undo and redo stacks of snapshots.
+//! [`HistoryPlugin`] connects the history to the host:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{SnapshotApp, UndoHistory};
+//!
+//! # let mut app = App::new();
+//! app.add_plugins(bevy_embedded::HistoryPlugin)
+//!     .snapshot_component::<Transform>();
+//!
+//! fn before_edit(mut commands: Commands) {
+//!     // Record the state the next undo returns to
+//!     commands.queue(|world: &mut World| UndoHistory::checkpoint(world));
+//! }
+//! ```
+//!
+//! Restoring puts registered components back on the entities that had them and removes
+//! them from entities that did not. Entities are not spawned or despawned, so entities
+//! despawned since the snapshot do not come back.
+//!
+//! Hosts drive the history from their native undo UI with [`ReservedTopic::History`]
+//! commands, a sequence of [`HistoryOp`] bytes. After every change the host receives
+//! [`ReservedTopic::HistoryChanged`] with `undo: u32 | redo: u32`, the number of steps
+//! available in each direction.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashSet,
+};
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        message::{MessageCursor, Messages},
+        query::With,
+        resource::Resource,
+        system::{Local, Res},
+        world::World,
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Applies host history commands and reports history changes; see the [module docs](self)
+#[derive(Default)]
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UndoHistory>()
+            .add_systems(PreUpdate, receive_history_commands)
+            .add_systems(Last, report_history_changes);
+    }
+}
+
+/// Operations that can appear in a history command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HistoryOp {
+    /// Record the current state as an undo step
+    Checkpoint = 0,
+    /// Go back one step
+    Undo = 1,
+    /// Go forward one undone step
+    Redo = 2,
+    /// Forget every step
+    Clear = 3,
+}
+
+impl HistoryOp {
+    /// Create a HistoryOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HistoryOp::Checkpoint),
+            1 => Some(HistoryOp::Undo),
+            2 => Some(HistoryOp::Redo),
+            3 => Some(HistoryOp::Clear),
+            _ => None,
+        }
+    }
+}
+
+/// Copied values of one registered type
+type SnapshotPart = Box<dyn Any + Send + Sync>;
+
+/// How to copy and put back one registered type
+#[derive(Clone, Copy)]
+struct SnapshotType {
+    id: TypeId,
+    capture: fn(&mut World) -> SnapshotPart,
+    restore: fn(&mut World, &SnapshotPart),
+}
+
+/// Types included in snapshots
+#[derive(Resource, Default)]
+struct SnapshotRegistry {
+    types: Vec<SnapshotType>,
+}
+
+fn capture_component<T: Component + Clone>(world: &mut World) -> SnapshotPart {
+    let values: Vec<(Entity, T)> = world
+        .query::<(Entity, &T)>()
+        .iter(world)
+        .map(|(entity, value)| (entity, value.clone()))
+        .collect();
+    Box::new(values)
+}
+
+fn restore_component<T: Component + Clone>(world: &mut World, part: &SnapshotPart) {
+    let Some(values) = part.downcast_ref::<Vec<(Entity, T)>>() else {
+        return;
+    };
+
+    let kept: HashSet<Entity> = values.iter().map(|(entity, _)| *entity).collect();
+    let current: Vec<Entity> = world
+        .query_filtered::<Entity, With<T>>()
+        .iter(world)
+        .collect();
+    for entity in current {
+        if !kept.contains(&entity) {
+            world.entity_mut(entity).remove::<T>();
+        }
+    }
+    for (entity, value) in values {
+        if let Ok(mut entity) = world.get_entity_mut(*entity) {
+            entity.insert(value.clone());
+        }
+    }
+}
+
+fn capture_resource<R: Resource + Clone>(world: &mut World) -> SnapshotPart {
+    Box::new(world.get_resource::<R>().cloned())
+}
+
+fn restore_resource<R: Resource + Clone>(world: &mut World, part: &SnapshotPart) {
+    match part.downcast_ref::<Option<R>>() {
+        Some(Some(value)) => world.insert_resource(value.clone()),
+        Some(None) => {
+            world.remove_resource::<R>();
+        }
+        None => {}
+    }
+}
+
+/// Registers the state that snapshots capture
+pub trait SnapshotApp {
+    /// Includes every `T` component in snapshots
+    fn snapshot_component<T: Component + Clone>(&mut self) -> &mut Self;
+
+    /// Includes the `R` resource in snapshots
+    fn snapshot_resource<R: Resource + Clone>(&mut self) -> &mut Self;
+}
+
+impl SnapshotApp for App {
+    fn snapshot_component<T: Component + Clone>(&mut self) -> &mut Self {
+        register_type(
+            self.world_mut(),
+            SnapshotType {
+                id: TypeId::of::<T>(),
+                capture: capture_component::<T>,
+                restore: restore_component::<T>,
+            },
+        );
+        self
+    }
+
+    fn snapshot_resource<R: Resource + Clone>(&mut self) -> &mut Self {
+        register_type(
+            self.world_mut(),
+            SnapshotType {
+                id: TypeId::of::<R>(),
+                capture: capture_resource::<R>,
+                restore: restore_resource::<R>,
+            },
+        );
+        self
+    }
+}
+
+fn register_type(world: &mut World, snapshot_type: SnapshotType) {
+    let mut registry = world.get_resource_or_init::<SnapshotRegistry>();
+    if !registry.types.iter().any(|t| t.id == snapshot_type.id) {
+        registry.types.push(snapshot_type);
+    }
+}
+
+/// Copy of the registered components and resources at one point in time
+pub struct WorldSnapshot {
+    parts: Vec<(SnapshotType, SnapshotPart)>,
+}
+
+impl WorldSnapshot {
+    /// Copies the registered state of `world`
+    pub fn take(world: &mut World) -> Self {
+        let types = world
+            .get_resource::<SnapshotRegistry>()
+            .map(|registry| registry.types.clone())
+            .unwrap_or_default();
+        let parts = types
+            .into_iter()
+            .map(|snapshot_type| (snapshot_type, (snapshot_type.capture)(world)))
+            .collect();
+        Self { parts }
+    }
+
+    /// Puts the copied state back into `world`
+    pub fn restore(&self, world: &mut World) {
+        for (snapshot_type, part) in &self.parts {
+            (snapshot_type.restore)(world, part);
+        }
+    }
+}
+
+/// Undo and redo stacks of world snapshots
+#[derive(Resource)]
+pub struct UndoHistory {
+    /// Most undo steps kept; the oldest are dropped first
+    pub limit: usize,
+    undo: Vec<WorldSnapshot>,
+    redo: Vec<WorldSnapshot>,
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self {
+            limit: 64,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl UndoHistory {
+    /// Number of steps that can be undone
+    pub fn undo_depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Number of undone steps that can be redone
+    pub fn redo_depth(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Forgets every step
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Records the current state of `world` as the state the next undo returns to
+    ///
+    /// Call before making a change. Discards the redo steps.
+    pub fn checkpoint(world: &mut World) {
+        let snapshot = WorldSnapshot::take(world);
+        let mut history = world.get_resource_or_init::<UndoHistory>();
+        history.undo.push(snapshot);
+        let excess = history.undo.len().saturating_sub(history.limit);
+        history.undo.drain(..excess);
+        history.redo.clear();
+    }
+
+    /// Returns `world` to the last checkpoint, returning false if there is nothing to undo
+    pub fn undo(world: &mut World) -> bool {
+        let Some(snapshot) = world
+            .get_resource_mut::<UndoHistory>()
+            .and_then(|mut history| history.undo.pop())
+        else {
+            return false;
+        };
+        let current = WorldSnapshot::take(world);
+        snapshot.restore(world);
+        world.resource_mut::<UndoHistory>().redo.push(current);
+        true
+    }
+
+    /// Reapplies the last undone step, returning false if there is nothing to redo
+    pub fn redo(world: &mut World) -> bool {
+        let Some(snapshot) = world
+            .get_resource_mut::<UndoHistory>()
+            .and_then(|mut history| history.redo.pop())
+        else {
+            return false;
+        };
+        let current = WorldSnapshot::take(world);
+        snapshot.restore(world);
+        world.resource_mut::<UndoHistory>().undo.push(current);
+        true
+    }
+}
+
+/// Applies history commands from the host
+fn receive_history_commands(world: &mut World, mut cursor: Local<MessageCursor<HostCommand>>) {
+    let ops: Vec<u8> = cursor
+        .read(world.resource::<Messages<HostCommand>>())
+        .filter(|command| command.topic == ReservedTopic::History)
+        .flat_map(|command| command.payload.iter().copied())
+        .collect();
+
+    for op in ops {
+        match HistoryOp::from_u8(op) {
+            Some(HistoryOp::Checkpoint) => UndoHistory::checkpoint(world),
+            Some(HistoryOp::Undo) => {
+                if !UndoHistory::undo(world) {
+                    log::debug!("Nothing to undo");
+                }
+            }
+            Some(HistoryOp::Redo) => {
+                if !UndoHistory::redo(world) {
+                    log::debug!("Nothing to redo");
+                }
+            }
+            Some(HistoryOp::Clear) => world.resource_mut::<UndoHistory>().clear(),
+            None => {
+                log::warn!("Ignoring history command with unknown operation {op}");
+                break;
+            }
+        }
+    }
+}
+
+/// Tells the host how far it can undo and redo whenever the history changes
+fn report_history_changes(channel: Res<HostChannel>, history: Res<UndoHistory>) {
+    if !history.is_changed() || history.is_added() {
+        return;
+    }
+
+    let mut payload = ByteWriter::new();
+    payload
+        .u32(history.undo_depth() as u32)
+        .u32(history.redo_depth() as u32);
+    channel.send_reserved(ReservedTopic::HistoryChanged, &payload.finish());
+}
@@ -0,0 +1,59 @@
+//! In-memory asset source filled by the host
+//!
+//! Bytes the host hands over at runtime (models, images, ...) are stored under the
+//! `host://` asset source so they load through the `AssetServer` like any other asset,
+//! with the usual loaders and dependency handling. The source is registered by
+//! `EmbeddedPlugin`, which must be added before `AssetPlugin` for it to be available.
+
+use std::path::Path;
+
+use bevy::{
+    app::App,
+    asset::{
+        AssetApp, AssetPath, AssetServer,
+        io::{
+            AssetSourceBuilder,
+            memory::{Dir, MemoryAssetReader},
+        },
+    },
+    ecs::resource::Resource,
+};
+
+/// Name of the asset source holding host-provided bytes
+pub const HOST_ASSET_SOURCE: &str = "host";
+
+/// Assets provided by the host, readable through `host://` paths
+#[derive(Resource, Clone, Default)]
+pub struct HostAssets {
+    root: Dir,
+}
+
+impl HostAssets {
+    /// Stores `bytes` at `path`, returning the path to load it from
+    pub fn insert(&self, path: impl AsRef<Path>, bytes: Vec<u8>) -> AssetPath<'static> {
+        let path = path.as_ref().to_path_buf();
+        self.root.insert_asset(&path, bytes);
+        AssetPath::from(path).with_source(HOST_ASSET_SOURCE)
+    }
+
+    /// Removes the bytes stored at `path`; assets already loaded from them stay loaded
+    pub fn remove(&self, path: impl AsRef<Path>) {
+        self.root.remove_asset(path.as_ref());
+    }
+}
+
+/// Registers the `host://` asset source, if `AssetPlugin` has not been built yet
+pub(crate) fn register_host_asset_source(app: &mut App) {
+    if app.world().contains_resource::<AssetServer>() {
+        log::warn!("EmbeddedPlugin was added after AssetPlugin; host:// assets are unavailable");
+        return;
+    }
+
+    let host_assets = HostAssets::default();
+    let root = host_assets.root.clone();
+    app.insert_resource(host_assets).register_asset_source(
+        HOST_ASSET_SOURCE,
+        AssetSourceBuilder::default()
+            .with_reader(move || Box::new(MemoryAssetReader { root: root.clone() })),
+    );
+}
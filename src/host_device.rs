@@ -0,0 +1,325 @@
+//! Rendering on the host's GPU device
+//!
+//! By default Bevy creates its own wgpu instance and device. A host that already renders
+//! with Metal or Vulkan can hand Bevy its own device instead
+//! (`bevy_embedded_ios_set_host_device`, `bevy_embedded_android_set_host_device`), so
+//! the process keeps a single GPU context and textures created by either side can be
+//! used by the other without copies, e.g. as [host frames](crate::HostFrame).
+//!
+//! The device is kept for the rest of the process and used by every app created after
+//! it was set. Apps pick it up through their `RenderPlugin`:
+//!
+//! ```ignore
+//! app.add_plugins(DefaultPlugins.build().disable::<WinitPlugin>().with_host_device());
+//! ```
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    app::PluginGroupBuilder,
+    render::{
+        RenderPlugin,
+        renderer::{
+            RenderAdapter, RenderAdapterInfo, RenderDevice, RenderInstance, RenderQueue,
+            WgpuWrapper,
+        },
+        settings::{RenderCreation, RenderResources},
+    },
+};
+
+/// Render resources wrapping the host's device, shared by all apps
+static HOST_DEVICE: Mutex<Option<RenderResources>> = Mutex::new(None);
+
+/// Why the host's device could not be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostDeviceError {
+    /// A handle the host passed was null
+    NullHandle(&'static str),
+    /// The GPU API could not be loaded
+    Loader(String),
+    /// wgpu does not support the host's adapter
+    UnsupportedAdapter,
+    /// The host's device lacks extensions wgpu needs
+    MissingExtensions(Vec<String>),
+    /// wgpu could not create a device from the host's handles
+    Device(String),
+}
+
+impl fmt::Display for HostDeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NullHandle(handle) => write!(f, "Host passed a null {handle}"),
+            Self::Loader(error) => write!(f, "Failed to load the GPU API: {error}"),
+            Self::UnsupportedAdapter => write!(f, "The host's GPU adapter is not supported"),
+            Self::MissingExtensions(extensions) => write!(
+                f,
+                "The host's device lacks required extensions: {}",
+                extensions.join(", ")
+            ),
+            Self::Device(error) => write!(f, "Failed to use the host's device: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for HostDeviceError {}
+
+/// Makes apps created from now on render with the given wgpu objects
+///
+/// The platform entry points build these from the host's native handles; Rust hosts
+/// that already have wgpu objects can pass them directly.
+pub fn set_host_device(
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+) {
+    let resources = RenderResources(
+        RenderDevice::from(device),
+        RenderQueue(Arc::new(WgpuWrapper::new(queue))),
+        RenderAdapterInfo(WgpuWrapper::new(adapter.get_info())),
+        RenderAdapter(Arc::new(WgpuWrapper::new(adapter))),
+        RenderInstance(Arc::new(WgpuWrapper::new(instance))),
+    );
+    if let Ok(mut host_device) = HOST_DEVICE.lock() {
+        *host_device = Some(resources);
+    }
+}
+
+/// Makes apps created from now on create their own device again
+///
+/// Apps already running keep rendering with the host's device.
+pub fn clear_host_device() {
+    if let Ok(mut host_device) = HOST_DEVICE.lock() {
+        *host_device = None;
+    }
+}
+
+/// Returns true if the host set its device
+pub fn has_host_device() -> bool {
+    HOST_DEVICE.lock().is_ok_and(|device| device.is_some())
+}
+
+/// How `RenderPlugin` should create its resources: from the host's device if the host
+/// set one, otherwise on its own
+pub fn host_render_creation() -> RenderCreation {
+    HOST_DEVICE
+        .lock()
+        .ok()
+        .and_then(|device| device.clone())
+        .map(RenderCreation::Manual)
+        .unwrap_or_default()
+}
+
+/// Configures a plugin group's `RenderPlugin` to render with the host's device
+pub trait HostDevicePlugins {
+    /// Replaces the group's `RenderPlugin` with one rendering on the host's device
+    ///
+    /// Does nothing if the host did not set a device or the group has no
+    /// `RenderPlugin`. Other `RenderPlugin` settings are reset to their defaults, so
+    /// apps customizing the plugin should set [`host_render_creation`] themselves.
+    fn with_host_device(self) -> Self;
+}
+
+impl HostDevicePlugins for PluginGroupBuilder {
+    fn with_host_device(self) -> Self {
+        if !has_host_device() || !self.contains::<RenderPlugin>() {
+            return self;
+        }
+        self.set(RenderPlugin {
+            render_creation: host_render_creation(),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(not(target_vendor = "apple"))]
+pub use vulkan::*;
+
+/// Wrapping a `VkDevice` created by the host
+#[cfg(not(target_vendor = "apple"))]
+mod vulkan {
+    use std::ffi::{CStr, c_char};
+
+    use ash::vk::{self, Handle};
+    use wgpu::hal::api::Vulkan;
+
+    use super::HostDeviceError;
+
+    /// Vulkan handles of the host's device
+    ///
+    /// The extension lists are the ones the host enabled when creating its instance
+    /// and device; wgpu only uses features those extensions provide. The handles must
+    /// outlive every app rendering with them.
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy)]
+    pub struct HostVulkanDevice {
+        /// The host's `vkGetInstanceProcAddr`, or null to load the system Vulkan library
+        pub get_instance_proc_addr: Option<vk::PFN_vkGetInstanceProcAddr>,
+        /// `VkInstance`
+        pub instance: u64,
+        /// `VkPhysicalDevice` the device was created on
+        pub physical_device: u64,
+        /// `VkDevice`
+        pub device: u64,
+        /// `apiVersion` the instance was created with
+        pub api_version: u32,
+        /// Family of the queue Bevy submits to
+        pub queue_family_index: u32,
+        /// Index of the queue Bevy submits to within its family
+        pub queue_index: u32,
+        /// Names of the enabled instance extensions
+        pub instance_extensions: *const *const c_char,
+        /// Number of enabled instance extensions
+        pub instance_extension_count: u32,
+        /// Names of the enabled device extensions
+        pub device_extensions: *const *const c_char,
+        /// Number of enabled device extensions
+        pub device_extension_count: u32,
+    }
+
+    /// Copies a C array of extension names
+    ///
+    /// # Safety
+    ///
+    /// `names` must point to `count` valid C strings, or be null with `count` zero.
+    unsafe fn extension_names<'a>(names: *const *const c_char, count: u32) -> Vec<&'a CStr> {
+        if names.is_null() {
+            return Vec::new();
+        }
+        unsafe { std::slice::from_raw_parts(names, count as usize) }
+            .iter()
+            .filter(|name| !name.is_null())
+            .map(|&name| unsafe { CStr::from_ptr(name) })
+            .collect()
+    }
+
+    /// Makes apps created from now on render with the host's Vulkan device
+    ///
+    /// Bevy submits to the host's queue, so the host must not submit to it while an
+    /// app updates. Neither the instance nor the device is destroyed by Bevy.
+    ///
+    /// # Safety
+    ///
+    /// `host` must describe a valid instance, physical device and device, created with
+    /// the listed extensions.
+    pub unsafe fn set_host_vulkan_device(host: &HostVulkanDevice) -> Result<(), HostDeviceError> {
+        if host.instance == 0 {
+            return Err(HostDeviceError::NullHandle("VkInstance"));
+        }
+        if host.physical_device == 0 {
+            return Err(HostDeviceError::NullHandle("VkPhysicalDevice"));
+        }
+        if host.device == 0 {
+            return Err(HostDeviceError::NullHandle("VkDevice"));
+        }
+
+        let entry = match host.get_instance_proc_addr {
+            Some(get_instance_proc_addr) => unsafe {
+                ash::Entry::from_static_fn(ash::StaticFn {
+                    get_instance_proc_addr,
+                })
+            },
+            None => unsafe { ash::Entry::load() }
+                .map_err(|error| HostDeviceError::Loader(error.to_string()))?,
+        };
+        let raw_instance = unsafe {
+            ash::Instance::load(entry.static_fn(), vk::Instance::from_raw(host.instance))
+        };
+
+        // wgpu only uses the instance extensions it wants that the host enabled
+        let host_instance_extensions =
+            unsafe { extension_names(host.instance_extensions, host.instance_extension_count) };
+        let flags = wgpu::InstanceFlags::from_build_config();
+        let instance_extensions =
+            wgpu::hal::vulkan::Instance::desired_extensions(&entry, host.api_version, flags)
+                .map_err(|error| HostDeviceError::Loader(error.to_string()))?
+                .into_iter()
+                .filter(|extension| host_instance_extensions.contains(extension))
+                .collect();
+
+        let hal_instance = unsafe {
+            wgpu::hal::vulkan::Instance::from_raw(
+                entry,
+                raw_instance.clone(),
+                host.api_version,
+                // Only selects swapchain workarounds; 0 picks the conservative ones
+                0,
+                None,
+                instance_extensions,
+                flags,
+                wgpu::MemoryBudgetThresholds::default(),
+                false,
+                Some(Box::new(|| {})),
+            )
+        }
+        .map_err(|error| HostDeviceError::Device(error.to_string()))?;
+        let physical_device = vk::PhysicalDevice::from_raw(host.physical_device);
+        let exposed = hal_instance
+            .expose_adapter(physical_device)
+            .ok_or(HostDeviceError::UnsupportedAdapter)?;
+
+        // Enable the adapter features whose extensions the host's device has
+        let host_device_extensions =
+            unsafe { extension_names(host.device_extensions, host.device_extension_count) };
+        let hal_adapter = &exposed.adapter;
+        let required = hal_adapter.required_device_extensions(wgpu::Features::empty());
+        let missing: Vec<String> = required
+            .iter()
+            .filter(|extension| !host_device_extensions.contains(extension))
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .collect();
+        if !missing.is_empty() {
+            return Err(HostDeviceError::MissingExtensions(missing));
+        }
+        let features = exposed
+            .features
+            .iter()
+            .filter(|feature| {
+                hal_adapter
+                    .required_device_extensions(*feature)
+                    .iter()
+                    .all(|extension| host_device_extensions.contains(extension))
+            })
+            .fold(wgpu::Features::empty(), |features, feature| {
+                features | feature
+            });
+        let enabled_extensions = hal_adapter.required_device_extensions(features);
+
+        let raw_device =
+            unsafe { ash::Device::load(raw_instance.fp_v1_0(), vk::Device::from_raw(host.device)) };
+        let open_device = unsafe {
+            hal_adapter.device_from_raw(
+                raw_device,
+                Some(Box::new(|| {})),
+                &enabled_extensions,
+                features,
+                &wgpu::MemoryHints::default(),
+                host.queue_family_index,
+                host.queue_index,
+            )
+        }
+        .map_err(|error| HostDeviceError::Device(error.to_string()))?;
+
+        let instance = unsafe { wgpu::Instance::from_hal::<Vulkan>(hal_instance) };
+        let limits = exposed.capabilities.limits.clone();
+        let adapter = unsafe { instance.create_adapter_from_hal(exposed) };
+        let (device, queue) = unsafe {
+            adapter.create_device_from_hal(
+                open_device,
+                &wgpu::DeviceDescriptor {
+                    label: Some("host_device"),
+                    required_features: features,
+                    required_limits: limits,
+                    ..Default::default()
+                },
+            )
+        }
+        .map_err(|error| HostDeviceError::Device(error.to_string()))?;
+
+        super::set_host_device(instance, adapter, device, queue);
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+//! Remote assets downloaded by the host in the background
+//!
+//! Content packs and large models take long enough to fetch that the user may leave the
+//! app halfway. The platforms' background transfer APIs, such as a background
+//! `URLSession` on iOS or WorkManager on Android, keep downloading while the app is
+//! suspended or even killed, so assets loaded from the `download://` source are fetched
+//! by the host with them:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//!
+//! fn load_sky(asset_server: Res<AssetServer>, mut sky: Local<Handle<Image>>) {
+//!     *sky = asset_server.load("download://packs/winter/sky.ktx2");
+//! }
+//! ```
+//!
+//! The first read of a path sends [`ReservedTopic::DownloadRequest`] with the path,
+//! `path: string`, and the load waits until the host reports the download on
+//! [`ReservedTopic::Download`], a sequence of operations each starting with a
+//! [`DownloadOp`] byte:
+//!
+//! ```text
+//! Progress: path: string | received: u64 | total: u64
+//! Finished: path: string | file: string
+//! Failed:   path: string | error: string
+//! ```
+//!
+//! Strings are `u32 len + UTF-8`. The host maps paths to URLs, and `file` is where it
+//! stored the download on the device, which the crate then reads like any other asset.
+//! `total` is 0 when unknown. Progress is delivered as [`AssetDownloadProgress`]
+//! messages, and a failure fails the load, which is also reported as an
+//! [asset error](crate::AssetErrorKind).
+//!
+//! Hosts should keep finished files and answer a request for one right away, so a
+//! recreated app picks up downloads that finished while it was gone. A failed download is
+//! requested again when the asset is reloaded. The source is registered by
+//! [`HostDownloadsPlugin`], which must be added before `AssetPlugin` for it to be
+//! available.
+
+use std::{
+    collections::HashMap,
+    future::poll_fn,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    asset::{
+        AssetApp, AssetServer,
+        io::{AssetReader, AssetReaderError, AssetSourceBuilder, PathStream, Reader, VecReader},
+    },
+    ecs::{
+        message::{Message, MessageReader, MessageWriter},
+        resource::Resource,
+        system::Res,
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Name of the asset source downloaded by the host
+pub const DOWNLOAD_ASSET_SOURCE: &str = "download";
+
+/// Operations in a download report from the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DownloadOp {
+    /// Bytes received so far
+    Progress = 0,
+    /// The download is stored in a local file
+    Finished = 1,
+    /// The download failed
+    Failed = 2,
+}
+
+impl DownloadOp {
+    /// Create a DownloadOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(DownloadOp::Progress),
+            1 => Some(DownloadOp::Finished),
+            2 => Some(DownloadOp::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Sent when the host reports progress of a download
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct AssetDownloadProgress {
+    /// Path of the asset in the `download://` source
+    pub path: PathBuf,
+    /// Bytes received so far
+    pub received: u64,
+    /// Size of the download, if the host knows it
+    pub total: Option<u64>,
+}
+
+#[derive(Debug)]
+enum Download {
+    /// Waiting for the host, with the loads to wake
+    Pending(Vec<Waker>),
+    Finished(PathBuf),
+    Failed(String),
+}
+
+#[derive(Debug, Default)]
+struct Downloads {
+    downloads: HashMap<PathBuf, Download>,
+    /// Paths to request from the host
+    outgoing: Vec<PathBuf>,
+}
+
+/// Downloads of `download://` assets by the host
+#[derive(Resource, Clone, Debug, Default)]
+pub struct HostDownloads {
+    state: Arc<Mutex<Downloads>>,
+}
+
+impl HostDownloads {
+    /// The local file the host downloaded `path` to, if it finished
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        let state = self.state.lock().ok()?;
+        match state.downloads.get(path.as_ref()) {
+            Some(Download::Finished(file)) => Some(file.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns true while the host is downloading `path`
+    pub fn is_downloading(&self, path: impl AsRef<Path>) -> bool {
+        self.state.lock().is_ok_and(|state| {
+            matches!(
+                state.downloads.get(path.as_ref()),
+                Some(Download::Pending(_))
+            )
+        })
+    }
+
+    /// Waits for the local file of `path`, asking the host for it unless it already was
+    async fn wait(&self, path: &Path) -> Result<PathBuf, AssetReaderError> {
+        let mut waiting = false;
+        poll_fn(|cx| {
+            let Ok(mut state) = self.state.lock() else {
+                return Poll::Ready(Err(AssetReaderError::NotFound(path.to_path_buf())));
+            };
+            let state = &mut *state;
+            match state.downloads.get_mut(path) {
+                Some(Download::Pending(wakers)) => {
+                    if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                        wakers.push(cx.waker().clone());
+                    }
+                    waiting = true;
+                    return Poll::Pending;
+                }
+                // Files the host has since removed are downloaded again
+                Some(Download::Finished(file)) if waiting || file.exists() => {
+                    return Poll::Ready(Ok(file.clone()));
+                }
+                // A new load of a failed download retries it
+                Some(Download::Failed(error)) if waiting => {
+                    let error = std::io::Error::other(error.clone());
+                    return Poll::Ready(Err(AssetReaderError::Io(Arc::new(error))));
+                }
+                _ => {}
+            }
+            state.downloads.insert(
+                path.to_path_buf(),
+                Download::Pending(vec![cx.waker().clone()]),
+            );
+            state.outgoing.push(path.to_path_buf());
+            waiting = true;
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Stores the outcome of a download and wakes the loads waiting for it
+    fn complete(&self, path: &Path, download: Download) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(Download::Pending(wakers)) =
+            state.downloads.insert(path.to_path_buf(), download)
+        {
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+/// Reads `download://` assets from the files the host downloaded
+struct HostDownloadReader {
+    downloads: HostDownloads,
+}
+
+impl AssetReader for HostDownloadReader {
+    async fn read<'a>(&'a self, path: &'a Path) -> Result<Box<dyn Reader + 'a>, AssetReaderError> {
+        let file = self.downloads.wait(path).await?;
+        let bytes = std::fs::read(&file).map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => AssetReaderError::NotFound(path.to_path_buf()),
+            _ => AssetReaderError::Io(Arc::new(error)),
+        })?;
+        Ok(Box::new(VecReader::new(bytes)))
+    }
+
+    async fn read_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<dyn Reader + 'a>, AssetReaderError> {
+        // Downloads have no meta files; not asking the host saves a round trip per asset
+        Err(AssetReaderError::NotFound(path.to_path_buf()))
+    }
+
+    async fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> Result<Box<PathStream>, AssetReaderError> {
+        Err(AssetReaderError::NotFound(path.to_path_buf()))
+    }
+
+    async fn is_directory<'a>(&'a self, _path: &'a Path) -> Result<bool, AssetReaderError> {
+        Ok(false)
+    }
+}
+
+/// Loads `download://` assets through the host; see the [module docs](self)
+#[derive(Default)]
+pub struct HostDownloadsPlugin;
+
+impl Plugin for HostDownloadsPlugin {
+    fn build(&self, app: &mut App) {
+        register_download_asset_source(app);
+        app.add_message::<AssetDownloadProgress>()
+            .add_systems(PreUpdate, receive_downloads)
+            .add_systems(Last, send_download_requests);
+    }
+}
+
+/// Registers the `download://` asset source, if `AssetPlugin` has not been built yet
+fn register_download_asset_source(app: &mut App) {
+    if app.world().contains_resource::<AssetServer>() {
+        log::warn!(
+            "HostDownloadsPlugin was added after AssetPlugin; download:// assets are unavailable"
+        );
+        return;
+    }
+
+    let downloads = HostDownloads::default();
+    let reader_downloads = downloads.clone();
+    app.insert_resource(downloads).register_asset_source(
+        DOWNLOAD_ASSET_SOURCE,
+        AssetSourceBuilder::default().with_reader(move || {
+            Box::new(HostDownloadReader {
+                downloads: reader_downloads.clone(),
+            })
+        }),
+    );
+}
+
+/// Applies one operation, returning `None` if the payload is malformed
+fn apply_op(
+    reader: &mut ByteReader,
+    downloads: &HostDownloads,
+    progress: &mut MessageWriter<AssetDownloadProgress>,
+) -> Option<()> {
+    let op = reader.u8()?;
+    let Some(op) = DownloadOp::from_u8(op) else {
+        log::warn!("Ignoring download report with unknown operation {op}");
+        return None;
+    };
+    let path = Path::new(reader.string()?);
+    match op {
+        DownloadOp::Progress => {
+            let received = reader.u64()?;
+            let total = reader.u64()?;
+            progress.write(AssetDownloadProgress {
+                path: path.to_path_buf(),
+                received,
+                total: (total > 0).then_some(total),
+            });
+        }
+        DownloadOp::Finished => {
+            let file = PathBuf::from(reader.string()?);
+            downloads.complete(path, Download::Finished(file));
+        }
+        DownloadOp::Failed => {
+            let error = reader.string()?.to_owned();
+            log::warn!("Download of {} failed: {error}", path.display());
+            downloads.complete(path, Download::Failed(error));
+        }
+    }
+    Some(())
+}
+
+/// Applies download reports from the host
+fn receive_downloads(
+    mut commands: MessageReader<HostCommand>,
+    downloads: Option<Res<HostDownloads>>,
+    mut progress: MessageWriter<AssetDownloadProgress>,
+) {
+    let Some(downloads) = downloads else {
+        commands.clear();
+        return;
+    };
+    for command in commands.read() {
+        if command.topic != ReservedTopic::Download {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while !reader.is_empty() {
+            if apply_op(&mut reader, &downloads, &mut progress).is_none() {
+                log::warn!("Ignoring malformed download report");
+                break;
+            }
+        }
+    }
+}
+
+/// Asks the host for the downloads loads are waiting for
+fn send_download_requests(channel: Res<HostChannel>, downloads: Option<Res<HostDownloads>>) {
+    let Some(outgoing) = downloads.as_ref().and_then(|downloads| {
+        let mut state = downloads.state.lock().ok()?;
+        Some(std::mem::take(&mut state.outgoing))
+    }) else {
+        return;
+    };
+    for path in outgoing {
+        let mut payload = ByteWriter::new();
+        payload.string(&path.to_string_lossy());
+        channel.send_reserved(ReservedTopic::DownloadRequest, &payload.finish());
+    }
+}
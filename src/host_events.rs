@@ -0,0 +1,1122 @@
+//! Typed events between the host and the app
+//!
+//! Adding a message to an app's protocol otherwise takes a hand-written encoder and
+//! decoder on each side. Deriving [`HostEvent`](derive@crate::HostEvent) declares a serde
+//! struct as an event instead, sent either by the host to Bevy or by Bevy to the host:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{EventField, HostEvent, HostEventApp};
+//! use serde::{Deserialize, Serialize};
+//!
+//! /// The user picked a product in the host's list
+//! #[derive(HostEvent, Serialize, Deserialize, Debug, Clone)]
+//! #[host_event(to_bevy)]
+//! pub struct ProductPicked {
+//!     pub id: u64,
+//!     pub name: String,
+//!     pub variant: Option<Variant>,
+//! }
+//!
+//! #[derive(EventField, Serialize, Deserialize, Debug, Clone)]
+//! pub struct Variant {
+//!     pub size: Size,
+//!     pub tags: Vec<String>,
+//! }
+//!
+//! #[derive(EventField, Serialize, Deserialize, Debug, Clone, Copy)]
+//! pub enum Size {
+//!     Small,
+//!     Large,
+//! }
+//!
+//! /// The player scored
+//! #[derive(HostEvent, Serialize, Deserialize, Debug, Clone)]
+//! #[host_event(to_host)]
+//! pub struct ScoreChanged {
+//!     pub score: u32,
+//! }
+//!
+//! fn setup(app: &mut App) {
+//!     app.add_host_event::<ProductPicked>()
+//!         .add_host_event::<ScoreChanged>()
+//!         .add_systems(Update, score_picks);
+//! }
+//!
+//! fn score_picks(
+//!     mut picked: MessageReader<ProductPicked>,
+//!     mut scores: MessageWriter<ScoreChanged>,
+//!     mut score: Local<u32>,
+//! ) {
+//!     for _ in picked.read() {
+//!         *score += 1;
+//!         scores.write(ScoreChanged { score: *score });
+//!     }
+//! }
+//! ```
+//!
+//! Events are Bevy messages: host events are written before `Update` for systems to read,
+//! and events systems write are sent to the host at the end of the frame. They travel as
+//! [`ReservedTopic::HostEvent`] and [`ReservedTopic::AppEvent`] messages:
+//!
+//! ```text
+//! event id: u32 | fields in declaration order
+//! ```
+//!
+//! The id is a hash of the struct's name (see [`event_id`]), so it stays the same across
+//! builds as long as the struct isn't renamed. Registering two events whose names hash to
+//! the same id panics. The fields are encoded with serde in the crate's payload encoding
+//! (see [`EventCodecError`] for what it refuses).
+//!
+//! Serde's data model doesn't describe a type without serializing a value, so the derive
+//! also walks the field types: each implements [`EventField`](trait@EventField), which
+//! primitives, `String`, `Option` and `Vec` do, and nested structs and enums without
+//! variant data derive. `Vec<u8>` is sent as bytes. Generic types and `#[serde]`
+//! attributes, which change the encoding, are refused at compile time.
+//!
+//! Hosts don't write the other side by hand either: [`HostEvents::swift_source`] and
+//! [`HostEvents::kotlin_source`] generate a Swift struct or Kotlin class for every event
+//! the app declared and every struct and enum its fields use, with `encode` for events to
+//! Bevy and `decode` for events to the host. Kotlin has no nested nullable types, so an
+//! `Option<Option<T>>` field can't tell `Some(None)` from `None` there.
+
+use std::fmt::Write;
+
+use bevy::{
+    app::{App, Last, PreUpdate},
+    ecs::{
+        message::{Message, MessageReader, MessageWriter},
+        resource::Resource,
+        system::Res,
+    },
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    HostChannel,
+    event_codec::{self, EventCodecError},
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic, encode_reserved},
+};
+
+/// Which side sends an event
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventDirection {
+    /// The host sends the event to Bevy
+    ToBevy = 0,
+    /// Bevy sends the event to the host
+    ToHost = 1,
+}
+
+impl EventDirection {
+    /// Create an EventDirection from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(EventDirection::ToBevy),
+            1 => Some(EventDirection::ToHost),
+            _ => None,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            EventDirection::ToBevy => "sent to Bevy",
+            EventDirection::ToHost => "sent by Bevy",
+        }
+    }
+
+    /// Reserved topic carrying events in this direction
+    pub fn topic(self) -> ReservedTopic {
+        match self {
+            EventDirection::ToBevy => ReservedTopic::HostEvent,
+            EventDirection::ToHost => ReservedTopic::AppEvent,
+        }
+    }
+}
+
+/// Encoding of an event field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    /// One byte, non-zero for true
+    Bool,
+    /// `u8`
+    U8,
+    /// Little-endian `u16`
+    U16,
+    /// Little-endian `u32`
+    U32,
+    /// Little-endian `u64`
+    U64,
+    /// Little-endian two's complement `i32`
+    I32,
+    /// Little-endian two's complement `i64`
+    I64,
+    /// Little-endian `f32`
+    F32,
+    /// Little-endian `f64`
+    F64,
+    /// UTF-8 prefixed with its `u32` length
+    String,
+    /// A `u8` tag: 1 followed by the value, or 0 for none
+    Option(Box<FieldType>),
+    /// Elements prefixed with their `u32` count; a list of `U8` is bytes
+    List(Box<FieldType>),
+    /// The fields of a nested struct in declaration order
+    Struct(StructSchema),
+    /// The `u32` index of a variant of an enum without variant data
+    Enum(EnumSchema),
+}
+
+/// A struct used in event fields, derived with [`EventField`](derive@crate::EventField)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructSchema {
+    /// Name of the struct
+    pub name: &'static str,
+    /// Names and encodings of the fields, in declaration order
+    pub fields: Vec<(&'static str, FieldType)>,
+}
+
+/// An enum used in event fields, derived with [`EventField`](derive@crate::EventField)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumSchema {
+    /// Name of the enum
+    pub name: &'static str,
+    /// Names of the variants, in declaration order
+    pub variants: Vec<&'static str>,
+}
+
+impl FieldType {
+    /// Name of the struct or enum, if the field is one
+    fn type_name(&self) -> Option<&'static str> {
+        match self {
+            FieldType::Struct(schema) => Some(schema.name),
+            FieldType::Enum(schema) => Some(schema.name),
+            _ => None,
+        }
+    }
+
+    /// Adds the structs and enums the field uses to `named`, each after those it uses
+    fn collect_named(&self, named: &mut Vec<FieldType>) {
+        match self {
+            FieldType::Option(inner) | FieldType::List(inner) => inner.collect_named(named),
+            FieldType::Struct(schema) => {
+                for (_, field_type) in &schema.fields {
+                    field_type.collect_named(named);
+                }
+            }
+            _ => {}
+        }
+        if self.type_name().is_some()
+            && !named
+                .iter()
+                .any(|other| other.type_name() == self.type_name())
+        {
+            named.push(self.clone());
+        }
+    }
+
+    fn swift_type(&self) -> String {
+        match self {
+            FieldType::Bool => "Bool".to_string(),
+            FieldType::U8 => "UInt8".to_string(),
+            FieldType::U16 => "UInt16".to_string(),
+            FieldType::U32 => "UInt32".to_string(),
+            FieldType::U64 => "UInt64".to_string(),
+            FieldType::I32 => "Int32".to_string(),
+            FieldType::I64 => "Int64".to_string(),
+            FieldType::F32 => "Float".to_string(),
+            FieldType::F64 => "Double".to_string(),
+            FieldType::String => "String".to_string(),
+            FieldType::Option(inner) => format!("{}?", inner.swift_type()),
+            FieldType::List(inner) if **inner == FieldType::U8 => "Data".to_string(),
+            FieldType::List(inner) => format!("[{}]", inner.swift_type()),
+            FieldType::Struct(schema) => schema.name.to_string(),
+            FieldType::Enum(schema) => schema.name.to_string(),
+        }
+    }
+
+    /// The type spelled so that `.self` is its metatype
+    fn swift_metatype(&self) -> String {
+        match self {
+            FieldType::Option(inner) => format!("Optional<{}>", inner.swift_metatype()),
+            FieldType::List(inner) if **inner != FieldType::U8 => {
+                format!("Array<{}>", inner.swift_metatype())
+            }
+            other => other.swift_type(),
+        }
+    }
+
+    fn swift_read(&self) -> String {
+        format!("reader.get({}.self)", self.swift_metatype())
+    }
+
+    /// Unsigned values are widened where Kotlin has no unsigned type of the same size;
+    /// `u64` keeps its bits in a `Long`
+    fn kotlin_type(&self) -> String {
+        match self {
+            FieldType::Bool => "Boolean".to_string(),
+            FieldType::U8 | FieldType::U16 | FieldType::I32 => "Int".to_string(),
+            FieldType::U32 | FieldType::U64 | FieldType::I64 => "Long".to_string(),
+            FieldType::F32 => "Float".to_string(),
+            FieldType::F64 => "Double".to_string(),
+            FieldType::String => "String".to_string(),
+            // Kotlin has no nested nullable types
+            FieldType::Option(inner) => match inner.kotlin_type() {
+                nullable if nullable.ends_with('?') => nullable,
+                inner => format!("{inner}?"),
+            },
+            FieldType::List(inner) if **inner == FieldType::U8 => "ByteArray".to_string(),
+            FieldType::List(inner) => format!("List<{}>", inner.kotlin_type()),
+            FieldType::Struct(schema) => schema.name.to_string(),
+            FieldType::Enum(schema) => schema.name.to_string(),
+        }
+    }
+
+    /// Statement writing `value` with the generated Kotlin writer; lambdas nested `depth`
+    /// deep name their parameter `item{depth}`
+    fn kotlin_write(&self, value: &str, depth: usize) -> String {
+        let method = match self {
+            FieldType::Bool => "Boolean",
+            FieldType::U8 => "U8",
+            FieldType::U16 => "U16",
+            FieldType::U32 => "U32",
+            FieldType::U64 | FieldType::I64 => "Long",
+            FieldType::I32 => "Int",
+            FieldType::F32 => "Float",
+            FieldType::F64 => "Double",
+            FieldType::String => "String",
+            FieldType::List(inner) if **inner == FieldType::U8 => "Bytes",
+            FieldType::Option(inner) | FieldType::List(inner) => {
+                let kind = if matches!(self, FieldType::Option(_)) {
+                    "Optional"
+                } else {
+                    "List"
+                };
+                let item = format!("item{depth}");
+                return format!(
+                    "writer.put{kind}({value}) {{ {item} -> {} }}",
+                    inner.kotlin_write(&item, depth + 1)
+                );
+            }
+            FieldType::Struct(_) | FieldType::Enum(_) => return format!("{value}.write(writer)"),
+        };
+        format!("writer.put{method}({value})")
+    }
+
+    /// Expression reading a value with the generated Kotlin reader
+    fn kotlin_read(&self) -> String {
+        let method = match self {
+            FieldType::Bool => "Boolean",
+            FieldType::U8 => "U8",
+            FieldType::U16 => "U16",
+            FieldType::U32 => "U32",
+            FieldType::U64 | FieldType::I64 => "Long",
+            FieldType::I32 => "Int",
+            FieldType::F32 => "Float",
+            FieldType::F64 => "Double",
+            FieldType::String => "String",
+            FieldType::List(inner) if **inner == FieldType::U8 => "Bytes",
+            FieldType::Option(inner) => {
+                return format!("reader.getOptional {{ {} }}", inner.kotlin_read());
+            }
+            FieldType::List(inner) => {
+                return format!("reader.getList {{ {} }}", inner.kotlin_read());
+            }
+            FieldType::Struct(schema) => return format!("{}.read(reader)", schema.name),
+            FieldType::Enum(schema) => return format!("{}.read(reader)", schema.name),
+        };
+        format!("reader.get{method}()")
+    }
+}
+
+/// A value that can be a field of a [`HostEvent`](trait@HostEvent)
+///
+/// Derive it for structs and enums without variant data used in event fields.
+pub trait EventField {
+    /// How the value is encoded, for generated host code
+    fn field_type() -> FieldType;
+}
+
+macro_rules! event_field {
+    ($($ty:ty => $field_type:ident),* $(,)?) => {
+        $(
+            impl EventField for $ty {
+                fn field_type() -> FieldType {
+                    FieldType::$field_type
+                }
+            }
+        )*
+    };
+}
+
+event_field!(
+    bool => Bool,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    i32 => I32,
+    i64 => I64,
+    f32 => F32,
+    f64 => F64,
+    String => String,
+);
+
+impl<T: EventField> EventField for Option<T> {
+    fn field_type() -> FieldType {
+        FieldType::Option(Box::new(T::field_type()))
+    }
+}
+
+impl<T: EventField> EventField for Vec<T> {
+    fn field_type() -> FieldType {
+        FieldType::List(Box::new(T::field_type()))
+    }
+}
+
+/// Stable id of the event named `name`: the 32-bit FNV-1a hash of the name
+pub const fn event_id(name: &str) -> u32 {
+    let bytes = name.as_bytes();
+    let mut hash = 0x811c_9dc5_u32;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+/// An event sent between the host and the app
+///
+/// Derive it with [`HostEvent`](derive@crate::HostEvent) and serde's `Serialize` and
+/// `Deserialize`, adding `#[host_event(to_bevy)]` for events the host sends or
+/// `#[host_event(to_host)]` for events the app sends. The derive also makes the struct a
+/// Bevy [`Message`], and the app registers it with [`HostEventApp::add_host_event`].
+pub trait HostEvent: Message + Serialize + DeserializeOwned {
+    /// Name of the event's struct
+    const NAME: &'static str;
+    /// Id identifying the event in its reserved topic
+    const ID: u32;
+    /// Which side sends the event
+    const DIRECTION: EventDirection;
+
+    /// Names and encodings of the fields, in declaration order
+    fn fields() -> Vec<(&'static str, FieldType)>;
+
+    /// Encodes the event as a reserved message
+    fn encode(&self) -> Result<Vec<u8>, EventCodecError> {
+        let mut writer = ByteWriter::new();
+        writer.u32(Self::ID);
+        event_codec::write(self, &mut writer)?;
+        Ok(encode_reserved(Self::DIRECTION.topic(), &writer.finish()))
+    }
+
+    /// Decodes the payload of a reserved message, returning `None` for other events and
+    /// malformed ones
+    fn decode(payload: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(payload);
+        if reader.u32()? != Self::ID {
+            return None;
+        }
+        let event = event_codec::read(&mut reader).ok()?;
+        reader.is_empty().then_some(event)
+    }
+}
+
+/// Description of a registered event, for generating host code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventSchema {
+    /// Name of the event's struct
+    pub name: &'static str,
+    /// Id identifying the event in its reserved topic
+    pub id: u32,
+    /// Which side sends the event
+    pub direction: EventDirection,
+    /// Names and encodings of the fields, in declaration order
+    pub fields: Vec<(&'static str, FieldType)>,
+}
+
+impl EventSchema {
+    /// Field names as `camelCase` Swift and Kotlin properties
+    fn camel_case_fields(&self) -> Vec<(String, &FieldType)> {
+        camel_case_fields(&self.fields)
+    }
+}
+
+fn camel_case_fields<'a>(fields: &'a [(&'static str, FieldType)]) -> Vec<(String, &'a FieldType)> {
+    fields
+        .iter()
+        .map(|(name, field_type)| (camel_case(name), field_type))
+        .collect()
+}
+
+/// Events the app registered with [`HostEventApp::add_host_event`]
+///
+/// Added by registering the first event.
+#[derive(Resource, Debug, Default)]
+pub struct HostEvents {
+    events: Vec<EventSchema>,
+}
+
+impl HostEvents {
+    /// The registered events, in registration order
+    pub fn schemas(&self) -> &[EventSchema] {
+        &self.events
+    }
+
+    /// Structs and enums the registered events use, each after those it uses
+    fn named_types(&self) -> Vec<FieldType> {
+        let mut named = Vec::new();
+        for event in &self.events {
+            for (_, field_type) in &event.fields {
+                field_type.collect_named(&mut named);
+            }
+        }
+        named
+    }
+
+    /// Swift source declaring every registered event and the structs and enums they use
+    ///
+    /// Events to Bevy get an `encode() -> Data` method producing a message for the
+    /// channel, and events to the host a `static func decode(_: Data)` that returns nil for
+    /// other messages.
+    pub fn swift_source(&self) -> String {
+        let mut source = format!("// {GENERATED_NOTICE}\n{SWIFT_PRELUDE}");
+        for named in self.named_types() {
+            swift_named_type(&mut source, &named);
+        }
+        for event in &self.events {
+            let fields = event.camel_case_fields();
+            let topic = event.direction.topic() as u16;
+            // Readers and writers of events without fields are never mutated
+            let binding = if fields.is_empty() { "let" } else { "var" };
+
+            let _ = writeln!(
+                source,
+                "\n/// `{}`, {}",
+                event.name,
+                event.direction.describe()
+            );
+            let _ = writeln!(source, "struct {} {{", event.name);
+            let _ = writeln!(source, "    static let eventId: UInt32 = {}\n", event.id);
+            for (name, field_type) in &fields {
+                let _ = writeln!(source, "    var {name}: {}", field_type.swift_type());
+            }
+            if !fields.is_empty() {
+                source.push('\n');
+            }
+
+            match event.direction {
+                EventDirection::ToBevy => {
+                    source += "    func encode() -> Data {\n";
+                    let _ = writeln!(
+                        source,
+                        "        {binding} writer = BevyEventWriter(topic: {topic}, event: Self.eventId)"
+                    );
+                    for (name, _) in &fields {
+                        let _ = writeln!(source, "        writer.put({name})");
+                    }
+                    source += "        return writer.data\n    }\n";
+                }
+                EventDirection::ToHost => {
+                    source += "    /// Decodes the event, or returns nil for other messages\n";
+                    let _ = writeln!(
+                        source,
+                        "    static func decode(_ message: Data) -> {}? {{",
+                        event.name
+                    );
+                    let _ = writeln!(
+                        source,
+                        "        guard {binding} reader = BevyEventReader(message, topic: {topic}, event: eventId),"
+                    );
+                    for (name, field_type) in &fields {
+                        let _ = writeln!(
+                            source,
+                            "              let {name} = {},",
+                            field_type.swift_read()
+                        );
+                    }
+                    source += "              reader.isAtEnd\n        else { return nil }\n";
+                    let arguments: Vec<_> = fields
+                        .iter()
+                        .map(|(name, _)| format!("{name}: {name}"))
+                        .collect();
+                    let _ = writeln!(
+                        source,
+                        "        return {}({})\n    }}",
+                        event.name,
+                        arguments.join(", ")
+                    );
+                }
+            }
+            source += "}\n";
+        }
+        source
+    }
+
+    /// Kotlin source in `package` declaring every registered event and the structs and
+    /// enums they use
+    ///
+    /// Events to Bevy get an `encode(): ByteArray` method producing a message for the
+    /// channel, and events to the host a companion `decode(ByteArray)` that returns null
+    /// for other messages.
+    pub fn kotlin_source(&self, package: &str) -> String {
+        let mut source = format!("// {GENERATED_NOTICE}\n\npackage {package}\n{KOTLIN_PRELUDE}");
+        for named in self.named_types() {
+            kotlin_named_type(&mut source, &named);
+        }
+        for event in &self.events {
+            let fields = event.camel_case_fields();
+            let topic = event.direction.topic() as u16;
+            let id = kotlin_int(event.id as i32);
+
+            let _ = writeln!(
+                source,
+                "\n/** `{}`, {} */",
+                event.name,
+                event.direction.describe()
+            );
+            // Data classes need at least one property
+            if fields.is_empty() {
+                let _ = writeln!(source, "class {} {{", event.name);
+            } else {
+                let _ = writeln!(source, "data class {}(", event.name);
+                for (name, field_type) in &fields {
+                    let _ = writeln!(source, "    val {name}: {},", field_type.kotlin_type());
+                }
+                source += ") {\n";
+            }
+
+            match event.direction {
+                EventDirection::ToBevy => {
+                    source += "    fun encode(): ByteArray {\n";
+                    let _ = writeln!(source, "        val writer = BevyEventWriter({topic}, ID)");
+                    for (name, field_type) in &fields {
+                        let _ = writeln!(source, "        {}", field_type.kotlin_write(name, 0));
+                    }
+                    source += "        return writer.toByteArray()\n    }\n\n";
+                    source += "    companion object {\n";
+                    let _ = writeln!(source, "        const val ID: Int = {id}");
+                    source += "    }\n";
+                }
+                EventDirection::ToHost => {
+                    source += "    companion object {\n";
+                    let _ = writeln!(source, "        const val ID: Int = {id}\n");
+                    source +=
+                        "        /** Decodes the event, or returns null for other messages */\n";
+                    let _ = writeln!(
+                        source,
+                        "        fun decode(message: ByteArray): {}? {{",
+                        event.name
+                    );
+                    let _ = writeln!(
+                        source,
+                        "            val reader = BevyEventReader.open(message, {topic}, ID) ?: return null"
+                    );
+                    source += "            return try {\n";
+                    if fields.is_empty() {
+                        let _ = writeln!(
+                            source,
+                            "                {}().takeIf {{ reader.isAtEnd }}",
+                            event.name
+                        );
+                    } else {
+                        let _ = writeln!(source, "                {}(", event.name);
+                        for (name, field_type) in &fields {
+                            let _ = writeln!(
+                                source,
+                                "                    {name} = {},",
+                                field_type.kotlin_read()
+                            );
+                        }
+                        source += "                ).takeIf { reader.isAtEnd }\n";
+                    }
+                    source += "            } catch (e: BufferUnderflowException) {\n";
+                    source += "                null\n            }\n        }\n    }\n";
+                }
+            }
+            source += "}\n";
+        }
+        source
+    }
+}
+
+/// Declares a struct or enum the events use in Swift, conforming to `BevyEventField`
+fn swift_named_type(source: &mut String, named: &FieldType) {
+    match named {
+        FieldType::Struct(schema) => {
+            let fields = camel_case_fields(&schema.fields);
+            let _ = writeln!(source, "\n/// `{}`, used in events", schema.name);
+            let _ = writeln!(source, "struct {}: BevyEventField {{", schema.name);
+            for (name, field_type) in &fields {
+                let _ = writeln!(source, "    var {name}: {}", field_type.swift_type());
+            }
+            if !fields.is_empty() {
+                source.push('\n');
+            }
+
+            *source += "    func write(to writer: inout BevyEventWriter) {\n";
+            for (name, _) in &fields {
+                let _ = writeln!(source, "        writer.put({name})");
+            }
+            *source += "    }\n\n";
+
+            let _ = writeln!(
+                source,
+                "    static func read(from reader: inout BevyEventReader) -> {}? {{",
+                schema.name
+            );
+            if !fields.is_empty() {
+                let reads: Vec<_> = fields
+                    .iter()
+                    .map(|(name, field_type)| format!("let {name} = {}", field_type.swift_read()))
+                    .collect();
+                let _ = writeln!(
+                    source,
+                    "        guard {}\n        else {{ return nil }}",
+                    reads.join(",\n              ")
+                );
+            }
+            let arguments: Vec<_> = fields
+                .iter()
+                .map(|(name, _)| format!("{name}: {name}"))
+                .collect();
+            let _ = writeln!(
+                source,
+                "        return {}({})\n    }}\n}}",
+                schema.name,
+                arguments.join(", ")
+            );
+        }
+        FieldType::Enum(schema) => {
+            let _ = writeln!(source, "\n/// `{}`, used in events", schema.name);
+            let _ = writeln!(source, "enum {}: UInt32, BevyEventField {{", schema.name);
+            for (index, variant) in schema.variants.iter().enumerate() {
+                let _ = writeln!(source, "    case {} = {index}", swift_case(variant));
+            }
+            source.push('\n');
+            *source +=
+                "    func write(to writer: inout BevyEventWriter) { writer.put(rawValue) }\n\n";
+            let _ = writeln!(
+                source,
+                "    static func read(from reader: inout BevyEventReader) -> {}? {{",
+                schema.name
+            );
+            let _ = writeln!(
+                source,
+                "        reader.get(UInt32.self).flatMap({}.init(rawValue:))\n    }}\n}}",
+                schema.name
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Declares a struct or enum the events use in Kotlin, with `write` and a companion `read`
+fn kotlin_named_type(source: &mut String, named: &FieldType) {
+    match named {
+        FieldType::Struct(schema) => {
+            let fields = camel_case_fields(&schema.fields);
+            let _ = writeln!(source, "\n/** `{}`, used in events */", schema.name);
+            // Data classes need at least one property
+            if fields.is_empty() {
+                let _ = writeln!(source, "class {} {{", schema.name);
+            } else {
+                let _ = writeln!(source, "data class {}(", schema.name);
+                for (name, field_type) in &fields {
+                    let _ = writeln!(source, "    val {name}: {},", field_type.kotlin_type());
+                }
+                *source += ") {\n";
+            }
+
+            *source += "    internal fun write(writer: BevyEventWriter) {\n";
+            for (name, field_type) in &fields {
+                let _ = writeln!(source, "        {}", field_type.kotlin_write(name, 0));
+            }
+            *source += "    }\n\n    companion object {\n";
+            if fields.is_empty() {
+                let _ = writeln!(
+                    source,
+                    "        internal fun read(reader: BevyEventReader) = {}()",
+                    schema.name
+                );
+            } else {
+                let _ = writeln!(
+                    source,
+                    "        internal fun read(reader: BevyEventReader) = {}(",
+                    schema.name
+                );
+                for (name, field_type) in &fields {
+                    let _ = writeln!(source, "            {name} = {},", field_type.kotlin_read());
+                }
+                *source += "        )\n";
+            }
+            *source += "    }\n}\n";
+        }
+        FieldType::Enum(schema) => {
+            let _ = writeln!(source, "\n/** `{}`, used in events */", schema.name);
+            let _ = writeln!(source, "enum class {} {{", schema.name);
+            let _ = writeln!(source, "    {};\n", schema.variants.join(",\n    "));
+            *source +=
+                "    internal fun write(writer: BevyEventWriter) = writer.putInt(ordinal)\n\n";
+            *source += "    companion object {\n";
+            *source +=
+                "        internal fun read(reader: BevyEventReader) = reader.getEnum(values())\n";
+            *source += "    }\n}\n";
+        }
+        _ => {}
+    }
+}
+
+const GENERATED_NOTICE: &str =
+    "Generated by bevy_embedded from the app's host events. Do not edit.";
+
+const SWIFT_PRELUDE: &str = r#"
+import Foundation
+
+/// A value events encode, in the encoding of bevy_embedded's payloads
+protocol BevyEventField {
+    func write(to writer: inout BevyEventWriter)
+    static func read(from reader: inout BevyEventReader) -> Self?
+}
+
+/// Writes an event as a reserved message
+struct BevyEventWriter {
+    private(set) var data = Data([0xFF, 0x42, 0x45, 0x4D])
+
+    init(topic: UInt16, event: UInt32) {
+        put(topic)
+        put(event)
+    }
+
+    mutating func put<T: BevyEventField>(_ value: T) { value.write(to: &self) }
+
+    mutating func append<Bytes: Sequence>(_ bytes: Bytes) where Bytes.Element == UInt8 {
+        data.append(contentsOf: bytes)
+    }
+}
+
+/// Reads an event from a reserved message
+struct BevyEventReader {
+    private let bytes: [UInt8]
+    private var offset = 0
+
+    /// Reads the header, or returns nil for messages of another topic or event
+    init?(_ message: Data, topic: UInt16, event: UInt32) {
+        bytes = [UInt8](message)
+        guard take(4) == [0xFF, 0x42, 0x45, 0x4D],
+              get(UInt16.self) == topic,
+              get(UInt32.self) == event
+        else { return nil }
+    }
+
+    var isAtEnd: Bool { offset == bytes.count }
+
+    mutating func take(_ count: Int) -> ArraySlice<UInt8>? {
+        guard count <= bytes.count - offset else { return nil }
+        defer { offset += count }
+        return bytes[offset..<offset + count]
+    }
+
+    mutating func get<T: BevyEventField>(_: T.Type) -> T? { T.read(from: &self) }
+}
+
+extension BevyEventField where Self: FixedWidthInteger {
+    func write(to writer: inout BevyEventWriter) {
+        withUnsafeBytes(of: littleEndian) { writer.append($0) }
+    }
+
+    static func read(from reader: inout BevyEventReader) -> Self? {
+        reader.take(MemoryLayout<Self>.size)?.reversed().reduce(Self.zero) { $0 << 8 | Self(truncatingIfNeeded: $1) }
+    }
+}
+
+extension UInt8: BevyEventField {}
+extension UInt16: BevyEventField {}
+extension UInt32: BevyEventField {}
+extension UInt64: BevyEventField {}
+extension Int32: BevyEventField {}
+extension Int64: BevyEventField {}
+
+extension Bool: BevyEventField {
+    func write(to writer: inout BevyEventWriter) { writer.put(UInt8(self ? 1 : 0)) }
+    static func read(from reader: inout BevyEventReader) -> Bool? { reader.get(UInt8.self).map { $0 != 0 } }
+}
+
+extension Float: BevyEventField {
+    func write(to writer: inout BevyEventWriter) { writer.put(bitPattern) }
+    static func read(from reader: inout BevyEventReader) -> Float? { reader.get(UInt32.self).map(Float.init(bitPattern:)) }
+}
+
+extension Double: BevyEventField {
+    func write(to writer: inout BevyEventWriter) { writer.put(bitPattern) }
+    static func read(from reader: inout BevyEventReader) -> Double? { reader.get(UInt64.self).map(Double.init(bitPattern:)) }
+}
+
+extension Data: BevyEventField {
+    func write(to writer: inout BevyEventWriter) {
+        writer.put(UInt32(count))
+        writer.append(self)
+    }
+
+    static func read(from reader: inout BevyEventReader) -> Data? {
+        guard let count = reader.get(UInt32.self), let bytes = reader.take(Int(count)) else { return nil }
+        return Data(bytes)
+    }
+}
+
+extension String: BevyEventField {
+    func write(to writer: inout BevyEventWriter) { writer.put(Data(utf8)) }
+    static func read(from reader: inout BevyEventReader) -> String? { reader.get(Data.self).flatMap { String(data: $0, encoding: .utf8) } }
+}
+
+extension Optional: BevyEventField where Wrapped: BevyEventField {
+    func write(to writer: inout BevyEventWriter) {
+        if let value = self {
+            writer.put(UInt8(1))
+            writer.put(value)
+        } else {
+            writer.put(UInt8(0))
+        }
+    }
+
+    static func read(from reader: inout BevyEventReader) -> Wrapped?? {
+        switch reader.get(UInt8.self) {
+        case 0: return .some(nil)
+        case 1: return reader.get(Wrapped.self).map { .some($0) }
+        default: return nil
+        }
+    }
+}
+
+extension Array: BevyEventField where Element: BevyEventField {
+    func write(to writer: inout BevyEventWriter) {
+        writer.put(UInt32(count))
+        for element in self { writer.put(element) }
+    }
+
+    static func read(from reader: inout BevyEventReader) -> [Element]? {
+        guard let count = reader.get(UInt32.self) else { return nil }
+        var elements: [Element] = []
+        for _ in 0..<count {
+            guard let element = reader.get(Element.self) else { return nil }
+            elements.append(element)
+        }
+        return elements
+    }
+}
+"#;
+
+const KOTLIN_PRELUDE: &str = r#"
+import java.io.ByteArrayOutputStream
+import java.nio.BufferUnderflowException
+import java.nio.ByteBuffer
+import java.nio.ByteOrder
+
+/** Writes an event as a reserved message */
+internal class BevyEventWriter(topic: Int, event: Int) {
+    private val bytes = ByteArrayOutputStream()
+
+    init {
+        bytes.write(byteArrayOf(0xFF.toByte(), 0x42, 0x45, 0x4D))
+        putU16(topic)
+        putInt(event)
+    }
+
+    fun putBoolean(value: Boolean) = bytes.write(if (value) 1 else 0)
+    fun putU8(value: Int) = bytes.write(value)
+    fun putU16(value: Int) = put(buffer(2).putShort(value.toShort()))
+    fun putU32(value: Long) = putInt(value.toInt())
+    fun putInt(value: Int) = put(buffer(4).putInt(value))
+    fun putLong(value: Long) = put(buffer(8).putLong(value))
+    fun putFloat(value: Float) = putInt(value.toRawBits())
+    fun putDouble(value: Double) = putLong(value.toRawBits())
+    fun putString(value: String) = putBytes(value.toByteArray(Charsets.UTF_8))
+
+    fun putBytes(value: ByteArray) {
+        putInt(value.size)
+        bytes.write(value)
+    }
+
+    fun <T> putOptional(value: T?, put: (T) -> Unit) {
+        if (value == null) {
+            putU8(0)
+        } else {
+            putU8(1)
+            put(value)
+        }
+    }
+
+    fun <T> putList(value: List<T>, put: (T) -> Unit) {
+        putInt(value.size)
+        value.forEach(put)
+    }
+
+    fun toByteArray(): ByteArray = bytes.toByteArray()
+
+    private fun buffer(size: Int) = ByteBuffer.allocate(size).order(ByteOrder.LITTLE_ENDIAN)
+
+    private fun put(buffer: ByteBuffer) = bytes.write(buffer.array())
+}
+
+/** Reads an event from a reserved message; reads past the end or of invalid values throw BufferUnderflowException */
+internal class BevyEventReader private constructor(private val buffer: ByteBuffer) {
+    val isAtEnd: Boolean get() = !buffer.hasRemaining()
+
+    fun getBoolean() = buffer.get().toInt() != 0
+    fun getU8() = buffer.get().toInt() and 0xFF
+    fun getU16() = buffer.getShort().toInt() and 0xFFFF
+    fun getU32() = buffer.getInt().toLong() and 0xFFFFFFFFL
+    fun getInt() = buffer.getInt()
+    fun getLong() = buffer.getLong()
+    fun getFloat() = buffer.getFloat()
+    fun getDouble() = buffer.getDouble()
+    fun getString() = String(getBytes(), Charsets.UTF_8)
+
+    fun getBytes(): ByteArray {
+        val size = buffer.getInt()
+        if (size < 0 || size > buffer.remaining()) throw BufferUnderflowException()
+        return ByteArray(size).also { buffer.get(it) }
+    }
+
+    fun <T> getOptional(get: () -> T): T? = when (getU8()) {
+        0 -> null
+        1 -> get()
+        else -> throw BufferUnderflowException()
+    }
+
+    fun <T> getList(get: () -> T): List<T> {
+        val size = buffer.getInt()
+        if (size < 0 || size > buffer.remaining()) throw BufferUnderflowException()
+        return List(size) { get() }
+    }
+
+    fun <T> getEnum(values: Array<T>): T =
+        values.getOrNull(buffer.getInt()) ?: throw BufferUnderflowException()
+
+    companion object {
+        /** Reads the header, or returns null for messages of another topic or event */
+        fun open(message: ByteArray, topic: Int, event: Int): BevyEventReader? {
+            val buffer = ByteBuffer.wrap(message).order(ByteOrder.LITTLE_ENDIAN)
+            if (buffer.remaining() < 10 || buffer.getInt() != 0x4D4542FF) return null
+            if ((buffer.getShort().toInt() and 0xFFFF) != topic || buffer.getInt() != event) return null
+            return BevyEventReader(buffer)
+        }
+    }
+}
+"#;
+
+/// Converts a `snake_case` name to `camelCase`
+fn camel_case(name: &str) -> String {
+    let mut camel = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.trim_start_matches("r#").chars() {
+        if c == '_' {
+            upper = !camel.is_empty();
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+/// A `lowerCamelCase` Swift enum case for a variant, quoted if it is a keyword
+fn swift_case(variant: &str) -> String {
+    let mut chars = variant.chars();
+    let case: String = chars
+        .next()
+        .map(|first| first.to_lowercase().chain(chars).collect())
+        .unwrap_or_default();
+    match case.as_str() {
+        "default" | "case" | "class" | "enum" | "func" | "import" | "in" | "init" | "internal"
+        | "let" | "private" | "protocol" | "public" | "repeat" | "return" | "self" | "static"
+        | "struct" | "subscript" | "super" | "switch" | "var" | "where" | "while" => {
+            format!("`{case}`")
+        }
+        _ => case,
+    }
+}
+
+/// An `Int` literal, which Kotlin can't write for `Int.MIN_VALUE`
+fn kotlin_int(value: i32) -> String {
+    if value == i32::MIN {
+        "Int.MIN_VALUE".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Extension trait for registering events deriving [`HostEvent`](derive@crate::HostEvent)
+pub trait HostEventApp {
+    /// Delivers events the host sends as messages, or sends events systems write to the
+    /// host, depending on the event's direction
+    ///
+    /// Registering an event again does nothing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another event with the same [`event_id`] is registered; rename one of them.
+    fn add_host_event<E: HostEvent>(&mut self) -> &mut Self;
+}
+
+impl HostEventApp for App {
+    fn add_host_event<E: HostEvent>(&mut self) -> &mut Self {
+        let mut events = self.world_mut().get_resource_or_init::<HostEvents>();
+        if let Some(registered) = events.events.iter().find(|event| event.id == E::ID) {
+            assert_eq!(
+                registered.name,
+                E::NAME,
+                "Host event {} has the id of {}; rename one of them",
+                E::NAME,
+                registered.name
+            );
+            return self;
+        }
+        events.events.push(EventSchema {
+            name: E::NAME,
+            id: E::ID,
+            direction: E::DIRECTION,
+            fields: E::fields(),
+        });
+
+        self.add_message::<E>();
+        match E::DIRECTION {
+            EventDirection::ToBevy => self.add_systems(PreUpdate, receive_host_events::<E>),
+            EventDirection::ToHost => self.add_systems(Last, send_app_events::<E>),
+        }
+    }
+}
+
+/// Writes the host's events of type `E` as messages
+fn receive_host_events<E: HostEvent>(
+    mut commands: MessageReader<HostCommand>,
+    mut events: MessageWriter<E>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::HostEvent
+            || command.payload.get(..4) != Some(&E::ID.to_le_bytes())
+        {
+            continue;
+        }
+        match E::decode(&command.payload) {
+            Some(event) => {
+                events.write(event);
+            }
+            None => log::warn!("Ignoring malformed host event {}", E::NAME),
+        }
+    }
+}
+
+/// Sends the app's events of type `E` to the host
+fn send_app_events<E: HostEvent>(mut events: MessageReader<E>, channel: Res<HostChannel>) {
+    for event in events.read() {
+        match event.encode() {
+            Ok(message) => channel.send(message),
+            Err(error) => log::error!("Failed to encode app event {}: {error}", E::NAME),
+        }
+    }
+}
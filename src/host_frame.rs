@@ -0,0 +1,91 @@
+//! Rendering into frames the host encodes itself
+//!
+//! Hosts that own the GPU frame (game engines, custom renderers) don't give Bevy a surface
+//! to present to. Instead, every frame they hand Bevy the texture to draw into and the
+//! viewport within it (`bevy_embedded_ios_set_host_frame`,
+//! `bevy_embedded_android_set_host_frame`), update the app, and wait on the GPU for the
+//! frame's [`FrameFence`](crate::FrameFence) value before reading the texture. On Metal
+//! Bevy encodes the wait into the host's command buffer
+//! (`bevy_embedded_ios_encode_host_frame`), so whatever the host encodes after the call
+//! can sample or composite Bevy's output. Vulkan waits belong to queue submissions, so
+//! native Android engines add the fence's semaphore and value to the submission that
+//! reads the image.
+//!
+//! While a [`HostFrame`] is set, cameras targeting the primary window render into the
+//! host texture at the viewport instead. Apps created headless get a primary window
+//! without a surface, sized to the viewport, so input and UI layout work as usual.
+//!
+//! The host must not write a texture while Bevy may still be rendering into it: rotate
+//! through several textures, as with a swapchain, rather than reusing one every frame.
+
+use bevy::{
+    camera::{Camera, ManualTextureViewHandle, RenderTarget, Viewport},
+    ecs::{
+        entity::Entity,
+        query::With,
+        resource::Resource,
+        system::{Query, Res},
+    },
+    math::{URect, UVec2},
+    window::{PrimaryWindow, WindowRef},
+};
+
+/// The manual texture view cameras render into while a [`HostFrame`] is set
+pub const HOST_FRAME_VIEW: ManualTextureViewHandle = ManualTextureViewHandle(u32::MAX);
+
+/// The host texture Bevy renders the current frame into
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostFrame {
+    pub(crate) size: UVec2,
+    pub(crate) viewport: URect,
+}
+
+impl HostFrame {
+    /// Size of the host texture in pixels
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// The region of the host texture Bevy renders into, in pixels
+    pub fn viewport(&self) -> URect {
+        self.viewport
+    }
+}
+
+/// Points cameras of the primary window at the host frame
+pub(crate) fn target_host_frame(
+    frame: Option<Res<HostFrame>>,
+    mut cameras: Query<&mut Camera>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+) {
+    let Some(frame) = frame else {
+        return;
+    };
+
+    let primary = primary_window.single().ok();
+    let (position, size) = (frame.viewport.min, frame.viewport.size());
+    for mut camera in &mut cameras {
+        match &camera.target {
+            RenderTarget::TextureView(handle) if *handle == HOST_FRAME_VIEW => {}
+            RenderTarget::Window(WindowRef::Primary) => {
+                camera.target = RenderTarget::TextureView(HOST_FRAME_VIEW);
+            }
+            RenderTarget::Window(WindowRef::Entity(window)) if Some(*window) == primary => {
+                camera.target = RenderTarget::TextureView(HOST_FRAME_VIEW);
+            }
+            _ => continue,
+        }
+
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size));
+        if current != Some((position, size)) {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: size,
+                ..Default::default()
+            });
+        }
+    }
+}
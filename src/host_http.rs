@@ -0,0 +1,236 @@
+//! HTTP through the host's networking stack
+//!
+//! Host apps often require their own networking layer (auth headers, certificate pinning,
+//! proxies, offline caches) to be the only path to the network. With [`HostHttpPlugin`]
+//! added, [`HostHttp::fetch`] sends a request to the host on [`ReservedTopic::HttpRequest`]
+//! instead of opening a socket:
+//!
+//! ```text
+//! Fetch:  op: u8 (0) | request: u32 | method: string | url: string
+//!         | headers: u32 × (name: string | value: string) | body ...
+//! Cancel: op: u8 (1) | request: u32
+//! ```
+//!
+//! The host performs it with `URLSession`, OkHttp or whatever it uses, and streams the
+//! response back on [`ReservedTopic::HttpResponse`] as it arrives:
+//!
+//! ```text
+//! Head:   request: u32 | part: u8 (0) | status: u16 | headers: u32 × (name: string | value: string)
+//! Body:   request: u32 | part: u8 (1) | bytes ...
+//! End:    request: u32 | part: u8 (2)
+//! Failed: request: u32 | part: u8 (3) | error: string
+//! ```
+//!
+//! Strings are `u32 len + UTF-8`. Each part is delivered as an [`HttpResponsePart`]
+//! message: one `Head`, any number of `Body` chunks and an `End`, or a `Failed` at any
+//! point. A cancelled request gets no further parts.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{HostHttp, HttpPart, HttpRequest, HttpResponsePart};
+//!
+//! fn fetch_scores(mut http: ResMut<HostHttp>) {
+//!     http.fetch(HttpRequest::get("https://example.com/scores").header("Accept", "application/json"));
+//! }
+//!
+//! fn read_scores(mut parts: MessageReader<HttpResponsePart>, mut body: Local<Vec<u8>>) {
+//!     for part in parts.read() {
+//!         match &part.part {
+//!             HttpPart::Body(chunk) => body.extend_from_slice(chunk),
+//!             HttpPart::End => println!("{}", String::from_utf8_lossy(&body)),
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    ecs::{
+        message::{Message, MessageReader, MessageWriter},
+        resource::Resource,
+        system::{Res, ResMut},
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Sends [`HostHttp`] requests and delivers the responses; see the [module docs](self)
+#[derive(Default)]
+pub struct HostHttpPlugin;
+
+impl Plugin for HostHttpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostHttp>()
+            .add_message::<HttpResponsePart>()
+            .add_systems(PreUpdate, receive_http_responses)
+            .add_systems(Last, send_http_requests);
+    }
+}
+
+/// An HTTP request for the host to perform
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// Method, such as `GET` or `POST`
+    pub method: String,
+    /// Absolute URL
+    pub url: String,
+    /// Header names and values, sent in order
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// A request with `method` to `url`, without headers or body
+    pub fn new(method: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// A `GET` request to `url`
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new("GET", url)
+    }
+
+    /// A `POST` request to `url` with `body`
+    pub fn post(url: impl Into<String>, body: Vec<u8>) -> Self {
+        Self::new("POST", url).body(body)
+    }
+
+    /// Adds a header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the body
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+}
+
+/// A part of a streamed response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpPart {
+    /// Status code and response headers
+    Head {
+        /// HTTP status code
+        status: u16,
+        /// Header names and values
+        headers: Vec<(String, String)>,
+    },
+    /// The next chunk of the body
+    Body(Vec<u8>),
+    /// The response is complete
+    End,
+    /// The request failed before completing, e.g. offline or rejected by the host
+    Failed(String),
+}
+
+/// Sent for each part of a response the host streams back
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponsePart {
+    /// Request id returned by [`HostHttp::fetch`]
+    pub request_id: u32,
+    /// The part received
+    pub part: HttpPart,
+}
+
+/// Sends HTTP requests through the host
+#[derive(Resource, Debug, Default)]
+pub struct HostHttp {
+    next_request: u32,
+    outgoing: Vec<Vec<u8>>,
+}
+
+impl HostHttp {
+    /// Asks the host to perform `request`, returning the id of its [`HttpResponsePart`]s
+    pub fn fetch(&mut self, request: HttpRequest) -> u32 {
+        let request_id = self.next_request;
+        self.next_request = self.next_request.wrapping_add(1);
+
+        let mut payload = ByteWriter::new();
+        payload
+            .u8(0)
+            .u32(request_id)
+            .string(&request.method)
+            .string(&request.url);
+        write_headers(&mut payload, &request.headers);
+        payload.bytes(&request.body);
+        self.outgoing.push(payload.finish());
+        request_id
+    }
+
+    /// Asks the host to stop a request it hasn't finished
+    pub fn cancel(&mut self, request_id: u32) {
+        let mut payload = ByteWriter::new();
+        payload.u8(1).u32(request_id);
+        self.outgoing.push(payload.finish());
+    }
+}
+
+fn write_headers(payload: &mut ByteWriter, headers: &[(String, String)]) {
+    payload.u32(headers.len() as u32);
+    for (name, value) in headers {
+        payload.string(name).string(value);
+    }
+}
+
+/// Reads a part from the host, returning `None` if the payload is malformed
+fn read_part(payload: &[u8]) -> Option<HttpResponsePart> {
+    let mut reader = ByteReader::new(payload);
+    let request_id = reader.u32()?;
+    let part = match reader.u8()? {
+        0 => {
+            let status = reader.u16()?;
+            let count = reader.u32()?;
+            let headers = (0..count)
+                .map(|_| Some((reader.string()?.to_owned(), reader.string()?.to_owned())))
+                .collect::<Option<_>>()?;
+            HttpPart::Head { status, headers }
+        }
+        1 => HttpPart::Body(reader.remaining().to_vec()),
+        2 => HttpPart::End,
+        3 => HttpPart::Failed(reader.string()?.to_owned()),
+        _ => return None,
+    };
+    Some(HttpResponsePart { request_id, part })
+}
+
+/// Delivers response parts from the host
+fn receive_http_responses(
+    mut commands: MessageReader<HostCommand>,
+    mut parts: MessageWriter<HttpResponsePart>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::HttpResponse {
+            continue;
+        }
+
+        match read_part(&command.payload) {
+            Some(part) => {
+                parts.write(part);
+            }
+            None => log::warn!("Ignoring malformed HTTP response command"),
+        }
+    }
+}
+
+/// Sends the frame's HTTP requests to the host
+fn send_http_requests(channel: Res<HostChannel>, mut http: ResMut<HostHttp>) {
+    if http.outgoing.is_empty() {
+        return;
+    }
+    for payload in http.outgoing.drain(..) {
+        channel.send_reserved(ReservedTopic::HttpRequest, &payload);
+    }
+}
@@ -0,0 +1,256 @@
+//! Long-running jobs the host submits to Bevy
+//!
+//! Work such as baking a thumbnail of an asset takes longer than a frame. Apps register
+//! a handler for each kind of job with [`HostJobApp`]. The handler gets the world and
+//! the host's input, and returns a future that runs on the [`AsyncComputeTaskPool`]:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::HostJobApp;
+//!
+//! # let mut app = App::new();
+//! app.add_host_job("checksum", |_world: &mut World, input: Vec<u8>| async move {
+//!     let sum = input.iter().fold(0u32, |sum, &byte| sum.wrapping_add(byte.into()));
+//!     Ok(sum.to_le_bytes().to_vec())
+//! });
+//! ```
+//!
+//! The host submits jobs and cancels them on [`ReservedTopic::Job`], identifying each by
+//! a token (`bevy_embedded_ios_submit_job` and `nativeSubmitJob` allocate one):
+//!
+//! ```text
+//! Submit: op: u8 = 0 | token: u64 | kind: u32 len + UTF-8 | input ...
+//! Cancel: op: u8 = 1 | token: u64
+//! ```
+//!
+//! Every submitted job is answered once on [`ReservedTopic::JobResult`], after the frame
+//! it finished in:
+//!
+//! ```text
+//! token: u64 | status: u8 | output ... (Completed) or UTF-8 error (Failed)
+//! ```
+//!
+//! Canceling drops the job's future, so it stops at its next await point. Cancels for
+//! jobs that already finished are ignored. Jobs of a kind without a handler fail right
+//! away.
+//!
+//! Jobs are run by [`HostJobsPlugin`], which registering the first handler adds. Apps
+//! without it ignore submitted jobs.
+
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    ecs::{
+        message::{MessageCursor, Messages},
+        resource::Resource,
+        system::{Local, Res, ResMut},
+        world::World,
+    },
+    tasks::{AsyncComputeTaskPool, Task, TaskPool, block_on, poll_once},
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Runs the jobs the host submits; see the [module docs](self)
+#[derive(Default)]
+pub struct HostJobsPlugin;
+
+impl Plugin for HostJobsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostJobs>()
+            .add_systems(PreUpdate, receive_host_jobs)
+            .add_systems(Last, poll_host_jobs);
+    }
+}
+
+/// What a job produces: its output, or an error message for the host
+pub type JobOutput = Result<Vec<u8>, String>;
+
+type JobFuture = Pin<Box<dyn Future<Output = JobOutput> + Send>>;
+type JobHandler = Arc<dyn Fn(&mut World, Vec<u8>) -> JobFuture + Send + Sync>;
+
+/// Operations the host performs on jobs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum JobOp {
+    /// Start a job
+    Submit = 0,
+    /// Stop a running job
+    Cancel = 1,
+}
+
+impl JobOp {
+    /// Create a JobOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(JobOp::Submit),
+            1 => Some(JobOp::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// How a job ended, reported to the host with its result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum JobStatus {
+    /// The job finished; its output follows
+    Completed = 0,
+    /// The job failed or had no handler; an error message follows
+    Failed = 1,
+    /// The host canceled the job
+    Canceled = 2,
+}
+
+/// Job handlers and the jobs currently running
+#[derive(Resource, Default)]
+pub struct HostJobs {
+    handlers: HashMap<String, JobHandler>,
+    running: HashMap<u64, Task<JobOutput>>,
+}
+
+impl HostJobs {
+    /// Returns true if the job with `token` is still running
+    pub fn is_running(&self, token: u64) -> bool {
+        self.running.contains_key(&token)
+    }
+
+    /// Number of jobs still running
+    pub fn running(&self) -> usize {
+        self.running.len()
+    }
+}
+
+/// Extension trait for registering job handlers while building the app
+pub trait HostJobApp {
+    /// Runs jobs of `kind` submitted by the host, adding [`HostJobsPlugin`] if needed
+    ///
+    /// `handler` is called on the main thread with the world and the job's input, e.g. to
+    /// look up the asset to work on. The future it returns runs on the
+    /// [`AsyncComputeTaskPool`], and its output is sent to the host.
+    fn add_host_job<F, Fut>(&mut self, kind: impl Into<String>, handler: F) -> &mut Self
+    where
+        F: Fn(&mut World, Vec<u8>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = JobOutput> + Send + 'static;
+}
+
+impl HostJobApp for App {
+    fn add_host_job<F, Fut>(&mut self, kind: impl Into<String>, handler: F) -> &mut Self
+    where
+        F: Fn(&mut World, Vec<u8>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = JobOutput> + Send + 'static,
+    {
+        let handler: JobHandler = Arc::new(move |world, input| Box::pin(handler(world, input)));
+        if !self.is_plugin_added::<HostJobsPlugin>() {
+            self.add_plugins(HostJobsPlugin);
+        }
+        self.world_mut()
+            .resource_mut::<HostJobs>()
+            .handlers
+            .insert(kind.into(), handler);
+        self
+    }
+}
+
+/// Encodes a job submission for [`ReservedTopic::Job`]
+pub(crate) fn encode_submit(token: u64, kind: &str, input: &[u8]) -> Vec<u8> {
+    let mut payload = ByteWriter::new();
+    payload
+        .u8(JobOp::Submit as u8)
+        .u64(token)
+        .string(kind)
+        .bytes(input);
+    payload.finish()
+}
+
+/// Encodes a job cancellation for [`ReservedTopic::Job`]
+pub(crate) fn encode_cancel(token: u64) -> Vec<u8> {
+    let mut payload = ByteWriter::new();
+    payload.u8(JobOp::Cancel as u8).u64(token);
+    payload.finish()
+}
+
+fn send_result(channel: &HostChannel, token: u64, status: JobStatus, output: &[u8]) {
+    let mut payload = ByteWriter::new();
+    payload.u64(token).u8(status as u8).bytes(output);
+    channel.send_reserved(ReservedTopic::JobResult, &payload.finish());
+}
+
+fn start_job(world: &mut World, token: u64, kind: &str, input: Vec<u8>) {
+    let jobs = world.resource::<HostJobs>();
+    let handler = if jobs.running.contains_key(&token) {
+        Err(format!("Job {token} is already running"))
+    } else {
+        jobs.handlers
+            .get(kind)
+            .cloned()
+            .ok_or_else(|| format!("No handler for job kind {kind:?}"))
+    };
+    let handler = match handler {
+        Ok(handler) => handler,
+        Err(error) => {
+            log::warn!("{error}");
+            let channel = world.resource::<HostChannel>();
+            return send_result(channel, token, JobStatus::Failed, error.as_bytes());
+        }
+    };
+
+    let future = handler(world, input);
+    let task = AsyncComputeTaskPool::get_or_init(TaskPool::new).spawn(future);
+    world.resource_mut::<HostJobs>().running.insert(token, task);
+}
+
+/// Starts and cancels jobs submitted by the host
+fn receive_host_jobs(world: &mut World, mut cursor: Local<MessageCursor<HostCommand>>) {
+    let payloads: Vec<Vec<u8>> = cursor
+        .read(world.resource::<Messages<HostCommand>>())
+        .filter(|command| command.topic == ReservedTopic::Job)
+        .map(|command| command.payload.clone())
+        .collect();
+
+    for payload in payloads {
+        let mut reader = ByteReader::new(&payload);
+        let (Some(op), Some(token)) = (reader.u8(), reader.u64()) else {
+            log::warn!("Ignoring malformed job command");
+            continue;
+        };
+        match JobOp::from_u8(op) {
+            Some(JobOp::Submit) => {
+                let Some(kind) = reader.string() else {
+                    log::warn!("Ignoring malformed job command");
+                    continue;
+                };
+                start_job(world, token, kind, reader.remaining().to_vec());
+            }
+            Some(JobOp::Cancel) => {
+                let mut jobs = world.resource_mut::<HostJobs>();
+                if jobs.running.remove(&token).is_some() {
+                    let channel = world.resource::<HostChannel>();
+                    send_result(channel, token, JobStatus::Canceled, &[]);
+                }
+            }
+            None => log::warn!("Ignoring job command with unknown operation {op}"),
+        }
+    }
+}
+
+/// Sends the results of jobs that finished to the host
+fn poll_host_jobs(channel: Res<HostChannel>, mut jobs: ResMut<HostJobs>) {
+    if jobs.running.is_empty() {
+        return;
+    }
+    jobs.running.retain(|&token, task| {
+        let Some(output) = block_on(poll_once(task)) else {
+            return true;
+        };
+        match output {
+            Ok(output) => send_result(&channel, token, JobStatus::Completed, &output),
+            Err(error) => send_result(&channel, token, JobStatus::Failed, error.as_bytes()),
+        }
+        false
+    });
+}
@@ -0,0 +1,424 @@
+//! Named properties the host sets
+//!
+//! Instead of every app decoding its own "set the cube color" messages, apps declare the
+//! values a host may change once, with a system that applies them:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{HostPropertyApp, PropertyValue};
+//!
+//! fn setup(app: &mut App) {
+//!     app.add_host_property("camera.fov", PropertyValue::Float(45.0), set_fov);
+//! }
+//!
+//! fn set_fov(In(value): In<PropertyValue>, mut projections: Query<&mut Projection>) {
+//!     let PropertyValue::Float(degrees) = value else { return };
+//!     for mut projection in &mut projections {
+//!         if let Projection::Perspective(perspective) = projection.as_mut() {
+//!             perspective.fov = degrees.to_radians();
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! The host binds a property name to a small id of its choosing once, then sets it with
+//! compact messages on [`ReservedTopic::Properties`], a sequence of operations each
+//! starting with a [`PropertyOp`] byte:
+//!
+//! ```text
+//! Bind: id: u16 | name: string
+//! Set:  id: u16 | duration: f32 | easing: u8 | value: n × f32
+//! ```
+//!
+//! Strings are `u32 len + UTF-8`. Each bind is answered on [`ReservedTopic::PropertyBound`]
+//! with `id: u16 | kind: u8`, the [`PropertyKind`] that decides how many floats a set
+//! carries, or 255 if the app declared no such property. `duration` and `easing` are a
+//! [`Transition`]: a set with a positive duration tweens from the current value instead of
+//! snapping to it, and the apply system runs once per frame while the value changes.
+//!
+//! The commands are handled by [`HostPropertiesPlugin`], which declaring the first
+//! property adds.
+
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    color::{Color, LinearRgba, Mix},
+    ecs::{
+        message::MessageReader,
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{In, IntoSystem, Res, ResMut, SystemId},
+        world::World,
+    },
+    math::{Quat, Vec2, Vec3, Vec4},
+    time::{Real, Time},
+    transform::components::Transform,
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+    tween::{Transition, Tween},
+};
+
+/// Binds and sets host properties; see the [module docs](self)
+#[derive(Default)]
+pub struct HostPropertiesPlugin;
+
+impl Plugin for HostPropertiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostProperties>().add_systems(
+            PreUpdate,
+            (
+                receive_properties,
+                apply_host_properties.after(receive_properties),
+            ),
+        );
+    }
+}
+
+/// Operations in a properties command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PropertyOp {
+    /// Bind a property name to an id
+    Bind = 0,
+    /// Set a bound property
+    Set = 1,
+}
+
+impl PropertyOp {
+    /// Create a PropertyOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(PropertyOp::Bind),
+            1 => Some(PropertyOp::Set),
+            _ => None,
+        }
+    }
+}
+
+/// Type of a property's value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PropertyKind {
+    /// One float
+    Float = 0,
+    /// Two floats
+    Vec2 = 1,
+    /// Three floats
+    Vec3 = 2,
+    /// Four floats
+    Vec4 = 3,
+    /// sRGB red, green, blue and alpha, tweened in linear space
+    Color = 4,
+    /// Rotation as x, y, z and w, tweened along the shortest arc
+    Quat = 5,
+    /// Translation x, y, z, rotation x, y, z, w and scale x, y, z
+    Transform = 6,
+}
+
+impl PropertyKind {
+    /// Create a PropertyKind from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(PropertyKind::Float),
+            1 => Some(PropertyKind::Vec2),
+            2 => Some(PropertyKind::Vec3),
+            3 => Some(PropertyKind::Vec4),
+            4 => Some(PropertyKind::Color),
+            5 => Some(PropertyKind::Quat),
+            6 => Some(PropertyKind::Transform),
+            _ => None,
+        }
+    }
+
+    /// Number of floats in a value of this kind
+    pub fn floats(self) -> usize {
+        match self {
+            PropertyKind::Float => 1,
+            PropertyKind::Vec2 => 2,
+            PropertyKind::Vec3 => 3,
+            PropertyKind::Vec4 | PropertyKind::Color | PropertyKind::Quat => 4,
+            PropertyKind::Transform => 10,
+        }
+    }
+}
+
+/// Value of a host property
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PropertyValue {
+    /// A float
+    Float(f32),
+    /// A 2D vector
+    Vec2(Vec2),
+    /// A 3D vector
+    Vec3(Vec3),
+    /// A 4D vector
+    Vec4(Vec4),
+    /// A color
+    Color(Color),
+    /// A rotation
+    Quat(Quat),
+    /// A transform
+    Transform(Transform),
+}
+
+impl PropertyValue {
+    /// Kind of the value
+    pub fn kind(&self) -> PropertyKind {
+        match self {
+            PropertyValue::Float(_) => PropertyKind::Float,
+            PropertyValue::Vec2(_) => PropertyKind::Vec2,
+            PropertyValue::Vec3(_) => PropertyKind::Vec3,
+            PropertyValue::Vec4(_) => PropertyKind::Vec4,
+            PropertyValue::Color(_) => PropertyKind::Color,
+            PropertyValue::Quat(_) => PropertyKind::Quat,
+            PropertyValue::Transform(_) => PropertyKind::Transform,
+        }
+    }
+
+    /// Reads a value of `kind` from a command
+    fn read(kind: PropertyKind, reader: &mut ByteReader) -> Option<Self> {
+        let mut values = [0.0; 10];
+        for value in &mut values[..kind.floats()] {
+            *value = reader.f32()?;
+        }
+        let [x, y, z, w, ..] = values;
+        Some(match kind {
+            PropertyKind::Float => PropertyValue::Float(x),
+            PropertyKind::Vec2 => PropertyValue::Vec2(Vec2::new(x, y)),
+            PropertyKind::Vec3 => PropertyValue::Vec3(Vec3::new(x, y, z)),
+            PropertyKind::Vec4 => PropertyValue::Vec4(Vec4::new(x, y, z, w)),
+            PropertyKind::Color => PropertyValue::Color(Color::srgba(x, y, z, w)),
+            PropertyKind::Quat => PropertyValue::Quat(Quat::from_xyzw(x, y, z, w).normalize()),
+            PropertyKind::Transform => {
+                let [_, _, _, qx, qy, qz, qw, sx, sy, sz] = values;
+                PropertyValue::Transform(Transform {
+                    translation: Vec3::new(x, y, z),
+                    rotation: Quat::from_xyzw(qx, qy, qz, qw).normalize(),
+                    scale: Vec3::new(sx, sy, sz),
+                })
+            }
+        })
+    }
+
+    /// The value `t` of the way from `self` to `to`, which must be of the same kind
+    pub fn lerp(&self, to: &Self, t: f32) -> Self {
+        match (*self, *to) {
+            (PropertyValue::Float(a), PropertyValue::Float(b)) => {
+                PropertyValue::Float(a + (b - a) * t)
+            }
+            (PropertyValue::Vec2(a), PropertyValue::Vec2(b)) => PropertyValue::Vec2(a.lerp(b, t)),
+            (PropertyValue::Vec3(a), PropertyValue::Vec3(b)) => PropertyValue::Vec3(a.lerp(b, t)),
+            (PropertyValue::Vec4(a), PropertyValue::Vec4(b)) => PropertyValue::Vec4(a.lerp(b, t)),
+            (PropertyValue::Color(a), PropertyValue::Color(b)) => {
+                PropertyValue::Color(LinearRgba::from(a).mix(&LinearRgba::from(b), t).into())
+            }
+            (PropertyValue::Quat(a), PropertyValue::Quat(b)) => PropertyValue::Quat(a.slerp(b, t)),
+            (PropertyValue::Transform(a), PropertyValue::Transform(b)) => {
+                PropertyValue::Transform(Transform {
+                    translation: a.translation.lerp(b.translation, t),
+                    rotation: a.rotation.slerp(b.rotation, t),
+                    scale: a.scale.lerp(b.scale, t),
+                })
+            }
+            _ => *to,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HostProperty {
+    name: String,
+    value: PropertyValue,
+    tween: Option<Tween<PropertyValue>>,
+    apply: SystemId<In<PropertyValue>>,
+    changed: bool,
+}
+
+/// Properties declared with [`HostPropertyApp::add_host_property`] and their values
+#[derive(Resource, Debug, Default)]
+pub struct HostProperties {
+    properties: Vec<HostProperty>,
+    by_name: HashMap<String, usize>,
+    bindings: HashMap<u16, usize>,
+}
+
+impl HostProperties {
+    /// Current value of the property declared as `name`
+    pub fn get(&self, name: &str) -> Option<PropertyValue> {
+        let index = *self.by_name.get(name)?;
+        Some(self.properties[index].value)
+    }
+
+    /// Sets the property declared as `name` with `transition`; returns false if there is
+    /// no such property or `value` has another kind
+    pub fn set(&mut self, name: &str, value: PropertyValue, transition: Transition) -> bool {
+        match self.by_name.get(name) {
+            Some(&index) => self.set_index(index, value, transition),
+            None => false,
+        }
+    }
+
+    fn set_index(&mut self, index: usize, value: PropertyValue, transition: Transition) -> bool {
+        let property = &mut self.properties[index];
+        if property.value.kind() != value.kind() {
+            return false;
+        }
+        if !transition.is_instant() {
+            property.tween = Some(Tween::new(property.value, value, transition));
+        } else {
+            property.tween = None;
+            property.value = value;
+            property.changed = true;
+        }
+        true
+    }
+
+    /// Advances tweens by `delta` seconds
+    fn advance(&mut self, delta: f32) {
+        for property in &mut self.properties {
+            let Some(tween) = &mut property.tween else {
+                continue;
+            };
+            property.value = tween.advance(delta, PropertyValue::lerp);
+            property.changed = true;
+            if tween.is_finished() {
+                property.tween = None;
+            }
+        }
+    }
+}
+
+/// Extension trait for declaring host properties while building the app
+pub trait HostPropertyApp {
+    /// Lets the host set `name`, starting at `initial`
+    ///
+    /// `apply` is a one-shot system that gets each new value, including every step of a
+    /// tween. It isn't run for `initial`. Adds [`HostPropertiesPlugin`] if needed.
+    fn add_host_property<M>(
+        &mut self,
+        name: impl Into<String>,
+        initial: PropertyValue,
+        apply: impl IntoSystem<In<PropertyValue>, (), M> + 'static,
+    ) -> &mut Self;
+}
+
+impl HostPropertyApp for App {
+    fn add_host_property<M>(
+        &mut self,
+        name: impl Into<String>,
+        initial: PropertyValue,
+        apply: impl IntoSystem<In<PropertyValue>, (), M> + 'static,
+    ) -> &mut Self {
+        if !self.is_plugin_added::<HostPropertiesPlugin>() {
+            self.add_plugins(HostPropertiesPlugin);
+        }
+        let apply = self.world_mut().register_system(apply);
+        let name = name.into();
+        let mut properties = self.world_mut().resource_mut::<HostProperties>();
+        let property = HostProperty {
+            name: name.clone(),
+            value: initial,
+            tween: None,
+            apply,
+            changed: false,
+        };
+        match properties.by_name.get(&name) {
+            Some(&index) => properties.properties[index] = property,
+            None => {
+                let index = properties.properties.len();
+                properties.properties.push(property);
+                properties.by_name.insert(name, index);
+            }
+        }
+        self
+    }
+}
+
+/// Applies properties commands from the host
+fn receive_properties(
+    mut commands: MessageReader<HostCommand>,
+    mut properties: ResMut<HostProperties>,
+    channel: Res<HostChannel>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::Properties {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while let Some(op) = reader.u8() {
+            match PropertyOp::from_u8(op) {
+                Some(PropertyOp::Bind) => {
+                    let (Some(id), Some(name)) = (reader.u16(), reader.string()) else {
+                        log::warn!("Ignoring truncated property bind");
+                        break;
+                    };
+                    let kind = match properties.by_name.get(name).copied() {
+                        Some(index) => {
+                            properties.bindings.insert(id, index);
+                            properties.properties[index].value.kind() as u8
+                        }
+                        None => {
+                            log::warn!("No host property named {name}");
+                            properties.bindings.remove(&id);
+                            u8::MAX
+                        }
+                    };
+                    let mut payload = ByteWriter::new();
+                    payload.u16(id).u8(kind);
+                    channel.send_reserved(ReservedTopic::PropertyBound, &payload.finish());
+                }
+                Some(PropertyOp::Set) => {
+                    let (Some(id), Some(transition)) =
+                        (reader.u16(), Transition::read(&mut reader))
+                    else {
+                        log::warn!("Ignoring truncated property set");
+                        break;
+                    };
+                    // Without the kind the rest of the command can't be parsed
+                    let Some(&index) = properties.bindings.get(&id) else {
+                        log::warn!("Ignoring set of unbound property {id}");
+                        break;
+                    };
+                    let kind = properties.properties[index].value.kind();
+                    let Some(value) = PropertyValue::read(kind, &mut reader) else {
+                        log::warn!("Ignoring truncated property set");
+                        break;
+                    };
+                    properties.set_index(index, value, transition);
+                }
+                None => {
+                    log::warn!("Ignoring invalid property op {op}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Advances tweens and runs the apply systems of changed properties
+fn apply_host_properties(world: &mut World) {
+    let delta = world
+        .get_resource::<Time<Real>>()
+        .map_or(0.0, |time| time.delta_secs());
+    let mut properties = world.resource_mut::<HostProperties>();
+    properties.advance(delta);
+
+    let changed: Vec<_> = properties
+        .properties
+        .iter_mut()
+        .filter_map(|property| {
+            std::mem::take(&mut property.changed)
+                .then(|| (property.apply, property.value, property.name.clone()))
+        })
+        .collect();
+    for (apply, value, name) in changed {
+        if let Err(error) = world.run_system_with(apply, value) {
+            log::warn!("Applying host property {name} failed: {error}");
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Cameras and render layers switched by the host
+//!
+//! Apps declare their views once and let the host switch between them, e.g. an exploded
+//! and an assembled view of a product picked from a native segmented control. Cameras are
+//! named with [`HostCamera`], render layers with [`HostViewApp::add_host_render_layer`]:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{HostCamera, HostViewApp};
+//!
+//! fn setup(app: &mut App) {
+//!     app.add_host_render_layer("assembled", 1, true)
+//!         .add_host_render_layer("exploded", 2, false)
+//!         .add_systems(Startup, spawn_cameras);
+//! }
+//!
+//! fn spawn_cameras(mut commands: Commands) {
+//!     commands.spawn((Camera3d::default(), HostCamera::new("front")));
+//!     commands.spawn((
+//!         Camera3d::default(),
+//!         Camera { is_active: false, ..default() },
+//!         HostCamera::new("top"),
+//!     ));
+//! }
+//! ```
+//!
+//! Hosts send [`ReservedTopic::HostViews`] commands, a sequence of operations each
+//! starting with a [`HostViewOp`] byte:
+//!
+//! ```text
+//! SetCameraActive: name: string | active: u8
+//! SetLayerVisible: name: string | visible: u8
+//! ```
+//!
+//! Strings are `u32 len + UTF-8`. A camera op applies to every camera with that name. A
+//! named layer is added to or removed from the `RenderLayers` of every [`HostCamera`];
+//! layers the host doesn't know by name are left as the app set them.
+//!
+//! The commands are handled by [`HostViewsPlugin`], which adding the first render layer
+//! adds. Apps that only name cameras add it themselves.
+
+use std::collections::BTreeMap;
+
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate},
+    camera::{Camera, visibility::RenderLayers},
+    ecs::{
+        change_detection::{DetectChanges, Ref},
+        component::Component,
+        entity::Entity,
+        message::MessageReader,
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+};
+
+use crate::protocol::{ByteReader, HostCommand, ReservedTopic};
+
+/// Switches host cameras and render layers; see the [module docs](self)
+#[derive(Default)]
+pub struct HostViewsPlugin;
+
+impl Plugin for HostViewsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostRenderLayers>()
+            .add_systems(PreUpdate, receive_host_views)
+            .add_systems(PostUpdate, apply_host_render_layers);
+    }
+}
+
+/// Operations in a host views command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum HostViewOp {
+    /// Activate or deactivate the cameras with a name
+    SetCameraActive = 0,
+    /// Show or hide a named render layer
+    SetLayerVisible = 1,
+}
+
+impl HostViewOp {
+    /// Create a HostViewOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HostViewOp::SetCameraActive),
+            1 => Some(HostViewOp::SetLayerVisible),
+            _ => None,
+        }
+    }
+}
+
+/// Names a camera so the host can activate or deactivate it
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct HostCamera {
+    /// Name the host uses for the camera
+    pub name: String,
+}
+
+impl HostCamera {
+    /// A camera the host knows as `name`
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+/// A render layer the host can show or hide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HostLayer {
+    layer: usize,
+    visible: bool,
+}
+
+/// Render layers declared with [`HostViewApp::add_host_render_layer`]
+#[derive(Resource, Debug, Default)]
+pub struct HostRenderLayers {
+    layers: BTreeMap<String, HostLayer>,
+}
+
+impl HostRenderLayers {
+    /// The render layer declared as `name`
+    pub fn layer(&self, name: &str) -> Option<usize> {
+        self.layers.get(name).map(|layer| layer.layer)
+    }
+
+    /// Whether the layer declared as `name` is shown
+    pub fn is_visible(&self, name: &str) -> Option<bool> {
+        self.layers.get(name).map(|layer| layer.visible)
+    }
+
+    /// Shows or hides the layer declared as `name`, returning false if there is none
+    pub fn set_visible(&mut self, name: &str, visible: bool) -> bool {
+        match self.layers.get_mut(name) {
+            Some(layer) => {
+                layer.visible = visible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds shown layers to `layers` and removes hidden ones
+    fn apply(&self, mut layers: RenderLayers) -> RenderLayers {
+        for layer in self.layers.values() {
+            layers = if layer.visible {
+                layers.with(layer.layer)
+            } else {
+                layers.without(layer.layer)
+            };
+        }
+        layers
+    }
+}
+
+/// Extension trait for declaring host-controlled render layers while building the app
+pub trait HostViewApp {
+    /// Lets the host show and hide render `layer` as `name`, starting out `visible`
+    ///
+    /// Adds [`HostViewsPlugin`] if needed.
+    fn add_host_render_layer(
+        &mut self,
+        name: impl Into<String>,
+        layer: usize,
+        visible: bool,
+    ) -> &mut Self;
+}
+
+impl HostViewApp for App {
+    fn add_host_render_layer(
+        &mut self,
+        name: impl Into<String>,
+        layer: usize,
+        visible: bool,
+    ) -> &mut Self {
+        if !self.is_plugin_added::<HostViewsPlugin>() {
+            self.add_plugins(HostViewsPlugin);
+        }
+        self.world_mut()
+            .resource_mut::<HostRenderLayers>()
+            .layers
+            .insert(name.into(), HostLayer { layer, visible });
+        self
+    }
+}
+
+/// Applies host views commands
+fn receive_host_views(
+    mut commands: MessageReader<HostCommand>,
+    mut layers: ResMut<HostRenderLayers>,
+    mut cameras: Query<(&HostCamera, &mut Camera)>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::HostViews {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while let Some(op) = reader.u8() {
+            let (Some(name), Some(enabled)) = (reader.string(), reader.u8()) else {
+                log::warn!("Ignoring truncated host views command");
+                break;
+            };
+            let enabled = enabled != 0;
+
+            match HostViewOp::from_u8(op) {
+                Some(HostViewOp::SetCameraActive) => {
+                    let mut found = false;
+                    for (_, mut camera) in cameras.iter_mut().filter(|(c, _)| c.name == name) {
+                        camera.is_active = enabled;
+                        found = true;
+                    }
+                    if !found {
+                        log::warn!("No host camera named {name}");
+                    }
+                }
+                Some(HostViewOp::SetLayerVisible) => {
+                    if !layers.set_visible(name, enabled) {
+                        log::warn!("No host render layer named {name}");
+                    }
+                }
+                None => {
+                    log::warn!("Ignoring invalid host views op {op}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Updates the render layers of host cameras
+fn apply_host_render_layers(
+    mut commands: Commands,
+    layers: Res<HostRenderLayers>,
+    cameras: Query<(Entity, Ref<HostCamera>, Option<&RenderLayers>)>,
+) {
+    if layers.layers.is_empty() {
+        return;
+    }
+
+    for (entity, camera, render_layers) in &cameras {
+        if !layers.is_changed() && !camera.is_added() {
+            continue;
+        }
+        let current = render_layers.cloned().unwrap_or_default();
+        let updated = layers.apply(current.clone());
+        if updated != current {
+            commands.entity(entity).insert(updated);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Sending images to the host's photo library or share sheet
+//!
+//! Covers the "screenshot my creation" flow: [`ImageExporter`] captures the window (or
+//! takes an `Image` the app rendered itself), encodes it as PNG, and asks the host to
+//! save or share it with [`ReservedTopic::SaveImage`]:
+//!
+//! ```text
+//! request: u32 | action: u8 (ImageExportAction) | width: u32 | height: u32 | PNG bytes ...
+//! ```
+//!
+//! The host answers on [`ReservedTopic::ImageSaved`] with
+//! `request: u32 | status: u8 (ImageSaveStatus)`, delivered as an [`ImageSaved`] message.
+//! Encoding runs on the main thread, so expect a frame hitch for large images.
+
+use std::io::Cursor;
+
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{
+        message::{Message, MessageReader, MessageWriter},
+        observer::On,
+        resource::Resource,
+        system::{Commands, Res, ResMut, SystemParam},
+    },
+    image::{Image, ImageFormat},
+    render::view::window::screenshot::{Screenshot, ScreenshotCaptured},
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// What the host should do with an exported image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum ImageExportAction {
+    /// Save it to the photo library
+    #[default]
+    SaveToPhotos = 0,
+    /// Present the share sheet
+    Share = 1,
+}
+
+impl ImageExportAction {
+    /// Create an ImageExportAction from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ImageExportAction::SaveToPhotos),
+            1 => Some(ImageExportAction::Share),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of an image export reported by the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ImageSaveStatus {
+    /// The image was saved or shared
+    Done = 0,
+    /// The user cancelled
+    Cancelled = 1,
+    /// The host could not save it, e.g. without photo library permission
+    Failed = 2,
+}
+
+impl ImageSaveStatus {
+    /// Create an ImageSaveStatus from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ImageSaveStatus::Done),
+            1 => Some(ImageSaveStatus::Cancelled),
+            2 => Some(ImageSaveStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Sent when the host reports the outcome of an image export
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageSaved {
+    /// Request id returned by [`ImageExporter`]
+    pub request_id: u32,
+    /// What happened to the image
+    pub status: ImageSaveStatus,
+}
+
+/// Ids handed out to image exports
+#[derive(Resource, Debug, Default)]
+pub(crate) struct ImageExportRequests {
+    next_request: u32,
+}
+
+impl ImageExportRequests {
+    fn next(&mut self) -> u32 {
+        let request_id = self.next_request;
+        self.next_request = request_id.wrapping_add(1);
+        request_id
+    }
+}
+
+/// Encodes images and sends them to the host
+#[derive(SystemParam)]
+pub struct ImageExporter<'w, 's> {
+    commands: Commands<'w, 's>,
+    requests: ResMut<'w, ImageExportRequests>,
+    images: Res<'w, Assets<Image>>,
+    channel: Res<'w, HostChannel>,
+}
+
+impl ImageExporter<'_, '_> {
+    /// Captures the primary window and sends it to the host once it is read back
+    ///
+    /// Returns the request id of the [`ImageSaved`] answer.
+    pub fn capture_window(&mut self, action: ImageExportAction) -> u32 {
+        let request_id = self.requests.next();
+        self.commands.spawn(Screenshot::primary_window()).observe(
+            move |captured: On<ScreenshotCaptured>, channel: Res<HostChannel>| {
+                send_image(&channel, request_id, action, &captured.image);
+            },
+        );
+        request_id
+    }
+
+    /// Sends a loaded image to the host
+    ///
+    /// Returns the request id of the [`ImageSaved`] answer, or `None` if the image is not
+    /// loaded.
+    pub fn send(&mut self, image: &Handle<Image>, action: ImageExportAction) -> Option<u32> {
+        let image = self.images.get(image)?;
+        let request_id = self.requests.next();
+        send_image(&self.channel, request_id, action, image);
+        Some(request_id)
+    }
+}
+
+/// Encodes `image` as PNG, returning `None` if its format can't be converted
+fn encode_png(image: &Image) -> Option<Vec<u8>> {
+    let format = ImageFormat::Png.as_image_crate_format()?;
+    // Drop the alpha channel, which holds brightness rather than coverage with HDR
+    let rgb = image.clone().try_into_dynamic().ok()?.to_rgb8();
+    let mut png = Cursor::new(Vec::new());
+    rgb.write_to(&mut png, format).ok()?;
+    Some(png.into_inner())
+}
+
+fn send_image(channel: &HostChannel, request_id: u32, action: ImageExportAction, image: &Image) {
+    let Some(png) = encode_png(image) else {
+        log::warn!(
+            "Cannot export image in format {:?}",
+            image.texture_descriptor.format
+        );
+        return;
+    };
+
+    let mut payload = ByteWriter::new();
+    payload
+        .u32(request_id)
+        .u8(action as u8)
+        .u32(image.width())
+        .u32(image.height())
+        .bytes(&png);
+    channel.send_reserved(ReservedTopic::SaveImage, &payload.finish());
+}
+
+/// Delivers the host's answers to image exports
+pub(crate) fn receive_image_saved(
+    mut commands: MessageReader<HostCommand>,
+    mut saved: MessageWriter<ImageSaved>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::ImageSaved {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        let (Some(request_id), Some(status)) =
+            (reader.u32(), reader.u8().and_then(ImageSaveStatus::from_u8))
+        else {
+            log::warn!("Ignoring malformed image saved command");
+            continue;
+        };
+        saved.write(ImageSaved { request_id, status });
+    }
+}
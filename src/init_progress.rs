@@ -0,0 +1,53 @@
+//! Progress of plugin initialization after creation
+//!
+//! Creating an app builds its plugins but leaves finishing them, which installs the render
+//! device and pipeline cache and runs every plugin's `finish` and `cleanup`, to the first
+//! update after the surface is attached. Creation returns sooner, and apps created
+//! before their view exists don't finish against a missing surface. Hosts showing a
+//! splash screen poll [`InitProgress`] (`bevy_embedded_ios_init_progress`,
+//! `nativeInitProgress`) and hide it once the app is finished and has run a frame.
+//!
+//! Bevy creates the wgpu device itself while `RenderPlugin` builds, so that part still
+//! happens during creation; apps that must not block the UI thread there are created
+//! [asynchronously](crate::pending). Preloaded and asynchronously created apps finish
+//! while they are prepared, off the host's critical path.
+
+use bevy::{
+    app::{App, PluginsState},
+    ecs::query::{With, Without},
+    window::{PrimaryWindow, RawHandleWrapper},
+};
+
+/// How far the app's plugins have initialized
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitProgress {
+    /// Plugins are still preparing, e.g. waiting on asynchronous setup
+    Building = 0,
+    /// Plugins are ready, but the primary window has no surface to finish against
+    WaitingForSurface = 1,
+    /// Plugins are ready and finish in the next update
+    Ready = 2,
+    /// Plugins are finished; the next update renders
+    Finished = 3,
+}
+
+impl InitProgress {
+    /// The progress of `app`'s plugins
+    pub fn of(app: &mut App) -> Self {
+        match app.plugins_state() {
+            PluginsState::Adding => Self::Building,
+            PluginsState::Ready if awaiting_surface(app) => Self::WaitingForSurface,
+            PluginsState::Ready => Self::Ready,
+            PluginsState::Finished | PluginsState::Cleaned => Self::Finished,
+        }
+    }
+}
+
+/// Whether the primary window exists but has no surface yet
+fn awaiting_surface(app: &mut App) -> bool {
+    let world = app.world_mut();
+    let mut windows =
+        world.query_filtered::<(), (With<PrimaryWindow>, Without<RawHandleWrapper>)>();
+    windows.iter(world).next().is_some()
+}
@@ -1,6 +1,12 @@
 //! Input event handling for embedded windows
 
-use bevy::{ecs::resource::Resource, math::Vec2};
+use bevy::{
+    ecs::{entity::Entity, message::Message, resource::Resource},
+    input::keyboard::KeyCode,
+    math::Vec2,
+};
+
+use crate::KeyModifiers;
 
 /// Touch phase for touch input events
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -34,10 +40,94 @@ impl TouchPhase {
 pub struct EmbeddedTouchEvent {
     /// Touch phase
     pub phase: TouchPhase,
-    /// Touch position in logical pixels
+    /// Touch position in the host's coordinate space
+    ///
+    /// See [`EmbeddedConfig::touch_coordinates`](crate::EmbeddedConfig::touch_coordinates).
     pub position: Vec2,
     /// Unique identifier for this touch
     pub id: u64,
+    /// Contact shape and pressure, if the host reports them
+    pub contact: Option<TouchContact>,
+}
+
+/// Contact data some hosts report with a touch, such as Android `MotionEvent` axes
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TouchContact {
+    /// Pressure, normally `0.0..=1.0` but may exceed 1 on some devices (`AXIS_PRESSURE`)
+    pub pressure: f32,
+    /// Length of the major axis of the contact ellipse (`AXIS_TOUCH_MAJOR`)
+    ///
+    /// In host coordinates on [`EmbeddedTouchEvent`], logical pixels on
+    /// [`TouchContactInput`].
+    pub touch_major: f32,
+    /// Length of the minor axis of the contact ellipse (`AXIS_TOUCH_MINOR`)
+    pub touch_minor: f32,
+    /// Angle of the major axis in radians, clockwise from vertical (`AXIS_ORIENTATION`)
+    ///
+    /// Relative to the host surface on [`EmbeddedTouchEvent`], to the content (with the
+    /// [`SurfaceTransform`](crate::SurfaceTransform) undone) on [`TouchContactInput`].
+    pub orientation: f32,
+}
+
+/// Contact data for a touch, sent right after its `TouchInput`
+///
+/// Only touches whose host reported contact data get one, e.g. for palm rejection or
+/// brush dynamics. The pressure is also available as `TouchInput::force`.
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+pub struct TouchContactInput {
+    /// Window the touch is in
+    pub window: Entity,
+    /// Touch id, matching `TouchInput::id`
+    pub id: u64,
+    /// Contact data, with sizes in logical pixels
+    pub contact: TouchContact,
+}
+
+/// Rotation of a rotary input such as a Wear OS bezel or crown
+///
+/// Also delivered as a vertical `MouseWheel` in pixels, so scroll handling written for
+/// mice works unchanged.
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+pub struct RotaryInput {
+    /// Window the input is for
+    pub window: Entity,
+    /// Scroll distance in logical pixels, positive for clockwise rotation (scrolling
+    /// content down)
+    pub delta: f32,
+}
+
+/// A predicted touch sample from the host (UIKit predicted touches, Android
+/// `MotionPredictor`)
+#[derive(Debug, Clone)]
+pub struct EmbeddedPredictedTouch {
+    /// Predicted position in the host's coordinate space
+    pub position: Vec2,
+    /// Identifier of the touch the prediction extends
+    pub id: u64,
+}
+
+/// A hardware key press or release from the host
+#[derive(Debug, Clone)]
+pub struct EmbeddedKeyEvent {
+    /// Physical key
+    pub key_code: KeyCode,
+    /// True for a press, false for a release
+    pub pressed: bool,
+    /// True if the press is a key repeat
+    pub repeat: bool,
+    /// Modifiers the host reports as held, including the key itself if it is one
+    pub modifiers: KeyModifiers,
+    /// Text the key produces, if any
+    pub text: Option<String>,
+}
+
+/// Keyboard input from the host, in the order it happened
+#[derive(Debug, Clone)]
+pub enum EmbeddedKeyboardEvent {
+    /// A key was pressed or released
+    Key(EmbeddedKeyEvent),
+    /// The widget gained (true) or lost (false) keyboard focus
+    Focus(bool),
 }
 
 /// Resource that stores queued input events from the host application
@@ -45,6 +135,12 @@ pub struct EmbeddedTouchEvent {
 pub struct EmbeddedInputEvents {
     /// Queued touch events
     pub touch_events: Vec<EmbeddedTouchEvent>,
+    /// Predicted samples for the current frame
+    pub predicted_touches: Vec<EmbeddedPredictedTouch>,
+    /// Queued key and keyboard focus events
+    pub keyboard_events: Vec<EmbeddedKeyboardEvent>,
+    /// Queued rotary input deltas in the host's coordinate space
+    pub rotary_events: Vec<f32>,
 }
 
 impl EmbeddedInputEvents {
@@ -53,8 +149,73 @@ impl EmbeddedInputEvents {
         self.touch_events.push(event);
     }
 
+    /// Adds a predicted touch sample to the queue
+    pub fn add_predicted_touch(&mut self, event: EmbeddedPredictedTouch) {
+        self.predicted_touches.push(event);
+    }
+
+    /// Adds a key or keyboard focus event to the queue
+    pub fn add_keyboard_event(&mut self, event: EmbeddedKeyboardEvent) {
+        self.keyboard_events.push(event);
+    }
+
+    /// Adds a rotary input delta to the queue
+    pub fn add_rotary_event(&mut self, delta: f32) {
+        self.rotary_events.push(delta);
+    }
+
     /// Clears all queued events (called after processing)
     pub fn clear(&mut self) {
         self.touch_events.clear();
+        self.predicted_touches.clear();
+        self.keyboard_events.clear();
+        self.rotary_events.clear();
+    }
+}
+
+/// A predicted future position of a touch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictedTouch {
+    /// Window the touch is in
+    pub window: Entity,
+    /// Touch id, matching `TouchInput::id`
+    pub id: u64,
+    /// Predicted position in logical pixels
+    pub position: Vec2,
+}
+
+/// Where the host predicts active touches will be next, for drawing ahead of input
+///
+/// Replaced every frame: predictions only extend the touches of the frame they arrived
+/// with, and are superseded by the real `TouchInput` and new predictions next frame.
+/// Drawing tools render the predicted segment provisionally and drop it when the frame
+/// ends.
+#[derive(Resource, Debug, Default)]
+pub struct PredictedTouches {
+    pub(crate) touches: Vec<PredictedTouch>,
+}
+
+impl PredictedTouches {
+    /// All predictions this frame, in the order the host sent them
+    pub fn iter(&self) -> impl Iterator<Item = &PredictedTouch> {
+        self.touches.iter()
+    }
+
+    /// Predicted positions of one touch this frame, nearest first
+    pub fn for_touch(&self, id: u64) -> impl Iterator<Item = Vec2> + '_ {
+        self.touches
+            .iter()
+            .filter(move |touch| touch.id == id)
+            .map(|touch| touch.position)
+    }
+
+    /// The furthest predicted position of one touch this frame
+    pub fn latest(&self, id: u64) -> Option<Vec2> {
+        self.for_touch(id).last()
+    }
+
+    /// Returns true if there are no predictions this frame
+    pub fn is_empty(&self) -> bool {
+        self.touches.is_empty()
     }
 }
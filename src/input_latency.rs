@@ -0,0 +1,271 @@
+//! Measuring how long touches take to reach the screen
+//!
+//! Lag in an embedded widget can come from the host's event loop, from touches waiting in
+//! the FFI queue for the next update, from the frame itself or from the compositor.
+//! While [`InputLatency`] is present, each touch is timed through these stages:
+//!
+//! ```text
+//! Host:    host timestamp → FFI call   (bevy_embedded_ios_input_timestamp, nativeInputTimestamp)
+//! Queue:   FFI call → Bevy TouchInput
+//! Frame:   Bevy TouchInput → frame submitted
+//! Present: frame submitted → present callback (bevy_embedded_ios_frame_presented, nativeFramePresented)
+//! Total:   earliest known time → present callback
+//! ```
+//!
+//! Hosts turn measuring on with `bevy_embedded_ios_set_latency_measurement` /
+//! `nativeSetLatencyMeasurement`. The `Host` stage needs the touch's timestamp, reported
+//! right after the touch on the clock passed to [`sync_clock`](crate::ffi::sync_clock),
+//! and presents are matched to submitted frames in order. Stages the host doesn't report
+//! are left out. Every [`InputLatency::report_interval`] the percentiles of the touches
+//! since the last report are sent on [`ReservedTopic::InputLatency`]:
+//!
+//! ```text
+//! stages: u8 | stages × (stage: u8 | samples: u32 | p50 ms: f32 | p90 ms: f32 | p99 ms: f32)
+//! ```
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use bevy::ecs::{resource::Resource, system::ResMut, world::World};
+
+use crate::{
+    HostChannel, HostClock,
+    protocol::{ByteWriter, ReservedTopic},
+};
+
+/// Most submitted frames kept waiting for their present callback
+const MAX_UNPRESENTED_FRAMES: usize = 8;
+
+/// Parts of a touch's path to the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum LatencyStage {
+    /// From the host's timestamp of the touch to the FFI call queuing it
+    Host = 0,
+    /// From the FFI call to the `TouchInput` message at the start of an update
+    Queue = 1,
+    /// From the `TouchInput` message to the end of the update that submitted the frame
+    Frame = 2,
+    /// From the frame's submission to the host's present callback
+    Present = 3,
+    /// From the earliest known time of the touch to its frame's present
+    Total = 4,
+}
+
+impl LatencyStage {
+    /// Every stage, in the order of a touch's path
+    pub const ALL: [LatencyStage; 5] = [
+        LatencyStage::Host,
+        LatencyStage::Queue,
+        LatencyStage::Frame,
+        LatencyStage::Present,
+        LatencyStage::Total,
+    ];
+
+    /// Create a LatencyStage from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+}
+
+/// Latency percentiles of one stage over a report interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    /// Number of touches measured
+    pub samples: u32,
+    /// Median latency
+    pub p50: Duration,
+    /// 90th percentile latency
+    pub p90: Duration,
+    /// 99th percentile latency
+    pub p99: Duration,
+}
+
+impl LatencyPercentiles {
+    fn new(durations: &mut [Duration]) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort_unstable();
+        let percentile = |p: usize| durations[(durations.len() - 1) * p / 100];
+        Some(Self {
+            samples: durations.len() as u32,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+        })
+    }
+}
+
+/// Timing of one touch so far
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    captured: Option<Instant>,
+    queued: Instant,
+    delivered: Option<Instant>,
+}
+
+impl Sample {
+    fn started(&self) -> Instant {
+        self.captured.unwrap_or(self.queued)
+    }
+}
+
+/// Per-stage touch latencies; measuring is on while this resource exists
+///
+/// Inserted by the host, or by the app to measure in development builds.
+#[derive(Resource, Debug)]
+pub struct InputLatency {
+    /// How often percentiles are sent to the host
+    pub report_interval: Duration,
+    /// Touches queued since the last update, and delivered during the current one
+    pending: Vec<Sample>,
+    /// Frames waiting for their present callback, oldest first
+    unpresented: VecDeque<(Instant, Vec<Sample>)>,
+    durations: [Vec<Duration>; LatencyStage::ALL.len()],
+    last_report: Instant,
+}
+
+impl Default for InputLatency {
+    fn default() -> Self {
+        Self {
+            report_interval: Duration::from_secs(1),
+            pending: Vec::new(),
+            unpresented: VecDeque::new(),
+            durations: Default::default(),
+            last_report: Instant::now(),
+        }
+    }
+}
+
+impl InputLatency {
+    /// Percentiles of `stage` for the touches measured since the last report
+    pub fn percentiles(&self, stage: LatencyStage) -> Option<LatencyPercentiles> {
+        LatencyPercentiles::new(&mut self.durations[stage as usize].clone())
+    }
+
+    fn measure(&mut self, stage: LatencyStage, from: Instant, to: Instant) {
+        self.durations[stage as usize].push(to.saturating_duration_since(from));
+    }
+
+    /// Encodes the percentiles for the host and starts a new interval
+    fn report(&mut self) -> Vec<u8> {
+        let stages: Vec<_> = LatencyStage::ALL
+            .into_iter()
+            .filter_map(|stage| {
+                LatencyPercentiles::new(&mut self.durations[stage as usize])
+                    .map(|percentiles| (stage, percentiles))
+            })
+            .collect();
+        self.durations.iter_mut().for_each(Vec::clear);
+        let millis = |duration: Duration| duration.as_secs_f32() * 1000.0;
+
+        let mut payload = ByteWriter::new();
+        payload.u8(stages.len() as u8);
+        for (stage, percentiles) in stages {
+            payload
+                .u8(stage as u8)
+                .u32(percentiles.samples)
+                .f32(millis(percentiles.p50))
+                .f32(millis(percentiles.p90))
+                .f32(millis(percentiles.p99));
+        }
+        payload.finish()
+    }
+}
+
+/// Starts timing a touch the host just queued, if measuring
+pub(crate) fn touch_queued(world: &mut World) {
+    if let Some(mut latency) = world.get_resource_mut::<InputLatency>() {
+        latency.pending.push(Sample {
+            captured: None,
+            queued: Instant::now(),
+            delivered: None,
+        });
+    }
+}
+
+/// Attaches the host's timestamp, in seconds, to the touch queued last
+pub(crate) fn touch_captured(world: &mut World, host_time: f64) {
+    let captured = world
+        .get_resource::<HostClock>()
+        .and_then(|clock| clock.to_instant(host_time));
+    let Some(mut latency) = world.get_resource_mut::<InputLatency>() else {
+        return;
+    };
+    let Some(sample) = latency.pending.last_mut() else {
+        return;
+    };
+    sample.captured = captured;
+    let queued = sample.queued;
+    if let Some(captured) = captured {
+        latency.measure(LatencyStage::Host, captured, queued);
+    }
+}
+
+/// Marks the queued touches as delivered to Bevy
+pub(crate) fn deliver_timed_touches(latency: Option<ResMut<InputLatency>>) {
+    let Some(mut latency) = latency else {
+        return;
+    };
+    let now = Instant::now();
+    for sample in &mut latency.pending {
+        sample.delivered.get_or_insert(now);
+    }
+}
+
+/// Ends the frame the delivered touches were handled in, and reports if it's time to
+pub(crate) fn frame_submitted(world: &mut World) {
+    let Some(mut latency) = world.get_resource_mut::<InputLatency>() else {
+        return;
+    };
+    let now = Instant::now();
+    // Touches queued after this update's input was processed wait for the next one
+    let (delivered, pending): (Vec<_>, Vec<_>) = latency
+        .pending
+        .drain(..)
+        .partition(|sample| sample.delivered.is_some());
+    latency.pending = pending;
+
+    for sample in &delivered {
+        let arrived = sample.delivered.unwrap_or(now);
+        latency.measure(LatencyStage::Queue, sample.queued, arrived);
+        latency.measure(LatencyStage::Frame, arrived, now);
+    }
+    if latency.unpresented.len() == MAX_UNPRESENTED_FRAMES {
+        latency.unpresented.pop_front();
+    }
+    latency.unpresented.push_back((now, delivered));
+
+    if now.duration_since(latency.last_report) < latency.report_interval {
+        return;
+    }
+    latency.last_report = now;
+    if latency.durations.iter().all(Vec::is_empty) {
+        return;
+    }
+    let payload = latency.report();
+    world
+        .resource::<HostChannel>()
+        .send_reserved(ReservedTopic::InputLatency, &payload);
+}
+
+/// Ends the oldest submitted frame at the host's present time, in seconds
+pub(crate) fn frame_presented(world: &mut World, host_time: f64) {
+    let presented = world
+        .get_resource::<HostClock>()
+        .and_then(|clock| clock.to_instant(host_time))
+        .unwrap_or_else(Instant::now);
+    let Some(mut latency) = world.get_resource_mut::<InputLatency>() else {
+        return;
+    };
+    let Some((submitted, samples)) = latency.unpresented.pop_front() else {
+        return;
+    };
+    for sample in samples {
+        latency.measure(LatencyStage::Present, submitted, presented);
+        latency.measure(LatencyStage::Total, sample.started(), presented);
+    }
+}
@@ -0,0 +1,464 @@
+//! World inspector for native debug menus
+//!
+//! QA builds of host apps can show an in-app inspector for the widget with their own UI.
+//! The protocol is smaller than the Bevy Remote Protocol and shaped for list and detail
+//! screens: list entities grouped by archetype, expand one entity into the leaf fields of
+//! its reflected components, and edit one field. Hosts send [`ReservedTopic::Inspect`]
+//! requests:
+//!
+//! ```text
+//! request: u32 | op: u8 (InspectOp) | arguments
+//! List:   limit: u32 (entities listed per archetype)
+//! Expand: entity: u64 (Entity::to_bits)
+//! Edit:   entity: u64 | component type path: string | field path: string | value
+//! ```
+//!
+//! and get one [`ReservedTopic::Inspection`] response per request:
+//!
+//! ```text
+//! request: u32 | op: u8 | status: u8 (InspectStatus) | result
+//! List:   archetypes: u32 × (entities: u32 | components: u16 × short name: string
+//!                            | listed: u32 × (entity: u64 | name: string))
+//! Expand: components: u16 × (short name: string | type path: string | reflected: u8
+//!                            | fields: u32 × (path: string | value))
+//! Edit:   (nothing)
+//! ```
+//!
+//! Strings are `u32 len + UTF-8`, and names are empty for entities without a [`Name`].
+//! Values are a [`FieldValue`] kind byte followed by its data; field paths use
+//! `bevy_reflect` path syntax (`.translation.x`, `.items[2]`). Only components whose
+//! types are registered with `ReflectComponent` are expanded and edited.
+//!
+//! The inspector reads and writes any reflected state, so it is behind the `inspector`
+//! cargo feature, meant for development and QA builds only.
+
+use bevy::{
+    ecs::{
+        entity::Entity,
+        message::{MessageCursor, Messages},
+        name::Name,
+        reflect::{AppTypeRegistry, ReflectComponent},
+        system::Local,
+        world::World,
+    },
+    reflect::{GetPath, PartialReflect, ReflectRef, TypeRegistry},
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Deepest nesting of fields expanded
+const MAX_FIELD_DEPTH: usize = 8;
+
+/// Most elements of a list or array expanded
+const MAX_LIST_ELEMENTS: usize = 32;
+
+/// Operations of an inspect request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum InspectOp {
+    /// List entities grouped by archetype
+    List = 0,
+    /// Expand the reflected components of one entity
+    Expand = 1,
+    /// Set one field of a component
+    Edit = 2,
+}
+
+impl InspectOp {
+    /// Create an InspectOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(InspectOp::List),
+            1 => Some(InspectOp::Expand),
+            2 => Some(InspectOp::Edit),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of an inspect request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum InspectStatus {
+    /// The request succeeded
+    Ok = 0,
+    /// The entity doesn't exist
+    NoEntity = 1,
+    /// The entity has no reflected component with that type path
+    NoComponent = 2,
+    /// The component has no field at that path
+    NoField = 3,
+    /// The value's kind or range doesn't fit the field
+    InvalidValue = 4,
+    /// The request couldn't be decoded
+    Malformed = 5,
+}
+
+/// Value of a leaf field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    /// Kind 0, `u8`
+    Bool(bool),
+    /// Kind 1, `i64`; any signed integer field
+    Int(i64),
+    /// Kind 2, `u64`; any unsigned integer field
+    Uint(u64),
+    /// Kind 3, `f64`; `f32` and `f64` fields
+    Float(f64),
+    /// Kind 4, string; `String` fields
+    String(String),
+    /// Kind 5, string; the debug text of a value that can't be edited, or an enum's variant
+    Text(String),
+}
+
+impl FieldValue {
+    /// Reads a kind byte and its data
+    pub fn read(reader: &mut ByteReader) -> Option<Self> {
+        match reader.u8()? {
+            0 => reader.bool().map(FieldValue::Bool),
+            1 => reader.u64().map(|value| FieldValue::Int(value as i64)),
+            2 => reader.u64().map(FieldValue::Uint),
+            3 => reader.f64().map(FieldValue::Float),
+            4 => reader
+                .string()
+                .map(|value| FieldValue::String(value.to_owned())),
+            5 => reader
+                .string()
+                .map(|value| FieldValue::Text(value.to_owned())),
+            _ => None,
+        }
+    }
+
+    /// Writes the kind byte and data
+    pub fn write(&self, writer: &mut ByteWriter) {
+        match self {
+            FieldValue::Bool(value) => writer.u8(0).bool(*value),
+            FieldValue::Int(value) => writer.u8(1).u64(*value as u64),
+            FieldValue::Uint(value) => writer.u8(2).u64(*value),
+            FieldValue::Float(value) => writer.u8(3).f64(*value),
+            FieldValue::String(value) => writer.u8(4).string(value),
+            FieldValue::Text(value) => writer.u8(5).string(value),
+        };
+    }
+
+    /// The value of a primitive field, or `None` for values with fields of their own
+    fn of(value: &dyn PartialReflect) -> Option<Self> {
+        macro_rules! downcast {
+            ($variant:ident as $target:ty: $($ty:ty),*) => {
+                $(if let Some(value) = value.try_downcast_ref::<$ty>() {
+                    return Some(FieldValue::$variant(*value as $target));
+                })*
+            };
+        }
+
+        if let Some(value) = value.try_downcast_ref::<bool>() {
+            return Some(FieldValue::Bool(*value));
+        }
+        downcast!(Int as i64: i8, i16, i32, i64, isize);
+        downcast!(Uint as u64: u8, u16, u32, u64, usize);
+        downcast!(Float as f64: f32, f64);
+        if let Some(value) = value.try_downcast_ref::<String>() {
+            return Some(FieldValue::String(value.clone()));
+        }
+        None
+    }
+
+    /// Writes this value into a primitive field, returning false if it doesn't fit
+    fn apply(&self, field: &mut dyn PartialReflect) -> bool {
+        macro_rules! convert {
+            ($value:expr => $($ty:ty),*) => {
+                $(if let Some(field) = field.try_downcast_mut::<$ty>() {
+                    return <$ty>::try_from($value).map(|value| *field = value).is_ok();
+                })*
+            };
+        }
+
+        match self {
+            FieldValue::Bool(value) => field
+                .try_downcast_mut::<bool>()
+                .map(|field| *field = *value)
+                .is_some(),
+            FieldValue::Int(value) => {
+                convert!(*value => i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+                false
+            }
+            FieldValue::Uint(value) => {
+                convert!(*value => i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+                false
+            }
+            FieldValue::Float(value) => {
+                if let Some(field) = field.try_downcast_mut::<f32>() {
+                    *field = *value as f32;
+                    true
+                } else if let Some(field) = field.try_downcast_mut::<f64>() {
+                    *field = *value;
+                    true
+                } else {
+                    false
+                }
+            }
+            FieldValue::String(value) => field
+                .try_downcast_mut::<String>()
+                .map(|field| field.clone_from(value))
+                .is_some(),
+            FieldValue::Text(_) => false,
+        }
+    }
+}
+
+/// Appends the leaf fields of `value` under `path`
+fn flatten(
+    value: &dyn PartialReflect,
+    path: &str,
+    depth: usize,
+    fields: &mut Vec<(String, FieldValue)>,
+) {
+    if let Some(leaf) = FieldValue::of(value) {
+        fields.push((path.to_owned(), leaf));
+        return;
+    }
+    let text = || FieldValue::Text(format!("{value:?}"));
+    if depth == MAX_FIELD_DEPTH {
+        fields.push((path.to_owned(), text()));
+        return;
+    }
+
+    let depth = depth + 1;
+    match value.reflect_ref() {
+        ReflectRef::Struct(value) => {
+            for index in 0..value.field_len() {
+                if let (Some(name), Some(field)) = (value.name_at(index), value.field_at(index)) {
+                    flatten(field, &format!("{path}.{name}"), depth, fields);
+                }
+            }
+        }
+        ReflectRef::TupleStruct(value) => {
+            for (index, field) in value.iter_fields().enumerate() {
+                flatten(field, &format!("{path}.{index}"), depth, fields);
+            }
+        }
+        ReflectRef::Tuple(value) => {
+            for (index, field) in value.iter_fields().enumerate() {
+                flatten(field, &format!("{path}.{index}"), depth, fields);
+            }
+        }
+        ReflectRef::List(value) => {
+            for (index, element) in value.iter().take(MAX_LIST_ELEMENTS).enumerate() {
+                flatten(element, &format!("{path}[{index}]"), depth, fields);
+            }
+        }
+        ReflectRef::Array(value) => {
+            for (index, element) in value.iter().take(MAX_LIST_ELEMENTS).enumerate() {
+                flatten(element, &format!("{path}[{index}]"), depth, fields);
+            }
+        }
+        ReflectRef::Enum(value) => {
+            fields.push((
+                path.to_owned(),
+                FieldValue::Text(value.variant_name().to_owned()),
+            ));
+            for index in 0..value.field_len() {
+                let Some(field) = value.field_at(index) else {
+                    continue;
+                };
+                match value.name_at(index) {
+                    Some(name) => flatten(field, &format!("{path}.{name}"), depth, fields),
+                    None => flatten(field, &format!("{path}.{index}"), depth, fields),
+                }
+            }
+        }
+        _ => fields.push((path.to_owned(), text())),
+    }
+}
+
+/// Short name of a component for list screens
+fn short_name(
+    world: &World,
+    registry: &TypeRegistry,
+    id: bevy::ecs::component::ComponentId,
+) -> String {
+    let Some(info) = world.components().get_info(id) else {
+        return String::new();
+    };
+    info.type_id()
+        .and_then(|type_id| registry.get(type_id))
+        .map(|registration| {
+            registration
+                .type_info()
+                .type_path_table()
+                .short_path()
+                .to_owned()
+        })
+        .unwrap_or_else(|| info.name().shortname().to_string())
+}
+
+fn list(world: &World, registry: &TypeRegistry, reader: &mut ByteReader, payload: &mut ByteWriter) {
+    let Some(limit) = reader.u32() else {
+        payload.u8(InspectStatus::Malformed as u8);
+        return;
+    };
+
+    let archetypes: Vec<_> = world
+        .archetypes()
+        .iter()
+        .filter(|archetype| !archetype.is_empty())
+        .collect();
+    payload
+        .u8(InspectStatus::Ok as u8)
+        .u32(archetypes.len() as u32);
+    for archetype in archetypes {
+        payload
+            .u32(archetype.len())
+            .u16(archetype.component_count() as u16);
+        for id in archetype.components() {
+            payload.string(&short_name(world, registry, *id));
+        }
+
+        let listed: Vec<Entity> = archetype
+            .entities()
+            .iter()
+            .take(limit as usize)
+            .map(|entity| entity.id())
+            .collect();
+        payload.u32(listed.len() as u32);
+        for entity in listed {
+            let name = world.get::<Name>(entity).map(Name::as_str).unwrap_or("");
+            payload.u64(entity.to_bits()).string(name);
+        }
+    }
+}
+
+fn expand(
+    world: &World,
+    registry: &TypeRegistry,
+    reader: &mut ByteReader,
+    payload: &mut ByteWriter,
+) {
+    let Some(entity) = reader.u64() else {
+        payload.u8(InspectStatus::Malformed as u8);
+        return;
+    };
+    let Some(entity) =
+        Entity::try_from_bits(entity).and_then(|entity| world.get_entity(entity).ok())
+    else {
+        payload.u8(InspectStatus::NoEntity as u8);
+        return;
+    };
+
+    let components = entity.archetype().components();
+    payload
+        .u8(InspectStatus::Ok as u8)
+        .u16(components.len() as u16);
+    for &id in components {
+        let registration = world
+            .components()
+            .get_info(id)
+            .and_then(|info| info.type_id())
+            .and_then(|type_id| registry.get(type_id));
+        let type_path =
+            registration.map_or("", |registration| registration.type_info().type_path());
+        let reflected = registration
+            .and_then(|registration| registration.data::<ReflectComponent>())
+            .and_then(|reflect| reflect.reflect(entity));
+        payload
+            .string(&short_name(world, registry, id))
+            .string(type_path)
+            .bool(reflected.is_some());
+
+        let mut fields = Vec::new();
+        if let Some(value) = reflected {
+            flatten(value.as_partial_reflect(), "", 0, &mut fields);
+        }
+        payload.u32(fields.len() as u32);
+        for (path, value) in fields {
+            payload.string(&path);
+            value.write(payload);
+        }
+    }
+}
+
+fn edit(world: &mut World, registry: &TypeRegistry, reader: &mut ByteReader) -> InspectStatus {
+    let (Some(entity), Some(type_path), Some(path), Some(value)) = (
+        reader.u64(),
+        reader.string(),
+        reader.string(),
+        FieldValue::read(reader),
+    ) else {
+        return InspectStatus::Malformed;
+    };
+    let Some(mut entity) =
+        Entity::try_from_bits(entity).and_then(|entity| world.get_entity_mut(entity).ok())
+    else {
+        return InspectStatus::NoEntity;
+    };
+    let Some(mut component) = registry
+        .get_with_type_path(type_path)
+        .and_then(|registration| registration.data::<ReflectComponent>())
+        .and_then(|reflect| reflect.reflect_mut(&mut entity))
+    else {
+        return InspectStatus::NoComponent;
+    };
+
+    // An empty path edits a primitive component itself
+    let field = if path.is_empty() {
+        Ok(component.as_partial_reflect_mut())
+    } else {
+        component.reflect_path_mut(path)
+    };
+    let Ok(field) = field else {
+        return InspectStatus::NoField;
+    };
+    if value.apply(field) {
+        InspectStatus::Ok
+    } else {
+        InspectStatus::InvalidValue
+    }
+}
+
+/// Answers inspect requests from the host
+pub(crate) fn receive_inspect_requests(
+    world: &mut World,
+    mut cursor: Local<MessageCursor<HostCommand>>,
+) {
+    let requests: Vec<Vec<u8>> = cursor
+        .read(world.resource::<Messages<HostCommand>>())
+        .filter(|command| command.topic == ReservedTopic::Inspect)
+        .map(|command| command.payload.clone())
+        .collect();
+    if requests.is_empty() {
+        return;
+    }
+    let Some(registry) = world.get_resource::<AppTypeRegistry>().cloned() else {
+        log::warn!("Ignoring inspect requests without an AppTypeRegistry");
+        return;
+    };
+    let registry = registry.read();
+
+    for request in requests {
+        let mut reader = ByteReader::new(&request);
+        let (Some(request_id), Some(op)) = (reader.u32(), reader.u8()) else {
+            log::warn!("Ignoring malformed inspect request");
+            continue;
+        };
+
+        let mut payload = ByteWriter::new();
+        payload.u32(request_id).u8(op);
+        match InspectOp::from_u8(op) {
+            Some(InspectOp::List) => list(world, &registry, &mut reader, &mut payload),
+            Some(InspectOp::Expand) => expand(world, &registry, &mut reader, &mut payload),
+            Some(InspectOp::Edit) => {
+                payload.u8(edit(world, &registry, &mut reader) as u8);
+            }
+            None => {
+                log::warn!("Ignoring inspect request with unknown operation {op}");
+                payload.u8(InspectStatus::Malformed as u8);
+            }
+        }
+        world
+            .resource::<HostChannel>()
+            .send_reserved(ReservedTopic::Inspection, &payload.finish());
+    }
+}
@@ -5,8 +5,6 @@
 #![allow(unsafe_code)]
 
 use bevy::app::App;
-use bevy::ecs::query::With;
-use bevy::math::Vec2;
 use bevy::window::{
     PrimaryWindow, RawHandleWrapper, RawHandleWrapperHolder, Window, WindowResolution,
     WindowWrapper,
@@ -15,11 +13,58 @@ use raw_window_handle::{
     HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle,
     UiKitDisplayHandle, UiKitWindowHandle,
 };
-use std::ffi::c_void;
+use std::cell::Cell;
+use std::ffi::{c_char, c_void};
 use std::ptr::NonNull;
 use std::sync::{Arc, Mutex};
 
-use crate::{EmbeddedInputEvents, EmbeddedTouchEvent, HostChannel, TouchPhase};
+use crate::{
+    EmbeddedConfig, HandshakeStatus, HostConfig, HostScene, InitProgress, KeyModifiers, ffi,
+    key_code_from_hid_usage, pending, preload, replay,
+};
+
+/// Opaque `CGColorSpace`
+#[cfg(feature = "wide_color")]
+#[repr(C)]
+struct CGColorSpace {
+    _private: [u8; 0],
+}
+
+#[cfg(feature = "wide_color")]
+unsafe impl objc2::encode::RefEncode for CGColorSpace {
+    const ENCODING_REF: objc2::encode::Encoding =
+        objc2::encode::Encoding::Pointer(&objc2::encode::Encoding::Struct("CGColorSpace", &[]));
+}
+
+#[cfg(feature = "wide_color")]
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    static kCGColorSpaceExtendedLinearSRGB: *const c_void;
+    fn CGColorSpaceCreateWithName(name: *const c_void) -> *mut CGColorSpace;
+    fn CGColorSpaceRelease(space: *mut CGColorSpace);
+}
+
+/// Tags the view's `CAMetalLayer` as holding extended linear sRGB, which `Rgba16Float`
+/// swapchains of the [color output](crate::ColorOutputPlugin) present
+///
+/// Must be called on the main thread with the host's `UIView`.
+#[cfg(feature = "wide_color")]
+pub(crate) unsafe fn tag_extended_linear_srgb(ui_view: *mut c_void) {
+    use objc2::{msg_send, runtime::AnyObject};
+
+    let view = ui_view as *mut AnyObject;
+    let layer: *mut AnyObject = msg_send![view, layer];
+    if layer.is_null() {
+        return;
+    }
+    let space = CGColorSpaceCreateWithName(kCGColorSpaceExtendedLinearSRGB);
+    if space.is_null() {
+        log::warn!("Extended linear sRGB color space is not available");
+        return;
+    }
+    let _: () = msg_send![layer, setColorspace: space];
+    CGColorSpaceRelease(space);
+}
 
 /// Wrapper for the UIView that implements the required traits
 struct MetalViewWrapper {
@@ -52,6 +97,7 @@ impl HasDisplayHandle for MetalViewWrapper {
 
 /// Surface info returned from the host app
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct EmbeddedSurfaceInfo {
     pub ui_view: *const c_void,
     pub width: u32,
@@ -59,6 +105,39 @@ pub struct EmbeddedSurfaceInfo {
     pub scale_factor: f32,
 }
 
+/// Creates the raw handle for a host `UIView`
+fn view_handle(ui_view: *const c_void) -> Option<RawHandleWrapper> {
+    let view_wrapper = MetalViewWrapper {
+        window_handle: UiKitWindowHandle::new(NonNull::new(ui_view as *mut _)?),
+        display_handle: UiKitDisplayHandle::new(),
+    };
+
+    let window_wrapper = WindowWrapper::new(view_wrapper);
+    RawHandleWrapper::new(&window_wrapper).ok()
+}
+
+thread_local! {
+    /// Scene and surface of the app being created by `bevy_embedded_ios_create_app_for_scene`
+    static SCENE_SURFACE: Cell<Option<(u64, EmbeddedSurfaceInfo)>> = const { Cell::new(None) };
+}
+
+/// Runs `create` with the window of the created app using `surface` of host scene `scene`
+/// instead of asking `bevy_embedded_get_surface`
+///
+/// Used by `bevy_embedded_ios_create_app_for_scene`, so scenes created concurrently don't
+/// share the host's global surface.
+#[doc(hidden)]
+pub fn with_scene_surface<R>(
+    scene: u64,
+    surface: EmbeddedSurfaceInfo,
+    create: impl FnOnce() -> R,
+) -> R {
+    SCENE_SURFACE.with(|current| current.set(Some((scene, surface))));
+    let result = create();
+    SCENE_SURFACE.with(|current| current.set(None));
+    result
+}
+
 /// Called by EmbeddedPlugin during finish() to create the window
 /// This requests the native surface from the host application
 pub fn create_window_from_host(app: &mut App) {
@@ -67,15 +146,23 @@ pub fn create_window_from_host(app: &mut App) {
         fn bevy_embedded_get_surface(out: *mut EmbeddedSurfaceInfo);
     }
 
-    let mut surface_info = EmbeddedSurfaceInfo {
-        ui_view: std::ptr::null(),
-        width: 0,
-        height: 0,
-        scale_factor: 1.0,
+    let surface_info = match SCENE_SURFACE.with(Cell::take) {
+        Some((scene, surface_info)) => {
+            app.insert_resource(HostScene::new(scene));
+            surface_info
+        }
+        None => {
+            let mut surface_info = EmbeddedSurfaceInfo {
+                ui_view: std::ptr::null(),
+                width: 0,
+                height: 0,
+                scale_factor: 1.0,
+            };
+            unsafe { bevy_embedded_get_surface(&mut surface_info) };
+            surface_info
+        }
     };
 
-    unsafe { bevy_embedded_get_surface(&mut surface_info) };
-
     if surface_info.ui_view.is_null() {
         log::error!("Host did not provide a valid surface");
         return;
@@ -88,18 +175,9 @@ pub fn create_window_from_host(app: &mut App) {
         surface_info.scale_factor
     );
 
-    // Create the view wrapper for raw-window-handle
-    let view_wrapper = MetalViewWrapper {
-        window_handle: unsafe {
-            UiKitWindowHandle::new(NonNull::new_unchecked(surface_info.ui_view as *mut _))
-        },
-        display_handle: UiKitDisplayHandle::new(),
-    };
-
     // Create WindowWrapper and RawHandleWrapper
-    let window_wrapper = WindowWrapper::new(view_wrapper);
     let handle_wrapper =
-        RawHandleWrapper::new(&window_wrapper).expect("Failed to create RawHandleWrapper");
+        view_handle(surface_info.ui_view).expect("Failed to create RawHandleWrapper");
 
     let handle_holder = RawHandleWrapperHolder(Arc::new(Mutex::new(Some(handle_wrapper.clone()))));
 
@@ -116,6 +194,47 @@ pub fn create_window_from_host(app: &mut App) {
     log::info!("Embedded window created successfully");
 }
 
+/// Create an empty creation config to pass to `bevy_embedded_create_app_with_config`,
+/// `bevy_embedded_create_app_async`, `bevy_embedded_ios_create_app_for_scene` or
+/// `bevy_embedded_ios_set_preload_target`
+///
+/// The create calls copy the values, so the config can be freed with
+/// `bevy_embedded_ios_free_config` right after them.
+#[unsafe(no_mangle)]
+pub extern "C" fn bevy_embedded_ios_new_config() -> *mut HostConfig {
+    ffi::new_host_config()
+}
+
+/// Set a creation config value
+///
+/// Recognised keys are `headless`, `touch_coordinates` and `color_space`; other keys
+/// are passed to the app in `EmbeddedConfig::values`.
+///
+/// # Safety
+///
+/// - `config` must be a pointer returned by `bevy_embedded_ios_new_config` that was not
+///   freed
+/// - `key` and `value` must be valid NUL-terminated UTF-8 strings
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_config_set(
+    config: *mut HostConfig,
+    key: *const c_char,
+    value: *const c_char,
+) {
+    let _ = unsafe { ffi::host_config_set(config, key, value) };
+}
+
+/// Free a config from `bevy_embedded_ios_new_config`
+///
+/// # Safety
+///
+/// - `config` must be null or a pointer returned by `bevy_embedded_ios_new_config` that
+///   was not freed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_free_config(config: *mut HostConfig) {
+    unsafe { ffi::free_host_config(config) };
+}
+
 /// Handle a touch event from iOS
 ///
 /// # Safety
@@ -130,19 +249,113 @@ pub unsafe extern "C" fn bevy_embedded_ios_touch_event(
     y: f32,
     id: u64,
 ) {
-    if app.is_null() {
-        return;
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::touch_event(&mut app, phase, x, y, id);
     }
+}
 
-    let app = &mut *(app as *mut App);
+/// Cancel every touch that is down, when the view loses the gesture without ending each
+/// touch
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_cancel_touches(app: *mut c_void) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        ffi::cancel_touches(&mut app);
+    }
+}
 
-    if let Some(touch_phase) = TouchPhase::from_u8(phase) {
-        let mut input_events = app.world_mut().resource_mut::<EmbeddedInputEvents>();
-        input_events.add_touch_event(EmbeddedTouchEvent {
-            phase: touch_phase,
-            position: Vec2::new(x, y),
-            id,
-        });
+/// Queue a predicted position for touch `id` (from `UIEvent.predictedTouches(for:)`)
+///
+/// Send predictions after the frame's real touches; they only last until the next update.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_predicted_touch(
+    app: *mut c_void,
+    x: f32,
+    y: f32,
+    id: u64,
+) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::predicted_touch(&mut app, x, y, id);
+    }
+}
+
+/// Handle rotary input, such as Digital Crown rotation forwarded from a companion device
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `delta` is a scroll distance in points, positive for clockwise rotation
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_rotary_event(app: *mut c_void, delta: f32) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::rotary_event(&mut app, delta);
+    }
+}
+
+/// Modifiers held according to `UIKeyModifierFlags`
+fn modifiers_from_uikit(flags: u64) -> KeyModifiers {
+    [
+        (1 << 17, KeyModifiers::SHIFT),
+        (1 << 18, KeyModifiers::CONTROL),
+        (1 << 19, KeyModifiers::ALT),
+        (1 << 20, KeyModifiers::SUPER),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| flags & flag != 0)
+    .fold(KeyModifiers::NONE, |held, (_, modifier)| held | modifier)
+}
+
+/// Handle a hardware key press or release from `pressesBegan` / `pressesEnded`
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `hid_usage` is `UIKey.keyCode`, `modifier_flags` is `UIKey.modifierFlags.rawValue`
+/// - `characters` must be null or a NUL-terminated string (`UIKey.characters`)
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_key_event(
+    app: *mut c_void,
+    hid_usage: u32,
+    pressed: bool,
+    modifier_flags: u64,
+    characters: *const c_char,
+) {
+    let Ok(mut app) = ffi::app_mut(app) else {
+        return;
+    };
+
+    let text = if characters.is_null() {
+        None
+    } else {
+        unsafe { ffi::c_str(characters, "characters") }.ok()
+    };
+    // UIKit does not report key repeats
+    ffi::key_event(
+        &mut app,
+        key_code_from_hid_usage(hid_usage),
+        pressed,
+        false,
+        modifiers_from_uikit(modifier_flags),
+        text,
+    );
+}
+
+/// Tell Bevy the view gained or lost first responder status
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_keyboard_focus(app: *mut c_void, focused: bool) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        ffi::keyboard_focus(&mut app, focused);
     }
 }
 
@@ -158,79 +371,1518 @@ pub unsafe extern "C" fn bevy_embedded_ios_resize(
     height: u32,
     scale_factor: f32,
 ) {
-    if app.is_null() {
-        return;
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::resize(&mut app, width, height, scale_factor);
     }
+}
 
-    let app = &mut *(app as *mut App);
+/// Move the Bevy view to a different `UIView`, e.g. a full-screen presentation
+///
+/// The app and its scene are kept. Keep the previous view alive until the next
+/// `bevy_embedded_update` returns.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `ui_view` must be a valid pointer to a `UIView` backed by a `CAMetalLayer`
+/// - `presentation`: 0 = inline, 1 = full screen, 2 = picture-in-picture
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_replace_surface(
+    app: *mut c_void,
+    ui_view: *const c_void,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+    presentation: u8,
+) {
+    let Ok(mut app) = ffi::app_mut(app) else {
+        return;
+    };
+    let Some(handle) = view_handle(ui_view) else {
+        log::error!("Host did not provide a valid surface");
+        return;
+    };
+    let _ = ffi::replace_surface(&mut app, handle, width, height, scale_factor, presentation);
+}
 
-    // Find the primary window and update its resolution
-    let mut query = app
-        .world_mut()
-        .query_filtered::<&mut Window, With<PrimaryWindow>>();
-    if let Ok(mut window) = query.single_mut(app.world_mut()) {
-        window.resolution.set_physical_resolution(width, height);
-        window.resolution.set_scale_factor(scale_factor);
-    }
+/// Ask the app to close its window, e.g. to open a new one
+///
+/// Keep calling `bevy_embedded_update` until `bevy_embedded_ios_window_closed` returns
+/// true, so the app's teardown systems run. `bevy_embedded_destroy` closes the window
+/// itself. Returns false if the app has no window.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_close_window(app: *mut c_void) -> bool {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    ffi::close_window(&mut app)
 }
 
-/// Send a binary message to Bevy from the host
+/// Check whether the app's window is closed
 ///
 /// # Safety
 ///
 /// - `app` must be a valid pointer to the App
-/// - `data` must be a valid pointer to `len` bytes
-/// - The data will be copied, so the caller retains ownership
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn bevy_embedded_ios_send_message(
+pub unsafe extern "C" fn bevy_embedded_ios_window_closed(app: *mut c_void) -> bool {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    ffi::window_closed(&mut app)
+}
+
+/// Open a new window on a `UIView` after the app's window was closed
+///
+/// Returns false if the app still has a window or the view is invalid.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `ui_view` must be a valid pointer to a `UIView` backed by a `CAMetalLayer`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_open_window(
     app: *mut c_void,
-    data: *const u8,
-    len: usize,
+    ui_view: *const c_void,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> bool {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    let Some(handle) = view_handle(ui_view) else {
+        log::error!("Host did not provide a valid surface");
+        return false;
+    };
+    ffi::open_window(&mut app, handle, width, height, scale_factor).is_ok()
+}
+
+/// Set how many apps to keep preloaded for views of the given size
+///
+/// The preloaded apps are created with a copy of `config` (may be null). Call
+/// `bevy_embedded_preload_step` while the host is idle to fill the pool.
+///
+/// # Safety
+///
+/// - `config` must be null or a pointer returned by `bevy_embedded_ios_new_config` that
+///   was not freed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_preload_target(
+    count: u32,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+    config: *const HostConfig,
 ) {
-    if app.is_null() || data.is_null() {
-        return;
-    }
+    let config = unsafe { ffi::host_config(config) };
+    let _ = ffi::set_preload_target(count, width, height, scale_factor, config);
+}
 
-    let app = &mut *(app as *mut App);
-    let slice = std::slice::from_raw_parts(data, len);
-    let message = slice.to_vec();
+/// Take a preloaded app and attach it to a `UIView`
+///
+/// Returns null if no app is preloaded; create one with `bevy_embedded_create_app`
+/// instead. The host owns the returned app like one it created.
+///
+/// # Safety
+///
+/// - `ui_view` must be a valid pointer to a `UIView` backed by a `CAMetalLayer`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_take_preloaded_app(
+    ui_view: *const c_void,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> *mut c_void {
+    let Some(handle) = view_handle(ui_view) else {
+        log::error!("Host did not provide a valid surface");
+        return std::ptr::null_mut();
+    };
+    let Some(app_ptr) = preload::take_preloaded() else {
+        return std::ptr::null_mut();
+    };
+    let app = unsafe { &mut *app_ptr };
+    if ffi::activate_preloaded(app, handle, width, height, scale_factor).is_err() {
+        preload::push_preloaded(app_ptr);
+        return std::ptr::null_mut();
+    }
+    app_ptr.cast()
+}
 
-    // Check if the resource exists before accessing it
-    if let Some(channel) = app.world().get_resource::<HostChannel>() {
-        channel.send(message);
-    } else {
-        log::warn!("HostChannel resource not available");
+/// Take the app created by `bevy_embedded_create_app_async` once it is ready, attaching
+/// it to `ui_view` and making it active
+///
+/// Call it on the main thread. Returns the app, now owned by the caller like one from
+/// `bevy_embedded_create_app`, or null if it is still initializing, was already taken,
+/// or the surface could not be attached. The handle must still be released with
+/// `bevy_embedded_release_pending_app`.
+///
+/// # Safety
+///
+/// - `pending` must be a handle from `bevy_embedded_create_app_async` that was not
+///   released
+/// - `ui_view` must be a valid pointer to a `UIView` backed by a `CAMetalLayer`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_take_async_app(
+    pending: *const pending::PendingApp,
+    ui_view: *const c_void,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> *mut c_void {
+    if pending.is_null() {
+        log::error!("Null pending app handle");
+        return std::ptr::null_mut();
+    }
+    let Some(handle) = view_handle(ui_view) else {
+        log::error!("Host did not provide a valid surface");
+        return std::ptr::null_mut();
+    };
+    let Some(app_ptr) = (unsafe { pending::take(pending) }) else {
+        return std::ptr::null_mut();
+    };
+    let app = unsafe { &mut *app_ptr };
+    if ffi::activate_preloaded(app, handle, width, height, scale_factor).is_err() {
+        unsafe { pending::restore(pending, app_ptr) };
+        return std::ptr::null_mut();
     }
+    app_ptr.cast()
 }
 
-/// Receive a binary message from Bevy (non-blocking poll)
+/// Get how far the app's plugins have initialized: 0 = building, 1 = waiting for a
+/// surface, 2 = ready to finish in the next update, 3 = finished
 ///
-/// Returns the number of bytes read, or 0 if no message is available.
-/// The buffer must be at least `buffer_len` bytes.
+/// Hosts keep a splash screen up until this reports 3 and the app has updated once.
 ///
 /// # Safety
 ///
 /// - `app` must be a valid pointer to the App
-/// - `buffer` must be a valid pointer to at least `buffer_len` bytes
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn bevy_embedded_ios_receive_message(
+pub unsafe extern "C" fn bevy_embedded_ios_init_progress(app: *mut c_void) -> u8 {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return InitProgress::Building as u8;
+    };
+    ffi::init_progress(&mut app)
+}
+
+/// Attach a `UIView` on an external display (e.g. AirPlay) as an additional window
+///
+/// Returns the display id, or 0 on failure.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `ui_view` must be a valid pointer to a `UIView` backed by a `CAMetalLayer`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_attach_external_display(
     app: *mut c_void,
-    buffer: *mut u8,
-    buffer_len: usize,
-) -> usize {
-    if app.is_null() || buffer.is_null() || buffer_len == 0 {
+    ui_view: *const c_void,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> u32 {
+    let Ok(mut app) = ffi::app_mut(app) else {
+        return 0;
+    };
+    let Some(handle) = view_handle(ui_view) else {
+        log::error!("Host did not provide a valid surface");
         return 0;
+    };
+    ffi::attach_external_display(&mut app, handle, width, height, scale_factor).unwrap_or(0)
+}
+
+/// Handle a resize of an external display window
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_resize_external_display(
+    app: *mut c_void,
+    display_id: u32,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::resize_external_display(&mut app, display_id, width, height, scale_factor);
+    }
+}
+
+/// Detach an external display window
+///
+/// Keep the display's view alive until the next `bevy_embedded_update` returns.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_detach_external_display(
+    app: *mut c_void,
+    display_id: u32,
+) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::detach_external_display(&mut app, display_id);
     }
+}
 
-    let app = &mut *(app as *mut App);
+/// Find the app created for a host scene with `bevy_embedded_ios_create_app_for_scene`
+///
+/// Returns null if the scene has no live app.
+#[unsafe(no_mangle)]
+pub extern "C" fn bevy_embedded_ios_app_for_scene(scene: u64) -> *mut c_void {
+    crate::registry::scene_app(scene).map_or(std::ptr::null_mut(), |app| app as *mut c_void)
+}
 
-    if let Some(channel) = app.world().get_resource::<HostChannel>() {
-        if let Some(message) = channel.receive() {
-            let copy_len = message.len().min(buffer_len);
-            std::ptr::copy_nonoverlapping(message.as_ptr(), buffer, copy_len);
-            return copy_len;
-        }
+/// Report that UIKit disconnected the app's scene
+///
+/// Releases the app's surface and moves it to the background. The view can be freed once
+/// the next `bevy_embedded_update` returns; keep the app to resume it with
+/// `bevy_embedded_ios_scene_connected`, or destroy it.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_scene_disconnected(app: *mut c_void) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::disconnect_scene(&mut app);
     }
+}
 
-    0
+/// Hand the app of a reconnected scene its new view and make it active
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `ui_view` must be a valid pointer to a `UIView` backed by a `CAMetalLayer`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_scene_connected(
+    app: *mut c_void,
+    ui_view: *const c_void,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) {
+    let Ok(mut app) = ffi::app_mut(app) else {
+        return;
+    };
+    let Some(handle) = view_handle(ui_view) else {
+        log::error!("Host did not provide a valid surface");
+        return;
+    };
+    let _ = ffi::connect_scene(&mut app, handle, width, height, scale_factor);
+}
+
+/// Report a host lifecycle change
+///
+/// Report picture-in-picture by replacing the surface with presentation 2; the app keeps
+/// animating in the floating window while the lifecycle is background.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `state`: 0 = Active, 1 = Inactive, 2 = Background
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_lifecycle(app: *mut c_void, state: u8) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::set_lifecycle(&mut app, state);
+    }
+}
+
+/// Report the user's accessibility preferences
+///
+/// Call it after creating the app and whenever `UIAccessibility` posts a change
+/// notification. `font_scale` is the preferred content size relative to the default,
+/// e.g. `UIFontMetrics.default.scaledValue(for: 17) / 17`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_accessibility(
+    app: *mut c_void,
+    reduce_motion: bool,
+    bold_text: bool,
+    font_scale: f32,
+) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::set_accessibility(&mut app, reduce_motion, bold_text, font_scale);
+    }
+}
+
+/// Report the view's safe area insets, in points
+///
+/// Call it from `safeAreaInsetsDidChange` and after creating the app.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_safe_area(
+    app: *mut c_void,
+    top: f32,
+    left: f32,
+    bottom: f32,
+    right: f32,
+) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::set_safe_area(&mut app, top, left, bottom, right);
+    }
+}
+
+/// Report the host's monotonic clock so host timestamps can be converted to Bevy time
+///
+/// Call it with `CACurrentMediaTime()` right before each update; `HostClock` fits an
+/// offset and drift to the recent readings.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_sync_clock(app: *mut c_void, host_time: f64) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::sync_clock(&mut app, host_time);
+    }
+}
+
+/// Turn touch latency measurement on or off
+///
+/// While on, per-stage percentiles are sent as `InputLatency` reserved messages.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_latency_measurement(
+    app: *mut c_void,
+    enabled: bool,
+) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app) } {
+        ffi::set_latency_measurement(&mut app, enabled);
+    }
+}
+
+/// Report the `UITouch.timestamp` of the touch sent last, for latency measurement
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_input_timestamp(app: *mut c_void, host_time: f64) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app) } {
+        let _ = ffi::input_timestamp(&mut app, host_time);
+    }
+}
+
+/// Report that the oldest unreported frame was presented, for latency measurement
+///
+/// Call it once per update from the drawable's presented handler with its
+/// `presentedTime`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_frame_presented(app: *mut c_void, host_time: f64) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app) } {
+        let _ = ffi::frame_presented(&mut app, host_time);
+    }
+}
+
+/// Set the rotation and mirroring the host applies when presenting the content
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `rotation`: 0 = none, 1 = 90, 2 = 180, 3 = 270 degrees clockwise
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_surface_transform(
+    app: *mut c_void,
+    rotation: u8,
+    mirror: bool,
+) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::set_surface_transform(&mut app, rotation, mirror);
+    }
+}
+
+/// Place the camera with a column-major world-to-view matrix
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `view` must point to 16 floats
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_camera_view(app: *mut c_void, view: *const f32) {
+    if let (Ok(mut app), Ok(view)) = (ffi::app_mut(app), ffi::matrix(view)) {
+        let _ = ffi::set_camera_view(&mut app, view);
+    }
+}
+
+/// Configure dynamic resolution scaling of the Bevy view
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `min_scale` and `max_scale` must satisfy `0 < min_scale <= max_scale <= 1`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_dynamic_resolution(
+    app: *mut c_void,
+    enabled: bool,
+    min_scale: f32,
+    max_scale: f32,
+    target_fps: f32,
+) {
+    if let Ok(mut app) = ffi::app_mut(app) {
+        let _ = ffi::set_dynamic_resolution(&mut app, enabled, min_scale, max_scale, target_fps);
+    }
+}
+
+/// Get the shared state block the host writes scalar state into every frame
+///
+/// Returns a pointer to `SHARED_STATE_LEN` floats that stays valid until
+/// `bevy_embedded_destroy`, or null for an unknown app. Values written here are seen by
+/// Bevy at the start of the next frame.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_shared_state(app: *mut c_void) -> *mut f32 {
+    ffi::shared_state(app).unwrap_or(std::ptr::null_mut())
+}
+
+/// Send a message to Bevy on a topic's ring channel
+///
+/// Copies straight into the ring. Returns false if the ring is full, the app has not
+/// opened a ring channel for `topic`, or after `bevy_embedded_destroy`.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `data` must be a valid pointer to `len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_ring_send(
+    app: *mut c_void,
+    topic: u16,
+    data: *const u8,
+    len: usize,
+) -> bool {
+    match ffi::message_slice(data, len) {
+        Ok(message) => ffi::ring_send(app, topic, message).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Receive the next message Bevy sent on a topic's ring channel (non-blocking poll)
+///
+/// Returns the message length, or 0 if no message is available. If the message is
+/// longer than `buffer_len` nothing is copied and the message stays queued, so call again
+/// with a buffer of at least the returned length.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `buffer` must be a valid pointer to at least `buffer_len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_ring_receive(
+    app: *mut c_void,
+    topic: u16,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> usize {
+    ffi::ring_receive_into(app, topic, buffer, buffer_len).unwrap_or(0)
+}
+
+/// Push PCM audio captured by the host, e.g. from an `AVAudioEngine` input tap
+///
+/// `samples` holds `frames * channels` interleaved `f32` samples. `timestamp` is the
+/// capture time of the first frame in seconds on the host's monotonic clock. Returns false
+/// if Bevy has fallen behind and the buffer was dropped. Does not allocate, so it may be
+/// called from the audio thread.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `samples` must be a valid pointer to `frames * channels` floats
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_push_audio_input(
+    app: *mut c_void,
+    samples: *const f32,
+    frames: usize,
+    channels: u16,
+    sample_rate: u32,
+    timestamp: f64,
+) -> bool {
+    if samples.is_null() {
+        return false;
+    }
+    let samples = std::slice::from_raw_parts(samples, frames * channels as usize);
+    ffi::push_audio_input(app, timestamp, sample_rate, channels, samples).unwrap_or(false)
+}
+
+/// Render Bevy's mixed audio output, e.g. from an `AVAudioSourceNode` render block
+///
+/// Fills `out` with `frames * channels` interleaved `f32` samples. The format must match
+/// the one `HostAudioPlugin` was added with; otherwise, or after `bevy_embedded_destroy`,
+/// returns false and the host should output silence. Requires the `host_audio` feature.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `out` must be a valid pointer to `frames * channels` writable floats
+#[cfg(feature = "host_audio")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_render_audio(
+    app: *mut c_void,
+    out: *mut f32,
+    frames: usize,
+    channels: u16,
+    sample_rate: u32,
+) -> bool {
+    if out.is_null() {
+        return false;
+    }
+    let out = std::slice::from_raw_parts_mut(out, frames * channels as usize);
+    ffi::render_audio(app, sample_rate, channels, out).is_ok()
+}
+
+/// Send a binary message to Bevy from the host
+///
+/// Safe to call after `bevy_embedded_destroy`; the message is then dropped.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `data` must be a valid pointer to `len` bytes
+/// - The data will be copied, so the caller retains ownership
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_send_message(
+    app: *mut c_void,
+    data: *const u8,
+    len: usize,
+) {
+    if let Ok(message) = ffi::message_slice(data, len) {
+        let _ = ffi::send_message(app, message.to_vec());
+    }
+}
+
+/// Receive a binary message from Bevy (non-blocking poll)
+///
+/// Returns the number of bytes read, or 0 if no message is available.
+/// The buffer must be at least `buffer_len` bytes. Returns 0 after
+/// `bevy_embedded_destroy`.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `buffer` must be a valid pointer to at least `buffer_len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_receive_message(
+    app: *mut c_void,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> usize {
+    ffi::receive_message_into(app, buffer, buffer_len).unwrap_or(0)
+}
+
+/// Receive up to `max_messages` messages from Bevy in one call (non-blocking poll)
+///
+/// Each message is written as a little-endian `u32` length followed by its bytes. Returns
+/// the number of bytes written, or 0 if no message is available. Messages that don't fit
+/// stay queued for the next call; if not even the first one fits, nothing is copied and
+/// the returned length is larger than `buffer_len`, so call again with a buffer of at
+/// least that length.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `buffer` must be a valid pointer to at least `buffer_len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_receive_messages(
+    app: *mut c_void,
+    buffer: *mut u8,
+    buffer_len: usize,
+    max_messages: usize,
+) -> usize {
+    ffi::receive_messages_into(app, buffer, buffer_len, max_messages).unwrap_or(0)
+}
+
+/// Seal a message with the app's channel key before `bevy_embedded_ios_send_message`
+///
+/// Writes the sealed message, `len + SEALED_OVERHEAD` (40) bytes, into `out` and returns
+/// its length, or 0 if the key is not 32 bytes or `out` is too small.
+///
+/// # Safety
+///
+/// - `key` must be a valid pointer to `key_len` bytes
+/// - `data` must be a valid pointer to `len` bytes
+/// - `out` must be a valid pointer to at least `out_len` bytes
+#[cfg(feature = "channel_encryption")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_seal_message(
+    key: *const u8,
+    key_len: usize,
+    data: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_len: usize,
+) -> usize {
+    let (Ok(key), Ok(data)) = (
+        ffi::message_slice(key, key_len),
+        ffi::message_slice(data, len),
+    ) else {
+        return 0;
+    };
+    ffi::seal_message(key, data)
+        .and_then(|sealed| unsafe { ffi::copy_to_buffer(&sealed, out, out_len) })
+        .unwrap_or(0)
+}
+
+/// Open a message from `bevy_embedded_ios_receive_message` with the app's channel key
+///
+/// Writes the plaintext, `len - SEALED_OVERHEAD` bytes, into `out` and returns its
+/// length, or -1 if the message failed to authenticate, the key is not 32 bytes or `out`
+/// is too small.
+///
+/// # Safety
+///
+/// - `key` must be a valid pointer to `key_len` bytes
+/// - `sealed` must be a valid pointer to `len` bytes
+/// - `out` must be a valid pointer to at least `out_len` bytes
+#[cfg(feature = "channel_encryption")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_open_message(
+    key: *const u8,
+    key_len: usize,
+    sealed: *const u8,
+    len: usize,
+    out: *mut u8,
+    out_len: usize,
+) -> isize {
+    let (Ok(key), Ok(sealed)) = (
+        ffi::message_slice(key, key_len),
+        ffi::message_slice(sealed, len),
+    ) else {
+        return -1;
+    };
+    ffi::open_message(key, sealed)
+        .and_then(|message| unsafe { ffi::copy_to_buffer(&message, out, out_len) })
+        .map_or(-1, |len| len as isize)
+}
+
+/// Receive the oldest lifecycle telemetry event of any app (non-blocking poll)
+///
+/// Returns the number of bytes read, or 0 if no event is queued. Events are reserved
+/// messages on the telemetry topic; see the `telemetry` module for the layout.
+///
+/// # Safety
+///
+/// - `buffer` must be a valid pointer to at least `buffer_len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_receive_telemetry(
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> usize {
+    unsafe { ffi::receive_telemetry_into(buffer, buffer_len) }.unwrap_or(0)
+}
+
+/// Get the regions that changed in the last update, to composite only those
+///
+/// Writes up to `capacity` rects into `rects` as `x, y, width, height` in physical
+/// pixels of Bevy's output. Returns the number of rects, 0 if nothing changed, or -1 if
+/// the whole frame must be recomposited.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `rects` must be a valid pointer to `capacity * 4` `u32`s
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_frame_damage(
+    app: *mut c_void,
+    rects: *mut u32,
+    capacity: usize,
+) -> i32 {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return -1;
+    };
+    let out: &mut [u32] = if rects.is_null() {
+        &mut []
+    } else {
+        unsafe { std::slice::from_raw_parts_mut(rects, capacity * 4) }
+    };
+    ffi::write_frame_damage(&mut app, out)
+}
+
+/// Get the GPU time of the most recently measured frame in milliseconds
+///
+/// Returns -1 if the app doesn't add `GpuTimingPlugin` or the device has no timestamp
+/// queries.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_gpu_frame_time_ms(app: *mut c_void) -> f64 {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return -1.0;
+    };
+    ffi::gpu_frame_time(&app).map_or(-1.0, |time| time.as_secs_f64() * 1000.0)
+}
+
+/// Get when a startup phase (0 = build, 1 = device request, 2 = finish, 3 = first
+/// pipeline, 4 = first frame, 5 = deferred plugins) started and how long it took
+///
+/// Writes the milliseconds from creation to the start and the phase's duration to `out`.
+/// Returns false if the phase hasn't ended or is unknown. See `StartupTimings`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `out` must be valid for writes of 2 doubles
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_startup_phase(
+    app: *mut c_void,
+    phase: u8,
+    out: *mut f64,
+) -> bool {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    if out.is_null() {
+        return false;
+    }
+    let Ok(Some(timing)) = ffi::startup_phase(&app, phase) else {
+        return false;
+    };
+    let out = unsafe { std::slice::from_raw_parts_mut(out, 2) };
+    out[0] = timing.start.as_secs_f64() * 1000.0;
+    out[1] = timing.duration.as_secs_f64() * 1000.0;
+    true
+}
+
+/// Get the GPU adapter and limits the app renders with as JSON, or null if it doesn't
+/// render
+///
+/// Available once the app is created. Free the string with `bevy_embedded_free_error`.
+/// See `GpuReport`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_gpu_report(app: *mut c_void) -> *mut c_char {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return std::ptr::null_mut();
+    };
+    ffi::gpu_report(&app)
+        .and_then(|report| std::ffi::CString::new(report).ok())
+        .map_or(std::ptr::null_mut(), std::ffi::CString::into_raw)
+}
+
+/// Get the error that froze the app, or null while it is running
+///
+/// A frozen app keeps its last frame on screen and no longer updates until it is
+/// destroyed. Free the string with `bevy_embedded_free_error`. See `RecoveryPolicy`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_frozen_error(app: *mut c_void) -> *mut c_char {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return std::ptr::null_mut();
+    };
+    ffi::frozen_error(&app)
+        .and_then(|error| std::ffi::CString::new(error).ok())
+        .map_or(std::ptr::null_mut(), std::ffi::CString::into_raw)
+}
+
+/// Set the image presented when the app freezes without a frame it can render, as
+/// `width * height` RGBA8 sRGB pixels, or clear it with null `pixels`
+///
+/// Returns false if `len` doesn't match the dimensions. See `frozen_frame`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `pixels` must be null or a valid pointer to `len` bytes
+/// - The pixels will be copied, so the caller retains ownership
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_fallback_frame(
+    app: *mut c_void,
+    pixels: *const u8,
+    len: usize,
+    width: u32,
+    height: u32,
+) -> bool {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    let pixels = (!pixels.is_null()).then(|| unsafe { std::slice::from_raw_parts(pixels, len) });
+    ffi::set_fallback_frame(&mut app, pixels, width, height).is_ok()
+}
+
+/// Release the GPU memory of dropped render resources, e.g. on a memory warning
+///
+/// Empties the texture cache and waits for the GPU. Returns false if the app has no render
+/// device. See `RenderGcPlugin`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_flush_render_resources(app: *mut c_void) -> bool {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    ffi::flush_render_resources(&mut app)
+}
+
+/// Capture the next frame the app renders in Xcode's GPU frame capture, or RenderDoc
+///
+/// Only the app's own work is captured, from its first command to presentation. Returns
+/// false if the app doesn't render. See `GpuCapture`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[cfg(feature = "gpu_capture")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_capture_gpu_frame(app: *mut c_void) -> bool {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    ffi::capture_gpu_frame(&app)
+}
+
+/// Start recording a Chrome trace of the schedules and systems of every app into a new
+/// file at `path`
+///
+/// Returns false if a trace is already recording or the file can't be created. Open the
+/// file in `chrome://tracing` or Perfetto after stopping the trace.
+///
+/// # Safety
+///
+/// - `path` must be a valid null-terminated UTF-8 string
+#[cfg(feature = "chrome_trace")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_start_chrome_trace(path: *const c_char) -> bool {
+    let Ok(path) = (unsafe { ffi::c_str(path, "path") }) else {
+        return false;
+    };
+    ffi::start_chrome_trace(path)
+}
+
+/// Stop recording the Chrome trace and complete its file
+///
+/// Returns false if no trace was recording or the file couldn't be written.
+#[cfg(feature = "chrome_trace")]
+#[unsafe(no_mangle)]
+pub extern "C" fn bevy_embedded_ios_stop_chrome_trace() -> bool {
+    ffi::stop_chrome_trace()
+}
+
+/// Switch a debug renderer on or off (0 = AABBs, 1 = wireframe, 2 = UI overlay,
+/// 3 = lights)
+///
+/// Returns false if the app doesn't have the renderer or the toggle is unknown.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[cfg(feature = "debug_toggles")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_set_debug_toggle(
+    app: *mut c_void,
+    toggle: u8,
+    enabled: bool,
+) -> bool {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    ffi::set_debug_toggle(&mut app, toggle, enabled).unwrap_or(false)
+}
+
+/// Whether a debug renderer is on: 1 if on, 0 if off, -1 if the app doesn't have it
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[cfg(feature = "debug_toggles")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_debug_toggle(app: *mut c_void, toggle: u8) -> i8 {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return -1;
+    };
+    match ffi::debug_toggle(&app, toggle) {
+        Ok(Some(enabled)) => enabled as i8,
+        _ => -1,
+    }
+}
+
+/// Send the host's protocol version, message schema hash and codecs to the app
+///
+/// `codecs` is a comma-separated list, most preferred first, or null for none. Check
+/// the result with `bevy_embedded_ios_handshake_status` after the next update.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `codecs` must be null or a NUL-terminated UTF-8 string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_send_handshake(
+    app: *mut c_void,
+    version: u16,
+    schema_hash: u64,
+    codecs: *const c_char,
+) {
+    let codecs = if codecs.is_null() {
+        ""
+    } else {
+        let Ok(codecs) = (unsafe { ffi::c_str(codecs, "codecs") }) else {
+            return;
+        };
+        codecs
+    };
+    let _ = ffi::send_handshake(app, version, schema_hash, codecs);
+}
+
+/// Get the state of the protocol handshake: 0 = pending, 1 = compatible, 2 = mismatched
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_handshake_status(app: *mut c_void) -> u8 {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return HandshakeStatus::Pending as u8;
+    };
+    ffi::handshake_status(&app) as u8
+}
+
+/// Get why the protocol handshake failed, or null if it hasn't
+///
+/// Free the string with `bevy_embedded_free_error`.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_handshake_error(app: *mut c_void) -> *mut c_char {
+    let Ok(app) = (unsafe { ffi::app_mut(app) }) else {
+        return std::ptr::null_mut();
+    };
+    ffi::handshake_error(&app)
+        .and_then(|error| std::ffi::CString::new(error).ok())
+        .map_or(std::ptr::null_mut(), std::ffi::CString::into_raw)
+}
+
+/// Submit a job of `kind` with `len` bytes of input, returning its token, or 0 on failure
+///
+/// The result arrives as a `JobResult` reserved message carrying the token. Safe to call
+/// after `bevy_embedded_destroy`; the job is then dropped.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+/// - `kind` must be a NUL-terminated UTF-8 string
+/// - `data` must be a valid pointer to `len` bytes, or null if `len` is 0
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_submit_job(
+    app: *mut c_void,
+    kind: *const c_char,
+    data: *const u8,
+    len: usize,
+) -> u64 {
+    let Ok(kind) = ffi::c_str(kind, "kind") else {
+        return 0;
+    };
+    let input = if len == 0 {
+        &[]
+    } else {
+        let Ok(input) = ffi::message_slice(data, len) else {
+            return 0;
+        };
+        input
+    };
+    ffi::submit_job(app, kind, input).unwrap_or(0)
+}
+
+/// Cancel the job with `token`
+///
+/// A `Canceled` result is sent unless the job already finished.
+///
+/// # Safety
+///
+/// - `app` must be a pointer returned by `bevy_embedded_create_app`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_cancel_job(app: *mut c_void, token: u64) {
+    let _ = ffi::cancel_job(app, token);
+}
+
+/// Start recording host input for replay, discarding a recording in progress
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_start_recording(app: *mut c_void) {
+    if let Ok(mut app) = unsafe { ffi::app_mut(app) } {
+        replay::start_recording(&mut app);
+    }
+}
+
+/// Length in bytes of the input recorded so far, or 0 if the app isn't recording
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_recording_len(app: *mut c_void) -> usize {
+    unsafe { ffi::app_mut(app) }.map_or(0, |app| ffi::recording_len(&app))
+}
+
+/// Stop recording and write the log into `out`, returning its length
+///
+/// Returns 0 if the app wasn't recording or `out` is too small, in which case recording
+/// continues. Size `out` with `bevy_embedded_ios_recording_len` right before calling.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `out` must be a valid pointer to at least `out_len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_stop_recording(
+    app: *mut c_void,
+    out: *mut u8,
+    out_len: usize,
+) -> usize {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return 0;
+    };
+    unsafe { ffi::stop_recording(&mut app, out, out_len) }.unwrap_or(0)
+}
+
+/// Replay a recorded log of `len` bytes, running one update per recorded frame
+///
+/// Returns the number of frames run, or -1 if the log is malformed.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `data` must be a valid pointer to `len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_replay(
+    app: *mut c_void,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return -1;
+    };
+    let Ok(log) = ffi::message_slice(data, len) else {
+        return -1;
+    };
+    ffi::replay(&mut app, log).map_or(-1, |frames| frames as i32)
+}
+
+/// Length in bytes of the state `bevy_embedded_ios_hibernate` would write
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_hibernation_len(app: *mut c_void) -> usize {
+    unsafe { ffi::app_mut(app) }.map_or(0, |mut app| ffi::hibernate(&mut app).len())
+}
+
+/// Save the app's state into `out` and destroy the app, returning the state's length
+///
+/// Returns 0 if `out` is too small, in which case the app stays alive. Size `out` with
+/// `bevy_embedded_ios_hibernation_len` right before calling. Pass the state to
+/// `bevy_embedded_ios_rehydrate` to restore it in a new app.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App, which is invalid once this returns
+///   non-zero
+/// - `out` must be a valid pointer to at least `out_len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_hibernate(
+    app: *mut c_void,
+    out: *mut u8,
+    out_len: usize,
+) -> usize {
+    // The borrow ends before the app is destroyed
+    let Ok(state) = (unsafe { ffi::app_mut(app) }).map(|mut app| ffi::hibernate(&mut app)) else {
+        return 0;
+    };
+    let Ok(written) = (unsafe { ffi::copy_to_buffer(&state, out, out_len) }) else {
+        return 0;
+    };
+
+    unsafe extern "C" {
+        fn bevy_embedded_destroy(app: *mut App);
+    }
+
+    unsafe { bevy_embedded_destroy(app as *mut App) };
+    written
+}
+
+/// Restore state from `bevy_embedded_ios_hibernate` in the app's next update
+///
+/// Returns false if the state is malformed or from another version.
+///
+/// # Safety
+///
+/// - `app` must be a valid pointer to the App
+/// - `data` must be a valid pointer to `len` bytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bevy_embedded_ios_rehydrate(
+    app: *mut c_void,
+    data: *const u8,
+    len: usize,
+) -> bool {
+    let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+        return false;
+    };
+    let Ok(state) = ffi::message_slice(data, len) else {
+        return false;
+    };
+    ffi::rehydrate(&mut app, state).is_ok()
+}
+
+#[cfg(feature = "gpu_interop")]
+pub use host_frame::*;
+
+/// Rendering on the host's `MTLDevice` and into frames the host encodes
+#[cfg(feature = "gpu_interop")]
+mod host_frame {
+    use std::ffi::c_void;
+
+    use std::time::Duration;
+
+    use bevy::{math::URect, render::renderer::RenderDevice};
+    use foreign_types::ForeignTypeRef;
+    use metal::{
+        CommandBufferRef, CommandQueueRef, DeviceRef, MTLPixelFormat, MTLTextureType, TextureRef,
+    };
+    use wgpu::hal::api::Metal;
+
+    use crate::{HostDeviceError, ffi};
+
+    /// The wgpu format of a host texture, for the formats Bevy can render to
+    fn texture_format(format: MTLPixelFormat) -> Option<wgpu::TextureFormat> {
+        match format {
+            MTLPixelFormat::BGRA8Unorm => Some(wgpu::TextureFormat::Bgra8Unorm),
+            MTLPixelFormat::BGRA8Unorm_sRGB => Some(wgpu::TextureFormat::Bgra8UnormSrgb),
+            MTLPixelFormat::RGBA8Unorm => Some(wgpu::TextureFormat::Rgba8Unorm),
+            MTLPixelFormat::RGBA8Unorm_sRGB => Some(wgpu::TextureFormat::Rgba8UnormSrgb),
+            MTLPixelFormat::RGB10A2Unorm => Some(wgpu::TextureFormat::Rgb10a2Unorm),
+            MTLPixelFormat::RGBA16Float => Some(wgpu::TextureFormat::Rgba16Float),
+            _ => None,
+        }
+    }
+
+    /// Wraps a host `MTLTexture` as a texture of Bevy's device
+    ///
+    /// # Safety
+    ///
+    /// `texture` must be a valid `MTLTexture` created on the same `MTLDevice`.
+    unsafe fn wrap_texture(device: &RenderDevice, texture: *mut c_void) -> Option<wgpu::Texture> {
+        if texture.is_null() {
+            return None;
+        }
+
+        let raw = unsafe { TextureRef::from_ptr(texture.cast()) }.to_owned();
+        let Some(format) = texture_format(raw.pixel_format()) else {
+            log::error!("Unsupported host texture format {:?}", raw.pixel_format());
+            return None;
+        };
+        let (width, height) = (raw.width() as u32, raw.height() as u32);
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING;
+
+        let hal_texture = unsafe {
+            wgpu::hal::metal::Device::texture_from_raw(
+                raw,
+                format,
+                MTLTextureType::D2,
+                1,
+                1,
+                wgpu::hal::CopyExtent {
+                    width,
+                    height,
+                    depth: 1,
+                },
+            )
+        };
+        let texture = unsafe {
+            device.wgpu_device().create_texture_from_hal::<Metal>(
+                hal_texture,
+                &wgpu::TextureDescriptor {
+                    label: Some("host_frame"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage,
+                    view_formats: &[],
+                },
+            )
+        };
+        Some(texture)
+    }
+
+    /// Creates Bevy's device from the host's `MTLDevice` and `MTLCommandQueue`
+    ///
+    /// # Safety
+    ///
+    /// `device` and `queue` must be null or a valid `MTLDevice` and a command queue of it.
+    unsafe fn wrap_device(device: *mut c_void, queue: *mut c_void) -> Result<(), HostDeviceError> {
+        if device.is_null() {
+            return Err(HostDeviceError::NullHandle("MTLDevice"));
+        }
+        if queue.is_null() {
+            return Err(HostDeviceError::NullHandle("MTLCommandQueue"));
+        }
+        let raw_device = unsafe { DeviceRef::from_ptr(device.cast()) }.to_owned();
+        let raw_queue = unsafe { CommandQueueRef::from_ptr(queue.cast()) }.to_owned();
+
+        // iOS devices have a single GPU, which is the adapter of the host's device
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::METAL,
+            ..Default::default()
+        });
+        let adapter = instance
+            .enumerate_adapters(wgpu::Backends::METAL)
+            .into_iter()
+            .next()
+            .ok_or(HostDeviceError::UnsupportedAdapter)?;
+        let features = adapter.features();
+        let limits = adapter.limits();
+
+        let open_device = wgpu::hal::OpenDevice::<Metal> {
+            device: unsafe { wgpu::hal::metal::Device::device_from_raw(raw_device, features) },
+            queue: unsafe { wgpu::hal::metal::Queue::queue_from_raw(raw_queue, 1.0) },
+        };
+        let (device, queue) = unsafe {
+            adapter.create_device_from_hal(
+                open_device,
+                &wgpu::DeviceDescriptor {
+                    label: Some("host_device"),
+                    required_features: features,
+                    required_limits: limits,
+                    ..Default::default()
+                },
+            )
+        }
+        .map_err(|error| HostDeviceError::Device(error.to_string()))?;
+
+        crate::set_host_device(instance, adapter, device, queue);
+        Ok(())
+    }
+
+    /// Render apps created from now on with the host's `MTLDevice` instead of a device
+    /// of their own
+    ///
+    /// Call it before creating apps whose `RenderPlugin` uses the host device (see
+    /// `HostDevicePlugins::with_host_device`). Bevy commits its command buffers to
+    /// `queue`, so they run in order with the host's own work on it.
+    /// Returns false if the device cannot be used; the reason is available from
+    /// `bevy_embedded_get_last_error`.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be a valid `MTLDevice` and `queue` a valid `MTLCommandQueue` created
+    /// from it; Bevy retains both.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn bevy_embedded_ios_set_host_device(
+        device: *mut c_void,
+        queue: *mut c_void,
+    ) -> bool {
+        match unsafe { wrap_device(device, queue) } {
+            Ok(()) => true,
+            Err(error) => {
+                log::error!("{error}");
+                crate::store_error(error.to_string());
+                false
+            }
+        }
+    }
+
+    /// Render the next frames into a host `MTLTexture` instead of a view
+    ///
+    /// Call it before `bevy_embedded_update`, then encode the frame's synchronization with
+    /// `bevy_embedded_ios_encode_host_frame`. Cameras of the primary window render into
+    /// the texture at the viewport `x`, `y`, `width`, `height` (in pixels).
+    ///
+    /// # Safety
+    ///
+    /// - `app` must be a valid pointer to the App
+    /// - `texture` must be a valid `MTLTexture` with render target usage, created on the
+    ///   system default `MTLDevice` or the device set with
+    ///   `bevy_embedded_ios_set_host_device`
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn bevy_embedded_ios_set_host_frame(
+        app: *mut c_void,
+        texture: *mut c_void,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        scale_factor: f32,
+    ) {
+        let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+            return;
+        };
+        let Ok(device) = ffi::render_device(&mut app) else {
+            return;
+        };
+        let Some(texture) = (unsafe { wrap_texture(&device, texture) }) else {
+            log::error!("Host did not provide a valid frame texture");
+            return;
+        };
+        let viewport = URect::new(x, y, x.saturating_add(width), y.saturating_add(height));
+        let _ = ffi::set_host_frame(&mut app, &texture, viewport, scale_factor);
+    }
+
+    /// Make the host's command buffer wait for the frame Bevy rendered into the host
+    /// texture
+    ///
+    /// Call it after `bevy_embedded_update`, before encoding work that reads the texture.
+    /// The wait happens on the GPU; the call itself does not block. It waits for the frame
+    /// the update ran, even if pipelined rendering is still encoding it on the render
+    /// thread.
+    ///
+    /// # Safety
+    ///
+    /// - `app` must be a valid pointer to the App
+    /// - `command_buffer` must be a valid, uncommitted `MTLCommandBuffer`
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn bevy_embedded_ios_encode_host_frame(
+        app: *mut c_void,
+        command_buffer: *mut c_void,
+    ) {
+        let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+            return;
+        };
+        if command_buffer.is_null() {
+            log::error!("Host did not provide a command buffer");
+            return;
+        }
+        let Ok(fence) = ffi::frame_fence(&mut app) else {
+            return;
+        };
+
+        let command_buffer = unsafe { CommandBufferRef::from_ptr(command_buffer.cast()) };
+        command_buffer.encode_wait_for_event(fence.shared_event(), fence.value());
+    }
+
+    /// Render the next frames into an `MTLTexture` Bevy allocates, returning the texture
+    ///
+    /// `format` is an `OffscreenFormat` (0 RGBA8, 1 BGRA8, 2 RGB10A2, 3 RGBA16Float), and
+    /// `srgb` selects sRGB encoding for the 8-bit formats. `mip_levels` of 0 allocates a
+    /// full chain; Bevy renders level 0 only. Call it again to resize or change the
+    /// format; the previous texture must not be used afterwards. Returns null if the
+    /// settings are invalid.
+    ///
+    /// The texture is owned by the app and valid until the next call or until the app is
+    /// destroyed. Wait on the frame fence before reading it.
+    ///
+    /// # Safety
+    ///
+    /// `app` must be a valid pointer to the App
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn bevy_embedded_ios_set_offscreen_target(
+        app: *mut c_void,
+        width: u32,
+        height: u32,
+        format: u8,
+        srgb: bool,
+        mip_levels: u32,
+        scale_factor: f32,
+    ) -> *mut c_void {
+        let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+            return std::ptr::null_mut();
+        };
+        let Ok(texture) = ffi::set_offscreen_target(
+            &mut app,
+            width,
+            height,
+            format,
+            srgb,
+            mip_levels,
+            scale_factor,
+        ) else {
+            return std::ptr::null_mut();
+        };
+        match unsafe { texture.as_hal::<Metal>() } {
+            Some(hal_texture) => unsafe { hal_texture.raw_handle() }.as_ptr().cast(),
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    /// The `MTLSharedEvent` Bevy signals when a frame is done, or null if the app does
+    /// not render
+    ///
+    /// The event is owned by the app and valid until it is destroyed. Wait for the value
+    /// from `bevy_embedded_ios_frame_fence_value` to know that the latest frame is done,
+    /// e.g. with `encodeWaitForEvent` in a command buffer of another queue or process.
+    ///
+    /// # Safety
+    ///
+    /// `app` must be a valid pointer to the App
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn bevy_embedded_ios_frame_fence_event(app: *mut c_void) -> *mut c_void {
+        let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+            return std::ptr::null_mut();
+        };
+        match ffi::frame_fence(&mut app) {
+            Ok(fence) => fence.shared_event().as_ptr().cast(),
+            Err(_) => std::ptr::null_mut(),
+        }
+    }
+
+    /// The value the frame fence reaches once the latest updated frame is done
+    ///
+    /// # Safety
+    ///
+    /// `app` must be a valid pointer to the App
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn bevy_embedded_ios_frame_fence_value(app: *mut c_void) -> u64 {
+        let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+            return 0;
+        };
+        ffi::frame_fence(&mut app).map_or(0, |fence| fence.value())
+    }
+
+    /// Block until the frame fence reaches `value`, for at most `timeout_ms` milliseconds
+    ///
+    /// Returns false on timeout or if the app has no fence.
+    ///
+    /// # Safety
+    ///
+    /// `app` must be a valid pointer to the App
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn bevy_embedded_ios_wait_frame_fence(
+        app: *mut c_void,
+        value: u64,
+        timeout_ms: u32,
+    ) -> bool {
+        let Ok(mut app) = (unsafe { ffi::app_mut(app) }) else {
+            return false;
+        };
+        ffi::frame_fence(&mut app)
+            .is_ok_and(|fence| fence.wait(value, Duration::from_millis(timeout_ms.into())))
+    }
 }
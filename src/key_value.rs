@@ -0,0 +1,197 @@
+//! Small persistent values stored by the host
+//!
+//! Embedded apps can't assume a writable filesystem, so settings live wherever the host
+//! keeps its own (`UserDefaults`, `SharedPreferences`, ...). [`HostKeyValueStore`] caches
+//! the values Bevy has seen and forwards reads and writes to the host.
+//!
+//! Requests go to the host on [`ReservedTopic::KeyValueRequest`], each starting with a
+//! [`KeyValueOp`] byte and the key:
+//!
+//! ```text
+//! Get:    key: u32 len + UTF-8
+//! Set:    key: u32 len + UTF-8 | value: u32 len + bytes
+//! Remove: key: u32 len + UTF-8
+//! ```
+//!
+//! The host answers a `Get`, and reports values changed outside Bevy, on
+//! [`ReservedTopic::KeyValue`] with a sequence of entries:
+//!
+//! ```text
+//! key: u32 len + UTF-8 | present: u8 | value: u32 len + bytes (if present)
+//! ```
+//!
+//! Every entry updates the cache and is delivered as a [`KeyValueChanged`] message. Hosts
+//! may send all stored values up front so they are available from the first frame.
+//!
+//! The store is added by [`KeyValuePlugin`].
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    ecs::{
+        message::{Message, MessageReader, MessageWriter},
+        resource::Resource,
+        system::{Res, ResMut},
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Syncs [`HostKeyValueStore`] with the host; see the [module docs](self)
+#[derive(Default)]
+pub struct KeyValuePlugin;
+
+impl Plugin for KeyValuePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostKeyValueStore>()
+            .add_message::<KeyValueChanged>()
+            .add_systems(PreUpdate, receive_key_values)
+            .add_systems(Last, send_key_value_requests);
+    }
+}
+
+/// Operations Bevy asks the host's store to perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeyValueOp {
+    /// Read a value
+    Get = 0,
+    /// Write a value
+    Set = 1,
+    /// Delete a value
+    Remove = 2,
+}
+
+impl KeyValueOp {
+    /// Create a KeyValueOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(KeyValueOp::Get),
+            1 => Some(KeyValueOp::Set),
+            2 => Some(KeyValueOp::Remove),
+            _ => None,
+        }
+    }
+}
+
+/// Sent when the host delivers a value, in answer to a request or because it changed
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct KeyValueChanged {
+    /// Key of the value
+    pub key: String,
+    /// The value, or `None` if the host has nothing stored
+    pub value: Option<Vec<u8>>,
+}
+
+/// Key-value store persisted by the host
+///
+/// Reads are answered from the cache; call [`request`](Self::request) to fetch a value
+/// the host has not sent yet and wait for its [`KeyValueChanged`] message. Writes update
+/// the cache immediately and are sent to the host at the end of the frame.
+#[derive(Resource, Debug, Default)]
+pub struct HostKeyValueStore {
+    values: HashMap<String, Vec<u8>>,
+    pending: HashSet<String>,
+    outgoing: Vec<Vec<u8>>,
+}
+
+impl HostKeyValueStore {
+    /// The cached value of `key`
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        self.values.get(key).map(Vec::as_slice)
+    }
+
+    /// The cached value of `key`, if it is valid UTF-8
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    /// Asks the host for the stored value of `key`
+    pub fn request(&mut self, key: impl Into<String>) {
+        let key = key.into();
+        let mut payload = ByteWriter::new();
+        payload.u8(KeyValueOp::Get as u8).string(&key);
+        self.outgoing.push(payload.finish());
+        self.pending.insert(key);
+    }
+
+    /// Returns true if a requested value has not arrived yet
+    pub fn is_pending(&self, key: &str) -> bool {
+        self.pending.contains(key)
+    }
+
+    /// Stores `value` under `key`
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<Vec<u8>>) {
+        let (key, value) = (key.into(), value.into());
+        let mut payload = ByteWriter::new();
+        payload
+            .u8(KeyValueOp::Set as u8)
+            .string(&key)
+            .u32(value.len() as u32)
+            .bytes(&value);
+        self.outgoing.push(payload.finish());
+        self.values.insert(key, value);
+    }
+
+    /// Deletes the value stored under `key`
+    pub fn remove(&mut self, key: &str) {
+        let mut payload = ByteWriter::new();
+        payload.u8(KeyValueOp::Remove as u8).string(key);
+        self.outgoing.push(payload.finish());
+        self.values.remove(key);
+    }
+}
+
+/// Reads one entry, returning `None` if the payload is malformed
+fn read_entry(reader: &mut ByteReader) -> Option<KeyValueChanged> {
+    let key = reader.string()?.to_string();
+    let value = if reader.bool()? {
+        let len = reader.u32()? as usize;
+        Some(reader.bytes(len)?.to_vec())
+    } else {
+        None
+    };
+    Some(KeyValueChanged { key, value })
+}
+
+/// Caches values delivered by the host
+fn receive_key_values(
+    mut commands: MessageReader<HostCommand>,
+    mut store: ResMut<HostKeyValueStore>,
+    mut changes: MessageWriter<KeyValueChanged>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::KeyValue {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while !reader.is_empty() {
+            let Some(entry) = read_entry(&mut reader) else {
+                log::warn!("Ignoring malformed key-value command");
+                break;
+            };
+            store.pending.remove(&entry.key);
+            match &entry.value {
+                Some(value) => store.values.insert(entry.key.clone(), value.clone()),
+                None => store.values.remove(&entry.key),
+            };
+            changes.write(entry);
+        }
+    }
+}
+
+/// Sends the frame's reads and writes to the host
+fn send_key_value_requests(channel: Res<HostChannel>, mut store: ResMut<HostKeyValueStore>) {
+    if store.outgoing.is_empty() {
+        return;
+    }
+    for payload in store.outgoing.drain(..) {
+        channel.send_reserved(ReservedTopic::KeyValueRequest, &payload);
+    }
+}
@@ -0,0 +1,603 @@
+//! Hardware keyboards attached to the host
+//!
+//! The host forwards key presses with `bevy_embedded_ios_key_event` /
+//! `nativeKeyEvent` and focus changes with `bevy_embedded_ios_keyboard_focus` /
+//! `nativeKeyboardFocus`. They arrive as Bevy's `KeyboardInput` and `KeyboardFocusLost`
+//! messages, so `ButtonInput<KeyCode>` works as with winit.
+//!
+//! The host also reports which modifiers are held with every key. Modifier releases the
+//! widget never saw, such as ⌘ let go while another view had focus, are synthesized from
+//! that, so a shortcut never fires with a stale modifier. The current state is in the
+//! [`KeyboardModifiers`] resource.
+//!
+//! Apps declare the shortcuts they handle and receive a [`ShortcutTriggered`] message
+//! when one is pressed:
+//!
+//! ```no_run
+//! use bevy::{input::keyboard::KeyCode, prelude::*};
+//! use bevy_embedded::{KeyModifiers, KeyboardShortcut, KeyboardShortcutApp, ShortcutTriggered};
+//!
+//! fn setup(app: &mut App) {
+//!     app.add_shortcut("undo", KeyboardShortcut::new(KeyModifiers::SUPER, KeyCode::KeyZ))
+//!         .add_shortcut(
+//!             "redo",
+//!             KeyboardShortcut::new(KeyModifiers::SUPER | KeyModifiers::SHIFT, KeyCode::KeyZ),
+//!         )
+//!         .add_systems(Update, handle_shortcuts);
+//! }
+//!
+//! fn handle_shortcuts(mut shortcuts: MessageReader<ShortcutTriggered>) {
+//!     for shortcut in shortcuts.read() {
+//!         match shortcut.name.as_str() {
+//!             "undo" => {}
+//!             "redo" => {}
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Shortcuts match physical keys, so ⌘Z is the same key on QWERTY and AZERTY layouts.
+
+use std::{collections::HashMap, ops::BitOr};
+
+use bevy::{
+    app::App,
+    ecs::{
+        entity::Entity,
+        message::{Message, MessageWriter},
+        query::Has,
+        resource::Resource,
+        system::{Query, Res, ResMut, SystemParam},
+    },
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyCode, KeyboardFocusLost, KeyboardInput, NativeKey, NativeKeyCode},
+    },
+    window::{PrimaryWindow, Window, WindowEvent},
+};
+
+use crate::{EmbeddedInputEvents, EmbeddedKeyEvent, EmbeddedKeyboardEvent};
+
+/// A set of modifier keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct KeyModifiers(u8);
+
+impl KeyModifiers {
+    /// No modifiers
+    pub const NONE: Self = Self(0);
+    /// Either Shift key
+    pub const SHIFT: Self = Self(1);
+    /// Either Control key
+    pub const CONTROL: Self = Self(1 << 1);
+    /// Either Alt / Option key
+    pub const ALT: Self = Self(1 << 2);
+    /// Either Super key: ⌘ on Apple keyboards, Meta on Android
+    pub const SUPER: Self = Self(1 << 3);
+
+    /// Create modifiers from their bits: 1 = Shift, 2 = Control, 4 = Alt, 8 = Super
+    pub fn from_bits(bits: u8) -> Self {
+        Self(bits & 0b1111)
+    }
+
+    /// The bits of these modifiers, as accepted by [`Self::from_bits`]
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns true if every modifier in `other` is held
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if no modifier is held
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The modifier a key is, if any
+    pub fn of_key(key_code: KeyCode) -> Self {
+        match key_code {
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => Self::SHIFT,
+            KeyCode::ControlLeft | KeyCode::ControlRight => Self::CONTROL,
+            KeyCode::AltLeft | KeyCode::AltRight => Self::ALT,
+            KeyCode::SuperLeft | KeyCode::SuperRight => Self::SUPER,
+            _ => Self::NONE,
+        }
+    }
+
+    fn with(self, other: Self, held: bool) -> Self {
+        if held {
+            Self(self.0 | other.0)
+        } else {
+            Self(self.0 & !other.0)
+        }
+    }
+}
+
+impl BitOr for KeyModifiers {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Modifier keys currently held on the host's keyboard
+#[derive(Resource, Debug, Default)]
+pub struct KeyboardModifiers {
+    modifiers: KeyModifiers,
+    /// Modifier keys pressed and not yet released
+    held: Vec<KeyCode>,
+}
+
+impl KeyboardModifiers {
+    /// The held modifiers
+    pub fn get(&self) -> KeyModifiers {
+        self.modifiers
+    }
+
+    /// Returns true if exactly `modifiers` are held
+    pub fn is(&self, modifiers: KeyModifiers) -> bool {
+        self.modifiers == modifiers
+    }
+}
+
+/// A key combination such as ⌘S
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyboardShortcut {
+    /// Modifiers that must be held, and no others
+    pub modifiers: KeyModifiers,
+    /// Key that triggers the shortcut
+    pub key: KeyCode,
+}
+
+impl KeyboardShortcut {
+    /// Create a shortcut for `key` pressed with exactly `modifiers`
+    pub const fn new(modifiers: KeyModifiers, key: KeyCode) -> Self {
+        Self { modifiers, key }
+    }
+}
+
+/// Shortcuts the app handles, by name
+#[derive(Resource, Debug, Default)]
+pub struct KeyboardShortcuts {
+    shortcuts: HashMap<KeyboardShortcut, String>,
+}
+
+impl KeyboardShortcuts {
+    /// Registers `shortcut` under `name`, replacing what it was registered as before
+    pub fn register(&mut self, name: impl Into<String>, shortcut: KeyboardShortcut) {
+        self.shortcuts.insert(shortcut, name.into());
+    }
+
+    /// Unregisters every shortcut named `name`
+    pub fn remove(&mut self, name: &str) {
+        self.shortcuts.retain(|_, registered| registered != name);
+    }
+
+    /// Name of the shortcut registered for a key combination
+    pub fn get(&self, shortcut: &KeyboardShortcut) -> Option<&str> {
+        self.shortcuts.get(shortcut).map(String::as_str)
+    }
+
+    /// All registered shortcuts and their names
+    pub fn iter(&self) -> impl Iterator<Item = (&KeyboardShortcut, &str)> {
+        self.shortcuts
+            .iter()
+            .map(|(shortcut, name)| (shortcut, name.as_str()))
+    }
+}
+
+/// A registered shortcut was pressed
+///
+/// The key press is also delivered as a `KeyboardInput`, without text.
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutTriggered {
+    /// Name the shortcut was registered under
+    pub name: String,
+    /// The key combination
+    pub shortcut: KeyboardShortcut,
+    /// Window that has keyboard focus
+    pub window: Entity,
+}
+
+/// Registering keyboard shortcuts while setting up an [`App`]
+pub trait KeyboardShortcutApp {
+    /// Sends [`ShortcutTriggered`] named `name` whenever `shortcut` is pressed
+    fn add_shortcut(&mut self, name: impl Into<String>, shortcut: KeyboardShortcut) -> &mut Self;
+}
+
+impl KeyboardShortcutApp for App {
+    fn add_shortcut(&mut self, name: impl Into<String>, shortcut: KeyboardShortcut) -> &mut Self {
+        self.init_resource::<KeyboardShortcuts>();
+        self.world_mut()
+            .resource_mut::<KeyboardShortcuts>()
+            .register(name, shortcut);
+        self
+    }
+}
+
+/// Messages keyboard input is delivered as
+#[derive(SystemParam)]
+pub(crate) struct KeyboardWriters<'w> {
+    keys: MessageWriter<'w, KeyboardInput>,
+    focus_lost: MessageWriter<'w, KeyboardFocusLost>,
+    window_events: MessageWriter<'w, WindowEvent>,
+    shortcuts: MessageWriter<'w, ShortcutTriggered>,
+}
+
+impl KeyboardWriters<'_> {
+    fn key(&mut self, input: KeyboardInput) {
+        self.keys.write(input.clone());
+        self.window_events.write(WindowEvent::KeyboardInput(input));
+    }
+}
+
+/// System that forwards host key and focus events to Bevy
+pub(crate) fn process_keyboard_input(
+    mut input_events: ResMut<EmbeddedInputEvents>,
+    mut writers: KeyboardWriters,
+    mut modifiers: ResMut<KeyboardModifiers>,
+    shortcuts: Res<KeyboardShortcuts>,
+    mut windows: Query<(Entity, &mut Window, Has<PrimaryWindow>)>,
+) {
+    if input_events.keyboard_events.is_empty() {
+        return;
+    }
+    let Some((entity, mut window, _)) = windows.iter_mut().max_by_key(|(_, _, primary)| *primary)
+    else {
+        return;
+    };
+
+    for event in input_events.keyboard_events.drain(..) {
+        match event {
+            EmbeddedKeyboardEvent::Focus(focused) => {
+                if window.focused != focused {
+                    window.focused = focused;
+                }
+                if !focused {
+                    // Bevy releases every key on focus loss; the host reports modifiers
+                    // again with the next key
+                    *modifiers = KeyboardModifiers::default();
+                    writers.focus_lost.write(KeyboardFocusLost);
+                    writers
+                        .window_events
+                        .write(WindowEvent::KeyboardFocusLost(KeyboardFocusLost));
+                }
+            }
+            EmbeddedKeyboardEvent::Key(event) => {
+                process_key(event, entity, &mut writers, &mut modifiers, &shortcuts);
+            }
+        }
+    }
+}
+
+fn process_key(
+    event: EmbeddedKeyEvent,
+    window: Entity,
+    writers: &mut KeyboardWriters,
+    modifiers: &mut KeyboardModifiers,
+    shortcuts: &KeyboardShortcuts,
+) {
+    // Release modifier keys the host no longer reports as held
+    let reported = event.modifiers;
+    modifiers.held.retain(|&key_code| {
+        let still_held =
+            key_code == event.key_code || reported.contains(KeyModifiers::of_key(key_code));
+        if !still_held {
+            writers.key(KeyboardInput {
+                key_code,
+                logical_key: logical_key(key_code, None),
+                state: ButtonState::Released,
+                text: None,
+                repeat: false,
+                window,
+            });
+        }
+        still_held
+    });
+
+    let modifier = KeyModifiers::of_key(event.key_code);
+    let mut current = reported;
+    if !modifier.is_empty() {
+        modifiers
+            .held
+            .retain(|&key_code| key_code != event.key_code);
+        if event.pressed {
+            modifiers.held.push(event.key_code);
+        }
+        let same_held = modifiers
+            .held
+            .iter()
+            .any(|&key_code| KeyModifiers::of_key(key_code) == modifier);
+        current = current.with(modifier, same_held);
+    }
+    modifiers.modifiers = current;
+
+    let shortcut = KeyboardShortcut::new(current, event.key_code);
+    let triggered = event.pressed && !event.repeat && modifier.is_empty();
+    let triggered = triggered.then(|| shortcuts.get(&shortcut)).flatten();
+
+    let character = event
+        .text
+        .filter(|text| !text.is_empty() && !text.starts_with(char::is_control));
+    // Control and ⌘ combinations are commands, not typing
+    let text = character.clone().filter(|_| {
+        event.pressed
+            && triggered.is_none()
+            && !current.contains(KeyModifiers::CONTROL)
+            && !current.contains(KeyModifiers::SUPER)
+    });
+    writers.key(KeyboardInput {
+        key_code: event.key_code,
+        logical_key: logical_key(event.key_code, character.as_deref()),
+        state: if event.pressed {
+            ButtonState::Pressed
+        } else {
+            ButtonState::Released
+        },
+        text: text.as_deref().map(Into::into),
+        repeat: event.repeat,
+        window,
+    });
+
+    if let Some(name) = triggered {
+        writers.shortcuts.write(ShortcutTriggered {
+            name: name.to_string(),
+            shortcut,
+            window,
+        });
+    }
+}
+
+/// The logical key of a physical key, from the text it produced if it is not a named key
+fn logical_key(key_code: KeyCode, text: Option<&str>) -> Key {
+    match key_code {
+        KeyCode::Enter | KeyCode::NumpadEnter => Key::Enter,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Space => Key::Space,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::ArrowUp => Key::ArrowUp,
+        KeyCode::ArrowDown => Key::ArrowDown,
+        KeyCode::ArrowLeft => Key::ArrowLeft,
+        KeyCode::ArrowRight => Key::ArrowRight,
+        KeyCode::ShiftLeft | KeyCode::ShiftRight => Key::Shift,
+        KeyCode::ControlLeft | KeyCode::ControlRight => Key::Control,
+        KeyCode::AltLeft | KeyCode::AltRight => Key::Alt,
+        KeyCode::SuperLeft | KeyCode::SuperRight => Key::Super,
+        KeyCode::CapsLock => Key::CapsLock,
+        KeyCode::NumLock => Key::NumLock,
+        KeyCode::ScrollLock => Key::ScrollLock,
+        KeyCode::ContextMenu => Key::ContextMenu,
+        KeyCode::PrintScreen => Key::PrintScreen,
+        KeyCode::Pause => Key::Pause,
+        KeyCode::F1 => Key::F1,
+        KeyCode::F2 => Key::F2,
+        KeyCode::F3 => Key::F3,
+        KeyCode::F4 => Key::F4,
+        KeyCode::F5 => Key::F5,
+        KeyCode::F6 => Key::F6,
+        KeyCode::F7 => Key::F7,
+        KeyCode::F8 => Key::F8,
+        KeyCode::F9 => Key::F9,
+        KeyCode::F10 => Key::F10,
+        KeyCode::F11 => Key::F11,
+        KeyCode::F12 => Key::F12,
+        _ => match text {
+            Some(text) => Key::Character(text.into()),
+            None => Key::Unidentified(NativeKey::Unidentified),
+        },
+    }
+}
+
+/// Convert a USB HID keyboard usage (`UIKey.keyCode` on iOS) to a Bevy key code
+pub fn key_code_from_hid_usage(usage: u32) -> KeyCode {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::KeyA,
+        KeyCode::KeyB,
+        KeyCode::KeyC,
+        KeyCode::KeyD,
+        KeyCode::KeyE,
+        KeyCode::KeyF,
+        KeyCode::KeyG,
+        KeyCode::KeyH,
+        KeyCode::KeyI,
+        KeyCode::KeyJ,
+        KeyCode::KeyK,
+        KeyCode::KeyL,
+        KeyCode::KeyM,
+        KeyCode::KeyN,
+        KeyCode::KeyO,
+        KeyCode::KeyP,
+        KeyCode::KeyQ,
+        KeyCode::KeyR,
+        KeyCode::KeyS,
+        KeyCode::KeyT,
+        KeyCode::KeyU,
+        KeyCode::KeyV,
+        KeyCode::KeyW,
+        KeyCode::KeyX,
+        KeyCode::KeyY,
+        KeyCode::KeyZ,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+        KeyCode::Digit0,
+    ];
+    const NUMPAD: [KeyCode; 10] = [
+        KeyCode::Numpad1,
+        KeyCode::Numpad2,
+        KeyCode::Numpad3,
+        KeyCode::Numpad4,
+        KeyCode::Numpad5,
+        KeyCode::Numpad6,
+        KeyCode::Numpad7,
+        KeyCode::Numpad8,
+        KeyCode::Numpad9,
+        KeyCode::Numpad0,
+    ];
+    const FUNCTION: [KeyCode; 12] = [
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+        KeyCode::F12,
+    ];
+
+    let usage = usage as usize;
+    match usage {
+        0x04..=0x1D => LETTERS[usage - 0x04],
+        0x1E..=0x27 => DIGITS[usage - 0x1E],
+        0x28 => KeyCode::Enter,
+        0x29 => KeyCode::Escape,
+        0x2A => KeyCode::Backspace,
+        0x2B => KeyCode::Tab,
+        0x2C => KeyCode::Space,
+        0x2D => KeyCode::Minus,
+        0x2E => KeyCode::Equal,
+        0x2F => KeyCode::BracketLeft,
+        0x30 => KeyCode::BracketRight,
+        0x31 | 0x32 => KeyCode::Backslash,
+        0x33 => KeyCode::Semicolon,
+        0x34 => KeyCode::Quote,
+        0x35 => KeyCode::Backquote,
+        0x36 => KeyCode::Comma,
+        0x37 => KeyCode::Period,
+        0x38 => KeyCode::Slash,
+        0x39 => KeyCode::CapsLock,
+        0x3A..=0x45 => FUNCTION[usage - 0x3A],
+        0x46 => KeyCode::PrintScreen,
+        0x47 => KeyCode::ScrollLock,
+        0x48 => KeyCode::Pause,
+        0x49 => KeyCode::Insert,
+        0x4A => KeyCode::Home,
+        0x4B => KeyCode::PageUp,
+        0x4C => KeyCode::Delete,
+        0x4D => KeyCode::End,
+        0x4E => KeyCode::PageDown,
+        0x4F => KeyCode::ArrowRight,
+        0x50 => KeyCode::ArrowLeft,
+        0x51 => KeyCode::ArrowDown,
+        0x52 => KeyCode::ArrowUp,
+        0x53 => KeyCode::NumLock,
+        0x54 => KeyCode::NumpadDivide,
+        0x55 => KeyCode::NumpadMultiply,
+        0x56 => KeyCode::NumpadSubtract,
+        0x57 => KeyCode::NumpadAdd,
+        0x58 => KeyCode::NumpadEnter,
+        0x59..=0x62 => NUMPAD[usage - 0x59],
+        0x63 => KeyCode::NumpadDecimal,
+        0x64 => KeyCode::IntlBackslash,
+        0x65 => KeyCode::ContextMenu,
+        0x66 => KeyCode::Power,
+        0x67 => KeyCode::NumpadEqual,
+        0x85 => KeyCode::NumpadComma,
+        0x87 => KeyCode::IntlRo,
+        0x88 => KeyCode::KanaMode,
+        0x89 => KeyCode::IntlYen,
+        0x8A => KeyCode::Convert,
+        0x8B => KeyCode::NonConvert,
+        0xE0 => KeyCode::ControlLeft,
+        0xE1 => KeyCode::ShiftLeft,
+        0xE2 => KeyCode::AltLeft,
+        0xE3 => KeyCode::SuperLeft,
+        0xE4 => KeyCode::ControlRight,
+        0xE5 => KeyCode::ShiftRight,
+        0xE6 => KeyCode::AltRight,
+        0xE7 => KeyCode::SuperRight,
+        _ => KeyCode::Unidentified(NativeKeyCode::Unidentified),
+    }
+}
+
+/// Convert an Android `KeyEvent` key code to a Bevy key code
+pub fn key_code_from_android(key_code: i32) -> KeyCode {
+    match key_code {
+        7 => KeyCode::Digit0,
+        8 => KeyCode::Digit1,
+        9 => KeyCode::Digit2,
+        10 => KeyCode::Digit3,
+        11 => KeyCode::Digit4,
+        12 => KeyCode::Digit5,
+        13 => KeyCode::Digit6,
+        14 => KeyCode::Digit7,
+        15 => KeyCode::Digit8,
+        16 => KeyCode::Digit9,
+        19 => KeyCode::ArrowUp,
+        20 => KeyCode::ArrowDown,
+        21 => KeyCode::ArrowLeft,
+        22 => KeyCode::ArrowRight,
+        29..=54 => key_code_from_hid_usage((key_code - 29 + 0x04) as u32),
+        55 => KeyCode::Comma,
+        56 => KeyCode::Period,
+        57 => KeyCode::AltLeft,
+        58 => KeyCode::AltRight,
+        59 => KeyCode::ShiftLeft,
+        60 => KeyCode::ShiftRight,
+        61 => KeyCode::Tab,
+        62 => KeyCode::Space,
+        66 => KeyCode::Enter,
+        67 => KeyCode::Backspace,
+        68 => KeyCode::Backquote,
+        69 => KeyCode::Minus,
+        70 => KeyCode::Equal,
+        71 => KeyCode::BracketLeft,
+        72 => KeyCode::BracketRight,
+        73 => KeyCode::Backslash,
+        74 => KeyCode::Semicolon,
+        75 => KeyCode::Quote,
+        76 => KeyCode::Slash,
+        82 => KeyCode::ContextMenu,
+        92 => KeyCode::PageUp,
+        93 => KeyCode::PageDown,
+        111 => KeyCode::Escape,
+        112 => KeyCode::Delete,
+        113 => KeyCode::ControlLeft,
+        114 => KeyCode::ControlRight,
+        115 => KeyCode::CapsLock,
+        116 => KeyCode::ScrollLock,
+        117 => KeyCode::SuperLeft,
+        118 => KeyCode::SuperRight,
+        119 => KeyCode::Fn,
+        120 => KeyCode::PrintScreen,
+        121 => KeyCode::Pause,
+        122 => KeyCode::Home,
+        123 => KeyCode::End,
+        124 => KeyCode::Insert,
+        131..=142 => key_code_from_hid_usage((key_code - 131 + 0x3A) as u32),
+        143 => KeyCode::NumLock,
+        144 => KeyCode::Numpad0,
+        145..=153 => key_code_from_hid_usage((key_code - 145 + 0x59) as u32),
+        154 => KeyCode::NumpadDivide,
+        155 => KeyCode::NumpadMultiply,
+        156 => KeyCode::NumpadSubtract,
+        157 => KeyCode::NumpadAdd,
+        158 => KeyCode::NumpadDecimal,
+        159 => KeyCode::NumpadComma,
+        160 => KeyCode::NumpadEnter,
+        161 => KeyCode::NumpadEqual,
+        _ => KeyCode::Unidentified(NativeKeyCode::Android(key_code as u32)),
+    }
+}
@@ -0,0 +1,81 @@
+//! Parameters the host launches an embedded scene with
+//!
+//! Hosts opening the widget from a deep link (or with some JSON describing what to show)
+//! set the `launch_url` and `launch_context` creation config keys. They are available as
+//! the [`LaunchParameters`] resource before `Startup` runs, so the first frame can already
+//! show the requested content instead of waiting for a message.
+
+use bevy::ecs::resource::Resource;
+
+/// Deep link and context the app was created with
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchParameters {
+    /// Deep link URL, e.g. `myapp://product/42?color=red`
+    pub url: Option<String>,
+    /// Free-form context from the host, typically JSON
+    pub context: Option<String>,
+}
+
+impl LaunchParameters {
+    /// The URL without its scheme, query and fragment, e.g. `product/42`
+    pub fn path(&self) -> Option<&str> {
+        let url = self.url.as_deref()?;
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let end = rest.find(['?', '#']).unwrap_or(rest.len());
+        Some(&rest[..end])
+    }
+
+    /// Decoded `name=value` pairs of the URL's query
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        let Some(url) = self.url.as_deref() else {
+            return Vec::new();
+        };
+        let Some((_, query)) = url.split_once('?') else {
+            return Vec::new();
+        };
+        let query = query.split_once('#').map_or(query, |(query, _)| query);
+        query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(name), percent_decode(value))
+            })
+            .collect()
+    }
+
+    /// The decoded value of the first query parameter called `name`
+    pub fn query(&self, name: &str) -> Option<String> {
+        self.query_pairs()
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Decodes `%XX` escapes and `+` as space, replacing invalid UTF-8
+fn percent_decode(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let escaped = rest
+                    .get(..2)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match escaped {
+                    Some(decoded) => {
+                        bytes.push(decoded);
+                        rest = &rest[2..];
+                    }
+                    None => bytes.push(b'%'),
+                }
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
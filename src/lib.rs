@@ -11,13 +11,120 @@
 //! - Uses existing `Window` component from bevy_window
 //! - Provides FFI for injecting window handles and input events
 //! - **BinaryChannel**: Bidirectional communication between Bevy and the host
+//! - **EmbeddedConfig**: Creation-time options such as headless (window-less) mode
 
 #![warn(missing_docs)]
 
+mod accessibility;
+mod annotations;
 mod app_trait;
+mod asset_errors;
+mod audio_input;
+#[cfg(feature = "host_audio")]
+mod audio_output;
+mod camera_rig;
 mod channel;
+mod clock_sync;
+mod color;
+#[cfg(feature = "wide_color")]
+mod color_output;
+mod config;
+mod coordinates;
+mod damage;
+#[cfg(feature = "debug_toggles")]
+mod debug_toggles;
+mod diagnostics_stream;
+mod display;
+mod event_codec;
+#[cfg(feature = "gltf_export")]
+mod export;
+mod file_picker;
+#[cfg(feature = "gpu_interop")]
+mod frame_fence;
+mod frame_info;
+#[cfg(feature = "gpu_capture")]
+mod gpu_capture;
+mod gpu_preferences;
+mod gpu_timing;
+mod handshake;
+mod heartbeat;
+mod hibernation;
+mod history;
+mod host_assets;
+#[cfg(feature = "gpu_interop")]
+mod host_device;
+mod host_downloads;
+mod host_events;
+#[cfg(feature = "gpu_interop")]
+mod host_frame;
+mod host_http;
+mod host_jobs;
+mod host_properties;
+mod host_views;
+#[cfg(feature = "image_export")]
+mod image_export;
+mod init_progress;
 mod input;
+mod input_latency;
+#[cfg(feature = "inspector")]
+mod inspector;
+mod key_value;
+mod keyboard;
+mod launch;
+mod lifecycle;
+mod localization;
+#[cfg(feature = "gltf")]
+mod model;
+#[cfg(feature = "gpu_interop")]
+mod offscreen;
+mod physics;
 mod plugin;
+mod render_gc;
+mod render_settings;
+mod resolution;
+mod ring_channel;
+mod safe_area;
+mod scene;
+mod screen_reader;
+mod scroll_timeline;
+mod selection;
+#[cfg(feature = "shader_reload")]
+mod shader_reload;
+mod shared_state;
+mod startup;
+mod sub_steps;
+mod surface;
+mod surface_epoch;
+mod surface_transform;
+mod task_pools;
+mod text_input;
+#[cfg(feature = "thumbnails")]
+mod thumbnail;
+mod time_control;
+mod touch_ids;
+mod tween;
+mod viewport;
+
+pub mod app_thread;
+pub mod ffi;
+pub mod frozen_frame;
+pub mod pending;
+pub mod preload;
+pub mod protocol;
+pub mod recovery;
+pub mod registry;
+pub mod replay;
+pub mod send_policy;
+pub mod teardown;
+pub mod telemetry;
+pub mod test;
+
+#[cfg(feature = "chrome_trace")]
+pub mod chrome_trace;
+#[cfg(feature = "channel_encryption")]
+pub mod encryption;
+#[cfg(feature = "snapshot_testing")]
+pub mod snapshot;
 
 #[cfg(target_os = "ios")]
 pub mod ios;
@@ -25,10 +132,141 @@ pub mod ios;
 #[cfg(target_os = "android")]
 pub mod android;
 
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+pub mod desktop;
+
+pub use accessibility::*;
+pub use annotations::*;
+pub use app_thread::ThreadPolicy;
 pub use app_trait::*;
+pub use asset_errors::AssetErrorKind;
+pub use audio_input::{AudioInputBuffer, AudioInputPlugin, HostAudioInput};
+#[cfg(feature = "host_audio")]
+pub use audio_output::{AudioRenderer, HostAudioOutput, HostAudioPlugin};
+// Lets the derive macros name Bevy items in crates that don't depend on `bevy` directly
+#[doc(hidden)]
+pub use bevy;
+/// Derive macros for [`HostEvent`] and [`EventField`]
+pub use bevy_embedded_macros::{EventField, HostEvent};
+pub use camera_rig::{CameraRigOp, CameraRigPlugin, OrbitCamera};
 pub use channel::*;
+#[cfg(feature = "chrome_trace")]
+pub use chrome_trace::{ChromeTraceLayer, chrome_trace_layer};
+pub use clock_sync::HostClock;
+pub use color::SurfaceColorSpace;
+#[cfg(feature = "wide_color")]
+pub use color_output::{ColorOutput, ColorOutputPlugin, DEFAULT_HDR_HEADROOM};
+pub use config::*;
+pub use coordinates::*;
+pub use damage::{FrameDamage, MAX_DAMAGE_RECTS};
+#[cfg(feature = "debug_toggles")]
+pub use debug_toggles::DebugToggle;
+pub use diagnostics_stream::DiagnosticsStream;
+pub use display::ExternalDisplay;
+#[cfg(feature = "channel_encryption")]
+pub use encryption::{ChannelCipher, ChannelDirection, ChannelKey};
+pub use event_codec::EventCodecError;
+#[cfg(feature = "gltf_export")]
+pub use export::GlbExport;
+pub use file_picker::{FilePicked, FilePicker, FilePickerKind, FilePickerPlugin, PickedFile};
+#[cfg(feature = "gpu_interop")]
+pub use frame_fence::FrameFence;
+pub use frame_info::FrameInfo;
+pub use frozen_frame::FallbackFrame;
+#[cfg(feature = "gpu_capture")]
+pub use gpu_capture::GpuCapture;
+pub use gpu_preferences::{GpuBackend, GpuLimits, GpuPreferencePlugins, GpuPreferences, GpuReport};
+pub use gpu_timing::{GpuTiming, GpuTimingPlugin};
+pub use handshake::{
+    Handshake, HandshakeCompleted, HandshakeError, HandshakeStatus, ProtocolSchema,
+};
+pub use heartbeat::{Heartbeat, UpdatesResumed};
+pub use hibernation::{HibernateApp, HibernatedState, HibernationPlugin};
+pub use history::{HistoryOp, HistoryPlugin, SnapshotApp, UndoHistory, WorldSnapshot};
+pub use host_assets::{HOST_ASSET_SOURCE, HostAssets};
+#[cfg(feature = "gpu_interop")]
+pub use host_device::*;
+pub use host_downloads::{
+    AssetDownloadProgress, DOWNLOAD_ASSET_SOURCE, DownloadOp, HostDownloads, HostDownloadsPlugin,
+};
+pub use host_events::{
+    EnumSchema, EventDirection, EventField, EventSchema, FieldType, HostEvent, HostEventApp,
+    HostEvents, StructSchema, event_id,
+};
+#[cfg(feature = "gpu_interop")]
+pub use host_frame::{HOST_FRAME_VIEW, HostFrame};
+pub use host_http::{HostHttp, HostHttpPlugin, HttpPart, HttpRequest, HttpResponsePart};
+pub use host_jobs::{HostJobApp, HostJobs, HostJobsPlugin, JobOp, JobOutput, JobStatus};
+pub use host_properties::{
+    HostProperties, HostPropertiesPlugin, HostPropertyApp, PropertyKind, PropertyOp, PropertyValue,
+};
+pub use host_views::{HostCamera, HostRenderLayers, HostViewApp, HostViewOp, HostViewsPlugin};
+#[cfg(feature = "image_export")]
+pub use image_export::{ImageExportAction, ImageExporter, ImageSaveStatus, ImageSaved};
+pub use init_progress::InitProgress;
 pub use input::*;
+pub use input_latency::{InputLatency, LatencyPercentiles, LatencyStage};
+#[cfg(feature = "inspector")]
+pub use inspector::{FieldValue, InspectOp, InspectStatus};
+pub use key_value::{HostKeyValueStore, KeyValueChanged, KeyValueOp, KeyValuePlugin};
+pub use keyboard::{
+    KeyModifiers, KeyboardModifiers, KeyboardShortcut, KeyboardShortcutApp, KeyboardShortcuts,
+    ShortcutTriggered, key_code_from_android, key_code_from_hid_usage,
+};
+pub use launch::LaunchParameters;
+pub use lifecycle::{HostLifecycle, HostLifecycleChanged, PictureInPictureSettings, host_visible};
+pub use localization::*;
+#[cfg(feature = "gltf")]
+pub use model::{HostModel, ModelLoaded, ModelOp};
+#[cfg(feature = "gpu_interop")]
+pub use offscreen::{OffscreenFormat, OffscreenTarget, OffscreenTargetSettings};
+pub use physics::{
+    HostPhysics, HostPhysicsPlugin, PhysicsCommand, PhysicsContact, PhysicsOp, PhysicsOverlaps,
+};
 pub use plugin::*;
+pub use recovery::{ErrorClass, Recovery, RecoveryAction, RecoveryPolicy};
+pub use render_gc::{RenderGc, RenderGcPlugin};
+pub use render_settings::{RenderSetting, RenderSettings, RenderSettingsPlugin};
+pub use replay::{ReplayEvent, ReplayLog};
+pub use resolution::{
+    DynamicResolution, DynamicResolutionPlugin, DynamicResolutionSettings, HostSurface,
+};
+pub use ring_channel::{
+    MAX_RING_CAPACITY, RingBuffer, RingChannel, RingChannelApp, RingChannels, RingEndpoint,
+};
+pub use safe_area::{SafeArea, SafeAreaChanged};
+pub use scene::HostScene;
+pub use screen_reader::{AnnouncementPriority, ScreenReader, ScreenReaderPlugin};
+pub use scroll_timeline::{ScrollTimeline, ScrollTimelinePlugin};
+pub use selection::{
+    Selectable, Selected, SelectionChanged, SelectionOp, SelectionPlugin, SelectionStyle,
+};
+pub use send_policy::{SendPolicy, SendPolicyApp};
+#[cfg(feature = "shader_reload")]
+pub use shader_reload::{MAX_RELOAD_FRAMES, ShaderReloadStatus};
+pub use shared_state::{HostState, SHARED_STATE_LEN, SharedStateBlock, SharedStatePlugin};
+pub use startup::{
+    DeferredPluginApp, PhaseTiming, StartupPhase, StartupTimingPlugins, StartupTimings,
+    record_build_end,
+};
+pub use sub_steps::HostSchedule;
+pub use surface::*;
+pub use surface_epoch::SurfaceEpoch;
+pub use surface_transform::*;
+pub use task_pools::TaskPoolSizes;
+pub use telemetry::{Telemetry, TelemetryEvent, TelemetryPlugin};
+pub use text_input::{
+    HostTextField, TextFieldSubmitted, TextInputFocus, TextInputKind, TextInputOp, TextInputPlugin,
+    TextInputRequestOp,
+};
+#[cfg(feature = "thumbnails")]
+pub use thumbnail::{
+    MAX_THUMBNAIL_SIZE, THUMBNAIL_LAYER, ThumbnailFraming, ThumbnailSource, ThumbnailStatus,
+};
+pub use time_control::{FrameStep, TimeControlOp, TimeControlPlugin, TimeLimits};
+pub use touch_ids::TouchIds;
+pub use tween::{Easing, Transition};
+pub use viewport::{HostViewport, HostViewportPlugin, ViewportScaling};
 
 #[cfg(target_os = "ios")]
 pub use ios::*;
@@ -36,9 +274,79 @@ pub use ios::*;
 #[cfg(target_os = "android")]
 pub use android::*;
 
+#[cfg(not(any(target_os = "ios", target_os = "android")))]
+pub use desktop::*;
+
 /// Prelude module for convenient imports
 pub mod prelude {
-    pub use crate::{app_trait::*, channel::*, input::*, plugin::EmbeddedPlugin};
+    pub use crate::{
+        accessibility::{AccessibilityPlugin, AccessibilitySettings, AccessibilitySettingsChanged},
+        annotations::{Annotation, Annotations, AnnotationsPlugin},
+        app_trait::*,
+        audio_input::{AudioInputPlugin, HostAudioInput},
+        camera_rig::{CameraRigPlugin, OrbitCamera},
+        channel::*,
+        clock_sync::HostClock,
+        color::SurfaceColorSpace,
+        config::EmbeddedConfig,
+        coordinates::*,
+        damage::FrameDamage,
+        diagnostics_stream::DiagnosticsStream,
+        display::ExternalDisplay,
+        file_picker::{FilePicked, FilePicker, FilePickerKind, FilePickerPlugin},
+        gpu_preferences::{GpuPreferencePlugins, GpuReport},
+        gpu_timing::{GpuTiming, GpuTimingPlugin},
+        handshake::{Handshake, HandshakeCompleted, ProtocolSchema},
+        heartbeat::{Heartbeat, UpdatesResumed},
+        hibernation::{HibernateApp, HibernationPlugin},
+        history::{HistoryPlugin, SnapshotApp, UndoHistory},
+        host_downloads::{AssetDownloadProgress, HostDownloads, HostDownloadsPlugin},
+        host_events::{HostEvent, HostEventApp, HostEvents},
+        host_http::{HostHttp, HostHttpPlugin, HttpPart, HttpRequest, HttpResponsePart},
+        host_jobs::{HostJobApp, HostJobs, HostJobsPlugin},
+        host_properties::{HostProperties, HostPropertiesPlugin, HostPropertyApp, PropertyValue},
+        host_views::{HostCamera, HostViewApp, HostViewsPlugin},
+        input::*,
+        input_latency::InputLatency,
+        key_value::{HostKeyValueStore, KeyValueChanged, KeyValuePlugin},
+        keyboard::{
+            KeyModifiers, KeyboardModifiers, KeyboardShortcut, KeyboardShortcutApp,
+            ShortcutTriggered,
+        },
+        launch::LaunchParameters,
+        lifecycle::{HostLifecycle, HostLifecycleChanged, host_visible},
+        localization::{HostLocalization, LocalizationChanged, LocalizationPlugin},
+        physics::{
+            HostPhysics, HostPhysicsPlugin, PhysicsCommand, PhysicsContact, PhysicsOverlaps,
+        },
+        plugin::{EmbeddedPlugin, PredictedTouchesPlugin},
+        protocol::{HostCommand, ReservedTopic},
+        recovery::{ErrorClass, Recovery, RecoveryAction, RecoveryPolicy},
+        render_gc::{RenderGc, RenderGcPlugin},
+        render_settings::{RenderSetting, RenderSettings, RenderSettingsPlugin},
+        resolution::{DynamicResolution, DynamicResolutionPlugin, DynamicResolutionSettings},
+        ring_channel::{RingChannelApp, RingChannels},
+        safe_area::{SafeArea, SafeAreaChanged},
+        scene::HostScene,
+        screen_reader::{AnnouncementPriority, ScreenReader, ScreenReaderPlugin},
+        scroll_timeline::{ScrollTimeline, ScrollTimelinePlugin},
+        selection::{Selectable, Selected, SelectionChanged, SelectionPlugin, SelectionStyle},
+        send_policy::{SendPolicy, SendPolicyApp},
+        shared_state::{HostState, SharedStatePlugin},
+        startup::{DeferredPluginApp, StartupTimingPlugins, StartupTimings},
+        surface::SurfacePresentation,
+        surface_transform::*,
+        telemetry::{Telemetry, TelemetryPlugin},
+        text_input::{
+            HostTextField, TextFieldSubmitted, TextInputFocus, TextInputKind, TextInputPlugin,
+        },
+        time_control::{FrameStep, TimeControlPlugin, TimeLimits},
+        tween::{Easing, Transition},
+    };
+    pub use bevy_embedded_macros::{EventField, HostEvent};
+
+    #[cfg(feature = "wide_color")]
+    pub use crate::color_output::{ColorOutput, ColorOutputPlugin};
 
     #[cfg(target_os = "ios")]
     pub use crate::ios::*;
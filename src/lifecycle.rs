@@ -0,0 +1,98 @@
+//! Host lifecycle and picture-in-picture low-power mode
+//!
+//! The host reports whether it is in the foreground or background. While the surface
+//! is presented picture-in-picture ([`SurfacePresentation::PictureInPicture`]) the host
+//! app is usually backgrounded, but the scene should keep animating in the floating
+//! window, so [`host_visible`] stays true. In picture-in-picture the surface is rendered
+//! at a lower resolution and only every few host frames, per
+//! [`PictureInPictureSettings`].
+
+use bevy::ecs::{message::Message, resource::Resource, system::Res, world::World};
+
+use crate::{EmbeddedConfig, SurfacePresentation};
+
+/// Lifecycle state of the host application
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum HostLifecycle {
+    /// In the foreground and interactive
+    #[default]
+    Active = 0,
+    /// Visible but not receiving input (e.g. an incoming call overlay)
+    Inactive = 1,
+    /// In the background; only a picture-in-picture window may still be visible
+    Background = 2,
+}
+
+impl HostLifecycle {
+    /// Create a HostLifecycle from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HostLifecycle::Active),
+            1 => Some(HostLifecycle::Inactive),
+            2 => Some(HostLifecycle::Background),
+            _ => None,
+        }
+    }
+}
+
+/// Sent when the host lifecycle state changes
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostLifecycleChanged {
+    /// State before the change
+    pub previous: HostLifecycle,
+    /// State after the change
+    pub current: HostLifecycle,
+    /// Whether the surface is currently shown picture-in-picture
+    pub picture_in_picture: bool,
+}
+
+/// Rendering budget while presented picture-in-picture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PictureInPictureSettings {
+    /// Multiplier applied to the surface resolution
+    pub resolution_scale: f32,
+    /// Update the app on every Nth host frame
+    pub frame_interval: u32,
+}
+
+impl Default for PictureInPictureSettings {
+    fn default() -> Self {
+        Self {
+            resolution_scale: 0.5,
+            frame_interval: 2,
+        }
+    }
+}
+
+/// Run condition that is true while any of the app's content is on screen
+///
+/// False only when the host is backgrounded without a picture-in-picture window.
+pub fn host_visible(lifecycle: Res<HostLifecycle>, presentation: Res<SurfacePresentation>) -> bool {
+    *lifecycle != HostLifecycle::Background
+        || *presentation == SurfacePresentation::PictureInPicture
+}
+
+/// Host frames seen since entering picture-in-picture
+#[derive(Resource, Default)]
+pub(crate) struct PictureInPictureFrames(u32);
+
+/// Returns false for host frames that picture-in-picture pacing skips
+pub(crate) fn frame_due(world: &mut World) -> bool {
+    let presentation = world
+        .get_resource::<SurfacePresentation>()
+        .copied()
+        .unwrap_or_default();
+    let interval = world
+        .get_resource::<EmbeddedConfig>()
+        .map_or(1, |config| config.picture_in_picture.frame_interval.max(1));
+    let mut frames = world.get_resource_or_init::<PictureInPictureFrames>();
+
+    if presentation != SurfacePresentation::PictureInPicture {
+        frames.0 = 0;
+        return true;
+    }
+    let due = frames.0.is_multiple_of(interval);
+    frames.0 = frames.0.wrapping_add(1);
+    due
+}
@@ -0,0 +1,213 @@
+//! Strings localized by the host
+//!
+//! The host app already ships its strings in its own format (`Localizable.strings`,
+//! `strings.xml`) and knows the user's language, so instead of duplicating string files in
+//! Rust it pushes the table Bevy needs on [`ReservedTopic::Localization`]:
+//!
+//! ```text
+//! locale: u32 len + UTF-8 | replace: u8 | entries ...
+//! entry:  key: u32 len + UTF-8 | value: u32 len + UTF-8
+//! ```
+//!
+//! With `replace` set the entries replace the whole table, otherwise they are merged into
+//! it, so large tables can be sent in several messages. Hosts send the table after
+//! creating the app and again when the locale changes. With [`LocalizationPlugin`] added,
+//! the strings are kept in the [`HostLocalization`] resource and each update is announced
+//! with [`LocalizationChanged`].
+//!
+//! With the `localization_ui` feature, bevy_ui `Text` entities with a [`LocalizedText`]
+//! component show the string for their key and follow locale changes.
+
+use std::collections::HashMap;
+
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    ecs::{
+        message::{Message, MessageReader, MessageWriter},
+        resource::Resource,
+        system::ResMut,
+    },
+};
+
+use crate::protocol::{ByteReader, HostCommand, ReservedTopic};
+
+/// Keeps the strings pushed by the host; see the [module docs](self)
+#[derive(Default)]
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostLocalization>()
+            .add_message::<LocalizationChanged>()
+            .add_systems(PreUpdate, receive_localization);
+    }
+
+    #[cfg(feature = "localization_ui")]
+    fn finish(&self, app: &mut App) {
+        use bevy::{app::PostUpdate, ecs::schedule::IntoScheduleConfigs};
+
+        // Localized text is bevy_ui text
+        if app.is_plugin_added::<bevy::ui::UiPlugin>() {
+            app.add_systems(
+                PostUpdate,
+                ui::apply_localized_text.before(bevy::ui::UiSystems::Prepare),
+            );
+        }
+    }
+}
+
+/// String table pushed by the host
+#[derive(Resource, Debug, Default)]
+pub struct HostLocalization {
+    locale: String,
+    strings: HashMap<String, String>,
+}
+
+impl HostLocalization {
+    /// The host's locale identifier, e.g. `en-US`, or empty before the host sent one
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// The localized string for `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+
+    /// The localized string for `key`, or the key itself if the host has none
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.get(key).unwrap_or(key)
+    }
+
+    /// The localized string for `key` with `{0}`, `{1}`, ... replaced by `args`
+    pub fn format(&self, key: &str, args: &[&str]) -> String {
+        let mut text = self.text(key).to_string();
+        for (index, arg) in args.iter().enumerate() {
+            text = text.replace(&format!("{{{index}}}"), arg);
+        }
+        text
+    }
+
+    /// Returns true if the host has not sent any strings
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// Sent when the host updates the string table
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct LocalizationChanged {
+    /// The locale of the table
+    pub locale: String,
+}
+
+/// Applies one command, returning `None` if the payload is malformed
+fn apply_command(localization: &mut HostLocalization, payload: &[u8]) -> Option<()> {
+    let mut reader = ByteReader::new(payload);
+    let locale = reader.string()?;
+    let replace = reader.bool()?;
+
+    let mut entries = Vec::new();
+    while !reader.is_empty() {
+        entries.push((reader.string()?, reader.string()?));
+    }
+
+    if replace {
+        localization.strings.clear();
+    }
+    localization.locale = locale.to_string();
+    localization.strings.extend(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string())),
+    );
+    Some(())
+}
+
+/// Updates the string table from host commands
+fn receive_localization(
+    mut commands: MessageReader<HostCommand>,
+    mut localization: ResMut<HostLocalization>,
+    mut changes: MessageWriter<LocalizationChanged>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::Localization {
+            continue;
+        }
+
+        if apply_command(&mut localization, &command.payload).is_none() {
+            log::warn!("Ignoring malformed localization command");
+            continue;
+        }
+        changes.write(LocalizationChanged {
+            locale: localization.locale.clone(),
+        });
+    }
+}
+
+#[cfg(feature = "localization_ui")]
+pub use ui::*;
+
+#[cfg(feature = "localization_ui")]
+mod ui {
+    use bevy::{
+        ecs::{
+            change_detection::{DetectChanges, Ref},
+            component::Component,
+            system::{Query, Res},
+        },
+        ui::widget::Text,
+    };
+
+    use super::HostLocalization;
+
+    /// Binds a bevy_ui `Text` entity to a key of the host's string table
+    ///
+    /// The text is the localized string with `{0}`, `{1}`, ... replaced by `args`, or the
+    /// key itself while the host has no string for it.
+    #[derive(Component, Debug, Clone, PartialEq, Eq)]
+    pub struct LocalizedText {
+        /// Key in the host's string table
+        pub key: String,
+        /// Values for the string's placeholders
+        pub args: Vec<String>,
+    }
+
+    impl LocalizedText {
+        /// Show the string for `key`
+        pub fn new(key: impl Into<String>) -> Self {
+            Self {
+                key: key.into(),
+                args: Vec::new(),
+            }
+        }
+
+        /// Fill the string's placeholders with `args`
+        pub fn with_args<I, S>(mut self, args: I) -> Self
+        where
+            I: IntoIterator<Item = S>,
+            S: Into<String>,
+        {
+            self.args = args.into_iter().map(Into::into).collect();
+            self
+        }
+    }
+
+    /// Sets the text of [`LocalizedText`] entities from the host's string table
+    pub(super) fn apply_localized_text(
+        localization: Res<HostLocalization>,
+        mut texts: Query<(Ref<LocalizedText>, &mut Text)>,
+    ) {
+        for (localized, mut text) in &mut texts {
+            if !localization.is_changed() && !localized.is_changed() {
+                continue;
+            }
+
+            let args: Vec<&str> = localized.args.iter().map(String::as_str).collect();
+            let value = localization.format(&localized.key, &args);
+            if text.0 != value {
+                text.0 = value;
+            }
+        }
+    }
+}
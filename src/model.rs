@@ -0,0 +1,281 @@
+//! Loading glTF models sent by the host
+//!
+//! Product-viewer hosts swap models at runtime with [`ReservedTopic::LoadModel`]
+//! commands. A command is a [`ModelOp`] byte followed by:
+//!
+//! ```text
+//! LoadBytes: request: u32 | GLB or self-contained glTF bytes ...
+//! LoadPath:  request: u32 | path: u32 len + UTF-8
+//! Unload:    entity: u64 (Entity::to_bits)
+//! ```
+//!
+//! Paths go through the `AssetServer`, so they can name bundled assets or, with Bevy's
+//! `http`/`https` features, URLs. The first scene of the file is spawned under a new
+//! root entity marked [`HostModel`]. Once it is in the world the host receives
+//! [`ReservedTopic::ModelLoaded`]:
+//!
+//! ```text
+//! request: u32 | status: u8 (0 loaded, 1 failed) | root: u64 | min: 3 × f32 | max: 3 × f32
+//! ```
+//!
+//! The bounds are the world-space box around every mesh in the model. Apps see the same
+//! result as a [`ModelLoaded`] message.
+
+use bevy::{
+    asset::{AssetPath, AssetServer, Handle},
+    camera::primitives::Aabb,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        hierarchy::Children,
+        message::{Message, MessageReader, MessageWriter},
+        name::Name,
+        query::With,
+        system::{Commands, Query, Res, SystemParam},
+    },
+    gltf::{Gltf, GltfAssetLabel},
+    math::{Vec3, Vec3A},
+    scene::{SceneInstance, SceneRoot, SceneSpawner},
+    transform::components::GlobalTransform,
+};
+
+use crate::{
+    HostAssets, HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Magic bytes at the start of a binary glTF file
+const GLB_MAGIC: &[u8] = b"glTF";
+
+/// Operations that can appear in a load model command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ModelOp {
+    /// Load a model from bytes in the command
+    LoadBytes = 0,
+    /// Load a model from an asset path or URL
+    LoadPath = 1,
+    /// Despawn a model loaded earlier
+    Unload = 2,
+}
+
+impl ModelOp {
+    /// Create a ModelOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ModelOp::LoadBytes),
+            1 => Some(ModelOp::LoadPath),
+            2 => Some(ModelOp::Unload),
+            _ => None,
+        }
+    }
+}
+
+/// Root of a model loaded by the host
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostModel {
+    /// Request id the host loaded it with
+    pub request_id: u32,
+}
+
+/// Sent when a model requested by the host has loaded or failed to load
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+pub struct ModelLoaded {
+    /// Request id the host loaded it with
+    pub request_id: u32,
+    /// Root entity of the model, or `None` if loading failed
+    pub root: Option<Entity>,
+    /// World-space minimum corner of the model's bounds
+    pub min: Vec3,
+    /// World-space maximum corner of the model's bounds
+    pub max: Vec3,
+}
+
+/// A model whose scene has not been spawned yet
+#[derive(Component)]
+pub(crate) struct LoadingModel {
+    request_id: u32,
+    gltf: Handle<Gltf>,
+    /// Host bytes to release once loaded
+    bytes_path: Option<AssetPath<'static>>,
+}
+
+/// Starts loading models requested by the host, and unloads models it is done with
+pub(crate) fn receive_model_commands(
+    mut commands: Commands,
+    mut host_commands: MessageReader<HostCommand>,
+    asset_server: Res<AssetServer>,
+    host_assets: Option<Res<HostAssets>>,
+    models: Query<(), With<HostModel>>,
+) {
+    for command in host_commands.read() {
+        if command.topic != ReservedTopic::LoadModel {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        let op = reader.u8().and_then(ModelOp::from_u8);
+        let (request_id, path, bytes_path) = match op {
+            Some(ModelOp::LoadBytes) => {
+                let Some(request_id) = reader.u32() else {
+                    log::warn!("Ignoring malformed load model command");
+                    continue;
+                };
+                let Some(host_assets) = &host_assets else {
+                    log::warn!("Cannot load model bytes without the host:// asset source");
+                    continue;
+                };
+                let bytes = reader.remaining();
+                let extension = if bytes.starts_with(GLB_MAGIC) {
+                    "glb"
+                } else {
+                    "gltf"
+                };
+                let path =
+                    host_assets.insert(format!("models/{request_id}.{extension}"), bytes.to_vec());
+                (request_id, path.clone(), Some(path))
+            }
+            Some(ModelOp::LoadPath) => {
+                let (Some(request_id), Some(path)) = (reader.u32(), reader.string()) else {
+                    log::warn!("Ignoring malformed load model command");
+                    continue;
+                };
+                (request_id, AssetPath::parse(path).into_owned(), None)
+            }
+            Some(ModelOp::Unload) => {
+                match reader.u64().and_then(Entity::try_from_bits) {
+                    Some(entity) if models.contains(entity) => {
+                        commands.entity(entity).despawn();
+                    }
+                    _ => log::warn!("Ignoring unload of an entity that is not a host model"),
+                }
+                continue;
+            }
+            None => {
+                log::warn!("Ignoring malformed load model command");
+                continue;
+            }
+        };
+
+        commands.spawn((
+            Name::new(format!("Host model {request_id}")),
+            SceneRoot(asset_server.load(GltfAssetLabel::Scene(0).from_asset(path.clone()))),
+            LoadingModel {
+                request_id,
+                gltf: asset_server.load(path),
+                bytes_path,
+            },
+        ));
+    }
+}
+
+/// Asset state needed to tell when a model has loaded
+#[derive(SystemParam)]
+pub(crate) struct ModelAssets<'w> {
+    asset_server: Res<'w, AssetServer>,
+    scene_spawner: Res<'w, SceneSpawner>,
+    host_assets: Option<Res<'w, HostAssets>>,
+}
+
+/// Corners of the world-space box around a mesh's local bounds
+pub(crate) fn world_aabb(transform: &GlobalTransform, aabb: &Aabb) -> (Vec3A, Vec3A) {
+    let affine = transform.affine();
+    let center = affine.transform_point3a(aabb.center);
+    let half_extents = (affine.matrix3.x_axis.abs() * aabb.half_extents.x)
+        + (affine.matrix3.y_axis.abs() * aabb.half_extents.y)
+        + (affine.matrix3.z_axis.abs() * aabb.half_extents.z);
+    (center - half_extents, center + half_extents)
+}
+
+/// Mesh bounds below a model root
+#[derive(SystemParam)]
+pub(crate) struct ModelBounds<'w, 's> {
+    children: Query<'w, 's, &'static Children>,
+    bounds: Query<'w, 's, (&'static GlobalTransform, Option<&'static Aabb>)>,
+}
+
+impl ModelBounds<'_, '_> {
+    /// World-space bounds of every mesh below `root`
+    fn of(&self, root: Entity) -> (Vec3, Vec3) {
+        let mut min = Vec3A::splat(f32::INFINITY);
+        let mut max = Vec3A::splat(f32::NEG_INFINITY);
+        for entity in self.children.iter_descendants(root) {
+            let Ok((transform, Some(aabb))) = self.bounds.get(entity) else {
+                continue;
+            };
+            let (mesh_min, mesh_max) = world_aabb(transform, aabb);
+            min = min.min(mesh_min);
+            max = max.max(mesh_max);
+        }
+
+        if min.x > max.x {
+            // No meshes; report the root's position
+            let position = self
+                .bounds
+                .get(root)
+                .map_or(Vec3::ZERO, |(transform, _)| transform.translation());
+            return (position, position);
+        }
+        (min.into(), max.into())
+    }
+}
+
+/// Reports models whose scene has been spawned, or whose file failed to load
+///
+/// Runs in `Last`, after transforms and mesh bounds of the new entities are computed.
+pub(crate) fn report_loaded_models(
+    mut commands: Commands,
+    channel: Res<HostChannel>,
+    assets: ModelAssets,
+    bounds: ModelBounds,
+    mut loaded: MessageWriter<ModelLoaded>,
+    loading: Query<(Entity, &LoadingModel, Option<&SceneInstance>)>,
+) {
+    for (entity, model, instance) in &loading {
+        let failed = assets.asset_server.load_state(&model.gltf).is_failed();
+        let ready =
+            instance.is_some_and(|instance| assets.scene_spawner.instance_is_ready(**instance));
+        if !failed && !ready {
+            continue;
+        }
+
+        if let (Some(path), Some(host_assets)) = (&model.bytes_path, &assets.host_assets) {
+            host_assets.remove(path.path());
+        }
+
+        let mut payload = ByteWriter::new();
+        payload.u32(model.request_id);
+        let message = if failed {
+            log::warn!("Failed to load host model {}", model.request_id);
+            commands.entity(entity).despawn();
+            payload.u8(1);
+            ModelLoaded {
+                request_id: model.request_id,
+                root: None,
+                min: Vec3::ZERO,
+                max: Vec3::ZERO,
+            }
+        } else {
+            let (min, max) = bounds.of(entity);
+            commands
+                .entity(entity)
+                .remove::<LoadingModel>()
+                .insert(HostModel {
+                    request_id: model.request_id,
+                });
+            payload.u8(0).u64(entity.to_bits());
+            for value in min.to_array().into_iter().chain(max.to_array()) {
+                payload.f32(value);
+            }
+            ModelLoaded {
+                request_id: model.request_id,
+                root: Some(entity),
+                min,
+                max,
+            }
+        };
+
+        channel.send_reserved(ReservedTopic::ModelLoaded, &payload.finish());
+        loaded.write(message);
+    }
+}
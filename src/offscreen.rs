@@ -0,0 +1,132 @@
+//! Offscreen targets Bevy allocates for the host to composite
+//!
+//! Unlike [host frames](crate::HostFrame), where the host hands Bevy its texture, an
+//! offscreen target is allocated by Bevy in the format the host composites in. The host
+//! chooses the size, pixel format, sRGB encoding and mip level count, and calls again
+//! with new settings to resize or reformat the target. The returned native texture
+//! (`MTLTexture`, `VkImage`) replaces the previous one.
+//!
+//! Cameras of the primary window render into mip level 0, so a host that asked for
+//! more levels generates the rest itself before sampling. Wait on the
+//! [`FrameFence`](crate::FrameFence) before reading the texture, and finish reading it
+//! before the next update. Frames declared [unchanged](FrameDamage::unchanged) are not
+//! rendered, so the target keeps showing the previous frame.
+
+use bevy::{
+    camera::{Camera, RenderTarget},
+    ecs::{
+        change_detection::DetectChanges,
+        entity::{Entity, EntityHashSet},
+        resource::Resource,
+        system::{Local, Query, Res},
+    },
+    math::UVec2,
+};
+
+use crate::{FrameDamage, HOST_FRAME_VIEW};
+
+/// Pixel formats the host can request for an offscreen target
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OffscreenFormat {
+    /// 8-bit RGBA
+    Rgba8 = 0,
+    /// 8-bit BGRA
+    Bgra8 = 1,
+    /// 10-bit RGB with 2-bit alpha, always linear
+    Rgb10a2 = 2,
+    /// 16-bit float RGBA, always linear
+    Rgba16Float = 3,
+}
+
+impl OffscreenFormat {
+    /// Converts the host's raw format value
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Rgba8),
+            1 => Some(Self::Bgra8),
+            2 => Some(Self::Rgb10a2),
+            3 => Some(Self::Rgba16Float),
+            _ => None,
+        }
+    }
+
+    /// The texture format for this pixel format, or `None` if it has no sRGB variant and
+    /// `srgb` is set
+    pub fn texture_format(self, srgb: bool) -> Option<wgpu::TextureFormat> {
+        use wgpu::TextureFormat;
+
+        match (self, srgb) {
+            (Self::Rgba8, false) => Some(TextureFormat::Rgba8Unorm),
+            (Self::Rgba8, true) => Some(TextureFormat::Rgba8UnormSrgb),
+            (Self::Bgra8, false) => Some(TextureFormat::Bgra8Unorm),
+            (Self::Bgra8, true) => Some(TextureFormat::Bgra8UnormSrgb),
+            (Self::Rgb10a2, false) => Some(TextureFormat::Rgb10a2Unorm),
+            (Self::Rgba16Float, false) => Some(TextureFormat::Rgba16Float),
+            (Self::Rgb10a2 | Self::Rgba16Float, true) => None,
+        }
+    }
+}
+
+/// Size and format of an offscreen target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffscreenTargetSettings {
+    /// Size of the texture in pixels
+    pub size: UVec2,
+    /// Pixel format
+    pub format: OffscreenFormat,
+    /// Whether 8-bit formats store sRGB-encoded color
+    pub srgb: bool,
+    /// Number of mip levels, at least 1
+    pub mip_levels: u32,
+}
+
+impl OffscreenTargetSettings {
+    /// Number of mip levels of a full chain for `size`
+    pub fn full_mip_chain(size: UVec2) -> u32 {
+        u32::BITS - size.max_element().max(1).leading_zeros()
+    }
+}
+
+/// The offscreen target cameras of the primary window render into
+#[derive(Resource, Debug)]
+pub struct OffscreenTarget {
+    pub(crate) settings: OffscreenTargetSettings,
+    pub(crate) texture: wgpu::Texture,
+}
+
+impl OffscreenTarget {
+    /// Size and format of the target
+    pub fn settings(&self) -> OffscreenTargetSettings {
+        self.settings
+    }
+
+    /// The texture Bevy renders into
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+}
+
+/// Suspends cameras rendering into the offscreen target during frames declared
+/// unchanged, and resumes them afterwards
+pub(crate) fn skip_unchanged_frames(
+    damage: Res<FrameDamage>,
+    target: Option<Res<OffscreenTarget>>,
+    mut cameras: Query<(Entity, &mut Camera)>,
+    mut suspended: Local<EntityHashSet>,
+) {
+    // A replaced target has no previous frame to keep
+    let skip = target.is_some_and(|target| !target.is_changed()) && damage.is_unchanged();
+    for (entity, mut camera) in &mut cameras {
+        let renders_target = matches!(
+            camera.target,
+            RenderTarget::TextureView(handle) if handle == HOST_FRAME_VIEW
+        );
+        if skip && renders_target && camera.is_active {
+            camera.is_active = false;
+            suspended.insert(entity);
+        } else if !skip && suspended.remove(&entity) {
+            camera.is_active = true;
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Creating apps on a worker thread
+//!
+//! `bevy_embedded_create_app` blocks while plugins build, asset IO starts and the render
+//! device is created, often long enough to drop frames of the host UI.
+//! `bevy_embedded_create_app_async` returns a [`PendingApp`] handle right away and builds
+//! the app on a worker thread. The app is built like a [preloaded](crate::preload) one,
+//! with a primary window that has no surface yet, because views and their layers belong
+//! to the UI thread. When it is ready the host's callback runs on the worker thread (or
+//! the host polls the handle), and the host takes the app on its UI thread, attaching
+//! the surface (`bevy_embedded_ios_take_async_app`, `nativeTakeAsyncApp`).
+//!
+//! The app is moved to the UI thread after it is built, so plugins must not insert
+//! non-send resources while building. Such apps must be created synchronously.
+
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+
+use bevy::app::App;
+
+use crate::{
+    HostConfig, app_thread,
+    preload::{self, PreloadSurface},
+};
+
+/// Called on the worker thread once the app is ready to be taken
+pub type ReadyCallback = unsafe extern "C" fn(user_data: *mut c_void, pending: *const PendingApp);
+
+/// An app still being created, or ready to be taken
+///
+/// The host holds it as an opaque pointer until it takes the app or releases the handle.
+pub struct PendingApp {
+    state: Mutex<PendingState>,
+    destroy: unsafe extern "C" fn(*mut App),
+}
+
+enum PendingState {
+    Initializing,
+    Ready(AppPtr),
+    /// Released by the host before the app was ready
+    Released,
+    Taken,
+}
+
+/// An app moved from the worker thread to the thread taking it
+struct AppPtr(*mut App);
+
+// SAFETY: the app is only used by one thread at a time: the worker until it is ready,
+// then the thread that takes it. Apps created asynchronously hold no non-send resources.
+unsafe impl Send for AppPtr {}
+
+/// Progress of a pending app
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingStatus {
+    /// Still being created on the worker thread
+    Initializing = 0,
+    /// Ready to be taken
+    Ready = 1,
+    /// Already taken
+    Taken = 2,
+}
+
+/// Starts creating an app on a worker thread, returning the handle the host holds
+///
+/// `create` runs with a surfaceless primary window of `surface`'s size and is given
+/// `config`.
+#[doc(hidden)]
+pub fn spawn(
+    surface: PreloadSurface,
+    config: HostConfig,
+    create: fn(HostConfig) -> *mut App,
+    destroy: unsafe extern "C" fn(*mut App),
+    on_ready: Option<ReadyCallback>,
+    user_data: *mut c_void,
+) -> *const PendingApp {
+    let pending = Arc::new(PendingApp {
+        state: Mutex::new(PendingState::Initializing),
+        destroy,
+    });
+    let worker = pending.clone();
+    let handle = Arc::into_raw(pending);
+    // Both are only handed back to the host, never dereferenced on the worker
+    let (handle_addr, user_data) = (handle as usize, user_data as usize);
+
+    let spawned = std::thread::Builder::new()
+        .name("bevy_embedded create".to_string())
+        .spawn(move || {
+            let app = AppPtr(preload::with_preloading(surface, || create(config)));
+            // Finish plugins here too, rather than in the first update on the UI thread
+            if let Some(address) = crate::registry::resolve(app.0) {
+                crate::ffi::finish_plugins(unsafe { &mut *address });
+            }
+            let mut state = worker
+                .state
+                .lock()
+                .unwrap_or_else(|error| error.into_inner());
+            if matches!(*state, PendingState::Released) {
+                drop(state);
+                unsafe { (worker.destroy)(app.0) };
+                return;
+            }
+            *state = PendingState::Ready(app);
+            drop(state);
+            log::info!("Asynchronously created app is ready");
+
+            if let Some(on_ready) = on_ready {
+                unsafe { on_ready(user_data as *mut c_void, handle_addr as *const PendingApp) };
+            }
+        });
+    if let Err(error) = spawned {
+        log::error!("Failed to spawn app creation thread: {error}");
+        crate::store_error(format!("Failed to spawn app creation thread: {error}"));
+        unsafe { drop(Arc::from_raw(handle)) };
+        return std::ptr::null();
+    }
+    handle
+}
+
+/// The progress of a pending app
+///
+/// # Safety
+///
+/// `pending` must be a handle from `bevy_embedded_create_app_async` that was not
+/// released.
+pub unsafe fn status(pending: *const PendingApp) -> PendingStatus {
+    let pending = unsafe { &*pending };
+    match *pending
+        .state
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+    {
+        PendingState::Initializing => PendingStatus::Initializing,
+        PendingState::Ready(_) => PendingStatus::Ready,
+        PendingState::Released | PendingState::Taken => PendingStatus::Taken,
+    }
+}
+
+/// Takes the app out of the handle once it is ready
+///
+/// The caller owns the app from now on and must attach a surface before updating it;
+/// the handle stays valid until released. The app runs on the calling thread from now
+/// on, see [`app_thread`].
+///
+/// # Safety
+///
+/// `pending` must be a handle from `bevy_embedded_create_app_async` that was not
+/// released.
+pub unsafe fn take(pending: *const PendingApp) -> Option<*mut App> {
+    let pending = unsafe { &*pending };
+    let mut state = pending
+        .state
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    match std::mem::replace(&mut *state, PendingState::Taken) {
+        PendingState::Ready(app) => {
+            app_thread::adopt(app.0);
+            Some(app.0)
+        }
+        previous => {
+            *state = previous;
+            None
+        }
+    }
+}
+
+/// Puts back an app taken from the handle, for when attaching its surface failed
+///
+/// # Safety
+///
+/// `pending` must be a handle from `bevy_embedded_create_app_async` that was not
+/// released, and `app` the app taken from it.
+#[doc(hidden)]
+pub unsafe fn restore(pending: *const PendingApp, app: *mut App) {
+    let pending = unsafe { &*pending };
+    *pending
+        .state
+        .lock()
+        .unwrap_or_else(|error| error.into_inner()) = PendingState::Ready(AppPtr(app));
+}
+
+/// Gives up the host's handle, destroying the app unless it was taken
+///
+/// An app still being created is destroyed as soon as it is ready.
+///
+/// # Safety
+///
+/// `pending` must be a handle from `bevy_embedded_create_app_async` that was not
+/// released; it is invalid afterwards.
+pub unsafe fn release(pending: *const PendingApp) {
+    let pending = unsafe { Arc::from_raw(pending) };
+    let mut state = pending
+        .state
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    match std::mem::replace(&mut *state, PendingState::Released) {
+        PendingState::Ready(app) => {
+            drop(state);
+            app_thread::adopt(app.0);
+            unsafe { (pending.destroy)(app.0) };
+        }
+        PendingState::Taken => *state = PendingState::Taken,
+        PendingState::Initializing | PendingState::Released => {}
+    }
+}
@@ -0,0 +1,325 @@
+//! Physics commands from the host
+//!
+//! Physics toys and configurators let native controls push bodies around, change gravity
+//! and ask what touches what. The crate doesn't depend on a physics engine: host commands
+//! arrive as [`PhysicsCommand`] messages that a few lines of glue apply with the engine the
+//! app uses, and the glue reports overlaps and contacts back as [`PhysicsOverlaps`] and
+//! [`PhysicsContact`] messages, once [`HostPhysicsPlugin`] is added. With avian 0.4, for
+//! example:
+//!
+//! ```ignore
+//! use avian3d::prelude::*;
+//! use bevy::prelude::*;
+//! use bevy_embedded::{PhysicsCommand, PhysicsContact, PhysicsOverlaps};
+//!
+//! fn apply_physics_commands(
+//!     mut commands: MessageReader<PhysicsCommand>,
+//!     mut gravity: ResMut<Gravity>,
+//!     mut bodies: Query<Forces>,
+//!     colliding: Query<&CollidingEntities>,
+//!     mut overlaps: MessageWriter<PhysicsOverlaps>,
+//! ) {
+//!     for command in commands.read() {
+//!         match *command {
+//!             PhysicsCommand::ApplyImpulse { entity, impulse } => {
+//!                 if let Ok(mut forces) = bodies.get_mut(entity) {
+//!                     forces.apply_linear_impulse(impulse);
+//!                 }
+//!             }
+//!             PhysicsCommand::SetGravity(value) => gravity.0 = value,
+//!             PhysicsCommand::QueryOverlaps { request, entity } => {
+//!                 let entities = colliding.get(entity).map(|c| c.iter().copied().collect());
+//!                 overlaps.write(PhysicsOverlaps {
+//!                     request,
+//!                     entities: entities.unwrap_or_default(),
+//!                 });
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! fn report_contacts(
+//!     mut started: MessageReader<CollisionStart>,
+//!     mut ended: MessageReader<CollisionEnd>,
+//!     mut contacts: MessageWriter<PhysicsContact>,
+//! ) {
+//!     for event in started.read() {
+//!         let (a, b) = (event.collider1, event.collider2);
+//!         contacts.write(PhysicsContact { a, b, started: true });
+//!     }
+//!     for event in ended.read() {
+//!         let (a, b) = (event.collider1, event.collider2);
+//!         contacts.write(PhysicsContact { a, b, started: false });
+//!     }
+//! }
+//! ```
+//!
+//! Hosts send [`ReservedTopic::Physics`] commands, a sequence of operations each starting
+//! with a [`PhysicsOp`] byte. Bodies are targeted by entity bits or by [`Name`]:
+//!
+//! ```text
+//! ApplyImpulse:        entity: u64 (Entity::to_bits) | impulse: 3 × f32
+//! ApplyImpulseByName:  name: string | impulse: 3 × f32
+//! SetGravity:          gravity: 3 × f32
+//! QueryOverlaps:       request: u32 | entity: u64
+//! QueryOverlapsByName: request: u32 | name: string
+//! StreamContacts:      enabled: u8
+//! ```
+//!
+//! Strings are `u32 len + UTF-8`. An impulse by name pushes every entity with that name; a
+//! query by name asks about the first. Each query is answered on
+//! [`ReservedTopic::PhysicsOverlaps`] with `request: u32 | count: u32` followed by
+//! `entity: u64 | name: string` per overlapping entity, with no entities if the target
+//! doesn't exist. Once the host streams contacts, each frame's contacts are sent on
+//! [`ReservedTopic::PhysicsContacts`] as `count: u32` followed by
+//! `a: u64 | a_name: string | b: u64 | b_name: string | started: u8` per contact. Names
+//! are empty for entities without a `Name`.
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    ecs::{
+        entity::Entity,
+        message::{Message, MessageReader, MessageWriter},
+        name::Name,
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Query, Res, ResMut},
+    },
+    math::Vec3,
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+    send_policy::flush_send_policies,
+};
+
+/// Bridges host physics commands and reports; see the [module docs](self)
+#[derive(Default)]
+pub struct HostPhysicsPlugin;
+
+impl Plugin for HostPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostPhysics>()
+            .add_message::<PhysicsCommand>()
+            .add_message::<PhysicsOverlaps>()
+            .add_message::<PhysicsContact>()
+            .add_systems(PreUpdate, receive_physics_commands)
+            .add_systems(Last, send_physics_reports.before(flush_send_policies));
+    }
+}
+
+/// Operations in a physics command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PhysicsOp {
+    /// Apply an impulse to an entity by its bits
+    ApplyImpulse = 0,
+    /// Apply an impulse to every entity with the given name
+    ApplyImpulseByName = 1,
+    /// Set the gravity of the world
+    SetGravity = 2,
+    /// Ask what an entity overlaps, by its bits
+    QueryOverlaps = 3,
+    /// Ask what the first entity with the given name overlaps
+    QueryOverlapsByName = 4,
+    /// Start or stop streaming contacts to the host
+    StreamContacts = 5,
+}
+
+impl PhysicsOp {
+    /// Create a PhysicsOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(PhysicsOp::ApplyImpulse),
+            1 => Some(PhysicsOp::ApplyImpulseByName),
+            2 => Some(PhysicsOp::SetGravity),
+            3 => Some(PhysicsOp::QueryOverlaps),
+            4 => Some(PhysicsOp::QueryOverlapsByName),
+            5 => Some(PhysicsOp::StreamContacts),
+            _ => None,
+        }
+    }
+}
+
+/// A physics command from the host, for the app's physics engine to apply
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+pub enum PhysicsCommand {
+    /// Apply a linear impulse to a body
+    ApplyImpulse {
+        /// Body to push
+        entity: Entity,
+        /// Impulse in world space
+        impulse: Vec3,
+    },
+    /// Set the gravity of the world
+    SetGravity(Vec3),
+    /// Find what a collider overlaps and answer with [`PhysicsOverlaps`]
+    QueryOverlaps {
+        /// Id to answer with
+        request: u32,
+        /// Collider to test
+        entity: Entity,
+    },
+}
+
+/// The answer to [`PhysicsCommand::QueryOverlaps`], sent to the host
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct PhysicsOverlaps {
+    /// Id of the query
+    pub request: u32,
+    /// Entities overlapping the queried collider
+    pub entities: Vec<Entity>,
+}
+
+/// Two colliders started or stopped touching, sent to the host while it streams contacts
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicsContact {
+    /// First collider
+    pub a: Entity,
+    /// Second collider
+    pub b: Entity,
+    /// Whether they started touching, rather than stopped
+    pub started: bool,
+}
+
+/// What the host asked of the physics integration
+#[derive(Resource, Debug, Default)]
+pub struct HostPhysics {
+    /// Whether the host wants [`PhysicsContact`]s, so glue can skip collecting them
+    pub stream_contacts: bool,
+}
+
+/// Applies one operation, returning `None` if the payload is malformed
+fn apply_op(
+    reader: &mut ByteReader,
+    physics: &mut HostPhysics,
+    named: &Query<(Entity, &Name)>,
+    commands: &mut MessageWriter<PhysicsCommand>,
+    overlaps: &mut MessageWriter<PhysicsOverlaps>,
+) -> Option<()> {
+    let op = reader.u8()?;
+    let Some(op) = PhysicsOp::from_u8(op) else {
+        log::warn!("Ignoring physics command with unknown operation {op}");
+        return None;
+    };
+    match op {
+        PhysicsOp::ApplyImpulse => {
+            let entity = Entity::try_from_bits(reader.u64()?)?;
+            let impulse = read_vec3(reader)?;
+            commands.write(PhysicsCommand::ApplyImpulse { entity, impulse });
+        }
+        PhysicsOp::ApplyImpulseByName => {
+            let name = reader.string()?;
+            let impulse = read_vec3(reader)?;
+            for (entity, _) in named.iter().filter(|(_, n)| n.as_str() == name) {
+                commands.write(PhysicsCommand::ApplyImpulse { entity, impulse });
+            }
+        }
+        PhysicsOp::SetGravity => {
+            commands.write(PhysicsCommand::SetGravity(read_vec3(reader)?));
+        }
+        PhysicsOp::QueryOverlaps | PhysicsOp::QueryOverlapsByName => {
+            let request = reader.u32()?;
+            let entity = if op == PhysicsOp::QueryOverlaps {
+                Entity::try_from_bits(reader.u64()?)
+            } else {
+                let name = reader.string()?;
+                named
+                    .iter()
+                    .find(|(_, n)| n.as_str() == name)
+                    .map(|(entity, _)| entity)
+            };
+            match entity {
+                Some(entity) => {
+                    commands.write(PhysicsCommand::QueryOverlaps { request, entity });
+                }
+                None => {
+                    overlaps.write(PhysicsOverlaps {
+                        request,
+                        entities: Vec::new(),
+                    });
+                }
+            }
+        }
+        PhysicsOp::StreamContacts => physics.stream_contacts = reader.bool()?,
+    }
+    Some(())
+}
+
+fn read_vec3(reader: &mut ByteReader) -> Option<Vec3> {
+    Some(Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?))
+}
+
+/// Turns physics commands from the host into [`PhysicsCommand`] messages
+fn receive_physics_commands(
+    mut host_commands: MessageReader<HostCommand>,
+    mut physics: ResMut<HostPhysics>,
+    named: Query<(Entity, &Name)>,
+    mut commands: MessageWriter<PhysicsCommand>,
+    mut overlaps: MessageWriter<PhysicsOverlaps>,
+) {
+    for command in host_commands.read() {
+        if command.topic != ReservedTopic::Physics {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while !reader.is_empty() {
+            if apply_op(
+                &mut reader,
+                &mut physics,
+                &named,
+                &mut commands,
+                &mut overlaps,
+            )
+            .is_none()
+            {
+                log::warn!("Ignoring malformed physics command");
+                break;
+            }
+        }
+    }
+}
+
+/// Sends overlap answers and, while the host streams them, contacts to the host
+fn send_physics_reports(
+    channel: Res<HostChannel>,
+    physics: Res<HostPhysics>,
+    mut overlaps: MessageReader<PhysicsOverlaps>,
+    mut contacts: MessageReader<PhysicsContact>,
+    names: Query<&Name>,
+) {
+    let name = |entity| names.get(entity).map_or("", Name::as_str);
+
+    for answer in overlaps.read() {
+        let mut payload = ByteWriter::new();
+        payload
+            .u32(answer.request)
+            .u32(answer.entities.len() as u32);
+        for &entity in &answer.entities {
+            payload.u64(entity.to_bits()).string(name(entity));
+        }
+        channel.send_reserved(ReservedTopic::PhysicsOverlaps, &payload.finish());
+    }
+
+    if !physics.stream_contacts {
+        contacts.clear();
+        return;
+    }
+    let contacts: Vec<_> = contacts.read().collect();
+    if contacts.is_empty() {
+        return;
+    }
+    let mut payload = ByteWriter::new();
+    payload.u32(contacts.len() as u32);
+    for contact in contacts {
+        payload
+            .u64(contact.a.to_bits())
+            .string(name(contact.a))
+            .u64(contact.b.to_bits())
+            .string(name(contact.b))
+            .bool(contact.started);
+    }
+    channel.send_reserved(ReservedTopic::PhysicsContacts, &payload.finish());
+}
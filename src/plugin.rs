@@ -1,19 +1,36 @@
 //! Embedded window plugin that replaces WinitPlugin for embedded mode
 
 use bevy::{
-    app::{App, Last, Plugin, PreUpdate},
+    app::{
+        App, First, FixedFirst, Last, MainScheduleOrder, Plugin, PreUpdate, RunFixedMainLoop,
+        RunFixedMainLoopSystems,
+    },
+    diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
     ecs::{
         entity::Entity,
         message::MessageWriter,
-        query::With,
-        system::{Query, ResMut},
+        query::Has,
+        schedule::{IntoScheduleConfigs, ScheduleLabel},
+        system::{Query, Res, ResMut, SystemParam},
     },
-    input::touch::TouchInput,
+    input::keyboard::{KeyboardFocusLost, KeyboardInput},
+    input::mouse::{MouseScrollUnit, MouseWheel},
     input::touch::TouchPhase as BevyTouchPhase,
-    window::{Window, exit_on_all_closed},
+    input::touch::{ForceTouch, TouchInput},
+    math::Vec2,
+    window::{
+        PrimaryWindow, Window, WindowCloseRequested, WindowClosed, WindowClosing, WindowCreated,
+        WindowEvent, exit_on_all_closed,
+    },
 };
 
-use crate::{channel::*, input::*};
+use crate::{
+    asset_errors::*, channel::*, config::*, coordinates::*, damage::*, diagnostics_stream::*,
+    handshake::*, heartbeat::*, host_assets::*, input::*, input_latency::*, keyboard::*,
+    lifecycle::*, protocol::*, recovery::*, resolution::*, ring_channel::*, safe_area::*,
+    send_policy::*, startup::*, surface::*, surface_epoch::*, surface_transform::*, task_pools::*,
+    time_control::*, touch_ids::*,
+};
 
 /// Plugin that provides embedded window support
 ///
@@ -21,6 +38,13 @@ use crate::{channel::*, input::*};
 /// Instead of creating windows through winit, the host application provides
 /// window surfaces and forwards input events to Bevy.
 ///
+/// Host touches are written as both `TouchInput` and `WindowEvent::TouchInput` before
+/// [`First`] runs, matching what winit does, so `bevy_picking` pointers (and with them
+/// `Pointer<Click>` and bevy_ui `Interaction`) work without extra glue.
+///
+/// When the [`EmbeddedConfig`] resource has `headless` set, no window is required
+/// and the app keeps running without one.
+///
 /// # Example
 ///
 /// ```no_run
@@ -45,13 +69,247 @@ impl Plugin for EmbeddedPlugin {
     }
 
     fn build(&self, app: &mut App) {
-        app.init_resource::<EmbeddedInputEvents>()
+        app.init_resource::<EmbeddedConfig>();
+        let config = app.world().resource::<EmbeddedConfig>();
+        let surface_transform = config.surface_transform;
+        let launch = config.launch.clone();
+        let handshake = Handshake::new(config.protocol.clone());
+        #[cfg(feature = "channel_encryption")]
+        let channel_key = config.channel_key.clone();
+        let diagnostics = config.diagnostics.clone();
+        let heartbeat = config.heartbeat_timeout.map(Heartbeat::new);
+        let time_limits = config.time_limits;
+
+        // Before `TaskPoolPlugin`, which keeps pools that already exist
+        init_task_pools(config.task_pools);
+
+        app.insert_resource(surface_transform)
+            .insert_resource(time_limits)
+            .insert_resource(launch)
+            .init_resource::<EmbeddedInputEvents>()
+            .init_resource::<TouchIds>()
+            .init_resource::<KeyboardModifiers>()
+            .init_resource::<KeyboardShortcuts>()
+            .init_resource::<SurfacePresentation>()
+            .init_resource::<HostLifecycle>()
+            .init_resource::<SafeArea>()
             .init_resource::<HostChannel>()
-            .add_systems(PreUpdate, process_embedded_input)
-            .add_systems(Last, exit_on_all_closed);
+            // Shared with the registry, so the host reaches channels opened at any time
+            .init_resource::<RingChannels>()
+            .init_resource::<FrameDamage>()
+            .init_resource::<SurfaceEpoch>()
+            .insert_resource(handshake)
+            .init_resource::<StartupTimings>()
+            .init_resource::<Recovery>()
+            .add_message::<TouchInput>()
+            .add_message::<TouchContactInput>()
+            .add_message::<RotaryInput>()
+            .add_message::<MouseWheel>()
+            .add_message::<KeyboardInput>()
+            .add_message::<KeyboardFocusLost>()
+            .add_message::<ShortcutTriggered>()
+            .add_message::<WindowEvent>()
+            .add_message::<WindowCreated>()
+            .add_message::<WindowCloseRequested>()
+            .add_message::<WindowClosing>()
+            .add_message::<WindowClosed>()
+            .add_message::<HostLifecycleChanged>()
+            .add_message::<UpdatesResumed>()
+            .add_message::<SafeAreaChanged>()
+            .add_message::<HostCommand>()
+            .add_message::<HandshakeCompleted>()
+            .init_schedule(EmbeddedInput)
+            .add_systems(
+                EmbeddedInput,
+                (
+                    route_host_messages,
+                    remap_touch_ids.before(process_embedded_input),
+                    process_keyboard_input.before(process_embedded_input),
+                    process_rotary_input.before(process_embedded_input),
+                    process_embedded_input,
+                    deliver_timed_touches.after(process_embedded_input),
+                ),
+            )
+            .add_systems(First, reset_frame_damage)
+            .add_systems(PreUpdate, receive_handshake)
+            .add_systems(
+                Last,
+                (
+                    stream_diagnostics,
+                    flush_send_policies,
+                    report_closed_windows,
+                    exit_on_all_closed.run_if(has_window_surface),
+                ),
+            );
+
+        if let Some(heartbeat) = heartbeat {
+            app.insert_resource(heartbeat);
+        }
+
+        // Streamed diagnostics include the frame time and entity count
+        if let Some(diagnostics) = diagnostics {
+            app.insert_resource(diagnostics);
+            if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+                app.add_plugins(FrameTimeDiagnosticsPlugin::default());
+            }
+            if !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
+                app.add_plugins(EntityCountDiagnosticsPlugin::default());
+            }
+        }
+
+        // Host-provided bytes are loaded through their own asset source
+        register_host_asset_source(app);
+
+        #[cfg(feature = "gltf")]
+        app.add_message::<crate::model::ModelLoaded>();
+
+        #[cfg(feature = "inspector")]
+        app.add_systems(PreUpdate, crate::inspector::receive_inspect_requests);
+
+        // Seal the channel before any message is sent on it
+        #[cfg(feature = "channel_encryption")]
+        if let Some(key) = channel_key {
+            app.world_mut()
+                .resource_mut::<HostChannel>()
+                .set_cipher(crate::ChannelCipher::new(&key));
+        }
+
+        app.world_mut()
+            .resource_mut::<MainScheduleOrder>()
+            .insert_before(First, EmbeddedInput);
     }
 
     fn finish(&self, app: &mut App) {
+        // Asset failures are only reported by apps with an asset server
+        if app.world().contains_resource::<bevy::asset::AssetServer>() {
+            app.add_systems(Last, (send_asset_errors, record_asset_failures));
+        }
+
+        // Wide color spaces are only presented by ColorOutputPlugin
+        let color_space = app.world().resource::<EmbeddedConfig>().color_space;
+        #[cfg(feature = "wide_color")]
+        let presented = app.world().contains_resource::<crate::ColorOutput>();
+        #[cfg(not(feature = "wide_color"))]
+        let presented = false;
+        if color_space != crate::SurfaceColorSpace::Srgb && !presented {
+            log::warn!(
+                "Color space {color_space:?} needs ColorOutputPlugin and the wide_color feature; \
+                 presenting sRGB"
+            );
+        }
+
+        // Host frames are rendered by cameras of the primary window
+        #[cfg(feature = "gpu_interop")]
+        app.add_systems(
+            bevy::app::PostUpdate,
+            crate::host_frame::target_host_frame.before(bevy::camera::CameraUpdateSystems),
+        );
+
+        // Unchanged frames are skipped only where the target keeps its contents
+        #[cfg(feature = "gpu_interop")]
+        app.add_systems(Last, crate::offscreen::skip_unchanged_frames);
+
+        // Surface changes wait for frames the render world is still presenting, and
+        // frame fences are signaled around its queue submission
+        let surface_epoch = app.world().resource::<SurfaceEpoch>().clone();
+        let startup_timings = app.world().resource::<StartupTimings>().clone();
+        let recovery = app.world().resource::<Recovery>().clone();
+        if let Some(render_app) = app.get_sub_app_mut(bevy::render::RenderApp) {
+            crate::surface_epoch::add_render_systems(render_app, surface_epoch);
+            crate::startup::add_render_systems(render_app, startup_timings);
+            crate::recovery::add_render_systems(render_app, recovery);
+            #[cfg(feature = "gpu_interop")]
+            crate::frame_fence::add_render_systems(render_app);
+            // EGL windows keep the size of the view unless told otherwise
+            #[cfg(all(target_os = "android", feature = "gles_fallback"))]
+            crate::android::add_gles_render_systems(render_app);
+        }
+
+        // GPU frame captures are requested from the main world and taken in the render world
+        #[cfg(feature = "gpu_capture")]
+        if let Some(render_app) = app.get_sub_app_mut(bevy::render::RenderApp) {
+            let gpu_capture = crate::GpuCapture::default();
+            crate::gpu_capture::add_render_systems(render_app, gpu_capture.clone());
+            app.insert_resource(gpu_capture);
+        }
+
+        // Shaders are replaced in the main world and their pipelines compiled in the
+        // render world
+        #[cfg(feature = "shader_reload")]
+        if let Some(render_app) = app.get_sub_app_mut(bevy::render::RenderApp) {
+            let shader_reloads = crate::shader_reload::ShaderReloads::default();
+            crate::shader_reload::add_render_systems(render_app, shader_reloads.clone());
+            app.insert_resource(shader_reloads)
+                .add_systems(PreUpdate, crate::shader_reload::receive_shader_sources);
+        }
+
+        // Loading models needs the asset server and scene spawner in the app
+        #[cfg(feature = "gltf")]
+        if app.world().contains_resource::<bevy::scene::SceneSpawner>() {
+            use crate::model::{receive_model_commands, report_loaded_models};
+
+            app.add_systems(PreUpdate, receive_model_commands)
+                .add_systems(Last, report_loaded_models);
+        }
+
+        // Thumbnails of assets are loaded like models, and read back from the render world
+        #[cfg(feature = "thumbnails")]
+        if app.world().contains_resource::<bevy::scene::SceneSpawner>()
+            && app.get_sub_app(bevy::render::RenderApp).is_some()
+        {
+            use crate::thumbnail::{Thumbnails, receive_thumbnail_requests, render_thumbnails};
+
+            app.init_resource::<Thumbnails>()
+                .add_systems(PreUpdate, receive_thumbnail_requests)
+                .add_systems(Last, render_thumbnails);
+        }
+
+        // Exporting needs mesh assets in the app
+        #[cfg(feature = "gltf_export")]
+        if app
+            .world()
+            .contains_resource::<bevy::asset::Assets<bevy::mesh::Mesh>>()
+        {
+            app.add_systems(Last, crate::export::receive_export_commands);
+        }
+
+        // Time limits act on virtual time, which needs TimePlugin
+        let max_delta = app.world().resource::<TimeLimits>().max_delta;
+        if let Some(mut time) = app
+            .world_mut()
+            .get_resource_mut::<bevy::time::Time<bevy::time::Virtual>>()
+        {
+            if max_delta.is_zero() {
+                log::warn!("Ignoring a zero TimeLimits::max_delta");
+            } else {
+                time.set_max_delta(max_delta);
+            }
+            app.init_resource::<FixedStepsRun>()
+                .add_systems(
+                    RunFixedMainLoop,
+                    reset_fixed_steps.in_set(RunFixedMainLoopSystems::BeforeFixedMainLoop),
+                )
+                .add_systems(FixedFirst, limit_fixed_steps);
+        }
+
+        // Exported images are encoded from screenshots or loaded images
+        #[cfg(feature = "image_export")]
+        if app
+            .world()
+            .contains_resource::<bevy::asset::Assets<bevy::image::Image>>()
+        {
+            use crate::image_export::{ImageExportRequests, ImageSaved, receive_image_saved};
+
+            app.init_resource::<ImageExportRequests>()
+                .add_message::<ImageSaved>()
+                .add_systems(PreUpdate, receive_image_saved);
+        }
+
+        if app.world().resource::<EmbeddedConfig>().headless {
+            log::debug!("EmbeddedPlugin running headless, skipping window checks");
+            return;
+        }
+
         // Verify WindowPlugin configuration after all plugins are added
         let windows = app
             .world_mut()
@@ -74,6 +332,74 @@ impl Plugin for EmbeddedPlugin {
                 windows
             );
         }
+
+        // Windows are created at the host surface size; apply the initial transform
+        init_host_surface(app.world_mut());
+    }
+
+    fn cleanup(&self, app: &mut App) {
+        // The render device exists once every plugin is finished
+        if let Some(report) = crate::GpuReport::of(app.world()) {
+            log::info!(
+                "Rendering with {} ({}, {})",
+                report.adapter,
+                report.backend,
+                report.device_type
+            );
+            if !report.meets_requirements {
+                log::warn!("{} is below the required GPU limits", report.adapter);
+            }
+            app.insert_resource(report);
+        }
+    }
+}
+
+/// Schedule that forwards host input to Bevy, run at the start of every frame before
+/// [`First`]
+///
+/// This is where winit would deliver window events, so input consumers in `First`
+/// (such as `bevy_picking`'s pointer input) and `PreUpdate` see touches on the same frame.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmbeddedInput;
+
+/// Run condition that is false for headless apps, which never have a window to close
+fn has_window_surface(config: Res<EmbeddedConfig>) -> bool {
+    !config.headless
+}
+
+/// Maps positions the host reports on its surface into a window's content
+#[derive(SystemParam)]
+struct HostInputSpace<'w> {
+    config: Res<'w, EmbeddedConfig>,
+    transform: Res<'w, SurfaceTransform>,
+    surface: Option<Res<'w, HostSurface>>,
+}
+
+impl HostInputSpace<'_> {
+    /// Converts a host position into logical content coordinates of `window`
+    fn to_content(&self, position: Vec2, window: &Window) -> Vec2 {
+        // Host positions are relative to the (possibly rotated) surface
+        let surface = host_surface_of(self.surface.as_deref(), window);
+        let position = host_to_window(position, &surface, self.config.touch_coordinates);
+        let surface_size = self.transform.surface_size(window.size());
+        self.transform.surface_to_content(position, surface_size)
+    }
+
+    /// Converts contact data reported by the host into logical pixels and content
+    /// orientation
+    fn contact_to_content(&self, contact: TouchContact, window: &Window) -> TouchContact {
+        let surface = host_surface_of(self.surface.as_deref(), window);
+        // Contact sizes scale like positions; rotation and mirroring only turn the ellipse
+        let size = self.config.touch_coordinates.to_logical(
+            Vec2::new(contact.touch_major, contact.touch_minor),
+            surface.scale_factor,
+        );
+        TouchContact {
+            touch_major: size.x,
+            touch_minor: size.y,
+            orientation: self.transform.surface_to_content_angle(contact.orientation),
+            ..contact
+        }
     }
 }
 
@@ -81,12 +407,18 @@ impl Plugin for EmbeddedPlugin {
 fn process_embedded_input(
     mut input_events: ResMut<EmbeddedInputEvents>,
     mut touch_writer: MessageWriter<TouchInput>,
-    windows: Query<Entity, With<Window>>,
+    mut contact_writer: MessageWriter<TouchContactInput>,
+    mut window_event_writer: MessageWriter<WindowEvent>,
+    space: HostInputSpace,
+    windows: Query<(Entity, &Window, Has<PrimaryWindow>)>,
 ) {
     // Get the primary window entity (or first available)
-    let window_entity = windows.iter().next();
+    let window = windows
+        .iter()
+        .max_by_key(|(_, _, primary)| *primary)
+        .map(|(entity, window, _)| (entity, window));
 
-    if let Some(window) = window_entity {
+    if let Some((entity, window)) = window {
         // Process touch events
         for event in input_events.touch_events.drain(..) {
             let bevy_phase = match event.phase {
@@ -96,15 +428,118 @@ fn process_embedded_input(
                 TouchPhase::Cancelled => BevyTouchPhase::Canceled,
             };
 
-            touch_writer.write(TouchInput {
+            let position = space.to_content(event.position, window);
+
+            let touch = TouchInput {
                 phase: bevy_phase,
-                position: event.position,
-                window,
-                force: None,
+                position,
+                window: entity,
+                force: event
+                    .contact
+                    .map(|contact| ForceTouch::Normalized(contact.pressure as f64)),
                 id: event.id,
-            });
+            };
+            touch_writer.write(touch);
+            // bevy_picking builds touch pointers from window events
+            window_event_writer.write(WindowEvent::TouchInput(touch));
+
+            if let Some(contact) = event.contact {
+                contact_writer.write(TouchContactInput {
+                    window: entity,
+                    id: event.id,
+                    contact: space.contact_to_content(contact, window),
+                });
+            }
         }
     }
 
     input_events.clear();
 }
+
+/// System that forwards rotary input as [`RotaryInput`] and `MouseWheel`
+fn process_rotary_input(
+    mut input_events: ResMut<EmbeddedInputEvents>,
+    mut rotary_writer: MessageWriter<RotaryInput>,
+    mut wheel_writer: MessageWriter<MouseWheel>,
+    mut window_event_writer: MessageWriter<WindowEvent>,
+    config: Res<EmbeddedConfig>,
+    host_surface: Option<Res<HostSurface>>,
+    windows: Query<(Entity, &Window, Has<PrimaryWindow>)>,
+) {
+    if input_events.rotary_events.is_empty() {
+        return;
+    }
+    let Some((entity, window, _)) = windows.iter().max_by_key(|(_, _, primary)| *primary) else {
+        return;
+    };
+    let surface = host_surface_of(host_surface.as_deref(), window);
+
+    for delta in input_events.rotary_events.drain(..) {
+        let delta = config
+            .touch_coordinates
+            .to_logical(Vec2::new(0.0, delta), surface.scale_factor)
+            .y;
+        rotary_writer.write(RotaryInput {
+            window: entity,
+            delta,
+        });
+        // Clockwise scrolls content down, like turning a mouse wheel towards the user
+        let wheel = MouseWheel {
+            unit: MouseScrollUnit::Pixel,
+            x: 0.0,
+            y: -delta,
+            window: entity,
+        };
+        wheel_writer.write(wheel);
+        window_event_writer.write(WindowEvent::MouseWheel(wheel));
+    }
+}
+
+/// Plugin that delivers the host's touch predictions as [`PredictedTouches`]
+///
+/// Without it, predictions sent by the host are dropped.
+#[derive(Default)]
+pub struct PredictedTouchesPlugin;
+
+impl Plugin for PredictedTouchesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PredictedTouches>().add_systems(
+            EmbeddedInput,
+            process_predicted_touches
+                .after(remap_touch_ids)
+                .before(process_embedded_input),
+        );
+    }
+}
+
+/// System that replaces [`PredictedTouches`] with the predictions sent this frame
+fn process_predicted_touches(
+    mut input_events: ResMut<EmbeddedInputEvents>,
+    mut predicted: ResMut<PredictedTouches>,
+    touch_ids: Res<TouchIds>,
+    space: HostInputSpace,
+    windows: Query<(Entity, &Window, Has<PrimaryWindow>)>,
+) {
+    // Last frame's predictions are superseded whether or not new ones arrived
+    if predicted.is_empty() && input_events.predicted_touches.is_empty() {
+        return;
+    }
+    predicted.touches.clear();
+
+    let Some((entity, window, _)) = windows.iter().max_by_key(|(_, _, primary)| *primary) else {
+        return;
+    };
+    predicted.touches = input_events
+        .predicted_touches
+        .drain(..)
+        .filter_map(|event| {
+            // Predictions only extend touches that are down
+            let id = touch_ids.get(event.id)?;
+            Some(PredictedTouch {
+                window: entity,
+                id,
+                position: space.to_content(event.position, window),
+            })
+        })
+        .collect();
+}
@@ -0,0 +1,249 @@
+//! Pool of app instances created ahead of time
+//!
+//! Creating an app takes hundreds of milliseconds, most of it plugin setup and render
+//! pipeline creation. Hosts that show many widgets, such as a scroll feed with a Bevy
+//! cell every few rows, set a preload target and call `bevy_embedded_preload_step` when
+//! they are idle (an idle handler, a display link with spare time). Each step creates at
+//! most one instance, so the work is spread over idle moments instead of stalling a
+//! frame.
+//!
+//! Preloaded apps have a primary window without a surface, run one update so `Startup`
+//! systems spawn the scene and start loading assets, and then wait in the background
+//! [lifecycle](crate::HostLifecycle) without being updated. When a cell appears, the host
+//! takes an app (`bevy_embedded_ios_take_preloaded_app`, `nativeTakePreloadedApp`) which
+//! attaches its surface and makes it active; it then owns the app like one it created.
+//! When nothing is preloaded the host falls back to creating an app directly.
+
+use std::{cell::Cell, collections::VecDeque, sync::Mutex};
+
+use bevy::{
+    app::App,
+    window::{PrimaryWindow, Window, WindowResolution},
+};
+
+use crate::{HostConfig, HostLifecycle, app_thread};
+
+/// Apps waiting in the pool and how many the host wants
+static POOL: Mutex<Pool> = Mutex::new(Pool {
+    target: 0,
+    surface: PreloadSurface {
+        width: 1,
+        height: 1,
+        scale_factor: 1.0,
+    },
+    config: HostConfig::new(),
+    apps: VecDeque::new(),
+});
+
+thread_local! {
+    /// Surface size of the app being preloaded on this thread
+    static PRELOADING: Cell<Option<PreloadSurface>> = const { Cell::new(None) };
+}
+
+struct Pool {
+    target: usize,
+    surface: PreloadSurface,
+    /// Creation config of every preloaded app
+    config: HostConfig,
+    /// Addresses of the preloaded apps, oldest first
+    apps: VecDeque<usize>,
+}
+
+/// Size preloaded apps are created at, usually the size of the host's cells
+///
+/// Apps are resized to their real surface when taken, so this only needs to be close.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreloadSurface {
+    /// Physical width in pixels
+    pub width: u32,
+    /// Physical height in pixels
+    pub height: u32,
+    /// Physical pixels per logical pixel
+    pub scale_factor: f32,
+}
+
+/// What the next preload step should do
+#[doc(hidden)]
+pub enum PreloadStep {
+    /// Create an app of this size with this creation config
+    Create(PreloadSurface, HostConfig),
+    /// Destroy this app, which the lowered target no longer needs
+    Destroy(*mut App),
+}
+
+/// Sets how many apps to keep preloaded, and the size and creation config to create
+/// them with
+///
+/// The pool keeps its own copy of `config`, so preloading never consumes values the
+/// host set for an app it creates directly. Lowering the target destroys surplus apps
+/// in the following preload steps; apps already preloaded keep the config they were
+/// created with.
+pub fn set_preload_target(count: usize, surface: PreloadSurface, config: HostConfig) {
+    if let Ok(mut pool) = POOL.lock() {
+        pool.target = count;
+        pool.surface = surface;
+        pool.config = config;
+    }
+}
+
+/// Number of apps currently waiting in the pool
+pub fn preloaded_count() -> usize {
+    POOL.lock().map_or(0, |pool| pool.apps.len())
+}
+
+/// Takes the oldest preloaded app out of the pool
+///
+/// The caller owns the app from now on and must attach a surface before updating it.
+/// The app runs on the calling thread from now on, see [`app_thread`].
+pub fn take_preloaded() -> Option<*mut App> {
+    let app = POOL.lock().ok()?.apps.pop_front()? as *mut App;
+    app_thread::adopt(app);
+    Some(app)
+}
+
+/// Returns the work the next preload step should do, if any
+#[doc(hidden)]
+pub fn next_step() -> Option<PreloadStep> {
+    let mut pool = POOL.lock().ok()?;
+    if pool.apps.len() > pool.target {
+        let app = pool.apps.pop_back()? as *mut App;
+        app_thread::adopt(app);
+        return Some(PreloadStep::Destroy(app));
+    }
+    (pool.apps.len() < pool.target).then(|| PreloadStep::Create(pool.surface, pool.config.clone()))
+}
+
+/// Runs `create` so that the created app gets a surfaceless window of `surface`'s size
+/// instead of asking the host for its surface
+#[doc(hidden)]
+pub fn with_preloading<R>(surface: PreloadSurface, create: impl FnOnce() -> R) -> R {
+    PRELOADING.with(|current| current.set(Some(surface)));
+    let result = create();
+    PRELOADING.with(|current| current.set(None));
+    result
+}
+
+/// Adds a freshly created app to the pool
+#[doc(hidden)]
+pub fn push_preloaded(app: *mut App) {
+    if let Ok(mut pool) = POOL.lock() {
+        pool.apps.push_back(app as usize);
+    }
+}
+
+/// Empties the pool, returning the apps for the caller to destroy
+#[doc(hidden)]
+pub fn drain_preloaded() -> Vec<*mut App> {
+    let apps: Vec<*mut App> = POOL.lock().map_or_else(
+        |_| Vec::new(),
+        |mut pool| pool.apps.drain(..).map(|app| app as *mut App).collect(),
+    );
+    for &app in &apps {
+        app_thread::adopt(app);
+    }
+    apps
+}
+
+/// Spawns the primary window of an app being preloaded, returning false if no app is
+/// being preloaded on this thread
+///
+/// Called instead of requesting the host's surface; the window gets its surface when
+/// the app is taken.
+#[doc(hidden)]
+pub fn spawn_preloaded_window(app: &mut App) -> bool {
+    let Some(surface) = PRELOADING.with(Cell::get) else {
+        return false;
+    };
+    let window = Window {
+        resolution: WindowResolution::new(surface.width, surface.height)
+            .with_scale_factor_override(surface.scale_factor),
+        ..Default::default()
+    };
+    app.world_mut().spawn((window, PrimaryWindow));
+    app.insert_resource(HostLifecycle::Background);
+    log::debug!(
+        "Preloading app at {}x{} @ {}x scale",
+        surface.width,
+        surface.height,
+        surface.scale_factor
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::MinimalPlugins;
+
+    use super::*;
+    use crate::test::{MockHost, MockSurface};
+
+    const SURFACE: PreloadSurface = PreloadSurface {
+        width: 300,
+        height: 200,
+        scale_factor: 2.0,
+    };
+
+    /// Runs the preload steps like `bevy_embedded_preload_step`, with mock hosts for apps
+    fn run_steps(hosts: &mut Vec<MockHost>) -> usize {
+        std::iter::from_fn(next_step)
+            .map(|step| match step {
+                PreloadStep::Create(surface, _) => {
+                    assert_eq!(surface, SURFACE);
+                    let mut host = MockHost::from_setup(MockSurface::default(), |app| {
+                        app.add_plugins(MinimalPlugins);
+                    });
+                    host.update();
+                    push_preloaded(host.app_ptr() as *mut App);
+                    hosts.push(host);
+                }
+                PreloadStep::Destroy(app) => {
+                    hosts.retain_mut(|host| host.app_ptr() as *mut App != app);
+                }
+            })
+            .count()
+    }
+
+    // The pool is shared by the whole process, so it is tested in one test
+    #[test]
+    fn pool_follows_its_target() {
+        let mut hosts = Vec::new();
+        set_preload_target(2, SURFACE, HostConfig::new());
+        assert_eq!(run_steps(&mut hosts), 2);
+        assert_eq!(preloaded_count(), 2);
+        assert!(next_step().is_none());
+
+        // Apps are taken oldest first, and taking one lets the next step replace it
+        let first = hosts[0].app_ptr() as *mut App;
+        assert_eq!(take_preloaded(), Some(first));
+        assert_eq!(run_steps(&mut hosts), 1);
+        assert_eq!(preloaded_count(), 2);
+
+        // Lowering the target destroys the newest apps
+        let second = hosts[1].app_ptr() as *mut App;
+        set_preload_target(1, SURFACE, HostConfig::new());
+        assert_eq!(run_steps(&mut hosts), 1);
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(drain_preloaded(), vec![second]);
+        assert_eq!(preloaded_count(), 0);
+        set_preload_target(0, SURFACE, HostConfig::new());
+    }
+
+    #[test]
+    fn preloaded_windows_wait_in_the_background() {
+        let mut app = App::new();
+        assert!(!spawn_preloaded_window(&mut app));
+        assert!(with_preloading(SURFACE, || spawn_preloaded_window(
+            &mut app
+        )));
+        assert!(!spawn_preloaded_window(&mut app));
+
+        let mut windows = app.world_mut().query::<(&Window, &PrimaryWindow)>();
+        let (window, _) = windows.single(app.world()).unwrap();
+        assert_eq!(window.resolution.physical_width(), SURFACE.width);
+        assert_eq!(window.resolution.scale_factor(), SURFACE.scale_factor);
+        assert_eq!(
+            *app.world().resource::<HostLifecycle>(),
+            HostLifecycle::Background
+        );
+    }
+}
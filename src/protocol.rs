@@ -0,0 +1,402 @@
+//! Reserved messages used by the crate itself
+//!
+//! Crate features that the host drives at runtime (render settings, and so on) share the
+//! binary channel with the app. Their messages are framed with [`RESERVED_MAGIC`]
+//! followed by a little-endian `u16` topic and the payload:
+//!
+//! ```text
+//! 0xFF 'B' 'E' 'M' | topic: u16 LE | payload ...
+//! ```
+//!
+//! The leading `0xFF` never starts a UTF-8 (and so JSON or text) message, so app
+//! protocols do not collide with it. Reserved messages from the host are taken off the
+//! channel at the start of each frame and delivered as [`HostCommand`] messages;
+//! `HostChannel::receive` only yields app messages.
+
+use bevy::ecs::{
+    message::{Message, MessageWriter},
+    system::{Res, ResMut},
+};
+
+use crate::{
+    HostChannel,
+    replay::{ReplayEvent, ReplayRecorder},
+};
+
+/// Version of the reserved message layout, exchanged in the [handshake](crate::Handshake)
+///
+/// Incremented whenever a reserved payload changes incompatibly.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Prefix marking a reserved message
+pub const RESERVED_MAGIC: [u8; 4] = [0xFF, b'B', b'E', b'M'];
+
+/// Length of the reserved message header (magic and topic)
+pub const RESERVED_HEADER_LEN: usize = RESERVED_MAGIC.len() + 2;
+
+/// Topics of reserved messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum ReservedTopic {
+    /// Host → Bevy: change render quality settings
+    RenderSettings = 1,
+    /// Host → Bevy: add or remove scene annotations
+    Annotations = 2,
+    /// Host → Bevy: select or deselect entities
+    Selection = 3,
+    /// Bevy → Host: an entity was selected or deselected
+    SelectionChanged = 4,
+    /// Host → Bevy: adjust the orbit camera rig
+    CameraRig = 5,
+    /// Host → Bevy: load or unload a glTF model
+    LoadModel = 6,
+    /// Bevy → Host: a model finished loading
+    ModelLoaded = 7,
+    /// Host → Bevy: export entities as a GLB file
+    ExportScene = 8,
+    /// Bevy → Host: an exported GLB file
+    SceneExported = 9,
+    /// Host → Bevy: checkpoint, undo or redo world state
+    History = 10,
+    /// Bevy → Host: the undo history changed
+    HistoryChanged = 11,
+    /// Host → Bevy: pause, slow down or step virtual time
+    TimeControl = 12,
+    /// Bevy → Host: read or write a value in the host's key-value store
+    KeyValueRequest = 13,
+    /// Host → Bevy: stored values, requested or changed
+    KeyValue = 14,
+    /// Bevy → Host: present a file picker
+    PickFile = 15,
+    /// Host → Bevy: the file the user picked
+    FilePicked = 16,
+    /// Bevy → Host: save an image to photos or share it
+    SaveImage = 17,
+    /// Host → Bevy: outcome of an image export
+    ImageSaved = 18,
+    /// Host → Bevy: localized strings
+    Localization = 19,
+    /// Bevy → Host: the app's protocol version, schema and codecs
+    Handshake = 20,
+    /// Host → Bevy: the host's protocol version, schema and codecs
+    HostHandshake = 21,
+    /// Bevy → Host: lifecycle telemetry, queued process-wide
+    Telemetry = 22,
+    /// Host → Bevy: submit or cancel a long-running job
+    Job = 23,
+    /// Bevy → Host: the result of a job
+    JobResult = 24,
+    /// Host → Bevy: render a thumbnail of an asset or the scene
+    RenderThumbnail = 25,
+    /// Bevy → Host: a rendered thumbnail
+    Thumbnail = 26,
+    /// Bevy → Host: touch latency percentiles per stage
+    InputLatency = 27,
+    /// Host → Bevy: list, expand or edit entities for a debug inspector
+    Inspect = 28,
+    /// Bevy → Host: the answer to an inspect request
+    Inspection = 29,
+    /// Bevy → Host: perform or cancel an HTTP request
+    HttpRequest = 30,
+    /// Host → Bevy: part of a streamed HTTP response
+    HttpResponse = 31,
+    /// Bevy → Host: an asset failed to load
+    AssetLoadFailed = 32,
+    /// Host → Bevy: viewport rectangle and scaling of the content
+    Viewport = 33,
+    /// Host → Bevy: activate cameras and show render layers by name
+    HostViews = 34,
+    /// Host → Bevy: bind and set properties declared by the app
+    Properties = 35,
+    /// Bevy → Host: the kind of a property the host bound
+    PropertyBound = 36,
+    /// Host → Bevy: apply impulses, set gravity and query overlaps
+    Physics = 37,
+    /// Bevy → Host: the answer to an overlap query
+    PhysicsOverlaps = 38,
+    /// Bevy → Host: colliders that started or stopped touching
+    PhysicsContacts = 39,
+    /// Bevy → Host: show or hide the keyboard for a text field
+    TextInputRequest = 40,
+    /// Host → Bevy: edits to the focused text field
+    TextInput = 41,
+    /// Bevy → Host: speak an announcement with the screen reader
+    Announce = 42,
+    /// Bevy → Host: download a remote asset in the background
+    DownloadRequest = 43,
+    /// Host → Bevy: progress and outcome of asset downloads
+    Download = 44,
+    /// Bevy → Host: an error of the app and how it recovered from it
+    Recovery = 45,
+    /// Host → Bevy: replace the WGSL source of a shader, in development builds
+    ReloadShader = 46,
+    /// Bevy → Host: the pipelines recompiled for a reloaded shader and their errors
+    ShaderReloaded = 47,
+    /// Bevy → Host: the latest values of the streamed diagnostics
+    Diagnostics = 48,
+    /// Bevy → Host: the host updated the app again after stalling
+    UpdatesResumed = 49,
+    /// Host → Bevy: an event deriving `HostEvent`
+    HostEvent = 50,
+    /// Bevy → Host: an event deriving `HostEvent`
+    AppEvent = 51,
+}
+
+impl ReservedTopic {
+    /// Create a ReservedTopic from a u16
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            1 => Some(ReservedTopic::RenderSettings),
+            2 => Some(ReservedTopic::Annotations),
+            3 => Some(ReservedTopic::Selection),
+            4 => Some(ReservedTopic::SelectionChanged),
+            5 => Some(ReservedTopic::CameraRig),
+            6 => Some(ReservedTopic::LoadModel),
+            7 => Some(ReservedTopic::ModelLoaded),
+            8 => Some(ReservedTopic::ExportScene),
+            9 => Some(ReservedTopic::SceneExported),
+            10 => Some(ReservedTopic::History),
+            11 => Some(ReservedTopic::HistoryChanged),
+            12 => Some(ReservedTopic::TimeControl),
+            13 => Some(ReservedTopic::KeyValueRequest),
+            14 => Some(ReservedTopic::KeyValue),
+            15 => Some(ReservedTopic::PickFile),
+            16 => Some(ReservedTopic::FilePicked),
+            17 => Some(ReservedTopic::SaveImage),
+            18 => Some(ReservedTopic::ImageSaved),
+            19 => Some(ReservedTopic::Localization),
+            20 => Some(ReservedTopic::Handshake),
+            21 => Some(ReservedTopic::HostHandshake),
+            22 => Some(ReservedTopic::Telemetry),
+            23 => Some(ReservedTopic::Job),
+            24 => Some(ReservedTopic::JobResult),
+            25 => Some(ReservedTopic::RenderThumbnail),
+            26 => Some(ReservedTopic::Thumbnail),
+            27 => Some(ReservedTopic::InputLatency),
+            28 => Some(ReservedTopic::Inspect),
+            29 => Some(ReservedTopic::Inspection),
+            30 => Some(ReservedTopic::HttpRequest),
+            31 => Some(ReservedTopic::HttpResponse),
+            32 => Some(ReservedTopic::AssetLoadFailed),
+            33 => Some(ReservedTopic::Viewport),
+            34 => Some(ReservedTopic::HostViews),
+            35 => Some(ReservedTopic::Properties),
+            36 => Some(ReservedTopic::PropertyBound),
+            37 => Some(ReservedTopic::Physics),
+            38 => Some(ReservedTopic::PhysicsOverlaps),
+            39 => Some(ReservedTopic::PhysicsContacts),
+            40 => Some(ReservedTopic::TextInputRequest),
+            41 => Some(ReservedTopic::TextInput),
+            42 => Some(ReservedTopic::Announce),
+            43 => Some(ReservedTopic::DownloadRequest),
+            44 => Some(ReservedTopic::Download),
+            45 => Some(ReservedTopic::Recovery),
+            46 => Some(ReservedTopic::ReloadShader),
+            47 => Some(ReservedTopic::ShaderReloaded),
+            48 => Some(ReservedTopic::Diagnostics),
+            49 => Some(ReservedTopic::UpdatesResumed),
+            50 => Some(ReservedTopic::HostEvent),
+            51 => Some(ReservedTopic::AppEvent),
+            _ => None,
+        }
+    }
+}
+
+/// Frames a payload as a reserved message
+pub fn encode_reserved(topic: ReservedTopic, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(RESERVED_HEADER_LEN + payload.len());
+    message.extend_from_slice(&RESERVED_MAGIC);
+    message.extend_from_slice(&(topic as u16).to_le_bytes());
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Splits a reserved message into its raw topic and payload
+///
+/// Returns `None` for app messages.
+pub fn decode_reserved(message: &[u8]) -> Option<(u16, &[u8])> {
+    let rest = message.strip_prefix(&RESERVED_MAGIC)?;
+    let (topic, payload) = rest.split_first_chunk::<2>()?;
+    Some((u16::from_le_bytes(*topic), payload))
+}
+
+/// A reserved command sent by the host
+#[derive(Message, Debug, Clone, PartialEq, Eq)]
+pub struct HostCommand {
+    /// Topic the command was sent on
+    pub topic: ReservedTopic,
+    /// Topic-specific payload
+    pub payload: Vec<u8>,
+}
+
+/// Takes reserved messages off the host channel and delivers them as [`HostCommand`]s
+pub(crate) fn route_host_messages(
+    channel: Res<HostChannel>,
+    mut commands: MessageWriter<HostCommand>,
+    mut recorder: Option<ResMut<ReplayRecorder>>,
+) {
+    while let Some(message) = channel.receive_raw() {
+        if let Some(recorder) = &mut recorder {
+            recorder.push(ReplayEvent::Message(message.clone()));
+        }
+        let Some((topic, payload)) = decode_reserved(&message) else {
+            channel.queue_app_message(message);
+            continue;
+        };
+        match ReservedTopic::from_u16(topic) {
+            Some(topic) => {
+                commands.write(HostCommand {
+                    topic,
+                    payload: payload.to_vec(),
+                });
+            }
+            None => log::debug!("Dropping reserved message with unknown topic {topic}"),
+        }
+    }
+}
+
+/// Reads little-endian values from a payload
+///
+/// Every read returns `None` once the payload is exhausted.
+#[derive(Debug, Clone)]
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    /// Creates a reader over `bytes`
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Bytes not read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Returns true if everything has been read
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Reads `len` raw bytes
+    pub fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.bytes.len() {
+            return None;
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (array, rest) = self.bytes.split_first_chunk::<N>()?;
+        self.bytes = rest;
+        Some(*array)
+    }
+
+    /// Reads a `u8`
+    pub fn u8(&mut self) -> Option<u8> {
+        self.array::<1>().map(|[byte]| byte)
+    }
+
+    /// Reads a `bool` (any non-zero byte is true)
+    pub fn bool(&mut self) -> Option<bool> {
+        self.u8().map(|byte| byte != 0)
+    }
+
+    /// Reads a `u16`
+    pub fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    /// Reads a `u32`
+    pub fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    /// Reads a `u64`
+    pub fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Reads an `f32`
+    pub fn f32(&mut self) -> Option<f32> {
+        self.array().map(f32::from_le_bytes)
+    }
+
+    /// Reads an `f64`
+    pub fn f64(&mut self) -> Option<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    /// Reads a UTF-8 string prefixed with its `u32` length
+    pub fn string(&mut self) -> Option<&'a str> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.bytes(len)?).ok()
+    }
+}
+
+/// Writes little-endian values into a payload
+#[derive(Debug, Clone, Default)]
+pub struct ByteWriter {
+    bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+    /// Creates an empty writer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the written payload
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Writes raw bytes
+    pub fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    /// Writes a `u8`
+    pub fn u8(&mut self, value: u8) -> &mut Self {
+        self.bytes(&[value])
+    }
+
+    /// Writes a `bool` as one byte
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        self.u8(value as u8)
+    }
+
+    /// Writes a `u16`
+    pub fn u16(&mut self, value: u16) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Writes a `u32`
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Writes a `u64`
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Writes an `f32`
+    pub fn f32(&mut self, value: f32) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Writes an `f64`
+    pub fn f64(&mut self, value: f64) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Writes a UTF-8 string prefixed with its `u32` length
+    pub fn string(&mut self, value: &str) -> &mut Self {
+        self.u32(value.len() as u32).bytes(value.as_bytes())
+    }
+}
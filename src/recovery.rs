@@ -0,0 +1,504 @@
+//! Recovering from errors instead of failing the update
+//!
+//! The [`RecoveryPolicy`] of an app's creation config picks a [`RecoveryAction`] for each
+//! [`ErrorClass`]: keep updating, recreate the surface, freeze the frame or make
+//! `bevy_embedded_update` return non-zero so the host destroys the app. The generated FFI
+//! applies it to the errors and panics of every update:
+//!
+//! ```ignore
+//! fn config() -> EmbeddedConfig {
+//!     EmbeddedConfig {
+//!         recovery: RecoveryPolicy::default()
+//!             .with(ErrorClass::System, RecoveryAction::Continue),
+//!         ..default()
+//!     }
+//! }
+//! ```
+//!
+//! By default failed asset loads are ignored, render errors recreate the surface, errors
+//! returned by systems exit the app, and panics freeze the frame. Each error is sent to
+//! the host with the action taken on [`ReservedTopic::Recovery`]; failed asset loads only
+//! when the policy doesn't ignore them, as they are also reported on
+//! [`ReservedTopic::AssetLoadFailed`]:
+//!
+//! ```text
+//! class: u8 | action: u8 | error: string
+//! ```
+//!
+//! A panic unwinds out of the schedule it was running, which Bevy drops, so the app can't
+//! be updated again: [`RecoveryAction::Continue`] and [`RecoveryAction::RecreateSurface`]
+//! freeze the frame for panics. The crate runs the render schedule in a guard that keeps
+//! it, which is what lets render errors, such as a lost swapchain, continue with a new
+//! surface. A render error in more than [`RENDER_RETRIES`] frames in a row is handled
+//! like a panic. Apps built with `panic = "abort"` can't recover from either.
+//!
+//! A frozen app keeps its last frame, or a fallback image from the host, on screen; see
+//! [`frozen_frame`](crate::frozen_frame).
+
+use std::{
+    any::Any,
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    app::{App, AppExit, SubApp},
+    asset::UntypedAssetLoadFailedEvent,
+    ecs::{
+        entity::Entity,
+        message::MessageReader,
+        resource::Resource,
+        schedule::{ExecutorKind, Schedule, ScheduleLabel},
+        system::Res,
+        world::World,
+    },
+    render::Render,
+    window::RawHandleWrapper,
+};
+
+use crate::{
+    EmbeddedConfig, FallbackFrame, HostChannel, SurfaceEpoch,
+    frozen_frame::FrozenRender,
+    protocol::{ByteWriter, ReservedTopic},
+    surface::replace_window_surface,
+    telemetry,
+};
+
+/// Consecutive frames with a render error before it is handled like a panic
+pub const RENDER_RETRIES: u32 = 3;
+
+/// Where an error came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ErrorClass {
+    /// An asset failed to load
+    Asset = 0,
+    /// A system returned an error, or the crate reported one (e.g. a failed handshake)
+    System = 1,
+    /// The render world panicked, e.g. because the swapchain was lost
+    Render = 2,
+    /// Any other panic during the update
+    Panic = 3,
+}
+
+impl ErrorClass {
+    /// Create an ErrorClass from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ErrorClass::Asset),
+            1 => Some(ErrorClass::System),
+            2 => Some(ErrorClass::Render),
+            3 => Some(ErrorClass::Panic),
+            _ => None,
+        }
+    }
+}
+
+/// What the app does after an error
+///
+/// Ordered by severity: when an update has several errors, the most severe action wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum RecoveryAction {
+    /// Log the error and keep updating
+    Continue = 0,
+    /// Drop the render surfaces and swapchains and create them again next frame
+    RecreateSurface = 1,
+    /// Stop updating and keep the last frame on screen until the host destroys the app
+    FreezeFrame = 2,
+    /// Make `bevy_embedded_update` return non-zero, so the host destroys the app
+    Exit = 3,
+}
+
+impl RecoveryAction {
+    /// Create a RecoveryAction from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(RecoveryAction::Continue),
+            1 => Some(RecoveryAction::RecreateSurface),
+            2 => Some(RecoveryAction::FreezeFrame),
+            3 => Some(RecoveryAction::Exit),
+            _ => None,
+        }
+    }
+
+    /// The action for an app that can't be updated again
+    fn fatal(self) -> Self {
+        self.max(RecoveryAction::FreezeFrame)
+    }
+}
+
+/// What the app does after each [`ErrorClass`]; see the [module docs](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryPolicy {
+    /// After a failed asset load
+    pub asset: RecoveryAction,
+    /// After a system error
+    pub system: RecoveryAction,
+    /// After a render error
+    pub render: RecoveryAction,
+    /// After any other panic; `Continue` and `RecreateSurface` freeze the frame
+    pub panic: RecoveryAction,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            asset: RecoveryAction::Continue,
+            system: RecoveryAction::Exit,
+            render: RecoveryAction::RecreateSurface,
+            panic: RecoveryAction::FreezeFrame,
+        }
+    }
+}
+
+impl RecoveryPolicy {
+    /// The action for errors of `class`
+    pub fn action(&self, class: ErrorClass) -> RecoveryAction {
+        match class {
+            ErrorClass::Asset => self.asset,
+            ErrorClass::System => self.system,
+            ErrorClass::Render => self.render,
+            ErrorClass::Panic => self.panic.fatal(),
+        }
+    }
+
+    /// Sets the action for errors of `class`
+    pub fn with(mut self, class: ErrorClass, action: RecoveryAction) -> Self {
+        match class {
+            ErrorClass::Asset => self.asset = action,
+            ErrorClass::System => self.system = action,
+            ErrorClass::Render => self.render = action,
+            ErrorClass::Panic => self.panic = action,
+        }
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct RecoveryState {
+    /// Errors of the last updates, not yet handled
+    pending: Vec<(ErrorClass, String)>,
+    /// Frames in a row the render world failed
+    render_failures: u32,
+    /// The error that froze the app
+    frozen: Option<String>,
+    /// Whether the render world completed a frame
+    rendered: bool,
+    /// Whether the render world failed since the app froze, or right before
+    render_failed: bool,
+    /// What the app presents when frozen without a world it can render
+    fallback: Option<FallbackFrame>,
+    /// Incremented whenever the host sets the fallback
+    fallback_generation: u64,
+    /// Surface epoch and fallback generation of the last frame presented while frozen
+    presented: Option<(u64, u64)>,
+    /// Whether the render world presents the fallback instead of rendering
+    presenting_fallback: bool,
+}
+
+/// Errors of the app and how it recovered from them
+///
+/// Shared by the main and render worlds.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Recovery {
+    state: Arc<Mutex<RecoveryState>>,
+}
+
+impl Recovery {
+    /// The error that froze the app, if it is frozen
+    pub fn frozen(&self) -> Option<String> {
+        self.state.lock().ok()?.frozen.clone()
+    }
+
+    /// Returns true once an error froze the app
+    pub fn is_frozen(&self) -> bool {
+        self.state.lock().is_ok_and(|state| state.frozen.is_some())
+    }
+
+    /// Sets the image presented when the app freezes without a world it can render
+    pub(crate) fn set_fallback(&self, fallback: Option<FallbackFrame>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.fallback = fallback;
+            state.fallback_generation += 1;
+        }
+    }
+
+    /// The image presented when the app freezes without a world it can render
+    pub(crate) fn fallback(&self) -> Option<FallbackFrame> {
+        self.state.lock().ok()?.fallback.clone()
+    }
+
+    /// Returns true if the frozen app has something to present for the surface `epoch`
+    /// that it hasn't presented yet, and chooses between its world and the fallback
+    pub(crate) fn frozen_present_due(&self, epoch: u64) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let fallback = !state.rendered || state.render_failed;
+        if fallback && state.fallback.is_none() {
+            return false;
+        }
+        let presented = (
+            epoch,
+            if fallback {
+                state.fallback_generation
+            } else {
+                0
+            },
+        );
+        if state.presented == Some(presented) {
+            return false;
+        }
+        state.presented = Some(presented);
+        state.presenting_fallback = fallback;
+        true
+    }
+
+    /// Falls back to the fallback after rendering the frozen world failed
+    pub(crate) fn frozen_present_failed(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.render_failed = true;
+            state.presented = None;
+        }
+    }
+
+    fn record(&self, class: ErrorClass, error: String) {
+        if let Ok(mut state) = self.state.lock() {
+            if class == ErrorClass::Render {
+                state.render_failures += 1;
+                if state.frozen.is_some() {
+                    state.render_failed = true;
+                    state.presented = None;
+                }
+            }
+            state.pending.push((class, error));
+        }
+    }
+}
+
+/// Returns true once an error froze `app`
+pub(crate) fn is_frozen(app: &App) -> bool {
+    app.world()
+        .get_resource::<Recovery>()
+        .is_some_and(Recovery::is_frozen)
+}
+
+/// Updates the app, recording panics and system errors instead of unwinding
+///
+/// Returns false if the update panicked.
+pub(crate) fn update_guarded(app: &mut App) -> bool {
+    let result = catch_unwind(AssertUnwindSafe(|| app.update()));
+    let Some(recovery) = app.world().get_resource::<Recovery>() else {
+        return result.is_ok();
+    };
+    if let Err(payload) = result {
+        let error = panic_message(&*payload);
+        log::error!("App panicked: {error}");
+        recovery.record(ErrorClass::Panic, error);
+        return false;
+    }
+    if let Some(error) = crate::take_last_error() {
+        recovery.record(ErrorClass::System, error);
+    }
+    true
+}
+
+/// Applies the app's [`RecoveryPolicy`] to the errors of its last updates
+///
+/// Returns the code for `bevy_embedded_update`: 0 while the app keeps running or is
+/// frozen, and the exit code once the host should destroy it, with the error stored for
+/// `bevy_embedded_get_last_error`.
+pub(crate) fn recover(app: &mut App) -> u8 {
+    let recovery = app.world().get_resource::<Recovery>().cloned();
+    // Errors of a frozen app only change what it presents
+    if let Some(Ok(mut state)) = recovery.as_ref().map(|recovery| recovery.state.lock())
+        && state.frozen.is_some()
+    {
+        state.pending.clear();
+        return 0;
+    }
+    let (pending, render_failures) = recovery
+        .as_ref()
+        .and_then(|recovery| recovery.state.lock().ok())
+        .map(|mut state| (std::mem::take(&mut state.pending), state.render_failures))
+        .unwrap_or_default();
+    let policy = app
+        .world()
+        .get_resource::<EmbeddedConfig>()
+        .map(|config| config.recovery)
+        .unwrap_or_default();
+
+    let mut most_severe: Option<(RecoveryAction, String)> = None;
+    for (class, error) in pending {
+        let mut action = policy.action(class);
+        if class == ErrorClass::Render && render_failures > RENDER_RETRIES {
+            action = policy.panic.fatal();
+        }
+        log::warn!("Recovering from {class:?} error with {action:?}: {error}");
+        if class != ErrorClass::Asset {
+            telemetry::report_error(app, &error);
+        }
+        if let Some(channel) = app.world().get_resource::<HostChannel>() {
+            let mut payload = ByteWriter::new();
+            payload.u8(class as u8).u8(action as u8).string(&error);
+            channel.send_reserved(ReservedTopic::Recovery, &payload.finish());
+        }
+        if most_severe
+            .as_ref()
+            .is_none_or(|(severest, _)| action > *severest)
+        {
+            most_severe = Some((action, error));
+        }
+    }
+
+    // An app that exited with an error (e.g. its render thread crashed) isn't recovered
+    if let Some(AppExit::Error(code)) = app.should_exit() {
+        let error = match most_severe {
+            Some((_, error)) => error,
+            None => {
+                let error = format!("App exited with {:?}", AppExit::Error(code));
+                telemetry::report_error(app, &error);
+                error
+            }
+        };
+        log::error!("App exiting with error: {error}");
+        crate::store_error(error);
+        return code.get();
+    }
+
+    let Some((action, error)) = most_severe else {
+        return 0;
+    };
+    match action {
+        RecoveryAction::Continue => 0,
+        RecoveryAction::RecreateSurface => {
+            recreate_surfaces(app.world_mut());
+            0
+        }
+        RecoveryAction::FreezeFrame => {
+            let epoch = app
+                .world()
+                .get_resource::<SurfaceEpoch>()
+                .map_or(0, SurfaceEpoch::epoch);
+            if let Some(Ok(mut state)) = recovery.as_ref().map(|recovery| recovery.state.lock()) {
+                state.frozen = Some(error);
+                // The last frame stays on screen unless the render world failed it
+                state.render_failed = state.render_failures > 0;
+                state.presented = (state.rendered && !state.render_failed).then_some((epoch, 0));
+            }
+            0
+        }
+        RecoveryAction::Exit => {
+            crate::store_error(error);
+            1
+        }
+    }
+}
+
+/// Gives every window a new render surface for the native surface it already has
+fn recreate_surfaces(world: &mut World) {
+    let windows: Vec<(Entity, RawHandleWrapper)> = world
+        .query::<(Entity, &RawHandleWrapper)>()
+        .iter(world)
+        .map(|(window, handle)| (window, handle.clone()))
+        .collect();
+    for (window, handle) in windows {
+        replace_window_surface(world, window, handle);
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unknown panic".to_string()
+    }
+}
+
+/// Records failed asset loads the policy doesn't ignore
+pub(crate) fn record_asset_failures(
+    mut failures: MessageReader<UntypedAssetLoadFailedEvent>,
+    recovery: Res<Recovery>,
+    config: Res<EmbeddedConfig>,
+) {
+    if config.recovery.asset == RecoveryAction::Continue {
+        failures.clear();
+        return;
+    }
+    for failure in failures.read() {
+        let error = format!("Failed to load {}: {}", failure.path, failure.error);
+        recovery.record(ErrorClass::Asset, error);
+    }
+}
+
+/// Runs the render schedule of the render world in a guard
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct GuardedRender;
+
+/// Makes the render world record its panics instead of losing the render schedule
+pub(crate) fn add_render_systems(render_app: &mut SubApp, recovery: Recovery) {
+    render_app.update_schedule = Some(GuardedRender.intern());
+    render_app
+        .insert_resource(recovery)
+        .add_systems(GuardedRender, render_guarded);
+    crate::frozen_frame::add_render_systems(render_app);
+}
+
+fn render_guarded(world: &mut World) {
+    let presenting_fallback = world
+        .resource::<Recovery>()
+        .state
+        .lock()
+        .is_ok_and(|state| state.presenting_fallback);
+    let label = if presenting_fallback {
+        FrozenRender.intern()
+    } else {
+        Render.intern()
+    };
+    let result = world.try_schedule_scope(label, |world, schedule| {
+        let result = catch_unwind(AssertUnwindSafe(|| schedule.run(world)));
+        if result.is_err() {
+            reset_executor(schedule);
+        }
+        result
+    });
+    let Ok(result) = result else {
+        return;
+    };
+    if result.is_err()
+        && let Some(surface_epoch) = world.get_resource::<SurfaceEpoch>()
+    {
+        // The frame never reaches its cleanup, so don't let surface changes wait for it
+        surface_epoch.finish_frames();
+    }
+
+    let recovery = world.resource::<Recovery>();
+    match result {
+        Ok(()) => {
+            if let Ok(mut state) = recovery.state.lock()
+                && !presenting_fallback
+            {
+                state.render_failures = 0;
+                state.rendered = true;
+            }
+        }
+        Err(payload) => {
+            let error = panic_message(&*payload);
+            log::error!("Render world panicked: {error}");
+            recovery.record(ErrorClass::Render, error);
+        }
+    }
+}
+
+/// Replaces the executor a panic left mid-run
+fn reset_executor(schedule: &mut Schedule) {
+    let kind = schedule.get_executor_kind();
+    let other = match kind {
+        ExecutorKind::SingleThreaded => ExecutorKind::MultiThreaded,
+        _ => ExecutorKind::SingleThreaded,
+    };
+    schedule.set_executor_kind(other).set_executor_kind(kind);
+}
@@ -0,0 +1,182 @@
+//! Registry of live embedded app instances
+//!
+//! The host identifies an instance by the opaque app pointer it got at creation. That
+//! pointer is a handle, not the app's address: handles are never reused, while the
+//! allocator may place a new app at the address of a destroyed one. Every entry point
+//! resolves the handle here, so a call with the handle of a destroyed app fails cleanly
+//! instead of touching freed memory or another app. Entry points that may race with
+//! `bevy_embedded_destroy` on another thread (such as the message FFI) only use the
+//! registered [`Instance`] and never the app itself.
+
+use std::collections::BTreeMap;
+use std::sync::{
+    Arc, Mutex, PoisonError,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use bevy::app::App;
+
+use crate::{
+    EmbeddedConfig, Heartbeat, HostAudioInput, HostChannel, HostEndpoint, HostScene, HostState,
+    RingBuffer, RingChannels, SharedStateBlock, app_thread::AppThread,
+};
+
+/// Live instances keyed by handle
+static INSTANCES: Mutex<BTreeMap<usize, Arc<Instance>>> = Mutex::new(BTreeMap::new());
+
+/// The handle of the next registered app
+static NEXT_HANDLE: AtomicUsize = AtomicUsize::new(1);
+
+/// Per-instance state that outlives any single FFI call
+///
+/// Handed out as an `Arc`, so an in-flight call keeps what it needs alive even if the
+/// instance is destroyed concurrently.
+pub struct Instance {
+    /// Address of the app
+    app: usize,
+    endpoint: HostEndpoint,
+    shared_state: Option<Arc<SharedStateBlock>>,
+    ring_channels: RingChannels,
+    audio_input: Option<Arc<RingBuffer>>,
+    #[cfg(feature = "host_audio")]
+    audio_output: Option<Arc<crate::AudioRenderer>>,
+    heartbeat: Option<Heartbeat>,
+    scene: Option<u64>,
+    thread: AppThread,
+}
+
+impl Instance {
+    /// The host side of the instance's channel
+    pub fn endpoint(&self) -> &HostEndpoint {
+        &self.endpoint
+    }
+
+    /// The block the host writes scalar state into, kept alive until the instance is
+    /// unregistered
+    pub fn shared_state(&self) -> Option<&Arc<SharedStateBlock>> {
+        self.shared_state.as_ref()
+    }
+
+    /// Ring channels opened by the app, including ones opened after registration
+    pub fn ring_channels(&self) -> &RingChannels {
+        &self.ring_channels
+    }
+
+    /// The ring the host pushes captured audio into
+    pub fn audio_input(&self) -> Option<&Arc<RingBuffer>> {
+        self.audio_input.as_ref()
+    }
+
+    /// The mixed audio output the host pulls from, if `HostAudioPlugin` was added
+    #[cfg(feature = "host_audio")]
+    pub fn audio_output(&self) -> Option<&Arc<crate::AudioRenderer>> {
+        self.audio_output.as_ref()
+    }
+
+    /// When the host last updated the app, if it detects stalls
+    pub fn heartbeat(&self) -> Option<&Heartbeat> {
+        self.heartbeat.as_ref()
+    }
+
+    /// The id of the host scene the instance was created for
+    pub fn scene(&self) -> Option<u64> {
+        self.scene
+    }
+
+    /// The thread the app runs on
+    pub(crate) fn thread(&self) -> &AppThread {
+        &self.thread
+    }
+}
+
+fn key<T>(handle: *const T) -> usize {
+    handle as usize
+}
+
+/// Registers a newly created app, returning the handle the host holds for it
+///
+/// Must be called once the app is at its final address (i.e. after boxing). The handle
+/// is typed as an app pointer for the FFI signatures, but is never dereferenced.
+pub fn register(app: &App) -> *mut App {
+    let endpoint = match app.world().get_resource::<HostChannel>() {
+        Some(channel) => channel.endpoint(),
+        None => {
+            log::warn!("Registering an app without a HostChannel");
+            HostChannel::default().endpoint()
+        }
+    };
+    let instance = Arc::new(Instance {
+        app: app as *const App as usize,
+        endpoint,
+        shared_state: app
+            .world()
+            .get_resource::<HostState>()
+            .map(|state| state.block().clone()),
+        ring_channels: app
+            .world()
+            .get_resource::<RingChannels>()
+            .cloned()
+            .unwrap_or_default(),
+        audio_input: app
+            .world()
+            .get_resource::<HostAudioInput>()
+            .map(|input| input.ring().clone()),
+        #[cfg(feature = "host_audio")]
+        audio_output: app
+            .world()
+            .get_resource::<crate::HostAudioOutput>()
+            .map(|output| output.renderer().clone()),
+        heartbeat: app.world().get_resource::<Heartbeat>().cloned(),
+        scene: app.world().get_resource::<HostScene>().map(HostScene::id),
+        thread: AppThread::new(
+            app.world()
+                .get_resource::<EmbeddedConfig>()
+                .map(|config| config.thread_policy)
+                .unwrap_or_default(),
+        ),
+    });
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    INSTANCES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(handle, instance);
+    handle as *mut App
+}
+
+/// Removes an app from the registry; call before the app is dropped
+///
+/// Returns true if the handle was registered.
+pub fn unregister<T>(handle: *const T) -> bool {
+    INSTANCES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(&key(handle))
+        .is_some()
+}
+
+/// Finds the app created for a host scene, as the handle the host holds
+pub fn scene_app(scene: u64) -> Option<usize> {
+    INSTANCES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|(_, instance)| instance.scene == Some(scene))
+        .map(|(&handle, _)| handle)
+}
+
+/// Looks up a live instance by the handle the host holds
+pub fn get<T>(handle: *const T) -> Option<Arc<Instance>> {
+    INSTANCES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&key(handle))
+        .cloned()
+}
+
+/// The address of a live app, or `None` if the handle is unknown or was destroyed
+///
+/// Dereferencing the address has the same requirements as dereferencing the app pointer
+/// did: the app must not be destroyed or aliased meanwhile.
+pub fn resolve<T>(handle: *const T) -> Option<*mut App> {
+    get(handle).map(|instance| instance.app as *mut App)
+}
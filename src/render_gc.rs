@@ -0,0 +1,119 @@
+//! Release of GPU memory in long-lived host apps
+//!
+//! Bevy drops the GPU copy of an asset once its last handle is gone, but wgpu only frees
+//! the memory during device maintenance, which otherwise runs when frames are submitted.
+//! A widget that stops rendering while hidden keeps the memory of everything dropped
+//! before, and the cache of per-frame textures holds on to render targets for a few
+//! frames after their last use. Host apps live for hours, so [`RenderGcPlugin`] reclaims
+//! that memory:
+//!
+//! - Every [`RenderGc::interval`] frames the render world polls the device without
+//!   waiting, releasing resources whose GPU work has completed. A sweep never blocks a
+//!   frame.
+//! - A flush (`bevy_embedded_ios_flush_render_resources`, `nativeFlushRenderResources`)
+//!   empties the texture cache and waits for the GPU, so the memory of every dropped
+//!   resource is released before it returns. Hosts call it on memory warnings, and with
+//!   [`RenderGc::flush_in_background`] the app flushes itself when the host moves to the
+//!   background.
+//!
+//! Add the plugin after `DefaultPlugins`, so the render world exists:
+//!
+//! ```ignore
+//! app.add_plugins((DefaultPlugins.build().disable::<WinitPlugin>(), EmbeddedPlugin))
+//!     .add_plugins(RenderGcPlugin);
+//! ```
+
+use bevy::{
+    app::{App, Plugin},
+    ecs::{
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Local, Res},
+    },
+    render::{
+        Render, RenderApp, RenderSystems,
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_resource::PollType,
+        renderer::RenderDevice,
+        texture::{TextureCache, update_texture_cache_system},
+    },
+};
+
+/// Collects GPU memory of dropped render resources; see the [module docs](self)
+#[derive(Default)]
+pub struct RenderGcPlugin;
+
+impl Plugin for RenderGcPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RenderGc>()
+            .add_plugins(ExtractResourcePlugin::<RenderGc>::default());
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(
+                Render,
+                collect_render_garbage
+                    .in_set(RenderSystems::Cleanup)
+                    .after(update_texture_cache_system),
+            );
+        }
+    }
+}
+
+/// How the render world collects GPU memory
+#[derive(Resource, ExtractResource, Debug, Clone, PartialEq)]
+pub struct RenderGc {
+    /// Frames between sweeps, or 0 to only collect when flushed
+    pub interval: u32,
+    /// Whether to flush when the host moves to the background
+    pub flush_in_background: bool,
+}
+
+impl Default for RenderGc {
+    fn default() -> Self {
+        Self {
+            interval: 60,
+            flush_in_background: true,
+        }
+    }
+}
+
+/// Releases the memory of resources whose GPU work completed, every few frames
+fn collect_render_garbage(
+    gc: Res<RenderGc>,
+    render_device: Res<RenderDevice>,
+    mut frames: Local<u32>,
+) {
+    if gc.interval == 0 {
+        return;
+    }
+    *frames += 1;
+    if *frames < gc.interval {
+        return;
+    }
+    *frames = 0;
+    if let Err(error) = render_device.poll(PollType::Poll) {
+        log::warn!("Render resource sweep failed: {error}");
+    }
+}
+
+/// Empties the texture cache and waits for the GPU to release dropped resources
+///
+/// Returns false if the app has no render device. Must be called between updates.
+pub(crate) fn flush_render_resources(app: &mut App) -> bool {
+    let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+        return false;
+    };
+    let world = render_app.world_mut();
+    if let Some(mut texture_cache) = world.get_resource_mut::<TextureCache>() {
+        *texture_cache = TextureCache::default();
+    }
+    let Some(render_device) = world.get_resource::<RenderDevice>() else {
+        return false;
+    };
+    if let Err(error) = render_device.poll(PollType::Wait) {
+        log::warn!("Render resource flush failed: {error}");
+        return false;
+    }
+    log::debug!("Flushed render resources");
+    true
+}
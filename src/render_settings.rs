@@ -0,0 +1,231 @@
+//! Render quality settings controlled by the host
+//!
+//! Hosts toggle shadows, MSAA, bloom and ambient occlusion with
+//! [`ReservedTopic::RenderSettings`] commands, e.g. from a quality option in their native
+//! preferences. Each command carries `(setting: u8, value: u8)` pairs, see
+//! [`RenderSetting`]. The result is kept in [`RenderSettings`]; settings the host never
+//! touched stay `None` and are left to the app.
+//!
+//! MSAA is applied to every camera; turn it off when enabling ambient occlusion, which
+//! Bevy does not support together with MSAA. Shadows, bloom and ambient occlusion are applied
+//! to lights and cameras with the `render_settings` cargo feature, which pulls in
+//! `bevy_pbr` and `bevy_post_process`; without it apps can react to the resource
+//! themselves.
+//!
+//! The commands are handled once [`RenderSettingsPlugin`] is added.
+
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate},
+    camera::Camera,
+    ecs::{
+        change_detection::{DetectChanges, Ref},
+        entity::Entity,
+        message::MessageReader,
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut},
+    },
+    render::view::Msaa,
+};
+
+use crate::protocol::{ByteReader, HostCommand, ReservedTopic};
+
+/// Applies render settings commands from the host; see the [module docs](self)
+#[derive(Default)]
+pub struct RenderSettingsPlugin;
+
+impl Plugin for RenderSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RenderSettings>()
+            .add_systems(PreUpdate, receive_render_settings)
+            .add_systems(PostUpdate, apply_msaa);
+
+        #[cfg(feature = "render_settings")]
+        app.add_systems(
+            PostUpdate,
+            (apply_shadows, apply_bloom, apply_ambient_occlusion),
+        );
+    }
+}
+
+/// Settings that can appear in a render settings command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RenderSetting {
+    /// Shadow casting for all lights (value: 0 or 1)
+    Shadows = 0,
+    /// MSAA sample count (value: 1, 2, 4 or 8)
+    Msaa = 1,
+    /// Bloom on cameras (value: 0 or 1)
+    Bloom = 2,
+    /// Screen space ambient occlusion on 3D cameras (value: 0 or 1)
+    AmbientOcclusion = 3,
+}
+
+impl RenderSetting {
+    /// Create a RenderSetting from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(RenderSetting::Shadows),
+            1 => Some(RenderSetting::Msaa),
+            2 => Some(RenderSetting::Bloom),
+            3 => Some(RenderSetting::AmbientOcclusion),
+            _ => None,
+        }
+    }
+}
+
+/// Render settings requested by the host
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderSettings {
+    /// Whether lights cast shadows
+    pub shadows: Option<bool>,
+    /// MSAA sample count
+    pub msaa_samples: Option<u8>,
+    /// Whether cameras render bloom
+    pub bloom: Option<bool>,
+    /// Whether 3D cameras render screen space ambient occlusion
+    pub ambient_occlusion: Option<bool>,
+}
+
+/// Applies render settings commands from the host
+fn receive_render_settings(
+    mut commands: MessageReader<HostCommand>,
+    mut settings: ResMut<RenderSettings>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::RenderSettings {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while let (Some(setting), Some(value)) = (reader.u8(), reader.u8()) {
+            match RenderSetting::from_u8(setting) {
+                Some(RenderSetting::Shadows) => settings.shadows = Some(value != 0),
+                Some(RenderSetting::Msaa) if matches!(value, 1 | 2 | 4 | 8) => {
+                    settings.msaa_samples = Some(value)
+                }
+                Some(RenderSetting::Bloom) => settings.bloom = Some(value != 0),
+                Some(RenderSetting::AmbientOcclusion) => {
+                    settings.ambient_occlusion = Some(value != 0)
+                }
+                _ => log::warn!("Ignoring invalid render setting {setting}={value}"),
+            }
+        }
+    }
+}
+
+/// Sets the MSAA sample count on cameras
+fn apply_msaa(
+    mut commands: Commands,
+    settings: Res<RenderSettings>,
+    cameras: Query<(Entity, Ref<Camera>)>,
+) {
+    let Some(samples) = settings.msaa_samples else {
+        return;
+    };
+    let msaa = match samples {
+        2 => Msaa::Sample2,
+        4 => Msaa::Sample4,
+        8 => Msaa::Sample8,
+        _ => Msaa::Off,
+    };
+
+    for (entity, camera) in &cameras {
+        if settings.is_changed() || camera.is_added() {
+            commands.entity(entity).insert(msaa);
+        }
+    }
+}
+
+#[cfg(feature = "render_settings")]
+use pbr::*;
+
+#[cfg(feature = "render_settings")]
+mod pbr {
+    use bevy::{
+        camera::{Camera, Camera3d},
+        ecs::{
+            change_detection::{DetectChanges, Ref},
+            entity::Entity,
+            system::{Commands, Query, Res},
+        },
+        light::{DirectionalLight, PointLight, SpotLight},
+        pbr::ScreenSpaceAmbientOcclusion,
+        post_process::bloom::Bloom,
+    };
+
+    use super::RenderSettings;
+
+    /// Enables or disables shadows on all lights
+    pub(crate) fn apply_shadows(
+        settings: Res<RenderSettings>,
+        mut directional: Query<&mut DirectionalLight>,
+        mut point: Query<&mut PointLight>,
+        mut spot: Query<&mut SpotLight>,
+    ) {
+        let Some(shadows) = settings.shadows else {
+            return;
+        };
+        let changed = settings.is_changed();
+
+        for mut light in &mut directional {
+            if (changed || light.is_added()) && light.shadows_enabled != shadows {
+                light.shadows_enabled = shadows;
+            }
+        }
+        for mut light in &mut point {
+            if (changed || light.is_added()) && light.shadows_enabled != shadows {
+                light.shadows_enabled = shadows;
+            }
+        }
+        for mut light in &mut spot {
+            if (changed || light.is_added()) && light.shadows_enabled != shadows {
+                light.shadows_enabled = shadows;
+            }
+        }
+    }
+
+    /// Adds or removes bloom on cameras
+    pub(crate) fn apply_bloom(
+        mut commands: Commands,
+        settings: Res<RenderSettings>,
+        cameras: Query<(Entity, Ref<Camera>)>,
+    ) {
+        let Some(bloom) = settings.bloom else {
+            return;
+        };
+        for (entity, camera) in &cameras {
+            if settings.is_changed() || camera.is_added() {
+                if bloom {
+                    commands.entity(entity).insert(Bloom::NATURAL);
+                } else {
+                    commands.entity(entity).remove::<Bloom>();
+                }
+            }
+        }
+    }
+
+    /// Adds or removes screen space ambient occlusion on 3D cameras
+    pub(crate) fn apply_ambient_occlusion(
+        mut commands: Commands,
+        settings: Res<RenderSettings>,
+        cameras: Query<(Entity, Ref<Camera3d>)>,
+    ) {
+        let Some(ambient_occlusion) = settings.ambient_occlusion else {
+            return;
+        };
+        for (entity, camera) in &cameras {
+            if settings.is_changed() || camera.is_added() {
+                if ambient_occlusion {
+                    commands
+                        .entity(entity)
+                        .insert(ScreenSpaceAmbientOcclusion::default());
+                } else {
+                    commands
+                        .entity(entity)
+                        .remove::<ScreenSpaceAmbientOcclusion>();
+                }
+            }
+        }
+    }
+}
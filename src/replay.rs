@@ -0,0 +1,599 @@
+//! Recording host input and replaying it into a fresh app
+//!
+//! Bugs reported against embedded widgets often depend on one device's touch timing,
+//! surface sizes or message order. While recording, every input the host sends through
+//! the FFI is appended to a [`ReplayLog`] along with the frame it arrived in, and each
+//! host frame records how much time passed. [`play`] feeds a log into a new instance of
+//! the same app, advancing time by the recorded amounts, so it renders the same frames.
+//!
+//! Hosts start and stop recording with `bevy_embedded_ios_start_recording` /
+//! `bevy_embedded_ios_stop_recording` (`nativeStartRecording` / `nativeStopRecording`),
+//! attach the log to the bug report, and replay it with `bevy_embedded_ios_replay` or
+//! [`MockHost::replay`](crate::test::MockHost::replay) on a desktop. The log is compact
+//! binary:
+//!
+//! ```text
+//! magic: "BEMR" | version: u16 | events ...
+//! Frame:          0 | delta ns: u64
+//! Touch:          1 | phase: u8 | x: f32 | y: f32 | id: u64 | contact: u8 (+ 4 × f32)
+//! PredictedTouch: 2 | x: f32 | y: f32 | id: u64
+//! Rotary:         3 | delta: f32
+//! Key:            4 | HID usage: u32 | pressed: u8 | repeat: u8 | modifiers: u8 |
+//!                     text: u8 (+ u32 len + UTF-8)
+//! KeyboardFocus:  5 | focused: u8
+//! Resize:         6 | width: u32 | height: u32 | scale factor: f32
+//! Lifecycle:      7 | state: u8
+//! Message:        8 | u32 len + bytes
+//! ```
+//!
+//! Events before a `Frame` are applied before that frame updates. Only whole host frames
+//! are recorded, so apps driven by [sub-steps](crate::HostSchedule) don't replay.
+//! Ring channels, shared state, audio and surface replacements are not recorded, and keys
+//! without a USB HID usage replay as unidentified. Messages are recorded as Bevy received
+//! them, after [decryption](crate::HostChannel::is_encrypted), so replay into an app
+//! without a channel key.
+
+use std::time::Duration;
+
+use bevy::{
+    app::App,
+    ecs::{resource::Resource, world::World},
+    input::keyboard::KeyCode,
+    math::Vec2,
+    time::{Real, Time, TimeUpdateStrategy},
+};
+
+use crate::{
+    HostChannel, HostLifecycle, KeyModifiers, TouchContact, TouchPhase, ffi,
+    key_code_from_hid_usage,
+    protocol::{ByteReader, ByteWriter},
+};
+
+/// Magic bytes at the start of an encoded log
+const LOG_MAGIC: &[u8; 4] = b"BEMR";
+
+/// Version of the encoded log layout
+const LOG_VERSION: u16 = 1;
+
+/// Highest USB HID keyboard usage with a key code
+const MAX_HID_USAGE: u32 = 0xE7;
+
+/// One input from the host, or the end of a host frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayEvent {
+    /// The host ran a frame, this long after the previous one
+    Frame(Duration),
+    /// A touch event, with contact data if the host sent it
+    Touch {
+        /// Phase of the touch
+        phase: TouchPhase,
+        /// Position in host coordinates
+        position: Vec2,
+        /// Touch id
+        id: u64,
+        /// Contact data such as pressure
+        contact: Option<TouchContact>,
+    },
+    /// A predicted touch sample
+    PredictedTouch {
+        /// Position in host coordinates
+        position: Vec2,
+        /// Id of the predicted touch
+        id: u64,
+    },
+    /// Rotary input scroll distance
+    Rotary(f32),
+    /// A hardware key press or release
+    Key {
+        /// The key
+        key_code: KeyCode,
+        /// Whether it was pressed or released
+        pressed: bool,
+        /// Whether it is an auto-repeat
+        repeat: bool,
+        /// Modifiers held
+        modifiers: KeyModifiers,
+        /// Text the key produced
+        text: Option<String>,
+    },
+    /// The widget gained or lost keyboard focus
+    KeyboardFocus(bool),
+    /// The host surface was resized
+    Resize {
+        /// Physical width
+        width: u32,
+        /// Physical height
+        height: u32,
+        /// Scale factor
+        scale_factor: f32,
+    },
+    /// The host lifecycle state changed
+    Lifecycle(HostLifecycle),
+    /// A message the app received from the host
+    Message(Vec<u8>),
+}
+
+/// Recorded host input, in the order it arrived
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayLog {
+    /// Events, each frame's input followed by its `Frame`
+    pub events: Vec<ReplayEvent>,
+}
+
+impl ReplayLog {
+    /// Number of recorded frames
+    pub fn frames(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|event| matches!(event, ReplayEvent::Frame(_)))
+            .count()
+    }
+
+    /// Encodes the log in its compact binary form
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+        writer.bytes(LOG_MAGIC).u16(LOG_VERSION);
+        for event in &self.events {
+            write_event(&mut writer, event);
+        }
+        writer.finish()
+    }
+
+    /// Decodes a log, returning `None` if it is malformed or from another version
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.bytes(LOG_MAGIC.len())? != LOG_MAGIC || reader.u16()? != LOG_VERSION {
+            return None;
+        }
+        let mut events = Vec::new();
+        while !reader.is_empty() {
+            events.push(read_event(&mut reader)?);
+        }
+        Some(Self { events })
+    }
+}
+
+fn write_event(writer: &mut ByteWriter, event: &ReplayEvent) {
+    match event {
+        ReplayEvent::Frame(delta) => {
+            writer.u8(0).u64(delta.as_nanos() as u64);
+        }
+        ReplayEvent::Touch {
+            phase,
+            position,
+            id,
+            contact,
+        } => {
+            writer
+                .u8(1)
+                .u8(*phase as u8)
+                .f32(position.x)
+                .f32(position.y)
+                .u64(*id)
+                .bool(contact.is_some());
+            if let Some(contact) = contact {
+                writer
+                    .f32(contact.pressure)
+                    .f32(contact.touch_major)
+                    .f32(contact.touch_minor)
+                    .f32(contact.orientation);
+            }
+        }
+        ReplayEvent::PredictedTouch { position, id } => {
+            writer.u8(2).f32(position.x).f32(position.y).u64(*id);
+        }
+        ReplayEvent::Rotary(delta) => {
+            writer.u8(3).f32(*delta);
+        }
+        ReplayEvent::Key {
+            key_code,
+            pressed,
+            repeat,
+            modifiers,
+            text,
+        } => {
+            writer
+                .u8(4)
+                .u32(hid_usage(*key_code))
+                .bool(*pressed)
+                .bool(*repeat)
+                .u8(modifiers.bits())
+                .bool(text.is_some());
+            if let Some(text) = text {
+                writer.string(text);
+            }
+        }
+        ReplayEvent::KeyboardFocus(focused) => {
+            writer.u8(5).bool(*focused);
+        }
+        ReplayEvent::Resize {
+            width,
+            height,
+            scale_factor,
+        } => {
+            writer.u8(6).u32(*width).u32(*height).f32(*scale_factor);
+        }
+        ReplayEvent::Lifecycle(state) => {
+            writer.u8(7).u8(*state as u8);
+        }
+        ReplayEvent::Message(message) => {
+            writer.u8(8).u32(message.len() as u32).bytes(message);
+        }
+    }
+}
+
+fn read_event(reader: &mut ByteReader) -> Option<ReplayEvent> {
+    let event = match reader.u8()? {
+        0 => ReplayEvent::Frame(Duration::from_nanos(reader.u64()?)),
+        1 => ReplayEvent::Touch {
+            phase: TouchPhase::from_u8(reader.u8()?)?,
+            position: Vec2::new(reader.f32()?, reader.f32()?),
+            id: reader.u64()?,
+            contact: match reader.bool()? {
+                true => Some(TouchContact {
+                    pressure: reader.f32()?,
+                    touch_major: reader.f32()?,
+                    touch_minor: reader.f32()?,
+                    orientation: reader.f32()?,
+                }),
+                false => None,
+            },
+        },
+        2 => ReplayEvent::PredictedTouch {
+            position: Vec2::new(reader.f32()?, reader.f32()?),
+            id: reader.u64()?,
+        },
+        3 => ReplayEvent::Rotary(reader.f32()?),
+        4 => ReplayEvent::Key {
+            key_code: key_code_from_hid_usage(reader.u32()?),
+            pressed: reader.bool()?,
+            repeat: reader.bool()?,
+            modifiers: KeyModifiers::from_bits(reader.u8()?),
+            text: match reader.bool()? {
+                true => Some(reader.string()?.to_string()),
+                false => None,
+            },
+        },
+        5 => ReplayEvent::KeyboardFocus(reader.bool()?),
+        6 => ReplayEvent::Resize {
+            width: reader.u32()?,
+            height: reader.u32()?,
+            scale_factor: reader.f32()?,
+        },
+        7 => ReplayEvent::Lifecycle(HostLifecycle::from_u8(reader.u8()?)?),
+        8 => {
+            let len = reader.u32()? as usize;
+            ReplayEvent::Message(reader.bytes(len)?.to_vec())
+        }
+        _ => return None,
+    };
+    Some(event)
+}
+
+/// The USB HID usage of `key_code`, or 0 if it has none
+fn hid_usage(key_code: KeyCode) -> u32 {
+    (0..=MAX_HID_USAGE)
+        .find(|&usage| key_code_from_hid_usage(usage) == key_code)
+        .unwrap_or(0)
+}
+
+/// Input recorded so far; present while the app is recording
+#[derive(Resource, Debug, Default)]
+pub(crate) struct ReplayRecorder {
+    log: ReplayLog,
+}
+
+impl ReplayRecorder {
+    pub(crate) fn push(&mut self, event: ReplayEvent) {
+        self.log.events.push(event);
+    }
+}
+
+/// Starts recording host input into a new log, discarding one in progress
+pub fn start_recording(app: &mut App) {
+    app.insert_resource(ReplayRecorder::default());
+}
+
+/// Stops recording and returns the log, or `None` if the app wasn't recording
+pub fn stop_recording(app: &mut App) -> Option<ReplayLog> {
+    app.world_mut()
+        .remove_resource::<ReplayRecorder>()
+        .map(|recorder| recorder.log)
+}
+
+/// Returns true while the app is recording
+pub fn is_recording(app: &App) -> bool {
+    app.world().contains_resource::<ReplayRecorder>()
+}
+
+/// The log recorded so far, or `None` if the app isn't recording
+pub fn recorded(app: &App) -> Option<&ReplayLog> {
+    app.world()
+        .get_resource::<ReplayRecorder>()
+        .map(|recorder| &recorder.log)
+}
+
+/// Appends an event to the log if the app is recording
+pub(crate) fn record(world: &mut World, event: impl FnOnce() -> ReplayEvent) {
+    if let Some(mut recorder) = world.get_resource_mut::<ReplayRecorder>() {
+        recorder.push(event());
+    }
+}
+
+/// Ends a recorded frame with the real time it advanced by
+pub(crate) fn record_frame(world: &mut World) {
+    if !world.contains_resource::<ReplayRecorder>() {
+        return;
+    }
+    let delta = world
+        .get_resource::<Time<Real>>()
+        .map_or(Duration::ZERO, Time::delta);
+    record(world, || ReplayEvent::Frame(delta));
+}
+
+/// Feeds `log` into `app`, running one update per recorded frame
+///
+/// Time advances by the recorded frame times rather than the wall clock while the log
+/// plays. Input after the last frame is left queued for the next update. Returns the
+/// number of frames run.
+pub fn play(app: &mut App, log: &ReplayLog) -> usize {
+    let strategy = app.world_mut().remove_resource::<TimeUpdateStrategy>();
+    let mut frames = 0;
+    for event in &log.events {
+        match event {
+            ReplayEvent::Frame(delta) => {
+                app.insert_resource(TimeUpdateStrategy::ManualDuration(*delta));
+                app.update();
+                frames += 1;
+            }
+            ReplayEvent::Touch {
+                phase,
+                position,
+                id,
+                contact: Some(contact),
+            } => {
+                let _ = ffi::touch_event_with_contact(
+                    app,
+                    *phase as u8,
+                    position.x,
+                    position.y,
+                    *id,
+                    *contact,
+                );
+            }
+            ReplayEvent::Touch {
+                phase,
+                position,
+                id,
+                contact: None,
+            } => {
+                let _ = ffi::touch_event(app, *phase as u8, position.x, position.y, *id);
+            }
+            ReplayEvent::PredictedTouch { position, id } => {
+                let _ = ffi::predicted_touch(app, position.x, position.y, *id);
+            }
+            ReplayEvent::Rotary(delta) => {
+                let _ = ffi::rotary_event(app, *delta);
+            }
+            ReplayEvent::Key {
+                key_code,
+                pressed,
+                repeat,
+                modifiers,
+                text,
+            } => {
+                ffi::key_event(
+                    app,
+                    *key_code,
+                    *pressed,
+                    *repeat,
+                    *modifiers,
+                    text.as_deref(),
+                );
+            }
+            ReplayEvent::KeyboardFocus(focused) => ffi::keyboard_focus(app, *focused),
+            ReplayEvent::Resize {
+                width,
+                height,
+                scale_factor,
+            } => {
+                let _ = ffi::resize(app, *width, *height, *scale_factor);
+            }
+            ReplayEvent::Lifecycle(state) => {
+                let _ = ffi::set_lifecycle(app, *state as u8);
+            }
+            ReplayEvent::Message(message) => {
+                app.world()
+                    .resource::<HostChannel>()
+                    .endpoint()
+                    .send(message.clone());
+            }
+        }
+    }
+    if let Some(strategy) = strategy {
+        app.insert_resource(strategy);
+    } else {
+        app.world_mut().remove_resource::<TimeUpdateStrategy>();
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        MinimalPlugins,
+        app::Update,
+        ecs::{
+            message::MessageReader,
+            system::{Res, ResMut},
+        },
+        input::touch::TouchInput,
+    };
+
+    use super::*;
+    use crate::test::{MockHost, MockSurface};
+
+    fn every_event() -> ReplayLog {
+        ReplayLog {
+            events: vec![
+                ReplayEvent::Touch {
+                    phase: TouchPhase::Started,
+                    position: Vec2::new(1.5, -2.0),
+                    id: u64::MAX,
+                    contact: Some(TouchContact {
+                        pressure: 0.5,
+                        touch_major: 10.0,
+                        touch_minor: 8.0,
+                        orientation: 1.25,
+                    }),
+                },
+                ReplayEvent::Touch {
+                    phase: TouchPhase::Cancelled,
+                    position: Vec2::ZERO,
+                    id: 3,
+                    contact: None,
+                },
+                ReplayEvent::PredictedTouch {
+                    position: Vec2::new(4.0, 5.0),
+                    id: 3,
+                },
+                ReplayEvent::Rotary(-0.75),
+                ReplayEvent::Frame(Duration::from_nanos(16_666_667)),
+                ReplayEvent::Key {
+                    key_code: KeyCode::KeyA,
+                    pressed: true,
+                    repeat: false,
+                    modifiers: KeyModifiers::from_bits(
+                        KeyModifiers::SHIFT.bits() | KeyModifiers::ALT.bits(),
+                    ),
+                    text: Some("Å".to_string()),
+                },
+                ReplayEvent::Key {
+                    key_code: KeyCode::Enter,
+                    pressed: false,
+                    repeat: true,
+                    modifiers: KeyModifiers::NONE,
+                    text: None,
+                },
+                ReplayEvent::KeyboardFocus(true),
+                ReplayEvent::Resize {
+                    width: 1170,
+                    height: 2532,
+                    scale_factor: 3.0,
+                },
+                ReplayEvent::Lifecycle(HostLifecycle::Background),
+                ReplayEvent::Message(b"hello".to_vec()),
+                ReplayEvent::Message(Vec::new()),
+                ReplayEvent::Frame(Duration::ZERO),
+            ],
+        }
+    }
+
+    #[test]
+    fn logs_round_trip() {
+        let log = every_event();
+        let encoded = log.encode();
+        assert_eq!(ReplayLog::decode(&encoded), Some(log));
+        assert_eq!(
+            ReplayLog::decode(&ReplayLog::default().encode()),
+            Some(ReplayLog::default())
+        );
+    }
+
+    #[test]
+    fn malformed_logs_are_rejected() {
+        let log = ReplayLog {
+            events: vec![ReplayEvent::Key {
+                key_code: KeyCode::KeyA,
+                pressed: true,
+                repeat: false,
+                modifiers: KeyModifiers::NONE,
+                text: Some("a".to_string()),
+            }],
+        };
+        let encoded = log.encode();
+        let header = ReplayLog::default().encode();
+        // Every truncation but the bare header falls in the header or the only event
+        for len in (0..encoded.len()).filter(|&len| len != header.len()) {
+            assert_eq!(ReplayLog::decode(&encoded[..len]), None, "{len} bytes");
+        }
+
+        let with = |bytes: &[u8]| [&header[..], bytes].concat();
+        // Unknown event
+        assert_eq!(ReplayLog::decode(&with(&[9])), None);
+        // Unknown touch phase
+        assert_eq!(ReplayLog::decode(&with(&[1, 4])), None);
+        // Unknown lifecycle state
+        assert_eq!(ReplayLog::decode(&with(&[7, 0xFF])), None);
+        // Message longer than the log
+        assert_eq!(ReplayLog::decode(&with(&[8, 9, 0, 0, 0, 1])), None);
+
+        let mut other_version = log.encode();
+        other_version[4] = other_version[4].wrapping_add(1);
+        assert_eq!(ReplayLog::decode(&other_version), None);
+        assert_eq!(ReplayLog::decode(b"not a replay log"), None);
+    }
+
+    /// What the app saw in a frame
+    #[derive(Debug, PartialEq)]
+    struct Frame {
+        delta: Duration,
+        touches: Vec<(Vec2, u64)>,
+        messages: Vec<Vec<u8>>,
+    }
+
+    #[derive(Resource, Debug, Default, PartialEq)]
+    struct Observed(Vec<Frame>);
+
+    fn observe(
+        time: Res<Time<Real>>,
+        mut touches: MessageReader<TouchInput>,
+        channel: Res<HostChannel>,
+        mut observed: ResMut<Observed>,
+    ) {
+        observed.0.push(Frame {
+            delta: time.delta(),
+            touches: touches
+                .read()
+                .map(|touch| (touch.position, touch.id))
+                .collect(),
+            messages: std::iter::from_fn(|| channel.receive()).collect(),
+        });
+    }
+
+    fn observing_host() -> MockHost {
+        MockHost::from_setup(MockSurface::default(), |app| {
+            app.add_plugins(MinimalPlugins)
+                .init_resource::<Observed>()
+                .add_systems(Update, observe);
+        })
+    }
+
+    #[test]
+    fn replays_recorded_input_into_a_new_app() {
+        let mut host = observing_host();
+        start_recording(host.app_mut());
+        let frames: [Duration; 3] = [1, 40, 8].map(Duration::from_millis);
+        for (i, delta) in frames.into_iter().enumerate() {
+            host.app_mut()
+                .insert_resource(TimeUpdateStrategy::ManualDuration(delta));
+            // Through the FFI, which records, like a real host
+            let started = TouchPhase::Started as u8;
+            ffi::touch_event(host.app_mut(), started, i as f32, 1.0, i as u64).unwrap();
+            host.send(vec![i as u8; i]);
+            host.update();
+        }
+        let log = stop_recording(host.app_mut()).unwrap();
+        assert_eq!(log.frames(), frames.len());
+
+        let mut replayed = observing_host();
+        assert_eq!(replayed.replay(&log), frames.len());
+
+        let recorded = host.app().world().resource::<Observed>();
+        let replayed = replayed.app().world().resource::<Observed>();
+        assert_eq!(replayed, recorded);
+        // Time followed the recording rather than the mock host's 60 Hz frames
+        let deltas: Vec<_> = replayed.0.iter().skip(1).map(|frame| frame.delta).collect();
+        assert_eq!(deltas, frames[1..]);
+    }
+}
@@ -0,0 +1,242 @@
+//! Render resolution of the primary window
+//!
+//! The primary window's physical resolution is derived from the host surface size,
+//! the [`SurfaceTransform`], picture-in-picture scaling and the dynamic resolution
+//! scale, so each of them can change independently without losing the others.
+//!
+//! Embedded apps share the GPU with the host UI. With [`DynamicResolutionPlugin`] added
+//! and [`DynamicResolution`] enabled, the render scale is lowered when frames take longer
+//! than the target frame time and raised again when there is headroom, within the host's
+//! min/max limits. Frame time is the wall time of `bevy_embedded_update`, which includes
+//! rendering and blocks on the swapchain when the GPU falls behind.
+//! [`GpuTiming`](crate::GpuTiming) isn't used: GPU timestamp queries are not available on
+//! enough mobile GPUs to rely on.
+
+use std::time::Duration;
+
+use bevy::{
+    app::{App, Plugin},
+    ecs::{query::With, resource::Resource, world::World},
+    window::{PrimaryWindow, Window},
+};
+
+use crate::{EmbeddedConfig, SurfacePresentation, SurfaceTransform};
+
+/// Frames to wait after a scale change before measuring again
+const SETTLE_FRAMES: u32 = 30;
+
+/// Smallest scale change worth recreating the swapchain for
+const MIN_SCALE_STEP: f32 = 0.05;
+
+/// Physical size and scale factor of the surface as reported by the host
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct HostSurface {
+    /// Physical width in pixels
+    pub width: u32,
+    /// Physical height in pixels
+    pub height: u32,
+    /// Physical pixels per logical pixel
+    pub scale_factor: f32,
+}
+
+impl HostSurface {
+    /// The surface a window was created for, before any scaling was applied
+    pub fn of(window: &Window) -> Self {
+        Self {
+            width: window.resolution.physical_width(),
+            height: window.resolution.physical_height(),
+            scale_factor: window.resolution.scale_factor(),
+        }
+    }
+}
+
+/// Scales the render resolution of the primary window with the frame time
+///
+/// Starts with the limits of [`EmbeddedConfig::dynamic_resolution`]; the host changes
+/// them at runtime.
+#[derive(Default)]
+pub struct DynamicResolutionPlugin;
+
+impl Plugin for DynamicResolutionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EmbeddedConfig>();
+        let settings = app.world().resource::<EmbeddedConfig>().dynamic_resolution;
+        app.insert_resource(DynamicResolution::new(settings));
+    }
+}
+
+/// Limits for dynamic resolution scaling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynamicResolutionSettings {
+    /// Whether the controller adjusts the render scale
+    pub enabled: bool,
+    /// Frame rate the controller tries to sustain
+    pub target_fps: f32,
+    /// Lowest render scale
+    pub min_scale: f32,
+    /// Highest render scale
+    pub max_scale: f32,
+}
+
+impl Default for DynamicResolutionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_fps: 60.0,
+            min_scale: 0.5,
+            max_scale: 1.0,
+        }
+    }
+}
+
+/// Dynamic resolution controller state
+///
+/// Apps can read [`scale`](Self::scale), e.g. to show it in a debug overlay.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct DynamicResolution {
+    /// Limits set at creation or by the host
+    pub settings: DynamicResolutionSettings,
+    scale: f32,
+    average_frame_time: f32,
+    settle_frames: u32,
+}
+
+impl DynamicResolution {
+    /// Creates a controller starting at the maximum scale
+    pub fn new(settings: DynamicResolutionSettings) -> Self {
+        Self {
+            settings,
+            scale: settings.max_scale,
+            average_frame_time: 0.0,
+            settle_frames: SETTLE_FRAMES,
+        }
+    }
+
+    /// Current render scale, 1.0 when disabled
+    pub fn scale(&self) -> f32 {
+        if self.settings.enabled {
+            self.scale
+        } else {
+            1.0
+        }
+    }
+
+    /// Smoothed frame time the controller is working from
+    pub fn average_frame_time(&self) -> Duration {
+        Duration::from_secs_f32(self.average_frame_time)
+    }
+
+    /// Changes the limits, clamping the current scale into them
+    pub(crate) fn set_settings(&mut self, settings: DynamicResolutionSettings) {
+        self.settings = settings;
+        self.scale = self.scale.clamp(settings.min_scale, settings.max_scale);
+        self.settle_frames = SETTLE_FRAMES;
+    }
+
+    /// Feeds a frame time to the controller, returning true if the scale changed
+    fn record(&mut self, frame_time: Duration) -> bool {
+        if !self.settings.enabled {
+            return false;
+        }
+
+        let frame_time = frame_time.as_secs_f32();
+        self.average_frame_time = if self.average_frame_time == 0.0 {
+            frame_time
+        } else {
+            self.average_frame_time * 0.9 + frame_time * 0.1
+        };
+        if self.settle_frames > 0 {
+            self.settle_frames -= 1;
+            return false;
+        }
+
+        // Cost scales with pixel count, i.e. with the square of the scale
+        let budget = 1.0 / self.settings.target_fps;
+        let target = if self.average_frame_time > budget * 0.95 {
+            budget * 0.85
+        } else if self.average_frame_time < budget * 0.6 {
+            budget * 0.75
+        } else {
+            return false;
+        };
+        let scale = (self.scale * (target / self.average_frame_time).sqrt())
+            .clamp(self.settings.min_scale, self.settings.max_scale);
+
+        // Always allow reaching the limits, otherwise skip changes too small to matter
+        let at_limit = scale == self.settings.min_scale || scale == self.settings.max_scale;
+        if scale == self.scale || (!at_limit && (scale - self.scale).abs() < MIN_SCALE_STEP) {
+            return false;
+        }
+        self.scale = scale;
+        self.settle_frames = SETTLE_FRAMES;
+        true
+    }
+}
+
+/// Feeds the duration of a host frame to the dynamic resolution controller
+pub(crate) fn record_frame_time(world: &mut World, frame_time: Duration) {
+    let changed = world
+        .get_resource_mut::<DynamicResolution>()
+        .is_some_and(|mut dynamic| dynamic.record(frame_time));
+    if changed {
+        apply_window_resolution(world);
+    }
+}
+
+/// Sets the primary window's resolution from the host surface and current scaling
+pub(crate) fn apply_window_resolution(world: &mut World) {
+    let Some(surface) = world.get_resource::<HostSurface>().copied() else {
+        return;
+    };
+
+    let (mut width, mut height) = (surface.width, surface.height);
+    if world
+        .get_resource::<SurfaceTransform>()
+        .is_some_and(SurfaceTransform::swaps_axes)
+    {
+        std::mem::swap(&mut width, &mut height);
+    }
+
+    // Reduced resolution keeps the same logical size
+    let mut scale = world
+        .get_resource::<DynamicResolution>()
+        .map_or(1.0, DynamicResolution::scale);
+    if world.get_resource::<SurfacePresentation>() == Some(&SurfacePresentation::PictureInPicture) {
+        scale *= world
+            .resource::<EmbeddedConfig>()
+            .picture_in_picture
+            .resolution_scale
+            .clamp(0.1, 1.0);
+    }
+    let width = ((width as f32 * scale) as u32).max(1);
+    let height = ((height as f32 * scale) as u32).max(1);
+    let scale_factor = surface.scale_factor * scale;
+
+    let mut query = world.query_filtered::<&mut Window, With<PrimaryWindow>>();
+    if let Ok(mut window) = query.single_mut(world) {
+        if window.resolution.physical_width() != width
+            || window.resolution.physical_height() != height
+        {
+            window.resolution.set_physical_resolution(width, height);
+        }
+        if window.resolution.scale_factor() != scale_factor {
+            window
+                .resolution
+                .set_scale_factor_override(Some(scale_factor));
+        }
+    }
+}
+
+/// Records the primary window's creation size as the host surface size
+pub(crate) fn init_host_surface(world: &mut World) {
+    if world.contains_resource::<HostSurface>() {
+        return;
+    }
+    let mut query = world.query_filtered::<&Window, With<PrimaryWindow>>();
+    let Ok(window) = query.single(world) else {
+        return;
+    };
+    let surface = HostSurface::of(window);
+    world.insert_resource(surface);
+    apply_window_resolution(world);
+}
@@ -0,0 +1,459 @@
+//! Shared memory ring buffers for high-throughput topics
+//!
+//! Every message on the [`HostChannel`](crate::HostChannel) is a separate allocation that
+//! goes through a crossbeam queue, and on Android through a Java byte array. That is fine
+//! for commands, but not for audio, point clouds or per-frame skeleton poses. Topics that
+//! need the throughput can instead be given a ring channel: a pair of fixed-size
+//! [`RingBuffer`]s, one per direction, with atomic read and write cursors. The host
+//! copies straight into and out of ring memory (`bevy_embedded_ios_ring_send` /
+//! `bevy_embedded_ios_ring_receive`, or `nativeRingSend` / `nativeRingReceive` with
+//! direct `ByteBuffer`s), so a message costs one copy each way and no allocation on the
+//! host side.
+//!
+//! Ring channels are opened per topic, an id the app and host agree on, when the app is
+//! set up:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{RingChannelApp, RingChannels};
+//!
+//! const POSES: u16 = 1;
+//!
+//! fn setup(app: &mut App) {
+//!     app.add_ring_channel(POSES, 256 * 1024)
+//!         .add_systems(Update, read_poses);
+//! }
+//!
+//! fn read_poses(channels: Res<RingChannels>, mut buffer: Local<Vec<u8>>) {
+//!     let Some(poses) = channels.get(POSES) else {
+//!         return;
+//!     };
+//!     while poses.receive_into(&mut buffer) {
+//!         // Decode the pose in `buffer`
+//!     }
+//! }
+//! ```
+//!
+//! A full ring rejects new messages instead of blocking, so the sender decides whether to
+//! drop or retry.
+
+use std::{
+    cell::UnsafeCell,
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+
+use bevy::{app::App, ecs::resource::Resource};
+
+/// Size of the length prefix written before every message
+const FRAME_HEADER_LEN: usize = 4;
+
+/// Largest capacity of a ring buffer
+pub const MAX_RING_CAPACITY: usize = 1 << 30;
+
+/// Single-direction ring of length-prefixed messages
+///
+/// Producers and consumers only synchronize through the two cursors, so the host writing
+/// while Bevy reads never waits. Concurrent producers (or concurrent consumers) on the
+/// same side are serialized by a lock.
+pub struct RingBuffer {
+    data: Box<[UnsafeCell<u8>]>,
+    /// Total bytes ever written, wrapping
+    write: AtomicU32,
+    /// Total bytes ever read, wrapping
+    read: AtomicU32,
+    push_lock: Mutex<()>,
+    pop_lock: Mutex<()>,
+}
+
+// Bytes between the cursors are only touched by the side that owns them: the producer
+// writes past `write`, the consumer reads before it
+unsafe impl Sync for RingBuffer {}
+
+impl RingBuffer {
+    /// Creates a ring holding `capacity` bytes, rounded up to a power of two
+    ///
+    /// Each message takes 4 bytes more than its length.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity
+            .clamp(FRAME_HEADER_LEN, MAX_RING_CAPACITY)
+            .next_power_of_two();
+        Self {
+            data: (0..capacity).map(|_| UnsafeCell::new(0)).collect(),
+            write: AtomicU32::new(0),
+            read: AtomicU32::new(0),
+            push_lock: Mutex::new(()),
+            pop_lock: Mutex::new(()),
+        }
+    }
+
+    /// Number of bytes the ring holds
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns true if no message is waiting
+    pub fn is_empty(&self) -> bool {
+        self.read.load(Ordering::Relaxed) == self.write.load(Ordering::Acquire)
+    }
+
+    /// Appends a message, returning false if there is not enough free space
+    pub fn push(&self, message: &[u8]) -> bool {
+        self.push_parts(&[message])
+    }
+
+    /// Appends one message made of `parts`, without joining them first
+    pub(crate) fn push_parts(&self, parts: &[&[u8]]) -> bool {
+        let message_len: usize = parts.iter().map(|part| part.len()).sum();
+        let Ok(len) = u32::try_from(message_len) else {
+            return false;
+        };
+        let _guard = self.push_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let write = self.write.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+        let free = self.capacity() - write.wrapping_sub(read) as usize;
+        if FRAME_HEADER_LEN + message_len > free {
+            return false;
+        }
+
+        self.copy_in(write, &len.to_le_bytes());
+        let mut at = write.wrapping_add(FRAME_HEADER_LEN as u32);
+        for part in parts {
+            self.copy_in(at, part);
+            at = at.wrapping_add(part.len() as u32);
+        }
+        self.write.store(at, Ordering::Release);
+        true
+    }
+
+    /// Length of the next message, if one is waiting
+    pub fn next_len(&self) -> Option<usize> {
+        let read = self.read.load(Ordering::Relaxed);
+        if read == self.write.load(Ordering::Acquire) {
+            return None;
+        }
+        let mut len = [0; FRAME_HEADER_LEN];
+        self.copy_out(read, &mut len);
+        Some(u32::from_le_bytes(len) as usize)
+    }
+
+    /// Takes the next message into the start of `buffer`, returning its length
+    ///
+    /// Returns `None`, leaving the message in the ring, if none is waiting or it does not
+    /// fit in `buffer`.
+    pub fn pop_into(&self, buffer: &mut [u8]) -> Option<usize> {
+        self.pop_with(move |len| buffer.get_mut(..len))
+    }
+
+    /// Takes the next message, replacing the contents of `buffer` and reusing its
+    /// allocation; returns false if none is waiting
+    pub fn pop_vec(&self, buffer: &mut Vec<u8>) -> bool {
+        self.pop_with(move |len| {
+            buffer.resize(len, 0);
+            Some(buffer.as_mut_slice())
+        })
+        .is_some()
+    }
+
+    /// Takes the next message into the slice `destination` returns for its length
+    fn pop_with<'a>(
+        &self,
+        destination: impl FnOnce(usize) -> Option<&'a mut [u8]>,
+    ) -> Option<usize> {
+        let _guard = self.pop_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let len = self.next_len()?;
+        let read = self.read.load(Ordering::Relaxed);
+        self.copy_out(
+            read.wrapping_add(FRAME_HEADER_LEN as u32),
+            destination(len)?,
+        );
+        self.read.store(
+            read.wrapping_add((FRAME_HEADER_LEN + len) as u32),
+            Ordering::Release,
+        );
+        Some(len)
+    }
+
+    fn base(&self) -> *mut u8 {
+        UnsafeCell::raw_get(self.data.as_ptr())
+    }
+
+    /// Copies `bytes` into the ring starting at cursor `at`, wrapping around the end
+    fn copy_in(&self, at: u32, bytes: &[u8]) {
+        let start = at as usize & (self.capacity() - 1);
+        let first = bytes.len().min(self.capacity() - start);
+        // SAFETY: both ranges are in bounds and in the free region owned by the producer
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), self.base().add(start), first);
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr().add(first),
+                self.base(),
+                bytes.len() - first,
+            );
+        }
+    }
+
+    /// Copies bytes out of the ring starting at cursor `at`, wrapping around the end
+    fn copy_out(&self, at: u32, bytes: &mut [u8]) {
+        let start = at as usize & (self.capacity() - 1);
+        let first = bytes.len().min(self.capacity() - start);
+        // SAFETY: both ranges are in bounds and in the filled region owned by the consumer
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.base().add(start), bytes.as_mut_ptr(), first);
+            std::ptr::copy_nonoverlapping(
+                self.base(),
+                bytes.as_mut_ptr().add(first),
+                bytes.len() - first,
+            );
+        }
+    }
+}
+
+/// The Bevy side of a topic's pair of rings
+#[derive(Clone)]
+pub struct RingChannel {
+    to_host: Arc<RingBuffer>,
+    to_bevy: Arc<RingBuffer>,
+}
+
+impl RingChannel {
+    fn new(capacity: usize) -> Self {
+        Self {
+            to_host: Arc::new(RingBuffer::new(capacity)),
+            to_bevy: Arc::new(RingBuffer::new(capacity)),
+        }
+    }
+
+    /// Send a message to the host, returning false if the ring is full
+    pub fn send(&self, message: &[u8]) -> bool {
+        self.to_host.push(message)
+    }
+
+    /// Receive the next message from the host (non-blocking)
+    pub fn receive(&self) -> Option<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.to_bevy.pop_vec(&mut buffer).then_some(buffer)
+    }
+
+    /// Receive the next message from the host into `buffer`, reusing its allocation;
+    /// returns false if none is waiting
+    pub fn receive_into(&self, buffer: &mut Vec<u8>) -> bool {
+        self.to_bevy.pop_vec(buffer)
+    }
+
+    /// Get the host side of this channel
+    pub fn endpoint(&self) -> RingEndpoint {
+        RingEndpoint {
+            to_host: self.to_host.clone(),
+            to_bevy: self.to_bevy.clone(),
+        }
+    }
+}
+
+/// The host side of a [`RingChannel`]
+#[derive(Clone)]
+pub struct RingEndpoint {
+    to_host: Arc<RingBuffer>,
+    to_bevy: Arc<RingBuffer>,
+}
+
+impl RingEndpoint {
+    /// Send a message to Bevy, returning false if the ring is full
+    pub fn send(&self, message: &[u8]) -> bool {
+        self.to_bevy.push(message)
+    }
+
+    /// The ring of messages Bevy sent to the host
+    pub fn incoming(&self) -> &RingBuffer {
+        &self.to_host
+    }
+}
+
+/// Ring channels opened by the app, keyed by topic
+///
+/// Shared with the instance [`registry`](crate::registry), so the host can reach them
+/// without going through the app.
+#[derive(Resource, Clone, Default)]
+pub struct RingChannels {
+    channels: Arc<Mutex<HashMap<u16, RingChannel>>>,
+}
+
+impl RingChannels {
+    /// Opens a ring channel for `topic` with `capacity` bytes per direction
+    ///
+    /// Returns the existing channel if the topic is already open.
+    pub fn open(&self, topic: u16, capacity: usize) -> RingChannel {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels
+            .entry(topic)
+            .or_insert_with(|| RingChannel::new(capacity))
+            .clone()
+    }
+
+    /// The ring channel of `topic`, if it is open
+    pub fn get(&self, topic: u16) -> Option<RingChannel> {
+        let channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+        channels.get(&topic).cloned()
+    }
+
+    /// The host side of the ring channel of `topic`, if it is open
+    pub fn endpoint(&self, topic: u16) -> Option<RingEndpoint> {
+        self.get(topic).map(|channel| channel.endpoint())
+    }
+}
+
+/// Opening ring channels while setting up an [`App`]
+pub trait RingChannelApp {
+    /// Carries `topic` over a ring channel with `capacity` bytes per direction
+    fn add_ring_channel(&mut self, topic: u16, capacity: usize) -> &mut Self;
+}
+
+impl RingChannelApp for App {
+    fn add_ring_channel(&mut self, topic: u16, capacity: usize) -> &mut Self {
+        self.init_resource::<RingChannels>();
+        self.world()
+            .resource::<RingChannels>()
+            .open(topic, capacity);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::MinimalPlugins;
+
+    use super::*;
+    use crate::{
+        registry,
+        test::{MockHost, MockSurface},
+    };
+
+    /// A ring whose cursors are both at `cursor`, as if that many bytes had passed through
+    fn ring_at(capacity: usize, cursor: u32) -> RingBuffer {
+        let ring = RingBuffer::new(capacity);
+        ring.write.store(cursor, Ordering::Relaxed);
+        ring.read.store(cursor, Ordering::Relaxed);
+        ring
+    }
+
+    fn pop(ring: &RingBuffer) -> Option<Vec<u8>> {
+        let mut buffer = Vec::new();
+        ring.pop_vec(&mut buffer).then_some(buffer)
+    }
+
+    #[test]
+    fn messages_cross_the_end_of_the_ring_and_u32_max() {
+        let ring = ring_at(16, u32::MAX - 5);
+        assert!(ring.push(b"abcdef"));
+        assert!(ring.write.load(Ordering::Relaxed) < 16);
+        assert_eq!(ring.next_len(), Some(6));
+        assert_eq!(pop(&ring).as_deref(), Some(&b"abcdef"[..]));
+        assert!(ring.is_empty());
+
+        for round in 0..8u8 {
+            assert!(ring.push(&[round; 5]));
+            assert_eq!(pop(&ring), Some(vec![round; 5]));
+        }
+    }
+
+    #[test]
+    fn full_ring_rejects_messages_until_read() {
+        let ring = RingBuffer::new(16);
+        assert!(ring.push(&[1; 4]));
+        assert!(ring.push(&[2; 4]));
+        assert!(!ring.push(&[]));
+        assert_eq!(pop(&ring), Some(vec![1; 4]));
+        assert!(ring.push(&[3; 4]));
+        assert_eq!(pop(&ring), Some(vec![2; 4]));
+        assert_eq!(pop(&ring), Some(vec![3; 4]));
+        assert_eq!(pop(&ring), None);
+    }
+
+    #[test]
+    fn oversize_messages_are_rejected() {
+        let ring = RingBuffer::new(16);
+        assert!(!ring.push(&[0; 13]));
+        assert!(ring.is_empty());
+        assert!(ring.push(&[0; 12]));
+
+        let ring = RingBuffer::new(16);
+        assert!(ring.push(&[7; 10]));
+        let mut small = [0; 4];
+        assert_eq!(ring.pop_into(&mut small), None);
+        assert_eq!(ring.next_len(), Some(10));
+        let mut large = [0; 16];
+        assert_eq!(ring.pop_into(&mut large), Some(10));
+        assert_eq!(large[..10], [7; 10]);
+    }
+
+    #[test]
+    fn zero_length_messages_take_only_a_header() {
+        let ring = RingBuffer::new(8);
+        assert!(ring.push(&[]));
+        assert!(ring.push(&[]));
+        assert!(!ring.push(&[]));
+        assert_eq!(ring.next_len(), Some(0));
+        assert_eq!(ring.pop_into(&mut []), Some(0));
+        assert_eq!(pop(&ring), Some(Vec::new()));
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn messages_arrive_in_order_across_threads() {
+        const MESSAGES: u32 = 20_000;
+        let ring = Arc::new(RingBuffer::new(256));
+
+        let producer = {
+            let ring = ring.clone();
+            std::thread::spawn(move || {
+                for i in 0..MESSAGES {
+                    // Lengths vary so messages straddle the end of the ring
+                    let message = vec![i as u8; (i % 23) as usize];
+                    let (head, tail) = message.split_at(message.len() / 2);
+                    while !ring.push_parts(&[&i.to_le_bytes(), head, tail]) {
+                        std::thread::yield_now();
+                    }
+                }
+            })
+        };
+
+        let mut buffer = Vec::new();
+        let mut received = 0;
+        while received < MESSAGES {
+            if !ring.pop_vec(&mut buffer) {
+                std::thread::yield_now();
+                continue;
+            }
+            let (index, message) = buffer.split_at(4);
+            assert_eq!(u32::from_le_bytes(index.try_into().unwrap()), received);
+            assert_eq!(message, vec![received as u8; (received % 23) as usize]);
+            received += 1;
+        }
+        producer.join().unwrap();
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn channels_opened_after_registration_reach_the_host() {
+        let mut host = MockHost::from_setup(MockSurface::default(), |app| {
+            app.add_plugins(MinimalPlugins);
+        });
+        host.app_mut().add_ring_channel(7, 256);
+        let instance = registry::get(host.app_ptr()).unwrap();
+        let endpoint = instance.ring_channels().endpoint(7).unwrap();
+
+        let channel = host
+            .app()
+            .world()
+            .resource::<RingChannels>()
+            .get(7)
+            .unwrap();
+        assert!(channel.send(b"to host"));
+        assert_eq!(pop(endpoint.incoming()), Some(b"to host".to_vec()));
+        assert!(endpoint.send(b"to app"));
+        assert_eq!(channel.receive(), Some(b"to app".to_vec()));
+    }
+}
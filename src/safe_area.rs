@@ -0,0 +1,44 @@
+//! Safe area of the host view
+//!
+//! Notches, rounded corners, the home indicator and system bars can cover the edges of
+//! the surface. The host reports how far they reach in with
+//! `bevy_embedded_ios_set_safe_area` / `nativeSetSafeArea` whenever its layout changes
+//! (`safeAreaInsetsDidChange`, `OnApplyWindowInsetsListener`). The insets are kept in
+//! the [`SafeArea`] resource, and each change is announced with [`SafeAreaChanged`], so
+//! UI can keep buttons and text clear of them.
+
+use bevy::{
+    ecs::{message::Message, resource::Resource},
+    math::{Rect, Vec2},
+};
+
+/// Distances from each edge of the surface that system UI may cover, in logical pixels
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct SafeArea {
+    /// Inset from the top edge
+    pub top: f32,
+    /// Inset from the left edge
+    pub left: f32,
+    /// Inset from the bottom edge
+    pub bottom: f32,
+    /// Inset from the right edge
+    pub right: f32,
+}
+
+impl SafeArea {
+    /// The part of a surface of logical `size` that nothing covers
+    pub fn rect(&self, size: Vec2) -> Rect {
+        let min = Vec2::new(self.left, self.top);
+        let max = (size - Vec2::new(self.right, self.bottom)).max(min);
+        Rect { min, max }
+    }
+}
+
+/// Sent when the host reports a changed safe area
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+pub struct SafeAreaChanged {
+    /// Insets before the change
+    pub previous: SafeArea,
+    /// Insets after the change
+    pub current: SafeArea,
+}
@@ -0,0 +1,48 @@
+//! Host scenes, such as iPad windows, each embedding their own instance
+//!
+//! On iPad a host app can show several windows at once, each a `UIScene` with its own
+//! session. Each scene gets its own instance, created with
+//! `bevy_embedded_ios_create_app_for_scene`, which takes the scene's surface directly
+//! instead of through the process-wide `bevy_embedded_get_surface` callback. The scene id
+//! is kept in the [`HostScene`] resource, and the host can find the instance of a scene
+//! again with `bevy_embedded_ios_app_for_scene`.
+//!
+//! UIKit may disconnect a scene in the background to reclaim memory and reconnect it when
+//! the user returns to the window. `bevy_embedded_ios_scene_disconnected` releases the
+//! instance's surface, so the host can free the view, and backgrounds it;
+//! `bevy_embedded_ios_scene_connected` hands it the new view and makes it active again,
+//! with the world as it was left.
+
+use bevy::ecs::resource::Resource;
+
+/// The host scene (window) this instance is embedded in
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostScene {
+    id: u64,
+    connected: bool,
+}
+
+impl HostScene {
+    /// A connected scene with the host's identifier `id`
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            connected: true,
+        }
+    }
+
+    /// The host's identifier of the scene
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns false while the host has disconnected the scene and the instance has no
+    /// surface
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub(crate) fn set_connected(&mut self, connected: bool) {
+        self.connected = connected;
+    }
+}
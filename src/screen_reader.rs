@@ -0,0 +1,89 @@
+//! Screen reader announcements through the host
+//!
+//! Transient events in accessible apps, like "model loaded" or "piece placed", have no
+//! element to focus, so they are spoken as announcements. [`ScreenReader::announce`] asks
+//! the host to post one with VoiceOver or TalkBack, sending [`ReservedTopic::Announce`]:
+//!
+//! ```text
+//! priority: u8 (AnnouncementPriority) | text: u32 len + UTF-8
+//! ```
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::{AnnouncementPriority, ScreenReader};
+//!
+//! fn announce_placed(mut screen_reader: ResMut<ScreenReader>) {
+//!     screen_reader.announce("Piece placed on E4", AnnouncementPriority::Polite);
+//! }
+//! ```
+//!
+//! Announcements are sent by [`ScreenReaderPlugin`]. Hosts without a running screen reader
+//! ignore them.
+
+use bevy::{
+    app::{App, Last, Plugin},
+    ecs::{
+        resource::Resource,
+        system::{Res, ResMut},
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteWriter, ReservedTopic},
+};
+
+/// Sends [`ScreenReader`] announcements to the host; see the [module docs](self)
+#[derive(Default)]
+pub struct ScreenReaderPlugin;
+
+impl Plugin for ScreenReaderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenReader>()
+            .add_systems(Last, send_announcements);
+    }
+}
+
+/// How urgently an announcement is spoken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum AnnouncementPriority {
+    /// Spoken after what the screen reader is saying
+    #[default]
+    Polite = 0,
+    /// Interrupts what the screen reader is saying
+    Assertive = 1,
+}
+
+impl AnnouncementPriority {
+    /// Create an AnnouncementPriority from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(AnnouncementPriority::Polite),
+            1 => Some(AnnouncementPriority::Assertive),
+            _ => None,
+        }
+    }
+}
+
+/// Posts announcements with the host's screen reader
+#[derive(Resource, Debug, Default)]
+pub struct ScreenReader {
+    outgoing: Vec<Vec<u8>>,
+}
+
+impl ScreenReader {
+    /// Asks the host to speak `text` with `priority`
+    pub fn announce(&mut self, text: impl AsRef<str>, priority: AnnouncementPriority) {
+        let mut payload = ByteWriter::new();
+        payload.u8(priority as u8).string(text.as_ref());
+        self.outgoing.push(payload.finish());
+    }
+}
+
+/// Sends the frame's announcements to the host
+fn send_announcements(channel: Res<HostChannel>, mut screen_reader: ResMut<ScreenReader>) {
+    for payload in screen_reader.outgoing.drain(..) {
+        channel.send_reserved(ReservedTopic::Announce, &payload);
+    }
+}
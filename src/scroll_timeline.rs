@@ -0,0 +1,185 @@
+//! Animations driven by a host scroll position
+//!
+//! "Scrolly-telling" pages animate a 3D scene as the user scrolls a native view. The host
+//! writes its scroll progress into a [`HostState`] slot every frame, and a
+//! [`ScrollTimeline`] maps that slot to a progress from 0 to 1 without any per-app
+//! message handling. [`ScrollTimelinePlugin`] updates the timelines:
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use bevy_embedded::ScrollTimeline;
+//!
+//! const SCROLL: usize = 0;
+//!
+//! fn spawn_camera(mut commands: Commands) {
+//!     commands.spawn((
+//!         Camera3d::default(),
+//!         // The host writes the scroll offset in points, the story spans 2000 of them
+//!         ScrollTimeline::new(SCROLL).range(0.0, 2000.0),
+//!     ));
+//! }
+//!
+//! fn fly_down(camera: Single<(&ScrollTimeline, &mut Transform)>) {
+//!     let (timeline, mut transform) = camera.into_inner();
+//!     transform.translation.y = 10.0 * (1.0 - timeline.progress());
+//! }
+//! ```
+//!
+//! With the `scroll_timeline` cargo feature, which pulls in `bevy_animation`, every
+//! `AnimationPlayer` on a timeline entity or one of its descendants is scrubbed, e.g. the
+//! players of a glTF scene spawned under a timeline: the animations it plays are paused
+//! and held at the timeline's progress through their clips. Start them with
+//! `AnimationPlayer::play` as usual.
+
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        system::{Query, Res},
+    },
+};
+
+use crate::{HostState, SharedStatePlugin};
+
+/// Updates [`ScrollTimeline`]s from host state, adding [`SharedStatePlugin`] if needed; see
+/// the [module docs](self)
+#[derive(Default)]
+pub struct ScrollTimelinePlugin;
+
+impl Plugin for ScrollTimelinePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SharedStatePlugin>() {
+            app.add_plugins(SharedStatePlugin);
+        }
+        app.add_systems(PreUpdate, update_scroll_timelines);
+
+        #[cfg(feature = "scroll_timeline")]
+        {
+            use bevy::{
+                app::{AnimationSystems, PostUpdate},
+                ecs::schedule::IntoScheduleConfigs,
+            };
+
+            app.add_systems(PostUpdate, scrub_scroll_animations.before(AnimationSystems));
+        }
+    }
+}
+
+/// Maps a host state value to an animation progress
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ScrollTimeline {
+    /// [`HostState`] index the host writes the scroll position into
+    pub slot: usize,
+    /// Value at which the timeline starts
+    pub start: f32,
+    /// Value at which the timeline ends
+    pub end: f32,
+    progress: f32,
+}
+
+impl ScrollTimeline {
+    /// A timeline driven by a 0 to 1 progress in `slot`
+    pub fn new(slot: usize) -> Self {
+        Self {
+            slot,
+            start: 0.0,
+            end: 1.0,
+            progress: 0.0,
+        }
+    }
+
+    /// Sets the values the timeline starts and ends at
+    pub fn range(mut self, start: f32, end: f32) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Progress from 0 to 1 as of this frame
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    /// Progress for a host value, clamped to 0..=1
+    fn progress_at(&self, value: f32) -> f32 {
+        let span = self.end - self.start;
+        if span == 0.0 || !value.is_finite() {
+            return 0.0;
+        }
+        ((value - self.start) / span).clamp(0.0, 1.0)
+    }
+}
+
+/// Updates timelines from this frame's host state
+fn update_scroll_timelines(state: Res<HostState>, mut timelines: Query<&mut ScrollTimeline>) {
+    for mut timeline in &mut timelines {
+        if !state.is_changed() && !timeline.is_added() {
+            continue;
+        }
+        let progress = timeline.progress_at(state.get(timeline.slot));
+        if timeline.progress != progress {
+            timeline.progress = progress;
+        }
+    }
+}
+
+#[cfg(feature = "scroll_timeline")]
+use animation::*;
+
+#[cfg(feature = "scroll_timeline")]
+mod animation {
+    use bevy::{
+        animation::{
+            AnimationClip, AnimationPlayer,
+            graph::{AnimationGraph, AnimationGraphHandle, AnimationNodeType},
+        },
+        asset::Assets,
+        ecs::{
+            entity::Entity,
+            hierarchy::ChildOf,
+            system::{Query, Res},
+        },
+    };
+
+    use super::ScrollTimeline;
+
+    /// Holds the animations of players under a timeline at its progress
+    pub(super) fn scrub_scroll_animations(
+        mut players: Query<(Entity, &mut AnimationPlayer, &AnimationGraphHandle)>,
+        timelines: Query<&ScrollTimeline>,
+        parents: Query<&ChildOf>,
+        graphs: Res<Assets<AnimationGraph>>,
+        clips: Res<Assets<AnimationClip>>,
+    ) {
+        for (entity, mut player, graph) in &mut players {
+            let Some(timeline) = std::iter::once(entity)
+                .chain(parents.iter_ancestors(entity))
+                .find_map(|entity| timelines.get(entity).ok())
+            else {
+                continue;
+            };
+            let Some(graph) = graphs.get(&graph.0) else {
+                continue;
+            };
+
+            for (node, active) in player.playing_animations_mut() {
+                let Some(AnimationNodeType::Clip(clip)) =
+                    graph.get(*node).map(|node| &node.node_type)
+                else {
+                    continue;
+                };
+                let Some(clip) = clips.get(clip) else {
+                    continue;
+                };
+                if !active.is_paused() {
+                    active.pause();
+                }
+                let seek_time = timeline.progress() * clip.duration();
+                if active.seek_time() != seek_time {
+                    active.seek_to(seek_time);
+                }
+            }
+        }
+    }
+}
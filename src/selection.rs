@@ -0,0 +1,318 @@
+//! Entity selection shared with the host
+//!
+//! Hosts building master/detail UIs around Bevy content select and deselect entities with
+//! [`ReservedTopic::Selection`] commands, by entity bits or by [`Name`]. Each command
+//! payload is a sequence of operations, each starting with a [`SelectionOp`] byte:
+//!
+//! ```text
+//! Select / Deselect:             entity: u64 (Entity::to_bits)
+//! SelectByName / DeselectByName: name: u32 len + UTF-8
+//! Clear
+//! ```
+//!
+//! Commands are handled once [`SelectionPlugin`] is added. Selected entities carry the
+//! [`Selected`] marker. Every change, whether from the host,
+//! from the app or from picking, is reported as a [`SelectionChanged`] message and sent
+//! to the host on [`ReservedTopic::SelectionChanged`] as
+//! `entity: u64 | selected: u8 | name: u32 len + UTF-8` (empty without a `Name`).
+//!
+//! With the `selection` cargo feature, tapping an entity marked [`Selectable`] (or one of
+//! its descendants) selects it in place of the current selection, and tapping anything
+//! else clears it. Selected meshes using `StandardMaterial` are tinted with
+//! [`SelectionStyle::tint`] through a per-entity copy of their material, which is
+//! restored on deselection.
+
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate},
+    color::{Color, LinearRgba},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        lifecycle::RemovedComponents,
+        message::{Message, MessageReader, MessageWriter},
+        name::Name,
+        query::{Added, With},
+        resource::Resource,
+        system::{Commands, Query, Res},
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Shares the selection with the host; see the [module docs](self)
+#[derive(Default)]
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectionStyle>()
+            .add_message::<SelectionChanged>()
+            .add_systems(PreUpdate, receive_selection)
+            .add_systems(PostUpdate, report_selection_changes);
+    }
+
+    // Picking selection and tinting need picking and PBR materials in the app
+    #[cfg(feature = "selection")]
+    fn finish(&self, app: &mut App) {
+        use bevy::{
+            app::Update,
+            asset::Assets,
+            ecs::message::Messages,
+            pbr::StandardMaterial,
+            picking::events::{Click, Pointer},
+        };
+
+        if app.world().contains_resource::<Messages<Pointer<Click>>>() {
+            app.add_systems(Update, select_on_click);
+        }
+        if app.world().contains_resource::<Assets<StandardMaterial>>() {
+            app.add_systems(PostUpdate, tint_selected);
+        }
+    }
+}
+
+/// Operations that can appear in a selection command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SelectionOp {
+    /// Select an entity by its bits
+    Select = 0,
+    /// Deselect an entity by its bits
+    Deselect = 1,
+    /// Select every entity with the given name
+    SelectByName = 2,
+    /// Deselect every entity with the given name
+    DeselectByName = 3,
+    /// Deselect everything
+    Clear = 4,
+}
+
+impl SelectionOp {
+    /// Create a SelectionOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SelectionOp::Select),
+            1 => Some(SelectionOp::Deselect),
+            2 => Some(SelectionOp::SelectByName),
+            3 => Some(SelectionOp::DeselectByName),
+            4 => Some(SelectionOp::Clear),
+            _ => None,
+        }
+    }
+}
+
+/// Marks a selected entity
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Selected;
+
+/// Marks an entity that picking can select
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Selectable;
+
+/// Sent when an entity is selected or deselected
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectionChanged {
+    /// Entity whose selection changed
+    pub entity: Entity,
+    /// Whether it is now selected
+    pub selected: bool,
+}
+
+/// How selected entities are shown
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct SelectionStyle {
+    /// Emissive tint added to selected meshes
+    pub tint: Color,
+}
+
+impl Default for SelectionStyle {
+    fn default() -> Self {
+        Self {
+            tint: LinearRgba::rgb(0.6, 0.35, 0.0).into(),
+        }
+    }
+}
+
+/// Applies one operation, returning `None` if the payload is malformed
+fn apply_op(
+    commands: &mut Commands,
+    reader: &mut ByteReader,
+    named: &Query<(Entity, &Name)>,
+    selected: &Query<Entity, With<Selected>>,
+) -> Option<()> {
+    let op = reader.u8()?;
+    let Some(op) = SelectionOp::from_u8(op) else {
+        log::warn!("Ignoring selection command with unknown operation {op}");
+        return None;
+    };
+    match op {
+        SelectionOp::Select | SelectionOp::Deselect => {
+            let entity = Entity::try_from_bits(reader.u64()?)?;
+            let Ok(mut entity_commands) = commands.get_entity(entity) else {
+                log::warn!("Cannot change selection of missing entity {entity}");
+                return Some(());
+            };
+            if op == SelectionOp::Select {
+                entity_commands.insert(Selected);
+            } else {
+                entity_commands.remove::<Selected>();
+            }
+        }
+        SelectionOp::SelectByName | SelectionOp::DeselectByName => {
+            let name = reader.string()?;
+            for (entity, _) in named.iter().filter(|(_, n)| n.as_str() == name) {
+                if op == SelectionOp::SelectByName {
+                    commands.entity(entity).insert(Selected);
+                } else {
+                    commands.entity(entity).remove::<Selected>();
+                }
+            }
+        }
+        SelectionOp::Clear => {
+            for entity in selected {
+                commands.entity(entity).remove::<Selected>();
+            }
+        }
+    }
+    Some(())
+}
+
+/// Applies selection commands from the host
+fn receive_selection(
+    mut commands: Commands,
+    mut host_commands: MessageReader<HostCommand>,
+    named: Query<(Entity, &Name)>,
+    selected: Query<Entity, With<Selected>>,
+) {
+    for command in host_commands.read() {
+        if command.topic != ReservedTopic::Selection {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while !reader.is_empty() {
+            if apply_op(&mut commands, &mut reader, &named, &selected).is_none() {
+                log::warn!("Ignoring malformed selection command");
+                break;
+            }
+        }
+    }
+}
+
+/// Reports selection changes to the app and the host
+fn report_selection_changes(
+    channel: Res<HostChannel>,
+    mut changes: MessageWriter<SelectionChanged>,
+    added: Query<Entity, Added<Selected>>,
+    mut removed: RemovedComponents<Selected>,
+    names: Query<&Name>,
+) {
+    let added = added.iter().map(|entity| (entity, true));
+    let removed = removed.read().map(|entity| (entity, false));
+    for (entity, selected) in added.chain(removed) {
+        changes.write(SelectionChanged { entity, selected });
+
+        let name = names.get(entity).map_or("", Name::as_str);
+        let mut payload = ByteWriter::new();
+        payload.u64(entity.to_bits()).bool(selected).string(name);
+        channel.send_reserved(ReservedTopic::SelectionChanged, &payload.finish());
+    }
+}
+
+/// The material a tinted mesh had before it was selected
+///
+/// [`GlbExport`](crate::GlbExport) exports it in place of the tinted copy.
+#[cfg(any(feature = "selection", feature = "gltf_export"))]
+#[derive(Component)]
+pub(crate) struct UntintedMaterial(pub(crate) bevy::asset::Handle<bevy::pbr::StandardMaterial>);
+
+#[cfg(feature = "selection")]
+use picking::*;
+
+#[cfg(feature = "selection")]
+mod picking {
+    use bevy::{
+        asset::Assets,
+        ecs::{
+            entity::Entity,
+            hierarchy::{ChildOf, Children},
+            lifecycle::RemovedComponents,
+            message::MessageReader,
+            query::{Added, With},
+            system::{Commands, Query, Res, ResMut},
+        },
+        pbr::{MeshMaterial3d, StandardMaterial},
+        picking::events::{Click, Pointer},
+    };
+
+    use super::{Selectable, Selected, SelectionStyle, UntintedMaterial};
+
+    /// Selects the tapped [`Selectable`] entity, or clears the selection
+    pub(crate) fn select_on_click(
+        mut commands: Commands,
+        mut clicks: MessageReader<Pointer<Click>>,
+        selectable: Query<(), With<Selectable>>,
+        parents: Query<&ChildOf>,
+        selected: Query<Entity, With<Selected>>,
+    ) {
+        let Some(click) = clicks.read().last() else {
+            return;
+        };
+
+        let target = std::iter::once(click.entity)
+            .chain(parents.iter_ancestors(click.entity))
+            .find(|entity| selectable.contains(*entity));
+        for entity in &selected {
+            if Some(entity) != target {
+                commands.entity(entity).remove::<Selected>();
+            }
+        }
+        if let Some(target) = target
+            && !selected.contains(target)
+        {
+            commands.entity(target).insert(Selected);
+        }
+    }
+
+    /// Swaps the materials of selected meshes for tinted copies, and back on deselection
+    pub(crate) fn tint_selected(
+        mut commands: Commands,
+        style: Res<SelectionStyle>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        added: Query<Entity, Added<Selected>>,
+        mut removed: RemovedComponents<Selected>,
+        children: Query<&Children>,
+        meshes: Query<(&MeshMaterial3d<StandardMaterial>, Option<&UntintedMaterial>)>,
+    ) {
+        for root in removed.read() {
+            for entity in std::iter::once(root).chain(children.iter_descendants(root)) {
+                if let Ok((_, Some(UntintedMaterial(original)))) = meshes.get(entity)
+                    && let Ok(mut entity_commands) = commands.get_entity(entity)
+                {
+                    entity_commands
+                        .insert(MeshMaterial3d(original.clone()))
+                        .remove::<UntintedMaterial>();
+                }
+            }
+        }
+
+        for root in &added {
+            for entity in std::iter::once(root).chain(children.iter_descendants(root)) {
+                let Ok((material, None)) = meshes.get(entity) else {
+                    continue;
+                };
+                let Some(mut copy) = materials.get(&material.0).cloned() else {
+                    continue;
+                };
+                copy.emissive += style.tint.to_linear();
+                commands.entity(entity).insert((
+                    MeshMaterial3d(materials.add(copy)),
+                    UntintedMaterial(material.0.clone()),
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Per-topic send policies for high-frequency outbound messages
+//!
+//! A system that sends on every change, such as a camera matrix per touch move, can queue
+//! messages faster than a slow host drains them. Messages sent with
+//! [`HostChannel::send_topic`] are tagged with an app-defined topic, and the policy set
+//! for that topic decides what reaches the host:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use bevy::prelude::*;
+//! use bevy_embedded::{HostChannel, SendPolicy, SendPolicyApp};
+//!
+//! const CAMERA: u16 = 1;
+//! const STATS: u16 = 2;
+//!
+//! fn setup(app: &mut App) {
+//!     app.set_send_policy(CAMERA, SendPolicy::KeepLatest)
+//!         .set_send_policy(STATS, SendPolicy::Interval(Duration::from_millis(250)))
+//!         .add_systems(Update, send_camera);
+//! }
+//!
+//! fn send_camera(camera: Single<&Transform, With<Camera3d>>, channel: Res<HostChannel>) {
+//!     let matrix = camera.to_matrix().to_cols_array();
+//!     channel.send_topic(CAMERA, matrix.iter().flat_map(|v| v.to_le_bytes()).collect());
+//! }
+//! ```
+//!
+//! The topic only selects the policy; the host receives the message bytes unchanged.
+//! Coalesced messages are sent at the end of the frame, after the frame's other messages.
+//! Topics without a policy are sent right away.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bevy::{app::App, ecs::system::Res};
+
+use crate::HostChannel;
+
+/// How messages sent on one topic reach the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SendPolicy {
+    /// Every message is sent right away
+    #[default]
+    Unlimited,
+    /// At most this many messages are sent per frame; later ones are dropped
+    MaxPerFrame(u32),
+    /// Only the last message of each frame is sent, at the end of the frame
+    KeepLatest,
+    /// The latest message is sent at the end of a frame at most once per interval
+    Interval(Duration),
+}
+
+/// Policies and their per-topic state, kept by the [`HostChannel`]
+#[derive(Default)]
+pub(crate) struct SendPolicies {
+    topics: Mutex<HashMap<u16, TopicState>>,
+}
+
+#[derive(Default)]
+struct TopicState {
+    policy: SendPolicy,
+    sent_this_frame: u32,
+    pending: Option<Vec<u8>>,
+    last_sent: Option<Instant>,
+    dropped: u64,
+}
+
+impl SendPolicies {
+    pub(crate) fn set(&self, topic: u16, policy: SendPolicy) {
+        if let Ok(mut topics) = self.topics.lock() {
+            topics.entry(topic).or_default().policy = policy;
+        }
+    }
+
+    /// Sends `data` through `send` now, holds it for the end of the frame, or drops it
+    pub(crate) fn send(&self, topic: u16, data: Vec<u8>, send: impl FnOnce(Vec<u8>)) {
+        let Ok(mut topics) = self.topics.lock() else {
+            return send(data);
+        };
+        let Some(state) = topics.get_mut(&topic) else {
+            drop(topics);
+            return send(data);
+        };
+        match state.policy {
+            SendPolicy::Unlimited => send(data),
+            SendPolicy::MaxPerFrame(max) if state.sent_this_frame < max => {
+                state.sent_this_frame += 1;
+                send(data);
+            }
+            SendPolicy::MaxPerFrame(_) => state.dropped += 1,
+            SendPolicy::KeepLatest | SendPolicy::Interval(_) => {
+                if state.pending.replace(data).is_some() {
+                    state.dropped += 1;
+                }
+            }
+        }
+    }
+
+    /// Sends the held messages that are due and starts a new frame
+    pub(crate) fn flush(&self, mut send: impl FnMut(Vec<u8>)) {
+        let Ok(mut topics) = self.topics.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        for state in topics.values_mut() {
+            state.sent_this_frame = 0;
+            let due = match state.policy {
+                SendPolicy::KeepLatest => true,
+                SendPolicy::Interval(interval) => state
+                    .last_sent
+                    .is_none_or(|last_sent| now.duration_since(last_sent) >= interval),
+                SendPolicy::Unlimited | SendPolicy::MaxPerFrame(_) => false,
+            };
+            if due && let Some(data) = state.pending.take() {
+                state.last_sent = Some(now);
+                send(data);
+            }
+        }
+    }
+
+    pub(crate) fn dropped(&self, topic: u16) -> u64 {
+        self.topics
+            .lock()
+            .ok()
+            .and_then(|topics| topics.get(&topic).map(|state| state.dropped))
+            .unwrap_or(0)
+    }
+}
+
+/// Extension trait for setting send policies while building the app
+pub trait SendPolicyApp {
+    /// Applies `policy` to messages sent with [`HostChannel::send_topic`] on `topic`
+    fn set_send_policy(&mut self, topic: u16, policy: SendPolicy) -> &mut Self;
+}
+
+impl SendPolicyApp for App {
+    fn set_send_policy(&mut self, topic: u16, policy: SendPolicy) -> &mut Self {
+        self.init_resource::<HostChannel>();
+        self.world()
+            .resource::<HostChannel>()
+            .set_send_policy(topic, policy);
+        self
+    }
+}
+
+/// Sends the messages held back by send policies at the end of each frame
+pub(crate) fn flush_send_policies(channel: Res<HostChannel>) {
+    channel.flush_topics();
+}
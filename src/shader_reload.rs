@@ -0,0 +1,326 @@
+//! Replacing shaders with WGSL source pushed by the host
+//!
+//! Iterating on a visual effect inside a host app normally means rebuilding and
+//! reinstalling the host for every change. With the `shader_reload` cargo feature, a dev
+//! tool on the host side (or a file watcher on the developer's machine, forwarded by the
+//! host) sends the new source of a shader on [`ReservedTopic::ReloadShader`]:
+//!
+//! ```text
+//! request: u32 | path: string | source: string
+//! ```
+//!
+//! `path` is the asset path the app loaded the shader from (`shaders/glow.wgsl`), or the
+//! path of an embedded shader (`embedded://my_crate/glow.wgsl`). The shader asset is
+//! replaced, Bevy recompiles every pipeline using it, and once they are done the app
+//! answers on [`ReservedTopic::ShaderReloaded`]:
+//!
+//! ```text
+//! request: u32 | status: u8 (ShaderReloadStatus) | pipelines: u32
+//!     | errors: u32 × (pipeline label: string | error: string)
+//! ```
+//!
+//! `pipelines` counts the pipelines recompiled; a shader no pipeline uses yet compiles
+//! when it is first used. A failed pipeline isn't drawn until a later reload fixes it.
+//! The replacement lasts until the app is destroyed, so fix the source in the app as well.
+//!
+//! Replacing shaders allows running arbitrary GPU code, so the feature is meant for
+//! development builds only.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    app::SubApp,
+    asset::Assets,
+    ecs::{
+        message::MessageReader,
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Res, ResMut},
+    },
+    render::{
+        ExtractSchedule, Render, RenderSystems,
+        render_resource::{CachedPipelineState, PipelineCache, PipelineDescriptor},
+    },
+    shader::{PipelineCacheError, Shader},
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Render frames a reload waits for its pipelines before reporting
+/// [`ShaderReloadStatus::TimedOut`]
+pub const MAX_RELOAD_FRAMES: u32 = 600;
+
+/// Outcome of a shader reload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ShaderReloadStatus {
+    /// Every pipeline using the shader compiled
+    Compiled = 0,
+    /// At least one pipeline failed to compile; the errors say why
+    Failed = 1,
+    /// The app has no shader with that path
+    UnknownShader = 2,
+    /// The request couldn't be decoded
+    Malformed = 3,
+    /// Pipelines still weren't compiled after [`MAX_RELOAD_FRAMES`], e.g. because the new
+    /// source imports a module that doesn't exist
+    TimedOut = 4,
+}
+
+impl ShaderReloadStatus {
+    /// Create a ShaderReloadStatus from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ShaderReloadStatus::Compiled),
+            1 => Some(ShaderReloadStatus::Failed),
+            2 => Some(ShaderReloadStatus::UnknownShader),
+            3 => Some(ShaderReloadStatus::Malformed),
+            4 => Some(ShaderReloadStatus::TimedOut),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ReloadQueue {
+    /// Replaced shaders, not yet extracted
+    replaced: Vec<u32>,
+    /// Answers of the render world, not yet sent
+    answers: Vec<Vec<u8>>,
+}
+
+/// Reloads passed between the main and render worlds
+#[derive(Resource, Clone, Default)]
+pub(crate) struct ShaderReloads {
+    queue: Arc<Mutex<ReloadQueue>>,
+}
+
+/// Replaces shaders with the source the host sent
+pub(crate) fn receive_shader_sources(
+    mut commands: MessageReader<HostCommand>,
+    mut shaders: ResMut<Assets<Shader>>,
+    reloads: Res<ShaderReloads>,
+    channel: Res<HostChannel>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::ReloadShader {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        let Some(request_id) = reader.u32() else {
+            log::warn!("Ignoring malformed shader reload");
+            continue;
+        };
+        let (Some(path), Some(source)) = (reader.string(), reader.string()) else {
+            log::warn!("Ignoring malformed shader reload");
+            send_answer(&channel, request_id, ShaderReloadStatus::Malformed, 0, &[]);
+            continue;
+        };
+        let Some((id, old)) = shaders.iter().find(|(_, shader)| shader.path == path) else {
+            log::warn!("Ignoring reload of unknown shader {path}");
+            send_answer(
+                &channel,
+                request_id,
+                ShaderReloadStatus::UnknownShader,
+                0,
+                &[],
+            );
+            continue;
+        };
+
+        let mut shader = Shader::from_wgsl(source.to_owned(), path);
+        // Keep the imports the loader resolved and the app's defines
+        shader.additional_imports = old.additional_imports.clone();
+        shader.shader_defs = old.shader_defs.clone();
+        shader.file_dependencies = old.file_dependencies.clone();
+        shader.validate_shader = old.validate_shader.clone();
+        if shaders.insert(id, shader).is_err() {
+            send_answer(
+                &channel,
+                request_id,
+                ShaderReloadStatus::UnknownShader,
+                0,
+                &[],
+            );
+            continue;
+        }
+        log::info!("Reloading shader {path}");
+        if let Ok(mut queue) = reloads.queue.lock() {
+            queue.replaced.push(request_id);
+        }
+    }
+
+    let answers = reloads
+        .queue
+        .lock()
+        .map(|mut queue| std::mem::take(&mut queue.answers))
+        .unwrap_or_default();
+    for answer in answers {
+        channel.send_reserved(ReservedTopic::ShaderReloaded, &answer);
+    }
+}
+
+fn encode_answer(
+    request_id: u32,
+    status: ShaderReloadStatus,
+    pipelines: u32,
+    errors: &[(String, String)],
+) -> Vec<u8> {
+    let mut payload = ByteWriter::new();
+    payload
+        .u32(request_id)
+        .u8(status as u8)
+        .u32(pipelines)
+        .u32(errors.len() as u32);
+    for (label, error) in errors {
+        payload.string(label).string(error);
+    }
+    payload.finish()
+}
+
+fn send_answer(
+    channel: &HostChannel,
+    request_id: u32,
+    status: ShaderReloadStatus,
+    pipelines: u32,
+    errors: &[(String, String)],
+) {
+    let answer = encode_answer(request_id, status, pipelines, errors);
+    channel.send_reserved(ReservedTopic::ShaderReloaded, &answer);
+}
+
+/// A reload waiting for the pipelines it invalidated
+struct TrackedReload {
+    request_id: u32,
+    /// Pipelines queued again when the new source was extracted
+    pipelines: Option<Vec<usize>>,
+    frames: u32,
+}
+
+#[derive(Resource, Default)]
+struct TrackedReloads {
+    reloads: Vec<TrackedReload>,
+    /// Pipelines queued again after the last frame, e.g. until their shader loads
+    retrying: HashSet<usize>,
+}
+
+/// Takes the reloads whose source is extracted in this frame
+fn extract_reloads(reloads: Res<ShaderReloads>, mut tracked: ResMut<TrackedReloads>) {
+    let Ok(mut queue) = reloads.queue.lock() else {
+        return;
+    };
+    tracked
+        .reloads
+        .extend(queue.replaced.drain(..).map(|request_id| TrackedReload {
+            request_id,
+            pipelines: None,
+            frames: 0,
+        }));
+}
+
+/// Records the pipelines the new source queued again, before the cache processes them
+fn track_reloaded_pipelines(cache: Res<PipelineCache>, mut tracked: ResMut<TrackedReloads>) {
+    if tracked
+        .reloads
+        .iter()
+        .all(|reload| reload.pipelines.is_some())
+    {
+        return;
+    }
+    let queued = queued_pipelines(&cache);
+    let TrackedReloads { reloads, retrying } = &mut *tracked;
+    for reload in reloads {
+        if reload.pipelines.is_none() {
+            reload.pipelines = Some(queued.difference(retrying).copied().collect());
+        }
+    }
+}
+
+/// Waiting pipelines the cache will create from scratch
+fn queued_pipelines(cache: &PipelineCache) -> HashSet<usize> {
+    let pipelines: Vec<_> = cache.pipelines().collect();
+    cache
+        .waiting_pipelines()
+        .filter(|&id| {
+            pipelines
+                .get(id)
+                .is_some_and(|pipeline| matches!(pipeline.state, CachedPipelineState::Queued))
+        })
+        .collect()
+}
+
+/// Answers reloads whose pipelines are all compiled or failed
+fn report_reloads(
+    cache: Res<PipelineCache>,
+    reloads: Res<ShaderReloads>,
+    mut tracked: ResMut<TrackedReloads>,
+) {
+    tracked.retrying = queued_pipelines(&cache);
+    let pipelines: Vec<_> = cache.pipelines().collect();
+    tracked.reloads.retain_mut(|reload| {
+        let ids = reload.pipelines.as_deref().unwrap_or_default();
+        reload.frames += 1;
+        let mut errors = Vec::new();
+        let mut waiting = false;
+        for pipeline in ids.iter().filter_map(|&id| pipelines.get(id)) {
+            match &pipeline.state {
+                CachedPipelineState::Ok(_) => {}
+                CachedPipelineState::Err(
+                    error @ (PipelineCacheError::ProcessShaderError(_)
+                    | PipelineCacheError::CreateShaderModule(_)),
+                ) => errors.push((label(&pipeline.descriptor), error.to_string())),
+                _ => waiting = true,
+            }
+        }
+        let status = if waiting && reload.frames < MAX_RELOAD_FRAMES {
+            return true;
+        } else if waiting {
+            ShaderReloadStatus::TimedOut
+        } else if errors.is_empty() {
+            ShaderReloadStatus::Compiled
+        } else {
+            ShaderReloadStatus::Failed
+        };
+        log::info!(
+            "Shader reload {} finished with {status:?}: {} pipelines, {} errors",
+            reload.request_id,
+            ids.len(),
+            errors.len()
+        );
+        let answer = encode_answer(reload.request_id, status, ids.len() as u32, &errors);
+        if let Ok(mut queue) = reloads.queue.lock() {
+            queue.answers.push(answer);
+        }
+        false
+    });
+}
+
+fn label(descriptor: &PipelineDescriptor) -> String {
+    let label = match descriptor {
+        PipelineDescriptor::RenderPipelineDescriptor(descriptor) => &descriptor.label,
+        PipelineDescriptor::ComputePipelineDescriptor(descriptor) => &descriptor.label,
+    };
+    label.as_deref().unwrap_or_default().to_owned()
+}
+
+/// Tracks the pipelines of reloaded shaders in the render world
+pub(crate) fn add_render_systems(render_app: &mut SubApp, reloads: ShaderReloads) {
+    render_app
+        .insert_resource(reloads)
+        .init_resource::<TrackedReloads>()
+        .add_systems(ExtractSchedule, extract_reloads)
+        .add_systems(
+            Render,
+            (
+                track_reloaded_pipelines.in_set(RenderSystems::ExtractCommands),
+                report_reloads.in_set(RenderSystems::Cleanup),
+            ),
+        );
+}
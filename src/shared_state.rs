@@ -0,0 +1,124 @@
+//! Scalar state the host writes every frame
+//!
+//! Scroll-linked and gesture-linked animations need a few floats from the host every
+//! frame (scroll offset, animation progress, slider values). Sending them as messages
+//! allocates and queues on every change; instead the host writes straight into a
+//! [`SharedStateBlock`] of [`SHARED_STATE_LEN`] floats, whose address it gets once from
+//! `bevy_embedded_ios_shared_state` (or a direct `ByteBuffer` from
+//! `nativeSharedState` on Android). The layout is plain `f32`s, so the host needs no
+//! bindings beyond the pointer.
+//!
+//! At the start of each frame the block is copied into the [`HostState`] resource, so
+//! every system sees the same values for the whole frame, and `Res<HostState>` is only
+//! marked changed when a value actually changed. Which index means what is up to the
+//! app and host. The block exists once [`SharedStatePlugin`] is added.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+
+use bevy::{
+    app::{App, Plugin},
+    ecs::{resource::Resource, system::ResMut},
+};
+
+use crate::EmbeddedInput;
+
+/// Adds [`HostState`] and its shared block; see the [module docs](self)
+#[derive(Default)]
+pub struct SharedStatePlugin;
+
+impl Plugin for SharedStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostState>()
+            .add_systems(EmbeddedInput, sync_host_state);
+    }
+}
+
+/// Number of floats in the shared state block
+pub const SHARED_STATE_LEN: usize = 64;
+
+/// Floats shared between the host and Bevy, laid out as `[f32; SHARED_STATE_LEN]`
+///
+/// Each value is stored as the bits of an `f32` in an atomic, so the host may write from
+/// another thread while Bevy reads.
+#[repr(C)]
+pub struct SharedStateBlock {
+    values: [AtomicU32; SHARED_STATE_LEN],
+}
+
+impl Default for SharedStateBlock {
+    fn default() -> Self {
+        Self {
+            values: std::array::from_fn(|_| AtomicU32::new(0.0f32.to_bits())),
+        }
+    }
+}
+
+impl SharedStateBlock {
+    /// Reads the value at `index`, or 0 if it is out of range
+    pub fn get(&self, index: usize) -> f32 {
+        self.values
+            .get(index)
+            .map_or(0.0, |value| f32::from_bits(value.load(Ordering::Relaxed)))
+    }
+
+    /// Writes the value at `index`; out of range indices are ignored
+    pub fn set(&self, index: usize, value: f32) {
+        if let Some(slot) = self.values.get(index) {
+            slot.store(value.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Address of the first float, for the host to write through
+    pub fn as_ptr(&self) -> *mut f32 {
+        self.values.as_ptr() as *mut f32
+    }
+}
+
+/// This frame's copy of the shared state block
+#[derive(Resource)]
+pub struct HostState {
+    block: Arc<SharedStateBlock>,
+    values: [f32; SHARED_STATE_LEN],
+}
+
+impl Default for HostState {
+    fn default() -> Self {
+        Self {
+            block: Arc::default(),
+            values: [0.0; SHARED_STATE_LEN],
+        }
+    }
+}
+
+impl HostState {
+    /// The value at `index` this frame, or 0 if it is out of range
+    pub fn get(&self, index: usize) -> f32 {
+        self.values.get(index).copied().unwrap_or_default()
+    }
+
+    /// All values this frame
+    pub fn values(&self) -> &[f32; SHARED_STATE_LEN] {
+        &self.values
+    }
+
+    /// The block the host writes into; writes show up at the start of the next frame
+    pub fn block(&self) -> &Arc<SharedStateBlock> {
+        &self.block
+    }
+}
+
+/// Copies the shared block into [`HostState`]
+fn sync_host_state(mut state: ResMut<HostState>) {
+    let values = std::array::from_fn(|index| state.block.get(index));
+    // Compare bits so NaNs written by the host don't count as a change every frame
+    let changed = values
+        .iter()
+        .zip(&state.values)
+        .any(|(new, old)| new.to_bits() != old.to_bits());
+    if changed {
+        state.values = values;
+    }
+}
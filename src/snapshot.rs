@@ -0,0 +1,291 @@
+//! Golden-image snapshot testing for embedded apps
+//!
+//! [`SnapshotHarness`] runs an embedded app headless on desktop, redirects every camera
+//! into an offscreen render target, drives a fixed number of frames with a fixed time
+//! step, and reads the framebuffer back so it can be compared against a reference PNG
+//! committed next to the tests.
+//!
+//! Set `BEVY_EMBEDDED_UPDATE_SNAPSHOTS=1` to (re)write reference images instead of
+//! comparing against them.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::{
+    app::{App, PostUpdate},
+    asset::{Assets, Handle, RenderAssetUsages},
+    camera::{Camera, RenderTarget},
+    ecs::{
+        observer::On,
+        query::Added,
+        resource::Resource,
+        system::{Query, Res},
+    },
+    image::{CompressedImageFormats, Image, ImageSampler, ImageType},
+    render::{
+        render_resource::TextureFormat,
+        view::window::screenshot::{Screenshot, ScreenshotCaptured},
+    },
+    time::TimeUpdateStrategy,
+};
+
+use crate::{EmbeddedApp, EmbeddedConfig, EmbeddedPlugin};
+
+/// Environment variable that switches the harness from comparing to recording
+pub const UPDATE_SNAPSHOTS_ENV: &str = "BEVY_EMBEDDED_UPDATE_SNAPSHOTS";
+
+/// Maximum number of frames to wait for a screenshot readback before giving up
+const MAX_CAPTURE_FRAMES: usize = 16;
+
+/// Offscreen image that all cameras render into while under the harness
+#[derive(Resource, Clone)]
+pub struct SnapshotTarget(pub Handle<Image>);
+
+/// Tolerances used when comparing a capture against its reference image
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotTolerance {
+    /// Largest per-channel difference (0-255) for a pixel to still count as equal
+    pub channel: u8,
+    /// Fraction of pixels (0.0-1.0) allowed to exceed `channel`
+    pub pixels: f32,
+}
+
+impl Default for SnapshotTolerance {
+    fn default() -> Self {
+        // GPU vendors disagree slightly on rasterization and filtering
+        Self {
+            channel: 2,
+            pixels: 0.001,
+        }
+    }
+}
+
+/// Drives an embedded app offscreen and compares its output with reference images
+pub struct SnapshotHarness {
+    app: App,
+    target: Handle<Image>,
+    reference_dir: PathBuf,
+    tolerance: SnapshotTolerance,
+}
+
+impl SnapshotHarness {
+    /// Creates a harness for an [`EmbeddedApp`] rendering at `width`x`height`
+    ///
+    /// The app is set up exactly as `export_embedded_app!` would, except that it runs
+    /// headless and its cameras render into an offscreen image. The app must add
+    /// `RenderPlugin` (e.g. through `DefaultPlugins`) in its `setup`.
+    pub fn new<A: EmbeddedApp>(width: u32, height: u32) -> Self {
+        A::pre_init();
+        Self::from_setup(width, height, |app| {
+            A::post_init(app);
+            A::setup(app);
+        })
+    }
+
+    /// Creates a harness from a plain setup function instead of an [`EmbeddedApp`]
+    pub fn from_setup(width: u32, height: u32, setup: impl FnOnce(&mut App)) -> Self {
+        let mut app = App::new();
+        app.insert_resource(EmbeddedConfig::headless())
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / 60.0,
+            )))
+            .add_plugins(EmbeddedPlugin);
+
+        setup(&mut app);
+
+        let image = Image::new_target_texture(width, height, TextureFormat::Rgba8UnormSrgb);
+        let target = app.world_mut().resource_mut::<Assets<Image>>().add(image);
+
+        app.insert_resource(SnapshotTarget(target.clone()))
+            .add_systems(PostUpdate, redirect_cameras_to_snapshot_target);
+
+        app.finish();
+        app.cleanup();
+
+        Self {
+            app,
+            target,
+            reference_dir: PathBuf::from("tests/snapshots"),
+            tolerance: SnapshotTolerance::default(),
+        }
+    }
+
+    /// Sets the directory reference images are read from and written to
+    ///
+    /// Defaults to `tests/snapshots`, relative to the crate being tested.
+    pub fn with_reference_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.reference_dir = dir.into();
+        self
+    }
+
+    /// Sets the comparison tolerances
+    pub fn with_tolerance(mut self, tolerance: SnapshotTolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Gives access to the app, e.g. to inject input or messages between frames
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Runs `frames` updates with a fixed 1/60s time step
+    pub fn run_frames(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.app.update();
+        }
+    }
+
+    /// Reads back the current contents of the offscreen target
+    ///
+    /// # Panics
+    ///
+    /// Panics if the GPU readback does not complete within a few frames.
+    pub fn capture(&mut self) -> Image {
+        let captured = Arc::new(Mutex::new(None));
+        let slot = captured.clone();
+
+        self.app
+            .world_mut()
+            .spawn(Screenshot::image(self.target.clone()))
+            .observe(move |event: On<ScreenshotCaptured>| {
+                if let Ok(mut slot) = slot.lock() {
+                    *slot = Some(event.image.clone());
+                }
+            });
+
+        for _ in 0..MAX_CAPTURE_FRAMES {
+            self.app.update();
+            if let Some(image) = captured.lock().ok().and_then(|mut c| c.take()) {
+                return image;
+            }
+        }
+
+        panic!("Snapshot readback did not complete within {MAX_CAPTURE_FRAMES} frames");
+    }
+
+    /// Captures the target and compares it with the reference image `name`.png
+    ///
+    /// On mismatch the capture is written next to the reference as `name.actual.png`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference is missing or differs beyond the configured tolerance. A
+    /// missing reference fails before anything is rendered; record it by running the test
+    /// with `BEVY_EMBEDDED_UPDATE_SNAPSHOTS=1` and commit the written PNG.
+    pub fn assert_snapshot(&mut self, name: &str) {
+        let reference_path = self.reference_dir.join(format!("{name}.png"));
+
+        if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            let actual = self.capture();
+            write_png(&actual, &reference_path);
+            return;
+        }
+
+        let reference = match std::fs::read(&reference_path) {
+            Ok(bytes) => decode_png(&bytes),
+            Err(e) => panic!(
+                "No reference image for snapshot `{name}` at {} ({e}). Record it by running \
+                the test with {UPDATE_SNAPSHOTS_ENV}=1 on a machine with a GPU, then commit \
+                the written PNG.",
+                reference_path.display()
+            ),
+        };
+        let actual = self.capture();
+
+        if let Err(message) = compare_images(&actual, &reference, self.tolerance) {
+            let actual_path = self.reference_dir.join(format!("{name}.actual.png"));
+            write_png(&actual, &actual_path);
+            panic!(
+                "Snapshot `{name}` does not match {}: {message} (capture written to {})",
+                reference_path.display(),
+                actual_path.display()
+            );
+        }
+    }
+}
+
+/// Points every newly spawned camera at the snapshot target
+fn redirect_cameras_to_snapshot_target(
+    target: Res<SnapshotTarget>,
+    mut cameras: Query<&mut Camera, Added<Camera>>,
+) {
+    for mut camera in &mut cameras {
+        camera.target = RenderTarget::Image(target.0.clone().into());
+    }
+}
+
+/// Compares two images pixel by pixel within the given tolerance
+pub fn compare_images(
+    actual: &Image,
+    reference: &Image,
+    tolerance: SnapshotTolerance,
+) -> Result<(), String> {
+    if actual.size() != reference.size() {
+        return Err(format!(
+            "size {} differs from reference size {}",
+            actual.size(),
+            reference.size()
+        ));
+    }
+
+    let actual = to_rgba8(actual)?;
+    let reference = to_rgba8(reference)?;
+
+    let pixel_count = actual.len() / 4;
+    let differing = actual
+        .chunks_exact(4)
+        .zip(reference.chunks_exact(4))
+        .filter(|(a, r)| {
+            a.iter()
+                .zip(r.iter())
+                .any(|(a, r)| a.abs_diff(*r) > tolerance.channel)
+        })
+        .count();
+
+    let fraction = differing as f32 / pixel_count.max(1) as f32;
+    if fraction > tolerance.pixels {
+        return Err(format!(
+            "{differing} of {pixel_count} pixels differ ({:.3}%)",
+            fraction * 100.0
+        ));
+    }
+
+    Ok(())
+}
+
+fn to_rgba8(image: &Image) -> Result<Vec<u8>, String> {
+    image
+        .clone()
+        .try_into_dynamic()
+        .map(|dynamic| dynamic.to_rgba8().into_raw())
+        .map_err(|e| format!("unsupported image format: {e}"))
+}
+
+fn decode_png(bytes: &[u8]) -> Image {
+    Image::from_buffer(
+        bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::default(),
+    )
+    .expect("Failed to decode reference image")
+}
+
+fn write_png(image: &Image, path: &Path) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    image
+        .clone()
+        .try_into_dynamic()
+        .expect("Unsupported capture format")
+        .to_rgba8()
+        .save(path)
+        .expect("Failed to write snapshot image");
+}
@@ -0,0 +1,393 @@
+//! Startup phases and deferred plugins
+//!
+//! Host apps hold embedded widgets to a time-to-first-frame budget, and a widget over it
+//! is a blank rectangle the user stares at. [`StartupTimings`] records when each
+//! [`StartupPhase`] started and how long it took, measured from the app's creation:
+//!
+//! - [`Build`](StartupPhase::Build): creating the app and building its plugins, which
+//!   includes requesting the device
+//! - [`DeviceRequest`](StartupPhase::DeviceRequest): building `RenderPlugin`, which creates
+//!   the wgpu instance, adapter and device; measured if the plugin group was configured
+//!   [`with_startup_timing`](StartupTimingPlugins::with_startup_timing)
+//! - [`Finish`](StartupPhase::Finish): finishing and cleaning up the plugins, which installs
+//!   the render device and pipeline cache
+//! - [`FirstPipeline`](StartupPhase::FirstPipeline): from then until the first render
+//!   pipeline is compiled
+//! - [`FirstFrame`](StartupPhase::FirstFrame): the first update after the plugins finished
+//! - [`DeferredPlugins`](StartupPhase::DeferredPlugins): from building the first deferred
+//!   plugin until the last one is built
+//!
+//! Hosts read the phases with `bevy_embedded_ios_startup_phase` or `nativeStartupPhase`,
+//! and with a [`startup_budget`](crate::EmbeddedConfig::startup_budget) the app logs the
+//! phases when its first frame misses the budget.
+//!
+//! Plugins the first frame doesn't need, such as audio or analytics, are added with
+//! [`add_deferred_plugin`](DeferredPluginApp::add_deferred_plugin) instead and built one per
+//! update after the first frame:
+//!
+//! ```ignore
+//! app.add_plugins(
+//!     DefaultPlugins
+//!         .build()
+//!         .disable::<WinitPlugin>()
+//!         .disable::<AudioPlugin>()
+//!         .with_startup_timing(),
+//! )
+//! .add_deferred_plugin(AudioPlugin::default());
+//! ```
+//!
+//! A deferred plugin is built, finished and cleaned up on its own, after `Startup` ran.
+//! It must not add plugins itself, which Bevy forbids once an app's plugins are finished,
+//! its `Startup` systems never run, and `App::is_plugin_added` doesn't know about it.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{App, Plugin, PluginGroupBuilder, SubApp},
+    ecs::{resource::Resource, schedule::IntoScheduleConfigs, system::Res, world::World},
+    render::{
+        Render, RenderPlugin, RenderSystems,
+        render_resource::{CachedPipelineState, Pipeline, PipelineCache},
+    },
+    tasks::tick_global_task_pools_on_main_thread,
+};
+
+use crate::EmbeddedConfig;
+
+/// Phases of an app's startup, in the order they start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum StartupPhase {
+    /// Creating the app and building its plugins
+    Build = 0,
+    /// Building `RenderPlugin`, which requests the device
+    DeviceRequest = 1,
+    /// Finishing and cleaning up the plugins
+    Finish = 2,
+    /// From finishing the plugins until the first render pipeline is compiled
+    FirstPipeline = 3,
+    /// The first update after the plugins finished
+    FirstFrame = 4,
+    /// From building the first deferred plugin until the last one is built, spanning the
+    /// updates in between
+    DeferredPlugins = 5,
+}
+
+impl StartupPhase {
+    /// Number of phases
+    pub const COUNT: usize = 6;
+
+    /// Create a StartupPhase from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(StartupPhase::Build),
+            1 => Some(StartupPhase::DeviceRequest),
+            2 => Some(StartupPhase::Finish),
+            3 => Some(StartupPhase::FirstPipeline),
+            4 => Some(StartupPhase::FirstFrame),
+            5 => Some(StartupPhase::DeferredPlugins),
+            _ => None,
+        }
+    }
+}
+
+/// When a startup phase started and how long it took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseTiming {
+    /// Time from the app's creation until the phase started
+    pub start: Duration,
+    /// Time the phase took
+    pub duration: Duration,
+}
+
+impl PhaseTiming {
+    /// Time from the app's creation until the phase ended
+    pub fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+}
+
+#[derive(Debug, Default)]
+struct Phases {
+    started: [Option<Duration>; StartupPhase::COUNT],
+    ended: [Option<Duration>; StartupPhase::COUNT],
+}
+
+/// Timings of the app's startup phases; see the [module docs](self)
+///
+/// Shared with the render world, which records the first pipeline.
+#[derive(Resource, Debug, Clone)]
+pub struct StartupTimings {
+    created: Instant,
+    phases: Arc<Mutex<Phases>>,
+}
+
+impl Default for StartupTimings {
+    fn default() -> Self {
+        let timings = Self {
+            created: Instant::now(),
+            phases: Arc::default(),
+        };
+        timings.start(StartupPhase::Build);
+        timings
+    }
+}
+
+impl StartupTimings {
+    /// Timing of `phase`, or `None` until it has ended
+    pub fn phase(&self, phase: StartupPhase) -> Option<PhaseTiming> {
+        let phases = self.phases.lock().ok()?;
+        let start = phases.started[phase as usize]?;
+        let end = phases.ended[phase as usize]?;
+        Some(PhaseTiming {
+            start,
+            duration: end.saturating_sub(start),
+        })
+    }
+
+    /// Time from the app's creation until its first frame was rendered
+    pub fn time_to_first_frame(&self) -> Option<Duration> {
+        self.phase(StartupPhase::FirstFrame)
+            .map(|timing| timing.end())
+    }
+
+    /// Whether `phase` has ended
+    pub fn has_ended(&self, phase: StartupPhase) -> bool {
+        self.phases
+            .lock()
+            .is_ok_and(|phases| phases.ended[phase as usize].is_some())
+    }
+
+    /// Records that `phase` started now, unless it already did
+    fn start(&self, phase: StartupPhase) {
+        self.start_at(phase, self.created.elapsed());
+    }
+
+    fn start_at(&self, phase: StartupPhase, at: Duration) {
+        if let Ok(mut phases) = self.phases.lock() {
+            phases.started[phase as usize].get_or_insert(at);
+        }
+    }
+
+    /// Records that `phase` ended now, unless it already did
+    fn end(&self, phase: StartupPhase) {
+        let now = self.created.elapsed();
+        if let Ok(mut phases) = self.phases.lock()
+            && phases.started[phase as usize].is_some()
+        {
+            phases.ended[phase as usize].get_or_insert(now);
+        }
+    }
+
+    /// Time from the app's creation until `phase` ended
+    fn ended(&self, phase: StartupPhase) -> Option<Duration> {
+        self.phases.lock().ok()?.ended[phase as usize]
+    }
+}
+
+/// Measures the device request of a plugin group's `RenderPlugin`
+pub trait StartupTimingPlugins {
+    /// Records [`StartupPhase::DeviceRequest`] around the group's `RenderPlugin`
+    ///
+    /// Does nothing if the group has no `RenderPlugin`.
+    fn with_startup_timing(self) -> Self;
+}
+
+impl StartupTimingPlugins for PluginGroupBuilder {
+    fn with_startup_timing(self) -> Self {
+        if !self.contains::<RenderPlugin>() {
+            return self;
+        }
+        self.add_before::<RenderPlugin>(DeviceRequestStarted)
+            .add_after::<RenderPlugin>(DeviceRequestEnded)
+    }
+}
+
+/// Starts [`StartupPhase::DeviceRequest`] when built
+struct DeviceRequestStarted;
+
+impl Plugin for DeviceRequestStarted {
+    fn build(&self, app: &mut App) {
+        if let Some(timings) = app.world().get_resource::<StartupTimings>() {
+            timings.start(StartupPhase::DeviceRequest);
+        }
+    }
+}
+
+/// Ends [`StartupPhase::DeviceRequest`] when built
+struct DeviceRequestEnded;
+
+impl Plugin for DeviceRequestEnded {
+    fn build(&self, app: &mut App) {
+        if let Some(timings) = app.world().get_resource::<StartupTimings>() {
+            timings.end(StartupPhase::DeviceRequest);
+        }
+    }
+}
+
+/// Plugins built after the first frame, oldest first
+#[derive(Resource, Default)]
+struct DeferredPlugins(Vec<Box<dyn Plugin>>);
+
+/// Defers plugins the first frame doesn't need
+pub trait DeferredPluginApp {
+    /// Builds `plugin` after the first frame instead of with the app
+    ///
+    /// Deferred plugins are built one per update, in the order they were added. See the
+    /// [module docs](self) for what they can't do.
+    fn add_deferred_plugin(&mut self, plugin: impl Plugin) -> &mut Self;
+}
+
+impl DeferredPluginApp for App {
+    fn add_deferred_plugin(&mut self, plugin: impl Plugin) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<DeferredPlugins>()
+            .0
+            .push(Box::new(plugin));
+        self
+    }
+}
+
+/// Records the end of the app's creation; called once `setup` returned
+#[doc(hidden)]
+pub fn record_build_end(app: &App) {
+    if let Some(timings) = app.world().get_resource::<StartupTimings>() {
+        timings.end(StartupPhase::Build);
+    }
+}
+
+/// Starts [`StartupPhase::Finish`], before the plugins are finished
+pub(crate) fn finish_started(world: &World) {
+    if let Some(timings) = world.get_resource::<StartupTimings>() {
+        timings.start(StartupPhase::Finish);
+    }
+}
+
+/// Ends [`StartupPhase::Finish`], once the plugins are cleaned up
+pub(crate) fn finish_ended(world: &World) {
+    if let Some(timings) = world.get_resource::<StartupTimings>() {
+        timings.end(StartupPhase::Finish);
+    }
+}
+
+/// Builds the next deferred plugin, once the first frame was rendered
+pub(crate) fn build_deferred_plugin(app: &mut App) {
+    let Some(timings) = app.world().get_resource::<StartupTimings>().cloned() else {
+        return;
+    };
+    if !timings.has_ended(StartupPhase::FirstFrame) {
+        return;
+    }
+    let Some(plugin) = app
+        .world_mut()
+        .get_resource_mut::<DeferredPlugins>()
+        .and_then(|mut deferred| (!deferred.0.is_empty()).then(|| deferred.0.remove(0)))
+    else {
+        return;
+    };
+
+    timings.start(StartupPhase::DeferredPlugins);
+    log::debug!("Building deferred plugin {}", plugin.name());
+    plugin.build(app);
+    while !plugin.ready(app) {
+        tick_global_task_pools_on_main_thread();
+    }
+    plugin.finish(app);
+    plugin.cleanup(app);
+
+    let done = app
+        .world()
+        .get_resource::<DeferredPlugins>()
+        .is_none_or(|deferred| deferred.0.is_empty());
+    if done {
+        timings.end(StartupPhase::DeferredPlugins);
+    }
+}
+
+/// Records the first update after the plugins finished, which started at `started`
+pub(crate) fn record_frame(world: &World, started: Instant) {
+    let Some(timings) = world.get_resource::<StartupTimings>() else {
+        return;
+    };
+    if !timings.has_ended(StartupPhase::Finish) || timings.has_ended(StartupPhase::FirstFrame) {
+        return;
+    }
+    timings.start_at(
+        StartupPhase::FirstFrame,
+        started.saturating_duration_since(timings.created),
+    );
+    timings.end(StartupPhase::FirstFrame);
+
+    let Some(time_to_first_frame) = timings.time_to_first_frame() else {
+        return;
+    };
+    let budget = world
+        .get_resource::<EmbeddedConfig>()
+        .and_then(|config| config.startup_budget);
+    match budget {
+        Some(budget) if time_to_first_frame > budget => {
+            log::warn!(
+                "First frame after {} ms, over the startup budget of {} ms: {}",
+                time_to_first_frame.as_millis(),
+                budget.as_millis(),
+                phase_summary(timings)
+            );
+        }
+        _ => log::info!(
+            "First frame after {} ms: {}",
+            time_to_first_frame.as_millis(),
+            phase_summary(timings)
+        ),
+    }
+}
+
+/// Durations of the phases that ended, for logs
+fn phase_summary(timings: &StartupTimings) -> String {
+    [
+        (StartupPhase::Build, "build"),
+        (StartupPhase::DeviceRequest, "device request"),
+        (StartupPhase::Finish, "finish"),
+        (StartupPhase::FirstPipeline, "first pipeline"),
+        (StartupPhase::FirstFrame, "first frame"),
+    ]
+    .into_iter()
+    .filter_map(|(phase, name)| {
+        let timing = timings.phase(phase)?;
+        Some(format!("{name} {} ms", timing.duration.as_millis()))
+    })
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Records the first compiled render pipeline in the render world
+pub(crate) fn add_render_systems(render_app: &mut SubApp, timings: StartupTimings) {
+    render_app
+        .insert_resource(timings)
+        .add_systems(Render, record_first_pipeline.in_set(RenderSystems::Cleanup));
+}
+
+fn record_first_pipeline(timings: Res<StartupTimings>, pipeline_cache: Option<Res<PipelineCache>>) {
+    if timings.has_ended(StartupPhase::FirstPipeline) {
+        return;
+    }
+    let Some(pipeline_cache) = pipeline_cache else {
+        return;
+    };
+    let compiled = pipeline_cache.pipelines().any(|pipeline| {
+        matches!(
+            pipeline.state,
+            CachedPipelineState::Ok(Pipeline::RenderPipeline(_))
+        )
+    });
+    if !compiled {
+        return;
+    }
+    // Pipelines are queued once the plugins finished
+    if let Some(finished) = timings.ended(StartupPhase::Finish) {
+        timings.start_at(StartupPhase::FirstPipeline, finished);
+        timings.end(StartupPhase::FirstPipeline);
+    }
+}
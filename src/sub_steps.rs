@@ -0,0 +1,123 @@
+//! Host-driven sub-steps of a frame
+//!
+//! `bevy_embedded_update` runs a whole frame: the main schedule, then extraction and
+//! rendering. Hosts with their own loop structure, such as a game server ticking the
+//! simulation or an audio engine driving it from its render callback, can instead run
+//! the parts they need with `bevy_embedded_run_schedule`: simulate with
+//! [`Main`](HostSchedule::Main) on every tick and [`Render`](HostSchedule::Render) only
+//! when a frame is displayed, or run an extra [`FixedStep`](HostSchedule::FixedStep) to
+//! catch up after a stall.
+//!
+//! Sub-steps bypass picture-in-picture pacing and dynamic resolution, which measure
+//! whole updates. Change detection ticks of the main world are cleared after rendering,
+//! or after [`Main`](HostSchedule::Main) in apps without a render world, so systems
+//! extracting changes see everything that changed since the previous frame.
+
+use bevy::{
+    app::{App, First, FixedMain, Last, Main, PostUpdate, PreUpdate, Update},
+    ecs::{
+        schedule::{ScheduleLabel, Schedules},
+        world::World,
+    },
+    time::{Fixed, Time, Virtual},
+};
+
+use crate::EmbeddedInput;
+
+/// Parts of a frame the host can run on their own
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostSchedule {
+    /// The whole main schedule, from input to `Last`, without rendering
+    Main = 0,
+    /// Extraction and rendering of the main world's current state
+    Render = 1,
+    /// Delivery of queued host input
+    Input = 2,
+    /// `First`, which advances time
+    First = 3,
+    /// `PreUpdate`
+    PreUpdate = 4,
+    /// One fixed timestep of `FixedMain`, in addition to those `Main` runs
+    FixedStep = 5,
+    /// `Update`
+    Update = 6,
+    /// `PostUpdate`
+    PostUpdate = 7,
+    /// `Last`
+    Last = 8,
+}
+
+impl HostSchedule {
+    /// Converts the host's raw schedule value
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Main),
+            1 => Some(Self::Render),
+            2 => Some(Self::Input),
+            3 => Some(Self::First),
+            4 => Some(Self::PreUpdate),
+            5 => Some(Self::FixedStep),
+            6 => Some(Self::Update),
+            7 => Some(Self::PostUpdate),
+            8 => Some(Self::Last),
+            _ => None,
+        }
+    }
+
+    /// Runs this part of a frame, returning false if the app doesn't have it
+    pub fn run(self, app: &mut App) -> bool {
+        match self {
+            Self::Main => {
+                if !app.world().resource::<Schedules>().contains(Main) {
+                    return false;
+                }
+                app.main_mut().run_default_schedule();
+                if app.sub_apps().sub_apps.is_empty() {
+                    app.world_mut().clear_trackers();
+                }
+                true
+            }
+            Self::Render => {
+                let sub_apps = app.sub_apps_mut();
+                if sub_apps.sub_apps.is_empty() {
+                    return false;
+                }
+                for sub_app in sub_apps.sub_apps.values_mut() {
+                    sub_app.extract(sub_apps.main.world_mut());
+                    sub_app.update();
+                }
+                sub_apps.main.world_mut().clear_trackers();
+                true
+            }
+            Self::Input => run_schedule(app.world_mut(), EmbeddedInput),
+            Self::First => run_schedule(app.world_mut(), First),
+            Self::PreUpdate => run_schedule(app.world_mut(), PreUpdate),
+            Self::FixedStep => run_fixed_step(app.world_mut()),
+            Self::Update => run_schedule(app.world_mut(), Update),
+            Self::PostUpdate => run_schedule(app.world_mut(), PostUpdate),
+            Self::Last => run_schedule(app.world_mut(), Last),
+        }
+    }
+}
+
+fn run_schedule(world: &mut World, label: impl ScheduleLabel) -> bool {
+    world.try_run_schedule(label).is_ok()
+}
+
+/// Runs `FixedMain` once, seeing `Time<Fixed>` advanced by one timestep
+fn run_fixed_step(world: &mut World) -> bool {
+    if !world.contains_resource::<Time>() || !world.contains_resource::<Time<Virtual>>() {
+        return false;
+    }
+    let Some(mut fixed) = world.get_resource_mut::<Time<Fixed>>() else {
+        return false;
+    };
+    let timestep = fixed.timestep();
+    fixed.advance_by(timestep);
+
+    *world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+    let ran = run_schedule(world, FixedMain);
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+    ran
+}
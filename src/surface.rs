@@ -0,0 +1,108 @@
+//! Moving windows between host surfaces
+//!
+//! A host can hand Bevy a different native surface at runtime, for example when the
+//! widget is expanded to a full-screen presentation and later returned inline. The
+//! `Window` entity and the rest of the `App` are kept; only the render surface (and
+//! with it the swapchain) is recreated on the next frame.
+//!
+//! To shut down, the host closes the window first: `bevy_embedded_ios_close_window` /
+//! `nativeCloseWindow` send `WindowCloseRequested`, as a desktop window's close button
+//! would. With `WindowPlugin::close_when_requested`, the window is despawned over the next
+//! two updates, which send `WindowClosing` and `WindowClosed` like `WinitPlugin` does, and
+//! `exit_on_all_closed` ends the app. Systems saving state or saying goodbye to a server
+//! run in those updates, and the host destroys the app once the window is closed. A host
+//! that changes its mind opens a new window on a surface with
+//! `bevy_embedded_ios_open_window` / `nativeOpenWindow`.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    ecs::{
+        entity::Entity, lifecycle::RemovedComponents, message::MessageWriter, query::With,
+        resource::Resource, system::Query, world::World,
+    },
+    window::{
+        ClosingWindow, RawHandleWrapper, RawHandleWrapperHolder, Window, WindowClosed,
+        WindowClosing,
+    },
+};
+
+/// How the host is currently presenting the primary window
+///
+/// Updated when the host replaces the surface; apps can react with
+/// `resource_changed::<SurfacePresentation>`, e.g. to show a close button in full screen.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum SurfacePresentation {
+    /// Embedded inline in the host's layout
+    #[default]
+    Inline = 0,
+    /// Taking over the whole screen
+    Fullscreen = 1,
+    /// In a small floating picture-in-picture window, rendered in low-power mode
+    PictureInPicture = 2,
+}
+
+impl SurfacePresentation {
+    /// Create a SurfacePresentation from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SurfacePresentation::Inline),
+            1 => Some(SurfacePresentation::Fullscreen),
+            2 => Some(SurfacePresentation::PictureInPicture),
+            _ => None,
+        }
+    }
+}
+
+/// Points a window entity at a new native surface
+///
+/// The old `RawHandleWrapper` is removed, which makes the renderer drop the old surface
+/// and swapchain during the next extract; a new one is created from `handle` the frame
+/// after. The host must keep the old native surface alive until the next update returns.
+pub fn replace_window_surface(world: &mut World, window: Entity, handle: RawHandleWrapper) {
+    let Ok(mut entity) = world.get_entity_mut(window) else {
+        log::warn!("Cannot replace surface of missing window {window}");
+        return;
+    };
+
+    let holder = RawHandleWrapperHolder(Arc::new(Mutex::new(Some(handle.clone()))));
+    entity.remove::<RawHandleWrapper>();
+    entity.insert((handle, holder));
+}
+
+/// Detaches a window entity from its native surface without giving it a new one
+///
+/// The renderer drops the surface and swapchain during the next extract and skips the
+/// window until [`replace_window_surface`] gives it a surface again. The host must keep
+/// the native surface alive until the next update returns.
+pub fn release_window_surface(world: &mut World, window: Entity) {
+    let Ok(mut entity) = world.get_entity_mut(window) else {
+        log::warn!("Cannot release surface of missing window {window}");
+        return;
+    };
+
+    entity.remove::<(RawHandleWrapper, RawHandleWrapperHolder)>();
+}
+
+/// Sends the messages `WinitPlugin` sends for windows that are closing and closed
+///
+/// The renderer drops the surface of a window when it reads `WindowClosing`.
+pub(crate) fn report_closed_windows(
+    closing: Query<Entity, With<ClosingWindow>>,
+    mut removed: RemovedComponents<Window>,
+    windows: Query<(), With<Window>>,
+    mut closing_writer: MessageWriter<WindowClosing>,
+    mut closed_writer: MessageWriter<WindowClosed>,
+) {
+    for window in &closing {
+        closing_writer.write(WindowClosing { window });
+    }
+    for window in removed.read() {
+        // The component may have been removed and added again in the same frame
+        if !windows.contains(window) {
+            log::info!("Closed window {window}");
+            closed_writer.write(WindowClosed { window });
+        }
+    }
+}
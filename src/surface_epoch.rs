@@ -0,0 +1,160 @@
+//! Keeping surface changes from racing frames still being rendered
+//!
+//! With pipelined rendering, frame N is rendered on the render thread while the host
+//! already runs update N+1, and it may still be acquiring or presenting a swapchain image
+//! when the host resizes, replaces or tears down the surface. Every host surface change
+//! advances the [`SurfaceEpoch`]: frames extracted before the change that haven't reached
+//! the swapchain yet are canceled, and the FFI call blocks until frames already past that
+//! point are done, so the host never frees a surface Bevy is presenting to.
+//!
+//! Without pipelined rendering frames are rendered within the update, so there is
+//! nothing to wait for.
+
+use std::{
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use bevy::{
+    app::SubApp,
+    ecs::{
+        entity::EntityHashMap,
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Res, ResMut},
+    },
+    render::{
+        ExtractSchedule, Render, RenderSystems,
+        view::{
+            ExtractedWindow, ExtractedWindows,
+            window::{create_surfaces, prepare_windows},
+        },
+    },
+};
+
+/// How long a surface change waits for in-flight frames before going ahead anyway
+pub(crate) const SURFACE_WAIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Counts host surface changes and the frames rendered for them
+///
+/// Shared by the main and render worlds.
+#[derive(Resource, Clone, Default)]
+pub struct SurfaceEpoch {
+    inner: Arc<EpochInner>,
+}
+
+#[derive(Default)]
+struct EpochInner {
+    /// Incremented with every host surface change
+    epoch: AtomicU64,
+    frames: Mutex<FrameCounts>,
+    finished: Condvar,
+}
+
+#[derive(Default)]
+struct FrameCounts {
+    extracted: u64,
+    finished: u64,
+}
+
+impl SurfaceEpoch {
+    /// The number of surface changes so far
+    pub fn epoch(&self) -> u64 {
+        self.inner.epoch.load(Ordering::Acquire)
+    }
+
+    /// Returns true if no extracted frame is still being rendered
+    pub fn is_idle(&self) -> bool {
+        self.inner
+            .frames
+            .lock()
+            .is_ok_and(|frames| frames.finished >= frames.extracted)
+    }
+
+    /// Blocks until every extracted frame is rendered or canceled, returning false on
+    /// timeout
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let Ok(frames) = self.inner.frames.lock() else {
+            return false;
+        };
+        self.inner
+            .finished
+            .wait_timeout_while(frames, timeout, |frames| frames.finished < frames.extracted)
+            .is_ok_and(|(_, result)| !result.timed_out())
+    }
+
+    /// Starts a new epoch, canceling frames of earlier ones that haven't started
+    /// presenting
+    pub(crate) fn advance(&self) -> u64 {
+        self.inner.epoch.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    /// Marks every extracted frame as done and wakes surface changes waiting for them
+    pub(crate) fn finish_frames(&self) {
+        if let Ok(mut frames) = self.inner.frames.lock() {
+            frames.finished = frames.extracted;
+        }
+        self.inner.finished.notify_all();
+    }
+}
+
+/// Epoch the frame in the render world was extracted in
+#[derive(Resource, Default)]
+pub(crate) struct FrameEpoch(u64);
+
+/// Windows of a canceled frame, put back once the frame is done
+#[derive(Resource, Default)]
+pub(crate) struct CanceledWindows(EntityHashMap<ExtractedWindow>);
+
+/// Stamps the extracted frame with the current epoch
+fn extract_frame_epoch(surface_epoch: Res<SurfaceEpoch>, mut frame: ResMut<FrameEpoch>) {
+    frame.0 = surface_epoch.epoch();
+    if let Ok(mut frames) = surface_epoch.inner.frames.lock() {
+        frames.extracted += 1;
+    }
+}
+
+/// Hides the windows of frames extracted before the latest surface change, so the
+/// frame doesn't touch their swapchains
+pub(crate) fn cancel_stale_frame(
+    surface_epoch: Res<SurfaceEpoch>,
+    frame: Res<FrameEpoch>,
+    mut windows: ResMut<ExtractedWindows>,
+    mut canceled: ResMut<CanceledWindows>,
+) {
+    if frame.0 != surface_epoch.epoch() && !windows.is_empty() {
+        log::debug!("Skipping presentation of a frame extracted before a surface change");
+        canceled.0 = std::mem::take(&mut windows.windows);
+    }
+}
+
+/// Restores canceled windows and wakes surface changes waiting for the frame
+fn finish_frame(
+    surface_epoch: Res<SurfaceEpoch>,
+    mut windows: ResMut<ExtractedWindows>,
+    mut canceled: ResMut<CanceledWindows>,
+) {
+    if !canceled.0.is_empty() {
+        windows.windows = std::mem::take(&mut canceled.0);
+    }
+    surface_epoch.finish_frames();
+}
+
+/// Tracks the epoch of frames in the render world
+pub(crate) fn add_render_systems(render_app: &mut SubApp, surface_epoch: SurfaceEpoch) {
+    render_app
+        .insert_resource(surface_epoch)
+        .init_resource::<FrameEpoch>()
+        .init_resource::<CanceledWindows>()
+        .add_systems(ExtractSchedule, extract_frame_epoch)
+        .add_systems(
+            Render,
+            cancel_stale_frame
+                .before(create_surfaces)
+                .before(prepare_windows),
+        )
+        .add_systems(Render, finish_frame.in_set(RenderSystems::Cleanup));
+}
@@ -0,0 +1,125 @@
+//! Rotation and mirroring between the host surface and Bevy's content
+//!
+//! Some hosts present a surface whose orientation differs from the content: a
+//! portrait-locked widget drawn into a landscape surface, or an Android swapchain that
+//! is pre-rotated to the display's native orientation. Bevy always renders in the
+//! content orientation; the host presents the result with [`SurfaceTransform`] applied
+//! (e.g. a `CALayer` or `TextureView` transform), and touches are mapped back through
+//! its inverse before they reach Bevy.
+
+use bevy::{ecs::resource::Resource, math::Vec2};
+
+/// Clockwise rotation applied to the content when it is presented on the host surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum SurfaceRotation {
+    /// No rotation
+    #[default]
+    None = 0,
+    /// Rotated 90 degrees clockwise
+    Deg90 = 1,
+    /// Rotated 180 degrees
+    Deg180 = 2,
+    /// Rotated 270 degrees clockwise
+    Deg270 = 3,
+}
+
+impl SurfaceRotation {
+    /// Create a SurfaceRotation from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SurfaceRotation::None),
+            1 => Some(SurfaceRotation::Deg90),
+            2 => Some(SurfaceRotation::Deg180),
+            3 => Some(SurfaceRotation::Deg270),
+            _ => None,
+        }
+    }
+
+    /// Rotation in radians, clockwise
+    pub fn radians(self) -> f32 {
+        self as u8 as f32 * std::f32::consts::FRAC_PI_2
+    }
+}
+
+/// Transform from Bevy's content to the host surface
+///
+/// The content is mirrored horizontally first (if `mirror` is set) and then rotated.
+/// Initialised from [`EmbeddedConfig::surface_transform`](crate::EmbeddedConfig) and
+/// updated at runtime with `bevy_embedded_ios_set_surface_transform` /
+/// `nativeSetSurfaceTransform`.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SurfaceTransform {
+    /// Rotation applied after mirroring
+    pub rotation: SurfaceRotation,
+    /// Mirror the content horizontally
+    pub mirror: bool,
+}
+
+impl SurfaceTransform {
+    /// Returns true if the transform does nothing
+    pub fn is_identity(&self) -> bool {
+        self.rotation == SurfaceRotation::None && !self.mirror
+    }
+
+    /// Returns true if width and height differ between surface and content
+    pub fn swaps_axes(&self) -> bool {
+        matches!(
+            self.rotation,
+            SurfaceRotation::Deg90 | SurfaceRotation::Deg270
+        )
+    }
+
+    /// Size of the content for a host surface of `surface_size`
+    pub fn content_size(&self, surface_size: Vec2) -> Vec2 {
+        if self.swaps_axes() {
+            Vec2::new(surface_size.y, surface_size.x)
+        } else {
+            surface_size
+        }
+    }
+
+    /// Size of the host surface for content of `content_size`
+    pub fn surface_size(&self, content_size: Vec2) -> Vec2 {
+        // Swapping axes is its own inverse
+        self.content_size(content_size)
+    }
+
+    /// Maps a position on the host surface into content coordinates
+    pub fn surface_to_content(&self, position: Vec2, surface_size: Vec2) -> Vec2 {
+        let content = match self.rotation {
+            SurfaceRotation::None => position,
+            SurfaceRotation::Deg90 => Vec2::new(position.y, surface_size.x - position.x),
+            SurfaceRotation::Deg180 => surface_size - position,
+            SurfaceRotation::Deg270 => Vec2::new(surface_size.y - position.y, position.x),
+        };
+        if self.mirror {
+            let content_size = self.content_size(surface_size);
+            Vec2::new(content_size.x - content.x, content.y)
+        } else {
+            content
+        }
+    }
+
+    /// Maps an angle on the host surface, in radians clockwise from vertical, into
+    /// content coordinates
+    pub fn surface_to_content_angle(&self, angle: f32) -> f32 {
+        let content = angle - self.rotation.radians();
+        if self.mirror { -content } else { content }
+    }
+
+    /// Maps a content position onto the host surface
+    pub fn content_to_surface(&self, position: Vec2, content_size: Vec2) -> Vec2 {
+        let position = if self.mirror {
+            Vec2::new(content_size.x - position.x, position.y)
+        } else {
+            position
+        };
+        match self.rotation {
+            SurfaceRotation::None => position,
+            SurfaceRotation::Deg90 => Vec2::new(content_size.y - position.y, position.x),
+            SurfaceRotation::Deg180 => content_size - position,
+            SurfaceRotation::Deg270 => Vec2::new(position.y, content_size.x - position.x),
+        }
+    }
+}
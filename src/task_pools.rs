@@ -0,0 +1,86 @@
+//! Sizing Bevy's task pools for a widget sharing cores with its host
+//!
+//! Bevy sizes its task pools for a game owning the device: compute threads on every core
+//! the IO and async compute pools leave over. Inside a host app, those threads compete with
+//! the host's UI and render threads, and a phone's efficiency cores make them contend with
+//! each other. `EmbeddedPlugin` creates the pools with the small thread counts of
+//! [`EmbeddedConfig::task_pools`](crate::EmbeddedConfig::task_pools) before
+//! `TaskPoolPlugin` builds, which then keeps them. Hosts override the counts with the
+//! `compute_threads`, `async_compute_threads` and `io_threads` creation config keys.
+//!
+//! Bevy's task pools are process-wide: the first app created in the process sizes them,
+//! and later apps share them whatever their config says.
+
+use bevy::{
+    app::{TaskPoolOptions, TaskPoolThreadAssignmentPolicy},
+    tasks::{AsyncComputeTaskPool, ComputeTaskPool, IoTaskPool},
+};
+
+/// Threads of each of Bevy's task pools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskPoolSizes {
+    /// Threads running systems and parallel queries, besides the thread updating the app
+    pub compute: usize,
+    /// Threads for background work such as pipeline compilation
+    pub async_compute: usize,
+    /// Threads loading assets and other blocking IO
+    pub io: usize,
+}
+
+impl Default for TaskPoolSizes {
+    fn default() -> Self {
+        Self {
+            compute: 2,
+            async_compute: 1,
+            io: 1,
+        }
+    }
+}
+
+/// A policy that always assigns `threads` threads
+fn exactly(threads: usize) -> TaskPoolThreadAssignmentPolicy {
+    TaskPoolThreadAssignmentPolicy {
+        min_threads: threads,
+        max_threads: threads,
+        percent: 0.0,
+        on_thread_spawn: None,
+        on_thread_destroy: None,
+    }
+}
+
+/// Creates Bevy's task pools with `sizes` unless an earlier app in the process did
+pub(crate) fn init_task_pools(sizes: TaskPoolSizes) {
+    if let (Some(compute), Some(async_compute), Some(io)) = (
+        ComputeTaskPool::try_get(),
+        AsyncComputeTaskPool::try_get(),
+        IoTaskPool::try_get(),
+    ) {
+        log::debug!(
+            "Sharing task pools of {} compute, {} async compute and {} IO threads",
+            compute.thread_num(),
+            async_compute.thread_num(),
+            io.thread_num()
+        );
+        return;
+    }
+
+    let sizes = TaskPoolSizes {
+        compute: sizes.compute.max(1),
+        async_compute: sizes.async_compute.max(1),
+        io: sizes.io.max(1),
+    };
+    TaskPoolOptions {
+        min_total_threads: 1,
+        max_total_threads: usize::MAX,
+        io: exactly(sizes.io),
+        async_compute: exactly(sizes.async_compute),
+        compute: exactly(sizes.compute),
+    }
+    .create_default_pools();
+    log::info!(
+        "Created task pools of {} compute, {} async compute and {} IO threads",
+        sizes.compute,
+        sizes.async_compute,
+        sizes.io
+    );
+}
@@ -0,0 +1,240 @@
+//! Shutting apps down before they are destroyed
+//!
+//! Dropping an app mid-frame leaves its GPU work in flight and gives its systems no
+//! chance to save state. Destroying an app therefore takes two phases:
+//!
+//! 1. Exit is requested: the primary window is [closed](crate::ffi::close_window), or
+//!    `AppExit::Success` is written in apps without one
+//! 2. Final updates run until the window is gone, then the app waits for the GPU and is
+//!    dropped
+//!
+//! The final updates stop after [`EmbeddedConfig::teardown_timeout`] or
+//! [`MAX_TEARDOWN_UPDATES`], e.g. when the app keeps its window open, and are skipped for
+//! frozen apps and apps whose plugins never finished. `bevy_embedded_destroy` runs both
+//! phases before returning. `bevy_embedded_destroy_async` only requests exit; the host's
+//! next `bevy_embedded_update` calls run the final updates, and the one that drops the
+//! app calls the host's callback, after which the app pointer is invalid. Hosts remove
+//! the app's view from the callback.
+//!
+//! [`EmbeddedConfig::teardown_timeout`]: crate::EmbeddedConfig::teardown_timeout
+
+use std::{
+    ffi::c_void,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::{App, AppExit},
+    ecs::resource::Resource,
+    render::{RenderApp, render_resource::PollType, renderer::RenderDevice},
+};
+
+use crate::{EmbeddedConfig, InitProgress, ffi, recovery};
+
+/// Time the final updates of a teardown may take unless the app configures it
+pub const DEFAULT_TEARDOWN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Final updates a teardown runs at most
+pub const MAX_TEARDOWN_UPDATES: u32 = 10;
+
+/// Called once an app destroyed with `bevy_embedded_destroy_async` is dropped
+pub type DestroyedCallback = unsafe extern "C" fn(user_data: *mut c_void);
+
+/// A teardown in progress
+#[derive(Resource, Clone, Copy)]
+struct Teardown {
+    started: Instant,
+    timeout: Duration,
+    updates: u32,
+    on_destroyed: Option<(DestroyedCallback, usize)>,
+}
+
+/// Notifies the host that its app was dropped
+pub struct DestroyedNotice {
+    callback: DestroyedCallback,
+    user_data: usize,
+}
+
+impl DestroyedNotice {
+    /// Calls the host's callback
+    ///
+    /// # Safety
+    ///
+    /// The callback must be safe to call with the host's `user_data` on this thread.
+    pub unsafe fn notify(self) {
+        unsafe { (self.callback)(self.user_data as *mut c_void) };
+    }
+}
+
+/// Requests the app to exit, starting its teardown
+///
+/// Does nothing if a teardown already started. `on_destroyed` is called with `user_data`
+/// once the app is dropped.
+pub fn begin(app: &mut App, on_destroyed: Option<DestroyedCallback>, user_data: *mut c_void) {
+    if in_progress(app) {
+        return;
+    }
+    let timeout = app
+        .world()
+        .get_resource::<EmbeddedConfig>()
+        .and_then(|config| config.teardown_timeout)
+        .unwrap_or(DEFAULT_TEARDOWN_TIMEOUT);
+    if !ffi::close_window(app) {
+        app.world_mut().write_message(AppExit::Success);
+    }
+    app.insert_resource(Teardown {
+        started: Instant::now(),
+        timeout,
+        updates: 0,
+        on_destroyed: on_destroyed.map(|callback| (callback, user_data as usize)),
+    });
+    log::info!("Tearing down app");
+}
+
+/// Whether the app's teardown started
+pub fn in_progress(app: &App) -> bool {
+    app.world().contains_resource::<Teardown>()
+}
+
+/// Runs the next final update, returning true once the app can be dropped
+pub fn step(app: &mut App) -> bool {
+    // Nothing ran yet, or nothing runs anymore
+    if InitProgress::of(app) != InitProgress::Finished || recovery::is_frozen(app) {
+        return true;
+    }
+    let Some(&teardown) = app.world().get_resource::<Teardown>() else {
+        return true;
+    };
+    if teardown.updates > 0 && ffi::window_closed(app) {
+        return true;
+    }
+    if teardown.updates >= MAX_TEARDOWN_UPDATES || teardown.started.elapsed() >= teardown.timeout {
+        log::warn!(
+            "App teardown timed out after {} updates; dropping it with its window open",
+            teardown.updates
+        );
+        return true;
+    }
+
+    ffi::update(app);
+    app.world_mut().resource_mut::<Teardown>().updates += 1;
+    false
+}
+
+/// Runs the whole teardown, returning the host's callback to call once the app is dropped
+///
+/// Continues a teardown started by [`begin`], or starts one without a callback.
+pub fn shutdown(app: &mut App) -> Option<DestroyedNotice> {
+    begin(app, None, std::ptr::null_mut());
+    while !step(app) {}
+
+    // Frames still on the GPU use the app's resources and surfaces
+    if let Some(render_device) = app
+        .get_sub_app(RenderApp)
+        .and_then(|render_app| render_app.world().get_resource::<RenderDevice>())
+        && let Err(error) = render_device.poll(PollType::Wait)
+    {
+        log::warn!("Waiting for the GPU before destroying the app failed: {error}");
+    }
+
+    let teardown = app.world_mut().remove_resource::<Teardown>()?;
+    log::info!("Tore down app in {} updates", teardown.updates);
+    let (callback, user_data) = teardown.on_destroyed?;
+    Some(DestroyedNotice {
+        callback,
+        user_data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use bevy::{MinimalPlugins, app::Last, ecs::system::ResMut, window::WindowPlugin};
+
+    use super::*;
+    use crate::test::{MockHost, MockSurface};
+
+    /// Updates run since the app was created
+    #[derive(Resource, Default)]
+    struct Updates(u32);
+
+    fn count_updates(mut updates: ResMut<Updates>) {
+        updates.0 += 1;
+    }
+
+    fn host(closes_window: bool) -> MockHost {
+        MockHost::from_setup(MockSurface::default(), |app| {
+            app.add_plugins(MinimalPlugins)
+                .init_resource::<Updates>()
+                .add_systems(Last, count_updates);
+            // The window plugin despawns windows whose close was requested
+            if closes_window {
+                app.add_plugins(WindowPlugin {
+                    primary_window: None,
+                    ..Default::default()
+                });
+            }
+        })
+    }
+
+    /// Steps the teardown until the app can be dropped, returning the updates it ran
+    fn steps(host: &mut MockHost) -> usize {
+        std::iter::repeat_with(|| step(host.app_mut()))
+            .take_while(|&done| !done)
+            .count()
+    }
+
+    fn updates(host: &MockHost) -> u32 {
+        host.app().world().resource::<Updates>().0
+    }
+
+    #[test]
+    fn steps_until_the_window_closes() {
+        let mut host = host(true);
+        host.update();
+
+        begin(host.app_mut(), None, std::ptr::null_mut());
+        assert!(in_progress(host.app()));
+        let steps = steps(&mut host);
+        assert!((1..MAX_TEARDOWN_UPDATES as usize).contains(&steps));
+        assert!(ffi::window_closed(host.app_mut()));
+        assert_eq!(updates(&host), 1 + steps as u32);
+
+        // Beginning again keeps the finished teardown
+        begin(host.app_mut(), None, std::ptr::null_mut());
+        assert!(step(host.app_mut()));
+        assert!(shutdown(host.app_mut()).is_none());
+        assert!(!in_progress(host.app()));
+    }
+
+    #[test]
+    fn steps_stop_at_the_update_limit_with_the_window_open() {
+        let mut host = host(false);
+        host.app_mut()
+            .world_mut()
+            .resource_mut::<EmbeddedConfig>()
+            .teardown_timeout = Some(Duration::from_secs(60));
+
+        begin(host.app_mut(), None, std::ptr::null_mut());
+        assert_eq!(steps(&mut host), MAX_TEARDOWN_UPDATES as usize);
+        assert_eq!(updates(&host), MAX_TEARDOWN_UPDATES);
+        assert!(!ffi::window_closed(host.app_mut()));
+    }
+
+    #[test]
+    fn shutdown_returns_the_hosts_callback() {
+        static DESTROYED: AtomicUsize = AtomicUsize::new(0);
+        unsafe extern "C" fn on_destroyed(user_data: *mut c_void) {
+            DESTROYED.store(user_data as usize, Ordering::SeqCst);
+        }
+
+        let mut host = host(true);
+        begin(host.app_mut(), Some(on_destroyed), 7 as *mut c_void);
+        let notice = shutdown(host.app_mut()).unwrap();
+        assert!(ffi::window_closed(host.app_mut()));
+        assert_eq!(DESTROYED.load(Ordering::SeqCst), 0);
+        unsafe { notice.notify() };
+        assert_eq!(DESTROYED.load(Ordering::SeqCst), 7);
+    }
+}
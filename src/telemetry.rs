@@ -0,0 +1,279 @@
+//! Standard lifecycle telemetry for host analytics
+//!
+//! Every app that adds [`TelemetryPlugin`] reports the same milestones, so hosts can
+//! track the health of their embedded widgets without instrumenting each app. Events are
+//! framed as reserved messages on [`ReservedTopic::Telemetry`] and queued process-wide
+//! rather than on the app's channel, so one analytics sink sees every instance, including
+//! the report sent while an instance is destroyed. Hosts drain the queue with
+//! `bevy_embedded_ios_receive_telemetry` or `nativeReceiveTelemetry`; the oldest events
+//! are dropped once [`MAX_QUEUED_TELEMETRY`] are waiting.
+//!
+//! ```text
+//! instance: u64 | event: u8 | elapsed ms since creation: u64 | event data ...
+//! Error:     message: u32 len + UTF-8
+//! Destroyed: frames: u64 | buckets: u8 | buckets × (upper bound ms: f32 | frames: u32)
+//! ```
+//!
+//! `instance` is the app pointer the host holds. `AssetsLoaded` is sent once every asset
+//! passed to [`Telemetry::track_asset`] has loaded with its dependencies, at the earliest
+//! with the first frame. Failed asset loads and failed updates are reported as `Error`.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use bevy::{
+    app::{App, Last, Plugin},
+    asset::{AssetServer, UntypedAssetId, UntypedAssetLoadFailedEvent},
+    ecs::{
+        message::MessageReader,
+        resource::Resource,
+        system::{Res, ResMut},
+        world::World,
+    },
+};
+
+use crate::protocol::{ByteWriter, ReservedTopic, encode_reserved};
+
+/// Reports the app's lifecycle milestones to the host; see the [module docs](self)
+#[derive(Default)]
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Telemetry>()
+            .add_systems(Last, report_milestones);
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Asset failures are only reported by apps with an asset server
+        if app.world().contains_resource::<AssetServer>() {
+            app.add_systems(Last, report_asset_failures);
+        }
+    }
+}
+
+/// Most telemetry events kept until the host receives them
+pub const MAX_QUEUED_TELEMETRY: usize = 256;
+
+/// Upper bounds of the frame time histogram buckets, in milliseconds
+pub const FRAME_TIME_BUCKETS_MS: [f32; 6] = [8.4, 16.7, 33.4, 50.0, 100.0, f32::INFINITY];
+
+/// Telemetry events of all instances, oldest first
+static QUEUE: Mutex<VecDeque<Vec<u8>>> = Mutex::new(VecDeque::new());
+
+/// Milestones reported to the host
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TelemetryEvent {
+    /// The app finished building its plugins
+    Created = 0,
+    /// The first update ran
+    FirstFrame = 1,
+    /// Every tracked asset loaded
+    AssetsLoaded = 2,
+    /// An update failed or an asset failed to load
+    Error = 3,
+    /// The app is being destroyed; carries the frame time histogram
+    Destroyed = 4,
+}
+
+/// Progress of the app's telemetry milestones
+#[derive(Resource, Debug)]
+pub struct Telemetry {
+    created: Instant,
+    frames: [u32; FRAME_TIME_BUCKETS_MS.len()],
+    first_frame: bool,
+    assets_loaded: bool,
+    tracked: Vec<UntypedAssetId>,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self {
+            created: Instant::now(),
+            frames: [0; FRAME_TIME_BUCKETS_MS.len()],
+            first_frame: false,
+            assets_loaded: false,
+            tracked: Vec::new(),
+        }
+    }
+}
+
+impl Telemetry {
+    /// Holds back `AssetsLoaded` until this asset has loaded with its dependencies
+    ///
+    /// Has no effect once `AssetsLoaded` was sent.
+    pub fn track_asset(&mut self, id: impl Into<UntypedAssetId>) {
+        if !self.assets_loaded {
+            self.tracked.push(id.into());
+        }
+    }
+
+    /// Time since the app started building
+    pub fn elapsed(&self) -> Duration {
+        self.created.elapsed()
+    }
+
+    /// Number of updates in each bucket of [`FRAME_TIME_BUCKETS_MS`]
+    pub fn frame_histogram(&self) -> &[u32] {
+        &self.frames
+    }
+
+    fn record_frame(&mut self, frame_time: Duration) {
+        let ms = frame_time.as_secs_f32() * 1000.0;
+        let bucket = FRAME_TIME_BUCKETS_MS
+            .iter()
+            .position(|&bound| ms < bound)
+            .unwrap_or(FRAME_TIME_BUCKETS_MS.len() - 1);
+        self.frames[bucket] = self.frames[bucket].saturating_add(1);
+    }
+}
+
+/// Takes the oldest queued telemetry event, framed as a reserved message
+pub fn receive_telemetry() -> Option<Vec<u8>> {
+    QUEUE.lock().ok()?.pop_front()
+}
+
+/// Queues an event of the app at `instance`
+fn report(instance: usize, telemetry: &Telemetry, event: TelemetryEvent, data: &[u8]) {
+    let mut writer = ByteWriter::new();
+    writer
+        .u64(instance as u64)
+        .u8(event as u8)
+        .u64(telemetry.elapsed().as_millis() as u64)
+        .bytes(data);
+    let message = encode_reserved(ReservedTopic::Telemetry, &writer.finish());
+
+    let Ok(mut queue) = QUEUE.lock() else {
+        return;
+    };
+    if queue.len() >= MAX_QUEUED_TELEMETRY {
+        queue.pop_front();
+    }
+    queue.push_back(message);
+}
+
+/// Payload of an `Error` event
+fn error_data(message: &str) -> Vec<u8> {
+    let mut writer = ByteWriter::new();
+    writer.string(message);
+    writer.finish()
+}
+
+/// Handle the host holds for the app
+///
+/// Systems only see the world, so the handle is recorded once the app is registered.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub(crate) struct TelemetryInstance(pub(crate) usize);
+
+impl TelemetryInstance {
+    fn of(app: &App) -> usize {
+        app.world()
+            .get_resource::<Self>()
+            .map_or(0, |instance| instance.0)
+    }
+}
+
+/// Reports that the app finished creation; called once it is registered as `handle`
+#[doc(hidden)]
+pub fn report_created(app: &mut App, handle: *const App) {
+    let instance = handle as usize;
+    app.insert_resource(TelemetryInstance(instance));
+    if let Some(telemetry) = app.world().get_resource::<Telemetry>() {
+        report(instance, telemetry, TelemetryEvent::Created, &[]);
+    }
+}
+
+/// Reports an error of the app
+#[doc(hidden)]
+pub fn report_error(app: &App, message: &str) {
+    if let Some(telemetry) = app.world().get_resource::<Telemetry>() {
+        report(
+            TelemetryInstance::of(app),
+            telemetry,
+            TelemetryEvent::Error,
+            &error_data(message),
+        );
+    }
+}
+
+/// Reports that the app is being destroyed, with its frame time histogram
+#[doc(hidden)]
+pub fn report_destroyed(app: &App) {
+    let Some(telemetry) = app.world().get_resource::<Telemetry>() else {
+        return;
+    };
+    let total: u64 = telemetry.frames.iter().map(|&frames| frames as u64).sum();
+    let mut writer = ByteWriter::new();
+    writer.u64(total).u8(FRAME_TIME_BUCKETS_MS.len() as u8);
+    for (bound, frames) in FRAME_TIME_BUCKETS_MS.iter().zip(telemetry.frames) {
+        writer.f32(*bound).u32(frames);
+    }
+    report(
+        TelemetryInstance::of(app),
+        telemetry,
+        TelemetryEvent::Destroyed,
+        &writer.finish(),
+    );
+}
+
+/// Adds an update's duration to the frame time histogram
+pub(crate) fn record_frame(world: &mut World, frame_time: Duration) {
+    if let Some(mut telemetry) = world.get_resource_mut::<Telemetry>() {
+        telemetry.record_frame(frame_time);
+    }
+}
+
+/// Reports the first frame, and tracked assets once they have loaded
+fn report_milestones(
+    mut telemetry: ResMut<Telemetry>,
+    instance: Option<Res<TelemetryInstance>>,
+    asset_server: Option<Res<AssetServer>>,
+) {
+    // Apps not created through the FFI have no address to report
+    let Some(instance) = instance.map(|instance| instance.0) else {
+        return;
+    };
+    if !telemetry.first_frame {
+        telemetry.first_frame = true;
+        report(instance, &telemetry, TelemetryEvent::FirstFrame, &[]);
+    }
+    if telemetry.assets_loaded {
+        return;
+    }
+
+    let loaded = |id: &UntypedAssetId| {
+        asset_server
+            .as_ref()
+            .is_none_or(|server| server.is_loaded_with_dependencies(*id))
+    };
+    if telemetry.tracked.iter().all(loaded) {
+        telemetry.assets_loaded = true;
+        telemetry.tracked.clear();
+        report(instance, &telemetry, TelemetryEvent::AssetsLoaded, &[]);
+    }
+}
+
+/// Reports failed asset loads as errors
+fn report_asset_failures(
+    mut failures: MessageReader<UntypedAssetLoadFailedEvent>,
+    mut telemetry: ResMut<Telemetry>,
+    instance: Option<Res<TelemetryInstance>>,
+) {
+    let Some(instance) = instance.map(|instance| instance.0) else {
+        failures.clear();
+        return;
+    };
+    for failure in failures.read() {
+        let message = format!("Failed to load {}: {}", failure.path, failure.error);
+        report(
+            instance,
+            &telemetry,
+            TelemetryEvent::Error,
+            &error_data(&message),
+        );
+        // A failed tracked asset will never load
+        telemetry.tracked.retain(|id| *id != failure.id);
+    }
+}
@@ -0,0 +1,284 @@
+//! Test support for embedded apps
+//!
+//! [`MockHost`] stands in for the native host application so an [`EmbeddedApp`] can be
+//! exercised with `cargo test` on desktop: it provides fake surface info, feeds scripted
+//! touch sequences, and plays both ends of the binary channel.
+//!
+//! The mock window has no native handle, so apps under test should not add
+//! `RenderPlugin`. `MinimalPlugins` plus whatever logic plugins the app needs works well.
+//!
+//! # Example
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use bevy_embedded::{HostChannel, test::MockHost};
+//!
+//! let mut host = MockHost::from_setup(Default::default(), |app| {
+//!     app.add_plugins(MinimalPlugins).add_systems(
+//!         Update,
+//!         |channel: Res<HostChannel>| {
+//!             while let Some(message) = channel.receive() {
+//!                 channel.send(message);
+//!             }
+//!         },
+//!     );
+//! });
+//!
+//! host.send(b"ping".to_vec());
+//! host.update();
+//! assert_eq!(host.receive(), Some(b"ping".to_vec()));
+//! ```
+
+use std::ffi::c_void;
+use std::time::Duration;
+
+use bevy::{
+    app::App,
+    math::Vec2,
+    time::TimeUpdateStrategy,
+    window::{PrimaryWindow, Window, WindowResolution},
+};
+
+use crate::{
+    EmbeddedApp, EmbeddedConfig, EmbeddedInputEvents, EmbeddedPlugin, EmbeddedTouchEvent,
+    HostChannel, HostConfig, HostEndpoint, TouchPhase, ffi, registry,
+    replay::{self, ReplayLog},
+    startup, telemetry,
+};
+
+/// Fake surface info, mirroring what a real host reports at creation
+#[derive(Debug, Clone, Copy)]
+pub struct MockSurface {
+    /// Surface width in physical pixels
+    pub width: u32,
+    /// Surface height in physical pixels
+    pub height: u32,
+    /// Display scale factor
+    pub scale_factor: f32,
+}
+
+impl Default for MockSurface {
+    fn default() -> Self {
+        // A typical phone widget
+        Self {
+            width: 1170,
+            height: 1170,
+            scale_factor: 3.0,
+        }
+    }
+}
+
+/// A scripted sequence of touch events, grouped by frame
+#[derive(Debug, Clone, Default)]
+pub struct TouchScript {
+    frames: Vec<Vec<EmbeddedTouchEvent>>,
+}
+
+impl TouchScript {
+    /// Creates an empty script
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a frame containing the given events
+    pub fn frame(mut self, events: impl IntoIterator<Item = EmbeddedTouchEvent>) -> Self {
+        self.frames.push(events.into_iter().collect());
+        self
+    }
+
+    /// Adds a frame with no input, letting the app advance
+    pub fn idle(self) -> Self {
+        self.frame([])
+    }
+
+    /// Adds a tap: touch down and up on consecutive frames
+    pub fn tap(self, id: u64, position: Vec2) -> Self {
+        self.frame([touch(TouchPhase::Started, position, id)])
+            .frame([touch(TouchPhase::Ended, position, id)])
+    }
+
+    /// Adds a drag from `from` to `to` with `steps` intermediate move frames
+    pub fn drag(mut self, id: u64, from: Vec2, to: Vec2, steps: usize) -> Self {
+        self = self.frame([touch(TouchPhase::Started, from, id)]);
+        for step in 1..=steps {
+            let position = from.lerp(to, step as f32 / steps as f32);
+            self = self.frame([touch(TouchPhase::Moved, position, id)]);
+        }
+        self.frame([touch(TouchPhase::Ended, to, id)])
+    }
+
+    /// Number of frames in the script
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true if the script has no frames
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+fn touch(phase: TouchPhase, position: Vec2, id: u64) -> EmbeddedTouchEvent {
+    EmbeddedTouchEvent {
+        phase,
+        position,
+        id,
+        contact: None,
+    }
+}
+
+/// A fake host driving an embedded app
+///
+/// The app is boxed and registered in the instance [`registry`] like one created by
+/// `export_embedded_app!`, so pointer-based FFI functions work against it.
+pub struct MockHost {
+    app: Box<App>,
+    /// The registry handle a real host would hold
+    handle: usize,
+    endpoint: HostEndpoint,
+}
+
+impl MockHost {
+    /// Creates the app the same way `export_embedded_app!` would, against a mock surface
+    pub fn new<A: EmbeddedApp>(surface: MockSurface) -> Self {
+        Self::with_host_config::<A>(surface, HostConfig::new())
+    }
+
+    /// Like [`MockHost::new`], with creation config values a host would pass to the
+    /// create call
+    pub fn with_host_config<A: EmbeddedApp>(surface: MockSurface, host_config: HostConfig) -> Self {
+        A::pre_init();
+        let mut config = A::config();
+        config.apply_host_config(host_config);
+        Self::build(surface, config, |app| {
+            A::post_init(app);
+            A::setup(app);
+        })
+    }
+
+    /// Creates an app from a plain setup function instead of an [`EmbeddedApp`]
+    pub fn from_setup(surface: MockSurface, setup: impl FnOnce(&mut App)) -> Self {
+        Self::build(surface, EmbeddedConfig::default(), setup)
+    }
+
+    fn build(surface: MockSurface, config: EmbeddedConfig, setup: impl FnOnce(&mut App)) -> Self {
+        let mut app = App::new();
+        let headless = config.headless;
+        app.insert_resource(config)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / 60.0,
+            )))
+            .add_plugins(EmbeddedPlugin);
+
+        if !headless {
+            let window = Window {
+                resolution: WindowResolution::new(surface.width, surface.height)
+                    .with_scale_factor_override(surface.scale_factor),
+                ..Default::default()
+            };
+            app.world_mut().spawn((window, PrimaryWindow));
+        }
+
+        setup(&mut app);
+        startup::record_build_end(&app);
+
+        ffi::finish_plugins(&mut app);
+
+        let mut app = Box::new(app);
+        let handle = registry::register(&app);
+        telemetry::report_created(&mut app, handle);
+        let handle = handle as usize;
+        let endpoint = app.world().resource::<HostChannel>().endpoint();
+        Self {
+            app,
+            handle,
+            endpoint,
+        }
+    }
+
+    /// The opaque pointer a real host would hold for this instance
+    pub fn app_ptr(&mut self) -> *mut c_void {
+        self.handle as *mut c_void
+    }
+
+    /// The app under test
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    /// The app under test, mutably
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Runs one frame, as the host's display link would
+    ///
+    /// Like `bevy_embedded_update`, frames skipped by picture-in-picture pacing do not
+    /// update the app, errors and panics are handled by the app's
+    /// [`RecoveryPolicy`](crate::RecoveryPolicy), and the returned code is non-zero if the
+    /// app exited with an error. A panic freezes the app by default, which
+    /// [`Recovery::is_frozen`](crate::Recovery::is_frozen) tells.
+    pub fn update(&mut self) -> u8 {
+        ffi::update(&mut self.app);
+        ffi::recover(&mut self.app)
+    }
+
+    /// Runs `frames` frames
+    pub fn run_frames(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.update();
+        }
+    }
+
+    /// Queues a touch event for the next frame
+    pub fn touch(&mut self, phase: TouchPhase, position: Vec2, id: u64) {
+        self.app
+            .world_mut()
+            .resource_mut::<EmbeddedInputEvents>()
+            .add_touch_event(touch(phase, position, id));
+    }
+
+    /// Plays a touch script, running one frame per scripted frame
+    pub fn play(&mut self, script: &TouchScript) {
+        for frame in &script.frames {
+            for event in frame {
+                self.touch(event.phase, event.position, event.id);
+            }
+            self.update();
+        }
+    }
+
+    /// Replays input recorded on a device, running one frame per recorded frame
+    ///
+    /// Returns the number of frames run. See [`replay`](crate::replay).
+    pub fn replay(&mut self, log: &ReplayLog) -> usize {
+        replay::play(&mut self.app, log)
+    }
+
+    /// Resizes the mock surface, as a host layout change would
+    pub fn resize(&mut self, width: u32, height: u32, scale_factor: f32) {
+        let _ = ffi::resize(&mut self.app, width, height, scale_factor);
+    }
+
+    /// Sends a message to Bevy, as the host would
+    pub fn send(&self, data: Vec<u8>) {
+        self.endpoint.send(data);
+    }
+
+    /// Receives the next message Bevy sent to the host
+    pub fn receive(&self) -> Option<Vec<u8>> {
+        self.endpoint.receive()
+    }
+
+    /// Drains every message Bevy has sent to the host so far
+    pub fn receive_all(&self) -> Vec<Vec<u8>> {
+        std::iter::from_fn(|| self.receive()).collect()
+    }
+}
+
+impl Drop for MockHost {
+    fn drop(&mut self) {
+        telemetry::report_destroyed(&self.app);
+        registry::unregister(self.handle as *const App);
+    }
+}
@@ -0,0 +1,428 @@
+//! Text fields edited with the native keyboard
+//!
+//! Bevy can't drive a phone's software keyboard, IME composition, autocorrect or dictation
+//! itself. A [`HostTextField`] is a text field the host edits natively instead: focusing it
+//! with [`TextInputFocus`] asks the host to present its keyboard, and every edit the user
+//! makes streams back into the component. Fields are edited once [`TextInputPlugin`] is
+//! added.
+//!
+//! While a field is focused, Bevy sends [`ReservedTopic::TextInputRequest`] messages,
+//! each starting with a [`TextInputRequestOp`] byte:
+//!
+//! ```text
+//! Show: field: u64 (Entity::to_bits) | kind: u8 | multiline: u8 | text: string
+//!       | selection_start: u32 | selection_end: u32
+//! Hide: field: u64
+//! ```
+//!
+//! `kind` is a [`TextInputKind`]. Show is sent again when the app changes the text of the
+//! focused field, so the host can update its native field. The host answers on
+//! [`ReservedTopic::TextInput`] with operations starting with a [`TextInputOp`] byte:
+//!
+//! ```text
+//! Edit:      field: u64 | text: string | selection_start: u32 | selection_end: u32
+//!            | composing_start: u32 | composing_end: u32
+//! Submit:    field: u64
+//! Dismissed: field: u64
+//! ```
+//!
+//! Strings are `u32 len + UTF-8` and ranges are byte offsets into the text. Edits carry the
+//! whole text, including text the IME is still composing, which is empty when the start
+//! equals the end. Submit is the return key and is reported as a [`TextFieldSubmitted`]
+//! message; Dismissed means the user closed the keyboard, which blurs the field.
+//!
+//! With the `text_input` cargo feature, pressing a bevy_ui node with a [`HostTextField`]
+//! focuses it, and its `Text` shows the field's text, with dots for passwords.
+
+use std::ops::Range;
+
+use bevy::{
+    app::{App, Last, Plugin, PreUpdate},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        message::{Message, MessageReader, MessageWriter},
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Query, Res, ResMut},
+    },
+};
+
+use crate::{
+    HostChannel,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+    send_policy::flush_send_policies,
+};
+
+/// Edits [`HostTextField`]s with the host's keyboard; see the [module docs](self)
+#[derive(Default)]
+pub struct TextInputPlugin;
+
+impl Plugin for TextInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TextInputFocus>()
+            .add_message::<TextFieldSubmitted>()
+            .add_systems(PreUpdate, receive_text_input)
+            .add_systems(Last, request_text_input.before(flush_send_policies));
+    }
+
+    #[cfg(feature = "text_input")]
+    fn finish(&self, app: &mut App) {
+        use bevy::{app::PostUpdate, ui::UiSystems};
+
+        // Text fields are bevy_ui nodes
+        if app.is_plugin_added::<bevy::ui::UiPlugin>() {
+            app.add_systems(
+                PreUpdate,
+                (
+                    ui::add_text_field_interaction,
+                    ui::focus_pressed_text_fields.after(UiSystems::Focus),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                ui::show_text_field_text.before(UiSystems::Prepare),
+            );
+        }
+    }
+}
+
+/// Operations in a text input request to the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TextInputRequestOp {
+    /// Present the keyboard for a field, or update the field it edits
+    Show = 0,
+    /// Dismiss the keyboard
+    Hide = 1,
+}
+
+impl TextInputRequestOp {
+    /// Create a TextInputRequestOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TextInputRequestOp::Show),
+            1 => Some(TextInputRequestOp::Hide),
+            _ => None,
+        }
+    }
+}
+
+/// Operations in text input from the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TextInputOp {
+    /// The text, selection or composition of a field changed
+    Edit = 0,
+    /// The user pressed the return key
+    Submit = 1,
+    /// The user dismissed the keyboard
+    Dismissed = 2,
+}
+
+impl TextInputOp {
+    /// Create a TextInputOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TextInputOp::Edit),
+            1 => Some(TextInputOp::Submit),
+            2 => Some(TextInputOp::Dismissed),
+            _ => None,
+        }
+    }
+}
+
+/// Keyboard the host presents for a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum TextInputKind {
+    /// Any text
+    #[default]
+    Text = 0,
+    /// Whole numbers
+    Number = 1,
+    /// Numbers with a decimal separator
+    Decimal = 2,
+    /// An email address
+    Email = 3,
+    /// A URL
+    Url = 4,
+    /// A password, hidden while typed
+    Password = 5,
+    /// A phone number
+    Phone = 6,
+}
+
+impl TextInputKind {
+    /// Create a TextInputKind from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TextInputKind::Text),
+            1 => Some(TextInputKind::Number),
+            2 => Some(TextInputKind::Decimal),
+            3 => Some(TextInputKind::Email),
+            4 => Some(TextInputKind::Url),
+            5 => Some(TextInputKind::Password),
+            6 => Some(TextInputKind::Phone),
+            _ => None,
+        }
+    }
+}
+
+/// A text field edited by the host's keyboard
+#[derive(Component, Debug, Clone, PartialEq, Eq, Default)]
+pub struct HostTextField {
+    /// Current text, including text being composed
+    pub text: String,
+    /// Keyboard the host presents
+    pub kind: TextInputKind,
+    /// Whether the return key inserts a line break instead of submitting
+    pub multiline: bool,
+    /// Selected byte range, empty for a cursor
+    pub selection: Range<usize>,
+    /// Byte range the IME is still composing, if any
+    pub composing: Option<Range<usize>>,
+}
+
+impl HostTextField {
+    /// A field starting with `text` and the cursor at its end
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let end = text.len();
+        Self {
+            text,
+            selection: end..end,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the keyboard the host presents
+    pub fn kind(mut self, kind: TextInputKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Lets the return key insert line breaks
+    pub fn multiline(mut self) -> Self {
+        self.multiline = true;
+        self
+    }
+}
+
+/// Sent when the user presses the return key in a single-line field
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextFieldSubmitted {
+    /// The field
+    pub entity: Entity,
+}
+
+/// The text field the host's keyboard edits
+#[derive(Resource, Debug, Default)]
+pub struct TextInputFocus {
+    focused: Option<Entity>,
+    /// Field and text the host last showed
+    shown: Option<(Entity, String)>,
+}
+
+impl TextInputFocus {
+    /// The focused field
+    pub fn get(&self) -> Option<Entity> {
+        self.focused
+    }
+
+    /// Focuses `field`, presenting the host's keyboard
+    pub fn focus(&mut self, field: Entity) {
+        self.focused = Some(field);
+    }
+
+    /// Removes focus, dismissing the host's keyboard
+    pub fn blur(&mut self) {
+        self.focused = None;
+    }
+}
+
+/// Applies one operation, returning `None` if the payload is malformed
+fn apply_op(
+    reader: &mut ByteReader,
+    focus: &mut TextInputFocus,
+    fields: &mut Query<&mut HostTextField>,
+    submitted: &mut MessageWriter<TextFieldSubmitted>,
+) -> Option<()> {
+    let op = reader.u8()?;
+    let Some(op) = TextInputOp::from_u8(op) else {
+        log::warn!("Ignoring text input with unknown operation {op}");
+        return None;
+    };
+    let entity = Entity::try_from_bits(reader.u64()?)?;
+    match op {
+        TextInputOp::Edit => {
+            let text = reader.string()?;
+            let selection = read_range(reader, text)?;
+            let composing = read_range(reader, text)?;
+            // Edits for a field that lost focus are stale
+            if focus.focused != Some(entity) {
+                return Some(());
+            }
+            let Ok(mut field) = fields.get_mut(entity) else {
+                return Some(());
+            };
+            field.text = text.to_owned();
+            field.selection = selection;
+            field.composing = (!composing.is_empty()).then_some(composing);
+            // The host already shows this text
+            focus.shown = Some((entity, field.text.clone()));
+        }
+        TextInputOp::Submit => {
+            submitted.write(TextFieldSubmitted { entity });
+        }
+        TextInputOp::Dismissed => {
+            if focus.focused == Some(entity) {
+                focus.focused = None;
+                focus.shown = None;
+            }
+        }
+    }
+    Some(())
+}
+
+/// Reads a byte range, clamped to `text` and its character boundaries
+fn read_range(reader: &mut ByteReader, text: &str) -> Option<Range<usize>> {
+    let clamp = |offset: u32| {
+        let mut offset = (offset as usize).min(text.len());
+        while !text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    };
+    let start = clamp(reader.u32()?);
+    let end = clamp(reader.u32()?);
+    Some(start.min(end)..end.max(start))
+}
+
+/// Applies text input from the host
+fn receive_text_input(
+    mut commands: MessageReader<HostCommand>,
+    mut focus: ResMut<TextInputFocus>,
+    mut fields: Query<&mut HostTextField>,
+    mut submitted: MessageWriter<TextFieldSubmitted>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::TextInput {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while !reader.is_empty() {
+            if apply_op(&mut reader, &mut focus, &mut fields, &mut submitted).is_none() {
+                log::warn!("Ignoring malformed text input");
+                break;
+            }
+        }
+    }
+}
+
+/// Asks the host to show or hide its keyboard when focus or the focused text changes
+fn request_text_input(
+    channel: Res<HostChannel>,
+    mut focus: ResMut<TextInputFocus>,
+    fields: Query<&HostTextField>,
+) {
+    // A despawned field loses focus
+    let field = focus.focused.and_then(|entity| fields.get(entity).ok());
+    if field.is_none() {
+        focus.focused = None;
+    }
+
+    let requested = focus.focused.zip(field);
+    let up_to_date = match (requested, &focus.shown) {
+        (Some((entity, field)), Some((shown, text))) => *shown == entity && *text == field.text,
+        (None, None) => true,
+        _ => false,
+    };
+    if up_to_date {
+        return;
+    }
+
+    match requested {
+        Some((entity, field)) => {
+            // The app may have shortened the text without moving the selection
+            let selection = field.selection.start.min(field.text.len())
+                ..field.selection.end.min(field.text.len());
+            let mut payload = ByteWriter::new();
+            payload
+                .u8(TextInputRequestOp::Show as u8)
+                .u64(entity.to_bits())
+                .u8(field.kind as u8)
+                .bool(field.multiline)
+                .string(&field.text)
+                .u32(selection.start as u32)
+                .u32(selection.end as u32);
+            channel.send_reserved(ReservedTopic::TextInputRequest, &payload.finish());
+            focus.shown = Some((entity, field.text.clone()));
+        }
+        None => {
+            let Some((shown, _)) = focus.shown.take() else {
+                return;
+            };
+            let mut payload = ByteWriter::new();
+            payload
+                .u8(TextInputRequestOp::Hide as u8)
+                .u64(shown.to_bits());
+            channel.send_reserved(ReservedTopic::TextInputRequest, &payload.finish());
+        }
+    }
+}
+
+#[cfg(feature = "text_input")]
+mod ui {
+    use bevy::{
+        ecs::{
+            entity::Entity,
+            query::{Added, Changed, With, Without},
+            system::{Commands, Query, ResMut},
+        },
+        ui::{Interaction, widget::Text},
+    };
+
+    use super::{HostTextField, TextInputFocus, TextInputKind};
+
+    /// Makes new text fields pressable
+    pub(super) fn add_text_field_interaction(
+        mut commands: Commands,
+        fields: Query<Entity, (Added<HostTextField>, Without<Interaction>)>,
+    ) {
+        for entity in &fields {
+            commands.entity(entity).insert(Interaction::default());
+        }
+    }
+
+    /// Text fields whose interaction changed
+    type InteractedFields = (Changed<Interaction>, With<HostTextField>);
+
+    /// Focuses pressed text fields
+    pub(super) fn focus_pressed_text_fields(
+        mut focus: ResMut<TextInputFocus>,
+        fields: Query<(Entity, &Interaction), InteractedFields>,
+    ) {
+        for (entity, interaction) in &fields {
+            if *interaction == Interaction::Pressed && focus.get() != Some(entity) {
+                focus.focus(entity);
+            }
+        }
+    }
+
+    /// Shows the text of fields in their `Text`
+    pub(super) fn show_text_field_text(
+        mut fields: Query<(&HostTextField, &mut Text), Changed<HostTextField>>,
+    ) {
+        for (field, mut text) in &mut fields {
+            let shown = match field.kind {
+                TextInputKind::Password => "•".repeat(field.text.chars().count()),
+                _ => field.text.clone(),
+            };
+            if text.0 != shown {
+                text.0 = shown;
+            }
+        }
+    }
+}
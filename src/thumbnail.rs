@@ -0,0 +1,551 @@
+//! Rendering thumbnails of assets and the scene for the host
+//!
+//! Native list views show previews of 3D content without a live Bevy surface for every
+//! row. The host asks for a thumbnail with [`ReservedTopic::RenderThumbnail`]:
+//!
+//! ```text
+//! request: u32 | width: u32 | height: u32 | framing: u8 (ThumbnailFraming) | yaw: f32 |
+//! pitch: f32 | source: u8 (ThumbnailSource) | path: u32 len + UTF-8 (Asset only)
+//! ```
+//!
+//! Thumbnails are rendered into an image by a camera of their own, one at a time, and
+//! work whether or not the app has a window surface. An asset is loaded as a glTF scene
+//! on [`THUMBNAIL_LAYER`] with a transparent background and its own light, so it never
+//! shows up in the app's cameras. The host receives [`ReservedTopic::Thumbnail`]:
+//!
+//! ```text
+//! request: u32 | status: u8 (ThumbnailStatus) | width: u32 | height: u32 | PNG bytes ...
+//! ```
+//!
+//! The image is captured a few frames after the content is ready, so its pipelines have
+//! compiled. The asset's entities are part of the world while it renders, so app systems
+//! that act on every mesh see them.
+
+use std::{collections::VecDeque, f32::consts::FRAC_PI_2, io::Cursor};
+
+use bevy::{
+    asset::{AssetServer, Assets, Handle},
+    camera::{
+        Camera, Camera3d, ClearColorConfig, PerspectiveProjection, Projection, RenderTarget,
+        primitives::Aabb,
+        visibility::{InheritedVisibility, RenderLayers, Visibility},
+    },
+    color::Color,
+    core_pipeline::tonemapping::Tonemapping,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        hierarchy::{ChildOf, Children},
+        message::MessageReader,
+        name::Name,
+        observer::On,
+        query::{With, Without},
+        resource::Resource,
+        system::{Commands, Query, Res, ResMut, SystemParam},
+    },
+    gltf::{Gltf, GltfAssetLabel},
+    image::{Image, ImageFormat},
+    light::{AmbientLight, DirectionalLight},
+    math::{UVec2, Vec3, Vec3A},
+    render::{
+        render_resource::TextureFormat,
+        view::window::screenshot::{Screenshot, ScreenshotCaptured},
+    },
+    scene::{SceneInstance, SceneRoot, SceneSpawner},
+    transform::components::{GlobalTransform, Transform},
+};
+
+use crate::{
+    HostChannel,
+    model::world_aabb,
+    protocol::{ByteReader, ByteWriter, HostCommand, ReservedTopic},
+};
+
+/// Largest thumbnail width or height accepted from the host
+pub const MAX_THUMBNAIL_SIZE: u32 = 2048;
+
+/// Render layer assets are rendered on for their thumbnails; app cameras should not
+/// include it
+pub const THUMBNAIL_LAYER: usize = 31;
+
+/// Frames rendered between the content being ready and the capture
+const SETTLE_FRAMES: u32 = 3;
+
+/// What a thumbnail shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ThumbnailSource {
+    /// The app's world as its cameras see it
+    Scene = 0,
+    /// A glTF asset loaded from a path, on its own
+    Asset = 1,
+}
+
+impl ThumbnailSource {
+    /// Create a ThumbnailSource from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ThumbnailSource::Scene),
+            1 => Some(ThumbnailSource::Asset),
+            _ => None,
+        }
+    }
+}
+
+/// Where the thumbnail camera is placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ThumbnailFraming {
+    /// Orbit the content's bounds at the request's yaw and pitch, close enough to fit it
+    Fit = 0,
+    /// Copy the view of the app's main 3D camera, falling back to `Fit` without one
+    MainCamera = 1,
+}
+
+impl ThumbnailFraming {
+    /// Create a ThumbnailFraming from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ThumbnailFraming::Fit),
+            1 => Some(ThumbnailFraming::MainCamera),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of a thumbnail request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ThumbnailStatus {
+    /// The thumbnail was rendered; the PNG follows
+    Rendered = 0,
+    /// The asset failed to load or the image could not be encoded
+    Failed = 1,
+}
+
+/// A thumbnail the host asked for
+#[derive(Debug, Clone)]
+struct ThumbnailRequest {
+    request_id: u32,
+    size: UVec2,
+    framing: ThumbnailFraming,
+    yaw: f32,
+    pitch: f32,
+    path: Option<String>,
+}
+
+impl ThumbnailRequest {
+    /// Reads a request, returning `None` if the payload is malformed
+    fn read(reader: &mut ByteReader) -> Option<Self> {
+        let request_id = reader.u32()?;
+        let size = UVec2::new(reader.u32()?, reader.u32()?);
+        let framing = ThumbnailFraming::from_u8(reader.u8()?)?;
+        let (yaw, pitch) = (reader.f32()?, reader.f32()?);
+        let path = match ThumbnailSource::from_u8(reader.u8()?)? {
+            ThumbnailSource::Scene => None,
+            ThumbnailSource::Asset => Some(reader.string()?.to_string()),
+        };
+        Some(Self {
+            request_id,
+            size,
+            framing,
+            yaw,
+            pitch,
+            path,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    /// Waiting for the asset and its dependencies to load
+    Loading,
+    /// Rendering until the capture, in this many frames
+    Settling(u32),
+    /// Waiting for the screenshot to be read back
+    Capturing,
+}
+
+/// The thumbnail being rendered
+struct ActiveThumbnail {
+    request: ThumbnailRequest,
+    stage: Stage,
+    /// Root of the loaded asset
+    content: Option<Entity>,
+    gltf: Option<Handle<Gltf>>,
+    camera: Option<Entity>,
+    image: Handle<Image>,
+}
+
+/// Thumbnail requests waiting to render, and the one rendering
+#[derive(Resource, Default)]
+pub(crate) struct Thumbnails {
+    queue: VecDeque<ThumbnailRequest>,
+    active: Option<ActiveThumbnail>,
+}
+
+/// Marks cameras rendering thumbnails
+#[derive(Component)]
+struct ThumbnailCamera;
+
+/// Queues thumbnails requested by the host
+pub(crate) fn receive_thumbnail_requests(
+    mut commands: MessageReader<HostCommand>,
+    channel: Res<HostChannel>,
+    mut thumbnails: ResMut<Thumbnails>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::RenderThumbnail {
+            continue;
+        }
+
+        let Some(request) = ThumbnailRequest::read(&mut ByteReader::new(&command.payload)) else {
+            log::warn!("Ignoring malformed thumbnail request");
+            continue;
+        };
+        let valid = 1..=MAX_THUMBNAIL_SIZE;
+        if !valid.contains(&request.size.x) || !valid.contains(&request.size.y) {
+            log::warn!("Thumbnail size {} is out of range", request.size);
+            send_thumbnail(&channel, request.request_id, None);
+            continue;
+        }
+        if !(request.yaw.is_finite() && request.pitch.is_finite()) {
+            log::warn!("Thumbnail angles must be finite");
+            send_thumbnail(&channel, request.request_id, None);
+            continue;
+        }
+        thumbnails.queue.push_back(request);
+    }
+}
+
+/// Components of a mesh that count towards a thumbnail's bounds
+type MeshBounds = (
+    &'static GlobalTransform,
+    &'static Aabb,
+    &'static InheritedVisibility,
+    Option<&'static RenderLayers>,
+);
+
+/// Components of an app camera a thumbnail can copy its view from
+type AppCamera = (
+    &'static Camera,
+    &'static GlobalTransform,
+    &'static Projection,
+    Option<&'static Tonemapping>,
+);
+
+/// Content, cameras and assets needed to render thumbnails
+#[derive(SystemParam)]
+pub(crate) struct ThumbnailScene<'w, 's> {
+    asset_server: Res<'w, AssetServer>,
+    scene_spawner: Res<'w, SceneSpawner>,
+    images: ResMut<'w, Assets<Image>>,
+    instances: Query<'w, 's, &'static SceneInstance>,
+    children: Query<'w, 's, &'static Children>,
+    meshes: Query<'w, 's, MeshBounds>,
+    cameras: Query<'w, 's, AppCamera, (With<Camera3d>, Without<ThumbnailCamera>)>,
+}
+
+impl ThumbnailScene<'_, '_> {
+    /// The app's highest-order active 3D camera
+    fn main_camera(&self) -> Option<(&GlobalTransform, &Projection, Option<&Tonemapping>)> {
+        self.cameras
+            .iter()
+            .filter(|(camera, ..)| camera.is_active)
+            .max_by_key(|(camera, ..)| camera.order)
+            .map(|(_, transform, projection, tonemapping)| (transform, projection, tonemapping))
+    }
+
+    /// World-space bounds of the meshes below `content`, or of the visible meshes in the
+    /// app's default layer
+    fn bounds(&self, content: Option<Entity>) -> Option<(Vec3, Vec3)> {
+        let mut min = Vec3A::splat(f32::INFINITY);
+        let mut max = Vec3A::splat(f32::NEG_INFINITY);
+        let mut add = |transform, aabb| {
+            let (mesh_min, mesh_max) = world_aabb(transform, aabb);
+            min = min.min(mesh_min);
+            max = max.max(mesh_max);
+        };
+        match content {
+            Some(content) => {
+                for entity in self.children.iter_descendants(content) {
+                    if let Ok((transform, aabb, ..)) = self.meshes.get(entity) {
+                        add(transform, aabb);
+                    }
+                }
+            }
+            None => {
+                let default_layers = RenderLayers::default();
+                for (transform, aabb, visibility, layers) in &self.meshes {
+                    let layers = layers.unwrap_or(&default_layers);
+                    if visibility.get() && layers.intersects(&default_layers) {
+                        add(transform, aabb);
+                    }
+                }
+            }
+        }
+        (min.x <= max.x).then(|| (min.into(), max.into()))
+    }
+
+    /// The thumbnail camera's view of the content
+    fn view(&self, active: &ActiveThumbnail) -> (Transform, Projection) {
+        let request = &active.request;
+        if request.framing == ThumbnailFraming::MainCamera {
+            match self.main_camera() {
+                Some((transform, projection, _)) => {
+                    return (transform.compute_transform(), projection.clone());
+                }
+                None => log::debug!("No main camera to frame the thumbnail with; fitting it"),
+            }
+        }
+
+        let (min, max) = self
+            .bounds(active.content)
+            .unwrap_or((Vec3::ZERO, Vec3::ZERO));
+        let center = (min + max) / 2.0;
+        let radius = ((max - min).length() / 2.0).max(0.01);
+        let mut projection = PerspectiveProjection::default();
+        let aspect_ratio = request.size.x as f32 / request.size.y as f32;
+        let half_fov =
+            (projection.fov / 2.0).min((aspect_ratio * (projection.fov / 2.0).tan()).atan());
+        let distance = radius / half_fov.sin();
+        projection.far = projection.far.max(distance + radius);
+
+        let pitch = request.pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
+        let direction = Vec3::new(
+            pitch.cos() * request.yaw.sin(),
+            pitch.sin(),
+            pitch.cos() * request.yaw.cos(),
+        );
+        let transform =
+            Transform::from_translation(center + direction * distance).looking_at(center, Vec3::Y);
+        (transform, Projection::Perspective(projection))
+    }
+
+    /// Returns true once the content can be rendered
+    fn is_ready(&self, active: &ActiveThumbnail) -> bool {
+        let (Some(content), Some(gltf)) = (active.content, &active.gltf) else {
+            return true;
+        };
+        let instance_ready = self
+            .instances
+            .get(content)
+            .is_ok_and(|instance| self.scene_spawner.instance_is_ready(**instance));
+        instance_ready && self.asset_server.is_loaded_with_dependencies(gltf)
+    }
+}
+
+/// Starts rendering `request`, loading its asset if it has one
+fn start_thumbnail(
+    commands: &mut Commands,
+    scene: &mut ThumbnailScene,
+    request: ThumbnailRequest,
+) -> ActiveThumbnail {
+    let (content, gltf) = match &request.path {
+        Some(path) => {
+            let gltf = scene.asset_server.load(path.clone());
+            let content = commands
+                .spawn((
+                    Name::new(format!("Thumbnail {}", request.request_id)),
+                    SceneRoot(
+                        scene
+                            .asset_server
+                            .load(GltfAssetLabel::Scene(0).from_asset(path.clone())),
+                    ),
+                    Transform::default(),
+                    // Hidden until it is moved to the thumbnail layer
+                    Visibility::Hidden,
+                ))
+                .id();
+            (Some(content), Some(gltf))
+        }
+        None => (None, None),
+    };
+    let image = Image::new_target_texture(
+        request.size.x,
+        request.size.y,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    ActiveThumbnail {
+        request,
+        stage: Stage::Loading,
+        content,
+        gltf,
+        camera: None,
+        image: scene.images.add(image),
+    }
+}
+
+/// Moves loaded content to the thumbnail layer and spawns the camera rendering it
+fn spawn_thumbnail_camera(
+    commands: &mut Commands,
+    scene: &ThumbnailScene,
+    active: &ActiveThumbnail,
+) -> Entity {
+    let layer = RenderLayers::layer(THUMBNAIL_LAYER);
+    if let Some(content) = active.content {
+        for entity in scene.children.iter_descendants(content) {
+            commands.entity(entity).insert(layer.clone());
+        }
+        commands
+            .entity(content)
+            .insert((layer.clone(), Visibility::Inherited));
+    }
+
+    let (transform, projection) = scene.view(active);
+    let tonemapping = scene
+        .main_camera()
+        .and_then(|(_, _, tonemapping)| tonemapping.copied())
+        .unwrap_or_default();
+    let clear_color = match active.content {
+        Some(_) => ClearColorConfig::Custom(Color::NONE),
+        None => ClearColorConfig::Default,
+    };
+    let camera = commands
+        .spawn((
+            Name::new(format!("Thumbnail camera {}", active.request.request_id)),
+            ThumbnailCamera,
+            Camera3d::default(),
+            Camera {
+                target: RenderTarget::Image(active.image.clone().into()),
+                clear_color,
+                ..Default::default()
+            },
+            transform,
+            projection,
+            tonemapping,
+        ))
+        .id();
+
+    // Assets are lit from above the viewer, since the scene's lights don't reach them
+    if active.content.is_some() {
+        commands.entity(camera).insert((
+            layer.clone(),
+            AmbientLight {
+                brightness: 400.0,
+                ..Default::default()
+            },
+        ));
+        commands.spawn((
+            DirectionalLight::default(),
+            Transform::default().looking_to(Vec3::new(-0.5, -1.0, -1.0), Vec3::Y),
+            layer,
+            ChildOf(camera),
+        ));
+    }
+    camera
+}
+
+/// Advances the active thumbnail, starting the next one when it's done
+///
+/// Runs in `Last`, after transforms and mesh bounds of loaded content are computed.
+pub(crate) fn render_thumbnails(
+    mut commands: Commands,
+    channel: Res<HostChannel>,
+    mut thumbnails: ResMut<Thumbnails>,
+    mut scene: ThumbnailScene,
+) {
+    if thumbnails.active.is_none() {
+        let Some(request) = thumbnails.queue.pop_front() else {
+            return;
+        };
+        thumbnails.active = Some(start_thumbnail(&mut commands, &mut scene, request));
+    }
+    let Some(active) = thumbnails.active.as_mut() else {
+        return;
+    };
+
+    match active.stage {
+        Stage::Loading => {
+            let failed = active
+                .gltf
+                .as_ref()
+                .is_some_and(|gltf| scene.asset_server.load_state(gltf).is_failed());
+            if failed {
+                log::warn!("Failed to load thumbnail asset {:?}", active.request.path);
+                send_thumbnail(&channel, active.request.request_id, None);
+                if let Some(active) = thumbnails.active.take() {
+                    despawn_thumbnail(&mut commands, &active);
+                }
+                return;
+            }
+            if scene.is_ready(active) {
+                active.camera = Some(spawn_thumbnail_camera(&mut commands, &scene, active));
+                active.stage = Stage::Settling(SETTLE_FRAMES);
+            }
+        }
+        Stage::Settling(0) => {
+            commands
+                .spawn(Screenshot::image(active.image.clone()))
+                .observe(capture_thumbnail);
+            active.stage = Stage::Capturing;
+        }
+        Stage::Settling(frames) => {
+            // Bounds of content loaded this frame are only known now
+            if let Some(camera) = active.camera {
+                commands.entity(camera).insert(scene.view(active));
+            }
+            active.stage = Stage::Settling(frames - 1);
+        }
+        Stage::Capturing => {}
+    }
+}
+
+/// Sends the captured thumbnail to the host and cleans up after it
+fn capture_thumbnail(
+    captured: On<ScreenshotCaptured>,
+    mut commands: Commands,
+    channel: Res<HostChannel>,
+    mut thumbnails: ResMut<Thumbnails>,
+) {
+    let Some(active) = thumbnails.active.take() else {
+        return;
+    };
+    let png = encode_png(&captured.image);
+    if png.is_none() {
+        log::warn!(
+            "Cannot encode thumbnail in format {:?}",
+            captured.image.texture_descriptor.format
+        );
+    }
+    send_thumbnail(
+        &channel,
+        active.request.request_id,
+        png.map(|png| (active.request.size, png)),
+    );
+    despawn_thumbnail(&mut commands, &active);
+}
+
+fn despawn_thumbnail(commands: &mut Commands, active: &ActiveThumbnail) {
+    for entity in [active.content, active.camera].into_iter().flatten() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Encodes `image` as PNG with its alpha channel
+fn encode_png(image: &Image) -> Option<Vec<u8>> {
+    let format = ImageFormat::Png.as_image_crate_format()?;
+    let rgba = image.clone().try_into_dynamic().ok()?.to_rgba8();
+    let mut png = Cursor::new(Vec::new());
+    rgba.write_to(&mut png, format).ok()?;
+    Some(png.into_inner())
+}
+
+/// Sends a rendered thumbnail, or a failure without one
+fn send_thumbnail(channel: &HostChannel, request_id: u32, image: Option<(UVec2, Vec<u8>)>) {
+    let mut payload = ByteWriter::new();
+    payload.u32(request_id);
+    match image {
+        Some((size, png)) => {
+            payload
+                .u8(ThumbnailStatus::Rendered as u8)
+                .u32(size.x)
+                .u32(size.y)
+                .bytes(&png);
+        }
+        None => {
+            payload.u8(ThumbnailStatus::Failed as u8).u32(0).u32(0);
+        }
+    }
+    channel.send_reserved(ReservedTopic::Thumbnail, &payload.finish());
+}
@@ -0,0 +1,228 @@
+//! Pausing, slowing down and single-stepping the simulation
+//!
+//! Review and debugging tools in the host control the app's clock with
+//! [`ReservedTopic::TimeControl`] commands, a sequence of operations each starting with
+//! a [`TimeControlOp`] byte:
+//!
+//! ```text
+//! Pause
+//! Resume
+//! SetScale: scale: f32 (1 is normal speed, 0.5 slow motion)
+//! Step:     frames: u32
+//! ```
+//!
+//! All of them act on `Time<Virtual>`, so systems using `Time` see a zero delta while
+//! paused and scaled deltas in slow motion, while frames keep rendering. Apps can call
+//! `Time<Virtual>::pause` and `set_relative_speed` themselves, and step with
+//! [`FrameStep`]. Commands and frame steps are handled by [`TimeControlPlugin`].
+//!
+//! Host frames stutter when the user opens the app switcher or the host collects
+//! garbage. [`EmbeddedConfig::time_limits`](crate::EmbeddedConfig::time_limits) bounds
+//! what a late frame does to the simulation: virtual time advances by at most
+//! [`TimeLimits::max_delta`], and `FixedUpdate` runs at most
+//! [`TimeLimits::max_fixed_steps`] times per frame, dropping the time it couldn't catch up
+//! on.
+
+use std::time::Duration;
+
+use bevy::{
+    app::{App, First, Plugin, PreUpdate},
+    ecs::{
+        message::MessageReader,
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Res, ResMut},
+    },
+    time::{Fixed, Time, TimeSystems, Virtual},
+};
+
+use crate::protocol::{ByteReader, HostCommand, ReservedTopic};
+
+/// Applies host time control commands and [`FrameStep`]s; see the [module docs](self)
+#[derive(Default)]
+pub struct TimeControlPlugin;
+
+impl Plugin for TimeControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameStep>();
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Time control acts on virtual time, which needs TimePlugin
+        if app.world().contains_resource::<Time<Virtual>>() {
+            app.add_systems(First, apply_frame_steps.before(TimeSystems))
+                .add_systems(PreUpdate, receive_time_commands);
+        }
+    }
+}
+
+/// Operations that can appear in a time control command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TimeControlOp {
+    /// Stop virtual time
+    Pause = 0,
+    /// Restart virtual time
+    Resume = 1,
+    /// Set how fast virtual time runs relative to real time
+    SetScale = 2,
+    /// Advance a number of frames while paused
+    Step = 3,
+}
+
+impl TimeControlOp {
+    /// Create a TimeControlOp from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(TimeControlOp::Pause),
+            1 => Some(TimeControlOp::Resume),
+            2 => Some(TimeControlOp::SetScale),
+            3 => Some(TimeControlOp::Step),
+            _ => None,
+        }
+    }
+}
+
+/// Bounds on how far one frame advances the simulation
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLimits {
+    /// Largest delta of virtual time in one frame, set as `Time<Virtual>::max_delta`
+    pub max_delta: Duration,
+    /// Most `FixedUpdate` runs in one frame
+    pub max_fixed_steps: u32,
+}
+
+impl Default for TimeLimits {
+    fn default() -> Self {
+        Self {
+            max_delta: Duration::from_millis(100),
+            max_fixed_steps: 4,
+        }
+    }
+}
+
+/// `FixedUpdate` runs in the current frame
+#[derive(Resource, Default)]
+pub(crate) struct FixedStepsRun(u32);
+
+/// Resets the count of fixed steps before the fixed main loop
+pub(crate) fn reset_fixed_steps(mut steps: ResMut<FixedStepsRun>) {
+    steps.0 = 0;
+}
+
+/// Drops the whole timesteps left to catch up on once a frame ran its last allowed one
+///
+/// Runs in `FixedFirst`. The fraction of a timestep is kept, so interpolation stays smooth.
+pub(crate) fn limit_fixed_steps(
+    limits: Res<TimeLimits>,
+    mut steps: ResMut<FixedStepsRun>,
+    mut fixed: ResMut<Time<Fixed>>,
+) {
+    steps.0 += 1;
+    if steps.0 < limits.max_fixed_steps.max(1) {
+        return;
+    }
+    let timestep = fixed.timestep().as_nanos().max(1);
+    let overstep = fixed.overstep();
+    let dropped = overstep.as_nanos() / timestep;
+    if dropped == 0 {
+        return;
+    }
+    let fraction = Duration::from_nanos((overstep.as_nanos() % timestep) as u64);
+    fixed.discard_overstep(overstep - fraction);
+    log::debug!("Dropped {dropped} fixed steps after a late frame");
+}
+
+/// Frames to run while virtual time is paused
+///
+/// Each requested frame unpauses virtual time for exactly one update, so it advances by
+/// one (scaled) frame delta. Requests made while time is running are dropped.
+#[derive(Resource, Debug, Default)]
+pub struct FrameStep {
+    remaining: u32,
+    stepping: bool,
+}
+
+impl FrameStep {
+    /// Runs `frames` more frames while paused
+    pub fn step(&mut self, frames: u32) {
+        self.remaining = self.remaining.saturating_add(frames);
+    }
+
+    /// Frames still to run
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+}
+
+/// Applies one operation, returning `None` if the payload is malformed
+fn apply_op(
+    op: TimeControlOp,
+    reader: &mut ByteReader,
+    time: &mut Time<Virtual>,
+    step: &mut FrameStep,
+) -> Option<()> {
+    match op {
+        TimeControlOp::Pause => time.pause(),
+        TimeControlOp::Resume => {
+            time.unpause();
+            *step = FrameStep::default();
+        }
+        TimeControlOp::SetScale => {
+            let scale = reader.f32()?;
+            if scale.is_finite() && scale >= 0.0 {
+                time.set_relative_speed(scale);
+            } else {
+                log::warn!("Ignoring invalid time scale {scale}");
+            }
+        }
+        TimeControlOp::Step => step.step(reader.u32()?),
+    }
+    Some(())
+}
+
+/// Applies time control commands from the host
+fn receive_time_commands(
+    mut commands: MessageReader<HostCommand>,
+    mut time: ResMut<Time<Virtual>>,
+    mut step: ResMut<FrameStep>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::TimeControl {
+            continue;
+        }
+
+        let mut reader = ByteReader::new(&command.payload);
+        while let Some(op) = reader.u8() {
+            let Some(op) = TimeControlOp::from_u8(op) else {
+                log::warn!("Ignoring time control command with unknown operation {op}");
+                break;
+            };
+            if apply_op(op, &mut reader, &mut time, &mut step).is_none() {
+                log::warn!("Ignoring malformed time control command");
+                break;
+            }
+        }
+    }
+}
+
+/// Unpauses virtual time for one update per requested frame step
+///
+/// Runs in `First` before virtual time advances.
+fn apply_frame_steps(mut step: ResMut<FrameStep>, mut time: ResMut<Time<Virtual>>) {
+    if step.stepping {
+        time.pause();
+        step.stepping = false;
+    }
+    if step.remaining == 0 {
+        return;
+    }
+    if !time.is_paused() {
+        log::debug!("Dropping frame steps requested while time is running");
+        step.remaining = 0;
+        return;
+    }
+    time.unpause();
+    step.remaining -= 1;
+    step.stepping = true;
+}
@@ -0,0 +1,289 @@
+//! Stable touch ids across a gesture
+//!
+//! Host touch ids are only unique while a touch is down: Android reuses pointer ids as
+//! soon as a finger lifts, and iOS identifies touches by `UITouch` addresses, which UIKit
+//! recycles. Hosts also lose touches under heavy multi-touch, e.g. a pointer id reused
+//! before its `ACTION_POINTER_UP` was forwarded, which leaves a touch in Bevy's `Touches`
+//! that never ends.
+//!
+//! [`TouchIds`] gives each touch an id that is never reused while the app runs, used as
+//! `TouchInput::id` and by predicted touches and contact data. It keeps every gesture
+//! well-formed:
+//!
+//! - `Started` for a host id that is still down cancels the old touch first
+//! - `Moved` for a host id that isn't down starts a touch
+//! - `Ended` and `Cancelled` for a host id that isn't down are dropped
+//!
+//! When the host view loses the gesture without ending each touch, e.g. Android's
+//! `ACTION_CANCEL` or a gesture recognizer taking over, the host calls
+//! `bevy_embedded_ios_cancel_touches` or `nativeCancelTouches` to cancel every active
+//! touch at its last position.
+
+use bevy::{
+    ecs::{resource::Resource, system::ResMut},
+    math::Vec2,
+};
+
+use crate::{EmbeddedInputEvents, EmbeddedTouchEvent, TouchPhase};
+
+/// A touch that is down
+#[derive(Debug, Clone, Copy)]
+struct ActiveTouch {
+    host_id: u64,
+    id: u64,
+    /// Last position in host coordinates
+    position: Vec2,
+}
+
+/// Maps host touch ids to ids that are unique for the app's lifetime
+#[derive(Resource, Debug, Default)]
+pub struct TouchIds {
+    active: Vec<ActiveTouch>,
+    next: u64,
+}
+
+impl TouchIds {
+    /// The id Bevy uses for the host's touch `host_id`, if it is down
+    pub fn get(&self, host_id: u64) -> Option<u64> {
+        self.touch(host_id).map(|touch| touch.id)
+    }
+
+    /// Number of touches that are down
+    pub fn active(&self) -> usize {
+        self.active.len()
+    }
+
+    fn touch(&self, host_id: u64) -> Option<&ActiveTouch> {
+        self.active.iter().find(|touch| touch.host_id == host_id)
+    }
+
+    fn start(&mut self, host_id: u64, position: Vec2) -> u64 {
+        let id = self.next;
+        self.next += 1;
+        self.active.push(ActiveTouch {
+            host_id,
+            id,
+            position,
+        });
+        id
+    }
+
+    fn end(&mut self, host_id: u64) -> Option<ActiveTouch> {
+        let index = self
+            .active
+            .iter()
+            .position(|touch| touch.host_id == host_id)?;
+        Some(self.active.swap_remove(index))
+    }
+
+    /// Rewrites host touch events with stable ids, completing or dropping events of
+    /// gestures the host lost
+    pub(crate) fn remap(&mut self, events: Vec<EmbeddedTouchEvent>) -> Vec<EmbeddedTouchEvent> {
+        let mut remapped = Vec::with_capacity(events.len());
+        for event in events {
+            let host_id = event.id;
+            match event.phase {
+                TouchPhase::Started => {
+                    if let Some(lost) = self.end(host_id) {
+                        log::debug!("Touch {host_id} started again before it ended");
+                        remapped.push(EmbeddedTouchEvent {
+                            phase: TouchPhase::Cancelled,
+                            position: lost.position,
+                            id: lost.id,
+                            contact: None,
+                        });
+                    }
+                    let id = self.start(host_id, event.position);
+                    remapped.push(EmbeddedTouchEvent { id, ..event });
+                }
+                TouchPhase::Moved => {
+                    let id = match self.active.iter_mut().find(|t| t.host_id == host_id) {
+                        Some(touch) => {
+                            touch.position = event.position;
+                            touch.id
+                        }
+                        None => {
+                            log::debug!("Touch {host_id} moved without starting");
+                            let id = self.start(host_id, event.position);
+                            remapped.push(EmbeddedTouchEvent {
+                                phase: TouchPhase::Started,
+                                id,
+                                ..event
+                            });
+                            id
+                        }
+                    };
+                    remapped.push(EmbeddedTouchEvent { id, ..event });
+                }
+                TouchPhase::Ended | TouchPhase::Cancelled => match self.end(host_id) {
+                    Some(touch) => remapped.push(EmbeddedTouchEvent {
+                        id: touch.id,
+                        ..event
+                    }),
+                    None => log::debug!("Dropping end of touch {host_id}, which isn't down"),
+                },
+            }
+        }
+        remapped
+    }
+
+    /// Host ids and last positions of the touches that will be down once `queued` is
+    /// processed
+    pub(crate) fn down_after(&self, queued: &[EmbeddedTouchEvent]) -> Vec<(u64, Vec2)> {
+        let mut down: Vec<(u64, Vec2)> = self
+            .active
+            .iter()
+            .map(|touch| (touch.host_id, touch.position))
+            .collect();
+        for event in queued {
+            down.retain(|(host_id, _)| *host_id != event.id);
+            if matches!(event.phase, TouchPhase::Started | TouchPhase::Moved) {
+                down.push((event.id, event.position));
+            }
+        }
+        down
+    }
+}
+
+/// Replaces the host ids of this frame's touch events with stable ids
+pub(crate) fn remap_touch_ids(
+    mut input_events: ResMut<EmbeddedInputEvents>,
+    mut touch_ids: ResMut<TouchIds>,
+) {
+    if input_events.touch_events.is_empty() {
+        return;
+    }
+    let events = std::mem::take(&mut input_events.touch_events);
+    input_events.touch_events = touch_ids.remap(events);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TouchPhase::*;
+
+    /// `(phase, id, x)` of a touch event at `(x, 0)`
+    type Touch = (TouchPhase, u64, f32);
+
+    fn events(touches: &[Touch]) -> Vec<EmbeddedTouchEvent> {
+        touches
+            .iter()
+            .map(|&(phase, id, x)| EmbeddedTouchEvent {
+                phase,
+                position: Vec2::new(x, 0.0),
+                id,
+                contact: None,
+            })
+            .collect()
+    }
+
+    fn touches(events: &[EmbeddedTouchEvent]) -> Vec<Touch> {
+        events
+            .iter()
+            .map(|event| (event.phase, event.id, event.position.x))
+            .collect()
+    }
+
+    #[test]
+    fn remaps_host_gestures() {
+        // (case, host events, remapped events) with host ids from 7 and app ids from 0
+        let cases: &[(&str, &[Touch], &[Touch])] = &[
+            (
+                "gesture",
+                &[(Started, 7, 1.0), (Moved, 7, 2.0), (Ended, 7, 3.0)],
+                &[(Started, 0, 1.0), (Moved, 0, 2.0), (Ended, 0, 3.0)],
+            ),
+            (
+                "id reused while down",
+                &[(Started, 7, 1.0), (Moved, 7, 2.0), (Started, 7, 5.0)],
+                &[
+                    (Started, 0, 1.0),
+                    (Moved, 0, 2.0),
+                    (Cancelled, 0, 2.0),
+                    (Started, 1, 5.0),
+                ],
+            ),
+            (
+                "moved without started",
+                &[(Moved, 7, 1.0), (Moved, 7, 2.0), (Ended, 7, 2.0)],
+                &[
+                    (Started, 0, 1.0),
+                    (Moved, 0, 1.0),
+                    (Moved, 0, 2.0),
+                    (Ended, 0, 2.0),
+                ],
+            ),
+            (
+                "ended for an unknown id",
+                &[(Ended, 7, 1.0), (Cancelled, 8, 1.0), (Started, 7, 2.0)],
+                &[(Started, 0, 2.0)],
+            ),
+            (
+                "ended twice",
+                &[(Started, 7, 1.0), (Ended, 7, 1.0), (Ended, 7, 1.0)],
+                &[(Started, 0, 1.0), (Ended, 0, 1.0)],
+            ),
+            (
+                "interleaved touches",
+                &[
+                    (Started, 7, 1.0),
+                    (Started, 8, 2.0),
+                    (Moved, 8, 3.0),
+                    (Ended, 7, 1.0),
+                    (Cancelled, 8, 3.0),
+                ],
+                &[
+                    (Started, 0, 1.0),
+                    (Started, 1, 2.0),
+                    (Moved, 1, 3.0),
+                    (Ended, 0, 1.0),
+                    (Cancelled, 1, 3.0),
+                ],
+            ),
+        ];
+
+        for (case, host, expected) in cases {
+            let mut ids = TouchIds::default();
+            let remapped = ids.remap(events(host));
+            assert_eq!(touches(&remapped), *expected, "{case}");
+        }
+    }
+
+    #[test]
+    fn ids_are_never_reused() {
+        let mut ids = TouchIds::default();
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            // Android reuses pointer id 0 for every new first finger
+            let remapped = ids.remap(events(&[
+                (Started, 0, 0.0),
+                (Started, 1, 0.0),
+                (Ended, 0, 0.0),
+                (Started, 0, 0.0),
+                (Ended, 0, 0.0),
+                (Ended, 1, 0.0),
+            ]));
+            for event in remapped.iter().filter(|event| event.phase == Started) {
+                assert!(!seen.contains(&event.id), "{} was reused", event.id);
+                seen.push(event.id);
+            }
+        }
+        assert_eq!(seen.len(), 9);
+        assert_eq!(ids.active(), 0);
+    }
+
+    #[test]
+    fn tracks_touches_that_are_down() {
+        let mut ids = TouchIds::default();
+        ids.remap(events(&[(Started, 7, 1.0), (Started, 8, 2.0)]));
+        assert_eq!(ids.get(7), Some(0));
+        assert_eq!(ids.get(8), Some(1));
+        assert_eq!(ids.get(9), None);
+
+        let queued = events(&[(Ended, 7, 1.0), (Moved, 8, 4.0), (Started, 9, 5.0)]);
+        assert_eq!(
+            ids.down_after(&queued),
+            [(8, Vec2::new(4.0, 0.0)), (9, Vec2::new(5.0, 0.0))]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Tweening of values the host sets
+//!
+//! A host reacting to its own UI, such as a color swatch or a camera preset, usually wants
+//! the scene to animate to the new value rather than snap, without implementing the
+//! animation natively. Commands that set values carry a [`Transition`]:
+//!
+//! ```text
+//! duration: f32 | easing: u8
+//! ```
+//!
+//! `duration` is in seconds and `easing` an [`Easing`]. A zero duration applies the value
+//! right away. A value set during a tween starts from wherever the previous tween got to.
+//! Tweens advance with real time, so they finish even while virtual time is paused.
+
+use bevy::math::curve::{Curve, EaseFunction};
+
+use crate::protocol::ByteReader;
+
+/// Easing curve of a tween
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum Easing {
+    /// Constant speed
+    #[default]
+    Linear = 0,
+    /// Starts slow and speeds up
+    EaseIn = 1,
+    /// Starts fast and slows down
+    EaseOut = 2,
+    /// Slow at both ends
+    EaseInOut = 3,
+    /// Overshoots the target slightly and settles back
+    BackOut = 4,
+    /// Springs around the target before settling
+    ElasticOut = 5,
+    /// Bounces off the target
+    BounceOut = 6,
+}
+
+impl Easing {
+    /// Create an Easing from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Easing::Linear),
+            1 => Some(Easing::EaseIn),
+            2 => Some(Easing::EaseOut),
+            3 => Some(Easing::EaseInOut),
+            4 => Some(Easing::BackOut),
+            5 => Some(Easing::ElasticOut),
+            6 => Some(Easing::BounceOut),
+            _ => None,
+        }
+    }
+
+    /// The Bevy ease function of the curve
+    pub fn function(self) -> EaseFunction {
+        match self {
+            Easing::Linear => EaseFunction::Linear,
+            Easing::EaseIn => EaseFunction::CubicIn,
+            Easing::EaseOut => EaseFunction::CubicOut,
+            Easing::EaseInOut => EaseFunction::CubicInOut,
+            Easing::BackOut => EaseFunction::BackOut,
+            Easing::ElasticOut => EaseFunction::ElasticOut,
+            Easing::BounceOut => EaseFunction::BounceOut,
+        }
+    }
+
+    /// Eased progress for a linear progress `t` from 0 to 1
+    pub fn sample(self, t: f32) -> f32 {
+        self.function().sample_clamped(t)
+    }
+}
+
+/// How a value moves to a new one
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Transition {
+    /// Length of the tween in seconds
+    pub duration: f32,
+    /// Curve of the tween
+    pub easing: Easing,
+}
+
+impl Transition {
+    /// Applies the new value right away
+    pub const INSTANT: Self = Self {
+        duration: 0.0,
+        easing: Easing::Linear,
+    };
+
+    /// A tween of `duration` seconds along `easing`
+    pub fn new(duration: f32, easing: Easing) -> Self {
+        Self { duration, easing }
+    }
+
+    /// Returns true if the value is applied right away
+    pub fn is_instant(&self) -> bool {
+        // Also true for NaN durations
+        self.duration.is_nan() || self.duration <= 0.0
+    }
+
+    /// Reads a transition from a command; unknown easings are linear
+    pub(crate) fn read(reader: &mut ByteReader) -> Option<Self> {
+        let duration = reader.f32()?;
+        let easing = reader.u8()?;
+        let easing = Easing::from_u8(easing).unwrap_or_else(|| {
+            log::warn!("Unknown easing {easing}, tweening linearly");
+            Easing::Linear
+        });
+        Some(Self { duration, easing })
+    }
+}
+
+/// A value moving from one value to another
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Tween<T> {
+    from: T,
+    to: T,
+    elapsed: f32,
+    transition: Transition,
+}
+
+impl<T: Copy> Tween<T> {
+    /// Starts moving from `from` to `to`
+    pub(crate) fn new(from: T, to: T, transition: Transition) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0.0,
+            transition,
+        }
+    }
+
+    /// Advances by `delta` seconds and returns the value, interpolated with `lerp`
+    pub(crate) fn advance(&mut self, delta: f32, lerp: impl Fn(&T, &T, f32) -> T) -> T {
+        self.elapsed += delta;
+        if self.is_finished() {
+            return self.to;
+        }
+        let t = self.elapsed / self.transition.duration;
+        lerp(&self.from, &self.to, self.transition.easing.sample(t))
+    }
+
+    /// Returns true once the value has arrived
+    pub(crate) fn is_finished(&self) -> bool {
+        self.elapsed >= self.transition.duration
+    }
+}
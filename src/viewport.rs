@@ -0,0 +1,271 @@
+//! Host-controlled viewport and letterboxing
+//!
+//! Hosts that lay native chrome over the widget (a toolbar, a bottom sheet) can inset the
+//! rendered content instead of resizing the surface, which would recreate the swapchain.
+//! A [`ReservedTopic::Viewport`] command sets the rectangle cameras on the primary window
+//! draw into and how the content is scaled inside it:
+//!
+//! ```text
+//! x: f32 | y: f32 | width: f32 | height: f32 | scaling: u8 | aspect: f32 | letterbox: u32 RGBA
+//! ```
+//!
+//! The rectangle is in logical pixels of the host surface, before the
+//! [`SurfaceTransform`]; a zero width or height means the whole surface. `scaling` is a
+//! [`ViewportScaling`] and `aspect` (width / height) is only used by
+//! [`ViewportScaling::FixedAspect`]. Everything on the surface outside the content is
+//! cleared with the letterbox color, packed as `0xRRGGBBAA`.
+//!
+//! The host viewport replaces the viewports of the affected cameras; a command for the
+//! whole surface with [`ViewportScaling::Fill`] gives them back their own. The commands
+//! are handled by [`HostViewportPlugin`]. Touch positions
+//! stay relative to the surface, so `Camera::viewport_to_world` keeps working.
+
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate},
+    camera::{
+        Camera, CameraOutputMode, CameraUpdateSystems, ClearColorConfig, RenderTarget, Viewport,
+    },
+    color::{Color, Srgba},
+    ecs::{
+        change_detection::{DetectChanges, Ref},
+        component::Component,
+        entity::Entity,
+        message::MessageReader,
+        query::With,
+        resource::Resource,
+        schedule::IntoScheduleConfigs,
+        system::{Commands, Query, Res, ResMut},
+    },
+    math::{Rect, UVec2, Vec2},
+    window::{PrimaryWindow, Window, WindowRef},
+};
+
+use crate::{
+    SurfaceTransform,
+    protocol::{ByteReader, HostCommand, ReservedTopic},
+};
+
+/// Applies the host viewport to window cameras; see the [module docs](self)
+#[derive(Default)]
+pub struct HostViewportPlugin;
+
+impl Plugin for HostViewportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HostViewport>()
+            .add_systems(PreUpdate, receive_viewport)
+            .add_systems(PostUpdate, apply_host_viewport.before(CameraUpdateSystems));
+    }
+}
+
+/// How content is scaled into the host viewport
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum ViewportScaling {
+    /// Content fills the rectangle, and cameras adapt to its aspect ratio
+    #[default]
+    Fill = 0,
+    /// Content keeps the aspect ratio of the whole surface and is scaled down to fit, so
+    /// insetting it doesn't change what cameras frame
+    Fit = 1,
+    /// Content keeps a fixed aspect ratio and is scaled to fit
+    FixedAspect = 2,
+}
+
+impl ViewportScaling {
+    /// Create a ViewportScaling from a u8
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ViewportScaling::Fill),
+            1 => Some(ViewportScaling::Fit),
+            2 => Some(ViewportScaling::FixedAspect),
+            _ => None,
+        }
+    }
+}
+
+/// Where on the surface the host wants content to be drawn
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct HostViewport {
+    /// Rectangle in logical surface pixels, or `None` for the whole surface
+    pub rect: Option<Rect>,
+    /// How content is scaled into the rectangle
+    pub scaling: ViewportScaling,
+    /// Aspect ratio (width / height) for [`ViewportScaling::FixedAspect`]
+    pub aspect: f32,
+    /// Color of the surface around the content
+    pub letterbox: Color,
+}
+
+impl Default for HostViewport {
+    fn default() -> Self {
+        Self {
+            rect: None,
+            scaling: ViewportScaling::Fill,
+            aspect: 1.0,
+            letterbox: Color::BLACK,
+        }
+    }
+}
+
+impl HostViewport {
+    /// Returns true if the host leaves cameras their own viewports
+    pub fn is_full_surface(&self) -> bool {
+        self.rect.is_none() && self.scaling == ViewportScaling::Fill
+    }
+
+    /// The content rectangle in logical window coordinates, or `None` if it is empty
+    pub fn content_rect(&self, window: &Window, transform: &SurfaceTransform) -> Option<Rect> {
+        let window_size = window.size();
+        if window_size.min_element() <= 0.0 {
+            return None;
+        }
+        let surface_size = transform.surface_size(window_size);
+        let surface = Rect::from_corners(Vec2::ZERO, surface_size);
+        let rect = self.rect.map_or(surface, |rect| rect.intersect(surface));
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return None;
+        }
+
+        let rect = Rect::from_corners(
+            transform.surface_to_content(rect.min, surface_size),
+            transform.surface_to_content(rect.max, surface_size),
+        );
+        let aspect = match self.scaling {
+            ViewportScaling::Fill => return Some(rect),
+            ViewportScaling::Fit => window_size.x / window_size.y,
+            ViewportScaling::FixedAspect if self.aspect > 0.0 => self.aspect,
+            ViewportScaling::FixedAspect => return Some(rect),
+        };
+
+        let size = if rect.width() / rect.height() > aspect {
+            Vec2::new(rect.height() * aspect, rect.height())
+        } else {
+            Vec2::new(rect.width(), rect.width() / aspect)
+        };
+        Some(Rect::from_center_size(rect.center(), size))
+    }
+
+    /// The camera viewport for the content rectangle in `window`
+    pub fn viewport(&self, window: &Window, transform: &SurfaceTransform) -> Option<Viewport> {
+        let rect = self.content_rect(window, transform)?;
+        let physical = window.physical_size();
+        if physical.min_element() == 0 {
+            return None;
+        }
+        let scale = physical.as_vec2() / window.size();
+
+        let position = (rect.min * scale)
+            .round()
+            .as_uvec2()
+            .min(physical - UVec2::ONE);
+        let size = (rect.size() * scale)
+            .round()
+            .as_uvec2()
+            .clamp(UVec2::ONE, physical - position);
+        Some(Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..Viewport::default()
+        })
+    }
+}
+
+/// What a camera had before the host viewport replaced it
+#[derive(Component, Debug, Clone)]
+pub(crate) struct ReplacedViewport {
+    viewport: Option<Viewport>,
+    clear_color: ClearColorConfig,
+}
+
+/// Applies viewport commands from the host
+fn receive_viewport(
+    mut commands: MessageReader<HostCommand>,
+    mut host_viewport: ResMut<HostViewport>,
+) {
+    for command in commands.read() {
+        if command.topic != ReservedTopic::Viewport {
+            continue;
+        }
+
+        match read_viewport(&command.payload) {
+            Some(viewport) => *host_viewport = viewport,
+            None => log::warn!("Ignoring malformed viewport command"),
+        }
+    }
+}
+
+fn read_viewport(payload: &[u8]) -> Option<HostViewport> {
+    let mut reader = ByteReader::new(payload);
+    let (x, y) = (reader.f32()?, reader.f32()?);
+    let (width, height) = (reader.f32()?, reader.f32()?);
+    let scaling = ViewportScaling::from_u8(reader.u8()?)?;
+    let aspect = reader.f32()?;
+    let [r, g, b, a] = reader.u32()?.to_be_bytes();
+
+    let rect = (width > 0.0 && height > 0.0).then(|| Rect::new(x, y, x + width, y + height));
+    Some(HostViewport {
+        rect,
+        scaling,
+        aspect,
+        letterbox: Srgba::rgba_u8(r, g, b, a).into(),
+    })
+}
+
+/// Sets the viewport and letterbox color of cameras drawing to the primary window
+fn apply_host_viewport(
+    mut commands: Commands,
+    host_viewport: Res<HostViewport>,
+    surface_transform: Res<SurfaceTransform>,
+    windows: Query<(Entity, Ref<Window>), With<PrimaryWindow>>,
+    mut cameras: Query<(Entity, &mut Camera, Option<&ReplacedViewport>)>,
+) {
+    let Ok((window_entity, window)) = windows.single() else {
+        return;
+    };
+
+    if host_viewport.is_full_surface() {
+        for (entity, mut camera, replaced) in &mut cameras {
+            if let Some(replaced) = replaced {
+                camera.viewport = replaced.viewport.clone();
+                set_output_clear_color(&mut camera, replaced.clear_color);
+                commands.entity(entity).remove::<ReplacedViewport>();
+            }
+        }
+        return;
+    }
+
+    let changed =
+        host_viewport.is_changed() || surface_transform.is_changed() || window.is_changed();
+    let viewport = host_viewport.viewport(&window, &surface_transform);
+    let letterbox = ClearColorConfig::Custom(host_viewport.letterbox);
+    for (entity, mut camera, replaced) in &mut cameras {
+        let on_window = match camera.target {
+            RenderTarget::Window(WindowRef::Primary) => true,
+            RenderTarget::Window(WindowRef::Entity(target)) => target == window_entity,
+            _ => false,
+        };
+        if !on_window || !(changed || camera.is_added() || replaced.is_none()) {
+            continue;
+        }
+
+        if replaced.is_none() {
+            let clear_color = match camera.output_mode {
+                CameraOutputMode::Write { clear_color, .. } => clear_color,
+                CameraOutputMode::Skip => ClearColorConfig::Default,
+            };
+            commands.entity(entity).insert(ReplacedViewport {
+                viewport: camera.viewport.clone(),
+                clear_color,
+            });
+        }
+        camera.viewport = viewport.clone();
+        set_output_clear_color(&mut camera, letterbox);
+    }
+}
+
+/// Sets the color the camera clears the window with around its viewport
+fn set_output_clear_color(camera: &mut Camera, color: ClearColorConfig) {
+    if let CameraOutputMode::Write { clear_color, .. } = &mut camera.output_mode {
+        *clear_color = color;
+    }
+}
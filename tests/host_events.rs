@@ -0,0 +1,194 @@
+//! Derived host events: their encoding, schemas and generated host code
+
+use bevy::prelude::*;
+use bevy_embedded::{
+    EnumSchema, EventDirection, EventField, FieldType, HostEvent, HostEventApp, HostEvents,
+    StructSchema, event_id,
+    protocol::{ByteWriter, ReservedTopic, decode_reserved},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(HostEvent, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[host_event(to_bevy)]
+struct ProductPicked {
+    id: u64,
+    display_name: String,
+    variant: Option<Variant>,
+    thumbnail: Vec<u8>,
+}
+
+#[derive(EventField, Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Variant {
+    size: Size,
+    tags: Vec<String>,
+    offset: i32,
+}
+
+#[derive(EventField, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+enum Size {
+    Small,
+    Large,
+}
+
+#[derive(HostEvent, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[host_event(to_host)]
+struct ScoreChanged {
+    score: u32,
+    ratio: f32,
+}
+
+fn picked() -> ProductPicked {
+    ProductPicked {
+        id: 7,
+        display_name: "Lamp".to_string(),
+        variant: Some(Variant {
+            size: Size::Large,
+            tags: vec!["new".to_string(), "sale".to_string()],
+            offset: -3,
+        }),
+        thumbnail: vec![1, 2, 3],
+    }
+}
+
+/// Payload of an encoded event, after checking its topic
+fn payload<E: HostEvent>(event: &E) -> Vec<u8> {
+    let message = event.encode().unwrap();
+    let (topic, payload) = decode_reserved(&message).unwrap();
+    assert_eq!(topic, E::DIRECTION.topic() as u16);
+    payload.to_vec()
+}
+
+#[test]
+fn events_round_trip() {
+    let event = picked();
+    assert_eq!(ProductPicked::decode(&payload(&event)), Some(event));
+
+    let event = ProductPicked {
+        variant: None,
+        ..picked()
+    };
+    assert_eq!(ProductPicked::decode(&payload(&event)), Some(event));
+
+    let score = ScoreChanged {
+        score: 12,
+        ratio: 0.5,
+    };
+    assert_eq!(ScoreChanged::decode(&payload(&score)), Some(score));
+}
+
+#[test]
+fn encoding_matches_the_payload_encoding() {
+    let mut writer = ByteWriter::new();
+    writer
+        .u32(event_id("ProductPicked"))
+        .u64(7)
+        .string("Lamp")
+        .u8(1)
+        .u32(1)
+        .u32(2)
+        .string("new")
+        .string("sale")
+        .u32(-3i32 as u32)
+        .u32(3)
+        .bytes(&[1, 2, 3]);
+    assert_eq!(payload(&picked()), writer.finish());
+}
+
+#[test]
+fn malformed_events_are_ignored() {
+    let payload = payload(&picked());
+
+    let mut trailing = payload.clone();
+    trailing.push(0);
+    assert_eq!(ProductPicked::decode(&trailing), None);
+    assert_eq!(ProductPicked::decode(&payload[..payload.len() - 1]), None);
+    assert_eq!(ScoreChanged::decode(&payload), None);
+
+    // The option tag of `variant` follows the id, the id field and the name
+    let tag = 4 + 8 + 4 + "Lamp".len();
+    let mut bad_tag = payload.clone();
+    bad_tag[tag] = 2;
+    assert_eq!(ProductPicked::decode(&bad_tag), None);
+
+    // The variant index of `size` follows the tag
+    let mut bad_variant = payload;
+    bad_variant[tag + 1] = 2;
+    assert_eq!(ProductPicked::decode(&bad_variant), None);
+}
+
+#[test]
+fn schemas_describe_the_fields() {
+    let variant = FieldType::Struct(StructSchema {
+        name: "Variant",
+        fields: vec![
+            (
+                "size",
+                FieldType::Enum(EnumSchema {
+                    name: "Size",
+                    variants: vec!["Small", "Large"],
+                }),
+            ),
+            ("tags", FieldType::List(Box::new(FieldType::String))),
+            ("offset", FieldType::I32),
+        ],
+    });
+    assert_eq!(Variant::field_type(), variant);
+    assert_eq!(
+        ProductPicked::fields(),
+        vec![
+            ("id", FieldType::U64),
+            ("display_name", FieldType::String),
+            ("variant", FieldType::Option(Box::new(variant))),
+            ("thumbnail", FieldType::List(Box::new(FieldType::U8))),
+        ]
+    );
+    assert_eq!(ProductPicked::ID, event_id("ProductPicked"));
+    assert_eq!(ProductPicked::DIRECTION, EventDirection::ToBevy);
+    assert_eq!(ScoreChanged::DIRECTION.topic(), ReservedTopic::AppEvent);
+}
+
+fn host_events() -> HostEvents {
+    let mut app = App::new();
+    app.add_host_event::<ProductPicked>()
+        .add_host_event::<ScoreChanged>();
+    let events = app.world_mut().remove_resource::<HostEvents>().unwrap();
+    assert_eq!(events.schemas().len(), 2);
+    events
+}
+
+#[test]
+fn swift_declares_events_and_nested_types() {
+    let source = host_events().swift_source();
+    for snippet in [
+        "enum Size: UInt32, BevyEventField {\n    case small = 0\n    case large = 1",
+        "struct Variant: BevyEventField {\n    var size: Size\n    var tags: [String]\n    var offset: Int32",
+        "let tags = reader.get(Array<String>.self)",
+        "struct ProductPicked {",
+        "    var displayName: String\n    var variant: Variant?\n    var thumbnail: Data",
+        "        writer.put(variant)",
+        "let ratio = reader.get(Float.self),",
+    ] {
+        assert!(source.contains(snippet), "missing {snippet:?} in\n{source}");
+    }
+    // Nested types are declared before the types using them
+    assert!(source.find("enum Size").unwrap() < source.find("struct Variant").unwrap());
+}
+
+#[test]
+fn kotlin_declares_events_and_nested_types() {
+    let source = host_events().kotlin_source("com.example.events");
+    for snippet in [
+        "package com.example.events",
+        "enum class Size {\n    Small,\n    Large;",
+        "data class Variant(\n    val size: Size,\n    val tags: List<String>,\n    val offset: Int,\n)",
+        "writer.putList(tags) { item0 -> writer.putString(item0) }",
+        "tags = reader.getList { reader.getString() },",
+        "size = Size.read(reader),",
+        "writer.putOptional(variant) { item0 -> item0.write(writer) }",
+        "writer.putBytes(thumbnail)",
+        "ratio = reader.getFloat(),",
+    ] {
+        assert!(source.contains(snippet), "missing {snippet:?} in\n{source}");
+    }
+    assert!(source.find("enum class Size").unwrap() < source.find("data class Variant").unwrap());
+}
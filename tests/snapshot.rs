@@ -0,0 +1,26 @@
+//! Golden-image regression tests for `EmbeddedPlugin` rendering
+//!
+//! Requires a GPU (or software adapter). Run with
+//! `cargo test --features snapshot_testing --test snapshot`.
+
+#![cfg(feature = "snapshot_testing")]
+
+use bevy::{prelude::*, window::WindowPlugin};
+use bevy_embedded::snapshot::SnapshotHarness;
+
+#[test]
+fn clear_color_matches_reference() {
+    let mut harness = SnapshotHarness::from_setup(64, 64, |app| {
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: None,
+            ..Default::default()
+        }))
+        .insert_resource(ClearColor(Color::srgb(0.2, 0.4, 0.8)))
+        .add_systems(Startup, |mut commands: Commands| {
+            commands.spawn(Camera2d);
+        });
+    });
+
+    harness.run_frames(3);
+    harness.assert_snapshot("clear_color");
+}